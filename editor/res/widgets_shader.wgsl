@@ -12,11 +12,13 @@ var<uniform> system: SystemUniform;
 struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) tex_coords: vec2<f32>,
+    @location(2) tex_bounds: vec4<f32>,
 };
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) tex_coords: vec2<f32>,
+    @location(1) tex_bounds: vec4<f32>,
 };
 
 @vertex
@@ -25,6 +27,7 @@ fn vs_main(
 ) -> VertexOutput {
     var out: VertexOutput;
     out.tex_coords = model.tex_coords;
+    out.tex_bounds = model.tex_bounds;
     out.clip_position = system.view_proj * vec4<f32>(model.position, 1.0);
     return out;
 }
@@ -42,5 +45,8 @@ var s_diffuse: sampler;
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return textureSample(t_diffuse, s_diffuse, in.tex_coords);
+    // (staying half a texel inside of the widget's part of the atlas, so that filtering doesn't blend in its neighbours)
+    let half_texel = 0.5 / vec2<f32>(textureDimensions(t_diffuse));
+    let tex_coords = clamp(in.tex_coords, in.tex_bounds.xy + half_texel, in.tex_bounds.zw - half_texel);
+    return textureSample(t_diffuse, s_diffuse, tex_coords);
 }
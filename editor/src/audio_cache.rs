@@ -0,0 +1,328 @@
+use std::path::Path;
+#[cfg(not(target_arch = "wasm32"))]
+use std::{fs, path::PathBuf, time::Instant};
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{sample_packs::checksum, util::cache_dir};
+
+/// Bump whenever the cache file format (or the way summaries are computed) changes
+const CACHE_VERSION: &str = "v1";
+
+/// The number of (min, max, rms) buckets we keep, independent of how wide a widget ends up being drawn
+const OVERVIEW_RESOLUTION: usize = 2048;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioInfo {
+    pub format: String,
+    pub channels: u32,
+    pub sample_rate: u32,
+    pub num_samples: usize,
+}
+
+/**
+    Everything we need to know about an audio file to draw it, without having to decode it again.
+*/
+#[derive(Debug, Clone)]
+pub struct AudioSummary {
+    pub info: AudioInfo,
+    pub overall_max: f32,
+    /// (min, max, rms) per bucket
+    pub overview: Vec<(f32, f32, f32)>,
+}
+
+impl AudioSummary {
+    /**
+        Loads the summary from the cache if we've seen a file with the exact same contents before, or decodes the file (and caches the result) otherwise.
+    */
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let hash = checksum(path)?;
+
+        if let Some(summary) = read_cache(&hash) {
+            return Ok(summary);
+        }
+
+        let summary = Self::decode(path)?;
+        write_cache(&hash, &summary);
+
+        Ok(summary)
+    }
+
+    /**
+        (In the browser, there's no cache, and it's what was fetched, see `decode_mono`)
+    */
+    #[cfg(target_arch = "wasm32")]
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let (samples, sample_rate) = decode_mono(path)?;
+
+        let info = AudioInfo {
+            format: "web audio".into(),
+            channels: 1,
+            sample_rate,
+            num_samples: samples.len(),
+        };
+
+        Ok(Self::summarize(&samples, info))
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn decode(path: &Path) -> Result<Self, String> {
+        let t0 = Instant::now();
+
+        let decoder = creak::Decoder::open(path).map_err(|e| format!("{:?}", e))?;
+
+        let info = decoder.info();
+        let format = info.format().to_string();
+        let channels = info.channels() as u32;
+        let sample_rate = info.sample_rate() as u32;
+
+        let samples = decoder
+            .into_samples()
+            .map_err(|e| format!("{:?}", e))?
+            .collect::<Result<Vec<f32>, _>>()
+            .map_err(|e| format!("{:?}", e))?;
+
+        tracing::debug!(
+            "Decoded {:?}, took: {:?}",
+            path.file_name().unwrap_or_default(),
+            t0.elapsed()
+        );
+
+        let info = AudioInfo {
+            format,
+            channels,
+            sample_rate,
+            num_samples: samples.len(),
+        };
+
+        Ok(Self::summarize(&samples, info))
+    }
+
+    fn summarize(samples: &[f32], info: AudioInfo) -> Self {
+        let num_samples = samples.len();
+        let samples_per_bucket = (num_samples / OVERVIEW_RESOLUTION).max(1);
+
+        let overview = samples
+            .chunks(samples_per_bucket)
+            .map(|chunk| {
+                let min = chunk.iter().copied().fold(0.0, f32::min);
+                let max = chunk.iter().copied().fold(0.0, f32::max);
+                let sqr_sum = chunk.iter().map(|s| s * s).sum::<f32>();
+
+                (min, max, (sqr_sum / chunk.len() as f32).sqrt())
+            })
+            .collect::<Vec<_>>();
+
+        let overall_max = overview
+            .iter()
+            .map(|&(min, max, _)| max.max(-min))
+            .fold(0.0, f32::max);
+
+        Self {
+            info,
+            overall_max,
+            overview,
+        }
+    }
+
+    /**
+        The overview, resampled to exactly `width` buckets (e.g. one per physical pixel)
+    */
+    pub fn resample(&self, width: usize) -> Vec<(f32, f32, f32)> {
+        let n = self.overview.len();
+        if n == 0 {
+            return vec![(0.0, 0.0, 0.0); width];
+        }
+
+        (0..width)
+            .map(|x| {
+                let start = (x * n / width).min(n - 1);
+                let end = ((x + 1) * n / width).max(start + 1).min(n);
+                let buckets = &self.overview[start..end];
+
+                let min = buckets.iter().map(|b| b.0).fold(0.0, f32::min);
+                let max = buckets.iter().map(|b| b.1).fold(0.0, f32::max);
+                let rms = (buckets.iter().map(|b| b.2 * b.2).sum::<f32>()
+                    / buckets.len() as f32)
+                    .sqrt();
+
+                (min, max, rms)
+            })
+            .collect()
+    }
+}
+
+/**
+    Decodes a whole audio file, mixed down to mono, for playing it. Returns the samples with their sample rate.
+*/
+#[cfg(not(target_arch = "wasm32"))]
+pub fn decode_mono(path: &Path) -> Result<(Vec<f32>, u32), String> {
+    let decoder = creak::Decoder::open(path).map_err(|e| format!("{:?}", e))?;
+
+    let channels = (decoder.info().channels() as usize).max(1);
+    let sample_rate = decoder.info().sample_rate() as u32;
+
+    let samples = decoder
+        .into_samples()
+        .map_err(|e| format!("{:?}", e))?
+        .collect::<Result<Vec<f32>, _>>()
+        .map_err(|e| format!("{:?}", e))?;
+
+    let mono = samples
+        .chunks(channels)
+        .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+        .collect();
+
+    Ok((mono, sample_rate))
+}
+
+/**
+    In the browser, where there are no files, a sample is fetched from where the page is served, by its path, and decoded by the browser (at the sample rate it plays at). That's not done right away, so until it's there, it's not there, and it's played when the code is evaluated again.
+*/
+#[cfg(target_arch = "wasm32")]
+pub fn decode_mono(path: &Path) -> Result<(Vec<f32>, u32), String> {
+    let mut fetched = FETCHED.lock().map_err(|e| e.to_string())?;
+
+    match fetched.get(path) {
+        Some(Some(decoded)) => decoded.clone(),
+        Some(None) => Err("it's still being fetched".into()),
+        None => {
+            fetched.insert(path.to_path_buf(), None);
+
+            let path = path.to_path_buf();
+            wasm_bindgen_futures::spawn_local(async move {
+                let decoded = fetch_mono(&path).await;
+                if let Ok(mut fetched) = FETCHED.lock() {
+                    fetched.insert(path, Some(decoded));
+                }
+            });
+
+            Err("it's being fetched, evaluate again in a moment".into())
+        }
+    }
+}
+
+/// (what's fetched, by path, and `None` while it's being fetched)
+#[cfg(target_arch = "wasm32")]
+type Fetched =
+    std::collections::BTreeMap<std::path::PathBuf, Option<Result<(Vec<f32>, u32), String>>>;
+
+#[cfg(target_arch = "wasm32")]
+static FETCHED: std::sync::Mutex<Fetched> =
+    std::sync::Mutex::new(std::collections::BTreeMap::new());
+
+#[cfg(target_arch = "wasm32")]
+async fn fetch_mono(path: &Path) -> Result<(Vec<f32>, u32), String> {
+    use wasm_bindgen::JsCast;
+    use wasm_bindgen_futures::JsFuture;
+
+    let js_error = |e: wasm_bindgen::JsValue| format!("{:?}", e);
+
+    let window = web_sys::window().ok_or("there's no window")?;
+    let response = JsFuture::from(window.fetch_with_str(&path.to_string_lossy()))
+        .await
+        .map_err(js_error)?
+        .dyn_into::<web_sys::Response>()
+        .map_err(js_error)?;
+    if !response.ok() {
+        return Err(format!("fetching it failed ({})", response.status()));
+    }
+
+    let buffer = JsFuture::from(response.array_buffer().map_err(js_error)?)
+        .await
+        .map_err(js_error)?
+        .dyn_into::<js_sys::ArrayBuffer>()
+        .map_err(js_error)?;
+
+    let context = web_sys::AudioContext::new().map_err(js_error)?;
+    let audio = JsFuture::from(context.decode_audio_data(&buffer).map_err(js_error)?)
+        .await
+        .map_err(js_error)?
+        .dyn_into::<web_sys::AudioBuffer>()
+        .map_err(js_error)?;
+    let _ = context.close();
+
+    let channels = audio.number_of_channels().max(1);
+    let mut mono = vec![0.0; audio.length() as usize];
+    for channel in 0..audio.number_of_channels() {
+        let samples = audio.get_channel_data(channel).map_err(js_error)?;
+        for (mono, sample) in mono.iter_mut().zip(samples) {
+            *mono += sample / channels as f32;
+        }
+    }
+
+    Ok((mono, audio.sample_rate() as u32))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn cache_file(hash: &str) -> Option<PathBuf> {
+    let dir = cache_dir()?.join("audio");
+    fs::create_dir_all(&dir).ok()?;
+    Some(dir.join(format!("{}.summary", hash)))
+}
+
+/**
+    The cache file format is plain text: a header line `<version> <format> <channels> <sample rate> <num samples> <overall max>` (tab-separated), followed by one `<min> <max> <rms>` line per bucket.
+*/
+#[cfg(not(target_arch = "wasm32"))]
+fn read_cache(hash: &str) -> Option<AudioSummary> {
+    let contents = fs::read_to_string(cache_file(hash)?).ok()?;
+    let mut lines = contents.lines();
+
+    let header = lines.next()?.split('\t').collect::<Vec<_>>();
+    let [CACHE_VERSION, format, channels, sample_rate, num_samples, overall_max] = header[..]
+    else {
+        return None;
+    };
+
+    let overview = lines
+        .map(|line| {
+            let nums = line
+                .split_whitespace()
+                .map(|n| n.parse::<f32>().ok())
+                .collect::<Option<Vec<_>>>()?;
+
+            let [min, max, rms] = nums[..] else {
+                return None;
+            };
+
+            Some((min, max, rms))
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    Some(AudioSummary {
+        info: AudioInfo {
+            format: format.to_string(),
+            channels: channels.parse().ok()?,
+            sample_rate: sample_rate.parse().ok()?,
+            num_samples: num_samples.parse().ok()?,
+        },
+        overall_max: overall_max.parse().ok()?,
+        overview,
+    })
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write_cache(hash: &str, summary: &AudioSummary) {
+    let Some(file) = cache_file(hash) else {
+        return;
+    };
+
+    let mut contents = format!(
+        "{}\t{}\t{}\t{}\t{}\t{}\n",
+        CACHE_VERSION,
+        summary.info.format,
+        summary.info.channels,
+        summary.info.sample_rate,
+        summary.info.num_samples,
+        summary.overall_max
+    );
+
+    for (min, max, rms) in &summary.overview {
+        contents.push_str(&format!("{} {} {}\n", min, max, rms));
+    }
+
+    if let Err(e) = fs::write(file, contents) {
+        tracing::warn!("Could not write audio cache: {:?}", e);
+    }
+}
@@ -0,0 +1,435 @@
+use live_engine::{DeviceInfo, DeviceSettings, Devices, SAMPLE_RATE};
+use serde::{Deserialize, Serialize};
+
+use crate::{render::Overlay, storage::storage, util::config_dir};
+
+const FILE_NAME: &str = "audio.toml";
+
+const PANEL_WIDTH: f32 = 480.0;
+const PANEL_TOP: f32 = 64.0;
+const TITLE_HEIGHT: f32 = 36.0;
+const ROW_HEIGHT: f32 = 26.0;
+const FONT_SIZE: f32 = 15.0;
+/// (where the values start, after the labels)
+const VALUE_X: f32 = 130.0;
+/// Above this much callback load, it's shown in red (dropouts are near)
+const HIGH_LOAD: f32 = 0.8;
+
+const BACKDROP_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.08];
+const PANEL_COLOR: [f32; 4] = [0.99, 0.99, 0.98, 1.0];
+const SELECTED_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.08];
+const TEXT_COLOR: [f32; 4] = [0.02, 0.02, 0.02, 1.0];
+const DIM_TEXT_COLOR: [f32; 4] = [0.02, 0.02, 0.02, 0.45];
+const ERROR_COLOR: [f32; 4] = [0.8, 0.15, 0.15, 1.0];
+
+const ROWS: [&str; 7] = [
+    "output",
+    "input",
+    "sample rate",
+    "buffer size",
+    "channels",
+    "to outputs",
+    "when overloaded",
+];
+/// (how many channels a device plays by default, as far as the panel's concerned, usually stereo)
+const DEFAULT_CHANNELS: u16 = 2;
+
+/**
+    Which audio devices to play to and record from, from `audio.toml` in the config dir (so the engine starts on them next time too), e.g.
+
+    ```toml
+    output = "Scarlett 2i2 USB"
+    # in Hz (the engine renders at 44.1kHz, and resamples for other rates)
+    sample_rate = 48000
+    # in frames
+    buffer_size = 256
+    # how many output channels the engine renders (for `pan` and `channel` in the code), and which of the device's outputs they go to
+    channels = 4
+    channel_map = [3, 4, 5, 6]
+    # whether to economize (a thinner reverb) rather than glitch, when the engine can't keep up
+    economize = true
+    ```
+
+    Whatever's left out is the system's default (and not economizing).
+*/
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub output: Option<String>,
+    pub input: Option<String>,
+    pub sample_rate: Option<u32>,
+    pub buffer_size: Option<u32>,
+    pub channels: Option<u16>,
+    pub channel_map: Vec<u16>,
+    pub economize: bool,
+}
+
+impl AudioSettings {
+    pub fn load() -> Self {
+        let Some(contents) = config_dir().and_then(|dir| storage().read(&dir.join(FILE_NAME)).ok())
+        else {
+            return Self::default();
+        };
+
+        toml::from_str(&contents).unwrap_or_else(|e| {
+            tracing::warn!("Could not read {}: {}", FILE_NAME, e);
+            Self::default()
+        })
+    }
+
+    pub fn save(&self) {
+        let Some(dir) = config_dir() else {
+            return;
+        };
+
+        match toml::to_string(self) {
+            Ok(contents) => {
+                let _ = storage().write(&dir.join(FILE_NAME), &contents);
+            }
+            Err(e) => tracing::warn!("Could not write {}: {}", FILE_NAME, e),
+        }
+    }
+
+    pub fn devices(&self) -> DeviceSettings {
+        DeviceSettings {
+            output: self.output.clone(),
+            input: self.input.clone(),
+            sample_rate: self.sample_rate,
+            buffer_size: self.buffer_size,
+            channels: self.channels,
+            channel_map: self.channel_map.clone(),
+        }
+    }
+
+    /**
+        The output the engine's first channel goes to, if they go to the device's outputs in order from there (and not in some other order, from `audio.toml`)
+    */
+    fn first_output(&self) -> Option<u16> {
+        let first = *self.channel_map.first()?;
+        let in_order = self
+            .channel_map
+            .iter()
+            .zip(first..)
+            .all(|(&channel, expected)| channel == expected);
+
+        in_order.then_some(first)
+    }
+
+    /**
+        Maps as many channels as the engine renders to the device's outputs in order, from the first one (or from output 1, with `None`)
+    */
+    fn map_from(&mut self, first: Option<u16>) {
+        let channels = self.channels.unwrap_or(DEFAULT_CHANNELS);
+        self.channel_map = first.map_or(vec![], |first| (first..first + channels).collect());
+    }
+}
+
+/**
+    The Cmd+, audio settings: the output and input device, sample rate and buffer size, how many channels to play (for installations and quad rigs) and on which of the device's outputs, and what to do when the engine can't keep up (up and down to pick a setting, left and right to change it, Enter to switch to them, right away, while everything keeps playing), and how hard the audio callback is working.
+*/
+pub struct AudioSettingsPanel {
+    open: bool,
+    selected: usize,
+    settings: AudioSettings,
+    outputs: Vec<DeviceInfo>,
+    inputs: Vec<DeviceInfo>,
+}
+
+impl AudioSettingsPanel {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            selected: 0,
+            settings: AudioSettings::default(),
+            outputs: vec![],
+            inputs: vec![],
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn open(
+        &mut self,
+        settings: AudioSettings,
+        outputs: Vec<DeviceInfo>,
+        inputs: Vec<DeviceInfo>,
+    ) {
+        self.open = true;
+        self.selected = 0;
+        self.settings = settings;
+        self.outputs = outputs;
+        self.inputs = inputs;
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+    }
+
+    pub fn settings(&self) -> &AudioSettings {
+        &self.settings
+    }
+
+    pub fn move_selection(&mut self, delta: i32) {
+        self.selected = (self.selected as i32 + delta).rem_euclid(ROWS.len() as i32) as usize;
+    }
+
+    /**
+        The output device that's picked, or else the default one, for the sample rates and buffer sizes it can do
+    */
+    fn output(&self) -> Option<&DeviceInfo> {
+        match &self.settings.output {
+            Some(name) => self.outputs.iter().find(|info| &info.name == name),
+            None => self.outputs.iter().find(|info| info.is_default),
+        }
+    }
+
+    /**
+        Changes the selected setting to the next (or with a negative delta, the previous) option, where the first option is always the default
+    */
+    pub fn cycle(&mut self, delta: i32) {
+        fn next<T: Clone + PartialEq>(
+            options: Vec<T>,
+            current: &Option<T>,
+            delta: i32,
+        ) -> Option<T> {
+            let options = [None]
+                .into_iter()
+                .chain(options.into_iter().map(Some))
+                .collect::<Vec<_>>();
+            let i = options.iter().position(|o| o == current).unwrap_or(0) as i32;
+
+            options[(i + delta).rem_euclid(options.len() as i32) as usize].clone()
+        }
+
+        let names = |devices: &[DeviceInfo]| -> Vec<String> {
+            devices.iter().map(|info| info.name.clone()).collect()
+        };
+
+        match self.selected {
+            0 => {
+                self.settings.output = next(names(&self.outputs), &self.settings.output, delta);
+
+                // (the new device might not do the same)
+                let output = self.output().cloned();
+                if let Some(output) = output {
+                    self.settings.sample_rate = self
+                        .settings
+                        .sample_rate
+                        .filter(|rate| output.sample_rates.contains(rate));
+                    self.settings.buffer_size = self
+                        .settings
+                        .buffer_size
+                        .filter(|size| output.buffer_sizes.contains(size));
+                    self.settings.channels = self
+                        .settings
+                        .channels
+                        .filter(|&channels| channels <= output.channels);
+                    self.settings.channel_map.clear();
+                }
+            }
+            1 => self.settings.input = next(names(&self.inputs), &self.settings.input, delta),
+            2 => {
+                let rates = self
+                    .output()
+                    .map_or(vec![], |info| info.sample_rates.clone());
+                self.settings.sample_rate = next(rates, &self.settings.sample_rate, delta);
+            }
+            3 => {
+                let sizes = self
+                    .output()
+                    .map_or(vec![], |info| info.buffer_sizes.clone());
+                self.settings.buffer_size = next(sizes, &self.settings.buffer_size, delta);
+            }
+            4 => {
+                let most = self.output().map_or(0, |info| info.channels);
+                self.settings.channels = next((1..=most).collect(), &self.settings.channels, delta);
+
+                // (still from the same output, if that still fits)
+                let first = self.settings.first_output().filter(|first| {
+                    first + self.settings.channels.unwrap_or(DEFAULT_CHANNELS) - 1 <= most
+                });
+                self.settings.map_from(first);
+            }
+            5 => {
+                let most = self.output().map_or(0, |info| info.channels);
+                let channels = self.settings.channels.unwrap_or(DEFAULT_CHANNELS);
+                // (from output 1 is the default already)
+                let firsts = (2..=(most + 1).saturating_sub(channels)).collect();
+                let first = next(firsts, &self.settings.first_output(), delta);
+                self.settings.map_from(first);
+            }
+            _ => self.settings.economize = !self.settings.economize,
+        }
+    }
+
+    fn value(&self, row: usize) -> String {
+        match row {
+            0 => self
+                .settings
+                .output
+                .clone()
+                .unwrap_or("system default".into()),
+            1 => self
+                .settings
+                .input
+                .clone()
+                .unwrap_or("system default".into()),
+            2 => match self.settings.sample_rate {
+                Some(rate) => format!("{} Hz", rate),
+                None => format!("{} Hz (the engine's own)", SAMPLE_RATE),
+            },
+            3 => match self.settings.buffer_size {
+                Some(size) => format!("{} frames", size),
+                None => "device default".into(),
+            },
+            4 => match self.settings.channels {
+                Some(1) => "1 (mono)".into(),
+                Some(channels) => format!("{}", channels),
+                None => "device default".into(),
+            },
+            5 => match (
+                self.settings.first_output(),
+                self.settings.channel_map.as_slice(),
+            ) {
+                (_, []) => "in order, from output 1".into(),
+                (Some(first), map) => format!("{} to {}", first, first as usize + map.len() - 1),
+                (None, map) => map
+                    .iter()
+                    .map(|channel| channel.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            },
+            _ if self.settings.economize => "economize (thinner reverb)".into(),
+            _ => "just glitch".into(),
+        }
+    }
+
+    fn bounds(&self, (width, _): (f32, f32)) -> (f32, f32, f32, f32) {
+        let min_x = ((width - PANEL_WIDTH) / 2.0).max(0.0);
+
+        // (the rows, and then what's playing now, and how hard it's working)
+        (
+            min_x,
+            PANEL_TOP,
+            min_x + PANEL_WIDTH,
+            PANEL_TOP + TITLE_HEIGHT + (ROWS.len() as f32 + 3.5) * ROW_HEIGHT + 6.0,
+        )
+    }
+
+    /**
+        Which setting was clicked, if any. (`None` if the click was outside of the panel.)
+    */
+    pub fn hit_test(&self, window_size: (f32, f32), (x, y): (f32, f32)) -> Option<Option<usize>> {
+        let (min_x, min_y, max_x, max_y) = self.bounds(window_size);
+        if x < min_x || x > max_x || y < min_y || y > max_y {
+            return None;
+        }
+
+        let i = ((y - min_y - TITLE_HEIGHT) / ROW_HEIGHT).floor();
+        if i < 0.0 || i as usize >= ROWS.len() {
+            return Some(None);
+        }
+
+        Some(Some(i as usize))
+    }
+
+    /**
+        Clicking a setting selects it, and changes it to the next option
+    */
+    pub fn click(&mut self, row: usize) {
+        self.selected = row;
+        self.cycle(1);
+    }
+
+    /**
+        (`playing` is what the engine is actually doing now, and the callback load, if it's running.)
+    */
+    pub fn draw(
+        &self,
+        playing: Option<(Devices, f32)>,
+        window_size: (f32, f32),
+        overlay: &mut Overlay,
+    ) {
+        let (min_x, min_y, max_x, max_y) = self.bounds(window_size);
+        let text_y = |top: f32, height: f32| top + (height - FONT_SIZE) / 2.0;
+
+        overlay.quad((0.0, 0.0, window_size.0, window_size.1), BACKDROP_COLOR);
+        overlay.quad((min_x, min_y, max_x, max_y), PANEL_COLOR);
+
+        overlay.text(
+            (min_x + 12.0, text_y(min_y, TITLE_HEIGHT)),
+            "audio settings (Enter switches to them)",
+            FONT_SIZE,
+            DIM_TEXT_COLOR,
+        );
+
+        for (row, label) in ROWS.iter().enumerate() {
+            let top = min_y + TITLE_HEIGHT + row as f32 * ROW_HEIGHT;
+            let y = text_y(top, ROW_HEIGHT);
+
+            if row == self.selected {
+                overlay.quad((min_x, top, max_x, top + ROW_HEIGHT), SELECTED_COLOR);
+            }
+
+            overlay.text((min_x + 12.0, y), *label, FONT_SIZE, DIM_TEXT_COLOR);
+            overlay.text(
+                (min_x + VALUE_X, y),
+                format!("‹ {} ›", self.value(row)),
+                FONT_SIZE,
+                TEXT_COLOR,
+            );
+        }
+
+        let top = min_y + TITLE_HEIGHT + (ROWS.len() as f32 + 0.5) * ROW_HEIGHT;
+        let line = |i: usize| text_y(top + i as f32 * ROW_HEIGHT, ROW_HEIGHT);
+
+        let Some((devices, load)) = playing else {
+            overlay.text(
+                (min_x + 12.0, line(0)),
+                "the audio engine isn't running",
+                FONT_SIZE,
+                ERROR_COLOR,
+            );
+            return;
+        };
+
+        let now = match &devices.output {
+            Some(output) => format!(
+                "playing to {} at {} Hz{}{}",
+                output,
+                devices.sample_rate,
+                devices
+                    .buffer_size
+                    .map_or(String::new(), |size| format!(", {} frames", size)),
+                match devices.channels.len() {
+                    1 => ", mono".into(),
+                    channels => format!(", {} channels", channels),
+                },
+            ),
+            None => "not playing anywhere".into(),
+        };
+        overlay.text((min_x + 12.0, line(0)), now, FONT_SIZE, DIM_TEXT_COLOR);
+
+        overlay.text(
+            (min_x + 12.0, line(1)),
+            format!("callback load {:.0}%", load * 100.0),
+            FONT_SIZE,
+            if load > HIGH_LOAD {
+                ERROR_COLOR
+            } else {
+                DIM_TEXT_COLOR
+            },
+        );
+
+        if let Some(error) = &devices.error {
+            overlay.text(
+                (min_x + 12.0, line(2)),
+                format!("couldn't switch: {}", error),
+                FONT_SIZE,
+                ERROR_COLOR,
+            );
+        }
+    }
+}
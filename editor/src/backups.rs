@@ -0,0 +1,482 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use live_editor_state::{LineData, Token, WidgetInfo};
+
+use crate::{
+    render::Overlay,
+    storage::storage,
+    util::{config_dir, format_ago},
+    widget::WidgetManager,
+};
+
+const BACKUPS_DIR: &str = ".backups";
+const BACKUP_CONFIG_FILE: &str = "backups";
+const BACKUP_EXTENSION: &str = "live";
+
+const PICKER_WIDTH: f32 = 360.0;
+const PICKER_TOP: f32 = 64.0;
+const HEADER_HEIGHT: f32 = 36.0;
+const ROW_HEIGHT: f32 = 24.0;
+const MAX_ROWS: usize = 16;
+const FONT_SIZE: f32 = 14.0;
+
+const BACKDROP_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.08];
+const PICKER_COLOR: [f32; 4] = [0.99, 0.99, 0.98, 1.0];
+const SELECTED_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.08];
+const TEXT_COLOR: [f32; 4] = [0.02, 0.02, 0.02, 1.0];
+const DIM_TEXT_COLOR: [f32; 4] = [0.02, 0.02, 0.02, 0.45];
+
+/**
+    How many backups to keep, and how often to make one (if the code changed), configured in `~/.live_editor/backups` as e.g.
+
+    ```text
+    count 20
+    interval 60
+    ```
+
+    (with the interval in seconds)
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BackupConfig {
+    pub count: usize,
+    pub interval: Duration,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            count: 20,
+            interval: Duration::from_secs(60),
+        }
+    }
+}
+
+impl BackupConfig {
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let mut config = Self::default();
+
+        for (i, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let invalid = || format!("invalid backup config line {}: {:?}", i + 1, line);
+
+            match line.split_whitespace().collect::<Vec<_>>()[..] {
+                ["count", value] => {
+                    config.count = value.parse().map_err(|_| invalid())?;
+                }
+                ["interval", value] => {
+                    config.interval = Duration::from_secs(value.parse().map_err(|_| invalid())?);
+                }
+                _ => return Err(invalid()),
+            }
+        }
+
+        Ok(config)
+    }
+
+    pub fn load() -> Self {
+        let Some(contents) =
+            config_dir().and_then(|dir| storage().read(&dir.join(BACKUP_CONFIG_FILE)).ok())
+        else {
+            return Self::default();
+        };
+
+        Self::parse(&contents).unwrap_or_else(|e| {
+            tracing::warn!("Could not read backup config: {}", e);
+            Self::default()
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Backup {
+    path: PathBuf,
+    pub at: SystemTime,
+}
+
+/**
+    Rolling, timestamped backups of the code, in the workspace's `.backups` folder. Independent of git (or of saving at all), so that an improvised session that was never committed can't get lost.
+*/
+pub struct Backups {
+    dir: PathBuf,
+    config: BackupConfig,
+    started_at: SystemTime,
+    checked_at: Instant,
+    // what's in the newest backup (or what we started with)
+    last_source: String,
+}
+
+/**
+    The widget whose textual representation (`kind#id`) `s` starts with, if it's still around, and how long that representation is
+*/
+fn widget_at(s: &str, widget_manager: &WidgetManager) -> Option<(WidgetInfo, usize)> {
+    let (kind, rest) = s.split_once('#')?;
+    if kind.is_empty()
+        || !kind
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+    {
+        return None;
+    }
+
+    let digits = rest.chars().take_while(char::is_ascii_digit).count();
+    let id = rest[..digits].parse().ok()?;
+    let info = widget_manager.info(id).filter(|info| info.kind == kind)?;
+
+    Some((info, kind.len() + 1 + digits))
+}
+
+/**
+    Parses code as it's written to backups (or as it comes back from the formatter), turning references to widgets that still exist back into those widgets
+*/
+pub fn relink_widgets(source: &str, widget_manager: &WidgetManager) -> LineData {
+    source
+        .split('\n')
+        .map(|line| {
+            let mut tokens = vec![];
+            let mut rest = line;
+
+            while let Some(ch) = rest.chars().next() {
+                if let Some((info, len)) = widget_at(rest, widget_manager) {
+                    tokens.push(Token::Widget(info));
+                    rest = &rest[len..];
+                } else {
+                    tokens.push(Token::Char(ch));
+                    rest = &rest[ch.len_utf8()..];
+                }
+            }
+
+            tokens
+        })
+        .collect::<Vec<_>>()
+        .into()
+}
+
+impl Backups {
+    pub fn new(root: &Path, linedata: &LineData) -> Self {
+        Self {
+            dir: root.join(BACKUPS_DIR),
+            config: BackupConfig::load(),
+            started_at: SystemTime::now(),
+            checked_at: Instant::now(),
+            last_source: linedata.to_string(),
+        }
+    }
+
+    /**
+        Makes a backup if it's time for one, and the code changed since the last one
+    */
+    pub fn tick(&mut self, linedata: &LineData) {
+        if self.checked_at.elapsed() < self.config.interval {
+            return;
+        }
+
+        self.checked_at = Instant::now();
+        self.backup(linedata);
+    }
+
+    /**
+        Makes a backup right away (if the code changed since the last one), e.g. when quitting
+    */
+    pub fn backup(&mut self, linedata: &LineData) {
+        let source = linedata.to_string();
+        if source == self.last_source {
+            return;
+        }
+
+        if let Err(e) = self.write(&source) {
+            tracing::warn!("Could not back up the session: {}", e);
+            return;
+        }
+
+        self.last_source = source;
+        self.rotate();
+    }
+
+    /**
+        What's in the newest backup (or what we started with), with its widgets (for the diff mode)
+    */
+    pub fn last_saved(&self, widget_manager: &WidgetManager) -> LineData {
+        relink_widgets(&self.last_source, widget_manager)
+    }
+
+    fn write(&self, source: &str) -> Result<(), String> {
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| e.to_string())?
+            .as_millis();
+
+        let file = self.dir.join(format!("{}.{}", millis, BACKUP_EXTENSION));
+        storage().write(&file, source)
+    }
+
+    fn rotate(&self) {
+        for backup in self.list().into_iter().skip(self.config.count) {
+            if let Err(e) = storage().remove(&backup.path) {
+                tracing::warn!("Could not remove old backup {:?}: {:?}", backup.path, e);
+            }
+        }
+    }
+
+    /**
+        All backups, newest first
+    */
+    pub fn list(&self) -> Vec<Backup> {
+        let mut backups = storage()
+            .list(&self.dir)
+            .into_iter()
+            .filter_map(|path| {
+                if path.extension()? != BACKUP_EXTENSION {
+                    return None;
+                }
+
+                let millis = path.file_stem()?.to_str()?.parse::<u64>().ok()?;
+                let at = UNIX_EPOCH + Duration::from_millis(millis);
+
+                Some(Backup { path, at })
+            })
+            .collect::<Vec<_>>();
+
+        backups.sort_by_key(|backup| std::cmp::Reverse(backup.at));
+        backups
+    }
+
+    /**
+        Reads a backup back in. Backups made during this run of the editor get their widgets back, older ones just have them as text.
+    */
+    pub fn read(
+        &self,
+        backup: &Backup,
+        widget_manager: &WidgetManager,
+    ) -> Result<LineData, String> {
+        let source = storage().read(&backup.path)?;
+
+        if backup.at >= self.started_at {
+            Ok(relink_widgets(&source, widget_manager))
+        } else {
+            Ok(source.into())
+        }
+    }
+}
+
+/**
+    The "restore from backup" picker (Cmd+Shift+R): lists the session's backups, newest first. Restoring replaces the code, as a single edit that can be undone.
+*/
+pub struct BackupPicker {
+    open: bool,
+    // the backups, with how many lines each one has
+    entries: Vec<(Backup, usize)>,
+    selected: usize,
+}
+
+impl BackupPicker {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            entries: vec![],
+            selected: 0,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn open(&mut self, backups: &Backups) {
+        self.open = true;
+        self.selected = 0;
+        self.entries = backups
+            .list()
+            .into_iter()
+            .take(MAX_ROWS)
+            .map(|backup| {
+                let lines = storage()
+                    .read(&backup.path)
+                    .map_or(0, |source| source.lines().count());
+                (backup, lines)
+            })
+            .collect();
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+    }
+
+    pub fn move_selection(&mut self, delta: i32) {
+        let n = self.entries.len() as i32;
+        if n > 0 {
+            self.selected = (self.selected as i32 + delta).rem_euclid(n) as usize;
+        }
+    }
+
+    pub fn selected(&self) -> Option<&Backup> {
+        self.entries.get(self.selected).map(|(backup, _)| backup)
+    }
+
+    fn bounds(&self, (width, _): (f32, f32)) -> (f32, f32, f32, f32) {
+        let rows = self.entries.len().max(1);
+        let min_x = ((width - PICKER_WIDTH) / 2.0).max(0.0);
+
+        (
+            min_x,
+            PICKER_TOP,
+            min_x + PICKER_WIDTH,
+            PICKER_TOP + HEADER_HEIGHT + rows as f32 * ROW_HEIGHT + 6.0,
+        )
+    }
+
+    /**
+        Which backup was clicked, if any. (`None` if the click was outside of the picker.)
+    */
+    pub fn hit_test(&self, window_size: (f32, f32), (x, y): (f32, f32)) -> Option<Option<&Backup>> {
+        let (min_x, min_y, max_x, max_y) = self.bounds(window_size);
+        if x < min_x || x > max_x || y < min_y || y > max_y {
+            return None;
+        }
+
+        let i = ((y - min_y - HEADER_HEIGHT) / ROW_HEIGHT).floor();
+        if i < 0.0 {
+            return Some(None);
+        }
+
+        Some(self.entries.get(i as usize).map(|(backup, _)| backup))
+    }
+
+    pub fn draw(&self, window_size: (f32, f32), overlay: &mut Overlay) {
+        let (min_x, min_y, max_x, max_y) = self.bounds(window_size);
+        let text_y = |top: f32, height: f32| top + (height - FONT_SIZE) / 2.0;
+
+        overlay.quad((0.0, 0.0, window_size.0, window_size.1), BACKDROP_COLOR);
+        overlay.quad((min_x, min_y, max_x, max_y), PICKER_COLOR);
+
+        overlay.bold_text(
+            (min_x + 12.0, text_y(min_y, HEADER_HEIGHT)),
+            "Restore from backup",
+            FONT_SIZE,
+            TEXT_COLOR,
+        );
+
+        overlay.text(
+            (max_x - 130.0, text_y(min_y, HEADER_HEIGHT)),
+            "↑/↓, enter, esc",
+            FONT_SIZE,
+            DIM_TEXT_COLOR,
+        );
+
+        if self.entries.is_empty() {
+            overlay.text(
+                (min_x + 12.0, text_y(min_y + HEADER_HEIGHT, ROW_HEIGHT)),
+                "no backups yet",
+                FONT_SIZE,
+                DIM_TEXT_COLOR,
+            );
+            return;
+        }
+
+        for (i, (backup, lines)) in self.entries.iter().enumerate() {
+            let top = min_y + HEADER_HEIGHT + i as f32 * ROW_HEIGHT;
+            let y = text_y(top, ROW_HEIGHT);
+
+            if i == self.selected {
+                overlay.quad((min_x, top, max_x, top + ROW_HEIGHT), SELECTED_COLOR);
+            }
+
+            let ago = backup.at.elapsed().unwrap_or_default();
+            overlay.text((min_x + 12.0, y), format_ago(ago), FONT_SIZE, TEXT_COLOR);
+
+            overlay.text(
+                (max_x - 100.0, y),
+                format!("{} lines", lines),
+                FONT_SIZE,
+                DIM_TEXT_COLOR,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    /// (in a temp dir, which is removed when it's dropped, keeping 3, and backing up whenever it's asked)
+    fn backups(root: &TempDir) -> Backups {
+        Backups {
+            dir: root.path().join(BACKUPS_DIR),
+            config: BackupConfig {
+                count: 3,
+                interval: Duration::ZERO,
+            },
+            started_at: SystemTime::now(),
+            checked_at: Instant::now(),
+            last_source: String::new(),
+        }
+    }
+
+    /// (a millisecond apart at least, since that's what backups are named by)
+    fn back_up(backups: &mut Backups, source: &str) {
+        std::thread::sleep(Duration::from_millis(2));
+        backups.tick(&LineData::from(source));
+    }
+
+    fn sources(backups: &Backups) -> Vec<String> {
+        backups
+            .list()
+            .iter()
+            .map(|backup| storage().read(&backup.path).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_rotation() {
+        let root = tempfile::tempdir().unwrap();
+        let mut backups = backups(&root);
+
+        back_up(&mut backups, "a");
+        back_up(&mut backups, "b");
+        // (nothing changed, so no backup)
+        back_up(&mut backups, "b");
+        back_up(&mut backups, "c");
+        assert_eq!(sources(&backups), vec!["c", "b", "a"]);
+
+        // (only the newest ones are kept)
+        back_up(&mut backups, "d");
+        back_up(&mut backups, "e");
+        assert_eq!(sources(&backups), vec!["e", "d", "c"]);
+        assert_eq!(storage().list(&backups.dir).len(), 3);
+    }
+
+    #[test]
+    fn test_restore() {
+        let root = tempfile::tempdir().unwrap();
+        let mut backups = backups(&root);
+        let widget_manager = WidgetManager::new();
+
+        back_up(&mut backups, "play kick;\nplay hat;");
+        back_up(&mut backups, "play snare;");
+
+        let list = backups.list();
+        let restored = backups.read(&list[1], &widget_manager).unwrap();
+        assert_eq!(restored.to_string(), "play kick;\nplay hat;");
+        assert_eq!(
+            backups.last_saved(&widget_manager).to_string(),
+            "play snare;"
+        );
+
+        // (and one from before the editor started is just the text)
+        let old = Backup {
+            at: backups.started_at - Duration::from_secs(60),
+            ..list[0].clone()
+        };
+        assert_eq!(
+            backups.read(&old, &widget_manager).unwrap().to_string(),
+            "play snare;"
+        );
+    }
+}
@@ -0,0 +1,31 @@
+use live_editor_state::{Bookmarks, Pos};
+
+use crate::render::{Overlay, Renderer};
+
+const MARKER_WIDTH: f32 = 6.0;
+// (from where the code starts, left of the error icons)
+const MARKER_OFFSET: f32 = 32.0;
+const MARKER_COLOR: [f32; 4] = [0.45, 0.3, 0.85, 0.9];
+
+/**
+    A marker in the gutter of every bookmarked line (Cmd+F2 toggles them, F2 and Shift+F2 go to the next and previous one)
+*/
+pub fn draw_bookmarks(bookmarks: &Bookmarks, renderer: &Renderer, overlay: &mut Overlay) {
+    let system = &renderer.system;
+    let line_height = system.char_size.1 / system.scale_factor;
+
+    for row in bookmarks.rows() {
+        let (x, y) = system.pos_to_px(Pos { row, col: 0 });
+        let min_x = x - MARKER_OFFSET;
+
+        overlay.quad(
+            (
+                min_x,
+                y + line_height * 0.2,
+                min_x + MARKER_WIDTH,
+                y + line_height * 0.8,
+            ),
+            MARKER_COLOR,
+        );
+    }
+}
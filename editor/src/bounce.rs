@@ -0,0 +1,254 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        mpsc::{channel, Receiver, TryRecvError},
+        Arc,
+    },
+    thread,
+};
+
+use live_engine::{Bounce, SAMPLE_RATE};
+use live_language::{clips, evaluate_source_in, syntax_errors, Evaluation, Key};
+
+use crate::{
+    compile::{Compiler, Samples},
+    invalidation::Invalidator,
+    project::{find_project_root, ProjectFile},
+    widget::WidgetValue,
+};
+
+/// Where frozen definitions are rendered to, in the project (see `Editor::freeze`)
+pub(crate) const FROZEN_DIR: &str = "frozen";
+
+/// (a tenth of a second per chunk, so that the progress moves along smoothly)
+pub(crate) const CHUNK: usize = SAMPLE_RATE as usize / 10;
+
+/**
+    What to bounce: how many bars, and at which tempo and swing (the project's, see `ProjectFile`), with what everything that's random in the code comes out as
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BounceSettings {
+    pub bars: f64,
+    pub tempo: f64,
+    pub swing: f64,
+    pub seed: u64,
+}
+
+impl BounceSettings {
+    /**
+        The settings of the project that a document is in (for `live render`), with how many bars to render, unless the project file says
+    */
+    pub fn for_document(file: &Path, bars: Option<f64>) -> Self {
+        let project = file
+            .parent()
+            .and_then(find_project_root)
+            .map(|root| ProjectFile::load(&root))
+            .unwrap_or_default();
+
+        Self {
+            bars: bars.unwrap_or(project.bounce_bars()),
+            tempo: project.tempo(),
+            swing: project.swing(),
+            seed: 0,
+        }
+    }
+}
+
+/**
+    Where the paths in a document (like its samples) are relative to, for `live render`: its project, or else the directory it's in
+*/
+pub fn document_root(file: &Path) -> PathBuf {
+    let dir = file.parent().unwrap_or(Path::new("."));
+    find_project_root(dir).unwrap_or_else(|| dir.to_path_buf())
+}
+
+/**
+    Renders what the document plays (`bars` of it) into a WAV file, or a FLAC file when that's its extension, offline, so as fast as it goes, telling `progress` how far along it is (from 0 to 1). It doesn't render a document that doesn't evaluate, or that plays something that can't be bounced.
+
+    (For `live render`, where there are no widgets, so the code that uses them can't be bounced.)
+*/
+pub fn bounce(
+    source: &str,
+    root: &Path,
+    settings: BounceSettings,
+    path: &Path,
+    progress: impl FnMut(f32),
+) -> Result<(), String> {
+    bounce_with(source, root, &HashMap::new(), settings, path, progress)
+}
+
+/// (and with the widgets' values, by how the code refers to them, like in the editor)
+pub(crate) fn bounce_with(
+    source: &str,
+    root: &Path,
+    widgets: &HashMap<String, WidgetValue>,
+    settings: BounceSettings,
+    path: &Path,
+    progress: impl FnMut(f32),
+) -> Result<(), String> {
+    let evaluation = check(source, settings.seed, root)?;
+
+    let mut bounce = Bounce::new(settings.bars, settings.tempo);
+
+    let mut samples = Samples::default();
+    let (targets, errors) =
+        Compiler::new(&bounce, root, widgets, &mut samples).compile(&evaluation, source);
+    if let Some((name, message)) = errors.into_iter().next() {
+        return Err(format!("can't bounce {}: {}", name, message));
+    }
+
+    // (the clips are only heard while they're launched, which nothing does in a bounce)
+    let clips = clips(source);
+    for target in targets {
+        if !clips.iter().any(|clip| clip.target == target.name) {
+            bounce.play(target.name, target.node);
+        }
+    }
+
+    render(bounce, settings, path, progress)
+}
+
+/**
+    Renders just one of the document's definitions (`bars` of it) into a WAV file, like `bounce` does the whole document, for freezing it into a sample. It doesn't render a definition that can't be played on its own (like an effect that's waiting for its input).
+*/
+pub(crate) fn bounce_definition(
+    source: &str,
+    root: &Path,
+    widgets: &HashMap<String, WidgetValue>,
+    name: &str,
+    settings: BounceSettings,
+    path: &Path,
+    progress: impl FnMut(f32),
+) -> Result<(), String> {
+    let evaluation = check(source, settings.seed, root)?;
+
+    let key = Key::new(name);
+    let Some((_, value)) = evaluation.values.iter().find(|(k, _)| *k == key) else {
+        return Err(format!("{} isn't defined", name));
+    };
+
+    let mut bounce = Bounce::new(settings.bars, settings.tempo);
+
+    let mut samples = Samples::default();
+    let target = Compiler::new(&bounce, root, widgets, &mut samples)
+        .compile_value(&evaluation, &key, value)
+        .map_err(|message| format!("can't bounce {}: {}", name, message))?;
+    bounce.play(target.name, target.node);
+
+    render(bounce, settings, path, progress)
+}
+
+/// (it doesn't render, or play, a document that doesn't evaluate)
+pub(crate) fn check(source: &str, seed: u64, root: &Path) -> Result<Evaluation, String> {
+    if let Some((_, message)) = syntax_errors(source).into_iter().next() {
+        return Err(message);
+    }
+
+    let evaluation = evaluate_source_in(source, seed, root);
+    if let Some((_, message)) = evaluation.errors.first() {
+        return Err(message.clone());
+    }
+
+    Ok(evaluation)
+}
+
+fn render(
+    mut bounce: Bounce,
+    settings: BounceSettings,
+    path: &Path,
+    mut progress: impl FnMut(f32),
+) -> Result<(), String> {
+    bounce.set_swing(settings.swing);
+
+    while !bounce.is_done() {
+        progress(bounce.render(CHUNK));
+    }
+
+    write(&bounce, path)
+}
+
+/// (as a FLAC file when that's its extension, or else a WAV file)
+pub(crate) fn write(bounce: &Bounce, path: &Path) -> Result<(), String> {
+    let written = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("flac") => bounce.write_flac(path),
+        _ => bounce.write_wav(path),
+    };
+    written.map_err(|e| format!("could not write {}: {}", path.display(), e))
+}
+
+/**
+    A bounce that's rendering on a background thread, so that the editor keeps going meanwhile (and shows how far along it is in the status bar)
+*/
+pub struct BounceJob {
+    pub path: PathBuf,
+    // (the definition that's being frozen, when it's not the whole document)
+    pub definition: Option<String>,
+    // (in percent)
+    progress: Arc<AtomicU32>,
+    receiver: Receiver<Result<(), String>>,
+}
+
+impl BounceJob {
+    pub fn spawn(
+        source: String,
+        root: PathBuf,
+        widgets: HashMap<String, WidgetValue>,
+        definition: Option<String>,
+        settings: BounceSettings,
+        path: PathBuf,
+        invalidator: Invalidator,
+    ) -> Self {
+        let progress = Arc::new(AtomicU32::new(0));
+        let (sender, receiver) = channel();
+
+        thread::spawn({
+            let progress = progress.clone();
+            let path = path.clone();
+            let definition = definition.clone();
+            move || {
+                let report = |done: f32| {
+                    let percent = (done * 100.0) as u32;
+                    // (only redrawing when the status bar changes)
+                    if progress.swap(percent, Ordering::Relaxed) != percent {
+                        invalidator.invalidate();
+                    }
+                };
+
+                let result = match &definition {
+                    Some(name) => {
+                        bounce_definition(&source, &root, &widgets, name, settings, &path, report)
+                    }
+                    None => bounce_with(&source, &root, &widgets, settings, &path, report),
+                };
+
+                let _ = sender.send(result);
+                invalidator.invalidate();
+            }
+        });
+
+        Self {
+            path,
+            definition,
+            progress,
+            receiver,
+        }
+    }
+
+    pub fn percent(&self) -> u32 {
+        self.progress.load(Ordering::Relaxed)
+    }
+
+    /**
+        How it went, once it's done
+    */
+    pub fn poll(&self) -> Option<Result<(), String>> {
+        match self.receiver.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            // (the thread panicked)
+            Err(TryRecvError::Disconnected) => Some(Err("the bounce crashed".into())),
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use crate::{fuzzy::fuzzy_match, render::Overlay};
+
+const PICKER_WIDTH: f32 = 440.0;
+const PICKER_TOP: f32 = 64.0;
+const INPUT_HEIGHT: f32 = 36.0;
+const ROW_HEIGHT: f32 = 26.0;
+const MAX_ROWS: usize = 12;
+const FONT_SIZE: f32 = 15.0;
+
+const BACKDROP_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.08];
+const PICKER_COLOR: [f32; 4] = [0.99, 0.99, 0.98, 1.0];
+const SELECTED_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.08];
+const TEXT_COLOR: [f32; 4] = [0.02, 0.02, 0.02, 1.0];
+const DIM_TEXT_COLOR: [f32; 4] = [0.02, 0.02, 0.02, 0.45];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BranchPick {
+    Checkout(String),
+    /// (a name that's not a branch yet)
+    Create(String),
+}
+
+/**
+    The Cmd+Shift+G snapshot picker: named snapshots of the document are git branches (like "pre-show patch"), fuzzy filtered by name. Picking one checks it out and loads its document, and typing a name that isn't one yet makes a new one, from what's committed now.
+*/
+pub struct BranchPicker {
+    open: bool,
+    query: String,
+    selected: usize,
+    branches: Vec<String>,
+    current: Option<String>,
+}
+
+impl BranchPicker {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            query: String::new(),
+            selected: 0,
+            branches: vec![],
+            current: None,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn open(&mut self, branches: Vec<String>, current: Option<String>) {
+        self.open = true;
+        self.query.clear();
+        self.selected = 0;
+        self.branches = branches;
+        self.current = current;
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+    }
+
+    pub fn type_str(&mut self, s: &str) {
+        self.query.push_str(s);
+        self.selected = 0;
+    }
+
+    pub fn backspace(&mut self) {
+        self.query.pop();
+        self.selected = 0;
+    }
+
+    pub fn move_selection(&mut self, delta: i32) {
+        let n = self.picks().len() as i32;
+        if n > 0 {
+            self.selected = (self.selected as i32 + delta).rem_euclid(n) as usize;
+        }
+    }
+
+    /**
+        The branches that match, best match first, and then making a new one (if the query isn't a branch already)
+    */
+    fn picks(&self) -> Vec<BranchPick> {
+        let mut matches = self
+            .branches
+            .iter()
+            .filter_map(|branch| Some((branch, fuzzy_match(&self.query, branch)?)))
+            .collect::<Vec<_>>();
+
+        // stable, so equally good matches stay in alphabetical order
+        matches.sort_by_key(|&(_, score)| -score);
+
+        let mut picks = matches
+            .into_iter()
+            .map(|(branch, _)| BranchPick::Checkout(branch.clone()))
+            .take(MAX_ROWS)
+            .collect::<Vec<_>>();
+
+        let name = self.query.trim();
+        if !name.is_empty() && !self.branches.iter().any(|branch| branch == name) {
+            picks.push(BranchPick::Create(name.to_string()));
+        }
+
+        picks
+    }
+
+    pub fn selected(&self) -> Option<BranchPick> {
+        self.picks().get(self.selected).cloned()
+    }
+
+    fn bounds(&self, (width, _): (f32, f32)) -> (f32, f32, f32, f32) {
+        let rows = self.picks().len().max(1);
+        let min_x = ((width - PICKER_WIDTH) / 2.0).max(0.0);
+
+        (
+            min_x,
+            PICKER_TOP,
+            min_x + PICKER_WIDTH,
+            PICKER_TOP + INPUT_HEIGHT + rows as f32 * ROW_HEIGHT + 6.0,
+        )
+    }
+
+    /**
+        Which pick was clicked, if any. (`None` if the click was outside of the picker.)
+    */
+    pub fn hit_test(
+        &self,
+        window_size: (f32, f32),
+        (x, y): (f32, f32),
+    ) -> Option<Option<BranchPick>> {
+        let (min_x, min_y, max_x, max_y) = self.bounds(window_size);
+        if x < min_x || x > max_x || y < min_y || y > max_y {
+            return None;
+        }
+
+        let i = ((y - min_y - INPUT_HEIGHT) / ROW_HEIGHT).floor();
+        if i < 0.0 {
+            return Some(None);
+        }
+
+        Some(self.picks().get(i as usize).cloned())
+    }
+
+    pub fn draw(&self, window_size: (f32, f32), overlay: &mut Overlay) {
+        let (min_x, min_y, max_x, max_y) = self.bounds(window_size);
+        let text_y = |top: f32, height: f32| top + (height - FONT_SIZE) / 2.0;
+
+        overlay.quad((0.0, 0.0, window_size.0, window_size.1), BACKDROP_COLOR);
+        overlay.quad((min_x, min_y, max_x, max_y), PICKER_COLOR);
+
+        overlay.text(
+            (min_x + 12.0, text_y(min_y, INPUT_HEIGHT)),
+            format!("⎇ {}", self.query),
+            FONT_SIZE,
+            TEXT_COLOR,
+        );
+
+        let picks = self.picks();
+
+        if picks.is_empty() {
+            overlay.text(
+                (min_x + 12.0, text_y(min_y + INPUT_HEIGHT, ROW_HEIGHT)),
+                "no snapshots yet, type a name to make one",
+                FONT_SIZE,
+                DIM_TEXT_COLOR,
+            );
+        }
+
+        for (row, pick) in picks.iter().enumerate() {
+            let top = min_y + INPUT_HEIGHT + row as f32 * ROW_HEIGHT;
+            let y = text_y(top, ROW_HEIGHT);
+
+            if row == self.selected {
+                overlay.quad((min_x, top, max_x, top + ROW_HEIGHT), SELECTED_COLOR);
+            }
+
+            match pick {
+                BranchPick::Checkout(branch) => {
+                    overlay.text((min_x + 12.0, y), branch, FONT_SIZE, TEXT_COLOR);
+
+                    if self.current.as_ref() == Some(branch) {
+                        overlay.text((max_x - 72.0, y), "current", FONT_SIZE, DIM_TEXT_COLOR);
+                    }
+                }
+                BranchPick::Create(name) => {
+                    overlay.text(
+                        (min_x + 12.0, y),
+                        format!("new snapshot \"{}\"", name),
+                        FONT_SIZE,
+                        DIM_TEXT_COLOR,
+                    );
+                }
+            }
+        }
+    }
+}
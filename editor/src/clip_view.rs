@@ -0,0 +1,322 @@
+use std::time::{Duration, Instant};
+
+use live_engine::{EngineHandle, LauncherState};
+use live_language::Clip;
+
+use crate::{
+    panel::{
+        chars_in, contains, docked_bounds, draw_docked, fit, text_y, Bounds, HEADER_HEIGHT,
+        PANEL_MARGIN,
+    },
+    render::Overlay,
+};
+
+/// How often the clip view looks at the engine again while something's queued (which lands on its own, on the bar)
+const REFRESH_INTERVAL: Duration = Duration::from_millis(50);
+
+const ROW_HEIGHT: f32 = 28.0;
+const TRACK_WIDTH: f32 = 110.0;
+const CELL_WIDTH: f32 = 120.0;
+const CELL_GAP: f32 = 4.0;
+const FONT_SIZE: f32 = 13.0;
+
+/// (the pads of a controller, or the keys of musical typing: a row of this many notes per track, from `c2` on, where every note is a scene)
+const PAD_BASE: u8 = 36;
+const PADS_PER_ROW: u8 = 8;
+
+const PANEL_COLOR: [f32; 4] = [0.99, 0.99, 0.98, 0.97];
+const BORDER_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.1];
+const CELL_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.06];
+const EMPTY_CELL_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.02];
+const PLAYING_COLOR: [f32; 4] = [0.0, 0.6, 0.3, 1.0];
+const QUEUED_COLOR: [f32; 4] = [0.8, 0.45, 0.0, 1.0];
+const TEXT_COLOR: [f32; 4] = [0.02, 0.02, 0.02, 1.0];
+const DIM_TEXT_COLOR: [f32; 4] = [0.02, 0.02, 0.02, 0.45];
+const ACTIVE_TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+pub enum ClipViewHit {
+    /// a clip to launch on its track, or none, to stop the track
+    Launch {
+        track: String,
+        clip: Option<String>,
+    },
+    /// the number of a scene, to launch all of its clips at once
+    Scene(usize),
+    Panel,
+}
+
+/**
+    The clip launcher (Cmd+L), along the bottom of the window like the console: every `clip(beat, "drums", 1)` in the code is a cell in a grid, with a row per track and a column per scene. Clicking a clip launches it on the next bar, instead of what its track played, and clicking it again (or an empty cell) stops the track. Clicking a scene's number launches that whole column. Playing clips are green, and the ones that are about to start (or stop) are orange, until the bar comes.
+
+    While it's open, the notes that come in play the grid like pads (see `pad`), instead of the `midi_in` source.
+*/
+pub struct ClipView {
+    open: bool,
+    // (as of the last evaluation, which is when the engine hears about them)
+    clips: Vec<Clip>,
+    // (in the order they first appear in the code)
+    tracks: Vec<String>,
+    scenes: usize,
+    // what the launcher was doing when it was last drawn
+    seen: LauncherState,
+}
+
+impl ClipView {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            clips: vec![],
+            tracks: vec![],
+            scenes: 0,
+            seen: LauncherState::default(),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    /**
+        Puts the document's clips in the grid, and returns whether that changed anything (other than where they are in the code)
+    */
+    pub fn set_clips(&mut self, clips: Vec<Clip>) -> bool {
+        let cells = |clips: &[Clip]| {
+            clips
+                .iter()
+                .map(|clip| (clip.target.clone(), clip.track.clone(), clip.scene))
+                .collect::<Vec<_>>()
+        };
+        let changed = cells(&clips) != cells(&self.clips);
+
+        self.tracks.clear();
+        for clip in &clips {
+            if !self.tracks.contains(&clip.track) {
+                self.tracks.push(clip.track.clone());
+            }
+        }
+        self.scenes = clips.iter().map(|clip| clip.scene).max().unwrap_or(0);
+        self.clips = clips;
+
+        changed
+    }
+
+    /**
+        Tells the engine which targets are clips (so that they're only heard while they're launched), e.g. when it has just started
+    */
+    pub fn apply(&self, engine: &EngineHandle) {
+        engine.set_clips(
+            self.clips
+                .iter()
+                .map(|clip| (clip.target.clone(), clip.track.clone()))
+                .collect(),
+        );
+    }
+
+    /// (the first one, if there's more than one in the same cell)
+    fn clip_at(&self, track: &str, scene: usize) -> Option<&Clip> {
+        self.clips
+            .iter()
+            .find(|clip| clip.track == track && clip.scene == scene)
+    }
+
+    /**
+        What to launch for a whole scene: its clip on every track, and stopping the tracks that don't have one in it
+    */
+    pub fn scene(&self, scene: usize) -> Vec<(String, Option<String>)> {
+        self.tracks
+            .iter()
+            .map(|track| {
+                let clip = self.clip_at(track, scene).map(|clip| clip.target.clone());
+                (track.clone(), clip)
+            })
+            .collect()
+    }
+
+    fn is_playing(&self, track: &str, clip: &str) -> bool {
+        self.seen
+            .playing
+            .iter()
+            .any(|(t, c)| t == track && c == clip)
+    }
+
+    /// (what's queued for a track, if anything: a clip, or none, to stop it)
+    fn queued(&self, track: &str) -> Option<Option<&str>> {
+        self.seen
+            .queued
+            .iter()
+            .find(|(t, _)| t == track)
+            .map(|(_, clip)| clip.as_deref())
+    }
+
+    /**
+        When to look at the engine again, so that the event loop wakes up when what's queued lands
+    */
+    pub fn due_at(&self) -> Option<Instant> {
+        (self.open && !self.seen.queued.is_empty()).then(|| Instant::now() + REFRESH_INTERVAL)
+    }
+
+    pub fn needs_redraw(&self, engine: Option<&EngineHandle>) -> bool {
+        self.open && engine.is_some_and(|engine| engine.transport().launcher != self.seen)
+    }
+
+    fn bounds(&self, window_size: (f32, f32)) -> Bounds {
+        docked_bounds(
+            window_size,
+            HEADER_HEIGHT + self.tracks.len().max(1) as f32 * ROW_HEIGHT + 6.0,
+        )
+    }
+
+    /// (of a track's row, and a scene's column, where the first scene is 1)
+    fn cell_bounds(&self, (min_x, min_y, _, _): Bounds, row: usize, scene: usize) -> Bounds {
+        let x = min_x + PANEL_MARGIN + TRACK_WIDTH + (scene - 1) as f32 * (CELL_WIDTH + CELL_GAP);
+        let y = min_y + HEADER_HEIGHT + row as f32 * ROW_HEIGHT;
+
+        (x, y + 2.0, x + CELL_WIDTH, y + ROW_HEIGHT - 2.0)
+    }
+
+    pub fn hit_test(&self, window_size: (f32, f32), point: (f32, f32)) -> Option<ClipViewHit> {
+        let bounds = self.bounds(window_size);
+        if !contains(bounds, point) {
+            return None;
+        }
+
+        let (_, min_y, _, _) = bounds;
+        let inside = |cell: Bounds| contains(cell, point);
+
+        for scene in 1..=self.scenes {
+            let (cell_min_x, _, cell_max_x, _) = self.cell_bounds(bounds, 0, scene);
+            if inside((cell_min_x, min_y, cell_max_x, min_y + HEADER_HEIGHT)) {
+                return Some(ClipViewHit::Scene(scene));
+            }
+
+            for (row, track) in self.tracks.iter().enumerate() {
+                if !inside(self.cell_bounds(bounds, row, scene)) {
+                    continue;
+                }
+
+                return Some(self.launch(track, scene));
+            }
+        }
+
+        Some(ClipViewHit::Panel)
+    }
+
+    /**
+        What a note on a pad does, like clicking a cell: the pads from `c2` (36) on are the grid, a row of 8 per track, and a scene per note
+    */
+    pub fn pad(&self, note: u8) -> Option<ClipViewHit> {
+        let pad = note.checked_sub(PAD_BASE)?;
+        let (row, scene) = (pad / PADS_PER_ROW, pad % PADS_PER_ROW + 1);
+
+        let track = self.tracks.get(row as usize)?;
+        Some(self.launch(track, scene as usize))
+    }
+
+    /// (clicking a clip that's playing, or about to, stops it)
+    fn launch(&self, track: &str, scene: usize) -> ClipViewHit {
+        let clip = self
+            .clip_at(track, scene)
+            .map(|clip| clip.target.as_str())
+            .filter(|&clip| match self.queued(track) {
+                Some(queued) => queued != Some(clip),
+                None => !self.is_playing(track, clip),
+            });
+
+        ClipViewHit::Launch {
+            track: track.to_string(),
+            clip: clip.map(str::to_string),
+        }
+    }
+
+    pub fn draw(
+        &mut self,
+        engine: Option<&EngineHandle>,
+        window_size: (f32, f32),
+        overlay: &mut Overlay,
+    ) {
+        self.seen = engine
+            .map(|engine| engine.transport().launcher)
+            .unwrap_or_default();
+
+        let bounds = self.bounds(window_size);
+        let (min_x, min_y, _, _) = bounds;
+        let text_y = |top: f32, height: f32| text_y(top, height, FONT_SIZE);
+
+        draw_docked(
+            bounds,
+            "Clips",
+            FONT_SIZE,
+            [PANEL_COLOR, BORDER_COLOR, TEXT_COLOR],
+            overlay,
+        );
+
+        if self.clips.is_empty() {
+            overlay.text(
+                (
+                    min_x + PANEL_MARGIN,
+                    text_y(min_y + HEADER_HEIGHT, ROW_HEIGHT),
+                ),
+                "(no clips, like `clip(beat, \"drums\", 1)`, as of the last evaluation)",
+                FONT_SIZE,
+                DIM_TEXT_COLOR,
+            );
+            return;
+        }
+
+        let max_chars = chars_in(CELL_WIDTH - 2.0 * CELL_GAP);
+        let fit = |text: &str| fit(text, max_chars);
+
+        for scene in 1..=self.scenes {
+            let (cell_min_x, _, _, _) = self.cell_bounds(bounds, 0, scene);
+            overlay.text(
+                (cell_min_x + CELL_GAP, text_y(min_y, HEADER_HEIGHT)),
+                format!("▶ {}", scene),
+                FONT_SIZE,
+                DIM_TEXT_COLOR,
+            );
+        }
+
+        for (row, track) in self.tracks.iter().enumerate() {
+            let y = text_y(min_y + HEADER_HEIGHT + row as f32 * ROW_HEIGHT, ROW_HEIGHT);
+            overlay.bold_text((min_x + PANEL_MARGIN, y), fit(track), FONT_SIZE, TEXT_COLOR);
+
+            let queued = self.queued(track);
+
+            for scene in 1..=self.scenes {
+                let cell = self.cell_bounds(bounds, row, scene);
+
+                let Some(clip) = self.clip_at(track, scene) else {
+                    overlay.quad(cell, EMPTY_CELL_COLOR);
+                    continue;
+                };
+
+                let playing = self.is_playing(track, &clip.target);
+                // (about to start, or about to stop, because something else is queued)
+                let pending = match queued {
+                    Some(queued) => (queued == Some(clip.target.as_str())) != playing,
+                    None => false,
+                };
+
+                let (color, text_color) = if pending {
+                    (QUEUED_COLOR, ACTIVE_TEXT_COLOR)
+                } else if playing {
+                    (PLAYING_COLOR, ACTIVE_TEXT_COLOR)
+                } else {
+                    (CELL_COLOR, TEXT_COLOR)
+                };
+
+                overlay.quad(cell, color);
+                overlay.text(
+                    (cell.0 + CELL_GAP, text_y(cell.1, cell.3 - cell.1)),
+                    fit(&clip.target),
+                    FONT_SIZE,
+                    text_color,
+                );
+            }
+        }
+    }
+}
@@ -1,16 +1,113 @@
 use live_editor_state::LineData;
-use tao::clipboard::Clipboard as TaoClipboard;
+use std::{collections::HashMap, path::PathBuf};
 
+use crate::sample_packs::is_audio_file;
+
+/// How many numbered registers there are (1 up to this), next to the default one
+pub const REGISTERS: usize = 9;
+
+/**
+    The platform's clipboard, which mostly knows about text
+*/
+pub trait SystemClipboard {
+    fn read_text(&self) -> Option<String>;
+    fn write_text(&mut self, text: String);
+
+    // The files that are on it, like ones that were copied in Finder (if it knows about those)
+    fn read_files(&self) -> Vec<PathBuf> {
+        vec![]
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl SystemClipboard for tao::clipboard::Clipboard {
+    fn read_text(&self) -> Option<String> {
+        tao::clipboard::Clipboard::read_text(self)
+    }
+
+    fn write_text(&mut self, text: String) {
+        tao::clipboard::Clipboard::write_text(self, text);
+    }
+
+    fn read_files(&self) -> Vec<PathBuf> {
+        // (tao's clipboard only does text, but arboard can read file lists)
+        arboard::Clipboard::new()
+            .and_then(|mut clipboard| clipboard.get().file_list())
+            .unwrap_or_default()
+    }
+}
+
+/**
+    The browser's async clipboard API (which needs `--cfg=web_sys_unstable_apis`). It doesn't hand over what's on the clipboard right away (or at all, when the page isn't allowed to read it), so reading asks for it, and gives what it handed over last, or what we wrote ourselves, whichever came last.
+*/
+#[cfg(target_arch = "wasm32")]
+pub struct WebClipboard {
+    text: std::sync::Arc<std::sync::Mutex<Option<String>>>,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl WebClipboard {
+    fn clipboard() -> Option<web_sys::Clipboard> {
+        web_sys::window().map(|window| window.navigator().clipboard())
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl SystemClipboard for WebClipboard {
+    fn read_text(&self) -> Option<String> {
+        if let Some(clipboard) = Self::clipboard() {
+            let text = self.text.clone();
+            let read = wasm_bindgen_futures::JsFuture::from(clipboard.read_text());
+
+            wasm_bindgen_futures::spawn_local(async move {
+                if let Ok(Some(read)) = read.await.map(|read| read.as_string())
+                    && let Ok(mut text) = text.lock()
+                {
+                    *text = Some(read);
+                }
+            });
+        }
+
+        self.text.lock().ok()?.clone()
+    }
+
+    fn write_text(&mut self, text: String) {
+        if let Some(clipboard) = Self::clipboard() {
+            // (fire and forget, there's nothing we can do if it's refused)
+            let _ = clipboard.write_text(&text);
+        }
+
+        if let Ok(mut written) = self.text.lock() {
+            *written = Some(text);
+        }
+    }
+}
+
+fn system_clipboard() -> Box<dyn SystemClipboard> {
+    #[cfg(not(target_arch = "wasm32"))]
+    return Box::new(tao::clipboard::Clipboard::new());
+
+    #[cfg(target_arch = "wasm32")]
+    return Box::new(WebClipboard {
+        text: Default::default(),
+    });
+}
+
+/**
+    What's copied: the default register (Cmd+C and Cmd+V, which is shared with the system clipboard), and numbered ones, like vim's, which only the editor knows about. Either way, with more than one caret, what every caret copied is kept apart, so that it pastes back per caret.
+*/
 pub struct Clipboard {
-    system_clipboard: TaoClipboard,
+    system_clipboard: Box<dyn SystemClipboard>,
     copied: Option<Vec<LineData>>,
+    registers: HashMap<usize, Vec<LineData>>,
 }
 
 impl Clipboard {
     pub fn new() -> Self {
         Self {
-            system_clipboard: TaoClipboard::new(),
+            system_clipboard: system_clipboard(),
             copied: None,
+            registers: HashMap::new(),
         }
     }
 
@@ -22,6 +119,17 @@ impl Clipboard {
         })
     }
 
+    /**
+        The audio files on the clipboard, if it's files that are on it (like when they were copied in Finder), to paste as samples
+    */
+    pub fn read_audio_files(&self) -> Vec<PathBuf> {
+        self.system_clipboard
+            .read_files()
+            .into_iter()
+            .filter(|file| is_audio_file(file))
+            .collect()
+    }
+
     pub fn write(&mut self, data: impl AsRef<Vec<LineData>>) {
         let data = data.as_ref().clone();
 
@@ -34,4 +142,12 @@ impl Clipboard {
 
         self.copied = Some(data);
     }
+
+    pub fn read_register(&self, register: usize) -> Option<Vec<LineData>> {
+        self.registers.get(&register).cloned()
+    }
+
+    pub fn write_register(&mut self, register: usize, data: Vec<LineData>) {
+        self.registers.insert(register, data);
+    }
 }
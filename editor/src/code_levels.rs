@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+
+use live_editor_state::{LineData, LineSelection, Range};
+use live_engine::Level;
+use live_language::{outline, play_targets};
+
+use crate::util::span_to_range;
+
+/// RMS is quite a bit lower than peak for most sounds, so we boost it a bit before showing it
+const RMS_GAIN: f32 = 3.0;
+
+/**
+    Which parts of the code make which sound: for every `play name;`, that's the play statement itself plus the declaration of `name`. Re-derived from the source whenever it changes, like the outline.
+*/
+#[derive(Debug, Default)]
+pub struct CodeLevels {
+    source: Option<String>,
+    regions: Vec<(String, Range)>,
+}
+
+impl CodeLevels {
+    pub fn sync(&mut self, linedata: &LineData) {
+        let source = linedata.to_string();
+        if self.source.as_ref() == Some(&source) {
+            return;
+        }
+
+        let symbols = outline(&source);
+
+        self.regions = vec![];
+
+        for target in play_targets(&source) {
+            let declarations = symbols
+                .iter()
+                .filter(|symbol| symbol.name == target.name)
+                .map(|symbol| symbol.range);
+
+            for span in declarations.chain([target.range]) {
+                self.regions
+                    .push((target.name.clone(), span_to_range(linedata, span)));
+            }
+        }
+
+        self.source = Some(source);
+    }
+
+    /**
+        The line spans to tint, with how loud (0-1) they currently are. Silent targets are left out.
+    */
+    pub fn line_levels(
+        &self,
+        linedata: &LineData,
+        levels: &HashMap<String, Level>,
+    ) -> Vec<(LineSelection, f32)> {
+        let mut line_levels = vec![];
+
+        for (name, range) in &self.regions {
+            let Some(level) = levels.get(name) else {
+                continue;
+            };
+
+            let intensity = (level.rms * RMS_GAIN).min(1.0);
+            if intensity <= 0.0 {
+                continue;
+            }
+
+            for line in linedata.line_selections(*range) {
+                line_levels.push((line, intensity));
+            }
+        }
+
+        line_levels
+    }
+
+    /**
+        The line spans of every target's code (the play statement and the declaration, each), with what the target costs (see `Costs`). Targets that cost less than `min` are left out.
+    */
+    pub fn line_costs(
+        &self,
+        linedata: &LineData,
+        costs: &HashMap<String, f32>,
+        min: f32,
+    ) -> Vec<(Vec<LineSelection>, f32)> {
+        self.regions
+            .iter()
+            .filter_map(|(name, range)| {
+                let cost = *costs.get(name)?;
+                (cost >= min).then(|| (linedata.line_selections(*range), cost))
+            })
+            .collect()
+    }
+}
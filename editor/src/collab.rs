@@ -0,0 +1,256 @@
+use std::{
+    io::{ErrorKind, Read, Write},
+    net::TcpListener,
+    sync::mpsc::{channel, Receiver, Sender, TryRecvError},
+    thread,
+    time::Duration,
+};
+
+use live_editor_state::{Op, SharedSelection};
+use tungstenite::{stream::MaybeTlsStream, WebSocket};
+
+use crate::invalidation::Invalidator;
+
+/// The host's site id (every replica has its own, see `OpId`)
+pub const HOST_SITE: u32 = 1;
+/// The site id of whoever joins (there's just the one other performer)
+pub const GUEST_SITE: u32 = 2;
+
+/// How long the network thread waits for something to arrive, before sending what the editor queued up
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How to edit together, as chosen on the command line
+#[derive(Debug, Clone)]
+pub enum Sharing {
+    /// Hosting the document, at an address like `0.0.0.0:7878`
+    Host(String),
+    /// Joining someone's document, at a url like `ws://192.168.1.10:7878`
+    Join(String),
+}
+
+/// What's sent over the WebSocket
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    /// (from the host, to whoever joins) their site id, and the document
+    Welcome {
+        site: u32,
+        snapshot: Vec<Op>,
+    },
+    Ops(Vec<Op>),
+    Selections {
+        site: u32,
+        selections: Vec<SharedSelection>,
+    },
+}
+
+impl Message {
+    /**
+        A text message, with a header line, and then an op or selection per line
+    */
+    fn encode(&self) -> String {
+        let (header, lines) = match self {
+            Message::Welcome { site, snapshot } => (format!("welcome {}", site), lines(snapshot)),
+            Message::Ops(ops) => ("ops".into(), lines(ops)),
+            Message::Selections { site, selections } => {
+                (format!("selections {}", site), lines(selections))
+            }
+        };
+
+        [header]
+            .into_iter()
+            .chain(lines)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn decode(text: &str) -> Option<Self> {
+        let mut lines = text.lines();
+        let header = lines.next()?.split(' ').collect::<Vec<_>>();
+
+        match header[..] {
+            ["welcome", site] => Some(Message::Welcome {
+                site: site.parse().ok()?,
+                snapshot: lines.map(|line| line.parse().ok()).collect::<Option<_>>()?,
+            }),
+            ["ops"] => Some(Message::Ops(
+                lines.map(|line| line.parse().ok()).collect::<Option<_>>()?,
+            )),
+            ["selections", site] => Some(Message::Selections {
+                site: site.parse().ok()?,
+                selections: lines.map(|line| line.parse().ok()).collect::<Option<_>>()?,
+            }),
+            _ => None,
+        }
+    }
+}
+
+fn lines<T: ToString>(items: &[T]) -> Vec<String> {
+    items.iter().map(ToString::to_string).collect()
+}
+
+pub enum CollabEvent {
+    /// Someone joined (when hosting), or we got through to the host (when joining)
+    Connected,
+    Received(Message),
+    Disconnected,
+    /// Hosting or joining didn't work at all
+    Failed(String),
+}
+
+/**
+    Editing the same document together, over a WebSocket: one performer hosts (`live --host 0.0.0.0:7878`), and another joins (`live --join ws://<host>:7878`), after which both edit the host's document. Edits are replicated through the editor state's `Replica` (a CRDT), so nobody has to wait for the other, and everybody's selections are drawn in the other's editor too.
+
+    The network runs on its own thread, and the editor picks up what arrived (and hands over what to send) once per batch of events, see `Editor::sync_collab`. Widgets aren't shared: the other side gets a placeholder for them.
+*/
+pub struct Collab {
+    events: Receiver<CollabEvent>,
+    outgoing: Sender<Message>,
+    /// Whether there's someone on the other side
+    pub connected: bool,
+    /// Our site id, once it's known (the host's is, right away)
+    pub site: Option<u32>,
+    /// What we sent last of our selections, so that they're only sent when they change
+    pub sent_selections: Vec<SharedSelection>,
+}
+
+impl Collab {
+    pub fn start(sharing: Sharing, invalidator: Invalidator) -> Self {
+        let (event_sender, events) = channel();
+        let (outgoing, outgoing_receiver) = channel();
+
+        let site = match sharing {
+            Sharing::Host(_) => Some(HOST_SITE),
+            Sharing::Join(_) => None,
+        };
+
+        thread::spawn(move || {
+            let notify = |event| {
+                let _ = event_sender.send(event);
+                invalidator.invalidate();
+            };
+
+            let result = match sharing {
+                Sharing::Host(addr) => host(&addr, &notify, &outgoing_receiver),
+                Sharing::Join(url) => join(&url, &notify, &outgoing_receiver),
+            };
+
+            if let Err(e) = result {
+                notify(CollabEvent::Failed(e));
+            }
+        });
+
+        Self {
+            events,
+            outgoing,
+            connected: false,
+            site,
+            sent_selections: vec![],
+        }
+    }
+
+    /**
+        What happened since the last time this was called
+    */
+    pub fn events(&self) -> Vec<CollabEvent> {
+        self.events.try_iter().collect()
+    }
+
+    pub fn send(&self, message: Message) {
+        let _ = self.outgoing.send(message);
+    }
+}
+
+fn host(
+    addr: &str,
+    notify: &impl Fn(CollabEvent),
+    outgoing: &Receiver<Message>,
+) -> Result<(), String> {
+    let listener =
+        TcpListener::bind(addr).map_err(|e| format!("Could not host on {}: {}", addr, e))?;
+
+    // (one at a time)
+    for stream in listener.incoming() {
+        let Ok(mut socket) = stream
+            .map_err(|e| e.to_string())
+            .and_then(|stream| tungstenite::accept(stream).map_err(|e| e.to_string()))
+        else {
+            continue;
+        };
+
+        let _ = socket.get_ref().set_read_timeout(Some(POLL_INTERVAL));
+
+        // (what was queued up while nobody was there is part of the welcome)
+        while outgoing.try_recv().is_ok() {}
+
+        notify(CollabEvent::Connected);
+        let editor_gone = session(&mut socket, notify, outgoing);
+        notify(CollabEvent::Disconnected);
+
+        if editor_gone {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+fn join(
+    url: &str,
+    notify: &impl Fn(CollabEvent),
+    outgoing: &Receiver<Message>,
+) -> Result<(), String> {
+    let (mut socket, _) =
+        tungstenite::connect(url).map_err(|e| format!("Could not join {}: {}", url, e))?;
+
+    if let MaybeTlsStream::Plain(stream) = socket.get_ref() {
+        let _ = stream.set_read_timeout(Some(POLL_INTERVAL));
+    }
+
+    notify(CollabEvent::Connected);
+    session(&mut socket, notify, outgoing);
+    notify(CollabEvent::Disconnected);
+
+    Ok(())
+}
+
+/**
+    Passes messages both ways until either side hangs up, and returns whether it was the editor (that is, whether the app's closing)
+*/
+fn session<S: Read + Write>(
+    socket: &mut WebSocket<S>,
+    notify: &impl Fn(CollabEvent),
+    outgoing: &Receiver<Message>,
+) -> bool {
+    loop {
+        match socket.read() {
+            Ok(tungstenite::Message::Text(text)) => match Message::decode(&text) {
+                Some(message) => notify(CollabEvent::Received(message)),
+                None => tracing::warn!("Could not read a message from the other side"),
+            },
+            Ok(tungstenite::Message::Close(_)) => return false,
+            Ok(_) => {}
+            // (nothing arrived in time, so it's our turn to send)
+            Err(tungstenite::Error::Io(e))
+                if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(_) => return false,
+        }
+
+        loop {
+            match outgoing.try_recv() {
+                Ok(message) => {
+                    if socket
+                        .send(tungstenite::Message::Text(message.encode()))
+                        .is_err()
+                    {
+                        return false;
+                    }
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    let _ = socket.close(None);
+                    return true;
+                }
+            }
+        }
+    }
+}
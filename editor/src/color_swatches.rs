@@ -0,0 +1,229 @@
+use live_editor_state::{LineData, Pos, Range};
+use live_language::{color_literals, format_color};
+use palette::{FromColor, Hsla, Srgba};
+
+use crate::{
+    panel::{contains, Bounds},
+    render::{InlayHint, Overlay, Renderer},
+    util::span_to_range,
+};
+
+/// (the room a swatch takes up after its literal, in columns)
+const SWATCH_HINT: &str = "   ";
+
+const HUES: usize = 12;
+const LIGHTNESS: [f32; 6] = [0.85, 0.72, 0.6, 0.48, 0.36, 0.24];
+const SATURATION: f32 = 0.8;
+const CELL_SIZE: f32 = 18.0;
+const CELL_GAP: f32 = 2.0;
+const PADDING: f32 = 8.0;
+const HEADER_HEIGHT: f32 = 22.0;
+const FONT_SIZE: f32 = 13.0;
+
+const BORDER_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.5];
+const PANEL_COLOR: [f32; 4] = [0.1, 0.1, 0.1, 0.92];
+const SELECTED_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const TEXT_COLOR: [f32; 4] = [0.98, 0.98, 0.98, 1.0];
+
+pub enum ColorSwatchesHit {
+    Swatch(usize),
+    /// a color in the open picker, for the literal it's open for
+    Pick(usize, [u8; 4]),
+    /// somewhere on the open picker, but not on a color
+    Popup,
+}
+
+/**
+    A swatch right after every color literal in the code (like `#ff8800`), drawn in the room of an inlay hint, so that it's not part of the document (like a widget would be). Clicking one opens a picker, and picking a color rewrites the literal.
+*/
+#[derive(Default)]
+pub struct ColorSwatches {
+    source: Option<String>,
+    literals: Vec<(Range, [u8; 4])>,
+    // (the literal the picker is open for)
+    open: Option<usize>,
+}
+
+fn to_rgba([r, g, b, a]: [u8; 4]) -> [f32; 4] {
+    [r, g, b, a].map(|channel| channel as f32 / 255.0)
+}
+
+/**
+    What the picker offers: rows of hues getting darker, and then grays, from white to black
+*/
+fn picker_colors() -> Vec<Vec<[u8; 4]>> {
+    let mut rows = LIGHTNESS
+        .iter()
+        .map(|&lightness| {
+            (0..HUES)
+                .map(|i| {
+                    let hue = i as f32 * 360.0 / HUES as f32;
+                    Srgba::from_color(Hsla::new(hue, SATURATION, lightness, 1.0))
+                        .into_format()
+                        .into()
+                })
+                .collect()
+        })
+        .collect::<Vec<_>>();
+
+    rows.push(
+        (0..HUES)
+            .map(|i| {
+                let gray = (255.0 * (1.0 - i as f32 / (HUES - 1) as f32)).round() as u8;
+                [gray, gray, gray, 255]
+            })
+            .collect(),
+    );
+
+    rows
+}
+
+impl ColorSwatches {
+    /**
+        Finds the color literals again (when the code changed), and makes room for their swatches
+    */
+    pub fn sync(&mut self, linedata: &LineData, renderer: &mut Renderer) {
+        let source = linedata.to_string();
+        if self.source.as_ref() == Some(&source) {
+            return;
+        }
+
+        self.literals = color_literals(&source)
+            .into_iter()
+            .map(|literal| (span_to_range(linedata, literal.range), literal.color))
+            .collect();
+
+        renderer.set_inlay_hints(
+            self.literals
+                .iter()
+                .map(|(range, _)| InlayHint {
+                    pos: range.end,
+                    text: SWATCH_HINT.into(),
+                })
+                .collect(),
+        );
+
+        self.open = self.open.filter(|&i| i < self.literals.len());
+        self.source = Some(source);
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open.is_some()
+    }
+
+    pub fn open(&mut self, i: usize) {
+        self.open = Some(i);
+    }
+
+    pub fn close(&mut self) {
+        self.open = None;
+    }
+
+    /**
+        Where the literal is, and what it is
+    */
+    pub fn literal(&self, i: usize) -> Option<(Range, [u8; 4])> {
+        self.literals.get(i).copied()
+    }
+
+    fn swatch_bounds(renderer: &Renderer, pos: Pos) -> (f32, f32, f32, f32) {
+        let system = &renderer.system;
+        let (x, y) = system.pos_to_px(pos);
+        let char_width = system.char_size.0 / system.scale_factor;
+        let line_height = system.char_size.1 / system.scale_factor;
+
+        (
+            x + char_width * 0.5,
+            y + line_height * 0.2,
+            x + char_width * 2.5,
+            y + line_height * 0.8,
+        )
+    }
+
+    /**
+        Right below the swatch it's open for
+    */
+    fn picker_bounds(renderer: &Renderer, pos: Pos) -> (f32, f32, f32, f32) {
+        let (min_x, _, _, max_y) = Self::swatch_bounds(renderer, pos);
+        let rows = LIGHTNESS.len() + 1;
+
+        let min_y = max_y + 4.0;
+        let width = 2.0 * PADDING + HUES as f32 * (CELL_SIZE + CELL_GAP) - CELL_GAP;
+        let height =
+            2.0 * PADDING + HEADER_HEIGHT + rows as f32 * (CELL_SIZE + CELL_GAP) - CELL_GAP;
+
+        (min_x, min_y, min_x + width, min_y + height)
+    }
+
+    fn cell_bounds(picker: Bounds, row: usize, col: usize) -> Bounds {
+        let min_x = picker.0 + PADDING + col as f32 * (CELL_SIZE + CELL_GAP);
+        let min_y = picker.1 + PADDING + HEADER_HEIGHT + row as f32 * (CELL_SIZE + CELL_GAP);
+
+        (min_x, min_y, min_x + CELL_SIZE, min_y + CELL_SIZE)
+    }
+
+    pub fn hit_test(&self, renderer: &Renderer, mouse: (f32, f32)) -> Option<ColorSwatchesHit> {
+        let inside = |bounds: Bounds| contains(bounds, mouse);
+
+        if let Some(i) = self.open
+            && let Some((range, _)) = self.literals.get(i)
+        {
+            let picker = Self::picker_bounds(renderer, range.end);
+            if inside(picker) {
+                for (row, colors) in picker_colors().into_iter().enumerate() {
+                    for (col, color) in colors.into_iter().enumerate() {
+                        if inside(Self::cell_bounds(picker, row, col)) {
+                            return Some(ColorSwatchesHit::Pick(i, color));
+                        }
+                    }
+                }
+
+                return Some(ColorSwatchesHit::Popup);
+            }
+        }
+
+        self.literals
+            .iter()
+            .position(|(range, _)| inside(Self::swatch_bounds(renderer, range.end)))
+            .map(ColorSwatchesHit::Swatch)
+    }
+
+    pub fn draw(&self, renderer: &Renderer, overlay: &mut Overlay) {
+        for (range, color) in &self.literals {
+            let (min_x, min_y, max_x, max_y) = Self::swatch_bounds(renderer, range.end);
+
+            overlay.quad(
+                (min_x - 1.0, min_y - 1.0, max_x + 1.0, max_y + 1.0),
+                BORDER_COLOR,
+            );
+            overlay.quad((min_x, min_y, max_x, max_y), to_rgba(*color));
+        }
+
+        let Some((range, current)) = self.open.and_then(|i| self.literal(i)) else {
+            return;
+        };
+
+        let picker = Self::picker_bounds(renderer, range.end);
+        overlay.quad(picker, PANEL_COLOR);
+        overlay.text(
+            (picker.0 + PADDING, picker.1 + PADDING),
+            format_color(current),
+            FONT_SIZE,
+            TEXT_COLOR,
+        );
+
+        for (row, colors) in picker_colors().into_iter().enumerate() {
+            for (col, color) in colors.into_iter().enumerate() {
+                let (min_x, min_y, max_x, max_y) = Self::cell_bounds(picker, row, col);
+
+                if color[..3] == current[..3] {
+                    overlay.quad(
+                        (min_x - 2.0, min_y - 2.0, max_x + 2.0, max_y + 2.0),
+                        SELECTED_COLOR,
+                    );
+                }
+                overlay.quad((min_x, min_y, max_x, max_y), to_rgba(color));
+            }
+        }
+    }
+}
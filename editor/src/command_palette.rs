@@ -0,0 +1,164 @@
+use crate::{commands::EditorCommand, fuzzy::fuzzy_match, render::Overlay};
+
+const PALETTE_WIDTH: f32 = 480.0;
+const PALETTE_TOP: f32 = 64.0;
+const INPUT_HEIGHT: f32 = 36.0;
+const ROW_HEIGHT: f32 = 26.0;
+const MAX_ROWS: usize = 12;
+const FONT_SIZE: f32 = 15.0;
+
+const BACKDROP_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.08];
+const PALETTE_COLOR: [f32; 4] = [0.99, 0.99, 0.98, 1.0];
+const SELECTED_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.08];
+const TEXT_COLOR: [f32; 4] = [0.02, 0.02, 0.02, 1.0];
+const DIM_TEXT_COLOR: [f32; 4] = [0.02, 0.02, 0.02, 0.45];
+
+/**
+    The Cmd+Shift+P command palette: fuzzy filters everything the editor can do (see `EditorCommand::all`) by name, with its shortcut next to it, for when it's easier to find than to remember.
+*/
+pub struct CommandPalette {
+    open: bool,
+    query: String,
+    selected: usize,
+}
+
+impl CommandPalette {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            query: String::new(),
+            selected: 0,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn open(&mut self) {
+        self.open = true;
+        self.query.clear();
+        self.selected = 0;
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+    }
+
+    pub fn type_str(&mut self, s: &str) {
+        self.query.push_str(s);
+        self.selected = 0;
+    }
+
+    pub fn backspace(&mut self) {
+        self.query.pop();
+        self.selected = 0;
+    }
+
+    pub fn move_selection(&mut self, delta: i32) {
+        let n = self.matches().len() as i32;
+        if n > 0 {
+            self.selected = (self.selected as i32 + delta).rem_euclid(n) as usize;
+        }
+    }
+
+    /**
+        The commands that match the query, best match first
+    */
+    fn matches(&self) -> Vec<EditorCommand> {
+        let mut matches = EditorCommand::all()
+            .into_iter()
+            .filter_map(|command| Some((command, fuzzy_match(&self.query, &command.name())?)))
+            .collect::<Vec<_>>();
+
+        // stable, so equally good matches stay in the order they're listed in
+        matches.sort_by_key(|&(_, score)| -score);
+
+        matches
+            .into_iter()
+            .map(|(command, _)| command)
+            .take(MAX_ROWS)
+            .collect()
+    }
+
+    pub fn selected_command(&self) -> Option<EditorCommand> {
+        self.matches().get(self.selected).copied()
+    }
+
+    fn bounds(&self, (width, _): (f32, f32)) -> (f32, f32, f32, f32) {
+        let rows = self.matches().len().max(1);
+        let min_x = ((width - PALETTE_WIDTH) / 2.0).max(0.0);
+
+        (
+            min_x,
+            PALETTE_TOP,
+            min_x + PALETTE_WIDTH,
+            PALETTE_TOP + INPUT_HEIGHT + rows as f32 * ROW_HEIGHT + 6.0,
+        )
+    }
+
+    /**
+        Which command was clicked, if any. (`None` if the click was outside of the palette.)
+    */
+    pub fn hit_test(
+        &self,
+        window_size: (f32, f32),
+        (x, y): (f32, f32),
+    ) -> Option<Option<EditorCommand>> {
+        let (min_x, min_y, max_x, max_y) = self.bounds(window_size);
+        if x < min_x || x > max_x || y < min_y || y > max_y {
+            return None;
+        }
+
+        let i = ((y - min_y - INPUT_HEIGHT) / ROW_HEIGHT).floor();
+        if i < 0.0 {
+            return Some(None);
+        }
+
+        Some(self.matches().get(i as usize).copied())
+    }
+
+    pub fn draw(&self, window_size: (f32, f32), overlay: &mut Overlay) {
+        let (min_x, min_y, max_x, max_y) = self.bounds(window_size);
+        let text_y = |top: f32, height: f32| top + (height - FONT_SIZE) / 2.0;
+
+        overlay.quad((0.0, 0.0, window_size.0, window_size.1), BACKDROP_COLOR);
+        overlay.quad((min_x, min_y, max_x, max_y), PALETTE_COLOR);
+
+        overlay.text(
+            (min_x + 12.0, text_y(min_y, INPUT_HEIGHT)),
+            format!("> {}", self.query),
+            FONT_SIZE,
+            TEXT_COLOR,
+        );
+
+        let matches = self.matches();
+
+        if matches.is_empty() {
+            overlay.text(
+                (min_x + 12.0, text_y(min_y + INPUT_HEIGHT, ROW_HEIGHT)),
+                "no matching commands",
+                FONT_SIZE,
+                DIM_TEXT_COLOR,
+            );
+        }
+
+        for (row, command) in matches.iter().enumerate() {
+            let top = min_y + INPUT_HEIGHT + row as f32 * ROW_HEIGHT;
+            let y = text_y(top, ROW_HEIGHT);
+
+            if row == self.selected {
+                overlay.quad((min_x, top, max_x, top + ROW_HEIGHT), SELECTED_COLOR);
+            }
+
+            overlay.text((min_x + 12.0, y), command.name(), FONT_SIZE, TEXT_COLOR);
+
+            overlay.text(
+                (max_x - 120.0, y),
+                command.shortcut(),
+                FONT_SIZE,
+                DIM_TEXT_COLOR,
+            );
+        }
+    }
+}
@@ -0,0 +1,286 @@
+use crate::{clipboard::REGISTERS, morph::SLOTS};
+
+/**
+    Everything the editor can be told to do, by a shortcut or from the command palette (which is how the ones that are rarely used can do without a chord that has to be remembered)
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditorCommand {
+    Evaluate,
+    FormatDocument,
+    RenameSymbol,
+    ExtractDefinition,
+    GoToSymbol,
+    GoToDefinition,
+    Complete,
+    SearchProject,
+    ToggleBookmark,
+    NextBookmark,
+    PreviousBookmark,
+    JumpBack,
+    JumpForward,
+    Cut,
+    Copy,
+    Paste,
+    SelectAll,
+    SelectWord,
+    AddCaretAbove,
+    AddCaretBelow,
+    Undo,
+    Redo,
+    BrowseHistory,
+    RestoreBackup,
+    ToggleDiff,
+    Commit,
+    SwitchSnapshot,
+    InsertKnob,
+    InsertSlider,
+    InsertPianoRoll,
+    ToggleWatch,
+    ToggleLevels,
+    ToggleMusicalTyping,
+    CycleQuantize,
+    SwingLess,
+    SwingMore,
+    TapTempo,
+    Reseed,
+    Hush,
+    Panic,
+    Bounce,
+    Freeze,
+    RecordSession,
+    ReplaySession,
+    SaveToLibrary,
+    OpenLibrary,
+    ShowPluginEditor,
+    AudioSettings,
+    ToggleSplit,
+    ToggleConsole,
+    ToggleClipView,
+    ZoomIn,
+    ZoomOut,
+    ResetZoom,
+    CopyToRegister(usize),
+    PasteFromRegister(usize),
+    CaptureSnapshot(usize),
+}
+
+impl EditorCommand {
+    /**
+        All of them, in the order the command palette lists them (when it's not filtering)
+    */
+    pub fn all() -> Vec<EditorCommand> {
+        let registers = (1..=REGISTERS).flat_map(|register| {
+            [
+                EditorCommand::CopyToRegister(register),
+                EditorCommand::PasteFromRegister(register),
+            ]
+        });
+
+        let snapshots = (0..SLOTS.len()).map(EditorCommand::CaptureSnapshot);
+
+        Self::FIXED
+            .iter()
+            .copied()
+            .chain(registers)
+            .chain(snapshots)
+            .collect()
+    }
+
+    /// (the ones that don't take a register or snapshot slot)
+    const FIXED: &[EditorCommand] = &[
+        EditorCommand::Evaluate,
+        EditorCommand::FormatDocument,
+        EditorCommand::RenameSymbol,
+        EditorCommand::ExtractDefinition,
+        EditorCommand::GoToSymbol,
+        EditorCommand::GoToDefinition,
+        EditorCommand::Complete,
+        EditorCommand::SearchProject,
+        EditorCommand::ToggleBookmark,
+        EditorCommand::NextBookmark,
+        EditorCommand::PreviousBookmark,
+        EditorCommand::JumpBack,
+        EditorCommand::JumpForward,
+        EditorCommand::Cut,
+        EditorCommand::Copy,
+        EditorCommand::Paste,
+        EditorCommand::SelectAll,
+        EditorCommand::SelectWord,
+        EditorCommand::AddCaretAbove,
+        EditorCommand::AddCaretBelow,
+        EditorCommand::Undo,
+        EditorCommand::Redo,
+        EditorCommand::BrowseHistory,
+        EditorCommand::RestoreBackup,
+        EditorCommand::ToggleDiff,
+        EditorCommand::Commit,
+        EditorCommand::SwitchSnapshot,
+        EditorCommand::InsertKnob,
+        EditorCommand::InsertSlider,
+        EditorCommand::InsertPianoRoll,
+        EditorCommand::ToggleWatch,
+        EditorCommand::ToggleLevels,
+        EditorCommand::ToggleMusicalTyping,
+        EditorCommand::CycleQuantize,
+        EditorCommand::SwingLess,
+        EditorCommand::SwingMore,
+        EditorCommand::TapTempo,
+        EditorCommand::Reseed,
+        EditorCommand::Hush,
+        EditorCommand::Panic,
+        EditorCommand::Bounce,
+        EditorCommand::Freeze,
+        EditorCommand::RecordSession,
+        EditorCommand::ReplaySession,
+        EditorCommand::SaveToLibrary,
+        EditorCommand::OpenLibrary,
+        EditorCommand::ShowPluginEditor,
+        EditorCommand::AudioSettings,
+        EditorCommand::ToggleSplit,
+        EditorCommand::ToggleConsole,
+        EditorCommand::ToggleClipView,
+        EditorCommand::ZoomIn,
+        EditorCommand::ZoomOut,
+        EditorCommand::ResetZoom,
+    ];
+
+    /**
+        What it's called in the command palette
+    */
+    pub fn name(self) -> String {
+        let name = match self {
+            EditorCommand::Evaluate => "evaluate block",
+            EditorCommand::FormatDocument => "format document",
+            EditorCommand::RenameSymbol => "rename symbol",
+            EditorCommand::ExtractDefinition => "extract selection to definition",
+            EditorCommand::GoToSymbol => "go to symbol",
+            EditorCommand::GoToDefinition => "go to definition",
+            EditorCommand::Complete => "complete name",
+            EditorCommand::SearchProject => "search (and replace) in project",
+            EditorCommand::ToggleBookmark => "bookmark (or unbookmark) line",
+            EditorCommand::NextBookmark => "go to next bookmark",
+            EditorCommand::PreviousBookmark => "go to previous bookmark",
+            EditorCommand::JumpBack => "jump back",
+            EditorCommand::JumpForward => "jump forward",
+            EditorCommand::Cut => "cut",
+            EditorCommand::Copy => "copy",
+            EditorCommand::Paste => "paste",
+            EditorCommand::SelectAll => "select all",
+            EditorCommand::SelectWord => "select word, or its next occurrence",
+            EditorCommand::AddCaretAbove => "add caret above",
+            EditorCommand::AddCaretBelow => "add caret below",
+            EditorCommand::Undo => "undo",
+            EditorCommand::Redo => "redo",
+            EditorCommand::BrowseHistory => "browse undo history",
+            EditorCommand::RestoreBackup => "restore a backup",
+            EditorCommand::ToggleDiff => "show changes since the last commit (or backup)",
+            EditorCommand::Commit => "commit",
+            EditorCommand::SwitchSnapshot => "switch snapshot (branch)",
+            EditorCommand::InsertKnob => "insert knob",
+            EditorCommand::InsertSlider => "insert slider",
+            EditorCommand::InsertPianoRoll => "insert piano roll",
+            EditorCommand::ToggleWatch => "watch (or unwatch) name",
+            EditorCommand::ToggleLevels => "toggle levels",
+            EditorCommand::ToggleMusicalTyping => "toggle musical typing",
+            EditorCommand::CycleQuantize => "cycle launch quantization",
+            EditorCommand::SwingLess => "less swing",
+            EditorCommand::SwingMore => "more swing",
+            EditorCommand::TapTempo => "tap tempo",
+            EditorCommand::Reseed => "reseed randomness (`rand`, `choose`, ..)",
+            EditorCommand::Hush => "hush (fade out everything)",
+            EditorCommand::Panic => "panic (stop everything)",
+            EditorCommand::Bounce => "bounce to file",
+            EditorCommand::Freeze => "freeze definition (render it into a sample)",
+            EditorCommand::RecordSession => "record (or stop recording) session",
+            EditorCommand::ReplaySession => "replay a recorded session",
+            EditorCommand::SaveToLibrary => "save definition to library",
+            EditorCommand::OpenLibrary => "insert from library",
+            EditorCommand::ShowPluginEditor => "show plugin editor",
+            EditorCommand::AudioSettings => "audio settings",
+            EditorCommand::ToggleSplit => "split (or unsplit) editor",
+            EditorCommand::ToggleConsole => "toggle log console",
+            EditorCommand::ToggleClipView => "toggle clip launcher",
+            EditorCommand::ZoomIn => "zoom in",
+            EditorCommand::ZoomOut => "zoom out",
+            EditorCommand::ResetZoom => "reset zoom",
+            EditorCommand::CopyToRegister(register) => {
+                return format!("copy to register {}", register)
+            }
+            EditorCommand::PasteFromRegister(register) => {
+                return format!("paste from register {}", register)
+            }
+            EditorCommand::CaptureSnapshot(slot) => {
+                return format!("capture snapshot {}", SLOTS[slot])
+            }
+        };
+
+        name.into()
+    }
+
+    pub fn shortcut(self) -> String {
+        let shortcut = match self {
+            EditorCommand::Evaluate => "Cmd+Enter",
+            EditorCommand::FormatDocument => "Cmd+Shift+F",
+            EditorCommand::RenameSymbol => "Cmd+R",
+            EditorCommand::ExtractDefinition => "Cmd+Shift+X",
+            EditorCommand::GoToSymbol => "Cmd+Shift+O",
+            EditorCommand::GoToDefinition => "F12",
+            EditorCommand::Complete => "Ctrl+Space",
+            EditorCommand::SearchProject => "Cmd+Shift+S",
+            EditorCommand::ToggleBookmark => "Cmd+F2",
+            EditorCommand::NextBookmark => "F2",
+            EditorCommand::PreviousBookmark => "Shift+F2",
+            EditorCommand::JumpBack => "Ctrl+O",
+            EditorCommand::JumpForward => "Ctrl+I",
+            EditorCommand::Cut => "Cmd+X",
+            EditorCommand::Copy => "Cmd+C",
+            EditorCommand::Paste => "Cmd+V",
+            EditorCommand::SelectAll => "Cmd+A",
+            EditorCommand::SelectWord => "Cmd+D",
+            EditorCommand::AddCaretAbove => "Cmd+Alt+↑",
+            EditorCommand::AddCaretBelow => "Cmd+Alt+↓",
+            EditorCommand::Undo => "Cmd+Z",
+            EditorCommand::Redo => "Cmd+Shift+Z",
+            EditorCommand::BrowseHistory => "Cmd+Shift+H",
+            EditorCommand::RestoreBackup => "Cmd+Shift+R",
+            EditorCommand::ToggleDiff => "Cmd+Shift+D",
+            EditorCommand::Commit => "Cmd+Shift+C",
+            EditorCommand::SwitchSnapshot => "Cmd+Shift+G",
+            EditorCommand::InsertKnob => "Cmd+K",
+            EditorCommand::InsertSlider => "Cmd+Shift+K",
+            EditorCommand::InsertPianoRoll => "Cmd+Shift+N",
+            EditorCommand::ToggleWatch => "Cmd+Shift+W",
+            EditorCommand::ToggleLevels => "Cmd+Shift+L",
+            EditorCommand::ToggleMusicalTyping => "Cmd+Shift+M",
+            EditorCommand::CycleQuantize => "Cmd+Shift+B",
+            EditorCommand::SwingLess => "Cmd+Shift+[",
+            EditorCommand::SwingMore => "Cmd+Shift+]",
+            EditorCommand::TapTempo => "Cmd+T",
+            EditorCommand::Reseed => "Cmd+;",
+            EditorCommand::Hush => "Cmd+.",
+            EditorCommand::Panic => "Cmd+Shift+.",
+            EditorCommand::Bounce => "Cmd+Shift+E",
+            EditorCommand::Freeze => "Cmd+Shift+U",
+            EditorCommand::RecordSession => "Cmd+Shift+Y",
+            EditorCommand::ReplaySession => "Cmd+Shift+J",
+            EditorCommand::SaveToLibrary => "Cmd+Shift+A",
+            EditorCommand::OpenLibrary => "Cmd+Shift+T",
+            EditorCommand::ShowPluginEditor => "Cmd+Shift+I",
+            EditorCommand::AudioSettings => "Cmd+,",
+            EditorCommand::ToggleSplit => "Cmd+\\",
+            EditorCommand::ToggleConsole => "Cmd+J",
+            EditorCommand::ToggleClipView => "Cmd+L",
+            EditorCommand::ZoomIn => "Cmd+=",
+            EditorCommand::ZoomOut => "Cmd+-",
+            EditorCommand::ResetZoom => "Cmd+0",
+            // (wherever the digits are, see `register_key`)
+            EditorCommand::CopyToRegister(register) => {
+                return format!("Cmd+Alt+Shift+{}", register)
+            }
+            EditorCommand::PasteFromRegister(register) => return format!("Cmd+Alt+{}", register),
+            EditorCommand::CaptureSnapshot(slot) => return format!("Shift+F{}", 5 + slot),
+        };
+
+        shortcut.into()
+    }
+}
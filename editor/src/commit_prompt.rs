@@ -0,0 +1,96 @@
+use crate::render::Overlay;
+
+const PROMPT_WIDTH: f32 = 440.0;
+const PROMPT_TOP: f32 = 64.0;
+const INPUT_HEIGHT: f32 = 36.0;
+const FONT_SIZE: f32 = 15.0;
+
+const BACKDROP_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.08];
+const PROMPT_COLOR: [f32; 4] = [0.99, 0.99, 0.98, 1.0];
+const TEXT_COLOR: [f32; 4] = [0.02, 0.02, 0.02, 1.0];
+const DIM_TEXT_COLOR: [f32; 4] = [0.02, 0.02, 0.02, 0.45];
+
+/**
+    The Cmd+Shift+C mini prompt for a commit message: Enter commits the document (to the branch that's checked out), Esc doesn't.
+*/
+pub struct CommitPrompt {
+    open: bool,
+    message: String,
+    // (to show where it's going)
+    branch: Option<String>,
+}
+
+impl CommitPrompt {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            message: String::new(),
+            branch: None,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn open(&mut self, branch: Option<String>) {
+        self.open = true;
+        self.message.clear();
+        self.branch = branch;
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+    }
+
+    pub fn type_str(&mut self, s: &str) {
+        self.message.push_str(s);
+    }
+
+    pub fn backspace(&mut self) {
+        self.message.pop();
+    }
+
+    /**
+        The message, unless there's nothing (but whitespace) in it
+    */
+    pub fn message(&self) -> Option<&str> {
+        Some(self.message.trim()).filter(|message| !message.is_empty())
+    }
+
+    fn bounds(&self, (width, _): (f32, f32)) -> (f32, f32, f32, f32) {
+        let min_x = ((width - PROMPT_WIDTH) / 2.0).max(0.0);
+        (
+            min_x,
+            PROMPT_TOP,
+            min_x + PROMPT_WIDTH,
+            PROMPT_TOP + INPUT_HEIGHT,
+        )
+    }
+
+    /**
+        Whether the click was on the prompt (clicking outside of it closes it)
+    */
+    pub fn hit_test(&self, window_size: (f32, f32), (x, y): (f32, f32)) -> bool {
+        let (min_x, min_y, max_x, max_y) = self.bounds(window_size);
+        min_x <= x && x <= max_x && min_y <= y && y <= max_y
+    }
+
+    pub fn draw(&self, window_size: (f32, f32), overlay: &mut Overlay) {
+        let (min_x, min_y, max_x, max_y) = self.bounds(window_size);
+        let y = min_y + (INPUT_HEIGHT - FONT_SIZE) / 2.0;
+
+        overlay.quad((0.0, 0.0, window_size.0, window_size.1), BACKDROP_COLOR);
+        overlay.quad((min_x, min_y, max_x, max_y), PROMPT_COLOR);
+
+        if self.message.is_empty() {
+            let placeholder = match &self.branch {
+                Some(branch) => format!("commit to {}…", branch),
+                None => "commit message…".to_string(),
+            };
+            overlay.text((min_x + 12.0, y), placeholder, FONT_SIZE, DIM_TEXT_COLOR);
+        } else {
+            overlay.text((min_x + 12.0, y), &self.message, FONT_SIZE, TEXT_COLOR);
+        }
+    }
+}
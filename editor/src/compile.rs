@@ -0,0 +1,1080 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use live_engine::{
+    detect_slices, slice, AudioNode, Bounce, BusReturn, BusSend, Compare, Comparison, Dc, Effect,
+    EngineHandle, Gain, Groove, Hit, Humanize, Mix, Modulation, Osc, Placement, Poly, Sampler,
+    Sequencer, Sometimes, Switch, EFFECTS,
+};
+use live_language::{clips, expand_glob, play_targets, resolve_path, seed, Evaluation, Key, Value};
+
+use crate::{
+    audio_cache::decode_mono,
+    widget::{SampleMarkers, WidgetValue},
+};
+
+type Node = Box<dyn AudioNode + Send>;
+
+/// (the oscillators, as what `Osc`'s squareness is for them)
+const OSCILLATORS: &[(&str, f32)] = &[("sin", 0.0), ("square", 0.9)];
+
+/// (the note that a step of a step sequence plays, which is `c4`, for what plays notes)
+const STEP_NOTE: u8 = 60;
+
+/// (how many voices a `poly` plays at once, when it doesn't say)
+const VOICES: usize = 8;
+
+/**
+    Where the compiled nodes are going to play (live, or in a bounce), for what they need from there
+*/
+pub trait Studio {
+    fn bus(&self, name: &str) -> BusReturn;
+
+    fn send(&self, node: Node, name: &str, gain: f32) -> BusSend;
+
+    fn input(&self, channel: usize) -> Result<Node, String>;
+
+    fn record_buffer(&self, channel: usize, duration: Duration) -> Result<Node, String>;
+
+    /// (where `key` is where it's played, so that it's the same one when it's played there again)
+    fn plugin(&self, key: Option<&str>, name: &str, node: Node) -> Result<Node, String>;
+}
+
+impl Studio for EngineHandle {
+    fn bus(&self, name: &str) -> BusReturn {
+        EngineHandle::bus(self, name)
+    }
+
+    fn send(&self, node: Node, name: &str, gain: f32) -> BusSend {
+        EngineHandle::send(self, node, name, gain)
+    }
+
+    fn input(&self, channel: usize) -> Result<Node, String> {
+        Ok(Box::new(EngineHandle::input(self, channel)))
+    }
+
+    fn record_buffer(&self, channel: usize, duration: Duration) -> Result<Node, String> {
+        Ok(Box::new(EngineHandle::record_buffer(
+            self, channel, duration,
+        )))
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn plugin(&self, key: Option<&str>, name: &str, node: Node) -> Result<Node, String> {
+        Ok(Box::new(EngineHandle::plugin(self, key, name, node)?))
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn plugin(&self, _key: Option<&str>, _name: &str, _node: Node) -> Result<Node, String> {
+        Err("plugins don't run in the browser".into())
+    }
+}
+
+// (offline, there's no audio input, and no plugins are loaded)
+impl Studio for Bounce {
+    fn bus(&self, name: &str) -> BusReturn {
+        Bounce::bus(self, name)
+    }
+
+    fn send(&self, node: Node, name: &str, gain: f32) -> BusSend {
+        Bounce::send(self, node, name, gain)
+    }
+
+    fn input(&self, _channel: usize) -> Result<Node, String> {
+        Err("the audio input can't be bounced".into())
+    }
+
+    fn record_buffer(&self, _channel: usize, _duration: Duration) -> Result<Node, String> {
+        Err("the audio input can't be bounced".into())
+    }
+
+    fn plugin(&self, _key: Option<&str>, _name: &str, _node: Node) -> Result<Node, String> {
+        Err("plugins can't be bounced".into())
+    }
+}
+
+/**
+    The audio files that are played, decoded once, by where they are on this machine
+*/
+#[derive(Default)]
+pub struct Samples(HashMap<PathBuf, (Vec<f32>, u32)>);
+
+impl Samples {
+    fn get(&mut self, path: &Path) -> Result<(Vec<f32>, u32), String> {
+        if !self.0.contains_key(path) {
+            let decoded =
+                decode_mono(path).map_err(|e| format!("can't read {}: {}", path.display(), e))?;
+            self.0.insert(path.to_path_buf(), decoded);
+        }
+
+        Ok(self.0[path].clone())
+    }
+
+    /// (when it changed on disk)
+    pub fn forget(&mut self, path: &Path) {
+        self.0.remove(path);
+    }
+}
+
+/**
+    What's played as one target of the engine: a `play` statement, or a definition that's a clip
+*/
+pub struct Target {
+    /// What's played, like `beat` for `play beat`, or otherwise where, like `program.play[1]`
+    pub name: String,
+    pub node: Node,
+    pub placement: Placement,
+}
+
+/**
+    Turns what the code evaluated to into the engine's nodes. Names that the code leaves for the engine (see `live_language::evaluate`) are built here: the oscillators, the built-in effects, the buses, the samples and the widgets.
+
+    A setting that's a number can be changed while it's playing, by the name of where it is, like `fx.f` for the `f` of `def fx = lowpass{f = 800hz}` (wherever `fx` is played), which is what `latches` and the widgets set.
+*/
+pub struct Compiler<'a, S: Studio + ?Sized> {
+    studio: &'a S,
+    root: &'a Path,
+    /// (by how they're referred to in the code, like `sample#3`)
+    widgets: &'a HashMap<String, WidgetValue>,
+    samples: &'a mut Samples,
+    definitions: Vec<(Key, Value)>,
+}
+
+impl<'a, S: Studio + ?Sized> Compiler<'a, S> {
+    pub fn new(
+        studio: &'a S,
+        root: &'a Path,
+        widgets: &'a HashMap<String, WidgetValue>,
+        samples: &'a mut Samples,
+    ) -> Self {
+        Self {
+            studio,
+            root,
+            widgets,
+            samples,
+            definitions: vec![],
+        }
+    }
+
+    /**
+        Everything the document plays: its `play` statements, and the definitions in its `clip(..)`s that aren't played already (which the engine only lets through while they're launched). What can't be compiled is left out, with why, by the name of its target.
+    */
+    pub fn compile(
+        &mut self,
+        evaluation: &Evaluation,
+        source: &str,
+    ) -> (Vec<Target>, Vec<(String, String)>) {
+        self.definitions = definitions(evaluation);
+
+        let named = play_targets(source);
+        let (mut targets, mut errors) = (vec![], vec![]);
+
+        for (key, value) in &evaluation.values {
+            let Some(i) = play_index(key) else {
+                continue;
+            };
+            let name = named
+                .iter()
+                .find(|target| target.index == i)
+                .map_or_else(|| key.to_string(), |target| target.name.clone());
+
+            match self.target(name.clone(), key, value) {
+                Ok(target) => targets.push(target),
+                Err(message) => errors.push((name, message)),
+            }
+        }
+
+        for clip in clips(source) {
+            if targets.iter().any(|target| target.name == clip.target) {
+                continue;
+            }
+
+            let key = Key::new(&clip.target);
+            let compiled = match evaluation.values.iter().find(|(k, _)| *k == key) {
+                Some((_, value)) => self.target(clip.target.clone(), &key, value),
+                None => Err(format!("`{}` isn't defined", clip.target)),
+            };
+
+            match compiled {
+                Ok(target) => targets.push(target),
+                Err(message) => errors.push((clip.target, message)),
+            }
+        }
+
+        (targets, errors)
+    }
+
+    /**
+        Just one value, as a target of its own by its key (like what a timer's callback returned, see `Timer::fire`), where what it refers to is defined like in the evaluation
+    */
+    pub fn compile_value(
+        &mut self,
+        evaluation: &Evaluation,
+        key: &Key,
+        value: &Value,
+    ) -> Result<Target, String> {
+        self.definitions = definitions(evaluation);
+        self.target(key.to_string(), key, value)
+    }
+
+    /// (`pan` and `channel` place the target as a whole, so they're only ever the outermost thing, see `live_language::evaluate`)
+    fn target(&mut self, name: String, key: &Key, value: &Value) -> Result<Target, String> {
+        let (signal, placement) = match value {
+            Value::Node(op, args) if op == "pan" => match args.as_slice() {
+                [(None, signal), (None, Value::Num(position))] => {
+                    (signal, Placement::Pan(position.value as f32))
+                }
+                [(None, signal), (None, Value::Array(gains))] => (
+                    signal,
+                    Placement::Gains(
+                        gains
+                            .iter()
+                            .map(|(_, gain)| match gain {
+                                Value::Num(gain) => Ok(gain.value as f32),
+                                _ => Err("`pan` needs a gain per channel".to_string()),
+                            })
+                            .collect::<Result<_, _>>()?,
+                    ),
+                ),
+                _ => return Err("`pan` needs a signal and where to place it".into()),
+            },
+            Value::Node(op, args) if op == "channel" => match args.as_slice() {
+                [(None, signal), (None, Value::Num(channel))] => {
+                    (signal, Placement::Channel(channel.value as usize))
+                }
+                _ => return Err("`channel` needs a signal and a channel".into()),
+            },
+            value => (value, Placement::Everywhere),
+        };
+
+        Ok(Target {
+            name,
+            node: self.signal(signal, Some(key))?,
+            placement,
+        })
+    }
+
+    /**
+        A value as a node, where `key` is where it is (if it has one of its own, see `names`)
+    */
+    fn signal(&mut self, value: &Value, key: Option<&Key>) -> Result<Node, String> {
+        let (op, args) = match value {
+            Value::Num(quantity) => return Ok(Box::new(Dc::new(quantity.gain() as f32))),
+            Value::Bool(b) => return Ok(Box::new(Dc::new(if *b { 1.0 } else { 0.0 }))),
+            Value::Node(op, args) => (op.as_str(), args.as_slice()),
+            Value::Array(_) => {
+                return Err("can't play an array (but `xs.sum()` mixes its signals)".into())
+            }
+            Value::Str(_) => return Err("can't play a string".into()),
+            Value::Color(_) => return Err("can't play a color".into()),
+            Value::Tuple(_) => return Err("can't play a tuple".into()),
+            Value::Fn(_) => return Err("can't play a function (but what it returns)".into()),
+        };
+
+        if is_control(value) || matches!(op, "ease" | "perform") {
+            return Ok(Box::new(Dc::new(self.modulation(value, key)?)));
+        }
+
+        if let Some(widget) = self.widgets.get(op) {
+            return self.widget(op, widget.clone(), args, key);
+        }
+
+        if let Some(&(_, squareness)) = OSCILLATORS.iter().find(|(name, _)| *name == op) {
+            return self.oscillator(op, squareness, args, key);
+        }
+
+        if EFFECTS.iter().any(|(name, _)| *name == op) {
+            return self.effect(op, args, key);
+        }
+
+        match (op, args) {
+            ("+", [(None, a), (None, b)]) => Ok(Box::new(
+                Mix::default()
+                    .add(self.signal(a, None)?)
+                    .add(self.signal(b, None)?),
+            )),
+            ("-", [(None, a), (None, b)]) => Ok(Box::new(
+                Mix::default()
+                    .add(self.signal(a, None)?)
+                    .add(Box::new(Gain::new(self.signal(b, None)?, -1.0))),
+            )),
+            // (the number, or the control, is what the other one's scaled by)
+            ("*", [(None, a), (None, b)]) if self.is_modulation(a) => Ok(Box::new(Gain::new(
+                self.signal(b, None)?,
+                self.modulation(a, None)?,
+            ))),
+            ("*", [(None, a), (None, b)]) => Ok(Box::new(Gain::new(
+                self.signal(a, None)?,
+                self.modulation(b, None)?,
+            ))),
+            ("/", [(None, a), (None, Value::Num(b))]) if b.value != 0.0 => Ok(Box::new(Gain::new(
+                self.signal(a, None)?,
+                (1.0 / b.gain()) as f32,
+            ))),
+            ("/", _) => Err("can only divide a signal by a number".into()),
+            ("if", [(None, condition), (None, then), (None, otherwise)]) => {
+                Ok(Box::new(Switch::new(
+                    self.modulation(condition, None)?,
+                    self.signal(then, None)?,
+                    self.signal(otherwise, None)?,
+                )))
+            }
+            // (1 while it's true, and 0 otherwise)
+            ("==" | "!=" | "<" | "<=" | ">" | ">=" | "&&" | "||", [(None, a), (None, b)]) => {
+                Ok(Box::new(Compare::new(
+                    Comparison::from_symbol(op).unwrap(),
+                    self.modulation(a, None)?,
+                    self.modulation(b, None)?,
+                )))
+            }
+            ("!", [(None, a)]) => Ok(Box::new(Compare::new(
+                Comparison::Not,
+                self.modulation(a, None)?,
+                0.0,
+            ))),
+            ("bus", [(None, Value::Str(bus))]) => Ok(Box::new(self.studio.bus(bus))),
+            ("send", [(None, signal), (None, Value::Str(bus)), rest @ ..]) => {
+                let gain = match rest {
+                    [] => 1.0,
+                    [(None, Value::Num(gain))] => gain.gain() as f32,
+                    _ => return Err("`send` needs a gain that's a number, like `-6db`".into()),
+                };
+                let signal = self.signal(signal, None)?;
+                Ok(Box::new(self.studio.send(signal, bus, gain)))
+            }
+            ("input", [(None, Value::Num(channel)), settings @ ..]) => {
+                let mut input = self.studio.input(channel.value as usize)?;
+                self.configure(&mut *input, op, settings, key)?;
+                Ok(input)
+            }
+            ("record_buffer", [(None, Value::Num(duration)), settings @ ..]) => {
+                let mut recorder = self
+                    .studio
+                    .record_buffer(1, Duration::from_secs_f64(duration.value.max(0.0)))?;
+                self.configure(&mut *recorder, op, settings, key)?;
+                Ok(recorder)
+            }
+            ("plugin", args) => {
+                let (settings, unnamed) = split(args);
+                let [Value::Str(name), signal] = unnamed[..] else {
+                    return Err("`plugin` needs the plugin's name, and a signal".into());
+                };
+                let signal = self.signal(signal, None)?;
+                let played = self.names(op, args, key).into_iter().next();
+                let mut plugin = self.studio.plugin(played.as_deref(), name, signal)?;
+                self.configure(&mut *plugin, op, &settings, key)?;
+                Ok(plugin)
+            }
+            ("poly", args) => self.poly(args, key),
+            ("swing" | "humanize" | "sometimes", _) => {
+                Ok(Box::new(self.pattern(value, Groove::default())?))
+            }
+            ("path", [(None, Value::Str(path)), settings @ ..]) => {
+                let path = resolve_path(self.root, path);
+                self.sampler(&path, None, None, op, settings, key)
+            }
+            ("[]", [(None, of), (None, Value::Num(i)), settings @ ..])
+                if i.value >= 0.0 && i.value.fract() == 0.0 =>
+            {
+                self.element(of, i.value as usize, settings, key)
+            }
+            ("[]", _) => Err("can only pick a sample by a whole number, like `drums[3]`".into()),
+            ("slices", _) => Err("`slices` is an array of samples, like `drums[3]`".into()),
+            ("samples", _) => Err("`samples` is an array of samples, like `kicks[0]`".into()),
+            ("pan" | "channel", _) => Err(format!(
+                "`{}` places what's played as a whole, so it has to be the outermost thing",
+                op
+            )),
+            _ if args.is_empty() && op.contains('#') => {
+                Err(format!("`{}` isn't in the code anymore", op))
+            }
+            _ => Err(format!("there's no `{}` to play", op)),
+        }
+    }
+
+    /**
+        One of an array of samples: `slices(loop)[i]`, or `samples(pattern)[i]`
+    */
+    fn element(
+        &mut self,
+        of: &Value,
+        i: usize,
+        settings: &[(Option<String>, Value)],
+        key: Option<&Key>,
+    ) -> Result<Node, String> {
+        match of {
+            Value::Node(op, args) if op == "samples" => {
+                let [(None, Value::Str(pattern))] = args.as_slice() else {
+                    return Err("`samples` needs a pattern, like `samples(\"kicks/*.wav\")`".into());
+                };
+                let paths = expand_glob(self.root, pattern);
+                let path = paths.get(i).ok_or_else(|| {
+                    format!(
+                        "there's no sample {} of the {} in {:?}",
+                        i,
+                        paths.len(),
+                        pattern
+                    )
+                })?;
+                self.sampler(path, None, None, "[]", settings, key)
+            }
+            Value::Node(op, args) if op == "slices" => {
+                let (path, starts) = match args.as_slice() {
+                    [(None, Value::Node(widget, _))] if self.widgets.contains_key(widget) => {
+                        match &self.widgets[widget] {
+                            WidgetValue::Slices(path, starts) => {
+                                (path.clone(), Some(starts.clone()))
+                            }
+                            WidgetValue::Sample(path, _) => (path.clone(), None),
+                            _ => return Err("`slices` needs a sample".into()),
+                        }
+                    }
+                    [(None, Value::Node(path, args))] if path == "path" => match args.as_slice() {
+                        [(None, Value::Str(path))] => (resolve_path(self.root, path), None),
+                        _ => return Err("`slices` needs a sample".into()),
+                    },
+                    _ => return Err("`slices` needs a sample".into()),
+                };
+                self.sampler(&path, None, Some((starts, i)), "[]", settings, key)
+            }
+            _ => Err("can only pick from `slices(..)` or `samples(..)`, like `drums[3]`".into()),
+        }
+    }
+
+    fn widget(
+        &mut self,
+        reference: &str,
+        widget: WidgetValue,
+        settings: &[(Option<String>, Value)],
+        key: Option<&Key>,
+    ) -> Result<Node, String> {
+        match widget {
+            WidgetValue::Number(value) => Ok(Box::new(Dc::new(value))),
+            WidgetValue::Sample(path, markers) => {
+                self.sampler(&path, Some(&markers), None, reference, settings, key)
+            }
+            // (as a whole, when it's not cut up)
+            WidgetValue::Slices(path, _) => {
+                self.sampler(&path, None, None, reference, settings, key)
+            }
+            WidgetValue::Pattern(_) | WidgetValue::Notes(_) => Ok(Box::new(self.sequence(
+                reference,
+                widget,
+                settings,
+                Groove::default(),
+            )?)),
+        }
+    }
+
+    /**
+        A pattern that's placed in a groove of its own, like `matrix#0(kick).swing(.56).humanize(10ms, .1)` (where what's applied last is what counts), or that sometimes plays a step as another pattern does
+    */
+    fn pattern(&mut self, value: &Value, mut groove: Groove) -> Result<Sequencer, String> {
+        let not_a_pattern = || {
+            "can only swing, humanize or sometimes play a pattern, like `matrix#0(kick).swing(.56)`"
+                .to_string()
+        };
+        let Value::Node(op, args) = value else {
+            return Err(not_a_pattern());
+        };
+
+        match (op.as_str(), args.as_slice()) {
+            ("swing", [(None, pattern), (None, Value::Num(amount))]) => {
+                groove.swing.get_or_insert(amount.value);
+                self.pattern(pattern, groove)
+            }
+            // (randomly, but the same for the same code)
+            (
+                "humanize",
+                [(None, pattern), (None, Value::Num(timing)), (None, Value::Num(velocity))],
+            ) => {
+                groove.humanize.get_or_insert(Humanize {
+                    timing: timing.value,
+                    velocity: velocity.value as f32,
+                    seed: seed(0, &[&value.to_string()]),
+                });
+                self.pattern(pattern, groove)
+            }
+            // (see `live_language::evaluate`, for where the other pattern and the seed come from)
+            (
+                "sometimes",
+                [(None, pattern), (None, Value::Num(probability)), (None, other), (None, Value::Num(seed))],
+            ) => {
+                let sometimes = Sometimes {
+                    probability: probability.value,
+                    seed: seed.value as u64,
+                };
+                Ok(self
+                    .pattern(pattern, groove)?
+                    .sometimes(self.pattern(other, groove)?, sometimes))
+            }
+            _ => match self.widgets.get(op) {
+                Some(widget @ (WidgetValue::Pattern(_) | WidgetValue::Notes(_))) => {
+                    self.sequence(op, widget.clone(), args, groove)
+                }
+                _ => Err(not_a_pattern()),
+            },
+        }
+    }
+
+    /**
+        A step sequence, or the notes of a piano roll, as a pattern that plays (in a groove) on the signals it's applied to
+    */
+    fn sequence(
+        &mut self,
+        reference: &str,
+        widget: WidgetValue,
+        settings: &[(Option<String>, Value)],
+        groove: Groove,
+    ) -> Result<Sequencer, String> {
+        let signals = self.played_by(reference, settings)?;
+
+        match widget {
+            // (every lane plays the signal it's for, or every lane the one signal there is)
+            WidgetValue::Pattern(pattern) => {
+                let lanes = signals.len() > 1;
+
+                let hits = (pattern.lanes.iter().enumerate())
+                    .flat_map(|(lane, cells)| {
+                        cells.iter().enumerate().filter_map(move |(step, cell)| {
+                            cell.map(|velocity| Hit {
+                                start: step as f64,
+                                length: 1.0,
+                                note: STEP_NOTE,
+                                velocity,
+                                target: lanes.then_some(lane),
+                            })
+                        })
+                    })
+                    .collect();
+
+                Ok(Sequencer::new(pattern.steps, hits, signals).groove(groove))
+            }
+            // (every note plays on every signal)
+            WidgetValue::Notes(notes) => {
+                let hits = (notes.notes.iter())
+                    .map(|note| Hit {
+                        start: note.start as f64,
+                        length: note.length as f64,
+                        note: note.pitch,
+                        velocity: note.velocity,
+                        target: None,
+                    })
+                    .collect();
+
+                Ok(Sequencer::new(notes.steps, hits, signals).groove(groove))
+            }
+            _ => Err(format!("`{}` isn't a pattern", reference)),
+        }
+    }
+
+    /**
+        The signals that a pattern is applied to, like the `kick, snare` of `matrix#2(kick, snare)`, which is what it plays
+    */
+    fn played_by(
+        &mut self,
+        reference: &str,
+        args: &[(Option<String>, Value)],
+    ) -> Result<Vec<Node>, String> {
+        let (settings, signals) = split(args);
+        if !settings.is_empty() || signals.is_empty() {
+            return Err(format!(
+                "`{}` is a pattern, which plays the signals it's applied to, like `{}(kick, snare)`",
+                reference, reference
+            ));
+        }
+
+        signals
+            .into_iter()
+            .map(|signal| self.signal(signal, None))
+            .collect()
+    }
+
+    /**
+        A synth that plays every note on a voice of its own, like `poly{voices = 4}(|| sin(midi.freq) * midi.gate)`, where the voices are compiled up front (twice as many, so that the stolen ones fade out), since nothing can be compiled while it's playing
+    */
+    fn poly(
+        &mut self,
+        args: &[(Option<String>, Value)],
+        key: Option<&Key>,
+    ) -> Result<Node, String> {
+        let (settings, unnamed) = split(args);
+        let [synth] = unnamed[..] else {
+            return Err(
+                "`poly` needs what a voice plays, like `poly(|| sin(midi.freq) * midi.gate)`"
+                    .into(),
+            );
+        };
+
+        let voices = match settings
+            .iter()
+            .find(|(name, _)| name.as_deref() == Some("voices"))
+        {
+            None => VOICES,
+            Some((_, Value::Num(voices))) if voices.value >= 1.0 => voices.value as usize,
+            Some(_) => {
+                return Err("`poly` plays a number of voices, like `poly{voices = 4}`".into())
+            }
+        };
+
+        let nodes = (0..voices * 2)
+            .map(|_| self.signal(synth, None))
+            .collect::<Result<_, _>>()?;
+        let mut poly = Poly::pooled(nodes).max_voices(voices);
+        self.configure(&mut poly, "poly", &settings, key)?;
+        Ok(Box::new(poly))
+    }
+
+    /**
+        A sample, maybe with the loop and the cues that are set on its widget, or one of its slices (where they start, or otherwise where its hits are)
+    */
+    fn sampler(
+        &mut self,
+        path: &Path,
+        markers: Option<&SampleMarkers>,
+        slice_of: Option<(Option<Vec<f32>>, usize)>,
+        op: &str,
+        settings: &[(Option<String>, Value)],
+        key: Option<&Key>,
+    ) -> Result<Node, String> {
+        let (mut samples, sample_rate) = self.samples.get(path)?;
+
+        if let Some((starts, i)) = slice_of {
+            let starts = starts.unwrap_or_else(|| detect_slices(&samples, sample_rate));
+            samples = slice(&samples, &starts, i).ok_or_else(|| {
+                format!("there's no slice {} of the {} there are", i, starts.len())
+            })?;
+        }
+
+        let mut sampler = Sampler::new(samples, sample_rate);
+        if let Some(markers) = markers {
+            if let Some((start, end)) = markers.loop_region {
+                sampler.apply("loop", 1.0);
+                sampler.apply("loop_start", start);
+                sampler.apply("loop_end", end);
+            }
+            sampler.set_cues(markers.cues.clone());
+        }
+
+        self.configure(&mut sampler, op, settings, key)?;
+        Ok(Box::new(sampler))
+    }
+
+    /// (its frequency can be a number, or follow a control, like `sin(midi.freq)`)
+    fn oscillator(
+        &mut self,
+        op: &str,
+        squareness: f32,
+        args: &[(Option<String>, Value)],
+        key: Option<&Key>,
+    ) -> Result<Node, String> {
+        let (settings, unnamed) = split(args);
+        let [frequency] = unnamed[..] else {
+            return Err(format!("`{}` needs a frequency, like `{}(440hz)`", op, op));
+        };
+
+        let mut osc = Osc::default();
+        osc.apply("volume", 1.0);
+        osc.apply("squareness", squareness);
+        match self.modulation(frequency, None)? {
+            Modulation::Constant(frequency) => osc.apply("frequency", frequency),
+            Modulation::Control(name, _) => osc.map(name, "frequency".into()),
+            Modulation::Signal(_) => {
+                return Err(format!(
+                    "`{}` follows a number or a control (like `midi.freq`), not another signal",
+                    op
+                ));
+            }
+        }
+
+        self.configure(&mut osc, op, &settings, key)?;
+        Ok(Box::new(osc))
+    }
+
+    /**
+        A built-in effect, like `lowpass{f = 800hz}(pad)`, where the settings that aren't named go in order, and the signals are what it processes (and for a compressor, what it listens to, like `compressor(pad, bus("kick"))`)
+    */
+    fn effect(
+        &mut self,
+        op: &str,
+        args: &[(Option<String>, Value)],
+        key: Option<&Key>,
+    ) -> Result<Node, String> {
+        let &(_, params) = EFFECTS.iter().find(|(name, _)| *name == op).unwrap();
+
+        let (mut settings, unnamed) = split(args);
+        let (in_order, signals): (Vec<_>, Vec<_>) =
+            unnamed.into_iter().partition(|arg| self.is_modulation(arg));
+        for (&(param, _), value) in params.iter().zip(in_order) {
+            settings.push((Some(param.to_string()), value.clone()));
+        }
+
+        let mut signals = signals.into_iter();
+        let Some(input) = signals.next() else {
+            return Err(format!(
+                "`{}` needs a signal to process, like `{}(pad)`",
+                op, op
+            ));
+        };
+        let mut effect = Effect::new(op, self.signal(input, None)?).unwrap();
+        if let Some(sidechain) = signals.next() {
+            effect = effect.with_sidechain(self.signal(sidechain, None)?);
+        }
+
+        let names = self.names(op, args, key);
+        for (setting, value) in &settings {
+            let Some(setting) = setting else {
+                continue;
+            };
+            if !params.iter().any(|(param, _)| param == setting) {
+                return Err(format!("`{}` has no setting `{}`", op, setting));
+            }
+
+            let key = key.map(|key| key.member(setting));
+            effect = effect.with(setting, self.modulation(value, key.as_ref())?);
+            for name in &names {
+                effect.map(format!("{}.{}", name, setting), setting.clone());
+            }
+        }
+
+        Ok(Box::new(effect))
+    }
+
+    /**
+        Sets the (named) settings of a node that has plain parameters (like a sampler's `rate`), which can be numbers or follow controls, but not other signals
+    */
+    fn configure(
+        &mut self,
+        node: &mut (dyn AudioNode + Send),
+        op: &str,
+        settings: &[(Option<String>, Value)],
+        key: Option<&Key>,
+    ) -> Result<(), String> {
+        let names = self.names(op, settings, key);
+
+        for (setting, value) in settings {
+            let Some(setting) = setting else {
+                return Err(format!("`{}` only has named settings, like `rate = 2`", op));
+            };
+
+            match self.modulation(value, None)? {
+                Modulation::Constant(value) => node.apply(setting, value),
+                Modulation::Control(control, _) => node.map(control, setting.clone()),
+                Modulation::Signal(_) => {
+                    return Err(format!("`{}` can't follow another signal", setting));
+                }
+            }
+            for name in &names {
+                node.map(format!("{}.{}", name, setting), setting.clone());
+            }
+        }
+
+        Ok(())
+    }
+
+    /**
+        What a setting is set to: a number (in the setting's own unit), a control, like `midi.velocity` (or a knob, which sets it by the name of where it is), or another signal
+    */
+    fn modulation(&mut self, value: &Value, key: Option<&Key>) -> Result<Modulation, String> {
+        match value {
+            Value::Num(quantity) => Ok(Modulation::Constant(quantity.value as f32)),
+            Value::Bool(b) => Ok(Modulation::Constant(if *b { 1.0 } else { 0.0 })),
+            // (it's just what it eases to, or performs, see `latches`)
+            Value::Node(op, args) if op == "ease" || op == "perform" => match args.first() {
+                Some((None, value)) => self.modulation(value, key),
+                _ => Err(format!("`{}` needs a number", op)),
+            },
+            Value::Node(op, _) if is_control(value) => Ok(Modulation::control(op, 0.0)),
+            value => match self.knob(value) {
+                Some(value) => Ok(Modulation::Constant(value)),
+                None => Ok(Modulation::Signal(self.signal(value, key)?)),
+            },
+        }
+    }
+
+    /// (what a widget that's a number is, like a knob)
+    fn knob(&self, value: &Value) -> Option<f32> {
+        match value {
+            Value::Node(op, args) if args.is_empty() => match self.widgets.get(op)? {
+                WidgetValue::Number(value) => Some(*value),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// (what can set a setting without being a signal of its own, see `modulation`)
+    fn is_modulation(&self, value: &Value) -> bool {
+        match value {
+            Value::Num(_) | Value::Bool(_) => true,
+            Value::Node(op, _) => {
+                is_control(value) || op == "ease" || op == "perform" || self.knob(value).is_some()
+            }
+            _ => false,
+        }
+    }
+
+    /**
+        The names a node's settings are set by: where it is (like `program.play[0]`), and the definitions it's made from, like `fx` for `fx(pad)` when `def fx = lowpass{f = 800hz}`
+    */
+    fn names(&self, op: &str, args: &[(Option<String>, Value)], key: Option<&Key>) -> Vec<String> {
+        let defined = self
+            .definitions
+            .iter()
+            .filter_map(|(name, value)| match value {
+                Value::Node(defined, config) if defined == op && args.starts_with(config) => {
+                    Some(name.to_string())
+                }
+                _ => None,
+            });
+
+        key.map(Key::to_string).into_iter().chain(defined).collect()
+    }
+}
+
+/// (what's bound to a name, so not what's played)
+fn definitions(evaluation: &Evaluation) -> Vec<(Key, Value)> {
+    evaluation
+        .values
+        .iter()
+        .filter(|(key, _)| !key.to_string().starts_with("program"))
+        .cloned()
+        .collect()
+}
+
+/// Which play statement it's the value of, for `program.play[i]`
+fn play_index(key: &Key) -> Option<usize> {
+    key.to_string()
+        .strip_prefix("program.play[")?
+        .strip_suffix(']')?
+        .parse()
+        .ok()
+}
+
+/// A value that's set from the outside, by its dotted name, like `midi.freq`
+fn is_control(value: &Value) -> bool {
+    matches!(value, Value::Node(op, args) if args.is_empty() && op.contains('.'))
+}
+
+/// A node's named settings, and its other arguments
+fn split(args: &[(Option<String>, Value)]) -> (Vec<(Option<String>, Value)>, Vec<&Value>) {
+    let named = args.iter().filter(|(name, _)| name.is_some()).cloned();
+    let unnamed = args
+        .iter()
+        .filter(|(name, _)| name.is_none())
+        .map(|(_, value)| value);
+
+    (named.collect(), unnamed.collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use live_engine::{MidiEvent, SAMPLE_RATE};
+    use live_language::evaluate_source;
+
+    use super::*;
+    use crate::pattern::{NotePattern, Pattern};
+
+    /// (where every sample is a short burst, of 10 samples at 1)
+    fn compile(source: &str, widgets: &[(&str, WidgetValue)]) -> Result<Node, String> {
+        let bounce = Bounce::new(1.0, 120.0);
+        let widgets = widgets
+            .iter()
+            .map(|(reference, value)| (reference.to_string(), value.clone()))
+            .collect();
+        let mut samples = Samples::default();
+        samples.0.insert(
+            PathBuf::from("/project/kick.wav"),
+            (vec![1.0; 10], SAMPLE_RATE),
+        );
+
+        let evaluation = evaluate_source(source);
+        assert_eq!(evaluation.errors, vec![]);
+        let (mut targets, mut errors) =
+            Compiler::new(&bounce, Path::new("/project"), &widgets, &mut samples)
+                .compile(&evaluation, source);
+
+        match errors.pop() {
+            Some((_, message)) => Err(message),
+            None => Ok(targets.remove(0).node),
+        }
+    }
+
+    fn render(node: &mut Node, n: usize) -> Vec<f32> {
+        (0..n)
+            .map(|_| {
+                node.tick();
+                node.get_next_sample()
+            })
+            .collect()
+    }
+
+    /// (where it's not silent, as (sample, level) at the start of every burst, to the 10 samples, since the beats add up to a little more or less than where they land exactly)
+    fn hits(samples: &[f32]) -> Vec<(usize, f32)> {
+        (0..samples.len())
+            .filter(|&i| samples[i] != 0.0 && (i == 0 || samples[i - 1] == 0.0))
+            .map(|i| ((i + 5) / 10 * 10, samples[i]))
+            .collect()
+    }
+
+    #[test]
+    fn test_step_sequence() {
+        // (at 120bpm, a bar of 16 steps is 88200 samples)
+        let mut pattern = Pattern::new(2, 16);
+        pattern.set(0, 0, Some(1.0));
+        pattern.set(1, 8, Some(0.5));
+        let widgets = [("matrix#0", WidgetValue::Pattern(pattern))];
+
+        let mut node = compile("play matrix#0(path(\"kick.wav\"));", &widgets).unwrap();
+        assert_eq!(
+            hits(&render(&mut node, 88000)),
+            vec![(0, 1.0), (44100, 0.5)]
+        );
+
+        // (with a signal per lane, every lane plays its own)
+        let mut node = compile(
+            "play matrix#0(path(\"kick.wav\"), path(\"kick.wav\") * 2);",
+            &widgets,
+        )
+        .unwrap();
+        assert_eq!(
+            hits(&render(&mut node, 88000)),
+            vec![(0, 1.0), (44100, 1.0)]
+        );
+
+        assert_eq!(
+            compile("play matrix#0;", &widgets).err(),
+            Some("`matrix#0` is a pattern, which plays the signals it's applied to, like `matrix#0(kick, snare)`".into())
+        );
+    }
+
+    #[test]
+    fn test_notes() {
+        let notes = NotePattern::parse("[c4@0:2 e4@4:1*0.5]").unwrap();
+        let widgets = [("piano_roll#0", WidgetValue::Notes(notes))];
+
+        let mut node = compile("play piano_roll#0(path(\"kick.wav\"));", &widgets).unwrap();
+        assert_eq!(
+            hits(&render(&mut node, 88000)),
+            vec![(0, 1.0), (22050, 0.5)]
+        );
+
+        // (and what doesn't play notes itself follows them as `midi.*`)
+        let mut node = compile("play piano_roll#0(sin(midi.freq));", &widgets).unwrap();
+        let crossings = |samples: Vec<f32>| {
+            samples
+                .windows(2)
+                .filter(|w| w[0] < 0.0 && w[1] >= 0.0)
+                .count()
+        };
+        assert!(crossings(render(&mut node, 4410)).abs_diff(26) <= 1);
+        render(&mut node, 22050 - 4410);
+        assert!(crossings(render(&mut node, 4410)).abs_diff(33) <= 1);
+    }
+
+    #[test]
+    fn test_poly() {
+        // (two notes at once, on a voice each, are louder than one sine)
+        let peak = |samples: Vec<f32>| samples.iter().fold(0.0f32, |peak, x| peak.max(x.abs()));
+
+        let mut node =
+            compile("play poly{voices = 2}(|| sin(midi.freq) * midi.gate);", &[]).unwrap();
+        for note in [57, 64] {
+            node.note(MidiEvent::NoteOn {
+                note,
+                velocity: 1.0,
+            });
+        }
+        assert!(peak(render(&mut node, 4410)) > 1.2);
+
+        // (and as a pattern plays them)
+        let notes = NotePattern::parse("[c4@0:8 e4@0:8]").unwrap();
+        let widgets = [("piano_roll#0", WidgetValue::Notes(notes))];
+        let mut node = compile("play piano_roll#0(poly(|| sin(midi.freq)));", &widgets).unwrap();
+        assert!(peak(render(&mut node, 4410)) > 1.2);
+
+        let mut node = compile("play piano_roll#0(sin(midi.freq));", &widgets).unwrap();
+        assert!(peak(render(&mut node, 4410)) <= 1.0);
+
+        assert_eq!(
+            compile("play poly{voices = 0}(|| sin(midi.freq));", &[]).err(),
+            Some("`poly` plays a number of voices, like `poly{voices = 4}`".into())
+        );
+    }
+
+    #[test]
+    fn test_groove() {
+        // (two 16ths, where the second one is swung to 3/4 of the pair, so half a 16th late)
+        let mut pattern = Pattern::new(1, 16);
+        pattern.set(0, 0, Some(1.0));
+        pattern.set(0, 1, Some(1.0));
+        let widgets = [("matrix#0", WidgetValue::Pattern(pattern))];
+
+        let mut node = compile("play matrix#0(path(\"kick.wav\")).swing(.75);", &widgets).unwrap();
+        assert_eq!(hits(&render(&mut node, 88000)), vec![(0, 1.0), (8270, 1.0)]);
+
+        // (humanized, a bit early or late, by up to 10ms, and softer or harder, but the same every time)
+        let source = "play matrix#0(path(\"kick.wav\")).humanize(10ms, .5);";
+        let humanized = hits(&render(&mut compile(source, &widgets).unwrap(), 88000));
+        assert_eq!(humanized.len(), 2);
+        assert!(humanized[0].0 <= 450 && humanized[1].0.abs_diff(5510) <= 450);
+        assert!(humanized.iter().any(|&(_, velocity)| velocity != 1.0));
+        assert_eq!(
+            hits(&render(&mut compile(source, &widgets).unwrap(), 88000)),
+            humanized
+        );
+
+        assert_eq!(
+            compile("play path(\"kick.wav\").swing(.75);", &widgets).err(),
+            Some("can only swing, humanize or sometimes play a pattern, like `matrix#0(kick).swing(.56)`".into())
+        );
+    }
+
+    #[test]
+    fn test_sometimes() {
+        let mut pattern = Pattern::new(1, 16);
+        for step in 0..16 {
+            pattern.set(0, step, Some(1.0));
+        }
+        let widgets = [("matrix#0", WidgetValue::Pattern(pattern))];
+
+        // (some of the steps play as the other pattern does, softer, but the same ones every time)
+        let source = "play matrix#0(path(\"kick.wav\")).sometimes(.5, |p| matrix#0(path(\"kick.wav\") * .5));";
+        let played = hits(&render(&mut compile(source, &widgets).unwrap(), 88000));
+        assert_eq!(played.len(), 16);
+        let soft = played.iter().filter(|&&(_, level)| level == 0.5).count();
+        assert!(soft > 0 && soft < 16, "{:?}", played);
+        assert_eq!(
+            hits(&render(&mut compile(source, &widgets).unwrap(), 88000)),
+            played
+        );
+    }
+
+    #[test]
+    fn test_comparison() {
+        let crossings = |samples: Vec<f32>| {
+            samples
+                .windows(2)
+                .filter(|w| w[0] < 0.0 && w[1] >= 0.0)
+                .count()
+        };
+
+        let mut node = compile(
+            "play if midi.gate > .5 { sin(440hz) } else { sin(220hz) };",
+            &[],
+        )
+        .unwrap();
+        assert!(crossings(render(&mut node, 4410)).abs_diff(22) <= 1);
+
+        // (once the gate has glided over, and it's faded from one to the other)
+        node.apply("midi.gate", 1.0);
+        render(&mut node, 2000);
+        assert!(crossings(render(&mut node, 4410)).abs_diff(44) <= 1);
+
+        let mut node = compile("play !(midi.gate > .5) && midi.velocity >= 0;", &[]).unwrap();
+        assert_eq!(render(&mut node, 1), vec![1.0]);
+    }
+}
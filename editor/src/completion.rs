@@ -0,0 +1,197 @@
+use live_editor_state::{LineData, Pos};
+use live_language::{completions, Documentation};
+use winit::keyboard::Key;
+
+use crate::{
+    doc_hover::draw_documentation,
+    panel::{text_width, text_y},
+    render::{Overlay, Renderer},
+};
+
+const FONT_SIZE: f32 = 13.0;
+const ROW_HEIGHT: f32 = 20.0;
+const PADDING: f32 = 4.0;
+const MAX_ROWS: usize = 8;
+
+const PANEL_COLOR: [f32; 4] = [0.1, 0.1, 0.1, 0.92];
+const SELECTED_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.15];
+const TEXT_COLOR: [f32; 4] = [0.98, 0.98, 0.98, 1.0];
+
+/// The name being completed, as it was typed up to the caret
+struct Word {
+    start: Pos,
+    caret: Pos,
+    source: String,
+    prefix: String,
+    completions: Vec<(String, Documentation)>,
+}
+
+/**
+    The Ctrl+Space completions: what the name before the caret could become, with the documentation of the selected one next to it (drawn like `DocHover` draws it). It follows the typing, and closes when the caret leaves the name, or nothing matches anymore.
+*/
+pub struct Completion {
+    word: Option<Word>,
+    selected: usize,
+}
+
+impl Completion {
+    pub fn new() -> Self {
+        Self {
+            word: None,
+            selected: 0,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.word.is_some()
+    }
+
+    pub fn open(&mut self, linedata: &LineData, caret: Pos) {
+        self.selected = 0;
+        self.word = None;
+        self.follow(linedata, caret);
+    }
+
+    pub fn close(&mut self) {
+        self.word = None;
+    }
+
+    /// The keys it takes while it's open (the rest go to the code, as usual)
+    pub fn captures(key: &Key) -> bool {
+        matches!(
+            key,
+            Key::Escape | Key::Enter | Key::Tab | Key::ArrowUp | Key::ArrowDown
+        )
+    }
+
+    /**
+        Looks up the completions again if the name before the caret changed, or closes if there isn't one
+    */
+    pub fn follow(&mut self, linedata: &LineData, caret: Pos) {
+        let source = linedata.to_string();
+        if self
+            .word
+            .as_ref()
+            .is_some_and(|word| word.caret == caret && word.source == source)
+        {
+            return;
+        }
+
+        let Some(word) = linedata.find_word_at(caret) else {
+            self.word = None;
+            return;
+        };
+        // (only at the end of a name)
+        if word.end != caret || word.start == caret {
+            self.word = None;
+            return;
+        }
+
+        let prefix =
+            source[linedata.pos_to_offset(word.start)..linedata.pos_to_offset(caret)].to_string();
+        if !prefix.starts_with(|c: char| c.is_alphabetic() || c == '_')
+            || !prefix.chars().all(|c| c.is_alphanumeric() || c == '_')
+        {
+            self.word = None;
+            return;
+        }
+
+        let completions = completions(&source, &prefix)
+            .into_iter()
+            .filter(|(name, _)| *name != prefix)
+            .collect::<Vec<_>>();
+        if completions.is_empty() {
+            self.word = None;
+            return;
+        }
+
+        self.selected = self.selected.min(completions.len() - 1);
+        self.word = Some(Word {
+            start: word.start,
+            caret,
+            source,
+            prefix,
+            completions,
+        });
+    }
+
+    pub fn move_selection(&mut self, delta: i32) {
+        if let Some(word) = &self.word {
+            let n = word.completions.len() as i32;
+            self.selected = (self.selected as i32 + delta).rem_euclid(n) as usize;
+        }
+    }
+
+    /**
+        What's left to type of the selected name
+    */
+    pub fn accept(&mut self) -> Option<String> {
+        let word = self.word.take()?;
+        let (name, _) = word.completions.get(self.selected)?;
+        Some(name[word.prefix.len()..].to_string())
+    }
+
+    /**
+        Draws the names just below the caret (like `DocHover`), scrolled to the selected one, with its documentation to the right
+    */
+    pub fn draw(
+        &mut self,
+        linedata: &LineData,
+        caret: Pos,
+        renderer: &Renderer,
+        window_size: (f32, f32),
+        overlay: &mut Overlay,
+    ) {
+        self.follow(linedata, caret);
+        let Some(word) = &self.word else {
+            return;
+        };
+
+        let system = &renderer.system;
+        let (x, caret_min_y) = system.pos_to_px(word.start);
+        let caret_max_y = caret_min_y + system.char_size.1 / system.scale_factor;
+
+        let first = self.selected.saturating_sub(MAX_ROWS - 1);
+        let shown = &word.completions[first..word.completions.len().min(first + MAX_ROWS)];
+        let longest = shown
+            .iter()
+            .map(|(name, _)| name.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        let width = PADDING * 2.0 + text_width(longest);
+        let height = PADDING * 2.0 + shown.len() as f32 * ROW_HEIGHT;
+        let min_x = x.min(window_size.0 - width).max(0.0);
+        let min_y = if caret_max_y + 4.0 + height <= window_size.1 {
+            caret_max_y + 4.0
+        } else {
+            (caret_min_y - 4.0 - height).max(0.0)
+        };
+
+        overlay.quad((min_x, min_y, min_x + width, min_y + height), PANEL_COLOR);
+
+        for (i, (name, _)) in shown.iter().enumerate() {
+            let row_min_y = min_y + PADDING + i as f32 * ROW_HEIGHT;
+            if first + i == self.selected {
+                overlay.quad(
+                    (min_x, row_min_y, min_x + width, row_min_y + ROW_HEIGHT),
+                    SELECTED_COLOR,
+                );
+            }
+            overlay.text(
+                (min_x + PADDING, text_y(row_min_y, ROW_HEIGHT, FONT_SIZE)),
+                name,
+                FONT_SIZE,
+                TEXT_COLOR,
+            );
+        }
+
+        let (_, docs) = &word.completions[self.selected];
+        draw_documentation(
+            docs,
+            (min_x + width + 4.0, min_y - 4.0, min_y - 4.0),
+            window_size,
+            overlay,
+        );
+    }
+}
@@ -0,0 +1,368 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::{BTreeSet, VecDeque},
+    sync::{mpsc::Receiver, Mutex},
+    time::{Duration, Instant},
+};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+    fmt::{self, Write},
+    sync::mpsc::{sync_channel, SyncSender},
+};
+
+use tracing::Level;
+#[cfg(not(target_arch = "wasm32"))]
+use tracing::{
+    field::{Field, Visit},
+    Event, Subscriber,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use tracing_subscriber::{
+    filter::{LevelFilter, Targets},
+    layer::Context,
+    prelude::*,
+    Layer,
+};
+
+use crate::{
+    panel::{
+        chars_in, contains, docked_bounds, draw_docked, fit, text_width, text_y, Bounds,
+        HEADER_HEIGHT, PANEL_MARGIN,
+    },
+    render::Overlay,
+};
+
+/// How many records the console keeps (the oldest ones go first)
+const MAX_RECORDS: usize = 500;
+/// How often the console looks for new records while it's open (they can come in from any thread)
+const REFRESH_INTERVAL: Duration = Duration::from_millis(250);
+
+const PANEL_HEIGHT: f32 = 240.0;
+const ROW_HEIGHT: f32 = 20.0;
+const FONT_SIZE: f32 = 13.0;
+const CHIP_PADDING: f32 = 8.0;
+const CHIP_GAP: f32 = 6.0;
+
+const PANEL_COLOR: [f32; 4] = [0.99, 0.99, 0.98, 0.97];
+const BORDER_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.1];
+const CHIP_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.06];
+const TEXT_COLOR: [f32; 4] = [0.02, 0.02, 0.02, 1.0];
+const DIM_TEXT_COLOR: [f32; 4] = [0.02, 0.02, 0.02, 0.45];
+const HIDDEN_TEXT_COLOR: [f32; 4] = [0.02, 0.02, 0.02, 0.25];
+const WARNING_COLOR: [f32; 4] = [0.8, 0.45, 0.0, 1.0];
+const ERROR_COLOR: [f32; 4] = [0.8, 0.1, 0.1, 1.0];
+
+struct Record {
+    level: Level,
+    target: String,
+    message: String,
+}
+
+/// (the console takes it when it's made, after `init`)
+static RECEIVER: Mutex<Option<Receiver<Record>>> = Mutex::new(None);
+
+/**
+    Sends what's logged to the console: everything of our own (see `targets`), and the warnings of the libraries (the ones that use `log`, like wgpu, too). Warnings also go to stderr, and with the `tracing` feature, so does how long every frame's render passes took (`cargo run --release --features tracing`).
+*/
+#[cfg(not(target_arch = "wasm32"))]
+pub fn init() {
+    let (sender, receiver) = sync_channel(MAX_RECORDS);
+    if let Ok(mut taken) = RECEIVER.lock() {
+        *taken = Some(receiver);
+    }
+
+    let stderr = tracing_subscriber::fmt::layer().with_writer(std::io::stderr);
+
+    #[cfg(feature = "tracing")]
+    let stderr = stderr
+        .with_span_events(tracing_subscriber::fmt::format::FmtSpan::CLOSE)
+        .with_filter(LevelFilter::INFO);
+    #[cfg(not(feature = "tracing"))]
+    let stderr = stderr.with_filter(LevelFilter::WARN);
+
+    tracing_subscriber::registry()
+        .with(ConsoleLayer(sender).with_filter(targets()))
+        .with(stderr)
+        .init();
+}
+
+// (there's no tracing-subscriber in the browser, so nothing's collected there, yet)
+#[cfg(target_arch = "wasm32")]
+pub fn init() {}
+
+/// (besides the crates, there are targets for what's not about any one module: "files" for watching the file system, "language" for parsing, and "startup")
+#[cfg(not(target_arch = "wasm32"))]
+fn targets() -> Targets {
+    Targets::new()
+        .with_target("live_editor", Level::DEBUG)
+        .with_target("live_engine", Level::DEBUG)
+        .with_target("files", Level::DEBUG)
+        .with_target("language", Level::DEBUG)
+        .with_target("startup", Level::DEBUG)
+        .with_default(Level::WARN)
+}
+
+/// What the console filters by: the crate (without the `live_`), or the target itself if it's one of the ones above
+fn area(target: &str) -> &str {
+    let target = target.strip_prefix("live_").unwrap_or(target);
+
+    // (the renderer is in the editor crate, but it's a thing of its own)
+    if target.starts_with("editor::render") {
+        return "render";
+    }
+
+    target.split("::").next().unwrap_or(target)
+}
+
+/**
+    Hands the records to the console over a channel, so that logging (from any thread, the audio callback's too) never waits on a lock. If the console is that far behind, the record is dropped instead.
+*/
+#[cfg(not(target_arch = "wasm32"))]
+struct ConsoleLayer(SyncSender<Record>);
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<S: Subscriber> Layer<S> for ConsoleLayer {
+    fn on_event(&self, event: &Event<'_>, _: Context<'_, S>) {
+        let mut message = Message::default();
+        event.record(&mut message);
+
+        let _ = self.0.try_send(Record {
+            level: *event.metadata().level(),
+            target: event.metadata().target().to_string(),
+            message: message.message + &message.fields,
+        });
+    }
+}
+
+/// The message of an event, with its other fields after it (like `parsed in 1.2ms lines=40`)
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Default)]
+struct Message {
+    message: String,
+    fields: String,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Visit for Message {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            let _ = write!(self.fields, " {}={}", field.name(), value);
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{:?}", value);
+        } else {
+            let _ = write!(self.fields, " {}={:?}", field.name(), value);
+        }
+    }
+}
+
+fn level_label(level: Level) -> (&'static str, [f32; 4]) {
+    match level {
+        Level::ERROR => ("error", ERROR_COLOR),
+        Level::WARN => ("warn", WARNING_COLOR),
+        Level::INFO => ("info", TEXT_COLOR),
+        _ => ("debug", DIM_TEXT_COLOR),
+    }
+}
+
+pub enum ConsoleHit {
+    /// the chip of an area, to show or hide what's logged there
+    Area(String),
+    Panel,
+}
+
+/**
+    The log console (Cmd+J), along the bottom of the window, right above the status bar: warnings, dropouts, files changing on disk, how long parsing took. The chips in its header show (or hide) what's logged by each part of the program, and the newest records are at the bottom.
+*/
+pub struct Console {
+    open: bool,
+    hidden: BTreeSet<String>,
+    receiver: Option<Receiver<Record>>,
+    // (in cells, so that looking for new records doesn't need `&mut`)
+    records: RefCell<VecDeque<Record>>,
+    // (whether there are new ones since it was last drawn)
+    unseen: Cell<bool>,
+}
+
+impl Console {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            hidden: BTreeSet::new(),
+            receiver: RECEIVER.lock().ok().and_then(|mut taken| taken.take()),
+            records: RefCell::new(VecDeque::new()),
+            unseen: Cell::new(false),
+        }
+    }
+
+    /// Takes what's been logged since (the oldest records go when there are too many)
+    fn receive(&self) {
+        let Some(receiver) = &self.receiver else {
+            return;
+        };
+
+        let mut records = self.records.borrow_mut();
+        for record in receiver.try_iter() {
+            if records.len() == MAX_RECORDS {
+                records.pop_front();
+            }
+            records.push_back(record);
+            self.unseen.set(true);
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    pub fn toggle_area(&mut self, area: &str) {
+        if !self.hidden.remove(area) {
+            self.hidden.insert(area.to_string());
+        }
+    }
+
+    /**
+        When to look for new records again, so that the event loop wakes up for them
+    */
+    pub fn due_at(&self) -> Option<Instant> {
+        self.open.then(|| Instant::now() + REFRESH_INTERVAL)
+    }
+
+    pub fn needs_redraw(&self) -> bool {
+        self.receive();
+        self.open && self.unseen.get()
+    }
+
+    fn bounds(&self, window_size: (f32, f32)) -> Bounds {
+        docked_bounds(window_size, PANEL_HEIGHT)
+    }
+
+    /// Every area there are records of (or that's hidden), with where its chip is
+    fn chips(&self, records: &VecDeque<Record>, bounds: Bounds) -> Vec<(String, Bounds)> {
+        let (min_x, min_y, _, _) = bounds;
+
+        let areas = records
+            .iter()
+            .map(|record| area(&record.target).to_string())
+            .chain(self.hidden.iter().cloned())
+            .collect::<BTreeSet<_>>();
+
+        let mut x = min_x + PANEL_MARGIN + text_width("Console".len()) + 2.0 * CHIP_PADDING;
+
+        areas
+            .into_iter()
+            .map(|area| {
+                let width = text_width(area.chars().count()) + 2.0 * CHIP_PADDING;
+                let bounds = (x, min_y + 5.0, x + width, min_y + HEADER_HEIGHT - 5.0);
+                x += width + CHIP_GAP;
+                (area, bounds)
+            })
+            .collect()
+    }
+
+    pub fn hit_test(&self, window_size: (f32, f32), point: (f32, f32)) -> Option<ConsoleHit> {
+        let bounds = self.bounds(window_size);
+        if !contains(bounds, point) {
+            return None;
+        }
+
+        for (area, chip) in self.chips(&self.records.borrow(), bounds) {
+            if contains(chip, point) {
+                return Some(ConsoleHit::Area(area));
+            }
+        }
+
+        Some(ConsoleHit::Panel)
+    }
+
+    pub fn draw(&mut self, window_size: (f32, f32), overlay: &mut Overlay) {
+        self.receive();
+        self.unseen.set(false);
+        let records = self.records.borrow();
+
+        let bounds = self.bounds(window_size);
+        let (min_x, min_y, max_x, max_y) = bounds;
+        let content_top = min_y + HEADER_HEIGHT;
+
+        draw_docked(
+            bounds,
+            "Console",
+            FONT_SIZE,
+            [PANEL_COLOR, BORDER_COLOR, TEXT_COLOR],
+            overlay,
+        );
+
+        for (area, (chip_min_x, chip_min_y, chip_max_x, chip_max_y)) in self.chips(&records, bounds)
+        {
+            let hidden = self.hidden.contains(&area);
+            if !hidden {
+                overlay.quad((chip_min_x, chip_min_y, chip_max_x, chip_max_y), CHIP_COLOR);
+            }
+            overlay.text(
+                (
+                    chip_min_x + CHIP_PADDING,
+                    text_y(chip_min_y, chip_max_y - chip_min_y, FONT_SIZE),
+                ),
+                area,
+                FONT_SIZE,
+                if hidden {
+                    HIDDEN_TEXT_COLOR
+                } else {
+                    TEXT_COLOR
+                },
+            );
+        }
+
+        let rows = ((max_y - content_top) / ROW_HEIGHT).max(0.0) as usize;
+        let shown = records
+            .iter()
+            .filter(|record| !self.hidden.contains(area(&record.target)))
+            .collect::<Vec<_>>();
+
+        if shown.is_empty() {
+            overlay.text(
+                (
+                    min_x + PANEL_MARGIN,
+                    text_y(content_top, ROW_HEIGHT, FONT_SIZE),
+                ),
+                "(nothing logged)",
+                FONT_SIZE,
+                DIM_TEXT_COLOR,
+            );
+        }
+
+        let message_x = min_x + PANEL_MARGIN + 140.0;
+        let max_chars = chars_in(max_x - PANEL_MARGIN - message_x);
+
+        for (i, record) in shown[shown.len().saturating_sub(rows)..].iter().enumerate() {
+            let y = text_y(content_top + i as f32 * ROW_HEIGHT, ROW_HEIGHT, FONT_SIZE);
+
+            let (label, color) = level_label(record.level);
+            overlay.text((min_x + PANEL_MARGIN, y), label, FONT_SIZE, color);
+            overlay.text(
+                (min_x + PANEL_MARGIN + 50.0, y),
+                area(&record.target),
+                FONT_SIZE,
+                DIM_TEXT_COLOR,
+            );
+
+            // (one line per record, cut off where the window ends)
+            let message = record.message.lines().next().unwrap_or_default();
+            overlay.text(
+                (message_x, y),
+                fit(message, max_chars),
+                FONT_SIZE,
+                TEXT_COLOR,
+            );
+        }
+    }
+}
@@ -0,0 +1,201 @@
+use crate::{
+    commands::EditorCommand,
+    panel::{contains, text_width, text_y, Bounds},
+    render::Overlay,
+    ui::WidgetAction,
+};
+
+const WIDTH: f32 = 240.0;
+const ROW_HEIGHT: f32 = 24.0;
+const PADDING: f32 = 4.0;
+const FONT_SIZE: f32 = 13.0;
+// (so that the mouse isn't on the first item right away, it's opened a bit to the right of it)
+const OFFSET: f32 = 2.0;
+
+const PANEL_COLOR: [f32; 4] = [0.1, 0.1, 0.1, 0.92];
+const SELECTED_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.15];
+const TEXT_COLOR: [f32; 4] = [0.98, 0.98, 0.98, 1.0];
+const DIM_TEXT_COLOR: [f32; 4] = [0.98, 0.98, 0.98, 0.5];
+const DESTRUCTIVE_TEXT_COLOR: [f32; 4] = [1.0, 0.5, 0.45, 1.0];
+
+/**
+    What's in a context menu: what can be done with a widget, or (on the code) the same commands as in the command palette
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuItem {
+    /// for the widget with that id, see `WidgetManager::actions`
+    Widget(usize, WidgetAction),
+    Command(EditorCommand),
+}
+
+impl MenuItem {
+    /// What right-clicking the code offers
+    pub const TEXT: &'static [MenuItem] = &[
+        MenuItem::Command(EditorCommand::Cut),
+        MenuItem::Command(EditorCommand::Copy),
+        MenuItem::Command(EditorCommand::Paste),
+        MenuItem::Command(EditorCommand::SelectAll),
+        MenuItem::Command(EditorCommand::Evaluate),
+        MenuItem::Command(EditorCommand::GoToDefinition),
+        MenuItem::Command(EditorCommand::Freeze),
+    ];
+
+    fn label(&self) -> String {
+        match self {
+            MenuItem::Widget(_, action) => action.label().into(),
+            MenuItem::Command(command) => command.name(),
+        }
+    }
+
+    fn shortcut(&self) -> Option<String> {
+        match self {
+            MenuItem::Widget(..) => None,
+            MenuItem::Command(command) => Some(command.shortcut()),
+        }
+    }
+}
+
+pub enum ContextMenuHit {
+    Item(MenuItem),
+    /// somewhere on the menu, but not on an item
+    Menu,
+}
+
+/**
+    The menu that right-clicking a widget (or the code) opens, where the mouse is. Its items can be picked with the mouse, or with the arrow keys and Enter. Picking one closes it, and so does clicking anywhere else, or Esc.
+*/
+#[derive(Default)]
+pub struct ContextMenu {
+    open: Option<(Vec<MenuItem>, (f32, f32))>,
+    // (the item that's under the mouse, or that was moved to with the arrow keys)
+    selected: Option<usize>,
+}
+
+impl ContextMenu {
+    pub fn is_open(&self) -> bool {
+        self.open.is_some()
+    }
+
+    pub fn open(&mut self, items: Vec<MenuItem>, at: (f32, f32)) {
+        self.open = (!items.is_empty()).then_some((items, at));
+        self.selected = None;
+    }
+
+    pub fn close(&mut self) {
+        self.open = None;
+        self.selected = None;
+    }
+
+    /**
+        Moves the selection up or down, wrapping around (the first arrow key press selects the first or last item)
+    */
+    pub fn move_selection(&mut self, delta: i32) {
+        let Some((items, _)) = &self.open else {
+            return;
+        };
+
+        let len = items.len() as i32;
+        self.selected = Some(match self.selected {
+            Some(i) => (i as i32 + delta).rem_euclid(len) as usize,
+            None if delta < 0 => items.len() - 1,
+            None => 0,
+        });
+    }
+
+    pub fn selected_item(&self) -> Option<MenuItem> {
+        let (items, _) = self.open.as_ref()?;
+        items.get(self.selected?).copied()
+    }
+
+    /**
+        Right below and to the right of where it was opened, unless that'd go past the edges of the window
+    */
+    fn bounds(items: &[MenuItem], at: (f32, f32), (width, height): (f32, f32)) -> Bounds {
+        let menu_height = 2.0 * PADDING + items.len() as f32 * ROW_HEIGHT;
+
+        let min_x = (at.0 + OFFSET).min(width - WIDTH).max(0.0);
+        let min_y = (at.1 + OFFSET).min(height - menu_height).max(0.0);
+
+        (min_x, min_y, min_x + WIDTH, min_y + menu_height)
+    }
+
+    fn row_bounds(menu: Bounds, i: usize) -> Bounds {
+        let min_y = menu.1 + PADDING + i as f32 * ROW_HEIGHT;
+        (menu.0, min_y, menu.2, min_y + ROW_HEIGHT)
+    }
+
+    /// Which item the mouse is on, if it's on the menu at all
+    fn row_at(&self, window_size: (f32, f32), mouse: (f32, f32)) -> Option<Option<usize>> {
+        let (items, at) = self.open.as_ref()?;
+
+        let menu = Self::bounds(items, *at, window_size);
+        if !contains(menu, mouse) {
+            return None;
+        }
+
+        Some((0..items.len()).find(|&i| contains(Self::row_bounds(menu, i), mouse)))
+    }
+
+    pub fn hit_test(&self, window_size: (f32, f32), mouse: (f32, f32)) -> Option<ContextMenuHit> {
+        let (items, _) = self.open.as_ref()?;
+
+        Some(match self.row_at(window_size, mouse)? {
+            Some(i) => ContextMenuHit::Item(items[i]),
+            None => ContextMenuHit::Menu,
+        })
+    }
+
+    /**
+        Selects the item under the mouse, returning whether that changed (moving off the menu keeps what was selected)
+    */
+    pub fn hover(&mut self, window_size: (f32, f32), mouse: (f32, f32)) -> bool {
+        let Some(Some(i)) = self.row_at(window_size, mouse) else {
+            return false;
+        };
+        if self.selected == Some(i) {
+            return false;
+        }
+
+        self.selected = Some(i);
+        true
+    }
+
+    pub fn draw(&self, window_size: (f32, f32), overlay: &mut Overlay) {
+        let Some((items, at)) = &self.open else {
+            return;
+        };
+
+        let menu = Self::bounds(items, *at, window_size);
+        overlay.quad(menu, PANEL_COLOR);
+
+        for (i, item) in items.iter().enumerate() {
+            let (min_x, min_y, max_x, max_y) = Self::row_bounds(menu, i);
+            let text_y = text_y(min_y, ROW_HEIGHT, FONT_SIZE);
+
+            if self.selected == Some(i) {
+                overlay.quad((min_x, min_y, max_x, max_y), SELECTED_COLOR);
+            }
+
+            overlay.text(
+                (min_x + 3.0 * PADDING, text_y),
+                item.label(),
+                FONT_SIZE,
+                if let MenuItem::Widget(_, WidgetAction::Remove) = item {
+                    DESTRUCTIVE_TEXT_COLOR
+                } else {
+                    TEXT_COLOR
+                },
+            );
+
+            if let Some(shortcut) = item.shortcut() {
+                let width = text_width(shortcut.chars().count());
+                overlay.text(
+                    (max_x - 3.0 * PADDING - width, text_y),
+                    shortcut,
+                    FONT_SIZE,
+                    DIM_TEXT_COLOR,
+                );
+            }
+        }
+    }
+}
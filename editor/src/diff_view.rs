@@ -0,0 +1,138 @@
+use live_editor_state::{diff, Hunk, HunkKind, LineData, Pos};
+
+use crate::render::{Overlay, Renderer};
+
+const STRIPE_WIDTH: f32 = 3.0;
+// (right in front of the code, so it doesn't overlap a pending swap's stripe)
+const STRIPE_OFFSET: f32 = 4.0;
+// (lines that were removed are marked in between the lines they were in between)
+const REMOVED_MARKER_HEIGHT: f32 = 3.0;
+const REMOVED_MARKER_WIDTH: f32 = 8.0;
+
+const ADDED_COLOR: [f32; 4] = [0.2, 0.65, 0.3, 0.9];
+const MODIFIED_COLOR: [f32; 4] = [0.25, 0.5, 0.9, 0.9];
+const REMOVED_COLOR: [f32; 4] = [0.85, 0.2, 0.2, 0.9];
+const CHANGE_COLOR: [f32; 4] = [0.25, 0.5, 0.9, 0.18];
+
+/**
+    The diff mode (Cmd+Shift+D): what changed since the code was last committed (in a git repository), or else since it was last backed up (see `Backups`), so what changed during a jam can be reviewed before committing it. Changed lines get a stripe in the gutter (green for added, blue for modified, and a red marker where lines were removed), and within modified lines, what's new is highlighted.
+
+    It follows along with edits, until it's closed again.
+*/
+#[derive(Default)]
+pub struct DiffView {
+    // what it's compared with, while it's open
+    base: Option<LineData>,
+    // (what the hunks are about, so they're only computed again when that changed)
+    source: Option<String>,
+    hunks: Vec<Hunk>,
+}
+
+impl DiffView {
+    pub fn is_open(&self) -> bool {
+        self.base.is_some()
+    }
+
+    pub fn open(&mut self, base: LineData, linedata: &LineData) {
+        self.base = Some(base);
+        self.source = None;
+        self.sync(linedata);
+    }
+
+    pub fn close(&mut self) {
+        *self = Self::default();
+    }
+
+    /**
+        Diffs again, when the document changed since
+    */
+    pub fn sync(&mut self, linedata: &LineData) {
+        let Some(base) = &self.base else {
+            return;
+        };
+
+        let source = linedata.to_string();
+        if self.source.as_deref() == Some(source.as_str()) {
+            return;
+        }
+
+        self.hunks = diff(base, linedata);
+        self.source = Some(source);
+    }
+
+    pub fn hunks(&self) -> &[Hunk] {
+        &self.hunks
+    }
+
+    /**
+        The row of the next change after (or with `forward` false, the previous one before) `row`, wrapping around
+    */
+    pub fn next_change(&self, row: i32, forward: bool) -> Option<i32> {
+        let starts = self.hunks.iter().map(|hunk| hunk.rows.start);
+
+        if forward {
+            starts.clone().find(|&start| start > row).or(starts.min())
+        } else {
+            starts
+                .clone()
+                .filter(|&start| start < row)
+                .max()
+                .or(starts.max())
+        }
+    }
+
+    pub fn draw(&self, renderer: &Renderer, overlay: &mut Overlay) {
+        if !self.is_open() {
+            return;
+        }
+
+        let system = &renderer.system;
+        let line_height = system.char_size.1 / system.scale_factor;
+
+        for hunk in &self.hunks {
+            let color = match hunk.kind() {
+                HunkKind::Added => ADDED_COLOR,
+                HunkKind::Modified => MODIFIED_COLOR,
+                HunkKind::Removed => {
+                    let (x, y) = system.pos_to_px(Pos {
+                        row: hunk.rows.start,
+                        col: 0,
+                    });
+                    let min_x = x - STRIPE_OFFSET - REMOVED_MARKER_WIDTH + STRIPE_WIDTH;
+                    let min_y = y - REMOVED_MARKER_HEIGHT / 2.0;
+
+                    overlay.quad(
+                        (
+                            min_x,
+                            min_y,
+                            min_x + REMOVED_MARKER_WIDTH,
+                            min_y + REMOVED_MARKER_HEIGHT,
+                        ),
+                        REMOVED_COLOR,
+                    );
+                    continue;
+                }
+            };
+
+            for row in hunk.rows.clone() {
+                let (x, y) = system.pos_to_px(Pos { row, col: 0 });
+                let min_x = x - STRIPE_OFFSET;
+
+                overlay.quad((min_x, y, min_x + STRIPE_WIDTH, y + line_height), color);
+            }
+
+            for change in &hunk.changes {
+                let (min_x, y) = system.pos_to_px(Pos {
+                    row: change.row,
+                    col: change.col_start,
+                });
+                let (max_x, _) = system.pos_to_px(Pos {
+                    row: change.row,
+                    col: change.col_end,
+                });
+
+                overlay.quad((min_x, y, max_x, y + line_height), CHANGE_COLOR);
+            }
+        }
+    }
+}
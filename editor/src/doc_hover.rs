@@ -0,0 +1,213 @@
+use std::time::{Duration, Instant};
+
+use live_editor_state::{LineData, Range};
+use live_language::{documentation, Documentation};
+
+use crate::{
+    panel::{text_width, text_y},
+    render::{Overlay, Renderer},
+};
+
+/// How long the mouse has to rest on a name before we show its documentation
+const HOVER_DELAY: Duration = Duration::from_millis(600);
+
+const FONT_SIZE: f32 = 13.0;
+const ROW_HEIGHT: f32 = 20.0;
+const PADDING: f32 = 8.0;
+/// Where the documentation text wraps
+const MAX_CHARS: usize = 64;
+
+const PANEL_COLOR: [f32; 4] = [0.1, 0.1, 0.1, 0.92];
+const TEXT_COLOR: [f32; 4] = [0.98, 0.98, 0.98, 1.0];
+const DIM_TEXT_COLOR: [f32; 4] = [0.98, 0.98, 0.98, 0.6];
+
+struct Hover {
+    word: Range,
+    since: Instant,
+    // (looked up once it's due, in the source as it was then)
+    docs: Option<(String, Option<Documentation>)>,
+}
+
+/**
+    Long-hovering a name in the code shows what it is: the `///` comments of its definition, or the documentation of the built-in, with its parameters (see `live_language::documentation`).
+*/
+pub struct DocHover {
+    hover: Option<Hover>,
+    drawn: bool,
+}
+
+impl DocHover {
+    pub fn new() -> Self {
+        Self {
+            hover: None,
+            drawn: false,
+        }
+    }
+
+    pub fn hover(&mut self, word: Range) {
+        if self.hover.as_ref().map(|hover| hover.word) != Some(word) {
+            self.hover = Some(Hover {
+                word,
+                since: Instant::now(),
+                docs: None,
+            });
+            self.drawn = false;
+        }
+    }
+
+    pub fn unhover(&mut self) {
+        self.hover = None;
+    }
+
+    fn is_due(&self) -> bool {
+        self.hover
+            .as_ref()
+            .is_some_and(|hover| hover.since.elapsed() >= HOVER_DELAY)
+    }
+
+    /**
+        When the documentation will (have to) appear, so that the event loop can wake up for it
+    */
+    pub fn due_at(&self) -> Option<Instant> {
+        let hover = self.hover.as_ref()?;
+
+        if self.drawn {
+            return None;
+        }
+
+        Some(hover.since + HOVER_DELAY)
+    }
+
+    pub fn needs_redraw(&self) -> bool {
+        self.is_due() && !self.drawn
+    }
+
+    /**
+        Draws the documentation just below the name (or above it, if there's no room). When the code has changed since it was looked up, it's gone until the mouse moves again.
+    */
+    pub fn draw(
+        &mut self,
+        linedata: &LineData,
+        renderer: &Renderer,
+        window_size: (f32, f32),
+        overlay: &mut Overlay,
+    ) {
+        if !self.is_due() {
+            return;
+        }
+        let Some(hover) = &mut self.hover else {
+            return;
+        };
+
+        let source = linedata.to_string();
+        match &hover.docs {
+            Some((looked_up, _)) if *looked_up != source => {
+                self.hover = None;
+                return;
+            }
+            Some(_) => {}
+            None => {
+                let docs = documentation(&source, linedata.pos_to_offset(hover.word.start));
+                hover.docs = Some((source, docs));
+            }
+        }
+
+        self.drawn = true;
+
+        let Some((_, Some(docs))) = &hover.docs else {
+            return;
+        };
+
+        let system = &renderer.system;
+        let (word_x, word_min_y) = system.pos_to_px(hover.word.start);
+        let word_max_y = word_min_y + system.char_size.1 / system.scale_factor;
+
+        draw_documentation(docs, (word_x, word_min_y, word_max_y), window_size, overlay);
+    }
+}
+
+/**
+    Draws documentation at `x`, just below `bottom` (or above `top`, if there's no room), for what it's about in between: a hovered name, or a completion (see `Completion`).
+*/
+pub fn draw_documentation(
+    docs: &Documentation,
+    (x, top, bottom): (f32, f32, f32),
+    window_size: (f32, f32),
+    overlay: &mut Overlay,
+) {
+    let doc_lines = wrap(&docs.doc);
+    let name_width = docs
+        .params
+        .iter()
+        .map(|(name, _)| name.chars().count() + 2)
+        .max()
+        .unwrap_or(0);
+
+    let longest = std::iter::once(docs.signature.chars().count())
+        .chain(doc_lines.iter().map(|line| line.chars().count()))
+        .chain(
+            docs.params
+                .iter()
+                .map(|(_, description)| name_width + description.chars().count()),
+        )
+        .max()
+        .unwrap_or(0);
+
+    let rows = 1 + doc_lines.len() + docs.params.len();
+    let width = PADDING * 2.0 + text_width(longest);
+    let height = PADDING * 2.0 + rows as f32 * ROW_HEIGHT;
+
+    let min_x = x.min(window_size.0 - width).max(0.0);
+    let min_y = if bottom + 4.0 + height <= window_size.1 {
+        bottom + 4.0
+    } else {
+        (top - 4.0 - height).max(0.0)
+    };
+
+    overlay.quad((min_x, min_y, min_x + width, min_y + height), PANEL_COLOR);
+
+    let row_y = |i: usize| {
+        text_y(
+            min_y + PADDING + i as f32 * ROW_HEIGHT,
+            ROW_HEIGHT,
+            FONT_SIZE,
+        )
+    };
+    let x = min_x + PADDING;
+
+    overlay.bold_text((x, row_y(0)), &docs.signature, FONT_SIZE, TEXT_COLOR);
+    for (i, line) in doc_lines.iter().enumerate() {
+        overlay.text((x, row_y(1 + i)), line, FONT_SIZE, TEXT_COLOR);
+    }
+    for (i, (name, description)) in docs.params.iter().enumerate() {
+        let y = row_y(1 + doc_lines.len() + i);
+        overlay.bold_text((x, y), name, FONT_SIZE, DIM_TEXT_COLOR);
+        overlay.text(
+            (x + text_width(name_width), y),
+            description,
+            FONT_SIZE,
+            DIM_TEXT_COLOR,
+        );
+    }
+}
+
+/// Wraps text at word boundaries, keeping its own line breaks
+fn wrap(text: &str) -> Vec<String> {
+    let mut lines = vec![];
+
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > MAX_CHARS {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        lines.push(line);
+    }
+
+    lines
+}
@@ -0,0 +1,258 @@
+use live_editor_state::{LineData, Pos};
+
+use crate::{
+    panel::{contains, popup_size, text_y, Bounds},
+    render::{Overlay, Renderer},
+};
+
+const ICON_SIZE: f32 = 12.0;
+// (from where the code starts, leaving room for the stripe of a pending swap)
+const ICON_OFFSET: f32 = 14.0;
+const ICON_FONT_SIZE: f32 = 10.0;
+
+const FONT_SIZE: f32 = 13.0;
+const ROW_HEIGHT: f32 = 20.0;
+const PADDING: f32 = 8.0;
+
+const ICON_COLOR: [f32; 4] = [0.8, 0.1, 0.1, 1.0];
+const ICON_TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const PANEL_COLOR: [f32; 4] = [0.1, 0.1, 0.1, 0.92];
+const TEXT_COLOR: [f32; 4] = [0.98, 0.98, 0.98, 1.0];
+const ACTION_COLOR: [f32; 4] = [0.55, 0.75, 1.0, 1.0];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuickFix {
+    /// Pick another file for the sample widget (with this id) that couldn't be read
+    RelocateSample(usize),
+    CommentOut,
+}
+
+impl QuickFix {
+    fn label(&self) -> &'static str {
+        match self {
+            Self::RelocateSample(_) => "relocate sample…",
+            Self::CommentOut => "comment out statement",
+        }
+    }
+}
+
+/**
+    What went wrong on a line, the last time it was evaluated
+*/
+#[derive(Debug, Clone)]
+pub struct EvalError {
+    pub row: i32,
+    pub messages: Vec<String>,
+    /// (where the first error is, in the evaluated document, to find its statement)
+    pub offset: usize,
+    /// (a sample widget on the line that couldn't be read)
+    pub widget: Option<usize>,
+}
+
+impl EvalError {
+    pub fn fixes(&self) -> Vec<QuickFix> {
+        self.widget
+            .map(QuickFix::RelocateSample)
+            .into_iter()
+            .chain([QuickFix::CommentOut])
+            .collect()
+    }
+}
+
+pub enum EvalErrorsHit {
+    Icon(usize),
+    Fix(usize, QuickFix),
+    /// (the quick info, but not one of its fixes)
+    Popup,
+}
+
+/**
+    The errors that evaluating code ran into (from the evaluator, the unit checker, and widgets that don't work, like a sample file that's missing), as icons in the gutter of the lines they're on. Hovering an icon shows what's wrong, and clicking it offers quick fixes.
+
+    They're about the document as it was evaluated, so they're gone as soon as it's edited (until it's evaluated again).
+*/
+#[derive(Debug, Default)]
+pub struct EvalErrors {
+    source: Option<String>,
+    pub entries: Vec<EvalError>,
+    hovering: Option<usize>,
+    open: Option<usize>,
+}
+
+impl EvalErrors {
+    /**
+        Replaces the errors with those of the latest evaluation, as (where, what, which widget) triples
+    */
+    pub fn set(&mut self, linedata: &LineData, errors: Vec<(Pos, String, Option<usize>)>) {
+        self.entries.clear();
+
+        for (pos, message, widget) in errors {
+            match self.entries.iter_mut().find(|entry| entry.row == pos.row) {
+                Some(entry) => {
+                    entry.messages.push(message);
+                    entry.widget = entry.widget.or(widget);
+                }
+                None => self.entries.push(EvalError {
+                    row: pos.row,
+                    messages: vec![message],
+                    offset: linedata.pos_to_offset(pos),
+                    widget,
+                }),
+            }
+        }
+
+        self.source = Some(linedata.to_string());
+        self.hovering = None;
+        self.open = None;
+    }
+
+    /**
+        Forgets about the errors when the document changed since
+    */
+    pub fn sync(&mut self, linedata: &LineData) {
+        if self.entries.is_empty() {
+            return;
+        }
+
+        if self.source.as_deref() != Some(linedata.to_string().as_str()) {
+            *self = Self::default();
+        }
+    }
+
+    /**
+        Takes out a fixed error
+    */
+    pub fn resolve(&mut self, i: usize) {
+        if i < self.entries.len() {
+            self.entries.remove(i);
+        }
+        self.hovering = None;
+        self.open = None;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open.is_some()
+    }
+
+    pub fn open(&mut self, i: usize) {
+        self.open = Some(i);
+    }
+
+    pub fn close(&mut self) {
+        self.open = None;
+    }
+
+    /**
+        Tracks which icon the mouse is over, returning whether that changed
+    */
+    pub fn hover(&mut self, renderer: &Renderer, mouse: (f32, f32)) -> bool {
+        let hovering = match self.hit_test(renderer, mouse) {
+            Some(EvalErrorsHit::Icon(i)) => Some(i),
+            // (the quick info stays up while the mouse moves onto it)
+            Some(_) => self.hovering,
+            None => None,
+        };
+
+        let changed = hovering != self.hovering;
+        self.hovering = hovering;
+        changed
+    }
+
+    pub fn hit_test(&self, renderer: &Renderer, (x, y): (f32, f32)) -> Option<EvalErrorsHit> {
+        let inside = |bounds: Bounds| contains(bounds, (x, y));
+
+        if let Some(i) = self.open.or(self.hovering)
+            && let Some(entry) = self.entries.get(i)
+        {
+            let open = self.open == Some(i);
+            let bounds = Self::popup_bounds(renderer, entry, open);
+            if inside(bounds) {
+                if !open {
+                    return Some(EvalErrorsHit::Popup);
+                }
+
+                let first_fix_y = bounds.1 + PADDING + entry.messages.len() as f32 * ROW_HEIGHT;
+                let j = ((y - first_fix_y) / ROW_HEIGHT).floor();
+
+                return match entry.fixes().get(j as usize) {
+                    Some(&fix) if j >= 0.0 => Some(EvalErrorsHit::Fix(i, fix)),
+                    _ => Some(EvalErrorsHit::Popup),
+                };
+            }
+        }
+
+        self.entries
+            .iter()
+            .position(|entry| inside(Self::icon_bounds(renderer, entry.row)))
+            .map(EvalErrorsHit::Icon)
+    }
+
+    fn icon_bounds(renderer: &Renderer, row: i32) -> Bounds {
+        let system = &renderer.system;
+        let (x, y) = system.pos_to_px(Pos { row, col: 0 });
+        let line_height = system.char_size.1 / system.scale_factor;
+
+        let min_x = x - ICON_OFFSET - ICON_SIZE;
+        let min_y = y + (line_height - ICON_SIZE) / 2.0;
+
+        (min_x, min_y, min_x + ICON_SIZE, min_y + ICON_SIZE)
+    }
+
+    /**
+        The quick info: right next to the icon, listing what went wrong, and (once clicked) the fixes
+    */
+    fn popup_bounds(renderer: &Renderer, entry: &EvalError, open: bool) -> Bounds {
+        let (_, icon_min_y, icon_max_x, _) = Self::icon_bounds(renderer, entry.row);
+
+        let mut lines = entry.messages.clone();
+        if open {
+            lines.extend(entry.fixes().iter().map(|fix| fix.label().to_string()));
+        }
+
+        let (width, height) = popup_size(&lines, PADDING, ROW_HEIGHT);
+
+        let min_x = icon_max_x + 4.0;
+        let min_y = icon_min_y - PADDING;
+
+        (min_x, min_y, min_x + width, min_y + height)
+    }
+
+    pub fn draw(&self, renderer: &Renderer, overlay: &mut Overlay) {
+        for entry in &self.entries {
+            let bounds = Self::icon_bounds(renderer, entry.row);
+
+            overlay.quad(bounds, ICON_COLOR);
+            overlay.bold_text(
+                (bounds.0 + 4.0, text_y(bounds.1, ICON_SIZE, ICON_FONT_SIZE)),
+                "!",
+                ICON_FONT_SIZE,
+                ICON_TEXT_COLOR,
+            );
+        }
+
+        let Some(i) = self.open.or(self.hovering) else {
+            return;
+        };
+        let Some(entry) = self.entries.get(i) else {
+            return;
+        };
+
+        let open = self.open == Some(i);
+        let (min_x, min_y, max_x, max_y) = Self::popup_bounds(renderer, entry, open);
+        overlay.quad((min_x, min_y, max_x, max_y), PANEL_COLOR);
+
+        let mut y = text_y(min_y + PADDING, ROW_HEIGHT, FONT_SIZE);
+
+        for message in &entry.messages {
+            overlay.text((min_x + PADDING, y), message, FONT_SIZE, TEXT_COLOR);
+            y += ROW_HEIGHT;
+        }
+
+        if open {
+            for fix in entry.fixes() {
+                overlay.text((min_x + PADDING, y), fix.label(), FONT_SIZE, ACTION_COLOR);
+                y += ROW_HEIGHT;
+            }
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use std::{
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use crate::sample_packs::is_audio_file;
+
+/// (how much of a file is looked at to see whether it's text)
+const SNIFF_BYTES: usize = 8 * 1024;
+
+/**
+    What's dragged onto the window (or dropped there), by what the files are
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub enum FileDrop {
+    /// Audio files, which become sample widgets (one after the other)
+    Samples(Vec<PathBuf>),
+    /// A code file (or any other text), to open, or to insert the contents of
+    Text(PathBuf),
+    /// Something we can't do anything with: other kinds of files, several text files, or a mix
+    Rejected,
+}
+
+impl FileDrop {
+    pub fn of(paths: &[PathBuf]) -> Self {
+        if !paths.is_empty() && paths.iter().all(|path| is_audio_file(path)) {
+            return FileDrop::Samples(paths.to_vec());
+        }
+
+        match paths {
+            [path] if is_text_file(path) => FileDrop::Text(path.clone()),
+            _ => FileDrop::Rejected,
+        }
+    }
+}
+
+/// Whether the file starts out as text (UTF-8 without any NUL bytes), whatever its extension
+fn is_text_file(path: &Path) -> bool {
+    let Ok(file) = File::open(path) else {
+        return false;
+    };
+
+    let mut bytes = vec![];
+    if file
+        .take(SNIFF_BYTES as u64)
+        .read_to_end(&mut bytes)
+        .is_err()
+    {
+        return false;
+    }
+
+    match std::str::from_utf8(&bytes) {
+        Ok(text) => !text.contains('\0'),
+        // (it may just have been cut off in the middle of a character)
+        Err(e) => e.error_len().is_none() && !bytes.contains(&0),
+    }
+}
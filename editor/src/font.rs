@@ -0,0 +1,125 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{storage::storage, util::config_dir};
+
+const FILE_NAME: &str = "font.toml";
+
+/// (in logical pixels)
+const DEFAULT_SIZE: f32 = 25.0;
+const MIN_SIZE: f32 = 8.0;
+const MAX_SIZE: f32 = 96.0;
+/// How much Cmd+= and Cmd+- zoom in or out
+const ZOOM_STEP: f32 = 2.0;
+
+const MIN_LINE_HEIGHT: f32 = 0.8;
+const MAX_LINE_HEIGHT: f32 = 3.0;
+
+/**
+    How the code is set, from `font.toml` in the config dir, e.g.
+
+    ```toml
+    # a .ttf or .otf file (Fira Code, which is built in, otherwise), and one for keywords (the same one, otherwise)
+    family = "/Library/Fonts/JetBrainsMono-Regular.ttf"
+    bold = "/Library/Fonts/JetBrainsMono-Bold.ttf"
+    # in logical pixels
+    size = 18
+    # as a multiple of the font's own line height
+    line_height = 1.4
+    ```
+
+    Zooming (Cmd+= and Cmd+-, and Cmd+0 to reset) changes the size, and remembers it here.
+*/
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FontSettings {
+    pub family: Option<PathBuf>,
+    pub bold: Option<PathBuf>,
+    pub size: f32,
+    pub line_height: f32,
+}
+
+impl Default for FontSettings {
+    fn default() -> Self {
+        Self {
+            family: None,
+            bold: None,
+            size: DEFAULT_SIZE,
+            line_height: 1.0,
+        }
+    }
+}
+
+impl FontSettings {
+    pub fn load() -> Self {
+        let Some(contents) = config_dir().and_then(|dir| storage().read(&dir.join(FILE_NAME)).ok())
+        else {
+            return Self::default();
+        };
+
+        toml::from_str::<Self>(&contents)
+            .unwrap_or_else(|e| {
+                tracing::warn!("Could not read {}: {}", FILE_NAME, e);
+                Self::default()
+            })
+            .clamped()
+    }
+
+    pub fn save(&self) {
+        let Some(dir) = config_dir() else {
+            return;
+        };
+
+        match toml::to_string(self) {
+            Ok(contents) => {
+                let _ = storage().write(&dir.join(FILE_NAME), &contents);
+            }
+            Err(e) => tracing::warn!("Could not write {}: {}", FILE_NAME, e),
+        }
+    }
+
+    /**
+        (So that a typo in the file can't make the code invisible, or enormous)
+    */
+    fn clamped(self) -> Self {
+        let size = if self.size.is_finite() {
+            self.size.clamp(MIN_SIZE, MAX_SIZE)
+        } else {
+            DEFAULT_SIZE
+        };
+
+        let line_height = if self.line_height.is_finite() {
+            self.line_height.clamp(MIN_LINE_HEIGHT, MAX_LINE_HEIGHT)
+        } else {
+            1.0
+        };
+
+        Self {
+            size,
+            line_height,
+            ..self
+        }
+    }
+
+    /**
+        Bigger (or smaller, with negative steps), within reason
+    */
+    pub fn zoomed(&self, steps: f32) -> Self {
+        Self {
+            size: self.size + steps * ZOOM_STEP,
+            ..self.clone()
+        }
+        .clamped()
+    }
+
+    /**
+        Back to the default size (but the same font)
+    */
+    pub fn unzoomed(&self) -> Self {
+        Self {
+            size: DEFAULT_SIZE,
+            ..self.clone()
+        }
+    }
+}
@@ -0,0 +1,50 @@
+/**
+    Sublime-style fuzzy matching: all characters of the query need to appear in the candidate, in order, but not necessarily consecutively. Returns a score (higher is better) if it matches.
+
+    Consecutive runs, matches at the start of a word and short candidates are preferred.
+*/
+pub fn fuzzy_match(query: &str, candidate: &str) -> Option<i32> {
+    let query = query.to_lowercase().chars().collect::<Vec<_>>();
+    let candidate = candidate.chars().collect::<Vec<_>>();
+
+    if query.is_empty() {
+        return Some(0);
+    }
+
+    let mut score = 0;
+    let mut qi = 0;
+    let mut prev_matched = false;
+
+    for (ci, &ch) in candidate.iter().enumerate() {
+        if qi >= query.len() {
+            break;
+        }
+
+        if ch.to_lowercase().eq(query[qi].to_lowercase()) {
+            score += 1;
+
+            if prev_matched {
+                score += 5;
+            }
+
+            let at_word_start = ci == 0
+                || !candidate[ci - 1].is_alphanumeric()
+                || (ch.is_uppercase() && candidate[ci - 1].is_lowercase());
+
+            if at_word_start {
+                score += 10;
+            }
+
+            qi += 1;
+            prev_matched = true;
+        } else {
+            prev_matched = false;
+        }
+    }
+
+    if qi < query.len() {
+        return None;
+    }
+
+    Some(score * 100 - candidate.len() as i32)
+}
@@ -0,0 +1,196 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use git2::{build::CheckoutBuilder, BranchType, Repository, Signature};
+
+/**
+    The git repository the workspace is in (if it's in one), to commit the document to, and to switch between named snapshots of it (which are just branches). It's all local, nothing is ever pushed or fetched.
+
+    The document is whichever code file the editor is editing (the session, or another one that was opened), by its path on disk.
+*/
+pub struct Git {
+    repo: Repository,
+}
+
+impl Git {
+    pub fn open(root: &Path) -> Option<Self> {
+        let repo = Repository::discover(root).ok()?;
+        // (a bare repository has nowhere to commit the document from)
+        repo.workdir()?;
+
+        Some(Self { repo })
+    }
+
+    /**
+        A document's path relative to the repository's working directory (it doesn't have to exist yet), if it's in there
+    */
+    fn relative(&self, document: &Path) -> Option<PathBuf> {
+        let workdir = self.repo.workdir()?.canonicalize().ok()?;
+        let dir = document.parent()?.canonicalize().ok()?;
+
+        let path = dir.join(document.file_name()?);
+        Some(path.strip_prefix(&workdir).ok()?.to_path_buf())
+    }
+
+    /**
+        The branch that's checked out (or `None` for a detached HEAD, or a repository without commits)
+    */
+    pub fn current_branch(&self) -> Option<String> {
+        let head = self.repo.head().ok()?;
+        if !head.is_branch() {
+            return None;
+        }
+
+        head.shorthand().map(str::to_string)
+    }
+
+    /**
+        The local branches, by name
+    */
+    pub fn branches(&self) -> Vec<String> {
+        let Ok(branches) = self.repo.branches(Some(BranchType::Local)) else {
+            return vec![];
+        };
+
+        let mut names = branches
+            .filter_map(|branch| {
+                let (branch, _) = branch.ok()?;
+                Some(branch.name().ok()??.to_string())
+            })
+            .collect::<Vec<_>>();
+
+        names.sort();
+        names
+    }
+
+    /**
+        The document as it was last committed (on the current branch), if it was
+    */
+    pub fn committed(&self, document: &Path) -> Option<String> {
+        let relative = self.relative(document)?;
+        let tree = self.repo.head().ok()?.peel_to_tree().ok()?;
+        let entry = tree.get_path(&relative).ok()?;
+        let blob = entry.to_object(&self.repo).ok()?.peel_to_blob().ok()?;
+
+        String::from_utf8(blob.content().to_vec()).ok()
+    }
+
+    /**
+        Writes the document, stages it, and commits it (just it, whatever else is staged stays staged) on the current branch
+    */
+    pub fn commit(&self, document: &Path, source: &str, message: &str) -> Result<(), String> {
+        let relative = self
+            .relative(document)
+            .ok_or("it's not in the repository")?;
+        fs::write(document, source).map_err(|e| e.to_string())?;
+
+        let mut index = self.repo.index().map_err(|e| e.to_string())?;
+        index.add_path(&relative).map_err(|e| e.to_string())?;
+        index.write().map_err(|e| e.to_string())?;
+
+        let tree = index
+            .write_tree()
+            .and_then(|id| self.repo.find_tree(id))
+            .map_err(|e| e.to_string())?;
+
+        // (whoever's configured, or else the editor itself)
+        let signature = self
+            .repo
+            .signature()
+            .or_else(|_| Signature::now("live", "live@localhost"))
+            .map_err(|e| e.to_string())?;
+
+        let parent = self
+            .repo
+            .head()
+            .ok()
+            .and_then(|head| head.peel_to_commit().ok());
+
+        self.repo
+            .commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                message,
+                &tree,
+                &parent.iter().collect::<Vec<_>>(),
+            )
+            .map_err(|e| e.to_string())?;
+
+        Ok(())
+    }
+
+    /**
+        Switches to a branch, returning the document as it's committed there. (Files with changes that would be overwritten make it fail, rather than losing them.)
+    */
+    pub fn checkout(&self, branch: &str, document: &Path) -> Result<Option<String>, String> {
+        let branch = self
+            .repo
+            .find_branch(branch, BranchType::Local)
+            .map_err(|e| e.to_string())?;
+        let reference = branch.get();
+        let name = reference.name().ok_or("the branch has a weird name")?;
+        let commit = reference.peel_to_commit().map_err(|e| e.to_string())?;
+
+        self.repo
+            .checkout_tree(commit.as_object(), Some(CheckoutBuilder::new().safe()))
+            .map_err(|e| e.to_string())?;
+        self.repo.set_head(name).map_err(|e| e.to_string())?;
+
+        Ok(self.committed(document))
+    }
+
+    /**
+        A new branch, from what's committed now, which is checked out right away (so that what's committed next goes there)
+    */
+    pub fn create_branch(&self, name: &str) -> Result<(), String> {
+        let head = self
+            .repo
+            .head()
+            .and_then(|head| head.peel_to_commit())
+            .map_err(|_| "commit something first")?;
+
+        let branch = self
+            .repo
+            .branch(name, &head, false)
+            .map_err(|e| e.to_string())?;
+        let name = branch.get().name().ok_or("the branch has a weird name")?;
+
+        self.repo.set_head(name).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commit_opened_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        Repository::init(root).unwrap();
+        let git = Git::open(root).unwrap();
+
+        let session = root.join("session.live");
+        git.commit(&session, "play kick;", "session").unwrap();
+
+        // (another code file is opened, and committed)
+        let opened = root.join("drums/kick.live");
+        fs::create_dir_all(opened.parent().unwrap()).unwrap();
+        fs::write(&opened, "let kick = 1;").unwrap();
+        git.commit(&opened, "let kick = 2;", "kick").unwrap();
+
+        assert_eq!(fs::read_to_string(&session).unwrap(), "play kick;");
+        assert_eq!(git.committed(&session).as_deref(), Some("play kick;"));
+        assert_eq!(fs::read_to_string(&opened).unwrap(), "let kick = 2;");
+        assert_eq!(git.committed(&opened).as_deref(), Some("let kick = 2;"));
+
+        // (and not what's outside the repository)
+        let outside = tempfile::tempdir().unwrap();
+        assert!(git
+            .commit(&outside.path().join("other.live"), "", "other")
+            .is_err());
+    }
+}
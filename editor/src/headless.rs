@@ -0,0 +1,57 @@
+use std::{collections::HashMap, path::Path, thread, time::Duration};
+
+use live_engine::{Engine, BEATS_PER_BAR};
+use live_language::clips;
+
+use crate::{
+    audio_settings::AudioSettings,
+    bounce::{check, BounceSettings},
+    compile::{Compiler, Samples},
+};
+
+/**
+    Plays what the document plays, without the editor (for `live play`), on the audio devices the editor plays on, at the tempo and swing of its project: for `bars`, or until it's interrupted. Like `bounce`, there are no widgets, so the code that uses them can't be played, and it doesn't play a document that doesn't evaluate.
+*/
+pub fn play_document(
+    source: &str,
+    root: &Path,
+    settings: BounceSettings,
+    bars: Option<f64>,
+) -> Result<(), String> {
+    let evaluation = check(source, settings.seed, root)?;
+
+    // (the streams stop when it's dropped, so it's kept until the end)
+    let engine = Engine::start(&AudioSettings::load().devices())?;
+    let handle = engine.handle();
+    handle.set_tempo(settings.tempo);
+    handle.set_swing(settings.swing);
+
+    let mut samples = Samples::default();
+    let (targets, errors) =
+        Compiler::new(&handle, root, &HashMap::new(), &mut samples).compile(&evaluation, source);
+    if let Some((name, message)) = errors.into_iter().next() {
+        return Err(format!("can't play {}: {}", name, message));
+    }
+
+    // (nothing launches the clips here, like in a bounce)
+    let clips = clips(source);
+    for target in targets {
+        if !clips.iter().any(|clip| clip.target == target.name) {
+            handle.place(&target.name, target.placement);
+            handle.play(target.name, target.node);
+        }
+    }
+
+    match bars {
+        Some(bars) => {
+            let seconds = bars.max(0.0) * BEATS_PER_BAR * 60.0 / settings.tempo;
+            thread::sleep(Duration::from_secs_f64(seconds));
+        }
+        None => loop {
+            thread::park();
+        },
+    }
+
+    drop(engine);
+    Ok(())
+}
@@ -0,0 +1,148 @@
+use std::time::{Duration, Instant};
+
+use live_editor_state::{LineData, Pos};
+use live_engine::{Costs, EngineHandle};
+
+use crate::{
+    code_levels::CodeLevels,
+    render::{Overlay, Renderer},
+};
+
+/// From this much callback load on, the heat shows (it's getting tight)
+const HEAT_LOAD: f32 = 0.5;
+/// And for this long after a dropout
+const HEAT_LINGER: Duration = Duration::from_secs(5);
+/// (targets that cost less than this share of the time there is aren't worth pointing at)
+const MIN_COST: f32 = 0.01;
+/// A target that costs this share (or more) is as hot as it gets
+const HOT_COST: f32 = 0.25;
+/// How many of the most expensive targets a dropout notice names
+const NOTICE_TARGETS: usize = 3;
+
+const LABEL_FONT_SIZE: f32 = 13.0;
+// (from where the line ends)
+const LABEL_OFFSET: f32 = 16.0;
+
+const HEAT_COLOR: [f32; 3] = [0.95, 0.4, 0.1];
+
+/**
+    What the engine spends its time on, as heat on the code: while it's working hard (or just had a dropout), the code of every target is tinted by what it costs, with its share of the CPU at the end of its lines, so it's clear what to cut.
+*/
+#[derive(Default)]
+pub struct Heat {
+    costs: Costs,
+    load: f32,
+    last_dropout: Option<Instant>,
+}
+
+impl Heat {
+    /**
+        Catches up with the engine, returning a notice if there were new dropouts, or if it started or stopped economizing since
+    */
+    pub fn update(&mut self, engine: Option<&EngineHandle>) -> Option<String> {
+        let engine = engine?;
+
+        let costs = engine.costs();
+        let before = std::mem::replace(&mut self.costs, costs);
+        self.load = engine.callback_load();
+
+        if self.costs.overruns > before.overruns {
+            self.last_dropout = Some(Instant::now());
+
+            let names = self
+                .costs
+                .most_expensive()
+                .into_iter()
+                .take(NOTICE_TARGETS)
+                .map(|(name, cost)| format!("{} {:.0}%", name, cost * 100.0))
+                .collect::<Vec<_>>();
+
+            let notice = if names.is_empty() {
+                "audio dropout".to_string()
+            } else {
+                format!("audio dropout, most expensive: {}", names.join(", "))
+            };
+
+            // (they're the engine's, we just notice them here)
+            tracing::warn!(
+                target: "live_engine",
+                overruns = self.costs.overruns - before.overruns,
+                load = self.load,
+                "{}",
+                notice
+            );
+            return Some(notice);
+        }
+
+        let notice = match (before.economizing, self.costs.economizing) {
+            (false, true) => "economizing (thinner reverb) to keep up",
+            (true, false) => "caught up, no longer economizing",
+            _ => return None,
+        };
+
+        tracing::info!(target: "live_engine", "{}", notice);
+        Some(notice.into())
+    }
+
+    fn visible(&self) -> bool {
+        self.load >= HEAT_LOAD
+            || self.costs.economizing
+            || self
+                .last_dropout
+                .is_some_and(|at| at.elapsed() < HEAT_LINGER)
+    }
+
+    pub fn draw(
+        &self,
+        code_levels: &mut CodeLevels,
+        linedata: &LineData,
+        renderer: &Renderer,
+        overlay: &mut Overlay,
+    ) {
+        if !self.visible() {
+            return;
+        }
+
+        code_levels.sync(linedata);
+
+        let system = &renderer.system;
+        let line_height = system.char_size.1 / system.scale_factor;
+        let [r, g, b] = HEAT_COLOR;
+
+        for (lines, cost) in code_levels.line_costs(linedata, &self.costs.per_target, MIN_COST) {
+            let heat = (cost / HOT_COST).min(1.0);
+
+            for line in &lines {
+                let (min_x, y) = system.pos_to_px(Pos {
+                    row: line.row,
+                    col: line.col_start,
+                });
+                let (max_x, _) = system.pos_to_px(Pos {
+                    row: line.row,
+                    col: line.col_end,
+                });
+
+                overlay.quad(
+                    (min_x, y, max_x, y + line_height),
+                    [r, g, b, 0.06 + 0.3 * heat],
+                );
+            }
+
+            let Some(first) = lines.first() else {
+                continue;
+            };
+
+            let (x, y) = system.pos_to_px(Pos {
+                row: first.row,
+                col: linedata.line_width(first.row),
+            });
+
+            overlay.text(
+                (x + LABEL_OFFSET, y + (line_height - LABEL_FONT_SIZE) / 2.0),
+                format!("{:.0}% cpu", cost * 100.0),
+                LABEL_FONT_SIZE,
+                [r, g, b, 0.5 + 0.5 * heat],
+            );
+        }
+    }
+}
@@ -1,4 +1,5 @@
-use live_editor_state::{LineData, Token, WidgetInfo};
+use live_editor_state::{Token, WidgetInfo};
+use live_language::{lex, TokenKind};
 
 pub enum CodeToken {
     Keyword { col: usize, text: String },
@@ -6,84 +7,74 @@ pub enum CodeToken {
     Widget { col: usize, id: usize, width: usize },
 }
 
-fn is_keyword(word: &str) -> bool {
-    word == "def"
-}
-
-pub fn syntax_highlight(data: &LineData) -> Vec<(usize, Vec<CodeToken>)> {
-    data.lines()
-        .iter()
-        .map(|line| {
-            let mut col = 0;
-
-            let mut tokens: Vec<CodeToken> = vec![];
-
-            let mut space: String = "".into();
-            let mut word: String = "".into();
-
-            for &cell in line.iter() {
-                match cell {
-                    Token::Widget(WidgetInfo { id, width, .. }) => {
-                        if word.len() > 0 {
-                            tokens.push(if is_keyword(&word) {
-                                CodeToken::Keyword { col, text: word }
-                            } else {
-                                CodeToken::Text { col, text: word }
-                            });
-
-                            word = "".into();
-                        }
+/**
+    Lexes the text in between widgets, with the language's own lexer (runs of anything that's not a keyword are kept together, since that's all that's highlighted)
+*/
+fn highlight_text(col: usize, text: &str, tokens: &mut Vec<CodeToken>) {
+    let mut run = String::new();
+    let mut run_col = col;
+
+    for (kind, range) in lex(text) {
+        let token_col = col + text[..range.start].chars().count();
+
+        if kind == TokenKind::Keyword {
+            if !run.is_empty() {
+                tokens.push(CodeToken::Text {
+                    col: run_col,
+                    text: std::mem::take(&mut run),
+                });
+            }
 
-                        if space.len() > 0 {
-                            tokens.push(CodeToken::Text { col, text: space });
+            tokens.push(CodeToken::Keyword {
+                col: token_col,
+                text: text[range].to_string(),
+            });
+        } else {
+            if run.is_empty() {
+                run_col = token_col;
+            }
 
-                            space = "".into();
-                        }
+            run.push_str(&text[range]);
+        }
+    }
 
-                        tokens.push(CodeToken::Widget { col, id, width });
-                    }
-                    Token::Char(ch) => {
-                        if ch == ' ' {
-                            if word.len() > 0 {
-                                tokens.push(if is_keyword(&word) {
-                                    CodeToken::Keyword { col, text: word }
-                                } else {
-                                    CodeToken::Text { col, text: word }
-                                });
+    if !run.is_empty() {
+        tokens.push(CodeToken::Text {
+            col: run_col,
+            text: run,
+        });
+    }
+}
 
-                                word = "".into();
-                            }
+/**
+    Highlighting is per line (there's no multi-line syntax to worry about yet), so that the renderer can cache highlighted lines
+*/
+pub fn highlight_line(line: &[Token]) -> Vec<CodeToken> {
+    let mut col = 0;
 
-                            space.push(ch);
-                        } else {
-                            if space.len() > 0 {
-                                tokens.push(CodeToken::Text { col, text: space });
+    let mut tokens: Vec<CodeToken> = vec![];
 
-                                space = "".into();
-                            }
+    let mut text: String = "".into();
+    let mut text_col = 0;
 
-                            word.push(ch);
-                        }
-                    }
-                }
+    for &cell in line.iter() {
+        match cell {
+            Token::Widget(WidgetInfo { id, width, .. }) => {
+                highlight_text(text_col, &text, &mut tokens);
+                text.clear();
 
-                col += cell.width();
+                tokens.push(CodeToken::Widget { col, id, width });
+                text_col = col + width;
             }
-
-            if word.len() > 0 {
-                tokens.push(if is_keyword(&word) {
-                    CodeToken::Keyword { col, text: word }
-                } else {
-                    CodeToken::Text { col, text: word }
-                });
+            Token::Char(ch) => {
+                text.push(ch);
             }
+        }
 
-            if space.len() > 0 {
-                tokens.push(CodeToken::Text { col, text: space });
-            }
+        col += cell.width();
+    }
+
+    highlight_text(text_col, &text, &mut tokens);
 
-            tokens
-        })
-        .enumerate()
-        .collect()
+    tokens
 }
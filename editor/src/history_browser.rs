@@ -0,0 +1,162 @@
+use live_editor_state::History;
+
+use crate::{render::Overlay, util::format_ago};
+
+const BROWSER_WIDTH: f32 = 360.0;
+const BROWSER_TOP: f32 = 64.0;
+const HEADER_HEIGHT: f32 = 36.0;
+const ROW_HEIGHT: f32 = 24.0;
+const MAX_ROWS: usize = 16;
+const BRANCH_WIDTH: f32 = 14.0;
+const FONT_SIZE: f32 = 14.0;
+
+const BACKDROP_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.08];
+const BROWSER_COLOR: [f32; 4] = [0.99, 0.99, 0.98, 1.0];
+const SELECTED_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.08];
+const TEXT_COLOR: [f32; 4] = [0.02, 0.02, 0.02, 1.0];
+const DIM_TEXT_COLOR: [f32; 4] = [0.02, 0.02, 0.02, 0.45];
+
+/**
+    Which column every history entry is drawn in: each branch off the main line of edits gets its own column, like in Vim's undotree
+*/
+fn branch_columns(history: &History) -> Vec<usize> {
+    let entries = history.entries();
+    let mut columns = vec![0; entries.len()];
+
+    // (parents always come before their children)
+    for (i, entry) in entries.iter().enumerate() {
+        for (k, &child) in entry.children.iter().enumerate() {
+            columns[child] = columns[i] + k;
+        }
+    }
+
+    columns
+}
+
+/**
+    The undo tree browser (Cmd+Shift+H): lists every state the document has been in, newest first, so you can go back to any of them, also ones on a branch that was abandoned by undoing and then editing. Moving through the list immediately shows that state in the editor.
+*/
+pub struct HistoryBrowser {
+    open: bool,
+}
+
+impl HistoryBrowser {
+    pub fn new() -> Self {
+        Self { open: false }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+    }
+
+    /**
+        The entry indices that are listed, newest first, scrolled so that the current one is visible
+    */
+    fn rows(&self, history: &History) -> Vec<usize> {
+        let newest = history.entries().len() - 1;
+        let current_row = newest - history.current();
+        let skip = current_row.saturating_sub(MAX_ROWS - 1);
+
+        (0..=newest).rev().skip(skip).take(MAX_ROWS).collect()
+    }
+
+    fn bounds(&self, history: &History, (width, _): (f32, f32)) -> (f32, f32, f32, f32) {
+        let rows = self.rows(history).len();
+        let min_x = ((width - BROWSER_WIDTH) / 2.0).max(0.0);
+
+        (
+            min_x,
+            BROWSER_TOP,
+            min_x + BROWSER_WIDTH,
+            BROWSER_TOP + HEADER_HEIGHT + rows as f32 * ROW_HEIGHT + 6.0,
+        )
+    }
+
+    /**
+        Which entry was clicked, if any. (`None` if the click was outside of the browser.)
+    */
+    pub fn hit_test(
+        &self,
+        history: &History,
+        window_size: (f32, f32),
+        (x, y): (f32, f32),
+    ) -> Option<Option<usize>> {
+        let (min_x, min_y, max_x, max_y) = self.bounds(history, window_size);
+        if x < min_x || x > max_x || y < min_y || y > max_y {
+            return None;
+        }
+
+        let i = ((y - min_y - HEADER_HEIGHT) / ROW_HEIGHT).floor();
+        if i < 0.0 {
+            return Some(None);
+        }
+
+        Some(self.rows(history).get(i as usize).copied())
+    }
+
+    pub fn draw(&self, history: &History, window_size: (f32, f32), overlay: &mut Overlay) {
+        let (min_x, min_y, max_x, max_y) = self.bounds(history, window_size);
+        let text_y = |top: f32, height: f32| top + (height - FONT_SIZE) / 2.0;
+
+        overlay.quad((0.0, 0.0, window_size.0, window_size.1), BACKDROP_COLOR);
+        overlay.quad((min_x, min_y, max_x, max_y), BROWSER_COLOR);
+
+        overlay.bold_text(
+            (min_x + 12.0, text_y(min_y, HEADER_HEIGHT)),
+            "Undo history",
+            FONT_SIZE,
+            TEXT_COLOR,
+        );
+
+        overlay.text(
+            (max_x - 130.0, text_y(min_y, HEADER_HEIGHT)),
+            "↑/↓, esc to close",
+            FONT_SIZE,
+            DIM_TEXT_COLOR,
+        );
+
+        let columns = branch_columns(history);
+        let entries = history.entries();
+
+        for (row, i) in self.rows(history).into_iter().enumerate() {
+            let entry = &entries[i];
+            let top = min_y + HEADER_HEIGHT + row as f32 * ROW_HEIGHT;
+            let y = text_y(top, ROW_HEIGHT);
+            let current = i == history.current();
+
+            if current {
+                overlay.quad((min_x, top, max_x, top + ROW_HEIGHT), SELECTED_COLOR);
+            }
+
+            overlay.text(
+                (min_x + 12.0 + columns[i] as f32 * BRANCH_WIDTH, y),
+                if current { "●" } else { "○" },
+                FONT_SIZE,
+                TEXT_COLOR,
+            );
+
+            let description = match (entry.parent, entry.changed_row) {
+                (None, _) => "original".to_string(),
+                (Some(_), Some(row)) => format!("line {}  {:+}", row + 1, entry.delta),
+                (Some(_), None) => format!("{:+}", entry.delta),
+            };
+
+            overlay.text((min_x + 110.0, y), description, FONT_SIZE, TEXT_COLOR);
+
+            overlay.text(
+                (max_x - 80.0, y),
+                format_ago(entry.at.elapsed()),
+                FONT_SIZE,
+                DIM_TEXT_COLOR,
+            );
+        }
+    }
+}
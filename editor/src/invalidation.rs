@@ -0,0 +1,52 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use winit::event_loop::EventLoopProxy;
+
+use crate::ui::WidgetEvent;
+
+#[derive(Debug, Clone, Copy)]
+pub enum UserEvent {
+    Widget(WidgetEvent),
+    /// Something (probably on another thread) changed, that needs to be drawn
+    Invalidate,
+}
+
+impl From<WidgetEvent> for UserEvent {
+    fn from(event: WidgetEvent) -> Self {
+        Self::Widget(event)
+    }
+}
+
+/**
+    A cheap, cloneable handle that anything can use to say "what's on screen is out of date", including other threads (background checks, audio meters, etc.). It wakes up the event loop if it's waiting, and multiple invalidations before the next redraw are coalesced into one.
+*/
+#[derive(Clone)]
+pub struct Invalidator {
+    proxy: EventLoopProxy<UserEvent>,
+    pending: Arc<AtomicBool>,
+}
+
+impl Invalidator {
+    pub fn new(proxy: EventLoopProxy<UserEvent>) -> Self {
+        Self {
+            proxy,
+            pending: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn invalidate(&self) {
+        if !self.pending.swap(true, Ordering::SeqCst) {
+            let _ = self.proxy.send_event(UserEvent::Invalidate);
+        }
+    }
+
+    /**
+        Whether there was an invalidation since the last time this was called
+    */
+    pub fn take(&self) -> bool {
+        self.pending.swap(false, Ordering::SeqCst)
+    }
+}
@@ -1,37 +1,173 @@
-#![feature(let_chains)]
-#![feature(slice_group_by)]
-
+mod audio_cache;
+mod audio_settings;
+mod backups;
+mod bookmarks;
+mod bounce;
+mod branch_picker;
+mod clip_view;
 mod clipboard;
+mod code_levels;
+#[cfg(not(target_arch = "wasm32"))]
+mod collab;
+mod color_swatches;
+mod command_palette;
+mod commands;
+mod commit_prompt;
+mod compile;
+mod completion;
+mod console;
+mod context_menu;
+mod diff_view;
+mod doc_hover;
+mod eval_errors;
+mod file_drop;
+mod font;
+mod fuzzy;
+#[cfg(not(target_arch = "wasm32"))]
+mod git;
+mod headless;
+mod heat;
 mod highlight;
+mod history_browser;
+mod invalidation;
+mod library;
+mod loop_tempo;
+mod mixer;
+mod morph;
+mod musical_typing;
+mod outline;
+mod panel;
+mod pattern;
+mod pending_swaps;
+mod problems;
+mod project;
+mod project_search;
+mod rename_prompt;
 mod render;
+mod sample_browser;
+mod sample_packs;
+#[cfg(not(target_arch = "wasm32"))]
+mod sample_watcher;
+mod search;
+mod session;
+mod signal_views;
+mod startup;
+mod status_bar;
+mod storage;
+mod symbol_picker;
 mod ui;
+#[cfg(not(target_arch = "wasm32"))]
+mod updates;
 mod util;
+mod watches;
 mod widget;
+mod widget_help;
 mod widgets;
+mod window_placement;
 
+use audio_settings::{AudioSettings, AudioSettingsPanel};
+use backups::{relink_widgets, Backup, BackupPicker, Backups};
+use bookmarks::draw_bookmarks;
+use bounce::{BounceJob, FROZEN_DIR};
+use branch_picker::{BranchPick, BranchPicker};
+use clip_view::{ClipView, ClipViewHit};
 use clipboard::Clipboard;
-use live_editor_state::{Direction, EditorState, LineData, MoveVariant, Pos, Token};
-use render::Renderer;
+use code_levels::CodeLevels;
+#[cfg(not(target_arch = "wasm32"))]
+use collab::{Collab, CollabEvent, Message, GUEST_SITE, HOST_SITE};
+use color_swatches::{ColorSwatches, ColorSwatchesHit};
+use command_palette::CommandPalette;
+use commands::EditorCommand;
+use commit_prompt::CommitPrompt;
+use compile::{Compiler, Samples};
+use completion::Completion;
+use console::{Console, ConsoleHit};
+use context_menu::{ContextMenu, ContextMenuHit, MenuItem};
+use diff_view::DiffView;
+use doc_hover::DocHover;
+use eval_errors::{EvalErrors, EvalErrorsHit, QuickFix};
+use file_drop::FileDrop;
+use font::FontSettings;
+#[cfg(not(target_arch = "wasm32"))]
+use git::Git;
+use heat::Heat;
+use history_browser::HistoryBrowser;
+use invalidation::{Invalidator, UserEvent};
+use library::{LibraryPanel, Preset};
+use loop_tempo::{format_bpm, LoopTempo, LoopTempoJob, SAME_TEMPO, STRETCHED_DIR};
+use musical_typing::MusicalTyping;
+use live_editor_state::{
+    Direction, EditorState, LineData, LineSelection, MoveVariant, Pos, Range, Token,
+};
+use live_engine::{
+    clamp_swing, input_devices, output_devices, DeviceSettings, Engine, EngineHandle, MidiEvent,
+    Quantize, TapTempo, MORPH, STRAIGHT,
+};
+use live_language::{
+    clips, definition_at, evaluate_source, evaluate_source_in, extract_definition, format_color,
+    latches, lint, outline, overlay_statements, performables, rename_symbol, statement_at,
+    syntax_errors, Evaluation, LintConfig, LintKind, SymbolKind, Timer, Timing, Value,
+};
+use mixer::Mixer;
+use morph::{MorphHit, Snapshots, SLOTS};
+use outline::{Outline, OutlinePanel};
+use panel::ListHit;
+use pattern::NotePattern;
+use pending_swaps::PendingSwaps;
+use problems::{load_lint_config, Problems, ProblemsPanel};
+use project::SESSION_FILE;
+use project_search::ProjectSearch;
+use rename_prompt::RenamePrompt;
+use render::{Hit, Overlay, Renderer};
+use rfd::{FileDialog, MessageButtons, MessageDialog, MessageLevel};
+use sample_browser::{audition_node, stop_audition, SampleBrowser, SampleDrag};
+use sample_packs::{check_packs, SamplePack, Workspace};
+#[cfg(not(target_arch = "wasm32"))]
+use sample_watcher::SampleWatcher;
+use session::{apply_edit, replays, SessionEvent, SessionRecorder, SessionReplay, SESSIONS_DIR};
+use signal_views::SignalViews;
+use startup::{Loading, StartupProfile};
+use status_bar::StatusBar;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
 use std::time::{Duration, Instant, SystemTime};
-use ui::WidgetEvent;
-use widget::WidgetManager;
-use widgets::sample::SampleWidget;
+use symbol_picker::SymbolPicker;
+use ui::{WidgetAction, WidgetEvent, WidgetKey};
+#[cfg(not(target_arch = "wasm32"))]
+use updates::UpdateChecker;
+use util::{loc_to_pos, span_to_range};
+use watches::{WatchPanel, Watches};
+use widget::{WidgetManager, WidgetValue};
+use widget_help::WidgetHelp;
+use widgets::{
+    knob::{KnobStyle, KnobWidget},
+    matrix::MatrixWidget,
+    piano_roll::PianoRollWidget,
+    sample::SampleWidget,
+};
+use window_placement::WindowPlacements;
 use winit::dpi::{LogicalPosition, LogicalSize, Size};
-use winit::event::{KeyEvent, MouseButton};
+use winit::event::{KeyEvent, MouseButton, MouseScrollDelta, TouchPhase};
 use winit::event_loop::EventLoopBuilder;
+#[cfg(target_os = "macos")]
 use winit::platform::macos::WindowBuilderExtMacOS;
 use winit::{
     event::{ElementState, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
-    keyboard::Key,
-    window::WindowBuilder,
+    keyboard::{Key, KeyCode},
+    window::{CursorIcon, Fullscreen, WindowBuilder},
 };
 
 struct Context {
     bounds: (f32, f32, f32, f32),
     mouse_at: Option<(f32, f32)>,
+    // (what's being dragged onto the window, from the OS)
+    file_drag: Option<FileDrop>,
     shift: bool,
     alt: bool,
+    meta: bool,
+    ctrl: bool,
     meta_or_ctrl: bool,
 }
 
@@ -40,50 +176,137 @@ impl Context {
         Self {
             bounds,
             mouse_at: None,
+            file_drag: None,
 
             shift: false,
             alt: false,
+            meta: false,
+            ctrl: false,
             meta_or_ctrl: false,
         }
     }
+
+    fn set_meta(&mut self, meta: bool) {
+        self.meta = meta;
+        self.meta_or_ctrl = self.meta || self.ctrl;
+    }
+
+    fn set_ctrl(&mut self, ctrl: bool) {
+        self.ctrl = ctrl;
+        self.meta_or_ctrl = self.meta || self.ctrl;
+    }
+}
+
+// (so that `live check` lints the same way the editor does)
+pub use problems::load_lint_config;
+
+// (so that `live render` bounces the same way the editor does, and renders recorded sessions)
+pub use bounce::{bounce, document_root, BounceSettings};
+pub use session::{render_session, Session, SESSION_EXTENSION};
+
+// (and `live play` plays it, on the devices the editor plays on)
+pub use headless::play_document;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use collab::Sharing;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn run(sharing: Option<Sharing>) {
+    pollster::block_on(start(sharing));
 }
 
+/**
+    In the browser, the editor starts as soon as the module's loaded, in a canvas that's added to the page
+*/
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen::prelude::wasm_bindgen(start)]
 pub fn run() {
-    env_logger::init();
+    wasm_bindgen_futures::spawn_local(start());
+}
 
-    let event_loop: EventLoop<WidgetEvent> = EventLoopBuilder::with_user_event().build();
+async fn start(#[cfg(not(target_arch = "wasm32"))] sharing: Option<Sharing>) {
+    let mut profile = StartupProfile::start();
+    console::init();
+
+    let event_loop: EventLoop<UserEvent> = EventLoopBuilder::with_user_event().build();
     let proxy = event_loop.create_proxy();
-    let window = WindowBuilder::new()
+    let invalidator = Invalidator::new(event_loop.create_proxy());
+
+    let mut window_placements = WindowPlacements::load();
+    let monitor_setup = window_placement::monitor_setup(event_loop.available_monitors());
+
+    let mut window_builder = WindowBuilder::new()
         .with_title("")
-        .with_fullsize_content_view(true)
-        .with_titlebar_transparent(true)
         .with_active(true)
         .with_inner_size(Size::Logical(LogicalSize {
             width: 900.0,
             height: 600.0,
         }))
-        .with_resizable(true)
-        .build(&event_loop)
-        .unwrap();
+        .with_resizable(true);
+
+    // (the code runs all the way up, under the traffic lights)
+    #[cfg(target_os = "macos")]
+    {
+        window_builder = window_builder
+            .with_fullsize_content_view(true)
+            .with_titlebar_transparent(true);
+    }
+
+    if let Some(placement) = window_placements
+        .get(&monitor_setup)
+        .or_else(|| window_placement::default_placement(event_loop.primary_monitor()))
+    {
+        window_builder = window_builder
+            .with_position(placement.position())
+            .with_inner_size(placement.size());
+    }
+
+    let window = window_builder.build(&event_loop).unwrap();
+    profile.phase("window");
 
-    let mut renderer = pollster::block_on(render::Renderer::new(&window));
+    #[cfg(target_arch = "wasm32")]
+    {
+        use winit::platform::web::WindowExtWebSys;
 
-    let mut editor = Editor::new();
+        web_sys::window()
+            .and_then(|window| window.document())
+            .and_then(|document| document.body())
+            .zip(window.canvas())
+            .and_then(|(body, canvas)| body.append_child(&canvas).ok())
+            .expect("could not add the canvas to the page");
+    }
+
+    let mut renderer = render::Renderer::new(&window, FontSettings::load()).await;
+    profile.phase("renderer");
+
+    // (the audio device, samples and sample packs load in the background, so we can draw and type right away)
+    let mut editor = Editor::new(&invalidator);
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(sharing) = sharing {
+        editor.start_collab(sharing, &invalidator);
+    }
+    profile.phase("editor");
     let mut ctx = Context::new((0.0, 0.0, renderer.width() as f32, renderer.height() as f32));
 
     let mut curr_press: Option<PressEventBuilder> = None;
 
+    #[cfg(not(target_arch = "wasm32"))]
+    let mut updates = UpdateChecker::start(invalidator.clone());
+
     // FPS and window updating:
     let mut then = SystemTime::now();
     let mut now = SystemTime::now();
     let mut fps = 0;
-    // change '60.0' if you want different FPS cap
+    // only relevant while something's animating, otherwise we just redraw when something changed
     let target_framerate = Duration::from_secs_f64(1.0 / 60.0);
-    let mut delta_time = Instant::now();
 
-    event_loop.run(move |event, _, control_flow| {
-        *control_flow = ControlFlow::Poll;
+    // (the browser runs the event loop itself, so there, we hand it over instead)
+    #[cfg(not(target_arch = "wasm32"))]
+    let run = EventLoop::run;
+    #[cfg(target_arch = "wasm32")]
+    let run = winit::platform::web::EventLoopExtWebSys::spawn;
 
+    run(event_loop, move |event, _, control_flow| {
         match event {
             winit::event::Event::WindowEvent { event, .. } => match event {
                 WindowEvent::Resized(size)
@@ -92,9 +315,53 @@ pub fn run() {
                     ..
                 } => {
                     renderer.resize(size);
+                    window.request_redraw();
                     ctx.bounds = (0.0, 0.0, renderer.width() as f32, renderer.height() as f32);
+
+                    // don't remember the fullscreen size as the "windowed" size
+                    if window.fullscreen().is_none() && let Some(placement) = window_placement::placement_from_window(&window) {
+                        window_placements.remember(monitor_setup.clone(), placement);
+                    }
+                }
+                WindowEvent::CloseRequested => {
+                    window_placements.save();
+                    editor.backups.backup(editor.editor_state.linedata());
+                    *control_flow = ControlFlow::Exit;
+                }
+                // in musical typing mode, the keyboard's a piano
+                WindowEvent::KeyboardInput {
+                    event:
+                        KeyEvent {
+                            physical_key,
+                            state,
+                            repeat,
+                            ..
+                        },
+                    ..
+                } if editor.musical_typing_captures(physical_key, &ctx) => {
+                    editor.musical_typing_key(physical_key, state == ElementState::Pressed, repeat);
+                }
+                // Cmd+Alt+1..9 pastes from a numbered register, and with Shift, copies to it
+                WindowEvent::KeyboardInput {
+                    event:
+                        KeyEvent {
+                            physical_key,
+                            state: ElementState::Pressed,
+                            ..
+                        },
+                    ..
+                } if ctx.meta_or_ctrl && ctx.alt && register_key(physical_key).is_some() => {
+                    if let Some(register) = register_key(physical_key) {
+                        editor.run_command(
+                            if ctx.shift {
+                                EditorCommand::CopyToRegister(register)
+                            } else {
+                                EditorCommand::PasteFromRegister(register)
+                            },
+                            &mut renderer,
+                        );
+                    }
                 }
-                WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
                 WindowEvent::KeyboardInput {
                     event:
                         KeyEvent {
@@ -102,6 +369,82 @@ pub fn run() {
                         },
                     ..
                 } => match (logical_key.clone(), state) {
+                    // the command palette captures all typing while it's open
+                    (key, ElementState::Pressed)
+                        if editor.command_palette.is_open() && !is_modifier_key(&key) =>
+                    {
+                        editor.command_palette_key(key, &ctx, &mut renderer);
+                    }
+                    // and so does the project search
+                    (key, ElementState::Pressed)
+                        if editor.project_search.is_open() && !is_modifier_key(&key) =>
+                    {
+                        editor.project_search_key(key, &ctx);
+                    }
+                    // and so does the symbol picker
+                    (key, ElementState::Pressed)
+                        if editor.symbol_picker.is_open() && !is_modifier_key(&key) =>
+                    {
+                        editor.symbol_picker_key(key, &ctx);
+                    }
+                    // and so does the library panel
+                    (key, ElementState::Pressed)
+                        if editor.library_panel.is_open() && !is_modifier_key(&key) =>
+                    {
+                        editor.library_panel_key(key, &ctx);
+                    }
+                    // and so does the undo history browser
+                    (key, ElementState::Pressed)
+                        if editor.history_browser.is_open() && !is_modifier_key(&key) =>
+                    {
+                        editor.history_browser_key(key);
+                    }
+                    // and the backup picker
+                    (key, ElementState::Pressed)
+                        if editor.backup_picker.is_open() && !is_modifier_key(&key) =>
+                    {
+                        editor.backup_picker_key(key);
+                    }
+                    // and the commit and rename prompts, and the snapshot picker
+                    (key, ElementState::Pressed)
+                        if editor.commit_prompt.is_open() && !is_modifier_key(&key) =>
+                    {
+                        editor.commit_prompt_key(key, &ctx);
+                    }
+                    (key, ElementState::Pressed)
+                        if editor.rename_prompt.is_open() && !is_modifier_key(&key) =>
+                    {
+                        editor.rename_prompt_key(key, &ctx);
+                    }
+                    (key, ElementState::Pressed)
+                        if editor.branch_picker.is_open() && !is_modifier_key(&key) =>
+                    {
+                        editor.branch_picker_key(key, &ctx);
+                    }
+                    // and the audio settings
+                    (key, ElementState::Pressed)
+                        if editor.audio_settings.is_open() && !is_modifier_key(&key) =>
+                    {
+                        editor.audio_settings_key(key);
+                    }
+                    // and the context menu (it's on top of whatever has the keyboard otherwise)
+                    (key, ElementState::Pressed)
+                        if editor.context_menu.is_open() && !is_modifier_key(&key) =>
+                    {
+                        editor.context_menu_key(key, &mut renderer);
+                    }
+                    // and a focused widget captures all keys, until Esc
+                    (key, ElementState::Pressed)
+                        if editor.widget_manager.focused().is_some() && !is_modifier_key(&key) =>
+                    {
+                        editor.focused_widget_key(key, &ctx);
+                    }
+                    // the completions only take the keys that pick one (the typing goes on)
+                    (key, ElementState::Pressed)
+                        if editor.completion.is_open() && Completion::captures(&key) =>
+                    {
+                        editor.completion_key(key);
+                    }
                     (Key::Escape, ElementState::Pressed) => {
                         // *control_flow = ControlFlow::Exit;
                         editor.editor_state.deselect();
@@ -120,9 +463,47 @@ pub fn run() {
                             editor.editor_state.tab();
                         }
                     }
+                    (Key::F7, ElementState::Pressed) if editor.diff_view.is_open() => {
+                        editor.next_change(!ctx.shift);
+                    }
+                    (Key::F2, ElementState::Pressed) => {
+                        editor.run_command(
+                            if ctx.meta_or_ctrl {
+                                EditorCommand::ToggleBookmark
+                            } else if ctx.shift {
+                                EditorCommand::PreviousBookmark
+                            } else {
+                                EditorCommand::NextBookmark
+                            },
+                            &mut renderer,
+                        );
+                    }
+                    // (Shift+F5/F6/F7 capture snapshot A/B/C)
+                    (key @ (Key::F5 | Key::F6 | Key::F7), ElementState::Pressed) if ctx.shift => {
+                        let slot = match key {
+                            Key::F5 => 0,
+                            Key::F6 => 1,
+                            _ => 2,
+                        };
+                        editor.run_command(EditorCommand::CaptureSnapshot(slot), &mut renderer);
+                    }
+                    (Key::F12, ElementState::Pressed) => {
+                        editor.run_command(EditorCommand::GoToDefinition, &mut renderer);
+                    }
+                    (Key::Space, ElementState::Pressed) if ctx.ctrl => {
+                        editor.run_command(EditorCommand::Complete, &mut renderer);
+                    }
                     (Key::Space, ElementState::Pressed) => {
                         editor.editor_state.write(" ");
                     }
+                    (Key::Enter, ElementState::Pressed) if ctx.meta_or_ctrl => {
+                        editor.run_command(EditorCommand::Evaluate, &mut renderer);
+                    }
+                    (Key::Enter, ElementState::Pressed)
+                        if editor.editor_state.selected_widget().is_some() =>
+                    {
+                        editor.focus_selected_widget();
+                    }
                     (Key::Enter, ElementState::Pressed) => {
                         editor.editor_state.write("\n");
                     }
@@ -138,13 +519,14 @@ pub fn run() {
                     (Key::ArrowUp | Key::ArrowDown, ElementState::Pressed)
                         if ctx.meta_or_ctrl && ctx.alt =>
                     {
-                        editor
-                            .editor_state
-                            .add_caret_vertically(match logical_key.clone() {
-                                Key::ArrowUp => Direction::Up,
-                                Key::ArrowDown => Direction::Down,
+                        editor.run_command(
+                            match logical_key.clone() {
+                                Key::ArrowUp => EditorCommand::AddCaretAbove,
+                                Key::ArrowDown => EditorCommand::AddCaretBelow,
                                 _ => unreachable!(),
-                            });
+                            },
+                            &mut renderer,
+                        );
                     }
                     (
                         Key::ArrowUp | Key::ArrowRight | Key::ArrowDown | Key::ArrowLeft,
@@ -168,23 +550,145 @@ pub fn run() {
                             },
                         );
                     }
+                    (Key::Character(s), ElementState::Pressed) if ctx.meta && ctx.ctrl => {
+                        if s.as_str() == "f" {
+                            // toggle fullscreen on whichever monitor the window is currently on
+                            if window.fullscreen().is_some() {
+                                window.set_fullscreen(None);
+                            } else {
+                                window.set_fullscreen(Some(Fullscreen::Borderless(None)));
+                            }
+                        } else if let Ok(n @ 1..=9) = s.as_str().parse::<usize>() {
+                            // "move to projector display": zen-mode fullscreen on the n-th monitor
+                            if let Some(monitor) = window.available_monitors().nth(n - 1) {
+                                window.set_fullscreen(Some(Fullscreen::Borderless(Some(monitor))));
+                            } else {
+                                tracing::warn!("No monitor #{}", n);
+                            }
+                        }
+                    }
                     (Key::Character(s), ElementState::Pressed) => {
-                        if s.as_str() == "c" && ctx.meta_or_ctrl {
+                        if s.as_str().eq_ignore_ascii_case("p") && ctx.meta_or_ctrl && ctx.shift {
+                            editor.open_command_palette();
+                        } else if s.as_str().eq_ignore_ascii_case("c") && ctx.meta_or_ctrl && ctx.shift {
+                            editor.run_command(EditorCommand::Commit, &mut renderer);
+                        } else if s.as_str() == "c" && ctx.meta_or_ctrl {
                             // todo improve (ctrl/meta depending on OS)
-                            editor.clipboard.write(editor.editor_state.copy());
+                            editor.run_command(EditorCommand::Copy, &mut renderer);
+                        } else if s.as_str().eq_ignore_ascii_case("x") && ctx.meta_or_ctrl && ctx.shift {
+                            editor.run_command(EditorCommand::ExtractDefinition, &mut renderer);
                         } else if s.as_str() == "x" && ctx.meta_or_ctrl {
                             // todo improve (ctrl/meta depending on OS)
-                            editor.clipboard.write(editor.editor_state.cut());
+                            editor.run_command(EditorCommand::Cut, &mut renderer);
+                        } else if s.as_str().eq_ignore_ascii_case("v") && ctx.meta_or_ctrl && ctx.shift {
+                            editor.paste(true);
                         } else if s.as_str() == "v" && ctx.meta_or_ctrl {
                             // todo improve (ctrl/meta depending on OS)
-                            if let Some(data) = editor.clipboard.read() {
-                                editor.editor_state.paste(data);
-                            }
+                            editor.run_command(EditorCommand::Paste, &mut renderer);
                         } else if s.as_str() == "d" && ctx.meta_or_ctrl {
                             // todo improve (ctrl/meta depending on OS)
-                            editor.editor_state.word_select();
+                            editor.run_command(EditorCommand::SelectWord, &mut renderer);
                         } else if s.as_str() == "a" && ctx.meta_or_ctrl {
-                            editor.editor_state.select_all();
+                            editor.run_command(EditorCommand::SelectAll, &mut renderer);
+                        } else if s.as_str().eq_ignore_ascii_case("z") && ctx.meta_or_ctrl {
+                            editor.run_command(
+                                if ctx.shift {
+                                    EditorCommand::Redo
+                                } else {
+                                    EditorCommand::Undo
+                                },
+                                &mut renderer,
+                            );
+                        } else if s.as_str().eq_ignore_ascii_case("h") && ctx.meta_or_ctrl && ctx.shift {
+                            editor.run_command(EditorCommand::BrowseHistory, &mut renderer);
+                        } else if s.as_str().eq_ignore_ascii_case("r") && ctx.meta_or_ctrl && ctx.shift {
+                            editor.run_command(EditorCommand::RestoreBackup, &mut renderer);
+                        } else if s.as_str() == "r" && ctx.meta_or_ctrl {
+                            editor.run_command(EditorCommand::RenameSymbol, &mut renderer);
+                        } else if s.as_str().eq_ignore_ascii_case("s") && ctx.meta_or_ctrl && ctx.shift {
+                            editor.run_command(EditorCommand::SearchProject, &mut renderer);
+                        } else if s.as_str().eq_ignore_ascii_case("y") && ctx.meta_or_ctrl && ctx.shift {
+                            editor.run_command(EditorCommand::RecordSession, &mut renderer);
+                        } else if s.as_str().eq_ignore_ascii_case("j") && ctx.meta_or_ctrl && ctx.shift {
+                            editor.run_command(EditorCommand::ReplaySession, &mut renderer);
+                        } else if s.as_str().eq_ignore_ascii_case("a") && ctx.meta_or_ctrl && ctx.shift {
+                            editor.run_command(EditorCommand::SaveToLibrary, &mut renderer);
+                        } else if s.as_str().eq_ignore_ascii_case("t") && ctx.meta_or_ctrl && ctx.shift {
+                            editor.run_command(EditorCommand::OpenLibrary, &mut renderer);
+                        } else if s.as_str() == "o" && ctx.ctrl && !ctx.shift {
+                            editor.run_command(EditorCommand::JumpBack, &mut renderer);
+                        } else if s.as_str() == "i" && ctx.ctrl && !ctx.shift {
+                            editor.run_command(EditorCommand::JumpForward, &mut renderer);
+                        } else if s.as_str().eq_ignore_ascii_case("o") && ctx.meta_or_ctrl && ctx.shift {
+                            editor.run_command(EditorCommand::GoToSymbol, &mut renderer);
+                        } else if s.as_str().eq_ignore_ascii_case("f") && ctx.meta_or_ctrl && ctx.shift {
+                            editor.run_command(EditorCommand::FormatDocument, &mut renderer);
+                        } else if s.as_str().eq_ignore_ascii_case("k") && ctx.meta_or_ctrl {
+                            editor.run_command(
+                                if ctx.shift {
+                                    EditorCommand::InsertSlider
+                                } else {
+                                    EditorCommand::InsertKnob
+                                },
+                                &mut renderer,
+                            );
+                        } else if s.as_str().eq_ignore_ascii_case("n") && ctx.meta_or_ctrl && ctx.shift {
+                            editor.run_command(EditorCommand::InsertPianoRoll, &mut renderer);
+                        } else if s.as_str().eq_ignore_ascii_case("l") && ctx.meta_or_ctrl && ctx.shift {
+                            editor.run_command(EditorCommand::ToggleLevels, &mut renderer);
+                        } else if s.as_str().eq_ignore_ascii_case("m") && ctx.meta_or_ctrl && ctx.shift {
+                            editor.run_command(EditorCommand::ToggleMusicalTyping, &mut renderer);
+                        } else if s.as_str().eq_ignore_ascii_case("w") && ctx.meta_or_ctrl && ctx.shift {
+                            editor.run_command(EditorCommand::ToggleWatch, &mut renderer);
+                        } else if s.as_str().eq_ignore_ascii_case("b") && ctx.meta_or_ctrl && ctx.shift {
+                            editor.run_command(EditorCommand::CycleQuantize, &mut renderer);
+                        } else if s.as_str().eq_ignore_ascii_case("e") && ctx.meta_or_ctrl && ctx.shift {
+                            editor.run_command(EditorCommand::Bounce, &mut renderer);
+                        } else if s.as_str().eq_ignore_ascii_case("u") && ctx.meta_or_ctrl && ctx.shift {
+                            editor.run_command(EditorCommand::Freeze, &mut renderer);
+                        } else if s.as_str().eq_ignore_ascii_case("d") && ctx.meta_or_ctrl && ctx.shift {
+                            editor.run_command(EditorCommand::ToggleDiff, &mut renderer);
+                        } else if s.as_str().eq_ignore_ascii_case("g") && ctx.meta_or_ctrl && ctx.shift {
+                            editor.run_command(EditorCommand::SwitchSnapshot, &mut renderer);
+                        } else if s.as_str().eq_ignore_ascii_case("i") && ctx.meta_or_ctrl && ctx.shift {
+                            editor.run_command(EditorCommand::ShowPluginEditor, &mut renderer);
+                        } else if (s.as_str() == "[" || s.as_str() == "{") && ctx.meta_or_ctrl && ctx.shift {
+                            // (shift-[ is { on most layouts)
+                            editor.run_command(EditorCommand::SwingLess, &mut renderer);
+                        } else if (s.as_str() == "]" || s.as_str() == "}") && ctx.meta_or_ctrl && ctx.shift {
+                            editor.run_command(EditorCommand::SwingMore, &mut renderer);
+                        } else if s.as_str() == "t" && ctx.meta_or_ctrl && !ctx.shift {
+                            editor.run_command(EditorCommand::TapTempo, &mut renderer);
+                        } else if (s.as_str() == "." || s.as_str() == ">") && ctx.meta_or_ctrl {
+                            // (shift-. is > on most layouts)
+                            editor.run_command(
+                                if ctx.shift {
+                                    EditorCommand::Panic
+                                } else {
+                                    EditorCommand::Hush
+                                },
+                                &mut renderer,
+                            );
+                        } else if s.as_str() == ";" && ctx.meta_or_ctrl {
+                            editor.run_command(EditorCommand::Reseed, &mut renderer);
+                        } else if s.as_str() == "," && ctx.meta_or_ctrl {
+                            editor.run_command(EditorCommand::AudioSettings, &mut renderer);
+                        } else if s.as_str() == "u" && ctx.meta_or_ctrl {
+                            #[cfg(not(target_arch = "wasm32"))]
+                            updates.show_changelog();
+                        } else if s.as_str() == "\\" && ctx.meta_or_ctrl {
+                            editor.run_command(EditorCommand::ToggleSplit, &mut renderer);
+                        } else if s.as_str() == "j" && ctx.meta_or_ctrl {
+                            editor.run_command(EditorCommand::ToggleConsole, &mut renderer);
+                        } else if s.as_str() == "l" && ctx.meta_or_ctrl {
+                            editor.run_command(EditorCommand::ToggleClipView, &mut renderer);
+                        } else if (s.as_str() == "=" || s.as_str() == "+") && ctx.meta_or_ctrl {
+                            // (shift-= is + on most layouts)
+                            editor.run_command(EditorCommand::ZoomIn, &mut renderer);
+                        } else if (s.as_str() == "-" || s.as_str() == "_") && ctx.meta_or_ctrl {
+                            editor.run_command(EditorCommand::ZoomOut, &mut renderer);
+                        } else if s.as_str() == "0" && ctx.meta_or_ctrl {
+                            editor.run_command(EditorCommand::ResetZoom, &mut renderer);
                         } else {
                             editor.editor_state.write(s.as_str());
                         }
@@ -202,22 +706,22 @@ pub fn run() {
                         ctx.shift = false;
                     }
                     (Key::Meta, ElementState::Pressed) => {
-                        ctx.meta_or_ctrl = true;
+                        ctx.set_meta(true);
                     }
                     (Key::Meta, ElementState::Released) => {
-                        ctx.meta_or_ctrl = false;
+                        ctx.set_meta(false);
                     }
                     (Key::Super, ElementState::Pressed) => {
-                        ctx.meta_or_ctrl = true;
+                        ctx.set_meta(true);
                     }
                     (Key::Super, ElementState::Released) => {
-                        ctx.meta_or_ctrl = false;
+                        ctx.set_meta(false);
                     }
                     (Key::Control, ElementState::Pressed) => {
-                        ctx.meta_or_ctrl = true;
+                        ctx.set_ctrl(true);
                     }
                     (Key::Control, ElementState::Released) => {
-                        ctx.meta_or_ctrl = false;
+                        ctx.set_ctrl(false);
                     }
                     _ => {
                         // println!("key: {:?}, state: {:?}", logical_key, state);
@@ -225,47 +729,63 @@ pub fn run() {
                 },
                 WindowEvent::MouseInput { state, button, .. } => {
                     if let Some(mouse) = ctx.mouse_at {
-                        if state == ElementState::Pressed {
-                            let _ = proxy.send_event(WidgetEvent::MouseDown {
-                                mouse,
-                                right_click: button == MouseButton::Right,
-                                bounds: ctx.bounds,
-                                shift: ctx.shift,
-                                alt: ctx.alt,
-                                meta_or_ctrl: ctx.meta_or_ctrl,
-                            });
-
-                            if let Some(builder) = &mut curr_press && !builder.canceled_double {
-                                println!("ms: {:?}", builder.started_at.elapsed().as_millis());
-                                builder.has_fired = Some(true);
-                                let _ = proxy.send_event(WidgetEvent::Press {
-                                    double: true,
+                        if state == ElementState::Pressed
+                            && button == MouseButton::Left
+                            && mouse.1 <= WINDOW_DRAG_SURFACE_HEIGHT
+                            && !matches!(renderer.hit_test(mouse), Hit::Widget { .. })
+                        {
+                            // there's no native titlebar, so the top strip of the editor acts as one (anything clickable that's drawn there still gets its clicks though)
+                            let _ = window.drag_window();
+                        } else if state == ElementState::Pressed {
+                            let _ = proxy.send_event(
+                                WidgetEvent::MouseDown {
                                     mouse,
                                     right_click: button == MouseButton::Right,
                                     bounds: ctx.bounds,
                                     shift: ctx.shift,
                                     alt: ctx.alt,
                                     meta_or_ctrl: ctx.meta_or_ctrl,
-                                });
+                                }
+                                .into(),
+                            );
+
+                            if let Some(builder) = &mut curr_press && !builder.canceled_double {
+                                tracing::trace!(
+                                    ms = builder.started_at.elapsed().as_millis(),
+                                    "double press"
+                                );
+                                builder.has_fired = Some(true);
+                                let _ = proxy.send_event(
+                                    WidgetEvent::Press {
+                                        double: true,
+                                        mouse,
+                                        right_click: button == MouseButton::Right,
+                                        bounds: ctx.bounds,
+                                        shift: ctx.shift,
+                                        alt: ctx.alt,
+                                        meta_or_ctrl: ctx.meta_or_ctrl,
+                                    }
+                                    .into(),
+                                );
                             } else {
                                 curr_press = Some(PressEventBuilder::new(mouse, button == MouseButton::Right));
                             }
                         } else if state == ElementState::Released {
-                            let _ = proxy.send_event(WidgetEvent::MouseUp);
+                            let _ = proxy.send_event(WidgetEvent::MouseUp.into());
 
                             if let Some(builder) = &mut curr_press {
                                 builder.release();
                             }
                         }
                     } else {
-                        println!("WEIRD 1");
+                        tracing::trace!("mouse input, but we don't know where the mouse is");
                     }
                 }
                 WindowEvent::CursorEntered { .. } => {
-                    println!("cursor entered");
+                    tracing::trace!("cursor entered");
                 }
                 WindowEvent::CursorLeft { .. } => {
-                    println!("cursor left");
+                    tracing::trace!("cursor left");
                     ctx.mouse_at = None;
                     // is_selecting = false;
                 }
@@ -275,122 +795,231 @@ pub fn run() {
                     let mouse = (position.x as f32, position.y as f32);
                     ctx.mouse_at = Some(mouse);
 
+                    // (a file drag that didn't end in a drop, there's no telling otherwise)
+                    if ctx.file_drag.take() == Some(FileDrop::Rejected) {
+                        window.set_cursor_icon(CursorIcon::Default);
+                    }
+
                     //(_, button, xy)
                     if let Some(builder) = &mut curr_press {
                         builder.dragged(mouse);
 
                         if builder.canceled_double && builder.has_fired.is_none() {
                             builder.has_fired = Some(false);
-                            let _ = proxy.send_event(WidgetEvent::Press {
-                                double: false,
-                                mouse,
-                                right_click: builder.right_click,
-                                bounds: ctx.bounds,
-                                shift: ctx.shift,
-                                alt: ctx.alt,
-                                meta_or_ctrl: ctx.meta_or_ctrl,
-                            });
+                            let _ = proxy.send_event(
+                                WidgetEvent::Press {
+                                    double: false,
+                                    mouse,
+                                    right_click: builder.right_click,
+                                    bounds: ctx.bounds,
+                                    shift: ctx.shift,
+                                    alt: ctx.alt,
+                                    meta_or_ctrl: ctx.meta_or_ctrl,
+                                }
+                                .into(),
+                            );
                         }
                     }
 
-                    let _ = proxy.send_event(WidgetEvent::MouseMove {
-                        bounds: (0.0, 0.0, renderer.width() as f32, renderer.height() as f32),
-                        mouse,
-                    });
+                    let _ = proxy.send_event(
+                        WidgetEvent::MouseMove {
+                            bounds: (0.0, 0.0, renderer.width() as f32, renderer.height() as f32),
+                            mouse,
+                        }
+                        .into(),
+                    );
                 }
-                WindowEvent::Moved(u) => {
-                    println!("moved {:?}", u);
+                WindowEvent::Moved(_) => {
+                    if window.fullscreen().is_none() && let Some(placement) = window_placement::placement_from_window(&window) {
+                        window_placements.remember(monitor_setup.clone(), placement);
+                    }
+                }
+                WindowEvent::DragEnter { paths, .. } => {
+                    let file_drag = FileDrop::of(&paths);
+                    if file_drag == FileDrop::Rejected {
+                        window.set_cursor_icon(CursorIcon::NotAllowed);
+                    }
+                    ctx.file_drag = Some(file_drag);
                 }
-                // WindowEvent::DragEnter { paths, position } => {
-                // println!("drag enter {:?}", position);
-                // for path in paths {
-                //     println!("  - {:?}", path);
-                // }
-                // }
                 WindowEvent::DragOver { position } => {
-                    let position: LogicalPosition<f32> =
-                        position.to_logical(renderer.system.scale_factor.into());
-                    let pos = renderer
-                        .system
-                        .px_to_pos((position.x as f32, position.y as f32));
+                    if ctx.file_drag != Some(FileDrop::Rejected) {
+                        let position: LogicalPosition<f32> =
+                            position.to_logical(renderer.system.scale_factor.into());
+                        let (_, pos) = renderer
+                            .system
+                            .code_pos_at((position.x as f32, position.y as f32));
 
-                    editor.editor_state.file_drag_hover(pos);
+                        editor.editor_state.file_drag_hover(pos);
+                    }
                 }
                 WindowEvent::DragDrop {
-                    mut paths,
+                    paths,
                     position,
                 } => {
-                    let Some(filepath) = paths.pop() else {
-                        return;
-                    };
+                    if ctx.file_drag.take() == Some(FileDrop::Rejected) {
+                        window.set_cursor_icon(CursorIcon::Default);
+                    }
 
                     let position: LogicalPosition<f32> =
                         position.to_logical(renderer.system.scale_factor.into());
-                    let pos = renderer
+                    let (_, pos) = renderer
                         .system
-                        .px_to_pos((position.x as f32, position.y as f32));
+                        .code_pos_at((position.x as f32, position.y as f32));
 
-                    let filepath = filepath.as_path().to_str().unwrap();
-                    let widget = SampleWidget::new(filepath);
-                    let widget_info = editor.widget_manager.add(Box::new(widget));
-
-                    editor
-                        .editor_state
-                        .insert(pos, Token::Widget(widget_info).into(), true);
+                    editor.drop_files(pos, FileDrop::of(&paths));
+                }
+                WindowEvent::MouseWheel { delta, phase, .. } => {
+                    if let Some(mouse) = ctx.mouse_at {
+                        editor.scroll(&mut renderer, mouse, delta, phase);
+                    }
                 }
                 _ => (),
             },
-            winit::event::Event::UserEvent(event) => {
-                editor.event(&renderer, event);
+            winit::event::Event::UserEvent(UserEvent::Widget(event)) => {
+                editor.event(&mut renderer, event);
+            },
+            winit::event::Event::UserEvent(UserEvent::Invalidate) => {
+                // (just wakes up the event loop, see below)
             },
             winit::event::Event::RedrawRequested(_) => {
-                renderer.draw(&editor.editor_state, &mut editor.widget_manager);
-                // if state.game_state != state::GameState::Quiting {
-                window.request_redraw();
-                // }
+                renderer.system.animate_scroll();
+
+                let levels = editor.line_levels();
+                let overlay = editor.overlay(&renderer);
+                renderer.draw(
+                    &editor.editor_state,
+                    &mut editor.widget_manager,
+                    &levels,
+                    &overlay,
+                );
+                editor.mark_drawn();
+                profile.first_frame();
 
                 fps += 1;
                 if now.duration_since(then).unwrap().as_millis() > 1000 {
-                    window.set_title(&format!("FPS: {}", fps));
+                    #[cfg(not(target_arch = "wasm32"))]
+                    let badge = {
+                        updates.poll();
+                        updates.badge()
+                    };
+                    // (there's nothing to update in the browser)
+                    #[cfg(target_arch = "wasm32")]
+                    let badge: Option<String> = None;
+                    match badge {
+                        Some(badge) => window.set_title(&format!("FPS: {}  —  {}", fps, badge)),
+                        None => window.set_title(&format!("FPS: {}", fps)),
+                    }
                     fps = 0;
                     then = now;
                 }
                 now = SystemTime::now();
             }
             winit::event::Event::MainEventsCleared => {
+                editor.poll_startup();
+                editor.poll_bounce();
+                editor.poll_loop_tempos();
+                editor.poll_timers();
+                #[cfg(not(target_arch = "wasm32"))]
+                editor.reload_changed_samples();
+                editor.sync_signal_views();
+                editor.sync_widget_wrapping();
+                editor.sync_widget_widths();
+                editor.sync_color_swatches(&mut renderer);
+                editor.replay_session(&mut renderer);
+
+                // (everything that happened in response to this batch of events is undone as a whole)
+                editor.editor_state.checkpoint();
+                #[cfg(not(target_arch = "wasm32"))]
+                editor.sync_collab();
+                editor.land_pending_swaps();
+                editor.follow_caret(&mut renderer);
+                editor.record_session();
+                editor.backups.tick(editor.editor_state.linedata());
+
                 if let Some(mouse) = ctx.mouse_at {
                     if let Some(builder) = &mut curr_press {
                         if builder.reached_double_press_timeout() {
                             if builder.has_fired.is_none() {
                                 builder.has_fired = Some(false);
-                                let _ = proxy.send_event(WidgetEvent::Press {
-                                    double: false,
-                                    mouse,
-                                    right_click: builder.right_click,
-                                    bounds: ctx.bounds,
-                                    shift: ctx.shift,
-                                    alt: ctx.alt,
-                                    meta_or_ctrl: ctx.meta_or_ctrl,
-                                });
+                                let _ = proxy.send_event(
+                                    WidgetEvent::Press {
+                                        double: false,
+                                        mouse,
+                                        right_click: builder.right_click,
+                                        bounds: ctx.bounds,
+                                        shift: ctx.shift,
+                                        alt: ctx.alt,
+                                        meta_or_ctrl: ctx.meta_or_ctrl,
+                                    }
+                                    .into(),
+                                );
                             }
 
                             if let Some(double) = builder.has_fired && builder.has_released() {
-                                let _ = proxy.send_event(WidgetEvent::Release { double });
+                                let _ = proxy.send_event(WidgetEvent::Release { double }.into());
                                 curr_press = None;
                             }
                         }
                     }
                 }
 
-                if target_framerate <= delta_time.elapsed() {
+                if editor.needs_redraw() || invalidator.take() || renderer.system.is_scrolling() {
                     window.request_redraw();
-                    delta_time = Instant::now();
-                } else {
-                    *control_flow = ControlFlow::WaitUntil(
-                        Instant::now().checked_sub(delta_time.elapsed()).unwrap()
-                            + target_framerate,
+                }
+
+                // only wake up by ourselves if there's something time-based going on
+                let mut wake_at = None;
+
+                if let Some(builder) = &curr_press && builder.has_fired.is_none() {
+                    wake_at = Some(
+                        builder.started_at + Duration::from_millis(DOUBLE_PRESS_TIMEOUT_MS as u64),
                     );
                 }
+
+                if let Some(t) = editor.widget_help.due_at() {
+                    wake_at = Some(wake_at.map_or(t, |t0: Instant| t0.min(t)));
+                }
+
+                if let Some(t) = editor.doc_hover.due_at() {
+                    wake_at = Some(wake_at.map_or(t, |t0: Instant| t0.min(t)));
+                }
+
+                if let Some(t) = editor.console.due_at() {
+                    wake_at = Some(wake_at.map_or(t, |t0: Instant| t0.min(t)));
+                }
+
+                if let Some(t) = editor.clip_view.due_at() {
+                    wake_at = Some(wake_at.map_or(t, |t0: Instant| t0.min(t)));
+                }
+
+                if let Some(t) = editor.watches_due_at() {
+                    wake_at = Some(wake_at.map_or(t, |t0: Instant| t0.min(t)));
+                }
+
+                if let Some(t) = editor.timers_due_at() {
+                    wake_at = Some(wake_at.map_or(t, |t0: Instant| t0.min(t)));
+                }
+
+                if let Some(t) = editor.pending_swaps.due_at() {
+                    wake_at = Some(wake_at.map_or(t, |t0: Instant| t0.min(t)));
+                }
+
+                if let Some(t) = editor.replay.as_ref().and_then(SessionReplay::due_at) {
+                    wake_at = Some(wake_at.map_or(t, |t0: Instant| t0.min(t)));
+                }
+
+                if editor.widget_manager.animating()
+                    || editor.levels_animating()
+                    || renderer.system.is_scrolling()
+                {
+                    let next_frame = Instant::now() + target_framerate;
+                    wake_at = Some(wake_at.map_or(next_frame, |t: Instant| t.min(next_frame)));
+                }
+
+                *control_flow = match wake_at {
+                    Some(t) => ControlFlow::WaitUntil(t),
+                    None => ControlFlow::Wait,
+                };
             }
             _ => (),
         }
@@ -401,28 +1030,135 @@ struct Editor {
     widget_manager: WidgetManager,
     editor_state: EditorState,
     clipboard: Clipboard,
+    workspace: Workspace,
+    // `None` while it's starting up, or if it couldn't be started
+    engine: Option<EngineHandle>,
+    engine_startup: Loading<Result<EngineHandle, String>>,
+    // (the engine's streams live on their own thread, which switches devices when asked to)
+    switch_devices: Sender<DeviceSettings>,
+    pack_check: Loading<Vec<(SamplePack, bool)>>,
+    // the document being rendered into a file, in the background
+    bouncing: Option<BounceJob>,
+    // (samples that were dropped in, whose tempo is being worked out, or that are being stretched to the session's)
+    loop_tempos: Vec<LoopTempoJob>,
+    tap_tempo: TapTempo,
 
     is_selecting: Option<usize>,
 
+    outline: Outline,
+    outline_panel: OutlinePanel,
+    problems: Problems,
+    problems_panel: ProblemsPanel,
+    watches: Watches,
+    watch_panel: WatchPanel,
+    // while editing together with someone else
+    #[cfg(not(target_arch = "wasm32"))]
+    collab: Option<Collab>,
+    // while recording the performance, and while playing one back
+    recorder: Option<SessionRecorder>,
+    replay: Option<SessionReplay>,
+    lint_config: LintConfig,
+    command_palette: CommandPalette,
+    project_search: ProjectSearch,
+    // the code file the document was opened from (by the project search, or by dropping it onto the window), if it's not the session
+    opened: Option<PathBuf>,
+    symbol_picker: SymbolPicker,
+    library_panel: LibraryPanel,
+    commit_prompt: CommitPrompt,
+    console: Console,
+    clip_view: ClipView,
+    rename_prompt: RenamePrompt,
+    branch_picker: BranchPicker,
+    audio_settings: AudioSettingsPanel,
+    history_browser: HistoryBrowser,
+    backups: Backups,
+    backup_picker: BackupPicker,
+    musical_typing: MusicalTyping,
+    sample_browser: SampleBrowser,
+    // a file that's being dragged out of the sample browser
+    sample_drag: Option<SampleDrag>,
+    #[cfg(not(target_arch = "wasm32"))]
+    sample_watcher: SampleWatcher,
+    widget_help: WidgetHelp,
+    doc_hover: DocHover,
+    completion: Completion,
+    status_bar: StatusBar,
+    code_levels: CodeLevels,
+    // (what the engine spends its time on, shown on the code while it's struggling)
+    heat: Heat,
+    // (which of the running plugins' editors Cmd+Shift+I shows next)
+    next_plugin_editor: usize,
+    mixer: Mixer,
+    snapshots: Snapshots,
+    // whether the morph slider is being dragged
+    morph_drag: bool,
+    signal_views: SignalViews,
+    // whether to tint the code that's currently making sound
+    show_levels: bool,
+    // whether the last frame had any tinted code, so we know to draw one more frame after the sound stops
+    levels_on_screen: bool,
+    // the code that was just evaluated, which flashes briefly
+    flash: Option<(Vec<LineSelection>, Instant)>,
+    // the code that was evaluated, but only lands at the next bar or phrase
+    pending_swaps: PendingSwaps,
+    // what's live: the statements as they were when they were last evaluated (see `overlay_statements`), and their values, to latch what changed since then
+    live: String,
+    evaluated: Option<Evaluation>,
+    // the targets the engine is playing for us (see `play`), to stop the ones that aren't played anymore
+    played: Vec<String>,
+    samples: Samples,
+    // what the engine is timing for us (see `sync_timers`), to call back when it fires
+    timers: Vec<Timer>,
+    // what everything that's random in the code comes out as, until it's reseeded
+    seed: u64,
+    diff_view: DiffView,
+    // (the git repository the workspace is in, if any)
+    #[cfg(not(target_arch = "wasm32"))]
+    git: Option<Git>,
+    // what went wrong evaluating code, in the gutter
+    eval_errors: EvalErrors,
+    color_swatches: ColorSwatches,
+    // (what right-clicking a widget, or the code, opens)
+    context_menu: ContextMenu,
+    // where the carets were when we last scrolled to them, so that we only do that when they move
+    followed_carets: Vec<Pos>,
+    // (the editor state and widgets keep track of this themselves, this is for the editor's own UI)
+    ui_needs_redraw: bool,
+
     // I think this is like the kind of hidden state that would be required to map an immediate mode API to a more stately underlying system, btw..
     hovering_widget_id: Option<usize>,
     pressing_widget_id: Option<usize>,
+    // a widget that captured the mouse on mouse down (with its bounds at that moment)
+    dragging_widget: Option<(usize, (f32, f32, f32, f32))>,
+
+    invalidator: Invalidator,
 }
 
 impl Editor {
-    fn new() -> Self {
+    fn new(invalidator: &Invalidator) -> Self {
         let clipboard = Clipboard::new();
+        let workspace = Workspace::open(Workspace::default_root());
 
         let mut widget_manager = WidgetManager::new();
 
         let w0 = widget_manager.add(Box::new(SampleWidget::new(
-            "./res/samples/Abroxis - Extended Oneshot 019.wav",
+            "res/samples/Abroxis - Extended Oneshot 019.wav",
+            workspace.sample_paths(),
         )));
-        let w1 = widget_manager.add(Box::new(SampleWidget::new("./res/samples/meii - Teag.wav")));
+        let w1 = widget_manager.add(Box::new(SampleWidget::new(
+            "res/samples/meii - Teag.wav",
+            workspace.sample_paths(),
+        )));
+
+        let w2 = widget_manager.add(Box::new(MatrixWidget::new()));
+
+        let w3 = widget_manager.add(Box::new(KnobWidget::new(KnobStyle::Slider, 1.0)));
 
         let linedata = LineData::from(
             "def beat = [..X. .X]
 
+def steps = 
+
 def main = sample_matrix%[midi.pitch.int] * fx + beat * kick
 
 def fx = lowpass{f = sin(4hz)} + select{, 10}
@@ -436,125 +1172,3899 @@ def matrix = [
   , , ,
 ].map(_ *= .2s)
 
-def kick =  *= .1s",
+def kick =  *= .1s
+
+def master = { volume =  }",
         )
-        .with_widget_at_pos(Pos { row: 4, col: 40 }, w0)
-        .with_widget_at_pos(Pos { row: 6, col: 18 }, w1);
+        .with_widget_at_pos(Pos { row: 2, col: 12 }, w2)
+        .with_widget_at_pos(Pos { row: 6, col: 40 }, w0)
+        .with_widget_at_pos(Pos { row: 8, col: 18 }, w1)
+        .with_widget_at_pos(Pos { row: 19, col: 24 }, w3);
 
         let editor_state = EditorState::new().with_linedata(linedata);
+        let backups = Backups::new(workspace.root(), editor_state.linedata());
+        let mixer = Mixer::load(workspace.root());
+        let snapshots = Snapshots::load(workspace.root());
+
+        let devices = AudioSettings::load().devices();
+        let (switch_devices, switch_requests) = mpsc::channel::<DeviceSettings>();
+        let engine_startup =
+            Loading::spawn_with("starting audio", invalidator.clone(), move |done| {
+                match Engine::start(&devices) {
+                    Ok(mut engine) => {
+                        done(Ok(engine.handle()));
+                        // the streams can't be moved to another thread (on every platform), so they live on this one, and are switched here, until the editor quits
+                        #[cfg(not(target_arch = "wasm32"))]
+                        for devices in switch_requests {
+                            let _ = engine.switch_devices(&devices);
+                        }
+                        // (in the browser, this runs on the main thread, which can't wait for requests, so the streams just live on until the page is closed)
+                        #[cfg(target_arch = "wasm32")]
+                        std::mem::forget((engine, switch_requests));
+                    }
+                    Err(e) => done(Err(e)),
+                }
+            });
+
+        // (in a git repository, the gutter shows what changed since the last commit, right away)
+        #[cfg(not(target_arch = "wasm32"))]
+        let git = Git::open(workspace.root());
+        #[allow(unused_mut)]
+        let mut diff_view = DiffView::default();
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(source) = git
+            .as_ref()
+            .and_then(|git| git.committed(&workspace.root().join(SESSION_FILE)))
+        {
+            diff_view.open(
+                relink_widgets(&source, &widget_manager),
+                editor_state.linedata(),
+            );
+        }
+
+        let packs = workspace.packs.clone();
+        let pack_check = Loading::spawn("checking sample packs", invalidator.clone(), move || {
+            check_packs(packs)
+        });
 
         Self {
             widget_manager,
             editor_state,
             clipboard,
+            workspace,
+            engine: None,
+            engine_startup,
+            switch_devices,
+            pack_check,
+            bouncing: None,
+            loop_tempos: vec![],
+            tap_tempo: TapTempo::default(),
 
             is_selecting: None,
+
+            outline: Outline::default(),
+            outline_panel: OutlinePanel::new(),
+            problems: Problems::default(),
+            problems_panel: ProblemsPanel::new(),
+            watches: Watches::default(),
+            watch_panel: WatchPanel::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            collab: None,
+            recorder: None,
+            replay: None,
+            lint_config: load_lint_config(),
+            command_palette: CommandPalette::new(),
+            project_search: ProjectSearch::new(),
+            opened: None,
+            symbol_picker: SymbolPicker::new(),
+            library_panel: LibraryPanel::new(),
+            commit_prompt: CommitPrompt::new(),
+            console: Console::new(),
+            clip_view: ClipView::new(),
+            rename_prompt: RenamePrompt::new(),
+            branch_picker: BranchPicker::new(),
+            audio_settings: AudioSettingsPanel::new(),
+            history_browser: HistoryBrowser::new(),
+            backups,
+            backup_picker: BackupPicker::new(),
+            musical_typing: MusicalTyping::new(),
+            sample_browser: SampleBrowser::new(),
+            sample_drag: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            sample_watcher: SampleWatcher::start(invalidator.clone()),
+            widget_help: WidgetHelp::new(),
+            doc_hover: DocHover::new(),
+            completion: Completion::new(),
+            status_bar: StatusBar::new(),
+            code_levels: CodeLevels::default(),
+            heat: Heat::default(),
+            next_plugin_editor: 0,
+            mixer,
+            snapshots,
+            morph_drag: false,
+            signal_views: SignalViews::new(),
+            show_levels: true,
+            levels_on_screen: false,
+            flash: None,
+            pending_swaps: PendingSwaps::default(),
+            live: String::new(),
+            evaluated: None,
+            played: vec![],
+            samples: Samples::default(),
+            timers: vec![],
+            seed: 0,
+            diff_view,
+            #[cfg(not(target_arch = "wasm32"))]
+            git,
+            eval_errors: EvalErrors::default(),
+            color_swatches: ColorSwatches::default(),
+            context_menu: ContextMenu::default(),
+            followed_carets: vec![],
+            ui_needs_redraw: true,
+
             hovering_widget_id: None,
             pressing_widget_id: None,
+            dragging_widget: None,
+
+            invalidator: invalidator.clone(),
         }
     }
 
-    fn find_widget(
-        &self,
-        renderer: &Renderer,
-        mouse: (f32, f32),
-    ) -> Option<(usize, (f32, f32, f32, f32), (f32, f32))> {
-        renderer.widget_at(mouse).map(|(id, quad)| {
-            return (id, quad, mouse);
-        })
-    }
+    /**
+        Builds this frame's UI on top of the code, making sure the outline and problems are up to date with the latest edits first
+    */
+    fn overlay(&mut self, renderer: &Renderer) -> Overlay {
+        let window_size = renderer.logical_size();
 
-    fn event(&mut self, renderer: &Renderer, event: WidgetEvent) -> bool {
-        match event {
-            WidgetEvent::Hover { .. } => {
-                println!("editor:: hover");
-                //
-            }
-            WidgetEvent::MouseMove { mouse, .. } => {
-                let hover = if self.is_selecting.is_none() {
-                    self.find_widget(renderer, mouse)
-                } else {
-                    None
-                };
+        self.outline.sync(self.editor_state.linedata());
+        let runaways = match &self.engine {
+            Some(engine) => engine.runaways(),
+            None => Default::default(),
+        };
 
-                if let Some(id) = self.hovering_widget_id && hover.map(|(id, _, _)| id) != self.hovering_widget_id {
-                self.widget_manager.event(id, WidgetEvent::Unhover);
-            }
-                if let Some((id, bounds, mouse)) = hover {
-                    // renderer
-                    self.widget_manager
-                        .event(id, WidgetEvent::Hover { bounds, mouse });
-                }
-                self.hovering_widget_id = hover.map(|(id, _, _)| id);
+        self.problems.sync(
+            self.editor_state.linedata(),
+            &self.widget_manager,
+            &self.workspace,
+            &runaways,
+            &self.lint_config,
+        );
 
-                if let Some(id) = self.is_selecting {
-                    let caret = renderer.system.px_to_pos(mouse);
-                    self.editor_state.drag_select(caret, id);
-                }
-            }
-            WidgetEvent::Unhover => {
-                println!("editor:: unhover");
-                if let Some(id) = self.hovering_widget_id {
-                    self.widget_manager.event(id, WidgetEvent::Unhover);
-                }
-            }
-            WidgetEvent::MouseDown {
-                mouse, shift, alt, ..
-            } => {
-                println!("editor:: mouse down");
-                if let Some((id, widget_bounds, _)) = self.find_widget(renderer, mouse) {
-                    self.widget_manager
-                        .event(id, event.child_relative(widget_bounds));
-                }
+        let mut overlay = Overlay::default();
 
-                let pos = renderer.system.px_to_pos(mouse);
-                if shift {
-                    if self.editor_state.has_selections() {
-                        self.is_selecting = self.editor_state.extend_selection_to(pos);
-                    } else {
-                        self.is_selecting = Some(self.editor_state.set_single_caret(pos));
-                    }
-                } else if alt {
-                    self.is_selecting = Some(self.editor_state.add_caret(pos));
-                } else {
-                    self.is_selecting = Some(self.editor_state.set_single_caret(pos));
-                }
+        // (they'd overlap, and overlays don't layer properly yet)
+        if self.command_palette.is_open() {
+            self.command_palette.draw(window_size, &mut overlay);
+        } else if self.project_search.is_open() {
+            self.project_search.draw(window_size, &mut overlay);
+        } else if self.symbol_picker.is_open() {
+            self.symbol_picker
+                .draw(&self.outline, window_size, &mut overlay);
+        } else if self.library_panel.is_open() {
+            self.library_panel.draw(window_size, &mut overlay);
+        } else if self.history_browser.is_open() {
+            self.history_browser
+                .draw(self.editor_state.history(), window_size, &mut overlay);
+        } else if self.backup_picker.is_open() {
+            self.backup_picker.draw(window_size, &mut overlay);
+        } else if self.commit_prompt.is_open() {
+            self.commit_prompt.draw(window_size, &mut overlay);
+        } else if self.rename_prompt.is_open() {
+            self.rename_prompt.draw(window_size, &mut overlay);
+        } else if self.branch_picker.is_open() {
+            self.branch_picker.draw(window_size, &mut overlay);
+        } else if self.audio_settings.is_open() {
+            let playing = self
+                .engine
+                .as_ref()
+                .map(|engine| (engine.devices(), engine.callback_load()));
+            self.audio_settings.draw(playing, window_size, &mut overlay);
+        } else if self.console.is_open() {
+            self.console.draw(window_size, &mut overlay);
+        } else if self.clip_view.is_open() {
+            self.clip_view
+                .draw(self.engine.as_ref(), window_size, &mut overlay);
+        } else {
+            self.sample_browser.poll();
+            self.sample_browser
+                .draw(self.sample_drag.as_ref(), window_size, &mut overlay);
+            self.outline_panel
+                .draw(&self.outline, window_size, &mut overlay);
+            self.problems_panel
+                .draw(&self.problems, window_size, &mut overlay);
+
+            self.watches.sync(
+                &self.editor_state.linedata().to_string(),
+                self.seed,
+                self.workspace.root(),
+                self.engine.as_ref(),
+            );
+            if let Some(engine) = &self.engine && !self.watch_panel.collapsed {
+                self.watches.refresh(engine);
             }
-            WidgetEvent::Press { double, mouse, .. } => {
-                println!(
-                    "editor:: press {:?}",
-                    if double { "DOUBLE" } else { "single" }
-                );
+            self.watch_panel
+                .draw(&self.watches, window_size, &mut overlay);
 
-                // pressing widgets
-                let w = self.find_widget(renderer, mouse);
-                if let Some(id) = self.pressing_widget_id && w.map(|(id, _, _)| id) != self.pressing_widget_id {
-                    self.widget_manager.event(id, WidgetEvent::Release { double });
-                }
-                if let Some((id, bounds, _)) = w {
-                    self.widget_manager.event(id, event.child_relative(bounds));
-                }
-                self.pressing_widget_id = w.map(|(id, _, _)| id);
+            self.mixer.sync(self.editor_state.linedata());
+            self.mixer.draw(renderer, &mut overlay);
+            self.snapshots.draw(window_size, &mut overlay);
+        }
 
-                // double press -> selecting words
-                if double {
-                    let pos = renderer.system.px_to_pos(mouse);
-                    self.editor_state.select_word_at(pos);
-                }
-            }
-            WidgetEvent::MouseUp => {
-                // hmm, can't sent this to the widget w/o coords..
-                println!("editor:: mouse up");
-                self.is_selecting = None;
-            }
-            WidgetEvent::Release { .. } => {
-                // hmm, can't sent this to the widget w/o coords..
-                println!("editor:: release");
+        for x in renderer.system.pane_dividers() {
+            overlay.quad((x - 0.5, 0.0, x + 0.5, window_size.1), PANE_DIVIDER_COLOR);
+        }
+
+        self.pending_swaps.draw(renderer, &mut overlay);
+        draw_bookmarks(self.editor_state.bookmarks(), renderer, &mut overlay);
+
+        self.diff_view.sync(self.editor_state.linedata());
+        self.diff_view.draw(renderer, &mut overlay);
+
+        if let Some(notice) = self.heat.update(self.engine.as_ref()) {
+            self.status_bar.notify(notice);
+        }
+        self.heat.draw(
+            &mut self.code_levels,
+            self.editor_state.linedata(),
+            renderer,
+            &mut overlay,
+        );
+
+        self.status_bar
+            .update(self.engine.as_ref().map(|engine| engine.master_level()));
+        self.status_bar.set_octave(self.musical_typing.octave());
+        self.status_bar.set_loading(self.loading());
+        self.status_bar.draw(window_size, &mut overlay);
+
+        if let Some(id) = self.widget_manager.focused() {
+            for bounds in renderer.widget_bounds(id) {
+                focus_ring(&mut overlay, bounds);
             }
         }
 
-        false
-    }
+        self.eval_errors.sync(self.editor_state.linedata());
+        self.eval_errors.draw(renderer, &mut overlay);
+        self.color_swatches.draw(renderer, &mut overlay);
+
+        if let Some(id) = self.widget_help.visible() {
+            let help = self.widget_manager.help(id);
+            self.widget_help.draw(help, window_size, &mut overlay);
+        }
+
+        self.doc_hover.draw(
+            self.editor_state.linedata(),
+            renderer,
+            window_size,
+            &mut overlay,
+        );
+
+        if let [caret] = self.editor_state.caret_positions()[..] {
+            self.completion.draw(
+                self.editor_state.linedata(),
+                caret,
+                renderer,
+                window_size,
+                &mut overlay,
+            );
+        } else {
+            self.completion.close();
+        }
+
+        self.context_menu.draw(window_size, &mut overlay);
+
+        overlay
+    }
+
+    /**
+        How loud the code is, per line span, for this frame's background tints
+    */
+    fn line_levels(&mut self) -> Vec<(LineSelection, f32)> {
+        let levels = match &self.engine {
+            Some(engine) if self.show_levels => engine.levels(),
+            _ => Default::default(),
+        };
+
+        let mut line_levels = if levels.is_empty() {
+            vec![]
+        } else {
+            self.code_levels.sync(self.editor_state.linedata());
+            self.code_levels.line_levels(self.editor_state.linedata(), &levels)
+        };
+
+        if let Some((region, at)) = &self.flash {
+            let t = at.elapsed().as_secs_f32() / FLASH_DURATION.as_secs_f32();
+            if t < 1.0 {
+                line_levels.extend(region.iter().map(|&line| (line, 1.0 - t)));
+            } else {
+                self.flash = None;
+            }
+        }
+
+        self.levels_on_screen = !line_levels.is_empty();
+
+        line_levels
+    }
+
+    /**
+        The levels (and master meter) change with the sound, so while anything's playing (or was, in the last frame) we have to keep drawing
+    */
+    fn levels_animating(&self) -> bool {
+        let playing = self.show_levels
+            && self
+                .engine
+                .as_ref()
+                .map_or(false, |engine| !engine.levels().is_empty());
+
+        // (the audio settings show the callback load, live)
+        playing
+            || self.levels_on_screen
+            || self.flash.is_some()
+            || self.status_bar.animating()
+            || self.audio_settings.is_open()
+    }
+
+    /**
+        Spawns (or updates) the scope and spectrum widgets of the `scope(..)` and `spectrum(..)` calls in the code
+    */
+    fn sync_signal_views(&mut self) {
+        self.signal_views.sync(
+            &mut self.editor_state,
+            &mut self.widget_manager,
+            self.engine.as_ref(),
+        );
+    }
+
+    /**
+        Lets the widgets know which functions they're passed to, like a sample that was just wrapped in `slices(..)` (which shows where its slices are)
+    */
+    fn sync_widget_wrapping(&mut self) {
+        self.widget_manager.sync_wrapping(self.editor_state.linedata());
+    }
+
+    /**
+        Makes the widgets in the code as wide as they want to be now (like a sample that was just wrapped in `slices(..)`)
+    */
+    fn sync_widget_widths(&mut self) {
+        for (id, width) in self.widget_manager.resized_in(self.editor_state.linedata()) {
+            self.editor_state.resize_widget(id, width);
+        }
+    }
+
+    /**
+        Makes room for a swatch after every color literal in the code (like `#ff8800`), when it changed
+    */
+    fn sync_color_swatches(&mut self, renderer: &mut Renderer) {
+        self.color_swatches
+            .sync(self.editor_state.linedata(), renderer);
+    }
+
+    /**
+        Picks up whatever finished loading in the background since startup
+    */
+    fn poll_startup(&mut self) {
+        if let Some(engine) = self.engine_startup.poll() {
+            match engine {
+                Ok(engine) => {
+                    engine.set_economize(AudioSettings::load().economize);
+                    self.mixer.apply(&engine);
+                    self.snapshots.apply(&engine);
+                    self.clip_view.apply(&engine);
+                    engine.set_tempo(self.workspace.tempo);
+                    engine.set_quantize(self.workspace.quantize);
+                    engine.set_swing(self.workspace.swing);
+                    self.engine = Some(engine);
+                }
+                Err(e) => tracing::warn!("Could not start the audio engine: {}", e),
+            }
+            self.ui_needs_redraw = true;
+        }
+
+        if let Some(checked) = self.pack_check.poll() {
+            // (this might ask the user what to do about moved packs)
+            self.workspace.verify_packs(checked);
+            self.ui_needs_redraw = true;
+        }
+    }
+
+    /**
+        Reads samples that changed on disk again, and keeps watching whatever the code refers to now
+    */
+    #[cfg(not(target_arch = "wasm32"))]
+    fn reload_changed_samples(&mut self) {
+        for path in self.sample_watcher.changed() {
+            tracing::info!(target: "files", "Reloading {:?}", path);
+            self.widget_manager.file_changed(&path);
+            self.samples.forget(&path);
+        }
+
+        self.sample_watcher
+            .watch(self.widget_manager.samples_in(self.editor_state.linedata()));
+    }
+
+    /**
+        What's still loading, for the status bar
+    */
+    fn loading(&self) -> Vec<String> {
+        let samples = self.widget_manager.loading();
+
+        self.engine_startup
+            .label()
+            .map(String::from)
+            .into_iter()
+            .chain(self.pack_check.label().map(String::from))
+            .chain((samples > 0).then(|| match samples {
+                1 => "loading a sample".to_string(),
+                n => format!("loading {} samples", n),
+            }))
+            .chain(self.bouncing.as_ref().map(|job| match &job.definition {
+                Some(name) => format!("freezing {} {}%", name, job.percent()),
+                None => format!("bouncing {}%", job.percent()),
+            }))
+            .collect()
+    }
+
+    /**
+        Cmd+Shift+E: renders what the document plays (as many bars as the project file says) into a WAV or FLAC file, in the background
+    */
+    fn bounce(&mut self) {
+        if self.bouncing.is_some() {
+            self.status_bar.notify("still bouncing");
+            self.ui_needs_redraw = true;
+            return;
+        }
+
+        let Some(path) = FileDialog::new()
+            .add_filter("wav", &["wav"])
+            .add_filter("flac", &["flac"])
+            .set_directory(self.workspace.root())
+            .set_file_name("bounce.wav")
+            .save_file()
+        else {
+            return;
+        };
+
+        let settings = BounceSettings {
+            bars: self.workspace.bounce_bars,
+            tempo: self.workspace.tempo,
+            swing: self.workspace.swing,
+            seed: self.seed,
+        };
+
+        let linedata = self.editor_state.linedata();
+        self.bouncing = Some(BounceJob::spawn(
+            linedata.to_string(),
+            self.workspace.root().to_path_buf(),
+            self.widget_manager.values_in(linedata),
+            None,
+            settings,
+            path,
+            self.invalidator.clone(),
+        ));
+        self.ui_needs_redraw = true;
+    }
+
+    fn poll_bounce(&mut self) {
+        let Some(result) = self.bouncing.as_ref().and_then(|job| job.poll()) else {
+            return;
+        };
+        let Some(job) = self.bouncing.take() else {
+            return;
+        };
+
+        match (result, job.definition) {
+            (Ok(()), Some(name)) => self.finish_freeze(&name, &job.path),
+            (Ok(()), None) => {
+                let name = job.path.file_name().unwrap_or_default().to_string_lossy();
+                self.status_bar.notify(format!("bounced to {}", name));
+            }
+            (Err(e), Some(name)) => {
+                tracing::warn!("Could not freeze {}: {}", name, e);
+                self.status_bar.notify(format!("could not freeze {}", name));
+            }
+            (Err(e), None) => {
+                tracing::warn!("Could not bounce: {}", e);
+                self.status_bar.notify("could not bounce");
+            }
+        }
+        self.ui_needs_redraw = true;
+    }
+
+    fn poll_loop_tempos(&mut self) {
+        let mut done = vec![];
+        self.loop_tempos.retain(|job| match job.poll() {
+            Some(result) => {
+                done.push((job.widget, job.path.clone(), result));
+                false
+            }
+            None => true,
+        });
+
+        for (widget, path, result) in done {
+            let name = path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+
+            match result {
+                Ok(LoopTempo::Detected(Some(bpm))) => self.offer_loop_tempo(widget, path, bpm),
+                // (a one-shot, nothing to do)
+                Ok(LoopTempo::Detected(None)) => {}
+                Ok(LoopTempo::Stretched(stretched)) => self.finish_stretch(widget, &stretched),
+                Err(e) => {
+                    tracing::warn!("Could not work out the tempo of {}: {}", name, e);
+                    self.status_bar
+                        .notify(format!("could not work out the tempo of {}", name));
+                }
+            }
+            self.ui_needs_redraw = true;
+        }
+    }
+
+    /**
+        A loop that was dropped in is at another tempo than the session: either the session goes along with the loop, or the loop is time-stretched (in the background) to the session's tempo
+    */
+    fn offer_loop_tempo(&mut self, widget: usize, path: PathBuf, bpm: f64) {
+        let tempo = self.workspace.tempo;
+        if (bpm / tempo - 1.0).abs() < SAME_TEMPO {
+            return;
+        }
+
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let follow = MessageDialog::new()
+            .set_level(MessageLevel::Info)
+            .set_title("Loop tempo")
+            .set_description(&format!(
+                "{} sounds like it's at {} bpm. Set the session's tempo to that (instead of {} bpm)?",
+                name,
+                format_bpm(bpm),
+                format_bpm(tempo)
+            ))
+            .set_buttons(MessageButtons::YesNo)
+            .show();
+
+        if follow {
+            self.set_tempo(bpm);
+            return;
+        }
+
+        let stretch = MessageDialog::new()
+            .set_level(MessageLevel::Info)
+            .set_title("Loop tempo")
+            .set_description(&format!(
+                "Time-stretch {} to {} bpm instead (keeping its pitch)? The stretched loop is saved in the project, in `{}`.",
+                name,
+                format_bpm(tempo),
+                STRETCHED_DIR
+            ))
+            .set_buttons(MessageButtons::YesNo)
+            .show();
+
+        if stretch {
+            self.status_bar.notify(format!("stretching {}", name));
+            self.loop_tempos.push(LoopTempoJob::stretch(
+                widget,
+                path,
+                bpm,
+                tempo,
+                self.workspace.root(),
+                self.invalidator.clone(),
+            ));
+        }
+    }
+
+    /**
+        Swaps the dropped loop for the time-stretched one, as one edit
+    */
+    fn finish_stretch(&mut self, widget: usize, path: &Path) {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let Some(range) = self.widget_range(widget) else {
+            self.status_bar.notify(format!(
+                "stretched {}, but the loop is gone from the code",
+                name
+            ));
+            return;
+        };
+
+        let stretched = SampleWidget::from_file(path, self.workspace.sample_paths());
+        let token = Token::Widget(self.widget_manager.add(Box::new(stretched)));
+
+        if self.widget_manager.focused() == Some(widget) {
+            self.widget_manager.unfocus();
+        }
+
+        self.is_selecting = None;
+        self.editor_state.checkpoint();
+        self.editor_state.remove(range);
+        self.editor_state
+            .insert(range.start, vec![token].into(), false);
+        self.editor_state.checkpoint();
+
+        self.status_bar.notify(format!("stretched to {}", name));
+    }
+
+    /**
+        Cmd+Shift+U (or right-clicking a definition): renders the definition at the caret on its own (as many bars as the project file says) into `frozen/<name>.wav` in the project, in the background, to then play that sample instead (see `finish_freeze`), which saves the CPU it takes
+    */
+    fn freeze(&mut self) {
+        self.ui_needs_redraw = true;
+
+        if self.bouncing.is_some() {
+            self.status_bar.notify("still bouncing");
+            return;
+        }
+
+        let Some(&caret) = self.editor_state.caret_positions().first() else {
+            return;
+        };
+
+        let linedata = self.editor_state.linedata();
+        let source = linedata.to_string();
+        let offset = linedata.pos_to_offset(caret);
+
+        let Some(symbol) = outline(&source).into_iter().find(|symbol| {
+            symbol.range.start.offset <= offset && offset <= symbol.range.end.offset
+        }) else {
+            self.status_bar
+                .notify("nothing to freeze (put the caret on a definition)");
+            return;
+        };
+
+        if symbol.kind == SymbolKind::Fn {
+            self.status_bar
+                .notify(format!("can't freeze {} (it's a function)", symbol.name));
+            return;
+        }
+
+        let dir = self.workspace.root().join(FROZEN_DIR);
+        if let Err(e) = fs::create_dir_all(&dir) {
+            tracing::warn!("Could not create {}: {}", dir.display(), e);
+            self.status_bar
+                .notify(format!("could not freeze {}", symbol.name));
+            return;
+        }
+
+        let settings = BounceSettings {
+            bars: self.workspace.freeze_bars,
+            tempo: self.workspace.tempo,
+            swing: self.workspace.swing,
+            seed: self.seed,
+        };
+
+        // (freezing it again overwrites the sample, and the widgets that play it pick that up)
+        self.bouncing = Some(BounceJob::spawn(
+            source,
+            self.workspace.root().to_path_buf(),
+            self.widget_manager.values_in(linedata),
+            Some(symbol.name.clone()),
+            settings,
+            dir.join(format!("{}.wav", symbol.name)),
+            self.invalidator.clone(),
+        ));
+    }
+
+    /**
+        Puts a sample widget that plays the frozen definition right below it (as one edit), and comments out its code (unless the project file says not to, in which case the sample's declared as `<name>_frozen` next to it)
+    */
+    fn finish_freeze(&mut self, name: &str, path: &Path) {
+        // (it's found again by name, because the code may have changed in the meantime)
+        let linedata = self.editor_state.linedata();
+        let Some(symbol) = outline(&linedata.to_string())
+            .into_iter()
+            .find(|symbol| symbol.name == name && symbol.kind != SymbolKind::Fn)
+        else {
+            self.status_bar
+                .notify(format!("froze {}, but it's gone from the code", name));
+            return;
+        };
+
+        let range = span_to_range(linedata, symbol.range);
+        let end = Pos {
+            row: range.end.row,
+            col: linedata.line_width(range.end.row),
+        };
+
+        let comment_out = self.workspace.freeze_comments_out;
+        let keyword = if symbol.kind == SymbolKind::Def {
+            "def"
+        } else {
+            "let"
+        };
+        let declared = if comment_out {
+            format!("{} {} = ", keyword, name)
+        } else {
+            format!("{} {}_frozen = ", keyword, name)
+        };
+
+        let widget = SampleWidget::from_file(path, self.workspace.sample_paths());
+        let mut line = declared.chars().map(Token::Char).collect::<Vec<_>>();
+        line.push(Token::Widget(self.widget_manager.add(Box::new(widget))));
+
+        self.is_selecting = None;
+        self.editor_state.checkpoint();
+        self.editor_state
+            .insert(end, vec![vec![], line].into(), false);
+        if comment_out {
+            for row in range.start.row..=range.end.row {
+                self.editor_state
+                    .insert(Pos { row, col: 0 }, LineData::from("// "), false);
+            }
+        }
+        self.editor_state.checkpoint();
+
+        self.status_bar.notify(format!("froze {}", name));
+    }
+
+    /**
+        Cmd+Shift+Y: starts recording the performance (see `SessionRecorder`), or stops it
+    */
+    fn toggle_recording(&mut self) {
+        self.ui_needs_redraw = true;
+
+        if let Some(recorder) = self.recorder.take() {
+            let name = recorder
+                .path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy();
+            self.status_bar.notify(format!("recorded {}", name));
+            return;
+        }
+
+        let root = self.workspace.root();
+        match SessionRecorder::start(root, self.workspace.tempo, self.workspace.swing) {
+            Ok(recorder) => {
+                self.recorder = Some(recorder);
+                self.record_session();
+                self.status_bar.notify("recording session");
+            }
+            Err(e) => {
+                tracing::warn!("Could not record session: {}", e);
+                self.status_bar.notify("could not record session");
+            }
+        }
+    }
+
+    /**
+        Records how the code changed (and where the caret went) since the last time, if the session is being recorded
+    */
+    fn record_session(&mut self) {
+        let Some(recorder) = &mut self.recorder else {
+            return;
+        };
+
+        let linedata = self.editor_state.linedata();
+        let caret = self
+            .editor_state
+            .caret_positions()
+            .last()
+            .map_or(0, |&caret| linedata.pos_to_offset(caret));
+
+        recorder.sync(&linedata.to_string(), caret);
+    }
+
+    /**
+        Records something that happened (after what the code was up to now, so that it's in order)
+    */
+    fn record_event(&mut self, event: SessionEvent) {
+        self.record_session();
+
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record(event);
+        }
+    }
+
+    /**
+        Cmd+Shift+J: plays a recorded session back (see `SessionReplay`), at the tempo and swing it was performed at, starting from an empty document (the code that was there is backed up first)
+    */
+    fn start_replay(&mut self) {
+        let Some(path) = FileDialog::new()
+            .add_filter("session", &[SESSION_EXTENSION])
+            .set_directory(self.workspace.root().join(SESSIONS_DIR))
+            .pick_file()
+        else {
+            return;
+        };
+
+        self.ui_needs_redraw = true;
+
+        let session = match Session::load(&path) {
+            Ok(session) => session,
+            Err(e) => {
+                tracing::warn!("Could not replay session: {}", e);
+                self.status_bar.notify("could not replay session");
+                return;
+            }
+        };
+
+        self.backups.backup(self.editor_state.linedata());
+        self.replace_document(LineData::new());
+
+        self.workspace.tempo = session.tempo;
+        self.workspace.swing = clamp_swing(session.swing);
+        if let Some(engine) = &self.engine {
+            engine.set_tempo(self.workspace.tempo);
+            engine.set_swing(self.workspace.swing);
+        }
+
+        self.replay = Some(SessionReplay::start(session));
+        self.status_bar.notify("replaying session");
+    }
+
+    /**
+        Performs what's due of the session that's being replayed. Editing along stops it (since the edits that are still to come wouldn't fit anymore).
+    */
+    fn replay_session(&mut self, renderer: &mut Renderer) {
+        let Some(mut replay) = self.replay.take() else {
+            return;
+        };
+
+        self.ui_needs_redraw = true;
+
+        if self.editor_state.linedata().to_string() != replay.source {
+            self.status_bar.notify("stopped replaying (the code was edited)");
+            return;
+        }
+
+        for event in replay.due() {
+            match event {
+                SessionEvent::Edit { start, end, text } => {
+                    if !apply_edit(&mut replay.source, start, end, &text) {
+                        tracing::warn!("Could not replay session: an edit doesn't fit the code");
+                        self.status_bar.notify("could not replay session");
+                        return;
+                    }
+
+                    let linedata = self.editor_state.linedata();
+                    let range = Range {
+                        start: linedata.offset_to_pos(start),
+                        end: linedata.offset_to_pos(end),
+                    };
+
+                    if start < end {
+                        self.editor_state.remove(range);
+                    }
+                    if !text.is_empty() {
+                        let inserted = relink_widgets(&text, &self.widget_manager);
+                        self.editor_state.insert(range.start, inserted, true);
+                    }
+                }
+                SessionEvent::Caret(offset) => {
+                    let pos = self.editor_state.linedata().offset_to_pos(offset);
+                    self.editor_state.set_single_caret(pos);
+                }
+                SessionEvent::Command(command) if replays(command) => {
+                    self.run_command(command, renderer);
+                }
+                SessionEvent::Command(_) => {}
+                SessionEvent::Param(name, value) => {
+                    if let Some(engine) = &self.engine {
+                        engine.set_param(name, value);
+                    }
+                }
+            }
+        }
+
+        if replay.is_done() {
+            self.status_bar.notify("done replaying");
+        } else {
+            self.replay = Some(replay);
+        }
+    }
+
+    /**
+        Cmd+.: fades out everything that's playing, over the time set in the project file
+    */
+    fn hush(&mut self) {
+        let Some(engine) = &self.engine else {
+            return;
+        };
+
+        engine.hush(self.workspace.hush);
+        self.status_bar.notify("hushed");
+        self.ui_needs_redraw = true;
+    }
+
+    /**
+        Cmd+Shift+.: stops everything right away, for when a feedback loop can't wait for a fade
+    */
+    fn panic(&mut self) {
+        let Some(engine) = &self.engine else {
+            return;
+        };
+
+        engine.panic();
+        self.status_bar.notify("PANIC — all sound stopped");
+        self.ui_needs_redraw = true;
+    }
+
+    /**
+        Cmd+;: everything that's random in the code (like `rand()` and `sometimes`) comes out differently, from now on. It's latched like evaluating what's live again would, so the numbers glide to their new values.
+    */
+    fn reseed(&mut self) {
+        self.seed = self.seed.wrapping_add(1);
+        self.latch(&self.live.clone());
+        self.sync_timers();
+
+        self.status_bar.notify(format!("reseeded ({})", self.seed));
+        self.ui_needs_redraw = true;
+    }
+
+    /**
+        Cmd+Shift+B: whether evaluated code lands right away, or at the next bar or phrase
+    */
+    fn cycle_quantize(&mut self) {
+        self.workspace.quantize = self.workspace.quantize.next();
+
+        if let Some(engine) = &self.engine {
+            engine.set_quantize(self.workspace.quantize);
+        }
+
+        self.status_bar.notify(match self.workspace.quantize {
+            Quantize::Now => "changes land right away",
+            Quantize::Bar => "changes land at the next bar",
+            Quantize::Phrase => "changes land at the next phrase",
+        });
+        self.ui_needs_redraw = true;
+    }
+
+    /**
+        Swings the patterns more (or less), for those that don't have a swing of their own
+    */
+    fn nudge_swing(&mut self, by: f64) {
+        self.workspace.swing = nudged_swing(self.workspace.swing, by);
+
+        if let Some(engine) = &self.engine {
+            engine.set_swing(self.workspace.swing);
+        }
+
+        self.status_bar.notify(if self.workspace.swing == STRAIGHT {
+            "no swing".to_string()
+        } else {
+            format!("swing {:.0}%", self.workspace.swing * 100.0)
+        });
+        self.ui_needs_redraw = true;
+    }
+
+    /**
+        Cmd+T, on the beat, a couple of times: sets the tempo to what's tapped
+    */
+    fn tap_tempo(&mut self) {
+        match self.tap_tempo.tap(Instant::now()) {
+            Some(tempo) => self.set_tempo(tempo),
+            None => {
+                self.status_bar.notify("tap again, on the beat");
+                self.ui_needs_redraw = true;
+            }
+        }
+    }
+
+    /**
+        Changes the session's tempo while playing (until the project is opened again, since the project file isn't changed)
+    */
+    fn set_tempo(&mut self, tempo: f64) {
+        self.workspace.tempo = tempo;
+
+        if let Some(engine) = &self.engine {
+            engine.set_tempo(self.workspace.tempo);
+        }
+        // (they're in seconds, which are another number of beats now)
+        self.sync_timers();
+
+        self.status_bar
+            .notify(format!("tempo {} bpm", format_bpm(self.workspace.tempo)));
+        self.ui_needs_redraw = true;
+    }
+
+    /**
+        Shift+F5/F6/F7: captures what the knobs and `perform(..)` numbers are now into snapshot A/B/C, to morph between with the slider
+    */
+    fn capture_snapshot(&mut self, slot: usize) {
+        let ids = self
+            .editor_state
+            .linedata()
+            .lines()
+            .iter()
+            .flatten()
+            .filter_map(|token| match token {
+                Token::Widget(info) => Some(info.id),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        let mut params = vec![];
+        for id in ids {
+            if let Some(WidgetValue::Number(value)) = self.widget_manager.value(id)
+                && let Some(name) = self.param_binding(id)
+            {
+                params.push((name, value));
+            }
+        }
+
+        if let Some(evaluated) = &self.evaluated {
+            for (key, value) in performables(&evaluated.values) {
+                let name = key.to_string();
+                // (a knob that's in a `perform(..)` is what the knob says)
+                if !params.iter().any(|(n, _)| *n == name) {
+                    params.push((name, value as f32));
+                }
+            }
+        }
+
+        if params.is_empty() {
+            self.status_bar
+                .notify("nothing to capture (knobs, or `perform(..)` numbers)");
+            self.ui_needs_redraw = true;
+            return;
+        }
+
+        let count = params.len();
+        self.snapshots.capture(slot, params);
+        if let Some(engine) = &self.engine {
+            self.snapshots.apply(engine);
+        }
+
+        self.status_bar.notify(format!(
+            "captured snapshot {} ({} parameters)",
+            SLOTS[slot], count
+        ));
+        self.ui_needs_redraw = true;
+    }
+
+    /**
+        Moves the morph slider, and with it, the parameters in the snapshots on either end
+    */
+    fn morph_to(&mut self, position: f32) {
+        self.snapshots.set_position(position);
+
+        if let Some(engine) = &self.engine {
+            engine.set_param(MORPH, self.snapshots.position());
+        }
+        self.ui_needs_redraw = true;
+    }
+
+    /**
+        Flashes the evaluated code that landed by now
+    */
+    fn land_pending_swaps(&mut self) {
+        let landed = self.pending_swaps.land();
+        if landed.is_empty() {
+            return;
+        }
+
+        self.flash = Some((landed, Instant::now()));
+        self.ui_needs_redraw = true;
+    }
+
+    fn toggle_levels(&mut self) {
+        self.show_levels = !self.show_levels;
+        self.ui_needs_redraw = true;
+    }
+
+    /**
+        Splits the editor into two panes on the same document (or back into one), which scroll on their own, while the caret and selections are shared
+    */
+    fn toggle_split(&mut self, renderer: &mut Renderer) {
+        renderer.system.toggle_split();
+        self.ui_needs_redraw = true;
+    }
+
+    /**
+        Makes the code bigger or smaller (or back to the default size, for 0 steps), remembering it for next time
+    */
+    fn zoom(&mut self, renderer: &mut Renderer, steps: f32) {
+        let font = if steps == 0.0 {
+            renderer.font().unzoomed()
+        } else {
+            renderer.font().zoomed(steps)
+        };
+
+        if &font == renderer.font() {
+            return;
+        }
+
+        font.save();
+        self.status_bar
+            .notify(format!("font size {}", font.size.round()));
+        renderer.set_font(font);
+
+        // (the caret's line moved, so keep it in view)
+        self.followed_carets.clear();
+        self.ui_needs_redraw = true;
+    }
+
+    fn needs_redraw(&self) -> bool {
+        self.ui_needs_redraw
+            || self.widget_help.needs_redraw()
+            || self.doc_hover.needs_redraw()
+            || self.console.needs_redraw()
+            || self.clip_view.needs_redraw(self.engine.as_ref())
+            || self.editor_state.needs_redraw()
+            || self.widget_manager.needs_redraw()
+            || self.levels_animating()
+            || self.watches_due_at().is_some_and(|at| at <= Instant::now())
+    }
+
+    /**
+        Starts editing together (see `Collab`), where the host shares the document right away
+    */
+    #[cfg(not(target_arch = "wasm32"))]
+    fn start_collab(&mut self, sharing: Sharing, invalidator: &Invalidator) {
+        if let Sharing::Host(_) = sharing {
+            self.editor_state.start_sharing(HOST_SITE);
+        }
+
+        self.collab = Some(Collab::start(sharing, invalidator.clone()));
+    }
+
+    /**
+        Applies what the other side did, and sends them what we did (after every batch of events, so that a key press goes out as a whole)
+    */
+    #[cfg(not(target_arch = "wasm32"))]
+    fn sync_collab(&mut self) {
+        let Some(collab) = &mut self.collab else {
+            return;
+        };
+
+        for event in collab.events() {
+            match event {
+                CollabEvent::Connected => {
+                    collab.connected = true;
+                    collab.sent_selections = vec![];
+
+                    // (when hosting, otherwise we wait for the host's welcome)
+                    if let Some(snapshot) = self.editor_state.shared_snapshot() {
+                        collab.send(Message::Welcome {
+                            site: GUEST_SITE,
+                            snapshot,
+                        });
+                        self.status_bar.notify("someone joined");
+                    }
+                }
+                CollabEvent::Received(Message::Welcome { site, snapshot }) => {
+                    self.editor_state.join_shared(site, snapshot);
+                    collab.site = Some(site);
+                    self.status_bar.notify("joined");
+                }
+                CollabEvent::Received(Message::Ops(ops)) => {
+                    self.editor_state.apply_shared_ops(ops);
+                }
+                CollabEvent::Received(Message::Selections { selections, site }) => {
+                    self.editor_state.set_remote_selections(site, selections);
+                }
+                CollabEvent::Disconnected => {
+                    collab.connected = false;
+                    self.editor_state.clear_remote_selections();
+                    self.status_bar.notify("the other side left");
+
+                    // (the host keeps sharing, for whoever joins next)
+                    if collab.site != Some(HOST_SITE) {
+                        self.editor_state.stop_sharing();
+                    }
+                }
+                CollabEvent::Failed(message) => {
+                    tracing::warn!("{}", message);
+                    self.status_bar.notify(message);
+                }
+            }
+        }
+
+        // (what was done while nobody was there is in the next welcome's snapshot)
+        let ops = self.editor_state.take_shared_ops();
+        let Some(site) = collab.site.filter(|_| collab.connected) else {
+            return;
+        };
+
+        if !ops.is_empty() {
+            collab.send(Message::Ops(ops));
+        }
+
+        let selections = self.editor_state.shared_selections();
+        if selections != collab.sent_selections {
+            collab.send(Message::Selections {
+                site,
+                selections: selections.clone(),
+            });
+            collab.sent_selections = selections;
+        }
+    }
+
+    /**
+        When the watch panel's live values have to be read again (not while it's collapsed, when they don't show)
+    */
+    fn watches_due_at(&self) -> Option<Instant> {
+        if self.watch_panel.collapsed {
+            return None;
+        }
+
+        self.watches.due_at()
+    }
+
+    /**
+        Pins the selected name (or the one at the caret) in the watch panel, or unpins it if it's pinned already
+    */
+    fn toggle_watch(&mut self) {
+        let linedata = self.editor_state.linedata();
+
+        let name = match self.editor_state.copy().first() {
+            Some(selected) => Some(selected.to_string()),
+            None => self
+                .editor_state
+                .caret_positions()
+                .first()
+                .and_then(|&pos| linedata.find_word_at(pos))
+                .map(|word| linedata.copy_range(word).to_string()),
+        };
+
+        let Some(name) = name.map(|name| name.trim().to_string()) else {
+            return;
+        };
+        if name.is_empty() {
+            return;
+        }
+
+        self.watches.toggle_pin(&name);
+        self.ui_needs_redraw = true;
+    }
+
+    fn mark_drawn(&mut self) {
+        self.ui_needs_redraw = false;
+        self.editor_state.mark_drawn();
+        self.editor_state.clear_dirty_lines();
+        self.widget_manager.mark_drawn();
+    }
+
+    fn jump_to(&mut self, pos: Pos) {
+        self.is_selecting = None;
+        self.editor_state.record_jump();
+        self.editor_state.set_single_caret(pos);
+    }
+
+    fn open_command_palette(&mut self) {
+        self.command_palette.open();
+        self.ui_needs_redraw = true;
+    }
+
+    fn command_palette_key(&mut self, key: Key, ctx: &Context, renderer: &mut Renderer) {
+        self.ui_needs_redraw = true;
+
+        match key {
+            Key::Escape => {
+                self.command_palette.close();
+            }
+            Key::Enter => {
+                self.command_palette.close();
+                if let Some(command) = self.command_palette.selected_command() {
+                    self.run_command(command, renderer);
+                }
+            }
+            Key::ArrowUp => {
+                self.command_palette.move_selection(-1);
+            }
+            Key::ArrowDown => {
+                self.command_palette.move_selection(1);
+            }
+            Key::Backspace => {
+                self.command_palette.backspace();
+            }
+            Key::Character(s) if !ctx.meta_or_ctrl => {
+                self.command_palette.type_str(s.as_str());
+            }
+            _ => {}
+        }
+    }
+
+    /**
+        Does what a shortcut (or the command palette) says, see `EditorCommand::all`
+    */
+    fn run_command(&mut self, command: EditorCommand, renderer: &mut Renderer) {
+        self.record_event(SessionEvent::Command(command));
+
+        match command {
+            EditorCommand::Evaluate => self.evaluate(),
+            EditorCommand::FormatDocument => self.format_document(),
+            EditorCommand::RenameSymbol => self.open_rename_prompt(),
+            EditorCommand::ExtractDefinition => self.extract_definition(),
+            EditorCommand::GoToSymbol => self.open_symbol_picker(),
+            EditorCommand::GoToDefinition => self.go_to_definition(),
+            EditorCommand::Complete => self.open_completion(),
+            EditorCommand::SearchProject => self.open_project_search(),
+            EditorCommand::ToggleBookmark => {
+                if let Some(bookmarked) = self.editor_state.toggle_bookmark() {
+                    self.ui_needs_redraw = true;
+                    self.status_bar.notify(if bookmarked {
+                        "bookmarked line"
+                    } else {
+                        "removed bookmark"
+                    });
+                }
+            }
+            EditorCommand::NextBookmark => self.go_to_bookmark(false),
+            EditorCommand::PreviousBookmark => self.go_to_bookmark(true),
+            EditorCommand::JumpBack => {
+                self.is_selecting = None;
+                if !self.editor_state.jump_back() {
+                    self.status_bar.notify("nothing to jump back to");
+                }
+            }
+            EditorCommand::JumpForward => {
+                self.is_selecting = None;
+                if !self.editor_state.jump_forward() {
+                    self.status_bar.notify("nothing to jump forward to");
+                }
+            }
+            EditorCommand::Cut => self.clipboard.write(self.editor_state.cut()),
+            EditorCommand::Copy => self.clipboard.write(self.editor_state.copy()),
+            EditorCommand::Paste => self.paste(false),
+            EditorCommand::SelectAll => {
+                self.editor_state.select_all();
+            }
+            EditorCommand::SelectWord => self.editor_state.word_select(),
+            EditorCommand::AddCaretAbove => self.editor_state.add_caret_vertically(Direction::Up),
+            EditorCommand::AddCaretBelow => self.editor_state.add_caret_vertically(Direction::Down),
+            EditorCommand::Undo => self.editor_state.undo(),
+            EditorCommand::Redo => self.editor_state.redo(),
+            EditorCommand::BrowseHistory => self.open_history_browser(),
+            EditorCommand::RestoreBackup => self.open_backup_picker(),
+            EditorCommand::ToggleDiff => self.toggle_diff_view(),
+            EditorCommand::Commit => self.open_commit_prompt(),
+            EditorCommand::SwitchSnapshot => self.open_branch_picker(),
+            EditorCommand::InsertKnob => self.insert_param_widget(KnobStyle::Knob),
+            EditorCommand::InsertSlider => self.insert_param_widget(KnobStyle::Slider),
+            EditorCommand::InsertPianoRoll => self.insert_piano_roll(),
+            EditorCommand::ToggleWatch => self.toggle_watch(),
+            EditorCommand::ToggleLevels => self.toggle_levels(),
+            EditorCommand::ToggleMusicalTyping => self.toggle_musical_typing(),
+            EditorCommand::CycleQuantize => self.cycle_quantize(),
+            EditorCommand::SwingLess => self.nudge_swing(-SWING_STEP),
+            EditorCommand::SwingMore => self.nudge_swing(SWING_STEP),
+            EditorCommand::TapTempo => self.tap_tempo(),
+            EditorCommand::Reseed => self.reseed(),
+            EditorCommand::Hush => self.hush(),
+            EditorCommand::Panic => self.panic(),
+            EditorCommand::Bounce => self.bounce(),
+            EditorCommand::Freeze => self.freeze(),
+            EditorCommand::RecordSession => self.toggle_recording(),
+            EditorCommand::ReplaySession => self.start_replay(),
+            EditorCommand::SaveToLibrary => self.save_to_library(),
+            EditorCommand::OpenLibrary => self.open_library(),
+            EditorCommand::ShowPluginEditor => self.show_plugin_editor(),
+            EditorCommand::AudioSettings => self.open_audio_settings(),
+            EditorCommand::ToggleSplit => self.toggle_split(renderer),
+            EditorCommand::ToggleConsole => {
+                self.console.toggle();
+                self.ui_needs_redraw = true;
+            }
+            EditorCommand::ToggleClipView => {
+                self.clip_view.toggle();
+                self.ui_needs_redraw = true;
+            }
+            EditorCommand::ZoomIn => self.zoom(renderer, 1.0),
+            EditorCommand::ZoomOut => self.zoom(renderer, -1.0),
+            EditorCommand::ResetZoom => self.zoom(renderer, 0.0),
+            EditorCommand::CopyToRegister(register) => self.copy_to_register(register),
+            EditorCommand::PasteFromRegister(register) => self.paste_from_register(register),
+            EditorCommand::CaptureSnapshot(slot) => self.capture_snapshot(slot),
+        }
+    }
+
+    /**
+        Copies what's selected into a numbered register (what every caret selected, apart), leaving the default one (and the system clipboard) as it is
+    */
+    fn copy_to_register(&mut self, register: usize) {
+        let copied = self.editor_state.copy();
+        if copied.is_empty() {
+            self.status_bar.notify("nothing's selected to copy");
+            return;
+        }
+
+        self.clipboard.write_register(register, copied);
+        self.status_bar.notify(format!("copied to register {}", register));
+    }
+
+    /**
+        Pastes what's in a numbered register, back per caret if it was copied with as many carets
+    */
+    fn paste_from_register(&mut self, register: usize) {
+        match self.clipboard.read_register(register) {
+            Some(data) => self.editor_state.paste(data),
+            None => self
+                .status_bar
+                .notify(format!("register {} is empty", register)),
+        }
+    }
+
+    /**
+        Cmd+Shift+S: searches (and replaces in) all of the project's code files, where the open document counts as what's in the editor, not what's on disk
+    */
+    fn open_project_search(&mut self) {
+        let root = self.workspace.root().to_path_buf();
+        let current = self.document_path();
+
+        let files = search::project_files(
+            &root,
+            &self.workspace.exclude,
+            &current,
+            self.editor_state.linedata().to_string(),
+        );
+
+        self.project_search.open(files);
+        self.ui_needs_redraw = true;
+    }
+
+    fn project_search_key(&mut self, key: Key, ctx: &Context) {
+        self.ui_needs_redraw = true;
+
+        match key {
+            Key::Escape => {
+                self.project_search.close();
+            }
+            Key::Enter if ctx.meta_or_ctrl => {
+                self.replace_in_project();
+            }
+            Key::Enter => {
+                self.open_search_hit();
+            }
+            Key::Tab => {
+                self.project_search.switch_field();
+            }
+            Key::ArrowUp => {
+                self.project_search.move_selection(-1);
+            }
+            Key::ArrowDown => {
+                self.project_search.move_selection(1);
+            }
+            Key::Backspace => {
+                self.project_search.backspace();
+            }
+            Key::Character(s) if !ctx.meta_or_ctrl => {
+                self.project_search.type_str(s.as_str());
+            }
+            _ => {}
+        }
+    }
+
+    /**
+        Jumps to the selected match, opening its file first if it's not the open document. (There's only ever the one document, so the one that was open is backed up, and can be restored with Cmd+Shift+R.)
+    */
+    fn open_search_hit(&mut self) {
+        let Some(hit) = self.project_search.selected_hit().cloned() else {
+            return;
+        };
+
+        let file = self.project_search.file(&hit);
+        let (path, name, source, open) = (
+            file.path.clone(),
+            file.name.clone(),
+            file.source.clone(),
+            file.open,
+        );
+
+        self.project_search.close();
+
+        if !open {
+            self.backups.backup(self.editor_state.linedata());
+            self.replace_document(relink_widgets(&source, &self.widget_manager));
+            self.opened = Some(path);
+            // (the diff is with what's committed of this file now)
+            self.rebase_diff_view();
+            self.status_bar.notify(format!("opened {}", name));
+        }
+
+        self.jump_to(Pos {
+            row: hit.row as i32,
+            col: hit.col as i32,
+        });
+    }
+
+    /**
+        Replaces every match, in the open document (as one edit, so it can be undone) and in the other files (on disk)
+    */
+    fn replace_in_project(&mut self) {
+        let replaced = self.project_search.replaced();
+        if replaced.is_empty() {
+            return;
+        }
+
+        let written = search::write_replaced(&replaced);
+        self.project_search.close();
+
+        if let Some(source) = &written.document {
+            self.replace_document(relink_widgets(source, &self.widget_manager));
+        }
+
+        if written.errors.is_empty() {
+            self.status_bar.notify(format!(
+                "replaced {} matches in {} files",
+                written.matches, written.files
+            ));
+        } else {
+            self.status_bar
+                .notify(format!("could not replace in {}", written.errors.join(", ")));
+        }
+    }
+
+    fn go_to_bookmark(&mut self, back: bool) {
+        self.is_selecting = None;
+        if !self.editor_state.jump_to_bookmark(back) {
+            self.status_bar.notify("no bookmarks (Cmd+F2 adds one)");
+        }
+    }
+
+    /**
+        Opens the completions for the name before the caret (when there's just the one caret)
+    */
+    fn open_completion(&mut self) {
+        if let [caret] = self.editor_state.caret_positions()[..] {
+            self.completion.open(self.editor_state.linedata(), caret);
+            self.doc_hover.unhover();
+            self.ui_needs_redraw = true;
+        }
+    }
+
+    fn completion_key(&mut self, key: Key) {
+        self.ui_needs_redraw = true;
+
+        // (the typing may have gone on since it was last drawn)
+        if let [caret] = self.editor_state.caret_positions()[..] {
+            self.completion.follow(self.editor_state.linedata(), caret);
+        }
+
+        match key {
+            Key::ArrowUp => self.completion.move_selection(-1),
+            Key::ArrowDown => self.completion.move_selection(1),
+            Key::Enter | Key::Tab => {
+                if let Some(rest) = self.completion.accept() {
+                    self.editor_state.write(&rest);
+                }
+            }
+            _ => self.completion.close(),
+        }
+    }
+
+    fn open_symbol_picker(&mut self) {
+        self.symbol_picker.open();
+        self.ui_needs_redraw = true;
+    }
+
+    fn symbol_picker_key(&mut self, key: Key, ctx: &Context) {
+        self.ui_needs_redraw = true;
+
+        match key {
+            Key::Escape => {
+                self.symbol_picker.close();
+            }
+            Key::Enter => {
+                if let Some(pos) = self.symbol_picker.selected_pos(&self.outline) {
+                    self.jump_to(pos);
+                }
+                self.symbol_picker.close();
+            }
+            Key::ArrowUp => {
+                self.symbol_picker.move_selection(&self.outline, -1);
+            }
+            Key::ArrowDown => {
+                self.symbol_picker.move_selection(&self.outline, 1);
+            }
+            Key::Backspace => {
+                self.symbol_picker.backspace();
+            }
+            Key::Character(s) if !ctx.meta_or_ctrl => {
+                self.symbol_picker.type_str(s.as_str());
+            }
+            _ => {}
+        }
+    }
+
+    /**
+        Saves the selected definition to the library (or the one the caret is in, if nothing's selected), under the name it declares
+    */
+    fn save_to_library(&mut self) {
+        let linedata = self.editor_state.linedata();
+
+        let code = match self.editor_state.copy().into_iter().next() {
+            Some(selected) => selected,
+            None => {
+                let Some(&caret) = self.editor_state.caret_positions().first() else {
+                    return;
+                };
+
+                let source = linedata.to_string();
+                let Some(span) = statement_at(&source, linedata.pos_to_offset(caret)) else {
+                    self.status_bar.notify("nothing to save (select a definition)");
+                    return;
+                };
+
+                linedata.copy_range(span_to_range(linedata, span))
+            }
+        };
+
+        let Some(preset) = Preset::from_code(&code, &self.widget_manager) else {
+            self.status_bar
+                .notify("nothing to save (the code doesn't declare anything)");
+            return;
+        };
+
+        match preset.save() {
+            Ok(true) => self
+                .status_bar
+                .notify(format!("replaced {} in the library", preset.name)),
+            Ok(false) => self
+                .status_bar
+                .notify(format!("saved {} to the library", preset.name)),
+            Err(e) => self
+                .status_bar
+                .notify(format!("could not save {}: {}", preset.name, e)),
+        }
+    }
+
+    fn open_library(&mut self) {
+        self.library_panel.open();
+        self.ui_needs_redraw = true;
+    }
+
+    /// (stopping whatever was auditioned)
+    fn close_library(&mut self) {
+        self.library_panel.close();
+
+        if let Some(engine) = &self.engine {
+            stop_audition(engine);
+        }
+    }
+
+    fn library_panel_key(&mut self, key: Key, ctx: &Context) {
+        self.ui_needs_redraw = true;
+
+        match key {
+            Key::Escape => {
+                self.close_library();
+            }
+            Key::Enter => {
+                let preset = self.library_panel.selected().cloned();
+                self.close_library();
+
+                if let Some(preset) = preset {
+                    self.insert_preset(&preset);
+                }
+            }
+            Key::Tab => {
+                if let Some(preset) = self.library_panel.selected().cloned() {
+                    self.audition_preset(&preset);
+                }
+            }
+            Key::ArrowUp => {
+                self.library_panel.move_selection(-1);
+            }
+            Key::ArrowDown => {
+                self.library_panel.move_selection(1);
+            }
+            Key::Backspace => {
+                self.library_panel.backspace();
+            }
+            Key::Character(s) if !ctx.meta_or_ctrl => {
+                self.library_panel.type_str(s.as_str());
+            }
+            _ => {}
+        }
+    }
+
+    /**
+        Inserts a preset above the line the caret is on, with new sample widgets for its samples, and whatever it declares renamed if the document already declares it
+    */
+    /**
+        Plays what the preset defines (its name), compiled from its code on its own, with its samples. Its other widgets aren't there, so code that refers to them can't be auditioned.
+    */
+    fn audition_preset(&mut self, preset: &Preset) {
+        let Some(engine) = &self.engine else {
+            self.status_bar.notify("the audio engine isn't running");
+            return;
+        };
+
+        let root = self.workspace.root();
+        let evaluation = evaluate_source_in(&preset.code, self.seed, root);
+        let key = live_language::Key::new(&preset.name);
+
+        let compiled = match evaluation.errors.first() {
+            Some((_, message)) => Err(message.clone()),
+            None => match evaluation.values.iter().find(|(k, _)| *k == key) {
+                Some((_, value)) => {
+                    Compiler::new(engine, root, &preset.widgets(), &mut self.samples)
+                        .compile_value(&evaluation, &key, value)
+                }
+                None => Err(format!("{} isn't defined", preset.name)),
+            },
+        };
+
+        match compiled {
+            Ok(target) => audition_node(engine, target.node, target.placement),
+            Err(message) => self
+                .status_bar
+                .notify(format!("can't audition {}: {}", preset.name, message)),
+        }
+    }
+
+    fn insert_preset(&mut self, preset: &Preset) {
+        let Some(&caret) = self.editor_state.caret_positions().last() else {
+            return;
+        };
+
+        let taken = self
+            .outline
+            .entries
+            .iter()
+            .map(|entry| entry.symbol.name.clone())
+            .collect();
+        let code = format!("{}\n", preset.code_for(&taken));
+
+        let lines = code
+            .split('\n')
+            .map(|line| {
+                let mut tokens = vec![];
+                let mut rest = line;
+
+                while let Some(ch) = rest.chars().next() {
+                    if let Some((index, len)) = preset.sample_at(rest) {
+                        let widget = SampleWidget::from_file(
+                            &preset.samples[index],
+                            self.workspace.sample_paths(),
+                        );
+                        tokens.push(Token::Widget(self.widget_manager.add(Box::new(widget))));
+                        rest = &rest[len..];
+                    } else {
+                        tokens.push(Token::Char(ch));
+                        rest = &rest[ch.len_utf8()..];
+                    }
+                }
+
+                tokens
+            })
+            .collect::<Vec<_>>();
+
+        let pos = Pos {
+            row: caret.row,
+            col: 0,
+        };
+
+        self.is_selecting = None;
+        self.editor_state.insert(pos, lines.into(), true);
+        self.status_bar
+            .notify(format!("inserted {} from the library", preset.name));
+    }
+
+    fn open_history_browser(&mut self) {
+        self.editor_state.checkpoint();
+        self.history_browser.open();
+        self.ui_needs_redraw = true;
+    }
+
+    fn history_browser_key(&mut self, key: Key) {
+        self.ui_needs_redraw = true;
+
+        let current = self.editor_state.history().current();
+
+        match key {
+            Key::Escape | Key::Enter => {
+                self.history_browser.close();
+            }
+            // (newest first, so up is forward in time)
+            Key::ArrowUp => {
+                self.editor_state.jump_to_history(current + 1);
+            }
+            Key::ArrowDown if current > 0 => {
+                self.editor_state.jump_to_history(current - 1);
+            }
+            _ => {}
+        }
+    }
+
+    /**
+        Cmd+Shift+D: shows (or hides again) what changed since the code was last committed (at git HEAD), or outside of a git repository since it was last backed up, with F7 and Shift+F7 going to the next and previous change
+    */
+    fn toggle_diff_view(&mut self) {
+        if self.diff_view.is_open() {
+            self.diff_view.close();
+        } else {
+            let (base, what) = self.diff_base();
+            self.diff_view.open(base, self.editor_state.linedata());
+
+            let n = self.diff_view.hunks().len();
+            self.status_bar.notify(match n {
+                0 => format!("no changes since the last {}", what),
+                1 => format!("1 change since the last {}", what),
+                n => format!("{} changes since the last {}", n, what),
+            });
+        }
+
+        self.ui_needs_redraw = true;
+    }
+
+    /**
+        Where the document is on disk: the code file it was opened from, or else the session
+    */
+    fn document_path(&self) -> PathBuf {
+        self.opened
+            .clone()
+            .unwrap_or_else(|| self.workspace.root().join(SESSION_FILE))
+    }
+
+    /// (relative to the project root, for messages)
+    fn document_name(&self) -> String {
+        let path = self.document_path();
+        path.strip_prefix(self.workspace.root())
+            .unwrap_or(&path)
+            .display()
+            .to_string()
+    }
+
+    /**
+        What the diff mode compares with: the last commit (in a git repository), or else the last backup
+    */
+    fn diff_base(&self) -> (LineData, &'static str) {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(source) = self
+            .git
+            .as_ref()
+            .and_then(|git| git.committed(&self.document_path()))
+        {
+            return (relink_widgets(&source, &self.widget_manager), "commit");
+        }
+
+        (self.backups.last_saved(&self.widget_manager), "backup")
+    }
+
+    /**
+        (After committing, or switching branches)
+    */
+    fn rebase_diff_view(&mut self) {
+        if self.diff_view.is_open() {
+            let (base, _) = self.diff_base();
+            self.diff_view.open(base, self.editor_state.linedata());
+        }
+    }
+
+    /**
+        Cmd+Shift+C: commits the document (the session, or the code file that was opened) to the branch that's checked out, with a message from a mini prompt
+    */
+    #[cfg(not(target_arch = "wasm32"))]
+    fn open_commit_prompt(&mut self) {
+        self.ui_needs_redraw = true;
+
+        let Some(git) = &self.git else {
+            self.status_bar.notify("not in a git repository");
+            return;
+        };
+
+        self.commit_prompt.open(git.current_branch());
+    }
+
+    // (there's no git in the browser, so neither the commit prompt nor the branch picker ever opens there)
+    #[cfg(target_arch = "wasm32")]
+    fn open_commit_prompt(&mut self) {
+        self.ui_needs_redraw = true;
+        self.status_bar.notify("not in a git repository");
+    }
+
+    fn commit_prompt_key(&mut self, key: Key, ctx: &Context) {
+        self.ui_needs_redraw = true;
+
+        match key {
+            Key::Escape => {
+                self.commit_prompt.close();
+            }
+            Key::Enter => {
+                if let Some(message) = self.commit_prompt.message().map(str::to_string) {
+                    self.commit_prompt.close();
+                    self.commit(&message);
+                }
+            }
+            Key::Backspace => {
+                self.commit_prompt.backspace();
+            }
+            Key::Space => {
+                self.commit_prompt.type_str(" ");
+            }
+            Key::Character(s) if !ctx.meta_or_ctrl => {
+                self.commit_prompt.type_str(s.as_str());
+            }
+            _ => {}
+        }
+    }
+
+    /**
+        Cmd+R: renames the name at the caret (wherever it refers to the same thing), to a name from a mini prompt
+    */
+    fn open_rename_prompt(&mut self) {
+        self.ui_needs_redraw = true;
+
+        let linedata = self.editor_state.linedata();
+        let Some(&caret) = self.editor_state.caret_positions().first() else {
+            return;
+        };
+        let Some(word) = linedata.find_word_at(caret) else {
+            self.status_bar.notify("there's no name here to rename");
+            return;
+        };
+
+        let name = linedata.copy_range(word).to_string();
+        let offset = linedata.pos_to_offset(caret);
+        self.rename_prompt.open(name, offset);
+    }
+
+    /**
+        F12: jumps to where the name at the caret is declared
+    */
+    fn go_to_definition(&mut self) {
+        let linedata = self.editor_state.linedata();
+        let Some(&caret) = self.editor_state.caret_positions().first() else {
+            return;
+        };
+
+        let source = linedata.to_string();
+        let definition = definition_at(&source, linedata.pos_to_offset(caret))
+            .map(|span| loc_to_pos(linedata, span.start));
+
+        match definition {
+            Some(pos) => self.jump_to(pos),
+            None => self
+                .status_bar
+                .notify("there's no definition here to go to (of a name in this document)"),
+        }
+    }
+
+    fn rename_prompt_key(&mut self, key: Key, ctx: &Context) {
+        self.ui_needs_redraw = true;
+
+        match key {
+            Key::Escape => {
+                self.rename_prompt.close();
+            }
+            Key::Enter => {
+                if let Some((name, offset)) = self.rename_prompt.rename() {
+                    let name = name.to_string();
+                    // (a name that's refused keeps the prompt open, to try another one)
+                    if self.rename_symbol(offset, &name) {
+                        self.rename_prompt.close();
+                    }
+                }
+            }
+            Key::Backspace => {
+                self.rename_prompt.backspace();
+            }
+            Key::Character(s) if !ctx.meta_or_ctrl => {
+                self.rename_prompt.type_str(s.as_str());
+            }
+            _ => {}
+        }
+    }
+
+    /**
+        Puts the new name everywhere at once, from the last one to the first (so the ones before don't move). It's all in response to the one key press, so it's undone as a whole.
+    */
+    fn rename_symbol(&mut self, offset: usize, name: &str) -> bool {
+        let source = self.editor_state.linedata().to_string();
+
+        let spans = match rename_symbol(&source, offset, name) {
+            Ok(spans) => spans,
+            Err(e) => {
+                self.status_bar.notify(format!("could not rename: {}", e));
+                return false;
+            }
+        };
+
+        let ranges = spans
+            .iter()
+            .map(|&span| span_to_range(self.editor_state.linedata(), span))
+            .collect::<Vec<_>>();
+
+        self.is_selecting = None;
+        for range in ranges.into_iter().rev() {
+            self.editor_state.remove(range);
+            self.editor_state.insert(range.start, LineData::from(name), false);
+        }
+
+        self.status_bar.notify(match spans.len() {
+            1 => format!("renamed to {}", name),
+            n => format!("renamed to {} ({} places)", name, n),
+        });
+        true
+    }
+
+    /**
+        Cmd+Shift+X: puts the selected expression in a `let` of its own, right above the statement it's in, and then asks for a better name than the one it got
+    */
+    fn extract_definition(&mut self) {
+        self.ui_needs_redraw = true;
+
+        let [selection] = self.editor_state.selected_ranges()[..] else {
+            self.status_bar.notify("select an expression to extract");
+            return;
+        };
+
+        let linedata = self.editor_state.linedata();
+        let source = linedata.to_string();
+        let selection =
+            linedata.pos_to_offset(selection.start)..linedata.pos_to_offset(selection.end);
+
+        let extraction = match extract_definition(&source, selection) {
+            Ok(extraction) => extraction,
+            Err(e) => {
+                self.status_bar.notify(format!("could not extract: {}", e));
+                return;
+            }
+        };
+
+        let edits = extraction
+            .edits
+            .iter()
+            .map(|(span, text)| (span_to_range(self.editor_state.linedata(), *span), text))
+            .collect::<Vec<_>>();
+
+        self.is_selecting = None;
+        for (range, text) in edits.into_iter().rev() {
+            if range.start != range.end {
+                self.editor_state.remove(range);
+            }
+            let inserted = relink_widgets(text, &self.widget_manager);
+            self.editor_state.insert(range.start, inserted, false);
+        }
+
+        self.rename_prompt
+            .open(extraction.name, extraction.name_offset);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn commit(&mut self, message: &str) {
+        let Some(git) = &self.git else {
+            return;
+        };
+
+        let document = self.document_path();
+        let source = self.editor_state.linedata().to_string();
+        match git.commit(&document, &source, message) {
+            Ok(()) => self
+                .status_bar
+                .notify(format!("committed {}", self.document_name())),
+            Err(e) => self.status_bar.notify(format!("could not commit: {}", e)),
+        }
+
+        self.rebase_diff_view();
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn commit(&mut self, _message: &str) {}
+
+    /**
+        Cmd+Shift+G: switches to another named snapshot (a branch) of the document, or makes a new one
+    */
+    #[cfg(not(target_arch = "wasm32"))]
+    fn open_branch_picker(&mut self) {
+        self.ui_needs_redraw = true;
+
+        let Some(git) = &self.git else {
+            self.status_bar.notify("not in a git repository");
+            return;
+        };
+
+        self.branch_picker
+            .open(git.branches(), git.current_branch());
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn open_branch_picker(&mut self) {
+        self.ui_needs_redraw = true;
+        self.status_bar.notify("not in a git repository");
+    }
+
+    fn branch_picker_key(&mut self, key: Key, ctx: &Context) {
+        self.ui_needs_redraw = true;
+
+        match key {
+            Key::Escape => {
+                self.branch_picker.close();
+            }
+            Key::Enter => {
+                let pick = self.branch_picker.selected();
+                self.branch_picker.close();
+                if let Some(pick) = pick {
+                    self.pick_branch(pick);
+                }
+            }
+            Key::ArrowUp => {
+                self.branch_picker.move_selection(-1);
+            }
+            Key::ArrowDown => {
+                self.branch_picker.move_selection(1);
+            }
+            Key::Backspace => {
+                self.branch_picker.backspace();
+            }
+            // (branch names can't have spaces)
+            Key::Space => {
+                self.branch_picker.type_str("-");
+            }
+            Key::Character(s) if !ctx.meta_or_ctrl => {
+                self.branch_picker.type_str(s.as_str());
+            }
+            _ => {}
+        }
+    }
+
+    /**
+        Checks out a snapshot, loading its document (in one edit, so it can be undone), or makes a new one
+    */
+    #[cfg(not(target_arch = "wasm32"))]
+    fn pick_branch(&mut self, pick: BranchPick) {
+        let Some(git) = &self.git else {
+            return;
+        };
+
+        match pick {
+            BranchPick::Checkout(branch) => match git.checkout(&branch, &self.document_path()) {
+                Ok(Some(source)) => {
+                    let linedata = relink_widgets(&source, &self.widget_manager);
+                    self.replace_document(linedata);
+                    self.status_bar.notify(format!("loaded {}", branch));
+                }
+                Ok(None) => self.status_bar.notify(format!(
+                    "switched to {}, which has no {}",
+                    branch,
+                    self.document_name()
+                )),
+                Err(e) => self
+                    .status_bar
+                    .notify(format!("could not check out {}: {}", branch, e)),
+            },
+            BranchPick::Create(branch) => match git.create_branch(&branch) {
+                Ok(()) => self.status_bar.notify(format!("made snapshot {}", branch)),
+                Err(e) => self
+                    .status_bar
+                    .notify(format!("could not make snapshot {}: {}", branch, e)),
+            },
+        }
+
+        self.rebase_diff_view();
+        self.ui_needs_redraw = true;
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn pick_branch(&mut self, _pick: BranchPick) {}
+
+    /**
+        Cmd+,: which audio devices to use, and how
+    */
+    fn open_audio_settings(&mut self) {
+        self.ui_needs_redraw = true;
+        self.audio_settings
+            .open(AudioSettings::load(), output_devices(), input_devices());
+    }
+
+    fn audio_settings_key(&mut self, key: Key) {
+        self.ui_needs_redraw = true;
+
+        match key {
+            Key::Escape => {
+                self.audio_settings.close();
+            }
+            Key::Enter => {
+                self.switch_audio_devices();
+            }
+            Key::ArrowUp => {
+                self.audio_settings.move_selection(-1);
+            }
+            Key::ArrowDown => {
+                self.audio_settings.move_selection(1);
+            }
+            Key::ArrowLeft => {
+                self.audio_settings.cycle(-1);
+            }
+            Key::ArrowRight | Key::Space => {
+                self.audio_settings.cycle(1);
+            }
+            _ => {}
+        }
+    }
+
+    /**
+        Switches the engine to the devices in the audio settings (everything keeps playing), and remembers them for next time. (The panel stays open, to see whether it worked out.)
+    */
+    fn switch_audio_devices(&mut self) {
+        if self.engine.is_none() {
+            self.status_bar.notify("the audio engine isn't running");
+            return;
+        }
+
+        let settings = self.audio_settings.settings().clone();
+        settings.save();
+
+        if let Some(engine) = &self.engine {
+            engine.set_economize(settings.economize);
+        }
+        let _ = self.switch_devices.send(settings.devices());
+        self.status_bar.notify(format!(
+            "switching to {}",
+            settings.output.as_deref().unwrap_or("the default output")
+        ));
+    }
+
+    /**
+        Cmd+Shift+I: opens the editor of a plugin that's playing, in a window of its own (and with more than one, the next one every time)
+    */
+    fn show_plugin_editor(&mut self) {
+        self.ui_needs_redraw = true;
+
+        let Some(engine) = &self.engine else {
+            self.status_bar.notify("the audio engine isn't running");
+            return;
+        };
+
+        let running = engine.running_plugins();
+        if running.is_empty() {
+            self.status_bar.notify("no plugins playing");
+            return;
+        }
+
+        let i = self.next_plugin_editor % running.len();
+        self.next_plugin_editor = i + 1;
+
+        match engine.show_plugin_editor(i) {
+            Ok(()) if running.len() == 1 => self.status_bar.notify(running[i].clone()),
+            Ok(()) => self
+                .status_bar
+                .notify(format!("{} ({} of {})", running[i], i + 1, running.len())),
+            Err(e) => self.status_bar.notify(e),
+        }
+    }
+
+    fn next_change(&mut self, forward: bool) {
+        let row = self
+            .editor_state
+            .caret_positions()
+            .last()
+            .map_or(0, |caret| caret.row);
+
+        match self.diff_view.next_change(row, forward) {
+            Some(row) => {
+                // (lines that were removed at the very end are marked after the last line)
+                let row = row.min(self.editor_state.linedata().len() as i32 - 1);
+                self.editor_state.set_single_caret(Pos { row, col: 0 });
+            }
+            None => self.status_bar.notify("no changes"),
+        }
+
+        self.ui_needs_redraw = true;
+    }
+
+    fn open_backup_picker(&mut self) {
+        self.backup_picker.open(&self.backups);
+        self.ui_needs_redraw = true;
+    }
+
+    fn backup_picker_key(&mut self, key: Key) {
+        self.ui_needs_redraw = true;
+
+        match key {
+            Key::Escape => {
+                self.backup_picker.close();
+            }
+            Key::Enter => {
+                if let Some(backup) = self.backup_picker.selected().cloned() {
+                    self.restore_backup(&backup);
+                }
+                self.backup_picker.close();
+            }
+            Key::ArrowUp => {
+                self.backup_picker.move_selection(-1);
+            }
+            Key::ArrowDown => {
+                self.backup_picker.move_selection(1);
+            }
+            _ => {}
+        }
+    }
+
+    fn restore_backup(&mut self, backup: &Backup) {
+        match self.backups.read(backup, &self.widget_manager) {
+            Ok(linedata) => self.replace_document(linedata),
+            Err(e) => tracing::warn!("Could not restore backup: {}", e),
+        }
+    }
+
+    /**
+        Replaces all of the code (in one edit, so it can be undone)
+    */
+    fn replace_document(&mut self, linedata: LineData) {
+        self.is_selecting = None;
+        self.editor_state.remove(Range {
+            start: (0, 0).into(),
+            end: self.editor_state.linedata().end(),
+        });
+        self.editor_state.insert((0, 0).into(), linedata, true);
+    }
+
+    /**
+        Evaluates the selected code, or else the top-level statement the caret is in (like in SuperCollider), on top of what's live, and flashes what was evaluated. The statements it touches are taken as they are now, and the rest as they were when they were last evaluated (so that evaluating a `play` doesn't also change the `def` it plays, until that's evaluated too).
+    */
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    fn evaluate(&mut self) {
+        let linedata = self.editor_state.linedata();
+        let source = linedata.to_string();
+
+        let (code, region, ranges) = if self.editor_state.copy().is_empty() {
+            let spans = self
+                .editor_state
+                .caret_positions()
+                .into_iter()
+                .filter_map(|caret| statement_at(&source, linedata.pos_to_offset(caret)))
+                .collect::<Vec<_>>();
+
+            let code = spans
+                .iter()
+                .map(|span| &source[span.range()])
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            let ranges = spans.iter().map(|span| span.range()).collect();
+
+            let region = spans
+                .into_iter()
+                .flat_map(|span| linedata.line_selections(span_to_range(linedata, span)))
+                .collect();
+
+            (code, region, ranges)
+        } else {
+            let code = self
+                .editor_state
+                .copy()
+                .iter()
+                .map(LineData::to_string)
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            let ranges = self
+                .editor_state
+                .selected_ranges()
+                .into_iter()
+                .map(|range| linedata.pos_to_offset(range.start)..linedata.pos_to_offset(range.end))
+                .collect::<Vec<_>>();
+
+            (code, self.editor_state.visual_selections(), ranges)
+        };
+
+        if code.trim().is_empty() {
+            return;
+        }
+
+        let parse_started_at = Instant::now();
+        let errors = syntax_errors(&code);
+        tracing::debug!(
+            target: "language",
+            lines = code.lines().count(),
+            "parsed in {:.1}ms",
+            parse_started_at.elapsed().as_secs_f32() * 1000.0
+        );
+
+        if !errors.is_empty() {
+            for (_, message) in errors {
+                tracing::warn!("Could not evaluate: {}", message);
+            }
+            return;
+        }
+
+        self.report_eval_errors(&region);
+        let live = overlay_statements(&self.live, &source, &ranges);
+        self.latch(&live);
+        self.live = live;
+        self.sync_timers();
+        self.sync_clips();
+        self.play();
+
+        // (when it's quantized, it only flashes when it lands)
+        match &self.engine {
+            Some(engine) if self.workspace.quantize != Quantize::Now => {
+                let mut transport = engine.transport();
+                transport.quantize = self.workspace.quantize;
+                self.pending_swaps
+                    .push(region, Instant::now() + transport.until_boundary());
+                self.ui_needs_redraw = true;
+            }
+            _ => self.flash = Some((region, Instant::now())),
+        }
+    }
+
+    /**
+        Glides the numbers that changed since the last evaluation (like the cutoff of a filter) from their old values to their new ones, over their `ease(..)` or else the project's ease, instead of letting them jump. They're paired up by their keys, so that `fx.f` is the `f` setting of `def fx`, like when it's bound to a widget.
+    */
+    fn latch(&mut self, live: &str) {
+        let evaluation = evaluate_source_in(live, self.seed, self.workspace.root());
+
+        if let (Some(engine), Some(evaluated)) = (&self.engine, &self.evaluated) {
+            for latch in latches(&evaluated.values, &evaluation.values) {
+                let ease = latch
+                    .ease
+                    .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+                    .map_or(self.workspace.ease, Duration::from_secs_f64);
+
+                // (when it's quantized, it glides from when the code it's in lands, see `play`)
+                match self.workspace.quantize {
+                    Quantize::Now => {
+                        engine.ease_param(latch.key.to_string(), latch.to as f32, ease)
+                    }
+                    _ => engine.schedule_ease(latch.key.to_string(), latch.to as f32, ease),
+                }
+            }
+        }
+
+        // (so that MIDI notes play in what the code is tuned to, too)
+        let tuning = &evaluation.tuning;
+        let retuned = self.evaluated.as_ref().map(|evaluated| &evaluated.tuning) != Some(tuning);
+        if let Some(engine) = &self.engine && retuned {
+            engine.set_tuning(live_engine::Tuning::new(|note| {
+                tuning.freq(note as i64).map(|freq| freq as f32)
+            }));
+        }
+
+        self.evaluated = Some(evaluation);
+    }
+
+    /**
+        Has the engine time the document's `every`, `after` and `at` (in beats, at the tempo as it is now), and stop timing the ones that aren't in it anymore. The ones that didn't change keep running, with their count.
+    */
+    fn sync_timers(&mut self) {
+        let timers = self
+            .evaluated
+            .as_ref()
+            .map_or(vec![], |evaluation| evaluation.timers.clone());
+
+        if let Some(engine) = &self.engine {
+            let beats = |seconds: f64| seconds * self.workspace.tempo / 60.0;
+
+            for timer in &self.timers {
+                if !timers.iter().any(|t| t.key == timer.key) {
+                    engine.clear_timer(timer.key.to_string());
+                    // (and what it played, see `poll_timers`)
+                    engine.stop(timer.key.to_string());
+                }
+            }
+
+            for timer in &timers {
+                let timing = match timer.timing {
+                    Timing::Every(seconds) => live_engine::Timing::Every(beats(seconds)),
+                    Timing::After(seconds) => live_engine::Timing::After(beats(seconds)),
+                    Timing::At(bar) => live_engine::Timing::At(bar),
+                };
+
+                // (the same timer as before in seconds, which are another number of beats when the tempo changed, so it keeps going)
+                if self
+                    .timers
+                    .iter()
+                    .any(|t| t.key == timer.key && t.timing == timer.timing)
+                {
+                    engine.retime_timer(timer.key.to_string(), timing);
+                } else {
+                    engine.set_timer(timer.key.to_string(), timing);
+                }
+            }
+        }
+
+        self.timers = timers;
+    }
+
+    /**
+        Has the engine play what's live (see `Compiler`), right away, or from the next boundary on when it's quantized, and stop what it doesn't play anymore. What can't be played is left out, and said in the status bar.
+    */
+    fn play(&mut self) {
+        let (Some(engine), Some(evaluation)) = (&self.engine, &self.evaluated) else {
+            return;
+        };
+
+        let widgets = self.widget_manager.values_in(self.editor_state.linedata());
+        let (targets, errors) =
+            Compiler::new(engine, self.workspace.root(), &widgets, &mut self.samples)
+                .compile(evaluation, &self.live);
+
+        let played = targets
+            .iter()
+            .map(|target| target.name.clone())
+            .collect::<Vec<_>>();
+        for name in &self.played {
+            if !played.contains(name) {
+                engine.stop(name);
+            }
+        }
+
+        for target in targets {
+            engine.place(&target.name, target.placement);
+            match self.workspace.quantize {
+                Quantize::Now => engine.play(target.name, target.node),
+                _ => engine.schedule(target.name, target.node),
+            }
+        }
+
+        for (name, message) in &errors {
+            tracing::warn!("Could not play {}: {}", name, message);
+        }
+        if let Some((name, message)) = errors.first() {
+            self.status_bar
+                .notify(format!("can't play {}: {}", name, message));
+        }
+
+        self.played = played;
+    }
+
+    /**
+        Puts the document's `clip(..)`s in the clip launcher's grid, and tells the engine which targets are clips, so they're only heard while they're launched
+    */
+    fn sync_clips(&mut self) {
+        let source = self.editor_state.linedata().to_string();
+
+        if self.clip_view.set_clips(clips(&source))
+            && let Some(engine) = &self.engine
+        {
+            self.clip_view.apply(engine);
+        }
+        self.ui_needs_redraw = true;
+    }
+
+    /**
+        Launches clips (or stops their tracks) on the next bar, from the clip launcher, as (track, clip)
+    */
+    fn launch_clips(&mut self, launches: Vec<(String, Option<String>)>) {
+        self.ui_needs_redraw = true;
+
+        let Some(engine) = &self.engine else {
+            self.status_bar.notify("the audio engine isn't running");
+            return;
+        };
+
+        for (track, clip) in launches {
+            engine.launch(track, clip);
+        }
+    }
+
+    /**
+        Calls back the timers that fired, showing what they watched
+    */
+    fn poll_timers(&mut self) {
+        let Some(engine) = &self.engine else {
+            return;
+        };
+
+        for fired in engine.fired_timers() {
+            let Some(timer) = self.timers.iter().find(|t| t.key.to_string() == fired.id) else {
+                continue;
+            };
+
+            let evaluation = timer.fire(fired.count);
+            for (_, message) in &evaluation.errors {
+                tracing::warn!("{} failed: {}", timer.key, message);
+            }
+
+            // (what it returns plays as a target of its own, when it's a signal: it fired at `fired.sample`, which has passed by now, so it lands right away, or at the next boundary when it's quantized, like evaluated code)
+            if let Some((key, value @ Value::Node(..))) = evaluation.values.first()
+                && let Some(live) = &self.evaluated
+            {
+                let widgets = self.widget_manager.values_in(self.editor_state.linedata());
+                let compiled =
+                    Compiler::new(engine, self.workspace.root(), &widgets, &mut self.samples)
+                        .compile_value(live, key, value);
+
+                match compiled {
+                    Ok(target) => {
+                        engine.place(&target.name, target.placement);
+                        match self.workspace.quantize {
+                            Quantize::Now => engine.play(target.name, target.node),
+                            _ => engine.schedule(target.name, target.node),
+                        }
+                    }
+                    Err(message) => tracing::warn!("Could not play {}: {}", timer.key, message),
+                }
+            }
+
+            if !evaluation.watched.is_empty() {
+                self.watches.show(evaluation.watched);
+                self.ui_needs_redraw = true;
+            }
+        }
+    }
+
+    /**
+        (While the engine is timing something, so that it's called back soon after it fired)
+    */
+    fn timers_due_at(&self) -> Option<Instant> {
+        (!self.timers.is_empty()).then(|| Instant::now() + TIMER_POLL_INTERVAL)
+    }
+
+    /**
+        Puts what went wrong on the evaluated lines in the gutter: runtime and unit errors in the code, and widgets that don't work (like samples that can't be read)
+    */
+    fn report_eval_errors(&mut self, region: &[LineSelection]) {
+        let linedata = self.editor_state.linedata();
+        let source = linedata.to_string();
+
+        // (the whole document is evaluated, because what was evaluated can depend on the rest of it)
+        let unit_errors = lint(&source, &self.lint_config)
+            .into_iter()
+            .filter(|lint| lint.kind == LintKind::UnitMismatch)
+            .filter_map(|lint| Some((lint.range?, lint.message)));
+
+        let code_errors = evaluate_source(&source)
+            .errors
+            .into_iter()
+            .chain(unit_errors)
+            .map(|(span, message)| (loc_to_pos(linedata, span.start), message, None));
+
+        let widget_errors = self
+            .widget_manager
+            .errors_in(linedata)
+            .into_iter()
+            .map(|(pos, id, message)| (pos, message, Some(id)));
+
+        let errors = code_errors
+            .chain(widget_errors)
+            .filter(|(pos, _, _)| region.iter().any(|line| line.row == pos.row))
+            .collect();
+
+        self.eval_errors.set(linedata, errors);
+        self.ui_needs_redraw = true;
+    }
+
+    fn quick_fix(&mut self, i: usize, fix: QuickFix) {
+        self.ui_needs_redraw = true;
+
+        match fix {
+            QuickFix::RelocateSample(id) => {
+                if self.widget_manager.relocate(id) {
+                    self.eval_errors.resolve(i);
+                } else {
+                    self.eval_errors.close();
+                }
+            }
+            QuickFix::CommentOut => {
+                // (which takes out the errors, as it edits the document)
+                if let Some(offset) = self.eval_errors.entries.get(i).map(|entry| entry.offset) {
+                    self.comment_out_statement(offset);
+                }
+            }
+        }
+    }
+
+    fn context_menu_key(&mut self, key: Key, renderer: &mut Renderer) {
+        self.ui_needs_redraw = true;
+
+        match key {
+            Key::ArrowUp => self.context_menu.move_selection(-1),
+            Key::ArrowDown => self.context_menu.move_selection(1),
+            Key::Enter => {
+                let item = self.context_menu.selected_item();
+                self.context_menu.close();
+                if let Some(item) = item {
+                    self.run_menu_item(item, renderer);
+                }
+            }
+            // (Esc, or any other key, just closes it)
+            _ => self.context_menu.close(),
+        }
+    }
+
+    fn run_menu_item(&mut self, item: MenuItem, renderer: &mut Renderer) {
+        match item {
+            MenuItem::Widget(id, action) => self.widget_action(id, action),
+            MenuItem::Command(command) => self.run_command(command, renderer),
+        }
+    }
+
+    /**
+        Does what was picked from a widget's context menu: the widget does what it offers itself, and the editor changes the code for the rest
+    */
+    fn widget_action(&mut self, id: usize, action: WidgetAction) {
+        self.ui_needs_redraw = true;
+
+        match action {
+            WidgetAction::Remove => self.replace_widget(id, ""),
+            WidgetAction::ConvertToReference => {
+                if let Some(reference) = self.widget_manager.reference(id) {
+                    self.replace_widget(id, &reference);
+                }
+            }
+            _ => {
+                self.widget_manager.event(id, WidgetEvent::Action(action));
+            }
+        }
+    }
+
+    /**
+        Replaces a widget in the code with some text, as one edit (the widget itself stays around, for undo)
+    */
+    fn replace_widget(&mut self, id: usize, text: &str) {
+        let Some(range) = self.widget_range(id) else {
+            return;
+        };
+
+        if self.widget_manager.focused() == Some(id) {
+            self.widget_manager.unfocus();
+        }
+
+        self.is_selecting = None;
+        self.editor_state.remove(range);
+        if !text.is_empty() {
+            self.editor_state
+                .insert(range.start, LineData::from(text), false);
+        }
+    }
+
+    /// (where a widget is in the code, if it still is)
+    fn widget_range(&self, id: usize) -> Option<Range> {
+        let linedata = self.editor_state.linedata();
+        linedata.lines().iter().enumerate().find_map(|(row, line)| {
+            let i = line.iter().position(|token| match token {
+                Token::Widget(info) => info.id == id,
+                _ => false,
+            })?;
+
+            let row = row as i32;
+            Some(Range {
+                start: Pos {
+                    row,
+                    col: linedata.line_index_col(row, i),
+                },
+                end: Pos {
+                    row,
+                    col: linedata.line_index_col(row, i + 1),
+                },
+            })
+        })
+    }
+
+    /**
+        Rewrites a color literal with the color that was picked for it (keeping its alpha), as one edit
+    */
+    fn pick_color(&mut self, i: usize, [r, g, b, _]: [u8; 4]) {
+        let Some((range, [.., alpha])) = self.color_swatches.literal(i) else {
+            return;
+        };
+
+        self.is_selecting = None;
+        self.editor_state.remove(range);
+        self.editor_state.insert(
+            range.start,
+            LineData::from(format_color([r, g, b, alpha]).as_str()),
+            false,
+        );
+        self.ui_needs_redraw = true;
+    }
+
+    /**
+        Comments out the lines of the top-level statement at the offset
+    */
+    fn comment_out_statement(&mut self, offset: usize) {
+        let linedata = self.editor_state.linedata();
+        let Some(span) = statement_at(&linedata.to_string(), offset) else {
+            return;
+        };
+
+        let start = loc_to_pos(linedata, span.start).row;
+        let end = loc_to_pos(linedata, span.end).row;
+
+        for row in start..=end {
+            self.editor_state
+                .insert(Pos { row, col: 0 }, LineData::from("// "), false);
+        }
+    }
+
+    /**
+        Runs the formatter over the whole document, as a single undo step, keeping the widgets as they are
+    */
+    fn format_document(&mut self) {
+        let source = self.editor_state.linedata().to_string();
+        let formatted = live_language::format_document(&source);
+        if formatted == source {
+            return;
+        }
+
+        let caret = self.editor_state.caret_positions().first().copied();
+
+        self.is_selecting = None;
+        self.editor_state.remove(Range {
+            start: (0, 0).into(),
+            end: self.editor_state.linedata().end(),
+        });
+        self.editor_state.insert(
+            (0, 0).into(),
+            relink_widgets(&formatted, &self.widget_manager),
+            true,
+        );
+
+        // (roughly where it was, the lines mostly stay put)
+        if let Some(caret) = caret {
+            self.editor_state.set_single_caret(caret);
+        }
+    }
+
+    fn musical_typing_captures(&self, key: KeyCode, ctx: &Context) -> bool {
+        // (shortcuts, and anything that's capturing typing itself, still get their keys)
+        let typing_elsewhere = self.command_palette.is_open()
+            || self.project_search.is_open()
+            || self.symbol_picker.is_open()
+            || self.library_panel.is_open()
+            || self.history_browser.is_open()
+            || self.backup_picker.is_open()
+            || self.commit_prompt.is_open()
+            || self.rename_prompt.is_open()
+            || self.branch_picker.is_open()
+            || self.audio_settings.is_open()
+            || self.completion.is_open()
+            || self.widget_manager.focused().is_some();
+
+        !ctx.meta_or_ctrl && !typing_elsewhere && self.musical_typing.captures(key)
+    }
+
+    fn musical_typing_key(&mut self, key: KeyCode, pressed: bool, repeat: bool) {
+        let Some(event) = self.musical_typing.key(key, pressed, repeat) else {
+            // (the octave might have changed)
+            self.ui_needs_redraw = true;
+            return;
+        };
+
+        self.midi(event);
+    }
+
+    fn toggle_musical_typing(&mut self) {
+        for event in self.musical_typing.toggle() {
+            self.midi(event);
+        }
+
+        self.ui_needs_redraw = true;
+    }
+
+    /**
+        A note that comes in: it plays the `midi_in` source, or while the clip view is open, it launches (or stops) the clip on its pad
+    */
+    fn midi(&mut self, event: MidiEvent) {
+        // (letting go of a note always goes through, so nothing keeps sounding)
+        if let MidiEvent::NoteOn { note, .. } = event
+            && self.clip_view.is_open()
+        {
+            if let Some(ClipViewHit::Launch { track, clip }) = self.clip_view.pad(note) {
+                self.launch_clips(vec![(track, clip)]);
+            }
+            return;
+        }
+
+        if let Some(engine) = &self.engine {
+            engine.midi(event);
+        }
+    }
+
+    fn focus_selected_widget(&mut self) {
+        let Some(info) = self.editor_state.selected_widget() else {
+            return;
+        };
+
+        self.widget_manager.focus(info.id);
+    }
+
+    /**
+        Routes keys to the focused widget (see `WidgetManager::key`), except for Esc, which gives focus back to the text
+    */
+    fn focused_widget_key(&mut self, key: Key, ctx: &Context) {
+        let key = match key {
+            Key::Escape => {
+                self.widget_manager.unfocus();
+                return;
+            }
+            Key::ArrowUp => WidgetKey::Up,
+            Key::ArrowDown => WidgetKey::Down,
+            Key::ArrowLeft => WidgetKey::Left,
+            Key::ArrowRight => WidgetKey::Right,
+            Key::Enter => WidgetKey::Enter,
+            Key::Space => WidgetKey::Space,
+            Key::Backspace => WidgetKey::Backspace,
+            Key::Character(s) => match s.chars().next() {
+                Some(c) => WidgetKey::Char(c),
+                None => return,
+            },
+            _ => return,
+        };
+
+        if let Some(id) = self
+            .widget_manager
+            .key(key, ctx.shift, ctx.alt, ctx.meta_or_ctrl)
+        {
+            self.send_widget_param(id);
+        }
+    }
+
+    /**
+        Inserts a knob (or slider) at the caret, taking over the number right before it, if any (so `f = 400|` becomes `f = ◉`)
+    */
+    fn insert_param_widget(&mut self, style: KnobStyle) {
+        let [caret] = self.editor_state.caret_positions()[..] else {
+            return;
+        };
+
+        let mut number = String::new();
+        let mut col = 0;
+        for token in &self.editor_state.linedata().lines()[caret.row as usize] {
+            if col >= caret.col {
+                break;
+            }
+
+            match token {
+                Token::Char(ch) if ch.is_ascii_digit() || *ch == '.' || *ch == '-' => {
+                    number.push(*ch)
+                }
+                _ => number.clear(),
+            }
+
+            col += token.width() as i32;
+        }
+
+        let value = number.parse::<f32>().ok();
+        let mut pos = caret;
+        if value.is_some() {
+            pos.col -= number.len() as i32;
+            self.editor_state.remove(Range { start: pos, end: caret });
+        }
+
+        let widget = KnobWidget::new(style, value.unwrap_or(0.5));
+        let info = self.widget_manager.add(Box::new(widget));
+
+        self.editor_state
+            .insert(pos, Token::Widget(info).into(), true);
+    }
+
+    /**
+        Inserts a piano roll at the caret, taking over the note pattern right before it, if any (so `[c4@0:2 e4@2:1]|` becomes the piano roll showing those notes)
+    */
+    fn insert_piano_roll(&mut self) {
+        let [caret] = self.editor_state.caret_positions()[..] else {
+            return;
+        };
+
+        // (the literal, and how many columns it spans)
+        let mut literal: Option<(String, i32)> = None;
+        let mut col = 0;
+        for token in &self.editor_state.linedata().lines()[caret.row as usize] {
+            if col >= caret.col {
+                break;
+            }
+
+            match (token, &mut literal) {
+                (Token::Char('['), _) => literal = Some(("[".into(), 1)),
+                (Token::Char(ch), Some((text, width))) if !text.ends_with(']') => {
+                    text.push(*ch);
+                    *width += 1;
+                }
+                _ => literal = None,
+            }
+
+            col += token.width() as i32;
+        }
+
+        let pattern = literal
+            .as_ref()
+            .and_then(|(text, _)| NotePattern::parse(text));
+        let mut pos = caret;
+        if let (Some(_), Some((_, width))) = (&pattern, literal) {
+            pos.col -= width;
+            self.editor_state.remove(Range { start: pos, end: caret });
+        }
+
+        let widget = match pattern {
+            Some(pattern) => PianoRollWidget::with_pattern(pattern),
+            None => PianoRollWidget::new(),
+        };
+        let info = self.widget_manager.add(Box::new(widget));
+
+        self.editor_state
+            .insert(pos, Token::Widget(info).into(), true);
+    }
+
+    /**
+        Which engine parameter a widget controls: for a widget placed right after `param =`, inside `def name = ..`, that's `name.param`
+    */
+    fn param_binding(&mut self, id: usize) -> Option<String> {
+        let linedata = self.editor_state.linedata();
+
+        let (row, line, i) = linedata.lines().iter().enumerate().find_map(|(row, line)| {
+            let i = line.iter().position(|token| match token {
+                Token::Widget(info) => info.id == id,
+                _ => false,
+            })?;
+
+            Some((row, line, i))
+        })?;
+
+        let before = line[..i]
+            .iter()
+            .map(|token| match token {
+                Token::Char(ch) => *ch,
+                _ => ' ',
+            })
+            .collect::<String>();
+
+        let before = before.trim_end().strip_suffix('=')?.trim_end();
+        let param = &before[before
+            .rfind(|ch: char| !(ch.is_alphanumeric() || ch == '_'))
+            .map_or(0, |i| i + 1)..];
+
+        if param.is_empty() {
+            return None;
+        }
+
+        self.outline.sync(linedata);
+        let def = self
+            .outline
+            .entries
+            .iter()
+            .rev()
+            .find(|entry| entry.pos.row as usize <= row)?;
+
+        Some(format!("{}.{}", def.symbol.name, param))
+    }
+
+    /**
+        Inserts a sample widget for every file (separated by commas), for files that are dropped onto the window, dragged out of the sample browser, or pasted. Returns the widgets' ids.
+    */
+    fn insert_samples(&mut self, pos: Pos, files: &[PathBuf]) -> Vec<usize> {
+        let mut tokens = vec![];
+        let mut ids = vec![];
+
+        for (i, file) in files.iter().enumerate() {
+            if i > 0 {
+                tokens.extend([Token::Char(','), Token::Char(' ')]);
+            }
+
+            let widget = SampleWidget::from_file(file, self.workspace.sample_paths());
+            let info = self.widget_manager.add(Box::new(widget));
+            ids.push(info.id);
+            tokens.push(Token::Widget(info));
+        }
+
+        if !tokens.is_empty() {
+            self.editor_state.insert(pos, tokens.into(), true);
+        }
+
+        ids
+    }
+
+    /**
+        Files dropped onto the window: audio files become samples (and when it's just the one, its tempo is worked out, in case it's a loop, see `offer_loop_tempo`), and a code (or other text) file is opened, or else its contents are inserted where it was dropped
+    */
+    fn drop_files(&mut self, pos: Pos, file_drop: FileDrop) {
+        match file_drop {
+            FileDrop::Samples(files) => {
+                let ids = self.insert_samples(pos, &files);
+                if let ([id], [file]) = (&ids[..], &files[..]) {
+                    self.loop_tempos.push(LoopTempoJob::detect(
+                        *id,
+                        file.clone(),
+                        self.invalidator.clone(),
+                    ));
+                }
+            }
+            FileDrop::Text(path) => self.drop_text_file(pos, &path),
+            FileDrop::Rejected => self
+                .status_bar
+                .notify("can only drop audio files, or one code file"),
+        }
+    }
+
+    fn drop_text_file(&mut self, pos: Pos, path: &Path) {
+        let name = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(e) => {
+                tracing::warn!("Could not read {}: {}", path.display(), e);
+                self.status_bar.notify(format!("could not read {}", name));
+                return;
+            }
+        };
+
+        let open = MessageDialog::new()
+            .set_level(MessageLevel::Info)
+            .set_title("Dropped file")
+            .set_description(&format!(
+                "Open {} instead of the code that's open now (which is backed up first)? Or else, its code is inserted where it was dropped.",
+                name
+            ))
+            .set_buttons(MessageButtons::YesNo)
+            .show();
+
+        let linedata = relink_widgets(&source, &self.widget_manager);
+        if open {
+            self.backups.backup(self.editor_state.linedata());
+            self.replace_document(linedata);
+            self.opened = Some(path.to_path_buf());
+            self.rebase_diff_view();
+            self.status_bar.notify(format!("opened {}", name));
+        } else {
+            self.editor_state.insert(pos, linedata, true);
+        }
+    }
+
+    /**
+        Pastes what's on the clipboard at the carets, re-indented to fit (or as it is, with Cmd+Shift+V)
+    */
+    fn paste(&mut self, verbatim: bool) {
+        let files = self.clipboard.read_audio_files();
+        if !files.is_empty() {
+            self.paste_samples(&files);
+        } else if let Some(data) = self.clipboard.read() {
+            if verbatim {
+                self.editor_state.paste_verbatim(data);
+            } else {
+                self.editor_state.paste(data);
+            }
+        }
+    }
+
+    /**
+        Cmd+V with audio files on the clipboard (copied in Finder, say): inserts them at the caret, just like dropping them there
+    */
+    fn paste_samples(&mut self, files: &[PathBuf]) {
+        if let Some(&pos) = self.editor_state.caret_positions().last() {
+            self.insert_samples(pos, files);
+        }
+    }
+
+    /**
+        Scrolls the sample browser when the mouse is over it, and the code otherwise
+    */
+    fn scroll(
+        &mut self,
+        renderer: &mut Renderer,
+        mouse: (f32, f32),
+        delta: MouseScrollDelta,
+        phase: TouchPhase,
+    ) {
+        let rows = match delta {
+            MouseScrollDelta::LineDelta(_, y) => y,
+            MouseScrollDelta::PixelDelta(position) => position.y as f32 / 20.0,
+        };
+
+        self.ui_needs_redraw = true;
+
+        if self
+            .sample_browser
+            .scroll(renderer.logical_size(), mouse, rows)
+        {
+            return;
+        }
+
+        let system = &mut renderer.system;
+        let line_height = system.char_size.1 / system.scale_factor;
+        let rows = self.editor_state.linedata().len() as i32;
+        let margin = self.workspace.scroll_margin;
+
+        // (the pane under the mouse, not necessarily the focused one)
+        let pane = system.pane_at(mouse);
+        system.in_pane(pane, |system| match delta {
+            MouseScrollDelta::LineDelta(_, y) => {
+                system.scroll_by(-y * WHEEL_LINES * line_height, true, rows, margin);
+            }
+            MouseScrollDelta::PixelDelta(position) => {
+                // (a new gesture takes over from wherever an animated scroll is)
+                if phase == TouchPhase::Started {
+                    system.stop_scrolling();
+                }
+
+                let px = position.y as f32 / system.scale_factor;
+                system.scroll_by(-px, false, rows, margin);
+            }
+        });
+    }
+
+    /**
+        Scrolls (smoothly) to the caret when it moved, keeping some lines of context around it
+    */
+    fn follow_caret(&mut self, renderer: &mut Renderer) {
+        let carets = self.editor_state.caret_positions();
+        if carets == self.followed_carets {
+            return;
+        }
+
+        // (the most recently added caret)
+        if let Some(caret) = carets.last() {
+            renderer.system.scroll_into_view(
+                caret.row,
+                self.editor_state.linedata().len() as i32,
+                self.workspace.scroll_margin,
+            );
+        }
+
+        self.followed_carets = carets;
+    }
+
+    /**
+        Sends a widget's value straight to the running audio node it's bound to (if any), so it changes without having to re-evaluate the code
+    */
+    fn send_widget_param(&mut self, id: usize) {
+        if self.engine.is_none() {
+            return;
+        }
+
+        let Some(WidgetValue::Number(value)) = self.widget_manager.value(id) else {
+            return;
+        };
+
+        let Some(name) = self.param_binding(id) else {
+            return;
+        };
+
+        if let Some(engine) = &self.engine {
+            engine.set_param(&name, value);
+        }
+        self.record_event(SessionEvent::Param(name, value));
+    }
+
+    /**
+        Clicks on the overlay UI (which is on top of everything else), returns whether the click was handled
+    */
+    fn overlay_mouse_down(&mut self, renderer: &mut Renderer, mouse: (f32, f32)) -> bool {
+        let window_size = renderer.logical_size();
+
+        match self.context_menu.hit_test(window_size, mouse) {
+            Some(ContextMenuHit::Item(item)) => {
+                self.context_menu.close();
+                self.run_menu_item(item, renderer);
+                return true;
+            }
+            Some(ContextMenuHit::Menu) => return true,
+            None if self.context_menu.is_open() => {
+                // (clicking anywhere else closes it, and goes on to do what it does)
+                self.context_menu.close();
+                self.ui_needs_redraw = true;
+            }
+            None => {}
+        }
+
+        if self.command_palette.is_open() {
+            self.ui_needs_redraw = true;
+
+            match self.command_palette.hit_test(window_size, mouse) {
+                Some(Some(command)) => {
+                    self.command_palette.close();
+                    self.run_command(command, renderer);
+                }
+                Some(None) => {}
+                None => {
+                    // clicking outside of it just closes it
+                    self.command_palette.close();
+                }
+            }
+
+            return true;
+        }
+
+        if self.project_search.is_open() {
+            self.ui_needs_redraw = true;
+
+            match self.project_search.hit_test(window_size, mouse) {
+                Some(Some(i)) => {
+                    self.project_search.select(i);
+                    self.open_search_hit();
+                }
+                Some(None) => {}
+                None => {
+                    // clicking outside of it just closes it
+                    self.project_search.close();
+                }
+            }
+
+            return true;
+        }
+
+        if self.symbol_picker.is_open() {
+            self.ui_needs_redraw = true;
+
+            match self.symbol_picker.hit_test(&self.outline, window_size, mouse) {
+                Some(Some(pos)) => {
+                    self.jump_to(pos);
+                    self.symbol_picker.close();
+                }
+                Some(None) => {}
+                None => {
+                    // clicking outside of it just closes it
+                    self.symbol_picker.close();
+                }
+            }
+
+            return true;
+        }
+
+        if self.library_panel.is_open() {
+            self.ui_needs_redraw = true;
+
+            match self.library_panel.hit_test(window_size, mouse) {
+                Some(Some(preset)) => {
+                    let preset = preset.clone();
+                    self.close_library();
+                    self.insert_preset(&preset);
+                }
+                Some(None) => {}
+                None => {
+                    self.close_library();
+                }
+            }
+
+            return true;
+        }
+
+        if self.history_browser.is_open() {
+            self.ui_needs_redraw = true;
+
+            match self
+                .history_browser
+                .hit_test(self.editor_state.history(), window_size, mouse)
+            {
+                Some(Some(i)) => self.editor_state.jump_to_history(i),
+                Some(None) => {}
+                None => self.history_browser.close(),
+            }
+
+            return true;
+        }
+
+        if self.backup_picker.is_open() {
+            self.ui_needs_redraw = true;
+
+            match self.backup_picker.hit_test(window_size, mouse) {
+                Some(Some(backup)) => {
+                    let backup = backup.clone();
+                    self.restore_backup(&backup);
+                    self.backup_picker.close();
+                }
+                Some(None) => {}
+                None => self.backup_picker.close(),
+            }
+
+            return true;
+        }
+
+        if self.commit_prompt.is_open() {
+            self.ui_needs_redraw = true;
+
+            if !self.commit_prompt.hit_test(window_size, mouse) {
+                self.commit_prompt.close();
+            }
+
+            return true;
+        }
+
+        if self.rename_prompt.is_open() {
+            self.ui_needs_redraw = true;
+
+            if !self.rename_prompt.hit_test(window_size, mouse) {
+                self.rename_prompt.close();
+            }
+
+            return true;
+        }
+
+        if self.branch_picker.is_open() {
+            self.ui_needs_redraw = true;
+
+            match self.branch_picker.hit_test(window_size, mouse) {
+                Some(Some(pick)) => {
+                    self.branch_picker.close();
+                    self.pick_branch(pick);
+                }
+                Some(None) => {}
+                None => self.branch_picker.close(),
+            }
+
+            return true;
+        }
+
+        if self.audio_settings.is_open() {
+            self.ui_needs_redraw = true;
+
+            match self.audio_settings.hit_test(window_size, mouse) {
+                Some(Some(row)) => self.audio_settings.click(row),
+                Some(None) => {}
+                None => self.audio_settings.close(),
+            }
+
+            return true;
+        }
+
+        match self.color_swatches.hit_test(renderer, mouse) {
+            Some(ColorSwatchesHit::Swatch(i)) => {
+                self.color_swatches.open(i);
+                self.ui_needs_redraw = true;
+                return true;
+            }
+            Some(ColorSwatchesHit::Pick(i, color)) => {
+                self.pick_color(i, color);
+                return true;
+            }
+            Some(ColorSwatchesHit::Popup) => return true,
+            None if self.color_swatches.is_open() => {
+                // (clicking anywhere else closes it, and goes on to do what it does)
+                self.color_swatches.close();
+                self.ui_needs_redraw = true;
+            }
+            None => {}
+        }
+
+        match self.eval_errors.hit_test(renderer, mouse) {
+            Some(EvalErrorsHit::Icon(i)) => {
+                self.eval_errors.open(i);
+                self.ui_needs_redraw = true;
+                return true;
+            }
+            Some(EvalErrorsHit::Fix(i, fix)) => {
+                self.quick_fix(i, fix);
+                return true;
+            }
+            Some(EvalErrorsHit::Popup) => return true,
+            None if self.eval_errors.is_open() => {
+                // (clicking anywhere else closes it, and goes on to do what it does)
+                self.eval_errors.close();
+                self.ui_needs_redraw = true;
+            }
+            None => {}
+        }
+
+        if self.console.is_open() {
+            // (the panels aren't there while it's open, and clicking the code still works)
+            return match self.console.hit_test(window_size, mouse) {
+                Some(ConsoleHit::Area(area)) => {
+                    self.console.toggle_area(&area);
+                    self.ui_needs_redraw = true;
+                    true
+                }
+                Some(ConsoleHit::Panel) => true,
+                None => renderer.hit_test(mouse) == Hit::StatusBar,
+            };
+        }
+
+        if self.clip_view.is_open() {
+            // (like the console, the panels aren't there while it's open)
+            return match self.clip_view.hit_test(window_size, mouse) {
+                Some(ClipViewHit::Launch { track, clip }) => {
+                    self.launch_clips(vec![(track, clip)]);
+                    true
+                }
+                Some(ClipViewHit::Scene(scene)) => {
+                    self.launch_clips(self.clip_view.scene(scene));
+                    true
+                }
+                Some(ClipViewHit::Panel) => true,
+                None => renderer.hit_test(mouse) == Hit::StatusBar,
+            };
+        }
+
+        if let Some((name, toggle)) = self.mixer.hit_test(renderer, mouse) {
+            self.mixer.toggle(&name, toggle);
+            if let Some(engine) = &self.engine {
+                self.mixer.apply(engine);
+            }
+            self.ui_needs_redraw = true;
+            return true;
+        }
+
+        match self.snapshots.hit_test(window_size, mouse) {
+            Some(MorphHit::End(end)) => {
+                self.snapshots.cycle(end);
+                if let Some(engine) = &self.engine {
+                    self.snapshots.apply(engine);
+                }
+                self.ui_needs_redraw = true;
+                return true;
+            }
+            Some(MorphHit::Slider(position)) => {
+                self.morph_drag = true;
+                self.morph_to(position);
+                return true;
+            }
+            None => {}
+        }
+
+        match self.sample_browser.hit_test(window_size, mouse) {
+            Some(ListHit::Header) => {
+                self.sample_browser
+                    .toggle(self.workspace.sample_paths(), self.invalidator.clone());
+                self.ui_needs_redraw = true;
+                return true;
+            }
+            Some(ListHit::Entry(i)) => {
+                // (it's a click, to audition it, or a drag into the code, we'll know on mouse up)
+                self.sample_drag = Some(SampleDrag::new(i, mouse));
+                self.ui_needs_redraw = true;
+                return true;
+            }
+            Some(ListHit::Panel) => return true,
+            None => {}
+        }
+
+        match self.outline_panel.hit_test(&self.outline, window_size, mouse) {
+            Some(ListHit::Header) => {
+                self.outline_panel.collapsed = !self.outline_panel.collapsed;
+                self.ui_needs_redraw = true;
+                return true;
+            }
+            Some(ListHit::Entry(i)) => {
+                self.jump_to(self.outline.entries[i].pos);
+                return true;
+            }
+            Some(ListHit::Panel) => return true,
+            None => {}
+        }
+
+        match self
+            .problems_panel
+            .hit_test(&self.problems, window_size, mouse)
+        {
+            Some(ListHit::Header) => {
+                self.problems_panel.collapsed = !self.problems_panel.collapsed;
+                self.ui_needs_redraw = true;
+                return true;
+            }
+            Some(ListHit::Entry(i)) => {
+                if let Some(pos) = self.problems.entries[i].pos {
+                    self.jump_to(pos);
+                }
+                return true;
+            }
+            Some(ListHit::Panel) => return true,
+            None => {}
+        }
+
+        match self.watch_panel.hit_test(&self.watches, window_size, mouse) {
+            Some(ListHit::Header) => {
+                self.watch_panel.collapsed = !self.watch_panel.collapsed;
+                self.ui_needs_redraw = true;
+                true
+            }
+            Some(ListHit::Entry(i)) => {
+                let watch = &self.watches.entries[i];
+                if watch.pinned {
+                    let label = watch.label.clone();
+                    self.watches.toggle_pin(&label);
+                    self.ui_needs_redraw = true;
+                }
+                true
+            }
+            Some(ListHit::Panel) => true,
+            None => renderer.hit_test(mouse) == Hit::StatusBar,
+        }
+    }
+
+    fn event(&mut self, renderer: &mut Renderer, event: WidgetEvent) -> bool {
+        match event {
+            WidgetEvent::Hover { .. } => {
+                tracing::trace!("hover");
+                //
+            }
+            WidgetEvent::MouseMove { mouse, .. } => {
+                if self.morph_drag {
+                    let position = self
+                        .snapshots
+                        .slider_position(renderer.logical_size(), mouse);
+                    self.morph_to(position);
+                    return false;
+                }
+
+                if let Some(drag) = &mut self.sample_drag {
+                    drag.mouse = mouse;
+                    self.ui_needs_redraw = true;
+
+                    // (showing where it would go, just like when dragging a file onto the window)
+                    if drag.is_drag() && self.sample_browser.hit_test(renderer.logical_size(), mouse).is_none() {
+                        self.editor_state.file_drag_hover(renderer.system.code_pos_at(mouse).1);
+                    }
+                    return false;
+                }
+
+                if let Some((id, bounds)) = self.dragging_widget {
+                    self.widget_manager.event(id, event.child_relative(bounds));
+                    self.send_widget_param(id);
+                    return false;
+                }
+
+                let hit = renderer.hit_test(mouse);
+                let hover = match hit {
+                    Hit::Widget { id, bounds, .. } if self.is_selecting.is_none() => {
+                        Some((id, bounds))
+                    }
+                    _ => None,
+                };
+
+                if let Some(id) = self.hovering_widget_id && hover.map(|(id, _)| id) != self.hovering_widget_id {
+                self.widget_manager.event(id, WidgetEvent::Unhover);
+            }
+                if let Some((id, bounds)) = hover {
+                    self.widget_manager
+                        .event(id, WidgetEvent::Hover { bounds, mouse });
+                    self.widget_help.hover(id, bounds);
+                } else {
+                    self.widget_help.unhover();
+                }
+                self.hovering_widget_id = hover.map(|(id, _)| id);
+
+                // (the name under the mouse, if it's on the text)
+                let linedata = self.editor_state.linedata();
+                let word = match hit {
+                    Hit::Code { pos, .. }
+                        if self.is_selecting.is_none()
+                            && pos.row >= 0
+                            && (pos.row as usize) < linedata.len()
+                            && pos.col <= linedata.line_width(pos.row) =>
+                    {
+                        linedata.find_word_at(pos)
+                    }
+                    _ => None,
+                };
+                match word {
+                    Some(word) => self.doc_hover.hover(word),
+                    None => self.doc_hover.unhover(),
+                }
+
+                if self.eval_errors.hover(renderer, mouse) {
+                    self.ui_needs_redraw = true;
+                }
+                if self.context_menu.hover(renderer.logical_size(), mouse) {
+                    self.ui_needs_redraw = true;
+                }
+
+                if let Some(id) = self.is_selecting {
+                    let caret = renderer.system.px_to_pos(mouse);
+                    self.editor_state.drag_select(caret, id);
+                }
+            }
+            WidgetEvent::Unhover => {
+                tracing::trace!("unhover");
+                self.widget_help.unhover();
+                self.doc_hover.unhover();
+                if let Some(id) = self.hovering_widget_id {
+                    self.widget_manager.event(id, WidgetEvent::Unhover);
+                }
+            }
+            WidgetEvent::MouseDown {
+                mouse,
+                right_click,
+                shift,
+                alt,
+                ..
+            } => {
+                tracing::trace!("mouse down");
+                if self.overlay_mouse_down(renderer, mouse) {
+                    return false;
+                }
+
+                self.widget_help.dismiss();
+                self.doc_hover.unhover();
+
+                // clicking a widget gives it the keyboard, and clicking anywhere else returns focus to the text
+                let widget = renderer.widget_at(mouse);
+                match widget {
+                    Some((id, _)) => self.widget_manager.focus(id),
+                    None => self.widget_manager.unfocus(),
+                }
+
+                if let Some((id, widget_bounds)) = widget {
+                    if self
+                        .widget_manager
+                        .event(id, event.child_relative(widget_bounds))
+                    {
+                        self.dragging_widget = Some((id, widget_bounds));
+                        self.send_widget_param(id);
+                        return false;
+                    }
+                }
+
+                // (the caret is shared, but it's the pane that's clicked in that follows it)
+                let (pane, pos) = match renderer.hit_test(mouse) {
+                    Hit::Code { pane, pos } => (pane, pos),
+                    // (next to a line is at its start)
+                    Hit::Gutter { pane, row } => (pane, Pos { row, col: 0 }),
+                    // (on a widget that doesn't take the click, it's for the code it's in)
+                    _ => renderer.system.code_pos_at(mouse),
+                };
+                renderer.system.focus_pane(pane);
+
+                // (right-clicking the selection keeps it, for the context menu to act on)
+                if right_click && self.editor_state.is_selected(pos) {
+                    return false;
+                }
+
+                if shift {
+                    if self.editor_state.has_selections() {
+                        self.is_selecting = self.editor_state.extend_selection_to(pos);
+                    } else {
+                        self.is_selecting = Some(self.editor_state.set_single_caret(pos));
+                    }
+                } else if alt {
+                    self.is_selecting = Some(self.editor_state.add_caret(pos));
+                } else {
+                    self.is_selecting = Some(self.editor_state.set_single_caret(pos));
+                }
+            }
+            WidgetEvent::Press {
+                double,
+                mouse,
+                right_click,
+                ..
+            } => {
+                tracing::trace!(double, "press");
+
+                let window_size = renderer.logical_size();
+                if self.sample_browser.hit_test(window_size, mouse).is_some()
+                    || self
+                        .outline_panel
+                        .hit_test(&self.outline, window_size, mouse)
+                        .is_some()
+                    || self
+                        .problems_panel
+                        .hit_test(&self.problems, window_size, mouse)
+                        .is_some()
+                    || self
+                        .watch_panel
+                        .hit_test(&self.watches, window_size, mouse)
+                        .is_some()
+                    || renderer.hit_test(mouse) == Hit::StatusBar
+                    || self.eval_errors.hit_test(renderer, mouse).is_some()
+                    || self.color_swatches.hit_test(renderer, mouse).is_some()
+                {
+                    return false;
+                }
+
+                // pressing widgets
+                let w = renderer.widget_at(mouse);
+                if let Some(id) = self.pressing_widget_id && w.map(|(id, _)| id) != self.pressing_widget_id {
+                    self.widget_manager.event(id, WidgetEvent::Release { double });
+                }
+                let mut toggled_expanded = false;
+                if let Some((id, bounds)) = w {
+                    let used = self.widget_manager.event(id, event.child_relative(bounds));
+                    self.send_widget_param(id);
+
+                    // double-clicking a widget in the code opens it up over the code (or closes it again), if it can be
+                    if double && !right_click && renderer.expanded_widget() != Some((id, bounds)) {
+                        toggled_expanded = self.widget_manager.toggle_expanded(id);
+                    }
+
+                    if right_click && !used {
+                        let items = self
+                            .widget_manager
+                            .actions(id)
+                            .into_iter()
+                            .map(|action| MenuItem::Widget(id, action))
+                            .collect();
+                        self.context_menu.open(items, mouse);
+                        self.ui_needs_redraw = true;
+                    }
+                } else if right_click
+                    && matches!(
+                        renderer.hit_test(mouse),
+                        Hit::Code { .. } | Hit::Gutter { .. }
+                    )
+                {
+                    self.context_menu.open(MenuItem::TEXT.to_vec(), mouse);
+                    self.ui_needs_redraw = true;
+                }
+                self.pressing_widget_id = w.map(|(id, _)| id);
+
+                // double press -> selecting words
+                if double && !toggled_expanded {
+                    let pos = renderer.system.px_to_pos(mouse);
+                    self.editor_state.select_word_at(pos);
+                }
+            }
+            WidgetEvent::MouseUp => {
+                // hmm, can't sent this to the widget w/o coords..
+                tracing::trace!("mouse up");
+                self.is_selecting = None;
+                self.morph_drag = false;
+
+                if let Some((id, _)) = self.dragging_widget.take() {
+                    self.widget_manager.event(id, WidgetEvent::MouseUp);
+                }
+
+                if let Some(drag) = self.sample_drag.take() {
+                    self.ui_needs_redraw = true;
+
+                    let over_browser = self
+                        .sample_browser
+                        .hit_test(renderer.logical_size(), drag.mouse)
+                        .is_some();
+
+                    if !drag.is_drag() {
+                        self.sample_browser.audition(drag.index, self.engine.as_ref());
+                    } else if !over_browser && let Some(file) = self.sample_browser.file(drag.index) {
+                        let path = file.path.clone();
+                        self.insert_samples(renderer.system.code_pos_at(drag.mouse).1, &[path]);
+                    }
+                }
+            }
+            WidgetEvent::Release { .. } => {
+                // hmm, can't sent this to the widget w/o coords..
+                tracing::trace!("release");
+            }
+            // (keyboard stuff goes straight to the widget manager, not through the event loop)
+            WidgetEvent::Focus
+            | WidgetEvent::Unfocus
+            | WidgetEvent::Adjust { .. }
+            | WidgetEvent::Key { .. }
+            | WidgetEvent::Action(_) => {}
+        }
+
+        false
+    }
+}
+
+const WINDOW_DRAG_SURFACE_HEIGHT: f32 = 54.0;
+
+/// How many lines a notch of the mouse wheel scrolls
+const WHEEL_LINES: f32 = 3.0;
+
+/// How much the swing changes per Cmd+Shift+[ or ]
+const SWING_STEP: f64 = 0.02;
+
+/// How long evaluated code flashes
+const FLASH_DURATION: Duration = Duration::from_millis(300);
+
+/// How often fired timers are picked up (and called back), while there are any
+const TIMER_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// (between the panes, when the editor is split)
+const PANE_DIVIDER_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.12];
+const FOCUS_RING_COLOR: [f32; 4] = [0.25, 0.5, 1.0, 0.9];
+const FOCUS_RING_WIDTH: f32 = 2.0;
+
+/**
+    A ring just around a focused widget (as four thin quads, so the widget itself stays visible)
+*/
+fn focus_ring(overlay: &mut Overlay, (min_x, min_y, max_x, max_y): (f32, f32, f32, f32)) {
+    let (min_x, min_y) = (min_x - FOCUS_RING_WIDTH, min_y - FOCUS_RING_WIDTH);
+    let (max_x, max_y) = (max_x + FOCUS_RING_WIDTH, max_y + FOCUS_RING_WIDTH);
+    let w = FOCUS_RING_WIDTH;
+
+    overlay.quad((min_x, min_y, max_x, min_y + w), FOCUS_RING_COLOR);
+    overlay.quad((min_x, max_y - w, max_x, max_y), FOCUS_RING_COLOR);
+    overlay.quad((min_x, min_y + w, min_x + w, max_y - w), FOCUS_RING_COLOR);
+    overlay.quad((max_x - w, min_y + w, max_x, max_y - w), FOCUS_RING_COLOR);
+}
+
+/**
+    The swing, nudged (by `SWING_STEP`), in whole percents, so that it gets back to straight exactly
+*/
+fn nudged_swing(swing: f64, by: f64) -> f64 {
+    clamp_swing(((swing + by) * 100.0).round() / 100.0)
+}
+
+/**
+    The numbered register of a digit key (by where it is, because Alt and Shift change what the digits type)
+*/
+fn register_key(key: KeyCode) -> Option<usize> {
+    let register = match key {
+        KeyCode::Digit1 | KeyCode::Numpad1 => 1,
+        KeyCode::Digit2 | KeyCode::Numpad2 => 2,
+        KeyCode::Digit3 | KeyCode::Numpad3 => 3,
+        KeyCode::Digit4 | KeyCode::Numpad4 => 4,
+        KeyCode::Digit5 | KeyCode::Numpad5 => 5,
+        KeyCode::Digit6 | KeyCode::Numpad6 => 6,
+        KeyCode::Digit7 | KeyCode::Numpad7 => 7,
+        KeyCode::Digit8 | KeyCode::Numpad8 => 8,
+        KeyCode::Digit9 | KeyCode::Numpad9 => 9,
+        _ => return None,
+    };
+
+    Some(register)
+}
+
+fn is_modifier_key(key: &Key) -> bool {
+    matches!(
+        key,
+        Key::Alt | Key::Shift | Key::Meta | Key::Super | Key::Control
+    )
 }
 
 fn dist(a: (f32, f32), b: (f32, f32)) -> f32 {
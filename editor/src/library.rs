@@ -0,0 +1,371 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+};
+
+use live_editor_state::{LineData, Token};
+use live_language::{outline, rename_symbols};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    fuzzy::fuzzy_match,
+    render::Overlay,
+    storage::storage,
+    util::config_dir,
+    widget::{SampleMarkers, WidgetManager, WidgetValue},
+};
+
+/// In the config dir, one `<name>.toml` per preset
+const LIBRARY_DIR: &str = "library";
+/// (how sample widgets are written in a preset's code, followed by the index into its samples)
+const SAMPLE_REF: &str = "sample#";
+
+const PANEL_WIDTH: f32 = 440.0;
+const PANEL_TOP: f32 = 64.0;
+const INPUT_HEIGHT: f32 = 36.0;
+const ROW_HEIGHT: f32 = 26.0;
+const MAX_ROWS: usize = 12;
+const FONT_SIZE: f32 = 15.0;
+/// Code longer than this is cut off in the list
+const MAX_CODE_CHARS: usize = 36;
+
+const BACKDROP_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.08];
+const PANEL_COLOR: [f32; 4] = [0.99, 0.99, 0.98, 1.0];
+const SELECTED_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.08];
+const TEXT_COLOR: [f32; 4] = [0.02, 0.02, 0.02, 1.0];
+const DIM_TEXT_COLOR: [f32; 4] = [0.02, 0.02, 0.02, 0.45];
+
+/**
+    A definition saved to the library, to reuse in other projects: its code, and the samples it plays (by their full path, so that they're found from anywhere). In the code, the sample widgets are written as `sample#0`, `sample#1`, .., referring to the samples in order. Other widgets (knobs and such) are kept as they're written in backups, but don't come back as widgets.
+*/
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Preset {
+    /// (the file name)
+    #[serde(skip)]
+    pub name: String,
+    pub code: String,
+    #[serde(default)]
+    pub samples: Vec<PathBuf>,
+}
+
+impl Preset {
+    /**
+        Makes a preset of a piece of the document, named after the first thing it declares (if it declares anything)
+    */
+    pub fn from_code(linedata: &LineData, widget_manager: &WidgetManager) -> Option<Self> {
+        let mut samples: Vec<PathBuf> = vec![];
+
+        let code = linedata
+            .lines()
+            .iter()
+            .map(|line| {
+                line.iter()
+                    .map(|token| match token {
+                        Token::Char(ch) => ch.to_string(),
+                        Token::Widget(info) => match widget_manager.value(info.id) {
+                            Some(WidgetValue::Sample(path, _) | WidgetValue::Slices(path, _)) => {
+                                let index = samples.iter().position(|p| *p == path);
+                                let index = index.unwrap_or_else(|| {
+                                    samples.push(path);
+                                    samples.len() - 1
+                                });
+                                format!("{}{}", SAMPLE_REF, index)
+                            }
+                            _ => format!("{}#{}", info.kind, info.id),
+                        },
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let name = outline(&code).into_iter().next()?.name;
+
+        Some(Self {
+            name,
+            code: code.trim().to_string(),
+            samples,
+        })
+    }
+
+    /**
+        Writes it to the library, returning whether it replaced a preset with the same name
+    */
+    pub fn save(&self) -> Result<bool, String> {
+        let dir = config_dir().ok_or("no config directory")?.join(LIBRARY_DIR);
+
+        let path = dir.join(format!("{}.toml", self.name));
+        let replaced = storage().read(&path).is_ok();
+
+        let contents = toml::to_string(self).map_err(|e| e.to_string())?;
+        storage()
+            .write(&path, &contents)
+            .map_err(|e| format!("could not write {}: {}", path.display(), e))?;
+
+        Ok(replaced)
+    }
+
+    /**
+        The code to insert into a document that already declares some names: whatever the preset declares that collides with those gets a number, like `bass_2`
+    */
+    pub fn code_for(&self, taken: &HashSet<String>) -> String {
+        let declared = outline(&self.code)
+            .into_iter()
+            .map(|symbol| symbol.name)
+            .collect::<HashSet<_>>();
+
+        let renames = declared
+            .iter()
+            .filter(|name| taken.contains(*name))
+            .map(|name| {
+                let renamed = (2..)
+                    .map(|n| format!("{}_{}", name, n))
+                    .find(|renamed| !taken.contains(renamed) && !declared.contains(renamed))
+                    .unwrap();
+
+                (name.clone(), renamed)
+            })
+            .collect::<HashMap<_, _>>();
+
+        rename_symbols(&self.code, &renames)
+    }
+
+    /**
+        The preset's samples as the widgets its code refers to (`sample#0`, ..), to compile its code with on its own, like when it's auditioned
+    */
+    pub fn widgets(&self) -> HashMap<String, WidgetValue> {
+        self.samples
+            .iter()
+            .enumerate()
+            .map(|(index, path)| {
+                (
+                    format!("{}{}", SAMPLE_REF, index),
+                    WidgetValue::Sample(path.clone(), SampleMarkers::default()),
+                )
+            })
+            .collect()
+    }
+
+    /**
+        The index of the sample written at the start of the text, and how long its reference is
+    */
+    pub fn sample_at(&self, text: &str) -> Option<(usize, usize)> {
+        let rest = text.strip_prefix(SAMPLE_REF)?;
+        let digits = rest.chars().take_while(char::is_ascii_digit).count();
+        let index = rest[..digits].parse().ok()?;
+
+        (index < self.samples.len()).then_some((index, SAMPLE_REF.len() + digits))
+    }
+}
+
+/**
+    All the presets in the library, by name
+*/
+pub fn load_presets() -> Vec<Preset> {
+    let Some(dir) = config_dir().map(|dir| dir.join(LIBRARY_DIR)) else {
+        return vec![];
+    };
+
+    let mut presets = storage()
+        .list(&dir)
+        .into_iter()
+        .filter_map(|path| {
+            if path.extension()? != "toml" {
+                return None;
+            }
+
+            let contents = storage().read(&path).ok()?;
+            match toml::from_str::<Preset>(&contents) {
+                Ok(preset) => Some(Preset {
+                    name: path.file_stem()?.to_string_lossy().into(),
+                    ..preset
+                }),
+                Err(e) => {
+                    tracing::warn!("Could not read preset {}: {}", path.display(), e);
+                    None
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+
+    presets.sort_by(|a, b| a.name.cmp(&b.name));
+    presets
+}
+
+/**
+    The Cmd+Shift+T library panel: fuzzy filters the presets by name, Enter inserts the selected one, and Tab auditions it (plays what its code defines, on its own)
+*/
+pub struct LibraryPanel {
+    open: bool,
+    query: String,
+    selected: usize,
+    presets: Vec<Preset>,
+}
+
+impl LibraryPanel {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            query: String::new(),
+            selected: 0,
+            presets: vec![],
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// (reading the library again, since presets might have been saved in the meantime)
+    pub fn open(&mut self) {
+        self.open = true;
+        self.query.clear();
+        self.selected = 0;
+        self.presets = load_presets();
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+    }
+
+    pub fn type_str(&mut self, s: &str) {
+        self.query.push_str(s);
+        self.selected = 0;
+    }
+
+    pub fn backspace(&mut self) {
+        self.query.pop();
+        self.selected = 0;
+    }
+
+    pub fn move_selection(&mut self, delta: i32) {
+        let n = self.matches().len() as i32;
+        if n > 0 {
+            self.selected = (self.selected as i32 + delta).rem_euclid(n) as usize;
+        }
+    }
+
+    /**
+        Indices into the presets, best match first
+    */
+    fn matches(&self) -> Vec<usize> {
+        let mut matches = self
+            .presets
+            .iter()
+            .enumerate()
+            .filter_map(|(i, preset)| Some((i, fuzzy_match(&self.query, &preset.name)?)))
+            .collect::<Vec<_>>();
+
+        matches.sort_by_key(|&(_, score)| -score);
+
+        matches.into_iter().map(|(i, _)| i).take(MAX_ROWS).collect()
+    }
+
+    pub fn selected(&self) -> Option<&Preset> {
+        let i = *self.matches().get(self.selected)?;
+        self.presets.get(i)
+    }
+
+    fn bounds(&self, (width, _): (f32, f32)) -> (f32, f32, f32, f32) {
+        let rows = self.matches().len().max(1);
+        let min_x = ((width - PANEL_WIDTH) / 2.0).max(0.0);
+
+        (
+            min_x,
+            PANEL_TOP,
+            min_x + PANEL_WIDTH,
+            PANEL_TOP + INPUT_HEIGHT + rows as f32 * ROW_HEIGHT + 6.0,
+        )
+    }
+
+    /**
+        Which preset was clicked, if any. (`None` if the click was outside of the panel.)
+    */
+    pub fn hit_test(&self, window_size: (f32, f32), (x, y): (f32, f32)) -> Option<Option<&Preset>> {
+        let (min_x, min_y, max_x, max_y) = self.bounds(window_size);
+        if x < min_x || x > max_x || y < min_y || y > max_y {
+            return None;
+        }
+
+        let i = ((y - min_y - INPUT_HEIGHT) / ROW_HEIGHT).floor();
+        if i < 0.0 {
+            return Some(None);
+        }
+
+        Some(self.matches().get(i as usize).map(|&i| &self.presets[i]))
+    }
+
+    pub fn draw(&self, window_size: (f32, f32), overlay: &mut Overlay) {
+        let (min_x, min_y, max_x, max_y) = self.bounds(window_size);
+        let text_y = |top: f32, height: f32| top + (height - FONT_SIZE) / 2.0;
+
+        overlay.quad((0.0, 0.0, window_size.0, window_size.1), BACKDROP_COLOR);
+        overlay.quad((min_x, min_y, max_x, max_y), PANEL_COLOR);
+
+        overlay.text(
+            (min_x + 12.0, text_y(min_y, INPUT_HEIGHT)),
+            format!("library: {}", self.query),
+            FONT_SIZE,
+            TEXT_COLOR,
+        );
+
+        let matches = self.matches();
+
+        if matches.is_empty() {
+            overlay.text(
+                (min_x + 12.0, text_y(min_y + INPUT_HEIGHT, ROW_HEIGHT)),
+                if self.presets.is_empty() {
+                    "no presets yet (Cmd+Shift+A saves a definition)"
+                } else {
+                    "no matching presets"
+                },
+                FONT_SIZE,
+                DIM_TEXT_COLOR,
+            );
+        }
+
+        for (row, &i) in matches.iter().enumerate() {
+            let preset = &self.presets[i];
+            let top = min_y + INPUT_HEIGHT + row as f32 * ROW_HEIGHT;
+            let y = text_y(top, ROW_HEIGHT);
+
+            if row == self.selected {
+                overlay.quad((min_x, top, max_x, top + ROW_HEIGHT), SELECTED_COLOR);
+            }
+
+            overlay.text((min_x + 12.0, y), &preset.name, FONT_SIZE, TEXT_COLOR);
+
+            let first_line = preset.code.lines().next().unwrap_or_default();
+            let code = match first_line.char_indices().nth(MAX_CODE_CHARS) {
+                Some((end, _)) => format!("{}…", &first_line[..end]),
+                None => first_line.to_string(),
+            };
+
+            overlay.text((min_x + 140.0, y), code, FONT_SIZE, DIM_TEXT_COLOR);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code_for() {
+        let preset = Preset {
+            name: "bass".into(),
+            code: "let env = .5;\nlet env_2 = 2;\ndef bass = lowpass{f = 200hz}(sin(55hz)) * env;\nfn pluck(t) { bass * t * env_2 }\nplay pluck(bass);".into(),
+            samples: vec![],
+        };
+        let taken = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+
+        // (nothing collides, nothing changes)
+        assert_eq!(preset.code_for(&taken(&["kick"])), preset.code);
+
+        // (both where they're declared and where they're used, skipping names that are taken in the document or in the preset itself, but not setting names)
+        assert_eq!(
+            preset.code_for(&taken(&["bass", "bass_2", "env", "f"])),
+            "let env_3 = .5;\nlet env_2 = 2;\ndef bass_3 = lowpass{f = 200hz}(sin(55hz)) * env_3;\nfn pluck(t) { bass_3 * t * env_2 }\nplay pluck(bass_3);"
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver, TryRecvError},
+    thread,
+};
+
+use live_engine::{detect_tempo, save_wav, time_stretch};
+
+use crate::{audio_cache::decode_mono, invalidation::Invalidator};
+
+/// Where loops are time-stretched to, in the project (see `Editor::poll_loop_tempos`)
+pub(crate) const STRETCHED_DIR: &str = "stretched";
+
+/// (tempos closer to each other than this, relatively, are the same tempo, more or less)
+pub(crate) const SAME_TEMPO: f64 = 0.005;
+
+/**
+    What a loop job found out, or made
+*/
+pub enum LoopTempo {
+    /// (`None` when it doesn't have a beat, like a one-shot)
+    Detected(Option<f64>),
+    Stretched(PathBuf),
+}
+
+/**
+    Works out the tempo of a sample that was dropped into the code, or time-stretches it to the session's, on a background thread (decoding a loop and going over all of it takes a moment)
+*/
+pub struct LoopTempoJob {
+    /// The sample widget it's about
+    pub widget: usize,
+    pub path: PathBuf,
+    receiver: Receiver<Result<LoopTempo, String>>,
+}
+
+impl LoopTempoJob {
+    pub fn detect(widget: usize, path: PathBuf, invalidator: Invalidator) -> Self {
+        Self::spawn(widget, path, invalidator, |path| {
+            let (samples, sample_rate) = decode_mono(path)?;
+            Ok(LoopTempo::Detected(detect_tempo(&samples, sample_rate)))
+        })
+    }
+
+    /**
+        Stretches the loop from the tempo it's at to another one (keeping its pitch), into `stretched/<name> <bpm>bpm.wav` in the project
+    */
+    pub fn stretch(
+        widget: usize,
+        path: PathBuf,
+        from: f64,
+        to: f64,
+        root: &Path,
+        invalidator: Invalidator,
+    ) -> Self {
+        let dir = root.join(STRETCHED_DIR);
+
+        Self::spawn(widget, path, invalidator, move |path| {
+            let (samples, sample_rate) = decode_mono(path)?;
+            let stretched = time_stretch(&samples, sample_rate, from / to);
+
+            let name = path.file_stem().unwrap_or_default().to_string_lossy();
+            let target = dir.join(format!("{} {}bpm.wav", name, format_bpm(to)));
+
+            fs::create_dir_all(&dir)
+                .and_then(|_| save_wav(&target, &stretched, sample_rate))
+                .map_err(|e| format!("could not write {}: {}", target.display(), e))?;
+
+            Ok(LoopTempo::Stretched(target))
+        })
+    }
+
+    fn spawn(
+        widget: usize,
+        path: PathBuf,
+        invalidator: Invalidator,
+        work: impl FnOnce(&Path) -> Result<LoopTempo, String> + Send + 'static,
+    ) -> Self {
+        let (sender, receiver) = channel();
+
+        thread::spawn({
+            let path = path.clone();
+            move || {
+                let _ = sender.send(work(&path));
+                invalidator.invalidate();
+            }
+        });
+
+        Self {
+            widget,
+            path,
+            receiver,
+        }
+    }
+
+    /**
+        What came out of it, once it's done
+    */
+    pub fn poll(&self) -> Option<Result<LoopTempo, String>> {
+        match self.receiver.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            // (the thread panicked)
+            Err(TryRecvError::Disconnected) => Some(Err("it crashed".into())),
+        }
+    }
+}
+
+/// (to a tenth of a bpm, but without the `.0` when it's a whole tempo)
+pub(crate) fn format_bpm(bpm: f64) -> String {
+    let tenths = (bpm * 10.0).round();
+    if tenths % 10.0 == 0.0 {
+        format!("{}", tenths / 10.0)
+    } else {
+        format!("{:.1}", tenths / 10.0)
+    }
+}
@@ -1,3 +1,319 @@
-fn main() {
-    live_editor::run();
+use std::{
+    fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use clap::{Parser, Subcommand};
+use live_editor::{document_root, BounceSettings, Session, Sharing, SESSION_EXTENSION};
+use live_language::{check_units, lint, parse_document, syntax_errors, LintKind, Loc, Severity};
+
+// (so that debug builds assert that the audio thread doesn't allocate or free, see `live_engine::RealtimeAllocator`)
+#[cfg(debug_assertions)]
+#[global_allocator]
+static ALLOCATOR: live_engine::RealtimeAllocator<std::alloc::System> =
+    live_engine::RealtimeAllocator(std::alloc::System);
+
+#[derive(Parser)]
+#[command(name = "live")]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Hosts the document for someone else to join and edit along, at an address like `0.0.0.0:7878`
+    #[arg(long, conflicts_with = "join")]
+    host: Option<String>,
+    /// Joins someone's document, at a url like `ws://192.168.1.10:7878`
+    #[arg(long)]
+    join: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Checks live code files for syntax errors, units that don't add up, and lints, like the editor's problems panel
+    Check { files: Vec<PathBuf> },
+    /// Formats live code files in place (or stdin to stdout, without files)
+    Fmt {
+        files: Vec<PathBuf>,
+        /// Don't write anything, just fail if any of the files isn't formatted
+        #[arg(long)]
+        check: bool,
+    },
+    /// Bounces what a live code file plays to a WAV (or FLAC) file, offline (as fast as it goes), at the tempo and swing of its project. Or a recorded session (a .session file), for as long as it was performed.
+    Render {
+        file: PathBuf,
+        /// How many bars to render (8, unless the project file says otherwise)
+        #[arg(long)]
+        bars: Option<f64>,
+        /// Where to write it (next to the file, as a .wav, unless it's given, which can also be a .flac)
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Plays what a live code file plays, without the editor, on the editor's audio devices, at the tempo and swing of its project (until it's interrupted, unless it's given how long)
+    Play {
+        file: PathBuf,
+        /// How many bars to play
+        #[arg(long)]
+        bars: Option<f64>,
+    },
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    match cli.command {
+        None => {
+            let sharing = cli.host.map(Sharing::Host).or(cli.join.map(Sharing::Join));
+            live_editor::run(sharing);
+            ExitCode::SUCCESS
+        }
+        Some(Command::Check { files }) => check(files),
+        Some(Command::Fmt { files, check }) => fmt(files, check),
+        Some(Command::Render { file, bars, output }) => render(file, bars, output),
+        Some(Command::Play { file, bars }) => play(file, bars),
+    }
+}
+
+fn check(files: Vec<PathBuf>) -> ExitCode {
+    let config = live_editor::load_lint_config();
+    let mut errors = 0;
+    let mut warnings = 0;
+
+    for file in files {
+        let source = match fs::read_to_string(&file) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("Could not read {}: {}", file.display(), e);
+                errors += 1;
+                continue;
+            }
+        };
+
+        let mut diagnostics = syntax_errors(&source)
+            .into_iter()
+            .map(|(loc, message)| (Severity::Error, message, Some(loc)))
+            .collect::<Vec<_>>();
+
+        // (always, even when the lint config turns them off, because it won't sound like anything that makes sense)
+        let (document, _) = parse_document(source.as_str());
+        diagnostics.extend(
+            check_units(&document)
+                .into_iter()
+                .map(|(span, message)| (Severity::Error, message, Some(span.start))),
+        );
+
+        diagnostics.extend(
+            lint(&source, &config)
+                .into_iter()
+                .filter(|lint| lint.kind != LintKind::UnitMismatch)
+                .map(|lint| {
+                    let loc = lint.range.map(|span| span.start);
+                    (lint.severity, lint.message, loc)
+                }),
+        );
+
+        for (severity, message, loc) in diagnostics {
+            match severity {
+                Severity::Error => errors += 1,
+                Severity::Warning => warnings += 1,
+                _ => {}
+            }
+
+            print_diagnostic(&file, &source, severity, &message, loc);
+        }
+    }
+
+    if errors + warnings > 0 {
+        println!("{} error(s), {} warning(s)", errors, warnings);
+    }
+
+    if errors > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/**
+    Prints a diagnostic like the Rust compiler does, with the line it's on and a caret where it starts
+*/
+fn print_diagnostic(
+    file: &Path,
+    source: &str,
+    severity: Severity,
+    message: &str,
+    loc: Option<Loc>,
+) {
+    let label = match severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        _ => "info",
+    };
+
+    println!("{}: {}", label, message);
+
+    let Some(Loc { line, col, .. }) = loc else {
+        println!("  --> {}", file.display());
+        println!();
+        return;
+    };
+
+    let text = source.split('\n').nth(line - 1).unwrap_or_default();
+    let gutter = " ".repeat(line.to_string().len());
+
+    println!("{}--> {}:{}:{}", gutter, file.display(), line, col);
+    println!("{} |", gutter);
+    println!("{} | {}", line, text);
+    println!("{} | {}^", gutter, " ".repeat(col - 1));
+    println!();
+}
+
+fn fmt(files: Vec<PathBuf>, check: bool) -> ExitCode {
+    if files.is_empty() {
+        let mut source = String::new();
+        if let Err(e) = io::stdin().read_to_string(&mut source) {
+            eprintln!("Could not read stdin: {}", e);
+            return ExitCode::FAILURE;
+        }
+
+        let formatted = formatted(&source);
+        if check {
+            return if formatted == source {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            };
+        }
+
+        print!("{}", formatted);
+        return ExitCode::SUCCESS;
+    }
+
+    let mut ok = true;
+
+    for file in files {
+        let source = match fs::read_to_string(&file) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("Could not read {}: {}", file.display(), e);
+                ok = false;
+                continue;
+            }
+        };
+
+        let formatted = formatted(&source);
+        if formatted == source {
+            continue;
+        }
+
+        if check {
+            println!("{} is not formatted", file.display());
+            ok = false;
+        } else if let Err(e) = fs::write(&file, formatted) {
+            eprintln!("Could not write {}: {}", file.display(), e);
+            ok = false;
+        }
+    }
+
+    if ok {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn render(file: PathBuf, bars: Option<f64>, output: Option<PathBuf>) -> ExitCode {
+    if file.extension().and_then(|ext| ext.to_str()) == Some(SESSION_EXTENSION) {
+        return render_session(file, output);
+    }
+
+    let source = match fs::read_to_string(&file) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("Could not read {}: {}", file.display(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let output = output.unwrap_or_else(|| file.with_extension("wav"));
+    let settings = BounceSettings::for_document(&file, bars);
+    let root = document_root(&file);
+
+    let result = live_editor::bounce(&source, &root, settings, &output, |done| {
+        eprint!("\rRendering {} bars… {:>3.0}%", settings.bars, done * 100.0);
+        let _ = io::stderr().flush();
+    });
+    eprintln!();
+
+    match result {
+        Ok(()) => {
+            println!("Rendered {}", output.display());
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("Could not render {}: {}", file.display(), e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn render_session(file: PathBuf, output: Option<PathBuf>) -> ExitCode {
+    let session = match Session::load(&file) {
+        Ok(session) => session,
+        Err(e) => {
+            eprintln!("Could not render {}: {}", file.display(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let output = output.unwrap_or_else(|| file.with_extension("wav"));
+    let root = document_root(&file);
+
+    let result = live_editor::render_session(&session, &root, &output, |done| {
+        eprint!("\rRendering session… {:>3.0}%", done * 100.0);
+        let _ = io::stderr().flush();
+    });
+    eprintln!();
+
+    match result {
+        Ok(()) => {
+            println!("Rendered {}", output.display());
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("Could not render {}: {}", file.display(), e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn play(file: PathBuf, bars: Option<f64>) -> ExitCode {
+    let source = match fs::read_to_string(&file) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("Could not read {}: {}", file.display(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let settings = BounceSettings::for_document(&file, None);
+    let root = document_root(&file);
+
+    println!("Playing {} at {} bpm", file.display(), settings.tempo);
+    match live_editor::play_document(&source, &root, settings, bars) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Could not play {}: {}", file.display(), e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn formatted(source: &str) -> String {
+    let formatted = live_language::format_document(source);
+    if formatted.is_empty() {
+        formatted
+    } else {
+        formatted + "\n"
+    }
 }
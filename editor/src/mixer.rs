@@ -0,0 +1,181 @@
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+
+use live_editor_state::{LineData, Pos};
+use live_engine::EngineHandle;
+use live_language::{outline, play_targets};
+
+use crate::{
+    render::{Overlay, Renderer},
+    storage::storage,
+    util::loc_to_pos,
+};
+
+/// Lives in the workspace root, next to the backups, because the code itself shouldn't change when you mute something
+const MIXER_FILE: &str = ".mixer";
+
+const TOGGLE_SIZE: f32 = 14.0;
+const TOGGLE_GAP: f32 = 4.0;
+const GUTTER_MARGIN: f32 = 6.0;
+const FONT_SIZE: f32 = 10.0;
+
+const TOGGLE_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.06];
+const MUTED_COLOR: [f32; 4] = [0.8, 0.45, 0.0, 1.0];
+const SOLOED_COLOR: [f32; 4] = [0.0, 0.6, 0.3, 1.0];
+const TEXT_COLOR: [f32; 4] = [0.02, 0.02, 0.02, 0.45];
+const ACTIVE_TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Toggle {
+    Mute,
+    Solo,
+}
+
+/**
+    Mute and solo toggles in the gutter, next to every top-level declaration that's being played (like `def beat` with a `play beat;`). They're sent to the engine as they are, without touching the code, and saved per workspace in `.mixer`, one `mute <name>` or `solo <name>` per line.
+*/
+pub struct Mixer {
+    file: PathBuf,
+    muted: BTreeSet<String>,
+    soloed: BTreeSet<String>,
+    source: Option<String>,
+    // (name, row) of every declaration that gets toggles
+    entries: Vec<(String, i32)>,
+}
+
+impl Mixer {
+    pub fn load(root: &Path) -> Self {
+        let file = root.join(MIXER_FILE);
+
+        let mut muted = BTreeSet::new();
+        let mut soloed = BTreeSet::new();
+
+        for line in storage().read(&file).unwrap_or_default().lines() {
+            match line.split_once(' ') {
+                Some(("mute", name)) => {
+                    muted.insert(name.trim().to_string());
+                }
+                Some(("solo", name)) => {
+                    soloed.insert(name.trim().to_string());
+                }
+                _ => {}
+            }
+        }
+
+        Self {
+            file,
+            muted,
+            soloed,
+            source: None,
+            entries: vec![],
+        }
+    }
+
+    fn save(&self) {
+        let contents = self
+            .muted
+            .iter()
+            .map(|name| format!("mute {}\n", name))
+            .chain(self.soloed.iter().map(|name| format!("solo {}\n", name)))
+            .collect::<String>();
+
+        if let Err(e) = storage().write(&self.file, &contents) {
+            tracing::warn!("Could not save mute/solo state: {}", e);
+        }
+    }
+
+    pub fn sync(&mut self, linedata: &LineData) {
+        let source = linedata.to_string();
+        if self.source.as_ref() == Some(&source) {
+            return;
+        }
+
+        let played = play_targets(&source)
+            .into_iter()
+            .map(|target| target.name)
+            .collect::<Vec<_>>();
+
+        self.entries = outline(&source)
+            .into_iter()
+            .filter(|symbol| played.contains(&symbol.name))
+            .map(|symbol| {
+                let row = loc_to_pos(linedata, symbol.range.start).row;
+                (symbol.name, row)
+            })
+            .collect();
+
+        self.source = Some(source);
+    }
+
+    /**
+        Sends the mute/solo state to the engine, e.g. when it has just started
+    */
+    pub fn apply(&self, engine: &EngineHandle) {
+        engine.set_mute_solo(
+            self.muted.iter().cloned().collect(),
+            self.soloed.iter().cloned().collect(),
+        );
+    }
+
+    pub fn toggle(&mut self, name: &str, toggle: Toggle) {
+        let set = match toggle {
+            Toggle::Mute => &mut self.muted,
+            Toggle::Solo => &mut self.soloed,
+        };
+
+        if !set.remove(name) {
+            set.insert(name.to_string());
+        }
+
+        self.save();
+    }
+
+    fn bounds(renderer: &Renderer, row: i32, toggle: Toggle) -> (f32, f32, f32, f32) {
+        let system = &renderer.system;
+        let (_, y) = system.pos_to_px(Pos { row, col: 0 });
+        let line_height = system.char_size.1 / system.scale_factor;
+
+        let min_x = match toggle {
+            Toggle::Mute => GUTTER_MARGIN,
+            Toggle::Solo => GUTTER_MARGIN + TOGGLE_SIZE + TOGGLE_GAP,
+        };
+        let min_y = y + (line_height - TOGGLE_SIZE) / 2.0;
+
+        (min_x, min_y, min_x + TOGGLE_SIZE, min_y + TOGGLE_SIZE)
+    }
+
+    pub fn hit_test(&self, renderer: &Renderer, (x, y): (f32, f32)) -> Option<(String, Toggle)> {
+        self.entries.iter().find_map(|(name, row)| {
+            [Toggle::Mute, Toggle::Solo].into_iter().find_map(|toggle| {
+                let (min_x, min_y, max_x, max_y) = Self::bounds(renderer, *row, toggle);
+                (min_x <= x && x <= max_x && min_y <= y && y <= max_y)
+                    .then(|| (name.clone(), toggle))
+            })
+        })
+    }
+
+    pub fn draw(&self, renderer: &Renderer, overlay: &mut Overlay) {
+        for (name, row) in &self.entries {
+            for (toggle, label, active, active_color) in [
+                (Toggle::Mute, "M", self.muted.contains(name), MUTED_COLOR),
+                (Toggle::Solo, "S", self.soloed.contains(name), SOLOED_COLOR),
+            ] {
+                let bounds = Self::bounds(renderer, *row, toggle);
+
+                overlay.quad(bounds, if active { active_color } else { TOGGLE_COLOR });
+                overlay.bold_text(
+                    (bounds.0 + 3.5, bounds.1 + (TOGGLE_SIZE - FONT_SIZE) / 2.0),
+                    label,
+                    FONT_SIZE,
+                    if active {
+                        ACTIVE_TEXT_COLOR
+                    } else {
+                        TEXT_COLOR
+                    },
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,267 @@
+use std::path::{Path, PathBuf};
+
+use live_engine::{EngineHandle, MORPH};
+
+use crate::{render::Overlay, status_bar::STATUS_BAR_HEIGHT, storage::storage};
+
+/// Lives in the workspace root, like the mixer's `.mixer`, so that the snapshots are still there for the next performance
+const SNAPSHOTS_FILE: &str = ".snapshots";
+
+/// The snapshot slots, by name
+pub const SLOTS: [char; 3] = ['A', 'B', 'C'];
+
+const PANEL_WIDTH: f32 = 300.0;
+const PANEL_MARGIN: f32 = 12.0;
+const PANEL_HEIGHT: f32 = 36.0;
+const FONT_SIZE: f32 = 14.0;
+// (the slot names on either end, which are clicked to morph from or to another one)
+const END_WIDTH: f32 = 32.0;
+const TRACK_HEIGHT: f32 = 4.0;
+const HANDLE_SIZE: f32 = 12.0;
+
+const PANEL_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.05];
+const TEXT_COLOR: [f32; 4] = [0.02, 0.02, 0.02, 1.0];
+const DIM_TEXT_COLOR: [f32; 4] = [0.02, 0.02, 0.02, 0.45];
+const TRACK_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.1];
+const HANDLE_COLOR: [f32; 4] = [0.0, 0.6, 0.3, 1.0];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum End {
+    From,
+    To,
+}
+
+pub enum MorphHit {
+    End(End),
+    /// (where on the slider, from 0 to 1)
+    Slider(f32),
+}
+
+/**
+    Snapshot slots (A, B and C) of the parameter values that are being performed with (the knobs and sliders, and the numbers marked with `perform(..)`), and a morph slider at the bottom of the window, that crossfades between two of them. The engine does the interpolating (see `EngineHandle::set_morph`), so the code doesn't change while morphing. Saved per workspace in `.snapshots`, one `<slot> <name> <value>` per line.
+*/
+pub struct Snapshots {
+    file: PathBuf,
+    // (parameter name, value), per slot, where an empty one wasn't captured (yet)
+    slots: [Vec<(String, f32)>; SLOTS.len()],
+    from: usize,
+    to: usize,
+    position: f32,
+}
+
+impl Snapshots {
+    pub fn load(root: &Path) -> Self {
+        let file = root.join(SNAPSHOTS_FILE);
+
+        let mut slots: [Vec<(String, f32)>; SLOTS.len()] = Default::default();
+
+        for line in storage().read(&file).unwrap_or_default().lines() {
+            let mut parts = line.split_whitespace();
+            let (Some(slot), Some(name), Some(value)) = (parts.next(), parts.next(), parts.next())
+            else {
+                continue;
+            };
+
+            let slot = SLOTS.iter().position(|s| slot == s.to_string());
+            if let (Some(slot), Ok(value)) = (slot, value.parse()) {
+                slots[slot].push((name.to_string(), value));
+            }
+        }
+
+        Self {
+            file,
+            slots,
+            from: 0,
+            to: 1,
+            position: 0.0,
+        }
+    }
+
+    fn save(&self) {
+        let contents = self
+            .slots
+            .iter()
+            .zip(SLOTS)
+            .flat_map(|(params, slot)| {
+                params
+                    .iter()
+                    .map(move |(name, value)| format!("{} {} {}\n", slot, name, value))
+            })
+            .collect::<String>();
+
+        if let Err(e) = storage().write(&self.file, &contents) {
+            tracing::warn!("Could not save snapshots: {}", e);
+        }
+    }
+
+    /**
+        Captures the parameters' values as they are now into a slot
+    */
+    pub fn capture(&mut self, slot: usize, params: Vec<(String, f32)>) {
+        self.slots[slot] = params;
+        self.save();
+    }
+
+    /**
+        Morphs from or to the next slot that has a snapshot in it (other than the one on the other end)
+    */
+    pub fn cycle(&mut self, end: End) {
+        let (this, other) = match end {
+            End::From => (self.from, self.to),
+            End::To => (self.to, self.from),
+        };
+
+        let next = (1..SLOTS.len())
+            .map(|i| (this + i) % SLOTS.len())
+            .find(|&slot| slot != other && !self.slots[slot].is_empty());
+
+        if let Some(next) = next {
+            match end {
+                End::From => self.from = next,
+                End::To => self.to = next,
+            }
+        }
+    }
+
+    pub fn position(&self) -> f32 {
+        self.position
+    }
+
+    pub fn set_position(&mut self, position: f32) {
+        self.position = position.clamp(0.0, 1.0);
+    }
+
+    /**
+        What's morphed between: every parameter that's in either snapshot, where one that's only in one of them stays what it is there
+    */
+    fn params(&self) -> Vec<(String, f32, f32)> {
+        let (from, to) = (&self.slots[self.from], &self.slots[self.to]);
+        let value_in = |params: &[(String, f32)], name: &str| {
+            params.iter().find(|(n, _)| n == name).map(|&(_, v)| v)
+        };
+
+        let mut params = from
+            .iter()
+            .map(|(name, a)| (name.clone(), *a, value_in(to, name).unwrap_or(*a)))
+            .collect::<Vec<_>>();
+        params.extend(
+            to.iter()
+                .filter(|(name, _)| value_in(from, name).is_none())
+                .map(|(name, b)| (name.clone(), *b, *b)),
+        );
+        params
+    }
+
+    /**
+        Sends the snapshots that are morphed between to the engine, and where it is, e.g. when it has just started
+    */
+    pub fn apply(&self, engine: &EngineHandle) {
+        engine.set_morph(self.params());
+        engine.set_param(MORPH, self.position);
+    }
+
+    /// (it's only shown once there's something to morph)
+    fn is_shown(&self) -> bool {
+        self.slots.iter().any(|params| !params.is_empty())
+    }
+
+    fn bounds(&self, (width, height): (f32, f32)) -> (f32, f32, f32, f32) {
+        let min_x = (width - PANEL_WIDTH) / 2.0;
+        let max_y = height - STATUS_BAR_HEIGHT - PANEL_MARGIN;
+
+        (min_x, max_y - PANEL_HEIGHT, min_x + PANEL_WIDTH, max_y)
+    }
+
+    /// (where the slider's track starts and ends)
+    fn track(&self, window_size: (f32, f32)) -> (f32, f32) {
+        let (min_x, _, max_x, _) = self.bounds(window_size);
+        (
+            min_x + END_WIDTH + HANDLE_SIZE,
+            max_x - END_WIDTH - HANDLE_SIZE,
+        )
+    }
+
+    /**
+        Where a mouse (that's dragging the slider) is on the slider, from 0 to 1, also when it's beyond either end
+    */
+    pub fn slider_position(&self, window_size: (f32, f32), (x, _): (f32, f32)) -> f32 {
+        let (start, end) = self.track(window_size);
+        ((x - start) / (end - start)).clamp(0.0, 1.0)
+    }
+
+    pub fn hit_test(&self, window_size: (f32, f32), (x, y): (f32, f32)) -> Option<MorphHit> {
+        if !self.is_shown() {
+            return None;
+        }
+
+        let (min_x, min_y, max_x, max_y) = self.bounds(window_size);
+        if x < min_x || x > max_x || y < min_y || y > max_y {
+            return None;
+        }
+
+        Some(if x < min_x + END_WIDTH {
+            MorphHit::End(End::From)
+        } else if x > max_x - END_WIDTH {
+            MorphHit::End(End::To)
+        } else {
+            MorphHit::Slider(self.slider_position(window_size, (x, y)))
+        })
+    }
+
+    pub fn draw(&self, window_size: (f32, f32), overlay: &mut Overlay) {
+        if !self.is_shown() {
+            return;
+        }
+
+        let (min_x, min_y, max_x, max_y) = self.bounds(window_size);
+        let mid_y = (min_y + max_y) / 2.0;
+        let text_y = mid_y - FONT_SIZE / 2.0;
+
+        overlay.quad((min_x, min_y, max_x, max_y), PANEL_COLOR);
+
+        for (end, slot, x) in [
+            (End::From, self.from, min_x + 12.0),
+            (End::To, self.to, max_x - END_WIDTH + 12.0),
+        ] {
+            let empty = self.slots[slot].is_empty();
+            // (the end that it's nearest to is the one that's heard most)
+            let nearest = match end {
+                End::From => self.position < 0.5,
+                End::To => self.position >= 0.5,
+            };
+
+            overlay.bold_text(
+                (x, text_y),
+                SLOTS[slot].to_string(),
+                FONT_SIZE,
+                if empty || !nearest {
+                    DIM_TEXT_COLOR
+                } else {
+                    TEXT_COLOR
+                },
+            );
+        }
+
+        let (start, end) = self.track(window_size);
+        overlay.quad(
+            (
+                start,
+                mid_y - TRACK_HEIGHT / 2.0,
+                end,
+                mid_y + TRACK_HEIGHT / 2.0,
+            ),
+            TRACK_COLOR,
+        );
+
+        let x = start + (end - start) * self.position;
+        overlay.quad(
+            (
+                x - HANDLE_SIZE / 2.0,
+                mid_y - HANDLE_SIZE / 2.0,
+                x + HANDLE_SIZE / 2.0,
+                mid_y + HANDLE_SIZE / 2.0,
+            ),
+            HANDLE_COLOR,
+        );
+    }
+}
@@ -0,0 +1,159 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use live_engine::MidiEvent;
+use winit::keyboard::KeyCode;
+
+/// The bottom row's C starts out as C4 (middle C)
+const DEFAULT_OCTAVE: i32 = 4;
+const MIN_OCTAVE: i32 = 0;
+const MAX_OCTAVE: i32 = 8;
+
+/// Strikes that follow the previous one this quickly get full velocity ..
+const FAST_STRIKE: Duration = Duration::from_millis(100);
+/// .. and strikes that come this long after it (or later), the softest
+const SLOW_STRIKE: Duration = Duration::from_millis(600);
+const MIN_VELOCITY: f32 = 0.5;
+
+/**
+    Semitones above the bottom row's C, by physical key position, like a piano keyboard: the bottom two letter rows are one octave, the top two the next
+*/
+fn semitone(key: KeyCode) -> Option<i32> {
+    Some(match key {
+        KeyCode::KeyZ => 0,
+        KeyCode::KeyS => 1,
+        KeyCode::KeyX => 2,
+        KeyCode::KeyD => 3,
+        KeyCode::KeyC => 4,
+        KeyCode::KeyV => 5,
+        KeyCode::KeyG => 6,
+        KeyCode::KeyB => 7,
+        KeyCode::KeyH => 8,
+        KeyCode::KeyN => 9,
+        KeyCode::KeyJ => 10,
+        KeyCode::KeyM => 11,
+        KeyCode::Comma => 12,
+        KeyCode::KeyL => 13,
+        KeyCode::Period => 14,
+        KeyCode::Semicolon => 15,
+        KeyCode::Slash => 16,
+
+        KeyCode::KeyQ => 12,
+        KeyCode::Digit2 => 13,
+        KeyCode::KeyW => 14,
+        KeyCode::Digit3 => 15,
+        KeyCode::KeyE => 16,
+        KeyCode::KeyR => 17,
+        KeyCode::Digit5 => 18,
+        KeyCode::KeyT => 19,
+        KeyCode::Digit6 => 20,
+        KeyCode::KeyY => 21,
+        KeyCode::Digit7 => 22,
+        KeyCode::KeyU => 23,
+        KeyCode::KeyI => 24,
+        KeyCode::Digit9 => 25,
+        KeyCode::KeyO => 26,
+        KeyCode::Digit0 => 27,
+        KeyCode::KeyP => 28,
+        _ => return None,
+    })
+}
+
+/**
+    Computer keys don't know how hard they're hit, so we go by how quickly a strike follows the previous one: fast runs and trills come out louder than lone notes.
+*/
+fn velocity(since_last_strike: Option<Duration>) -> f32 {
+    let Some(since) = since_last_strike else {
+        return MIN_VELOCITY;
+    };
+
+    let fraction = (since.saturating_sub(FAST_STRIKE).as_secs_f32()
+        / (SLOW_STRIKE - FAST_STRIKE).as_secs_f32())
+    .min(1.0);
+
+    1.0 - fraction * (1.0 - MIN_VELOCITY)
+}
+
+/**
+    "Musical typing" (Cmd+Shift+M): while it's on, the rows of the computer keyboard play notes into the engine's `midi_in` source instead of typing text. Minus and equals shift the octave.
+*/
+pub struct MusicalTyping {
+    enabled: bool,
+    octave: i32,
+    // the keys that are down, and which note they started (even if the octave was changed since)
+    held: HashMap<KeyCode, u8>,
+    last_strike: Option<Instant>,
+}
+
+impl MusicalTyping {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            octave: DEFAULT_OCTAVE,
+            held: HashMap::new(),
+            last_strike: None,
+        }
+    }
+
+    /**
+        The octave of the bottom row, while it's on
+    */
+    pub fn octave(&self) -> Option<i32> {
+        self.enabled.then_some(self.octave)
+    }
+
+    /**
+        Turns it on or off, returning note offs for any notes that are still held
+    */
+    pub fn toggle(&mut self) -> Vec<MidiEvent> {
+        self.enabled = !self.enabled;
+
+        self.held
+            .drain()
+            .map(|(_, note)| MidiEvent::NoteOff { note })
+            .collect()
+    }
+
+    /**
+        Whether the key plays (or releases) a note, rather than doing what it usually does
+    */
+    pub fn captures(&self, key: KeyCode) -> bool {
+        let is_octave_key = matches!(key, KeyCode::Minus | KeyCode::Equal);
+
+        (self.enabled && (semitone(key).is_some() || is_octave_key)) || self.held.contains_key(&key)
+    }
+
+    pub fn key(&mut self, key: KeyCode, pressed: bool, repeat: bool) -> Option<MidiEvent> {
+        if !pressed {
+            let note = self.held.remove(&key)?;
+            return Some(MidiEvent::NoteOff { note });
+        }
+
+        // (held keys auto-repeat, but a held note just keeps sounding)
+        if repeat || !self.enabled {
+            return None;
+        }
+
+        match key {
+            KeyCode::Minus => {
+                self.octave = (self.octave - 1).max(MIN_OCTAVE);
+                None
+            }
+            KeyCode::Equal => {
+                self.octave = (self.octave + 1).min(MAX_OCTAVE);
+                None
+            }
+            _ => {
+                let note = ((self.octave + 1) * 12 + semitone(key)?).clamp(0, 127) as u8;
+                let velocity = velocity(self.last_strike.map(|t| t.elapsed()));
+
+                self.last_strike = Some(Instant::now());
+                self.held.insert(key, note);
+
+                Some(MidiEvent::NoteOn { note, velocity })
+            }
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use live_editor_state::{LineData, Pos};
+use live_language::{outline, Symbol, SymbolKind};
+
+use crate::{
+    panel::{List, ListHit, PANEL_MARGIN},
+    render::Overlay,
+    util::loc_to_pos,
+};
+
+const PANEL_WIDTH: f32 = 220.0;
+const PANEL_TOP: f32 = 64.0;
+const ROW_HEIGHT: f32 = 24.0;
+const FONT_SIZE: f32 = 14.0;
+
+const PANEL_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.05];
+const TEXT_COLOR: [f32; 4] = [0.02, 0.02, 0.02, 1.0];
+const DIM_TEXT_COLOR: [f32; 4] = [0.02, 0.02, 0.02, 0.45];
+
+pub fn kind_label(kind: SymbolKind) -> &'static str {
+    match kind {
+        SymbolKind::Let => "let",
+        SymbolKind::Def => "def",
+        SymbolKind::Fn => "fn",
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OutlineEntry {
+    pub symbol: Symbol,
+    /// where the declared name starts in the editor
+    pub pos: Pos,
+}
+
+/**
+    The document's top-level declarations, as reported by the language crate. Re-derived from the source whenever it changes, so positions are always up to date with edits.
+*/
+#[derive(Debug, Default)]
+pub struct Outline {
+    source: Option<String>,
+    pub entries: Vec<OutlineEntry>,
+}
+
+impl Outline {
+    pub fn sync(&mut self, linedata: &LineData) {
+        let source = linedata.to_string();
+        if self.source.as_ref() == Some(&source) {
+            return;
+        }
+
+        self.entries = outline(&source)
+            .into_iter()
+            .map(|symbol| OutlineEntry {
+                pos: loc_to_pos(linedata, symbol.name_range.start),
+                symbol,
+            })
+            .collect();
+
+        self.source = Some(source);
+    }
+}
+
+/**
+    The collapsible outline side panel, on the right side of the window. Clicking the header collapses/expands it, clicking an entry jumps there.
+*/
+pub struct OutlinePanel {
+    pub collapsed: bool,
+}
+
+impl OutlinePanel {
+    pub fn new() -> Self {
+        Self { collapsed: false }
+    }
+
+    fn list(&self, outline: &Outline, (width, _): (f32, f32)) -> List {
+        List::below(
+            (width - PANEL_WIDTH - PANEL_MARGIN, PANEL_TOP),
+            PANEL_WIDTH,
+            ROW_HEIGHT,
+            self.collapsed,
+            outline.entries.len(),
+        )
+    }
+
+    pub fn hit_test(
+        &self,
+        outline: &Outline,
+        window_size: (f32, f32),
+        point: (f32, f32),
+    ) -> Option<ListHit> {
+        self.list(outline, window_size).hit_test(point)
+    }
+
+    pub fn draw(&self, outline: &Outline, window_size: (f32, f32), overlay: &mut Overlay) {
+        let list = self.list(outline, window_size);
+        list.draw(
+            "Outline",
+            "(no declarations)",
+            FONT_SIZE,
+            [PANEL_COLOR, TEXT_COLOR, DIM_TEXT_COLOR],
+            overlay,
+        );
+
+        if self.collapsed {
+            return;
+        }
+
+        let (_, _, max_x, _) = list.bounds;
+        for (i, entry) in outline.entries.iter().enumerate() {
+            let (x, y) = list.row_text(i, FONT_SIZE);
+
+            overlay.text(
+                (x, y),
+                kind_label(entry.symbol.kind),
+                FONT_SIZE,
+                DIM_TEXT_COLOR,
+            );
+
+            overlay.text((x + 40.0, y), &entry.symbol.name, FONT_SIZE, TEXT_COLOR);
+
+            overlay.text(
+                (max_x - 40.0, y),
+                format!("{}", entry.pos.row + 1),
+                FONT_SIZE,
+                DIM_TEXT_COLOR,
+            );
+        }
+    }
+}
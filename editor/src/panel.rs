@@ -0,0 +1,217 @@
+use crate::{render::Overlay, status_bar::STATUS_BAR_HEIGHT};
+
+/// (min_x, min_y, max_x, max_y), in logical pixels
+pub type Bounds = (f32, f32, f32, f32);
+
+// (no text measuring yet, this is roughly right for the 13 and 14px text of the panels)
+const CHAR_WIDTH: f32 = 7.0;
+
+pub const HEADER_HEIGHT: f32 = 30.0;
+// (between the panels and the edges of the window)
+pub const PANEL_MARGIN: f32 = 12.0;
+// (where the text in a list starts)
+const TEXT_INSET: f32 = 10.0;
+
+pub fn contains((min_x, min_y, max_x, max_y): Bounds, (x, y): (f32, f32)) -> bool {
+    min_x <= x && x <= max_x && min_y <= y && y <= max_y
+}
+
+/// Where text of this size goes, to be centered in a row from `top` that's `height` high
+pub fn text_y(top: f32, height: f32, font_size: f32) -> f32 {
+    top + (height - font_size) / 2.0
+}
+
+/// (about)
+pub fn text_width(chars: usize) -> f32 {
+    chars as f32 * CHAR_WIDTH
+}
+
+/// How many characters fit in a width (about)
+pub fn chars_in(width: f32) -> usize {
+    (width / CHAR_WIDTH).max(0.0) as usize
+}
+
+/// Cuts the text off (with an ellipsis) after so many characters
+pub fn fit(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((i, _)) => format!("{}…", &text[..i]),
+        None => text.to_string(),
+    }
+}
+
+/**
+    A popup with lines of text, like the documentation of a name or the quick info of an error: as wide as the longest line, with padding all around
+*/
+pub fn popup_size<S: AsRef<str>>(lines: &[S], padding: f32, row_height: f32) -> (f32, f32) {
+    let longest = lines
+        .iter()
+        .map(|line| line.as_ref().chars().count())
+        .max()
+        .unwrap_or(0);
+
+    (
+        2.0 * padding + text_width(longest),
+        2.0 * padding + lines.len() as f32 * row_height,
+    )
+}
+
+/**
+    A panel along the bottom of the window, right above the status bar, and as wide as it (like the console and the clip launcher)
+*/
+pub fn docked_bounds((width, height): (f32, f32), panel_height: f32) -> Bounds {
+    let max_y = height - STATUS_BAR_HEIGHT;
+    (0.0, (max_y - panel_height).max(0.0), width, max_y)
+}
+
+/// (with its title in the header, and a line along the top)
+pub fn draw_docked(
+    bounds: Bounds,
+    title: &str,
+    font_size: f32,
+    [panel_color, border_color, text_color]: [[f32; 4]; 3],
+    overlay: &mut Overlay,
+) {
+    let (min_x, min_y, max_x, _) = bounds;
+
+    overlay.quad(bounds, panel_color);
+    overlay.quad((min_x, min_y, max_x, min_y + 1.0), border_color);
+    overlay.bold_text(
+        (
+            min_x + PANEL_MARGIN,
+            text_y(min_y, HEADER_HEIGHT, font_size),
+        ),
+        title,
+        font_size,
+        text_color,
+    );
+}
+
+pub enum ListHit {
+    /// to collapse (or expand) it
+    Header,
+    Entry(usize),
+    /// somewhere on the panel, but not on anything clickable
+    Panel,
+}
+
+/**
+    A collapsible panel with a header, and a row for every entry (like the outline, the problems, and the sample browser), which is `width` wide and placed by its top left corner, or by its bottom left corner for one that hangs above the status bar.
+*/
+#[derive(Debug, Clone, Copy)]
+pub struct List {
+    pub bounds: Bounds,
+    pub row_height: f32,
+    pub collapsed: bool,
+    /// (how many are shown)
+    pub rows: usize,
+}
+
+impl List {
+    fn height(row_height: f32, collapsed: bool, rows: usize) -> f32 {
+        match collapsed {
+            true => HEADER_HEIGHT,
+            // (when there's nothing, there's still a row to say so)
+            false => HEADER_HEIGHT + rows.max(1) as f32 * row_height + 6.0,
+        }
+    }
+
+    pub fn below(
+        (min_x, min_y): (f32, f32),
+        width: f32,
+        row_height: f32,
+        collapsed: bool,
+        rows: usize,
+    ) -> Self {
+        let height = Self::height(row_height, collapsed, rows);
+
+        Self {
+            bounds: (min_x, min_y, min_x + width, min_y + height),
+            row_height,
+            collapsed,
+            rows,
+        }
+    }
+
+    pub fn above(
+        (min_x, max_y): (f32, f32),
+        width: f32,
+        row_height: f32,
+        collapsed: bool,
+        rows: usize,
+    ) -> Self {
+        let height = Self::height(row_height, collapsed, rows);
+
+        Self {
+            bounds: (min_x, max_y - height, min_x + width, max_y),
+            row_height,
+            collapsed,
+            rows,
+        }
+    }
+
+    /// (which entry is shown in a row is up to whatever's listed, like when it's scrolled)
+    pub fn hit_test(&self, (x, y): (f32, f32)) -> Option<ListHit> {
+        let (_, min_y, _, _) = self.bounds;
+        if !contains(self.bounds, (x, y)) {
+            return None;
+        }
+
+        if y < min_y + HEADER_HEIGHT {
+            return Some(ListHit::Header);
+        }
+
+        let i = ((y - min_y - HEADER_HEIGHT) / self.row_height) as usize;
+        if !self.collapsed && i < self.rows {
+            Some(ListHit::Entry(i))
+        } else {
+            Some(ListHit::Panel)
+        }
+    }
+
+    pub fn row_top(&self, i: usize) -> f32 {
+        self.bounds.1 + HEADER_HEIGHT + i as f32 * self.row_height
+    }
+
+    /// Where a row's text starts
+    pub fn row_text(&self, i: usize, font_size: f32) -> (f32, f32) {
+        (
+            self.bounds.0 + TEXT_INSET,
+            text_y(self.row_top(i), self.row_height, font_size),
+        )
+    }
+
+    /// Where the header's text is vertically, to put more in it
+    pub fn header_text_y(&self, font_size: f32) -> f32 {
+        text_y(self.bounds.1, HEADER_HEIGHT, font_size)
+    }
+
+    /**
+        Draws the panel with its title (and whether it's collapsed) in the header, and what to say when there's nothing in it, if there isn't. The rows are up to whatever's listed.
+    */
+    pub fn draw(
+        &self,
+        title: &str,
+        empty: &str,
+        font_size: f32,
+        [panel_color, text_color, dim_text_color]: [[f32; 4]; 3],
+        overlay: &mut Overlay,
+    ) {
+        overlay.quad(self.bounds, panel_color);
+
+        overlay.bold_text(
+            (self.bounds.0 + TEXT_INSET, self.header_text_y(font_size)),
+            format!("{} {}", if self.collapsed { "▸" } else { "▾" }, title),
+            font_size,
+            text_color,
+        );
+
+        if !self.collapsed && self.rows == 0 {
+            overlay.text(
+                self.row_text(0, font_size),
+                empty,
+                font_size,
+                dim_text_color,
+            );
+        }
+    }
+}
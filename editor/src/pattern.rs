@@ -0,0 +1,169 @@
+/**
+    A step sequence: for each lane (e.g. a sample, or a note), for each step, whether it triggers, and how hard.
+
+    This is the "labeled set of triggers" that the audio graph consumes, where the lane index is the label, and the velocity the value (in [0, 1]).
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pattern {
+    pub steps: usize,
+    pub lanes: Vec<Vec<Option<f32>>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Trigger {
+    pub lane: usize,
+    pub velocity: f32,
+}
+
+impl Pattern {
+    pub fn new(lanes: usize, steps: usize) -> Self {
+        Self {
+            steps,
+            lanes: vec![vec![None; steps]; lanes],
+        }
+    }
+
+    pub fn get(&self, lane: usize, step: usize) -> Option<f32> {
+        self.lanes.get(lane)?.get(step).copied().flatten()
+    }
+
+    pub fn set(&mut self, lane: usize, step: usize, velocity: Option<f32>) {
+        if let Some(cell) = self.lanes.get_mut(lane).and_then(|lane| lane.get_mut(step)) {
+            *cell = velocity.map(|v| v.max(0.0).min(1.0));
+        }
+    }
+
+    /**
+        Everything that triggers at the given step (patterns loop, so any step is fine)
+    */
+    #[allow(unused)]
+    pub fn triggers_at(&self, step: usize) -> Vec<Trigger> {
+        if self.steps == 0 {
+            return vec![];
+        }
+
+        let step = step % self.steps;
+
+        self.lanes
+            .iter()
+            .enumerate()
+            .filter_map(|(lane, cells)| cells[step].map(|velocity| Trigger { lane, velocity }))
+            .collect()
+    }
+}
+
+const NOTE_NAMES: &[&str] = &[
+    "c", "c#", "d", "d#", "e", "f", "f#", "g", "g#", "a", "a#", "b",
+];
+
+/**
+    A note in a note pattern, placed on the pattern's step grid (though it doesn't have to be on a step exactly)
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Note {
+    /// (MIDI note number, so 60 is `c4`)
+    pub pitch: u8,
+    pub start: f32,
+    pub length: f32,
+    pub velocity: f32,
+}
+
+/**
+    A melody (or chords): notes with a pitch, start, length (in steps) and velocity, looping every `steps` steps.
+
+    Written in the code as e.g. `[c4@0:2 e4@2:1 g4@3:1*0.5]`, that is, `pitch@start:length`, with an optional `*velocity` if it's not 1.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotePattern {
+    pub steps: usize,
+    pub notes: Vec<Note>,
+}
+
+impl NotePattern {
+    pub fn new(steps: usize) -> Self {
+        Self {
+            steps,
+            notes: vec![],
+        }
+    }
+
+    /**
+        The (topmost) note that sounds at the given pitch and step, if any
+    */
+    pub fn note_at(&self, pitch: u8, step: f32) -> Option<usize> {
+        self.notes
+            .iter()
+            .rposition(|n| n.pitch == pitch && n.start <= step && step < n.start + n.length)
+    }
+
+    pub fn parse(source: &str) -> Option<Self> {
+        let inner = source.trim().strip_prefix('[')?.strip_suffix(']')?;
+
+        let notes = inner
+            .split_whitespace()
+            .map(|note| {
+                let (pitch, rest) = note.split_once('@')?;
+                let (start, rest) = rest.split_once(':')?;
+                let (length, velocity) = match rest.split_once('*') {
+                    Some((length, velocity)) => (length, velocity.parse().ok()?),
+                    None => (rest, 1.0),
+                };
+
+                Some(Note {
+                    pitch: parse_note_name(pitch)?,
+                    start: start.parse().ok()?,
+                    length: length.parse().ok()?,
+                    velocity,
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        // (whatever the notes need, in whole bars of 16)
+        let end = notes.iter().map(|n| n.start + n.length).fold(0.0, f32::max);
+        let steps = ((end / 16.0).ceil() as usize).max(1) * 16;
+
+        Some(Self { steps, notes })
+    }
+}
+
+impl std::fmt::Display for NotePattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut notes = self.notes.clone();
+        notes.sort_by(|a, b| a.start.total_cmp(&b.start).then(a.pitch.cmp(&b.pitch)));
+
+        let notes = notes
+            .iter()
+            .map(|n| {
+                let mut s = format!("{}@{}:{}", note_name(n.pitch), n.start, n.length);
+                if n.velocity < 1.0 {
+                    s += &format!("*{:.2}", n.velocity);
+                }
+                s
+            })
+            .collect::<Vec<_>>();
+
+        write!(f, "[{}]", notes.join(" "))
+    }
+}
+
+/**
+    E.g. `c4` for 60, `a#-1` for 10
+*/
+pub fn note_name(pitch: u8) -> String {
+    let octave = pitch as i32 / 12 - 1;
+    format!("{}{}", NOTE_NAMES[pitch as usize % 12], octave)
+}
+
+pub fn parse_note_name(name: &str) -> Option<u8> {
+    let split = name.find(|c: char| c.is_ascii_digit() || c == '-')?;
+    let (name, octave) = name.split_at(split);
+
+    let semitone = NOTE_NAMES
+        .iter()
+        .position(|n| n.eq_ignore_ascii_case(name))? as i32;
+    let octave = octave.parse::<i32>().ok()?;
+
+    u8::try_from((octave + 1) * 12 + semitone)
+        .ok()
+        .filter(|&pitch| pitch < 128)
+}
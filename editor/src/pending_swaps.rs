@@ -0,0 +1,70 @@
+use std::time::Instant;
+
+use live_editor_state::{LineSelection, Pos};
+
+use crate::render::{Overlay, Renderer};
+
+const STRIPE_WIDTH: f32 = 3.0;
+// (between the gutter and the code)
+const STRIPE_OFFSET: f32 = 8.0;
+const STRIPE_COLOR: [f32; 4] = [0.8, 0.45, 0.0, 0.8];
+
+/**
+    Code that was evaluated while changes are quantized (see `live_engine::Quantize`), so it doesn't land until the next bar or phrase. Until then, its lines get a stripe in front of them, and when it lands, it flashes like code that's evaluated right away.
+*/
+#[derive(Default)]
+pub struct PendingSwaps {
+    // (the evaluated lines, and when they land)
+    entries: Vec<(Vec<LineSelection>, Instant)>,
+}
+
+impl PendingSwaps {
+    pub fn push(&mut self, region: Vec<LineSelection>, lands_at: Instant) {
+        self.entries.push((region, lands_at));
+    }
+
+    /**
+        When the next one lands, if any are pending
+    */
+    pub fn due_at(&self) -> Option<Instant> {
+        self.entries.iter().map(|(_, at)| *at).min()
+    }
+
+    /**
+        Takes out the lines of what landed by now
+    */
+    pub fn land(&mut self) -> Vec<LineSelection> {
+        let now = Instant::now();
+        let mut landed = vec![];
+
+        self.entries.retain(|(region, at)| {
+            if *at > now {
+                return true;
+            }
+            landed.extend(region.iter().copied());
+            false
+        });
+
+        landed
+    }
+
+    pub fn draw(&self, renderer: &Renderer, overlay: &mut Overlay) {
+        let system = &renderer.system;
+        let line_height = system.char_size.1 / system.scale_factor;
+
+        for (region, _) in &self.entries {
+            for line in region {
+                let (x, y) = system.pos_to_px(Pos {
+                    row: line.row,
+                    col: 0,
+                });
+                let min_x = x - STRIPE_OFFSET;
+
+                overlay.quad(
+                    (min_x, y, min_x + STRIPE_WIDTH, y + line_height),
+                    STRIPE_COLOR,
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use std::{collections::HashMap, path::PathBuf, time::Instant};
+
+use live_editor_state::{LineData, Pos};
+use live_engine::Runaway;
+use live_language::{lint_parsed, Lint, LintConfig, LintKind, ParsedDocument, Severity};
+
+use crate::{
+    panel::{List, ListHit, PANEL_MARGIN},
+    render::Overlay,
+    sample_packs::Workspace,
+    status_bar::STATUS_BAR_HEIGHT,
+    storage::storage,
+    util::{config_dir, loc_to_pos},
+    widget::WidgetManager,
+};
+
+/// Lives in the config dir, see `LintConfig::parse` for the format
+const LINT_CONFIG_FILE: &str = "lints";
+
+const PANEL_WIDTH: f32 = 420.0;
+const ROW_HEIGHT: f32 = 24.0;
+const MAX_ROWS: usize = 8;
+const FONT_SIZE: f32 = 14.0;
+
+const PANEL_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.05];
+const TEXT_COLOR: [f32; 4] = [0.02, 0.02, 0.02, 1.0];
+const DIM_TEXT_COLOR: [f32; 4] = [0.02, 0.02, 0.02, 0.45];
+const WARNING_COLOR: [f32; 4] = [0.8, 0.45, 0.0, 1.0];
+const ERROR_COLOR: [f32; 4] = [0.8, 0.1, 0.1, 1.0];
+
+pub fn load_lint_config() -> LintConfig {
+    let Some(contents) =
+        config_dir().and_then(|dir| storage().read(&dir.join(LINT_CONFIG_FILE)).ok())
+    else {
+        return LintConfig::default();
+    };
+
+    LintConfig::parse(&contents).unwrap_or_else(|e| {
+        tracing::warn!("Could not read lint config: {}", e);
+        LintConfig::default()
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct Problem {
+    pub lint: Lint,
+    /// where the problem starts in the editor, if it's in the code at all
+    pub pos: Option<Pos>,
+}
+
+/**
+    Structural issues with the session (as opposed to parse errors): the language crate's lints on the code, plus sample files in the workspace's packs that the code doesn't use.
+*/
+#[derive(Debug, Default)]
+pub struct Problems {
+    source: Option<(String, Vec<PathBuf>, Vec<(String, String)>)>,
+    // (kept from keystroke to keystroke, so that it's parsed again incrementally)
+    parsed: Option<ParsedDocument>,
+    pub entries: Vec<Problem>,
+}
+
+impl Problems {
+    pub fn sync(
+        &mut self,
+        linedata: &LineData,
+        widget_manager: &WidgetManager,
+        workspace: &Workspace,
+        runaways: &HashMap<String, Runaway>,
+        config: &LintConfig,
+    ) {
+        let referenced_samples = widget_manager.samples_in(linedata);
+
+        let mut runaways = runaways
+            .iter()
+            .map(|(name, runaway)| (name.clone(), runaway.message(name)))
+            .collect::<Vec<_>>();
+        runaways.sort();
+
+        let source = (linedata.to_string(), referenced_samples, runaways);
+        if self.source.as_ref() == Some(&source) {
+            return;
+        }
+
+        let (code, referenced_samples, runaways) = &source;
+
+        let started_at = Instant::now();
+        let parsed = self
+            .parsed
+            .get_or_insert_with(|| ParsedDocument::new(String::new()));
+        parsed.update(code.clone());
+        self.entries = lint_parsed(parsed, config)
+            .into_iter()
+            .map(|lint| Problem {
+                pos: lint.range.map(|span| loc_to_pos(linedata, span.start)),
+                lint,
+            })
+            .collect();
+        tracing::debug!(
+            target: "language",
+            lines = code.lines().count(),
+            "linted in {:.1}ms",
+            started_at.elapsed().as_secs_f32() * 1000.0
+        );
+
+        let severity = config.severity(LintKind::UnreferencedSample);
+        if severity != Severity::Off {
+            for (name, path) in workspace.pack_files() {
+                if !referenced_samples.contains(&path) {
+                    self.entries.push(Problem {
+                        lint: Lint {
+                            kind: LintKind::UnreferencedSample,
+                            severity,
+                            message: format!("{} isn't used", name),
+                            range: None,
+                        },
+                        pos: None,
+                    });
+                }
+            }
+        }
+
+        let severity = config.severity(LintKind::Runaway);
+        if severity != Severity::Off {
+            let symbols = parsed.outline();
+
+            for (name, message) in runaways {
+                // (the declaration of whatever ran away, if we can find it)
+                let range = symbols
+                    .iter()
+                    .find(|symbol| symbol.name == *name)
+                    .map(|symbol| symbol.name_range);
+
+                self.entries.push(Problem {
+                    pos: range.map(|span| loc_to_pos(linedata, span.start)),
+                    lint: Lint {
+                        kind: LintKind::Runaway,
+                        severity,
+                        message: message.clone(),
+                        range,
+                    },
+                });
+            }
+        }
+
+        self.source = Some(source);
+    }
+}
+
+fn severity_label(severity: Severity) -> (&'static str, [f32; 4]) {
+    match severity {
+        Severity::Error => ("error", ERROR_COLOR),
+        Severity::Warning => ("warning", WARNING_COLOR),
+        _ => ("info", DIM_TEXT_COLOR),
+    }
+}
+
+/**
+    The collapsible problems panel, in the bottom left corner of the window (right above the status bar). Collapsed by default, because lints are just suggestions. Clicking an entry jumps there (if it's somewhere in the code).
+*/
+pub struct ProblemsPanel {
+    pub collapsed: bool,
+}
+
+impl ProblemsPanel {
+    pub fn new() -> Self {
+        Self { collapsed: true }
+    }
+
+    fn list(&self, problems: &Problems, (_, height): (f32, f32)) -> List {
+        List::above(
+            (PANEL_MARGIN, height - STATUS_BAR_HEIGHT - PANEL_MARGIN),
+            PANEL_WIDTH,
+            ROW_HEIGHT,
+            self.collapsed,
+            problems.entries.len().min(MAX_ROWS),
+        )
+    }
+
+    pub fn hit_test(
+        &self,
+        problems: &Problems,
+        window_size: (f32, f32),
+        point: (f32, f32),
+    ) -> Option<ListHit> {
+        self.list(problems, window_size).hit_test(point)
+    }
+
+    pub fn draw(&self, problems: &Problems, window_size: (f32, f32), overlay: &mut Overlay) {
+        let list = self.list(problems, window_size);
+        list.draw(
+            &format!("Problems ({})", problems.entries.len()),
+            "(no problems)",
+            FONT_SIZE,
+            [PANEL_COLOR, TEXT_COLOR, DIM_TEXT_COLOR],
+            overlay,
+        );
+
+        if self.collapsed {
+            return;
+        }
+
+        let (_, _, max_x, _) = list.bounds;
+        for (i, problem) in problems.entries.iter().take(MAX_ROWS).enumerate() {
+            let (x, y) = list.row_text(i, FONT_SIZE);
+
+            let (label, color) = severity_label(problem.lint.severity);
+            overlay.text((x, y), label, FONT_SIZE, color);
+
+            overlay.text((x + 70.0, y), &problem.lint.message, FONT_SIZE, TEXT_COLOR);
+
+            if let Some(pos) = problem.pos {
+                overlay.text(
+                    (max_x - 40.0, y),
+                    format!("{}", pos.row + 1),
+                    FONT_SIZE,
+                    DIM_TEXT_COLOR,
+                );
+            }
+        }
+    }
+}
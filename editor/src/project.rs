@@ -0,0 +1,216 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use live_engine::{clamp_swing, Quantize, DEFAULT_EASE, DEFAULT_TEMPO, STRAIGHT};
+use serde::Deserialize;
+
+/// Marks the root of a project
+pub const PROJECT_FILE: &str = "live.toml";
+/// The session's code, in the root of the project (which is what's edited, unless another code file is opened)
+pub const SESSION_FILE: &str = "session.live";
+
+/// How long hushing takes, unless the project file says otherwise
+const DEFAULT_HUSH: Duration = Duration::from_secs(2);
+/// How many lines are kept in view around the caret, unless the project file says otherwise
+const DEFAULT_SCROLL_MARGIN: i32 = 3;
+/// How many bars a bounce is, unless the project file says otherwise
+const DEFAULT_BOUNCE_BARS: f64 = 8.0;
+/// (and freezing a definition)
+const DEFAULT_FREEZE_BARS: f64 = 4.0;
+
+/**
+    The project file, e.g.
+
+    ```toml
+    # where to look for samples, relative to the project root
+    samples = ["samples", "../shared/drums"]
+    # how long hushing (Cmd+.) takes to fade everything out, in seconds
+    hush = 4.0
+    # in bpm, and whether evaluated code lands right away ("now"), or at the next "bar" or "phrase"
+    tempo = 128
+    quantize = "bar"
+    # how late every second 16th of the patterns comes: .5 is straight, about .66 a triplet shuffle
+    swing = 0.56
+    # how many lines to keep in view above and below the caret
+    scroll_margin = 5
+    # how many bars bouncing (Cmd+Shift+E, or `live render`) renders
+    bounce = 16
+    # how many bars freezing a definition into a sample (Cmd+Shift+U) renders, and whether the code it froze is commented out (or kept, next to the sample)
+    freeze = 8
+    freeze_comments_out = false
+    # how long (in seconds) numbers that are changed in the code glide to their new values, unless they say otherwise with `ease(..)`
+    ease = 0.05
+    # directories (relative to the project root) that searching the project (Cmd+Shift+S) skips
+    exclude = ["old", "scratch"]
+    ```
+*/
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ProjectFile {
+    #[serde(default)]
+    pub samples: Vec<String>,
+    #[serde(default)]
+    pub hush: Option<f32>,
+    #[serde(default)]
+    pub tempo: Option<f64>,
+    #[serde(default)]
+    pub quantize: Option<String>,
+    #[serde(default)]
+    pub swing: Option<f64>,
+    #[serde(default)]
+    pub scroll_margin: Option<i32>,
+    #[serde(default)]
+    pub bounce: Option<f64>,
+    #[serde(default)]
+    pub freeze: Option<f64>,
+    #[serde(default)]
+    pub freeze_comments_out: Option<bool>,
+    #[serde(default)]
+    pub ease: Option<f64>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+impl ProjectFile {
+    pub fn load(root: &Path) -> Self {
+        let Ok(contents) = fs::read_to_string(root.join(PROJECT_FILE)) else {
+            return Self::default();
+        };
+
+        toml::from_str(&contents).unwrap_or_else(|e| {
+            tracing::warn!("Could not read {}: {}", PROJECT_FILE, e);
+            Self::default()
+        })
+    }
+
+    pub fn hush(&self) -> Duration {
+        self.hush
+            .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+            .map_or(DEFAULT_HUSH, Duration::from_secs_f32)
+    }
+
+    pub fn scroll_margin(&self) -> i32 {
+        self.scroll_margin
+            .map_or(DEFAULT_SCROLL_MARGIN, |lines| lines.max(0))
+    }
+
+    pub fn tempo(&self) -> f64 {
+        self.tempo
+            .filter(|bpm| bpm.is_finite() && *bpm > 0.0)
+            .unwrap_or(DEFAULT_TEMPO)
+    }
+
+    pub fn bounce_bars(&self) -> f64 {
+        self.bounce
+            .filter(|bars| bars.is_finite() && *bars > 0.0)
+            .unwrap_or(DEFAULT_BOUNCE_BARS)
+    }
+
+    pub fn freeze_bars(&self) -> f64 {
+        self.freeze
+            .filter(|bars| bars.is_finite() && *bars > 0.0)
+            .unwrap_or(DEFAULT_FREEZE_BARS)
+    }
+
+    pub fn freeze_comments_out(&self) -> bool {
+        self.freeze_comments_out.unwrap_or(true)
+    }
+
+    pub fn ease(&self) -> Duration {
+        self.ease
+            .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+            .map_or(DEFAULT_EASE, Duration::from_secs_f64)
+    }
+
+    pub fn swing(&self) -> f64 {
+        self.swing.map_or(STRAIGHT, clamp_swing)
+    }
+
+    pub fn quantize(&self) -> Quantize {
+        let Some(name) = &self.quantize else {
+            return Quantize::Now;
+        };
+
+        Quantize::from_name(name).unwrap_or_else(|| {
+            tracing::warn!(
+                "Could not read {}: quantize should be \"now\", \"bar\" or \"phrase\", not {:?}",
+                PROJECT_FILE,
+                name
+            );
+            Quantize::Now
+        })
+    }
+}
+
+/**
+    The nearest directory, from `dir` upwards, that has a project file
+*/
+pub fn find_project_root(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .find(|dir| dir.join(PROJECT_FILE).is_file())
+        .map(Path::to_path_buf)
+}
+
+/**
+    How a project refers to sample files, so that it can be moved to another machine: relative to one of the workspace's sample packs (as `<pack name>/<file>`), to one of the project's sample search directories, or to the project root. Only files outside of all of those are referred to by their absolute path.
+*/
+#[derive(Debug, Clone)]
+pub struct SamplePaths {
+    pub root: PathBuf,
+    pub search_dirs: Vec<PathBuf>,
+    /// (name, directory) of every sample pack
+    pub packs: Vec<(String, PathBuf)>,
+}
+
+impl SamplePaths {
+    /**
+        Where a sample path, as written in the project, is on this machine
+    */
+    pub fn resolve(&self, path: &str) -> PathBuf {
+        if Path::new(path).is_absolute() {
+            return PathBuf::from(path);
+        }
+
+        if let Some((name, rest)) = path.split_once('/') {
+            if let Some((_, dir)) = self.packs.iter().find(|(pack, _)| pack == name) {
+                return dir.join(rest);
+            }
+        }
+
+        self.search_dirs
+            .iter()
+            .map(|dir| dir.join(path))
+            .find(|candidate| candidate.exists())
+            .unwrap_or_else(|| self.root.join(path))
+    }
+
+    /**
+        How the project should refer to a sample file: the shortest portable path that resolves back to it
+    */
+    pub fn relative(&self, file: &Path) -> String {
+        let portable = |path: &Path| path.to_string_lossy().replace('\\', "/");
+
+        let in_pack = self.packs.iter().find_map(|(name, dir)| {
+            let rest = file.strip_prefix(dir).ok()?;
+            Some(format!("{}/{}", name, portable(rest)))
+        });
+
+        let in_search_dir = || {
+            self.search_dirs.iter().find_map(|dir| {
+                let rest = portable(file.strip_prefix(dir).ok()?);
+                // (only if it also resolves back to this file, and not to one with the same name in an earlier search directory)
+                (self.resolve(&rest) == file).then_some(rest)
+            })
+        };
+
+        in_pack
+            .or_else(in_search_dir)
+            .or_else(|| {
+                let rest = portable(file.strip_prefix(&self.root).ok()?);
+                (self.resolve(&rest) == file).then_some(rest)
+            })
+            .unwrap_or_else(|| portable(file))
+    }
+}
@@ -0,0 +1,263 @@
+use crate::{
+    render::Overlay,
+    search::{replace, replace_line, search, SearchFile, SearchHit},
+};
+
+/// (of a line of context)
+const MAX_CHARS: usize = 64;
+
+const PANEL_WIDTH: f32 = 680.0;
+const PANEL_TOP: f32 = 64.0;
+const INPUT_HEIGHT: f32 = 30.0;
+const ROW_HEIGHT: f32 = 24.0;
+const MAX_ROWS: usize = 14;
+const FONT_SIZE: f32 = 15.0;
+const FILE_COLUMN: f32 = 180.0;
+
+const BACKDROP_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.08];
+const PANEL_COLOR: [f32; 4] = [0.99, 0.99, 0.98, 1.0];
+const SELECTED_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.08];
+const TEXT_COLOR: [f32; 4] = [0.02, 0.02, 0.02, 1.0];
+const DIM_TEXT_COLOR: [f32; 4] = [0.02, 0.02, 0.02, 0.45];
+const REPLACED_COLOR: [f32; 4] = [0.1, 0.45, 0.15, 1.0];
+
+fn shorten(line: &str) -> String {
+    let line = line.trim();
+    if line.chars().count() <= MAX_CHARS {
+        return line.to_string();
+    }
+
+    line.chars().take(MAX_CHARS).chain(['…']).collect()
+}
+
+/**
+    Cmd+Shift+S: searches all of the project's code files at once, and replaces in all of them, showing every match with its line (and what it'll be, when replacing) first. (The searching and replacing itself is in `search`.)
+*/
+pub struct ProjectSearch {
+    open: bool,
+    query: String,
+    replacement: String,
+    // (whether typing goes into the replacement, Tab switches)
+    replacing: bool,
+    files: Vec<SearchFile>,
+    hits: Vec<SearchHit>,
+    selected: usize,
+}
+
+impl ProjectSearch {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            query: String::new(),
+            replacement: String::new(),
+            replacing: false,
+            files: vec![],
+            hits: vec![],
+            selected: 0,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /**
+        Opens it, to search these files (read once, now, so that typing doesn't go back to the disk every time), keeping the query from last time
+    */
+    pub fn open(&mut self, files: Vec<SearchFile>) {
+        self.open = true;
+        self.files = files;
+        self.replacing = false;
+        self.search();
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+        self.files.clear();
+        self.hits.clear();
+    }
+
+    pub fn type_str(&mut self, s: &str) {
+        if self.replacing {
+            self.replacement.push_str(s);
+        } else {
+            self.query.push_str(s);
+            self.search();
+        }
+    }
+
+    pub fn backspace(&mut self) {
+        if self.replacing {
+            self.replacement.pop();
+        } else {
+            self.query.pop();
+            self.search();
+        }
+    }
+
+    pub fn switch_field(&mut self) {
+        self.replacing = !self.replacing;
+    }
+
+    pub fn move_selection(&mut self, delta: i32) {
+        let n = self.hits.len() as i32;
+        if n > 0 {
+            self.selected = (self.selected as i32 + delta).rem_euclid(n) as usize;
+        }
+    }
+
+    fn search(&mut self) {
+        self.selected = 0;
+        self.hits = search(&self.files, &self.query);
+    }
+
+    pub fn file(&self, hit: &SearchHit) -> &SearchFile {
+        &self.files[hit.file]
+    }
+
+    pub fn selected_hit(&self) -> Option<&SearchHit> {
+        self.hits.get(self.selected)
+    }
+
+    /**
+        Every file that has matches, with what it'll be when they're all replaced (nothing, without a replacement)
+    */
+    pub fn replaced(&self) -> Vec<(&SearchFile, String, usize)> {
+        if self.replacement.is_empty() {
+            return vec![];
+        }
+
+        replace(&self.files, &self.query, &self.replacement)
+    }
+
+    /// (the first row that's shown, so that the selected one is always in view)
+    fn first_row(&self) -> usize {
+        self.selected.saturating_sub(MAX_ROWS - 1)
+    }
+
+    fn bounds(&self, (width, _): (f32, f32)) -> (f32, f32, f32, f32) {
+        let rows = self.hits.len().clamp(1, MAX_ROWS);
+        let min_x = ((width - PANEL_WIDTH) / 2.0).max(0.0);
+
+        (
+            min_x,
+            PANEL_TOP,
+            min_x + PANEL_WIDTH,
+            PANEL_TOP + 3.0 * INPUT_HEIGHT + rows as f32 * ROW_HEIGHT + 6.0,
+        )
+    }
+
+    /**
+        Which match was clicked, if any. (`None` if the click was outside of the panel.)
+    */
+    pub fn hit_test(&self, window_size: (f32, f32), (x, y): (f32, f32)) -> Option<Option<usize>> {
+        let (min_x, min_y, max_x, max_y) = self.bounds(window_size);
+        if x < min_x || x > max_x || y < min_y || y > max_y {
+            return None;
+        }
+
+        let i = ((y - min_y - 3.0 * INPUT_HEIGHT) / ROW_HEIGHT).floor();
+        if i < 0.0 {
+            return Some(None);
+        }
+
+        let i = self.first_row() + i as usize;
+        Some((i < self.hits.len()).then_some(i))
+    }
+
+    pub fn select(&mut self, i: usize) {
+        self.selected = i.min(self.hits.len().saturating_sub(1));
+    }
+
+    pub fn draw(&self, window_size: (f32, f32), overlay: &mut Overlay) {
+        let (min_x, min_y, max_x, max_y) = self.bounds(window_size);
+        let text_y = |top: f32, height: f32| top + (height - FONT_SIZE) / 2.0;
+
+        overlay.quad((0.0, 0.0, window_size.0, window_size.1), BACKDROP_COLOR);
+        overlay.quad((min_x, min_y, max_x, max_y), PANEL_COLOR);
+
+        let field = |top: f32, label: &str, text: &str, active: bool, overlay: &mut Overlay| {
+            if active {
+                overlay.quad((min_x, top, max_x, top + INPUT_HEIGHT), SELECTED_COLOR);
+            }
+            overlay.text(
+                (min_x + 12.0, text_y(top, INPUT_HEIGHT)),
+                label,
+                FONT_SIZE,
+                DIM_TEXT_COLOR,
+            );
+            overlay.text(
+                (min_x + 92.0, text_y(top, INPUT_HEIGHT)),
+                text,
+                FONT_SIZE,
+                TEXT_COLOR,
+            );
+        };
+
+        field(min_y, "search", &self.query, !self.replacing, overlay);
+        field(
+            min_y + INPUT_HEIGHT,
+            "replace",
+            &self.replacement,
+            self.replacing,
+            overlay,
+        );
+
+        let files = {
+            let mut files = self.hits.iter().map(|hit| hit.file).collect::<Vec<_>>();
+            files.dedup();
+            files.len()
+        };
+
+        let summary = match (self.hits.len(), self.replacement.is_empty()) {
+            (0, _) if self.query.is_empty() => "type to search all code files".to_string(),
+            (0, _) => "no matches".to_string(),
+            (n, true) => format!("{} matches in {} files, enter to open", n, files),
+            (n, false) => format!("{} matches in {} files, cmd+enter to replace all", n, files),
+        };
+
+        overlay.text(
+            (
+                min_x + 12.0,
+                text_y(min_y + 2.0 * INPUT_HEIGHT, INPUT_HEIGHT),
+            ),
+            summary,
+            FONT_SIZE,
+            DIM_TEXT_COLOR,
+        );
+
+        let first = self.first_row();
+        for (row, hit) in self.hits.iter().enumerate().skip(first).take(MAX_ROWS) {
+            let top = min_y + 3.0 * INPUT_HEIGHT + (row - first) as f32 * ROW_HEIGHT;
+            let y = text_y(top, ROW_HEIGHT);
+
+            if row == self.selected {
+                overlay.quad((min_x, top, max_x, top + ROW_HEIGHT), SELECTED_COLOR);
+            }
+
+            overlay.text(
+                (min_x + 12.0, y),
+                format!("{}:{}", self.files[hit.file].name, hit.row + 1),
+                FONT_SIZE,
+                DIM_TEXT_COLOR,
+            );
+
+            if self.replacement.is_empty() {
+                overlay.text(
+                    (min_x + FILE_COLUMN, y),
+                    shorten(&hit.line),
+                    FONT_SIZE,
+                    TEXT_COLOR,
+                );
+            } else {
+                // (a preview of what it'll be)
+                overlay.text(
+                    (min_x + FILE_COLUMN, y),
+                    shorten(&replace_line(&hit.line, &self.query, &self.replacement)),
+                    FONT_SIZE,
+                    REPLACED_COLOR,
+                );
+            }
+        }
+    }
+}
@@ -1,26 +1,59 @@
-use live_editor_state::{EditorState, Pos};
+use live_editor_state::{EditorState, Pos, Token};
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    fs,
+    hash::{Hash, Hasher},
+    path::Path,
+};
 use wgpu_text::{
     glyph_brush::{
-        ab_glyph::FontRef, FontId, HorizontalAlign, Layout, OwnedText, Section, Text, VerticalAlign,
+        ab_glyph::{FontArc, FontRef},
+        FontId, HorizontalAlign, Layout, OwnedSection, OwnedText, Section, Text, VerticalAlign,
     },
     BrushBuilder, TextBrush,
 };
 
-use crate::highlight::{syntax_highlight, CodeToken};
+use crate::{
+    font::FontSettings,
+    highlight::{highlight_line, CodeToken},
+};
 
 use super::system::SystemData;
 
 const CODE_COLOR: [f32; 4] = [0.02, 0.02, 0.02, 1.];
 const KW_COLOR: [f32; 4] = [0.02, 0.02, 0.02, 1.];
+const INLAY_HINT_COLOR: [f32; 4] = [0.02, 0.02, 0.02, 0.35];
+
+// (the code font, unless the font settings say otherwise)
+const FIRA_CODE_RETINA: &[u8] = include_bytes!("../../res/fonts/FiraCode-Retina.ttf");
+const FIRA_CODE_BOLD: &[u8] = include_bytes!("../../res/fonts/FiraCode-Bold.ttf");
+
+/**
+    A highlighted line, ready to be drawn (except for its position)
+*/
+struct ShapedLine {
+    section: OwnedSection,
+    /// (col, width, id)
+    widgets: Vec<(usize, usize, usize)>,
+}
 
 pub struct CodePass<'a> {
+    /// (the size of a line, which is taller than the glyphs when there's extra line height)
     char_size: (f32, f32),
+    /// (how far down the glyphs are within a line, so the extra line height is split above and below)
+    text_offset: f32,
     regular_font_id: FontId,
     bold_font_id: FontId,
     code_font_size: f32,
 
     title_brush: TextBrush<FontRef<'a>>,
-    code_brush: TextBrush<FontRef<'a>>,
+    code_brush: TextBrush<FontArc>,
+
+    surface_height: f32,
+    /// per row, the hash of the line that's there, see `line_hash`
+    row_hashes: Vec<u64>,
+    shaped_lines: HashMap<u64, ShapedLine>,
+    inlay_hints_revision: u64,
 }
 
 impl<'a> CodePass<'a> {
@@ -28,6 +61,8 @@ impl<'a> CodePass<'a> {
         device: &wgpu::Device,
         _queue: &wgpu::Queue,
         config: &wgpu::SurfaceConfiguration,
+        font: &FontSettings,
+        scale_factor: f32,
     ) -> Self {
         let roboto_slab: &[u8] = include_bytes!("../../res/fonts/RobotoSlab-Bold.ttf");
 
@@ -38,34 +73,33 @@ impl<'a> CodePass<'a> {
             config.format,
         );
 
-        let fira_code_bold_font =
-            FontRef::try_from_slice(include_bytes!("../../res/fonts/FiraCode-Bold.ttf")).unwrap();
-
-        let fira_code_retina_font =
-            FontRef::try_from_slice(include_bytes!("../../res/fonts/FiraCode-Retina.ttf")).unwrap();
+        let code_font_size = font.size * scale_factor;
 
-        let code_font_size = 50.0;
-
-        let mut code_brush =
-            BrushBuilder::using_fonts(vec![fira_code_retina_font.clone(), fira_code_bold_font])
-                .build(&device, config.width, config.height, config.format);
+        let mut code_brush = BrushBuilder::using_fonts(load_fonts(font)).build(
+            &device,
+            config.width,
+            config.height,
+            config.format,
+        );
 
         let regular_font_id = FontId(0);
         let bold_font_id = FontId(1);
 
-        let tmp_section = Section::default().add_text(Text::new("x").with_scale(code_font_size));
-
-        let x_bounds = code_brush.glyph_bounds(tmp_section).unwrap();
-
-        let char_size = (x_bounds.width(), x_bounds.height());
+        let (char_size, text_offset) = measure(&mut code_brush, code_font_size, font.line_height);
 
         Self {
             char_size,
+            text_offset,
             regular_font_id,
             bold_font_id,
             code_font_size,
             title_brush,
             code_brush,
+
+            surface_height: config.height as f32,
+            row_hashes: vec![],
+            shaped_lines: HashMap::new(),
+            inlay_hints_revision: 0,
         }
     }
 
@@ -73,7 +107,35 @@ impl<'a> CodePass<'a> {
         self.char_size
     }
 
+    /**
+        Switches to another font (or size, or line height), re-shaping all lines
+    */
+    pub fn set_font(
+        &mut self,
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        font: &FontSettings,
+        scale_factor: f32,
+    ) {
+        self.code_font_size = font.size * scale_factor;
+
+        self.code_brush = BrushBuilder::using_fonts(load_fonts(font)).build(
+            &device,
+            config.width,
+            config.height,
+            config.format,
+        );
+
+        (self.char_size, self.text_offset) =
+            measure(&mut self.code_brush, self.code_font_size, font.line_height);
+
+        // (the row hashes don't depend on the font, but the shapes do)
+        self.shaped_lines.clear();
+    }
+
     pub fn resize(&mut self, queue: &wgpu::Queue, config: &wgpu::SurfaceConfiguration) {
+        self.surface_height = config.height as f32;
+
         self.title_brush
             .resize_view(config.width as f32, config.height as f32, &queue);
 
@@ -81,42 +143,59 @@ impl<'a> CodePass<'a> {
             .resize_view(config.width as f32, config.height as f32, &queue);
     }
 
-    pub fn draw<'pass>(
-        &'pass mut self,
-        device: &wgpu::Device,
-        queue: &wgpu::Queue,
-        system: &SystemData,
-        editor_state: &EditorState,
-        render_pass: &mut wgpu::RenderPass<'pass>,
-    ) -> Vec<(usize, (f32, f32, f32, f32))> {
-        let sf = system.scale_factor;
+    /**
+        Brings the per-row hashes up to date with the edits since the last frame, and shapes the lines we haven't seen before. Unchanged lines (even if they moved) are never re-shaped.
+    */
+    fn sync(&mut self, system: &SystemData, editor_state: &EditorState) {
+        let lines = editor_state.linedata().lines();
+        let dirty = editor_state.dirty_lines();
 
-        let mut widget_instances = vec![];
+        if system.inlay_hints.revision() != self.inlay_hints_revision {
+            self.inlay_hints_revision = system.inlay_hints.revision();
+            self.row_hashes.clear();
+        }
 
-        let title_section = Section::default()
-            .add_text(
-                Text::new("Some title here")
-                    .with_scale(100.0)
-                    .with_color([0.01, 0.01, 0.01, 1.0]),
-            )
-            .with_layout(
-                Layout::default()
-                    .v_align(VerticalAlign::Top)
-                    .h_align(HorizontalAlign::Left),
-            )
-            // .with_bounds((config.width as f32 - 200.0, config.height as f32))
-            .with_screen_position((100.0, 100.0))
-            .to_owned();
+        if let Some(from) = dirty.shifted_from {
+            self.row_hashes.truncate(from.max(0) as usize);
+        }
+        self.row_hashes.truncate(lines.len());
+
+        for &row in &dirty.rows {
+            if row >= 0 && (row as usize) < self.row_hashes.len() {
+                self.row_hashes[row as usize] = line_hash(&lines[row as usize], system, row);
+            }
+        }
+
+        for row in self.row_hashes.len()..lines.len() {
+            self.row_hashes
+                .push(line_hash(&lines[row], system, row as i32));
+        }
+
+        for (row, &hash) in self.row_hashes.iter().enumerate() {
+            if !self.shaped_lines.contains_key(&hash) {
+                let shaped = self.shape_line(&lines[row], system, row as i32);
+                self.shaped_lines.insert(hash, shaped);
+            }
+        }
+
+        // don't hold on to the shapes of lines that were edited away forever
+        if self.shaped_lines.len() > 2 * self.row_hashes.len() + 64 {
+            let in_use = self.row_hashes.iter().collect::<HashSet<_>>();
+            self.shaped_lines.retain(|hash, _| in_use.contains(hash));
+        }
+    }
 
-        let mut code_section = Section::default()
+    fn shape_line(&self, line: &[Token], system: &SystemData, row: i32) -> ShapedLine {
+        let mut section = Section::default()
             .with_layout(
-                Layout::default()
+                Layout::default_single_line()
                     .v_align(VerticalAlign::Top)
                     .h_align(HorizontalAlign::Left),
             )
-            .with_screen_position((100.0, 260.0))
             .to_owned();
 
+        let mut widgets = vec![];
+
         let mk_widget_space = |width: usize| {
             OwnedText::new((0..width).map(|_| ' ').collect::<String>())
                 .with_font_id(self.bold_font_id)
@@ -138,38 +217,141 @@ impl<'a> CodePass<'a> {
                 .with_color(CODE_COLOR)
         };
 
-        for (row, line) in syntax_highlight(editor_state.linedata()) {
-            for token in line {
-                match token {
-                    CodeToken::Keyword { text, .. } => code_section.text.push(mk_keyword(text)),
-                    CodeToken::Text { text, .. } => code_section.text.push(mk_regular(text)),
-                    CodeToken::Widget { col, width, id } => {
-                        code_section.text.push(mk_widget_space(width));
-
-                        let (x_start, y) = system.pos_to_px(Pos {
-                            row: row as i32,
-                            col: col as i32,
-                        });
-
-                        let (x_end, _) = system.pos_to_px(Pos {
-                            row: row as i32,
-                            col: (col + width) as i32,
-                        });
-
-                        widget_instances.push((
-                            id,
-                            (
-                                x_start,
-                                y + 4.0 / sf,
-                                x_end,
-                                y + system.char_size.1 / sf - 4.0 / sf,
-                            ),
-                        ));
+        // same font & scale as the code, otherwise the visual columns don't line up anymore
+        let mk_inlay_hint = |text: String| {
+            OwnedText::new(text)
+                .with_font_id(self.regular_font_id)
+                .with_scale(self.code_font_size)
+                .with_color(INLAY_HINT_COLOR)
+        };
+
+        let mut hints = system.inlay_hints.on_row(row).peekable();
+        let mut at = 0;
+
+        for token in highlight_line(line) {
+            let (text, is_keyword) = match token {
+                CodeToken::Keyword { text, .. } => (text, true),
+                CodeToken::Text { text, .. } => (text, false),
+                CodeToken::Widget { col, width, id } => {
+                    while let Some(hint) = hints.next_if(|hint| hint.pos.col <= at) {
+                        section.text.push(mk_inlay_hint(hint.text.clone()));
                     }
+
+                    at += width as i32;
+                    section.text.push(mk_widget_space(width));
+                    widgets.push((col, width, id));
+
+                    continue;
+                }
+            };
+
+            let mk = |text: String| {
+                if is_keyword {
+                    mk_keyword(text)
+                } else {
+                    mk_regular(text)
                 }
+            };
+
+            // split the token wherever an inlay hint needs to go in between
+            let mut chunk = String::new();
+            for ch in text.chars() {
+                if hints.peek().map_or(false, |hint| hint.pos.col <= at) {
+                    if chunk.len() > 0 {
+                        section.text.push(mk(std::mem::take(&mut chunk)));
+                    }
+
+                    while let Some(hint) = hints.next_if(|hint| hint.pos.col <= at) {
+                        section.text.push(mk_inlay_hint(hint.text.clone()));
+                    }
+                }
+
+                chunk.push(ch);
+                at += 1;
+            }
+
+            if chunk.len() > 0 {
+                section.text.push(mk(chunk));
             }
+        }
 
-            code_section.text.push(mk_regular("\n".into()));
+        // hints at (or beyond) the end of the line
+        for hint in hints {
+            section.text.push(mk_inlay_hint(hint.text.clone()));
+        }
+
+        ShapedLine { section, widgets }
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "code_pass", skip_all))]
+    pub fn draw<'pass>(
+        &'pass mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        system: &SystemData,
+        editor_state: &EditorState,
+        render_pass: &mut wgpu::RenderPass<'pass>,
+    ) -> Vec<(usize, (f32, f32, f32, f32))> {
+        let sf = system.scale_factor;
+
+        self.sync(system, editor_state);
+
+        let mut widget_instances = vec![];
+
+        let title_section = Section::default()
+            .add_text(
+                Text::new("Some title here")
+                    .with_scale(100.0)
+                    .with_color([0.01, 0.01, 0.01, 1.0]),
+            )
+            .with_layout(
+                Layout::default()
+                    .v_align(VerticalAlign::Top)
+                    .h_align(HorizontalAlign::Left),
+            )
+            // .with_bounds((config.width as f32 - 200.0, config.height as f32))
+            .with_screen_position((system.code_left(), 100.0 - system.scroll()))
+            .to_owned();
+
+        // one section per visible line, which glyph_brush caches individually: lines that didn't change (or only moved) aren't laid out again, and if nothing changed at all, the glyph vertex buffer isn't even re-uploaded
+        let mut code_sections = vec![];
+
+        for (row, hash) in self.row_hashes.iter().enumerate() {
+            let y = system.line_top(row as i32);
+            if y + system.char_size.1 < 0.0 || y > self.surface_height {
+                continue;
+            }
+
+            let shaped = &self.shaped_lines[hash];
+
+            for &(col, width, id) in &shaped.widgets {
+                let (x_start, y) = system.pos_to_px(Pos {
+                    row: row as i32,
+                    col: col as i32,
+                });
+
+                let (x_end, _) = system.pos_to_px(Pos {
+                    row: row as i32,
+                    col: (col + width) as i32,
+                });
+
+                widget_instances.push((
+                    id,
+                    (
+                        x_start,
+                        y + 4.0 / sf,
+                        x_end,
+                        y + system.char_size.1 / sf - 4.0 / sf,
+                    ),
+                ));
+            }
+
+            code_sections.push(
+                shaped
+                    .section
+                    .to_borrowed()
+                    .with_screen_position((system.code_left(), y + self.text_offset)),
+            );
         }
 
         self.title_brush
@@ -177,7 +359,7 @@ impl<'a> CodePass<'a> {
             .unwrap();
 
         self.code_brush
-            .queue(&device, &queue, vec![&code_section])
+            .queue(&device, &queue, code_sections)
             .unwrap();
 
         self.title_brush.draw(render_pass);
@@ -186,3 +368,58 @@ impl<'a> CodePass<'a> {
         widget_instances
     }
 }
+
+/**
+    The code font (and the bold one, for keywords), from the files that the settings point to, or else the built-in Fira Code
+*/
+fn load_fonts(settings: &FontSettings) -> Vec<FontArc> {
+    let read = |path: &Path| match fs::read(path) {
+        Ok(bytes) => FontArc::try_from_vec(bytes)
+            .map_err(|e| tracing::warn!("Could not load font {}: {}", path.display(), e))
+            .ok(),
+        Err(e) => {
+            tracing::warn!("Could not read font {}: {}", path.display(), e);
+            None
+        }
+    };
+
+    let regular = settings.family.as_deref().and_then(read);
+    let bold = settings
+        .bold
+        .as_deref()
+        .and_then(read)
+        .or_else(|| regular.clone());
+
+    vec![
+        regular.unwrap_or_else(|| FontArc::try_from_slice(FIRA_CODE_RETINA).unwrap()),
+        bold.unwrap_or_else(|| FontArc::try_from_slice(FIRA_CODE_BOLD).unwrap()),
+    ]
+}
+
+/**
+    How big a character is (monospace, so any will do), at a font size, with the extra line height, and how far down the glyphs go in that
+*/
+fn measure(brush: &mut TextBrush<FontArc>, font_size: f32, line_height: f32) -> ((f32, f32), f32) {
+    let section = Section::default().add_text(Text::new("x").with_scale(font_size));
+    let x_bounds = brush.glyph_bounds(section).unwrap();
+
+    let height = x_bounds.height() * line_height;
+    let char_size = (x_bounds.width(), height);
+
+    (char_size, (height - x_bounds.height()) / 2.0)
+}
+
+/**
+    Identifies a line's rendering: its tokens, plus the inlay hints on it (but not its row, so moved lines don't need re-shaping)
+*/
+fn line_hash(line: &[Token], system: &SystemData, row: i32) -> u64 {
+    let mut hasher = DefaultHasher::new();
+
+    line.hash(&mut hasher);
+    for hint in system.inlay_hints.on_row(row) {
+        hint.pos.col.hash(&mut hasher);
+        hint.text.hash(&mut hasher);
+    }
+
+    hasher.finish()
+}
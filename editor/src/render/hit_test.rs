@@ -0,0 +1,66 @@
+use live_editor_state::Pos;
+
+use crate::status_bar::STATUS_BAR_HEIGHT;
+
+use super::Renderer;
+
+/**
+    What's under a (logical) mouse position, of what the renderer draws: see `Renderer::hit_test`. (The overlay's panels and popups do their own hit testing, they know where they drew themselves.)
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Hit {
+    StatusBar,
+    /// A widget in the code (or expanded over it), where it was drawn last frame (what it's sent is made relative to that with `WidgetEvent::child_relative`)
+    Widget {
+        id: usize,
+        bounds: (f32, f32, f32, f32),
+    },
+    /// Left of the code in a pane, next to a line
+    Gutter {
+        pane: usize,
+        row: i32,
+    },
+    /// On the code in a pane, or where there'd be code (past the end of a line, or of the document)
+    Code {
+        pane: usize,
+        pos: Pos,
+    },
+}
+
+impl Renderer<'_> {
+    /**
+        What's under the mouse: the status bar is on top of everything, then the expanded widget, then the widgets, and then it's the code (or the gutter next to it) of whichever pane it's in, as that's scrolled
+    */
+    pub fn hit_test(&self, (x, y): (f32, f32)) -> Hit {
+        let (_, height) = self.logical_size();
+        if y >= height - STATUS_BAR_HEIGHT {
+            return Hit::StatusBar;
+        }
+
+        if let Some(&(id, bounds)) = self
+            .expanded_widget
+            .iter()
+            .chain(&self.widget_instances)
+            .find(|&&(_, (min_x, min_y, max_x, max_y))| {
+                min_x <= x && x <= max_x && min_y <= y && y <= max_y
+            })
+        {
+            return Hit::Widget { id, bounds };
+        }
+
+        match self.system.code_pos_at((x, y)) {
+            (pane, pos) if pos.col < 0 => Hit::Gutter { pane, row: pos.row },
+            (pane, pos) => Hit::Code { pane, pos },
+        }
+    }
+
+    /**
+        The widget under the mouse, if any (see `hit_test`), with its bounds
+    */
+    pub fn widget_at(&self, mouse: (f32, f32)) -> Option<(usize, (f32, f32, f32, f32))> {
+        match self.hit_test(mouse) {
+            Hit::Widget { id, bounds, .. } => Some((id, bounds)),
+            _ => None,
+        }
+    }
+}
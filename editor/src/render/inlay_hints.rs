@@ -0,0 +1,83 @@
+use live_editor_state::Pos;
+
+/**
+    A bit of "phantom" text that's rendered inside the code (e.g. the inferred type after `let x`), but isn't part of the document. It takes up visual columns, but not logical ones, so carets and edits never see it.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct InlayHint {
+    pub pos: Pos,
+    pub text: String,
+}
+
+impl InlayHint {
+    pub fn width(&self) -> i32 {
+        self.text.chars().count() as i32
+    }
+}
+
+/**
+    The current set of inlay hints, kept sorted by position. These are meant to be thrown away and re-populated wholesale after each parse, rather than be kept up to date with edits.
+*/
+#[derive(Debug, Default)]
+pub struct InlayHints {
+    hints: Vec<InlayHint>,
+    revision: u64,
+}
+
+impl InlayHints {
+    pub fn set(&mut self, mut hints: Vec<InlayHint>) {
+        hints.sort_by_key(|hint| hint.pos);
+        self.hints = hints;
+        self.revision += 1;
+    }
+
+    pub fn clear(&mut self) {
+        self.hints.clear();
+        self.revision += 1;
+    }
+
+    /**
+        Changes whenever the hints do, so that whatever is derived from them knows when to update
+    */
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    pub fn on_row(&self, row: i32) -> impl Iterator<Item = &InlayHint> {
+        self.hints.iter().filter(move |hint| hint.pos.row == row)
+    }
+
+    /**
+        A hint at column `col` is rendered right before the character at `col`, so a caret at `col` is drawn before the hint, and only positions strictly after it are shifted.
+    */
+    pub fn visual_col(&self, pos: Pos) -> i32 {
+        pos.col
+            + self
+                .on_row(pos.row)
+                .take_while(|hint| hint.pos.col < pos.col)
+                .map(|hint| hint.width())
+                .sum::<i32>()
+    }
+
+    /**
+        The inverse of `visual_col`, snapping positions inside a hint to the logical position of that hint.
+    */
+    pub fn logical_col(&self, row: i32, visual_col: i32) -> i32 {
+        let mut shift = 0;
+
+        for hint in self.on_row(row) {
+            let hint_start = hint.pos.col + shift;
+            if visual_col <= hint_start {
+                break;
+            }
+
+            if visual_col < hint_start + hint.width() {
+                return hint.pos.col;
+            }
+
+            shift += hint.width();
+        }
+
+        visual_col - shift
+    }
+}
@@ -1,22 +1,32 @@
 mod buffer;
 mod code_pass;
+mod hit_test;
+mod inlay_hints;
+mod levels_pass;
+mod overlay_pass;
 mod pass;
 mod selections_pass;
 mod system;
 mod widget_vertex;
 mod widgets_pass;
 
+pub use hit_test::Hit;
+pub use inlay_hints::InlayHint;
+pub use overlay_pass::Overlay;
 pub use widgets_pass::WidgetTexture;
 
-use crate::widget::WidgetManager;
+use crate::{font::FontSettings, widget::WidgetManager};
 
 use self::{
-    code_pass::CodePass, selections_pass::SelectionsPass, system::SystemData,
-    widgets_pass::WidgetsPass,
+    code_pass::CodePass, levels_pass::LevelsPass, overlay_pass::OverlayPass,
+    selections_pass::SelectionsPass, system::SystemData, widgets_pass::WidgetsPass,
 };
-use live_editor_state::EditorState;
+use live_editor_state::{EditorState, LineSelection};
 use winit::dpi::PhysicalSize;
 
+// (between an expanded widget and the line it's on)
+const EXPANDED_WIDGET_GAP: f32 = 6.0;
+
 const BACKGROUND_COLOR: wgpu::Color = wgpu::Color {
     r: 243.0 / 255.0,
     g: 242.0 / 255.0,
@@ -32,15 +42,22 @@ pub struct Renderer<'a> {
 
     pub system: SystemData,
 
+    levels_pass: LevelsPass,
     code_pass: CodePass<'a>,
     widgets_pass: WidgetsPass,
     selections_pass: SelectionsPass,
+    // (the expanded widget has its own, because it's drawn at another size than where it is in the code)
+    expanded_widget_pass: WidgetsPass,
+    overlay_pass: OverlayPass<'a>,
 
     widget_instances: Vec<(usize, (f32, f32, f32, f32))>,
+    expanded_widget: Option<(usize, (f32, f32, f32, f32))>,
+
+    font: FontSettings,
 }
 
 impl<'a> Renderer<'a> {
-    pub async fn new(window: &winit::window::Window) -> Renderer<'a> {
+    pub async fn new(window: &winit::window::Window, font: FontSettings) -> Renderer<'a> {
         let scale_factor = window.scale_factor() as f32;
 
         let backends = wgpu::util::backend_bits_from_env().unwrap_or_else(wgpu::Backends::all);
@@ -80,7 +97,7 @@ impl<'a> Renderer<'a> {
 
         surface.configure(&device, &config);
 
-        let code_pass = CodePass::new(&device, &queue, &config);
+        let code_pass = CodePass::new(&device, &queue, &config, &font, scale_factor);
         let system = SystemData::new(
             scale_factor,
             code_pass.char_size(),
@@ -88,8 +105,11 @@ impl<'a> Renderer<'a> {
             &queue,
             &config,
         );
+        let levels_pass = LevelsPass::new(&device, &queue, &config, &system);
         let widgets_pass = WidgetsPass::new(&device, &queue, &config, &system);
         let selections_pass = SelectionsPass::new(&device, &queue, &config, &system);
+        let expanded_widget_pass = WidgetsPass::new(&device, &queue, &config, &system);
+        let overlay_pass = OverlayPass::new(&device, &queue, &config, &system);
 
         Self {
             device,
@@ -98,15 +118,35 @@ impl<'a> Renderer<'a> {
             config,
 
             system,
+            levels_pass,
             widgets_pass,
             code_pass,
             selections_pass,
+            expanded_widget_pass,
+            overlay_pass,
 
             // immediate mode UI state glue..
             widget_instances: vec![],
+            expanded_widget: None,
+
+            font,
         }
     }
 
+    pub fn font(&self) -> &FontSettings {
+        &self.font
+    }
+
+    /**
+        Switches the code to another font (or size, or line height) right away, re-laying out everything that depends on the size of a character
+    */
+    pub fn set_font(&mut self, font: FontSettings) {
+        self.code_pass
+            .set_font(&self.device, &self.config, &font, self.system.scale_factor);
+        self.system.set_char_size(self.code_pass.char_size());
+        self.font = font;
+    }
+
     #[allow(unused)]
     pub fn width(&self) -> f32 {
         self.config.width as f32
@@ -117,30 +157,90 @@ impl<'a> Renderer<'a> {
         self.config.height as f32
     }
 
+    /**
+        The window size in logical pixels, which is what all UI (mouse events, overlays) works in
+    */
+    pub fn logical_size(&self) -> (f32, f32) {
+        (
+            self.config.width as f32 / self.system.scale_factor,
+            self.config.height as f32 / self.system.scale_factor,
+        )
+    }
+
+    /**
+        Replaces all inlay hints, e.g. after re-parsing the document
+    */
+    #[allow(unused)]
+    pub fn set_inlay_hints(&mut self, hints: Vec<InlayHint>) {
+        self.system.inlay_hints.set(hints);
+    }
+
+    #[allow(unused)]
+    pub fn clear_inlay_hints(&mut self) {
+        self.system.inlay_hints.clear();
+    }
+
     pub fn resize(&mut self, size: PhysicalSize<u32>) {
         self.config.width = size.width.max(1);
         self.config.height = size.height.max(1);
 
         self.surface.configure(&self.device, &self.config);
         self.system.resize(&self.queue, &self.config);
+        self.levels_pass.resize(&self.queue, &self.config);
         self.code_pass.resize(&self.queue, &self.config);
         self.selections_pass.resize(&self.queue, &self.config);
+        self.overlay_pass.resize(&self.queue, &self.config);
     }
 
-    pub fn widget_at(&self, (x, y): (f32, f32)) -> Option<(usize, (f32, f32, f32, f32))> {
+    /**
+        Where a widget was drawn last frame (in both panes, when they both show it, and over the code, when it's expanded)
+    */
+    pub fn widget_bounds(&self, id: usize) -> impl Iterator<Item = (f32, f32, f32, f32)> + '_ {
         self.widget_instances
             .iter()
-            .find(|&&(_, (min_x, min_y, max_x, max_y))| {
-                min_x <= x && x <= max_x && min_y <= y && y <= max_y
-            })
-            .map(|t| *t)
+            .chain(&self.expanded_widget)
+            .filter(move |&&(instance, _)| instance == id)
+            .map(|&(_, bounds)| bounds)
     }
 
-    pub fn draw(&mut self, editor_state: &EditorState, widget_manager: &mut WidgetManager) {
-        let mut encoder = self
-            .device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    /**
+        Where the expanded widget (see `WidgetManager::expanded`) was drawn last frame, if it was
+    */
+    pub fn expanded_widget(&self) -> Option<(usize, (f32, f32, f32, f32))> {
+        self.expanded_widget
+    }
+
+    /**
+        Right above where the widget is in the code (or below it, if there's no room above), as far as it fits in the window
+    */
+    fn expanded_widget_bounds(
+        &self,
+        (id, (width, height)): (usize, (f32, f32)),
+    ) -> Option<(f32, f32, f32, f32)> {
+        let &(_, (min_x, min_y, _, max_y)) = self
+            .widget_instances
+            .iter()
+            .find(|&&(instance, _)| instance == id)?;
+        let (window_width, _) = self.logical_size();
+
+        let x = min_x.min(window_width - width).max(0.0);
+        let y = if min_y - EXPANDED_WIDGET_GAP - height >= 0.0 {
+            min_y - EXPANDED_WIDGET_GAP - height
+        } else {
+            max_y + EXPANDED_WIDGET_GAP
+        };
 
+        Some((x, y, x + width, y + height))
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "frame", skip_all))]
+    pub fn draw(
+        &mut self,
+        editor_state: &EditorState,
+        widget_manager: &mut WidgetManager,
+        levels: &[(LineSelection, f32)],
+        overlay: &Overlay,
+    ) {
         let frame = self
             .surface
             .get_current_texture()
@@ -148,43 +248,122 @@ impl<'a> Renderer<'a> {
 
         let view = frame.texture.create_view(&Default::default());
 
+        // each pane (when the editor is split) is drawn separately, clipped to its part of the window, and submitted right away, because the passes write their buffers anew for every pane
+        let mut widget_instances = vec![];
+
+        for pane in 0..self.system.pane_count() {
+            self.system.set_current_pane(pane);
+
+            let mut encoder = self
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+            {
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Background render pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: &view,
+                        resolve_target: None,
+
+                        ops: wgpu::Operations {
+                            load: if pane == 0 {
+                                wgpu::LoadOp::Clear(BACKGROUND_COLOR)
+                            } else {
+                                wgpu::LoadOp::Load
+                            },
+                            store: true,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                });
+
+                let (x, y, width, height) = self.system.pane_rect();
+                render_pass.set_scissor_rect(x, y, width, height);
+
+                self.levels_pass.draw(
+                    &self.device,
+                    &self.queue,
+                    &self.system,
+                    levels,
+                    &mut render_pass,
+                );
+
+                let pane_widget_instances = self.code_pass.draw(
+                    &self.device,
+                    &self.queue,
+                    &self.system,
+                    editor_state,
+                    &mut render_pass,
+                );
+
+                self.widgets_pass.draw(
+                    &self.device,
+                    &self.queue,
+                    &self.system,
+                    &pane_widget_instances,
+                    widget_manager,
+                    &mut render_pass,
+                );
+
+                self.selections_pass.draw(
+                    &self.device,
+                    &self.queue,
+                    &self.system,
+                    editor_state,
+                    &mut render_pass,
+                );
+
+                widget_instances.extend(pane_widget_instances);
+            }
+
+            self.queue.submit([encoder.finish()]);
+        }
+
+        self.system.set_current_pane(self.system.focused);
+        self.widget_instances = widget_instances;
+
+        // (only when the widget's in view, because it's anchored to it)
+        self.expanded_widget = widget_manager.expanded().and_then(|expanded| {
+            let bounds = self.expanded_widget_bounds(expanded)?;
+            Some((expanded.0, bounds))
+        });
+
+        // (the overlay goes over the whole window)
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
         {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Background render pass"),
+                label: Some("Overlay render pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
                     view: &view,
                     resolve_target: None,
 
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(BACKGROUND_COLOR),
+                        load: wgpu::LoadOp::Load,
                         store: true,
                     },
                 })],
                 depth_stencil_attachment: None,
             });
 
-            self.widget_instances = self.code_pass.draw(
-                &self.device,
-                &self.queue,
-                &self.system,
-                editor_state,
-                &mut render_pass,
-            );
-
-            self.widgets_pass.draw(
-                &self.device,
-                &self.queue,
-                &self.system,
-                &self.widget_instances,
-                widget_manager,
-                &mut render_pass,
-            );
-
-            self.selections_pass.draw(
+            if let Some(expanded) = self.expanded_widget {
+                self.expanded_widget_pass.draw(
+                    &self.device,
+                    &self.queue,
+                    &self.system,
+                    &[expanded],
+                    widget_manager,
+                    &mut render_pass,
+                );
+            }
+
+            self.overlay_pass.draw(
                 &self.device,
                 &self.queue,
                 &self.system,
-                editor_state,
+                overlay,
                 &mut render_pass,
             );
         }
@@ -0,0 +1,239 @@
+use wgpu_text::{
+    glyph_brush::{
+        ab_glyph::FontRef, FontId, HorizontalAlign, Layout, OwnedSection, OwnedText, Section,
+        VerticalAlign,
+    },
+    BrushBuilder, TextBrush,
+};
+
+use super::{
+    buffer::{QuadBufferBuilder, Vertex},
+    system::SystemData,
+};
+
+const MAX_QUADS: u64 = 400;
+
+#[derive(Debug, Clone)]
+pub struct OverlayText {
+    pub pos: (f32, f32),
+    pub text: String,
+    pub size: f32,
+    pub color: [f32; 4],
+    pub bold: bool,
+}
+
+/**
+    A flat description of the UI that's drawn on top of the code (side panels, pickers, etc.), in logical pixels. It's rebuilt every frame, immediate mode style, by whoever owns the UI state.
+
+    Note that all quads are drawn before all text, so overlapping bits of UI don't layer nicely (yet).
+*/
+#[derive(Debug, Default)]
+pub struct Overlay {
+    pub quads: Vec<((f32, f32, f32, f32), [f32; 4])>,
+    pub texts: Vec<OverlayText>,
+}
+
+impl Overlay {
+    pub fn quad(&mut self, bounds: (f32, f32, f32, f32), color: [f32; 4]) {
+        self.quads.push((bounds, color));
+    }
+
+    pub fn text(&mut self, pos: (f32, f32), text: impl Into<String>, size: f32, color: [f32; 4]) {
+        self.texts.push(OverlayText {
+            pos,
+            text: text.into(),
+            size,
+            color,
+            bold: false,
+        });
+    }
+
+    pub fn bold_text(
+        &mut self,
+        pos: (f32, f32),
+        text: impl Into<String>,
+        size: f32,
+        color: [f32; 4],
+    ) {
+        self.texts.push(OverlayText {
+            pos,
+            text: text.into(),
+            size,
+            color,
+            bold: true,
+        });
+    }
+}
+
+pub struct OverlayPass<'a> {
+    render_pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+
+    regular_font_id: FontId,
+    bold_font_id: FontId,
+    text_brush: TextBrush<FontRef<'a>>,
+}
+
+impl<'a> OverlayPass<'a> {
+    pub fn new(
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+        config: &wgpu::SurfaceConfiguration,
+        system: &SystemData,
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Overlay shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../../res/shader.wgsl").into()),
+        });
+
+        let render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Overlay render pipeline layout"),
+                bind_group_layouts: &[&system.bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Overlay render pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[Vertex::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: config.format,
+                    write_mask: wgpu::ColorWrites::ALL,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Overlay vertex buffer"),
+            size: Vertex::SIZE * 4 * MAX_QUADS,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let index_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Overlay index buffer"),
+            size: std::mem::size_of::<u32>() as u64 * 6 * MAX_QUADS,
+            usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let fira_code_bold_font =
+            FontRef::try_from_slice(include_bytes!("../../res/fonts/FiraCode-Bold.ttf")).unwrap();
+
+        let fira_code_retina_font =
+            FontRef::try_from_slice(include_bytes!("../../res/fonts/FiraCode-Retina.ttf")).unwrap();
+
+        let text_brush = BrushBuilder::using_fonts(vec![fira_code_retina_font, fira_code_bold_font])
+            .build(&device, config.width, config.height, config.format);
+
+        Self {
+            render_pipeline,
+            vertex_buffer,
+            index_buffer,
+
+            regular_font_id: FontId(0),
+            bold_font_id: FontId(1),
+            text_brush,
+        }
+    }
+
+    pub fn resize(&mut self, queue: &wgpu::Queue, config: &wgpu::SurfaceConfiguration) {
+        self.text_brush
+            .resize_view(config.width as f32, config.height as f32, &queue);
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "overlay_pass", skip_all))]
+    pub fn draw<'pass>(
+        &'pass mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        system: &'pass SystemData,
+        overlay: &Overlay,
+        render_pass: &mut wgpu::RenderPass<'pass>,
+    ) {
+        let sf = system.scale_factor;
+
+        let mut builder = QuadBufferBuilder::new();
+
+        for &((min_x, min_y, max_x, max_y), color) in
+            overlay.quads.iter().take(MAX_QUADS as usize)
+        {
+            builder.push_quad(min_x, min_y, max_x, max_y, color);
+        }
+
+        queue.write_buffer(
+            &self.vertex_buffer,
+            0,
+            bytemuck::cast_slice(&builder.vertex_data),
+        );
+        queue.write_buffer(
+            &self.index_buffer,
+            0,
+            bytemuck::cast_slice(&builder.index_data),
+        );
+
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, &system.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(0..builder.num_indices(), 0, 0..1);
+
+        // the text brush works in physical pixels
+        let sections = overlay
+            .texts
+            .iter()
+            .map(|text| {
+                Section::default()
+                    .with_layout(
+                        Layout::default_single_line()
+                            .v_align(VerticalAlign::Top)
+                            .h_align(HorizontalAlign::Left),
+                    )
+                    .with_screen_position((text.pos.0 * sf, text.pos.1 * sf))
+                    .to_owned()
+                    .add_text(
+                        OwnedText::new(text.text.clone())
+                            .with_font_id(if text.bold {
+                                self.bold_font_id
+                            } else {
+                                self.regular_font_id
+                            })
+                            .with_scale(text.size * sf)
+                            .with_color(text.color),
+                    )
+            })
+            .collect::<Vec<OwnedSection>>();
+
+        self.text_brush
+            .queue(&device, &queue, sections.iter().collect::<Vec<_>>())
+            .unwrap();
+
+        self.text_brush.draw(render_pass);
+    }
+}
@@ -5,6 +5,14 @@ use super::{
     system::SystemData,
 };
 
+/// Everyone else's carets and selections (when editing together) get their own color, by site id
+const REMOTE_COLORS: [[f32; 3]; 4] = [
+    [0.85, 0.2, 0.45],
+    [0.1, 0.45, 0.85],
+    [0.1, 0.6, 0.3],
+    [0.85, 0.5, 0.0],
+];
+
 pub struct SelectionsPass {
     render_pipeline: wgpu::RenderPipeline,
     vertex_buffer: wgpu::Buffer,
@@ -93,6 +101,7 @@ impl SelectionsPass {
 
     pub fn resize(&mut self, _queue: &wgpu::Queue, _config: &wgpu::SurfaceConfiguration) {}
 
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "selections_pass", skip_all))]
     pub fn draw<'pass>(
         &'pass mut self,
         _device: &wgpu::Device,
@@ -101,43 +110,26 @@ impl SelectionsPass {
         editor_state: &EditorState,
         render_pass: &mut wgpu::RenderPass<'pass>,
     ) {
-        let sf = system.scale_factor;
-
         let mut builder = QuadBufferBuilder::new();
 
-        for LineSelection {
-            row,
-            col_start,
-            col_end,
-        } in editor_state.visual_selections()
-        {
-            let (x_start, y) = system.pos_to_px(Pos {
-                row,
-                col: col_start,
-            });
-
-            let (x_end, _) = system.pos_to_px(Pos { row, col: col_end });
-
-            builder.push_quad(
-                x_start,
-                y,
-                x_end + 6.0 / sf,
-                y + system.char_size.1 / sf,
-                [0.0, 0.0, 0.0, 0.2],
+        for remote in editor_state.remote_selections() {
+            push_selections(
+                &mut builder,
+                system,
+                remote.visual,
+                remote.carets,
+                REMOTE_COLORS[remote.site as usize % REMOTE_COLORS.len()],
             );
         }
 
-        for caret in editor_state.caret_positions() {
-            let (cx, cy) = system.pos_to_px(caret);
-
-            builder.push_quad(
-                cx,
-                cy,
-                cx + 6.0 / sf,
-                cy + system.char_size.1 / sf,
-                [0.0, 0.0, 0.0, 1.0],
-            );
-        }
+        // (ours go on top)
+        push_selections(
+            &mut builder,
+            system,
+            editor_state.visual_selections(),
+            editor_state.caret_positions(),
+            [0.0, 0.0, 0.0],
+        );
 
         let vertex_data_raw: &[u8] = bytemuck::cast_slice(&builder.vertex_data);
         queue.write_buffer(&self.vertex_buffer, 0, vertex_data_raw);
@@ -154,3 +146,47 @@ impl SelectionsPass {
         render_pass.draw_indexed(0..num_indices, 0, 0..1); // 2.
     }
 }
+
+fn push_selections(
+    builder: &mut QuadBufferBuilder,
+    system: &SystemData,
+    visual: Vec<LineSelection>,
+    carets: Vec<Pos>,
+    [r, g, b]: [f32; 3],
+) {
+    let sf = system.scale_factor;
+
+    for LineSelection {
+        row,
+        col_start,
+        col_end,
+    } in visual
+    {
+        let (x_start, y) = system.pos_to_px(Pos {
+            row,
+            col: col_start,
+        });
+
+        let (x_end, _) = system.pos_to_px(Pos { row, col: col_end });
+
+        builder.push_quad(
+            x_start,
+            y,
+            x_end + 6.0 / sf,
+            y + system.char_size.1 / sf,
+            [r, g, b, 0.2],
+        );
+    }
+
+    for caret in carets {
+        let (cx, cy) = system.pos_to_px(caret);
+
+        builder.push_quad(
+            cx,
+            cy,
+            cx + 6.0 / sf,
+            cy + system.char_size.1 / sf,
+            [r, g, b, 1.0],
+        );
+    }
+}
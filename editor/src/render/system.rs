@@ -1,13 +1,59 @@
+use std::time::Instant;
+
 use cgmath::SquareMatrix;
 use live_editor_state::Pos;
 use wgpu::util::DeviceExt;
 
+use super::inlay_hints::InlayHints;
+
+/// How quickly (in seconds) an animated scroll eases towards where it's going: after this long, it's gone about two thirds of the way
+const SCROLL_EASING: f32 = 0.06;
+/// (physical pixels) close enough to just be there
+const SCROLL_SNAP: f32 = 0.5;
+/// (physical pixels) where the code starts, from the left of its pane
+const CODE_LEFT: f32 = 100.0;
+
+/**
+    A view on the document: the whole window, or one half of it when the editor is split. Panes all show the same document, but each is scrolled on its own.
+*/
+struct Pane {
+    /// (physical pixels)
+    left: f32,
+    width: f32,
+
+    scroll: f32,
+    scroll_target: f32,
+    // (when the scroll animation last moved, to know how far it moves next)
+    scrolled_at: Option<Instant>,
+}
+
+impl Pane {
+    fn new(left: f32, width: f32) -> Self {
+        Self {
+            left,
+            width,
+            scroll: 0.0,
+            scroll_target: 0.0,
+            scrolled_at: None,
+        }
+    }
+}
+
 /**
    System global stuff, like the projection matrix and coordinate stuff
 */
 pub struct SystemData {
     pub scale_factor: f32,
     pub char_size: (f32, f32),
+    pub inlay_hints: InlayHints,
+
+    panes: Vec<Pane>,
+    /// The pane with the keyboard focus, which follows the caret
+    pub focused: usize,
+    // (the pane that the coordinate stuff is about: the focused one, except while a pane is being drawn)
+    current: usize,
+    viewport_width: f32,
+    viewport_height: f32,
 
     pub system_uniform: SystemUniform,
     pub bind_group_layout: wgpu::BindGroupLayout,
@@ -77,6 +123,13 @@ impl SystemData {
         Self {
             scale_factor,
             char_size,
+            inlay_hints: InlayHints::default(),
+
+            panes: vec![Pane::new(0.0, config.width as f32)],
+            focused: 0,
+            current: 0,
+            viewport_width: config.width as f32,
+            viewport_height: config.height as f32,
 
             system_uniform,
             bind_group_layout,
@@ -85,18 +138,160 @@ impl SystemData {
         }
     }
 
+    fn pane(&self) -> &Pane {
+        &self.panes[self.current]
+    }
+
+    fn pane_mut(&mut self) -> &mut Pane {
+        &mut self.panes[self.current]
+    }
+
+    pub fn is_split(&self) -> bool {
+        self.panes.len() > 1
+    }
+
+    pub fn pane_count(&self) -> usize {
+        self.panes.len()
+    }
+
+    /**
+        Splits the editor into two panes side by side (the new one starts out scrolled like the focused one), or un-splits it again, keeping the focused pane
+    */
+    pub fn toggle_split(&mut self) {
+        if self.is_split() {
+            let kept = self.panes.swap_remove(self.focused);
+            self.panes = vec![kept];
+        } else {
+            let scroll = self.pane().scroll;
+            let mut pane = Pane::new(0.0, 0.0);
+            pane.scroll = scroll;
+            pane.scroll_target = scroll;
+            self.panes.push(pane);
+        }
+
+        self.focused = self.focused.min(self.panes.len() - 1);
+        self.current = self.focused;
+        self.layout();
+    }
+
+    fn layout(&mut self) {
+        let width = (self.viewport_width / self.panes.len() as f32).floor();
+
+        for (i, pane) in self.panes.iter_mut().enumerate() {
+            pane.left = width * i as f32;
+            pane.width = width;
+        }
+
+        // (the last one takes the rounding)
+        if let Some(last) = self.panes.last_mut() {
+            last.width = self.viewport_width - last.left;
+        }
+    }
+
+    /**
+        Which pane the (logical) mouse position is over
+    */
+    pub fn pane_at(&self, (x, _): (f32, f32)) -> usize {
+        let x = x * self.scale_factor;
+        self.panes
+            .iter()
+            .rposition(|pane| pane.left <= x)
+            .unwrap_or(0)
+    }
+
+    pub fn focus_pane(&mut self, pane: usize) {
+        self.focused = pane.min(self.panes.len() - 1);
+        self.current = self.focused;
+    }
+
+    /**
+        Does something (like scrolling, or mapping the mouse to a position) in another pane than the focused one
+    */
+    pub fn in_pane<R>(&mut self, pane: usize, f: impl FnOnce(&mut Self) -> R) -> R {
+        self.current = pane.min(self.panes.len() - 1);
+        let result = f(self);
+        self.current = self.focused;
+        result
+    }
+
+    /**
+        (While drawing the panes one by one, see `Renderer::draw`)
+    */
+    pub(super) fn set_current_pane(&mut self, pane: usize) {
+        self.current = pane;
+    }
+
+    /**
+        The pane that's being drawn, as a scissor rect (in physical pixels)
+    */
+    pub(super) fn pane_rect(&self) -> (u32, u32, u32, u32) {
+        let pane = self.pane();
+        (
+            pane.left as u32,
+            0,
+            (pane.width as u32).max(1),
+            (self.viewport_height as u32).max(1),
+        )
+    }
+
+    /**
+        The (logical) x coordinates of the borders between the panes, if split
+    */
+    pub fn pane_dividers(&self) -> Vec<f32> {
+        self.panes[1..]
+            .iter()
+            .map(|pane| pane.left / self.scale_factor)
+            .collect()
+    }
+
+    /**
+        How far the code is scrolled down (in physical pixels)
+    */
+    pub fn scroll(&self) -> f32 {
+        self.pane().scroll
+    }
+
+    /**
+        Where the code starts, horizontally, in physical pixels
+    */
+    pub fn code_left(&self) -> f32 {
+        self.pane().left + CODE_LEFT
+    }
+
+    /**
+        Where a line starts, vertically, in physical pixels (so scrolled)
+    */
+    pub fn line_top(&self, row: i32) -> f32 {
+        260.0 + self.char_size.1 * (row as f32) - self.scroll()
+    }
+
     pub fn pos_to_px(&self, pos: Pos) -> (f32, f32) {
         let sf = self.scale_factor;
-        let x = (100.0 + self.char_size.0 * (pos.col as f32)) / sf;
-        let y = (260.0 + self.char_size.1 * (pos.row as f32)) / sf;
+        let col = self.inlay_hints.visual_col(pos);
+        let x = (self.code_left() + self.char_size.0 * (col as f32)) / sf;
+        let y = self.line_top(pos.row) / sf;
         (x, y)
     }
 
-    pub fn px_to_pos(&self, (x, y): (f32, f32)) -> Pos {
+    pub fn px_to_pos(&self, mouse: (f32, f32)) -> Pos {
+        self.px_to_pos_in(self.pane(), mouse)
+    }
+
+    /**
+        The pane under the (logical) mouse position, and where that is in the code, as scrolled in that pane (which isn't necessarily the focused one). The column is negative left of the code, in the gutter.
+    */
+    pub fn code_pos_at(&self, mouse: (f32, f32)) -> (usize, Pos) {
+        let pane = self.pane_at(mouse);
+        (pane, self.px_to_pos_in(&self.panes[pane], mouse))
+    }
+
+    fn px_to_pos_in(&self, pane: &Pane, (x, y): (f32, f32)) -> Pos {
         let sf = self.scale_factor;
+        let row = ((y * sf + pane.scroll - 260.0) / self.char_size.1).floor() as i32;
+        let visual_col = ((x * sf - pane.left - CODE_LEFT) / self.char_size.0).round() as i32;
         Pos {
-            row: ((y * sf - 260.0) / self.char_size.1).floor() as i32,
-            col: ((x * sf - 100.0) / self.char_size.0).round() as i32,
+            row,
+            col: self.inlay_hints.logical_col(row, visual_col),
         }
     }
 
@@ -108,7 +303,107 @@ impl SystemData {
     //     }
     // }
 
+    /**
+        How far down it can scroll: until the last line (of `rows`), with `margin` lines below it, is at the bottom
+    */
+    fn max_scroll(&self, rows: i32, margin: i32) -> f32 {
+        let bottom = 260.0 + self.char_size.1 * (rows + margin) as f32;
+        (bottom - self.viewport_height).max(0.0)
+    }
+
+    /**
+        Scrolls by a number of logical pixels (down is positive). Mouse wheels scroll smoothly, while trackpads (and the momentum the OS adds after a flick) already move pixel by pixel, so they're followed as they are.
+    */
+    pub fn scroll_by(&mut self, px: f32, smooth: bool, rows: i32, margin: i32) {
+        let max = self.max_scroll(rows, margin);
+        let sf = self.scale_factor;
+        let pane = self.pane_mut();
+        pane.scroll_target = (pane.scroll_target + px * sf).clamp(0.0, max);
+
+        if !smooth {
+            pane.scroll = pane.scroll_target;
+            pane.scrolled_at = None;
+        }
+    }
+
+    /**
+        Stops the scroll animation where it is, like when a trackpad gesture starts
+    */
+    pub fn stop_scrolling(&mut self) {
+        let pane = self.pane_mut();
+        pane.scroll_target = pane.scroll;
+        pane.scrolled_at = None;
+    }
+
+    /**
+        Scrolls (smoothly) just enough that the row is in view, with `margin` lines of context above and below it (if the window's tall enough)
+    */
+    pub fn scroll_into_view(&mut self, row: i32, rows: i32, margin: i32) {
+        let line_height = self.char_size.1;
+        let row_top = 260.0 + line_height * row as f32;
+
+        let highest = row_top + line_height * (margin + 1) as f32 - self.viewport_height;
+        let lowest = row_top - line_height * margin as f32;
+
+        let max = self.max_scroll(rows, margin);
+        let pane = self.pane_mut();
+        let target = pane.scroll_target.max(highest).min(lowest);
+        pane.scroll_target = target.clamp(0.0, max);
+    }
+
+    /**
+        Changes the size of a character (when the font changes, or zooms), keeping the same line at the top of every pane
+    */
+    pub fn set_char_size(&mut self, char_size: (f32, f32)) {
+        let ratio = char_size.1 / self.char_size.1;
+        let rescale = |scroll: f32| (260.0 + (scroll - 260.0) * ratio).max(0.0);
+
+        for pane in &mut self.panes {
+            pane.scroll = rescale(pane.scroll);
+            pane.scroll_target = rescale(pane.scroll_target);
+        }
+
+        self.char_size = char_size;
+    }
+
+    pub fn is_scrolling(&self) -> bool {
+        self.panes
+            .iter()
+            .any(|pane| pane.scroll != pane.scroll_target)
+    }
+
+    /**
+        Moves the scroll (of every pane) towards where it's going (easing out exponentially, so it doesn't matter how often this is called), and returns whether it has to keep going
+    */
+    pub fn animate_scroll(&mut self) -> bool {
+        let now = Instant::now();
+
+        for pane in &mut self.panes {
+            if pane.scroll == pane.scroll_target {
+                pane.scrolled_at = None;
+                continue;
+            }
+
+            let dt = pane
+                .scrolled_at
+                .map_or(1.0 / 60.0, |at| now.duration_since(at).as_secs_f32());
+            pane.scrolled_at = Some(now);
+
+            let distance = pane.scroll_target - pane.scroll;
+            pane.scroll += distance * (1.0 - (-dt / SCROLL_EASING).exp());
+
+            if (pane.scroll_target - pane.scroll).abs() < SCROLL_SNAP {
+                pane.scroll = pane.scroll_target;
+            }
+        }
+
+        self.is_scrolling()
+    }
+
     pub fn resize(&mut self, queue: &wgpu::Queue, config: &wgpu::SurfaceConfiguration) {
+        self.viewport_width = config.width as f32;
+        self.viewport_height = config.height as f32;
+        self.layout();
         self.system_uniform.update(
             self.scale_factor,
             (config.width as f32, config.height as f32),
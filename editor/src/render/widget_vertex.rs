@@ -3,6 +3,8 @@
 pub struct WidgetVertex {
     position: [f32; 3],
     tex_coords: [f32; 2],
+    // where the widget is in the atlas, so that sampling doesn't bleed into its neighbours
+    tex_bounds: [f32; 4],
 }
 
 unsafe impl bytemuck::Pod for WidgetVertex {}
@@ -11,9 +13,10 @@ unsafe impl bytemuck::Zeroable for WidgetVertex {}
 impl WidgetVertex {
     pub const SIZE: wgpu::BufferAddress = std::mem::size_of::<Self>() as wgpu::BufferAddress;
 
-    const ATTRIBS: [wgpu::VertexAttribute; 2] = wgpu::vertex_attr_array![
+    const ATTRIBS: [wgpu::VertexAttribute; 3] = wgpu::vertex_attr_array![
         0 => Float32x3,
         1 => Float32x2,
+        2 => Float32x4,
     ];
 
     pub fn desc() -> wgpu::VertexBufferLayout<'static> {
@@ -42,23 +45,36 @@ impl WidgetQuadBufferBuilder {
         }
     }
 
-    pub fn push_quad(&mut self, (min_x, min_y, max_x, max_y): (f32, f32, f32, f32)) {
+    /**
+        A quad on the screen, showing the part of the atlas within `tex_bounds` (as (min_u, min_v, max_u, max_v))
+    */
+    pub fn push_quad(
+        &mut self,
+        (min_x, min_y, max_x, max_y): (f32, f32, f32, f32),
+        tex_bounds: [f32; 4],
+    ) {
+        let [min_u, min_v, max_u, max_v] = tex_bounds;
+
         self.vertex_data.extend(&[
             WidgetVertex {
                 position: [min_x, min_y, 0.0],
-                tex_coords: [0.0, 0.0],
+                tex_coords: [min_u, min_v],
+                tex_bounds,
             },
             WidgetVertex {
                 position: [max_x, min_y, 0.0],
-                tex_coords: [1.0, 0.0],
+                tex_coords: [max_u, min_v],
+                tex_bounds,
             },
             WidgetVertex {
                 position: [max_x, max_y, 0.0],
-                tex_coords: [1.0, 1.0],
+                tex_coords: [max_u, max_v],
+                tex_bounds,
             },
             WidgetVertex {
                 position: [min_x, max_y, 0.0],
-                tex_coords: [0.0, 1.0],
+                tex_coords: [min_u, max_v],
+                tex_bounds,
             },
         ]);
         self.index_data.extend(&[
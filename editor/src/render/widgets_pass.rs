@@ -7,10 +7,24 @@ use super::{
     widget_vertex::{WidgetQuadBufferBuilder, WidgetVertex},
 };
 
+// (in physical pixels, and square)
+const ATLAS_SIZE: u32 = 2048;
+
+/**
+    Draws the widgets, whose pixels all live in one texture (the "atlas"), so that they're drawn with one bind group and one draw call, and only the pixels that changed since the last frame are uploaded
+*/
 pub struct WidgetsPass {
     render_pipeline: wgpu::RenderPipeline,
-    texture_bind_group_layout: wgpu::BindGroupLayout,
+    atlas: wgpu::Texture,
+    bind_group: wgpu::BindGroup,
+    // where each widget is in the atlas, and what it drew last
     widget_textures: HashMap<usize, WidgetTexture>,
+    packer: ShelfPacker,
+
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    // (how many quads the buffers fit, they grow when there are more widgets in view)
+    quad_capacity: usize,
 }
 
 impl WidgetsPass {
@@ -97,13 +111,65 @@ impl WidgetsPass {
             multiview: None, // 5.
         });
 
+        let atlas = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Widgets atlas texture"),
+            size: wgpu::Extent3d {
+                width: ATLAS_SIZE,
+                height: ATLAS_SIZE,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        let atlas_view = atlas.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &texture_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&atlas_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+            label: Some("Widgets atlas bind group"),
+        });
+
+        let quad_capacity = 64;
+        let (vertex_buffer, index_buffer) = create_buffers(device, quad_capacity);
+
         Self {
             render_pipeline,
-            texture_bind_group_layout,
+            atlas,
+            bind_group,
             widget_textures: HashMap::new(),
+            packer: ShelfPacker::default(),
+
+            vertex_buffer,
+            index_buffer,
+            quad_capacity,
         }
     }
 
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "widgets_pass", skip_all))]
     pub fn draw<'pass>(
         &'pass mut self,
         device: &wgpu::Device,
@@ -114,221 +180,226 @@ impl WidgetsPass {
         widget_manager: &mut WidgetManager,
         render_pass: &mut wgpu::RenderPass<'pass>,
     ) {
-        let groups = widget_instances
-            .group_by(|a, b| a.0 == b.0)
-            .map(|group| {
-                let (id, quad) = group[0];
-                let (min_x, min_y, max_x, max_y) = quad;
-
-                // physical (multiplied by 2, hacky for now)
-                let width = (max_x - min_x).round() as usize * 2;
-                let height = (max_y - min_y).round() as usize * 2;
-
-                self.widget_textures.entry(id).or_insert_with(|| {
-                    WidgetTexture::new(
-                        id,
-                        width,
-                        height,
-                        device,
-                        queue,
-                        &self.texture_bind_group_layout,
-                    )
-                });
-
-                (id, widget_instances.iter().map(|i| i.1).collect::<Vec<_>>())
-            })
-            .collect::<Vec<_>>();
-
-        for (id, quads) in groups {
-            let widget_texture = self.widget_textures.get_mut(&id).unwrap();
+        let mut sizes: Vec<(usize, (usize, usize))> = vec![];
+        for &(id, (min_x, min_y, max_x, max_y)) in widget_instances {
+            // physical (multiplied by 2, hacky for now)
+            let width = (max_x - min_x).round() as usize * 2;
+            let height = (max_y - min_y).round() as usize * 2;
+
+            if !sizes.iter().any(|&(other, _)| other == id) {
+                sizes.push((id, (width, height)));
+            }
+        }
 
-            widget_manager.draw(id, widget_texture);
+        // making room for all of them first, so that if the atlas is full and has to be packed again (with just the widgets in view), that happens before anything's drawn in it
+        if !sizes.iter().all(|&(id, size)| self.allocate(id, size)) {
+            self.widget_textures.clear();
+            self.packer = ShelfPacker::default();
 
-            queue.write_texture(
-                // Tells wgpu where to copy the pixel data
-                wgpu::ImageCopyTexture {
-                    texture: &widget_texture.texture,
-                    mip_level: 0,
-                    origin: wgpu::Origin3d::ZERO,
-                    aspect: wgpu::TextureAspect::All,
-                },
-                // The actual pixel data
-                &widget_texture.frame(),
-                // The layout of the texture
-                wgpu::ImageDataLayout {
-                    offset: 0,
-                    bytes_per_row: Some(widget_texture.size.width * 4),
-                    rows_per_image: Some(widget_texture.size.height),
-                },
-                widget_texture.size,
-            );
+            // (whatever doesn't fit even then isn't drawn)
+            sizes.retain(|&(id, size)| self.allocate(id, size));
+        }
 
-            let mut widgets_builder = WidgetQuadBufferBuilder::new();
+        for &(id, _) in &sizes {
+            let widget_texture = self.widget_textures.get_mut(&id).unwrap();
+            widget_manager.draw(id, widget_texture);
+            widget_texture.upload(queue, &self.atlas);
+        }
 
-            for quad in quads {
-                widgets_builder.push_quad(quad);
+        let mut widgets_builder = WidgetQuadBufferBuilder::new();
+
+        for &(id, quad) in widget_instances {
+            if let Some(widget_texture) = self.widget_textures.get(&id) {
+                widgets_builder.push_quad(quad, widget_texture.atlas_bounds());
             }
+        }
+
+        let quads = widgets_builder.num_indices() as usize / 6;
+        if quads > self.quad_capacity {
+            self.quad_capacity = quads.next_power_of_two();
+            (self.vertex_buffer, self.index_buffer) = create_buffers(device, self.quad_capacity);
+        }
 
-            let vertex_data_raw: &[u8] = bytemuck::cast_slice(&widgets_builder.vertex_data);
-            queue.write_buffer(&widget_texture.vertex_buffer, 0, vertex_data_raw);
+        let vertex_data_raw: &[u8] = bytemuck::cast_slice(&widgets_builder.vertex_data);
+        queue.write_buffer(&self.vertex_buffer, 0, vertex_data_raw);
 
-            let index_data_raw: &[u8] = bytemuck::cast_slice(&widgets_builder.index_data);
-            queue.write_buffer(&widget_texture.index_buffer, 0, index_data_raw);
+        let index_data_raw: &[u8] = bytemuck::cast_slice(&widgets_builder.index_data);
+        queue.write_buffer(&self.index_buffer, 0, index_data_raw);
 
-            widget_texture.num_indices = widgets_builder.num_indices();
+        let num_indices = widgets_builder.num_indices();
+        if num_indices > 0 {
+            render_pass.set_pipeline(&self.render_pipeline);
+            render_pass.set_bind_group(0, &system.bind_group, &[]);
+            render_pass.set_bind_group(1, &self.bind_group, &[]);
+            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            render_pass.draw_indexed(0..num_indices, 0, 0..1);
         }
+    }
 
-        for (_, widget_texture) in &self.widget_textures {
-            if widget_texture.num_indices > 0 {
-                render_pass.set_pipeline(&self.render_pipeline);
-                render_pass.set_bind_group(0, &system.bind_group, &[]);
-                render_pass.set_bind_group(1, &widget_texture.bind_group, &[]);
-                render_pass.set_vertex_buffer(0, widget_texture.vertex_buffer.slice(..));
-                render_pass.set_index_buffer(
-                    widget_texture.index_buffer.slice(..),
-                    wgpu::IndexFormat::Uint32,
-                );
-                render_pass.draw_indexed(0..widget_texture.num_indices, 0, 0..1);
-            }
+    /**
+        Makes sure the widget has a place in the atlas, of the right size (a resized widget gets a new one, and its old one is only reused when the atlas is packed again), returning whether there was room
+    */
+    fn allocate(&mut self, id: usize, (width, height): (usize, usize)) -> bool {
+        if self
+            .widget_textures
+            .get(&id)
+            .is_some_and(|t| (t.width, t.height) == (width, height))
+        {
+            return true;
         }
+
+        let Some(origin) = self.packer.allocate(width as u32, height as u32) else {
+            return false;
+        };
+
+        self.widget_textures
+            .insert(id, WidgetTexture::new(origin, width, height));
+
+        true
     }
 }
 
-pub struct WidgetTexture {
-    texture: wgpu::Texture,
-    size: wgpu::Extent3d,
-    // texture_view: wgpu::TextureView,
-    // sampler: wgpu::Sampler,
-    bind_group: wgpu::BindGroup,
-    pixels: Vec<u8>,
+fn create_buffers(device: &wgpu::Device, quads: usize) -> (wgpu::Buffer, wgpu::Buffer) {
+    let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Widgets vertex buffer"),
+        size: WidgetVertex::SIZE * 4 * quads as u64,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    let index_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Widgets index buffer"),
+        size: std::mem::size_of::<u32>() as u64 * 6 * quads as u64,
+        usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    (vertex_buffer, index_buffer)
+}
 
-    vertex_buffer: wgpu::Buffer,
-    index_buffer: wgpu::Buffer,
-    num_indices: u32,
+/**
+    Packs rectangles into the atlas in rows ("shelves"), left to right, starting a new row below when one doesn't fit anymore. Nothing's ever freed, it's just started over when it's full (see `WidgetsPass::draw`).
+*/
+#[derive(Default)]
+struct ShelfPacker {
+    x: u32,
+    y: u32,
+    row_height: u32,
 }
 
-impl WidgetTexture {
-    pub fn new(
-        id: usize,
-        width: usize,
-        height: usize,
-        device: &wgpu::Device,
-        _queue: &wgpu::Queue,
-        bind_group_layout: &wgpu::BindGroupLayout,
-    ) -> Self {
-        // let diffuse_bytes = include_bytes!("../../res/example_waveform.png");
-        // let diffuse_image = image::load_from_memory(diffuse_bytes).unwrap();
-        // let diffuse_rgba = diffuse_image.to_rgba8();
-        // let dimensions = diffuse_image.dimensions();
-
-        let size = wgpu::Extent3d {
-            width: width as u32,
-            height: height as u32,
-            depth_or_array_layers: 1,
-        };
+impl ShelfPacker {
+    fn allocate(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        if width > ATLAS_SIZE {
+            return None;
+        }
 
-        let texture = device.create_texture(&wgpu::TextureDescriptor {
-            label: Some(&format!("Widget #{id} pixel texture")),
-            size,
-            mip_level_count: 1, // We'll talk about this a little later
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8UnormSrgb,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-            view_formats: &[],
-        });
+        if self.x + width > ATLAS_SIZE {
+            self.x = 0;
+            self.y += self.row_height;
+            self.row_height = 0;
+        }
 
-        // buffer size
-        // See [https://github.com/parasyte/pixels/blob/main/src/builder.rs]
-        // 32-bit formats, 8 bits per component
-        let texture_format_size = 4;
-        let pixels_buffer_size = (width * height * texture_format_size) as usize;
+        if self.y + height > ATLAS_SIZE {
+            return None;
+        }
 
-        let mut pixels = Vec::with_capacity(pixels_buffer_size);
-        pixels.resize_with(pixels_buffer_size, Default::default);
+        let origin = (self.x, self.y);
+        self.x += width;
+        self.row_height = self.row_height.max(height);
 
-        // We don't need to configure the texture view much, so let's
-        // let wgpu define it.
-        let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        Some(origin)
+    }
+}
 
-        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Nearest,
-            mipmap_filter: wgpu::FilterMode::Nearest,
-            ..Default::default()
-        });
+/**
+    What a widget draws itself on, which is uploaded to its place in the atlas (just the part that changed since the last time, if anything did)
+*/
+pub struct WidgetTexture {
+    // where it is in the atlas
+    origin: (u32, u32),
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+    // what changed since it was last uploaded, as (min_x, min_y, max_x, max_y), exclusive
+    dirty: Option<(usize, usize, usize, usize)>,
+}
 
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&texture_view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&sampler),
-                },
-            ],
-            label: Some("Texture bind group"),
-        });
+impl WidgetTexture {
+    fn new(origin: (u32, u32), width: usize, height: usize) -> Self {
+        // 32-bit formats, 8 bits per component
+        let texture_format_size = 4;
 
-        let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Widgets vertex buffer"),
-            size: WidgetVertex::SIZE * 400,
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
+        Self {
+            origin,
+            width,
+            height,
+            pixels: vec![0; width * height * texture_format_size],
+            // (its place in the atlas has whatever was there before)
+            dirty: Some((0, 0, width, height)),
+        }
+    }
 
-        let index_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Widgets index buffer"),
-            size: WidgetVertex::SIZE * 400,
-            usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
+    fn upload(&mut self, queue: &wgpu::Queue, atlas: &wgpu::Texture) {
+        let Some((min_x, min_y, max_x, max_y)) = self.dirty.take() else {
+            return;
+        };
 
-        Self {
-            texture,
-            size,
-            // texture_view,
-            // sampler,
-            bind_group,
-            pixels,
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: atlas,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: self.origin.0 + min_x as u32,
+                    y: self.origin.1 + min_y as u32,
+                    z: 0,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            // (starting at the dirty rect's first pixel, with rows as wide as the whole widget)
+            &self.pixels[(min_y * self.width + min_x) * 4..],
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(self.width as u32 * 4),
+                rows_per_image: Some((max_y - min_y) as u32),
+            },
+            wgpu::Extent3d {
+                width: (max_x - min_x) as u32,
+                height: (max_y - min_y) as u32,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
 
-            vertex_buffer,
-            index_buffer,
-            num_indices: 0,
-        }
+    /// Where it is in the atlas, in texture coordinates, as (min_u, min_v, max_u, max_v)
+    fn atlas_bounds(&self) -> [f32; 4] {
+        let size = ATLAS_SIZE as f32;
+        [
+            self.origin.0 as f32 / size,
+            self.origin.1 as f32 / size,
+            (self.origin.0 as usize + self.width) as f32 / size,
+            (self.origin.1 as usize + self.height) as f32 / size,
+        ]
     }
 
-    // pub fn draw(&mut self) {
-    //     queue.write_texture(
-    //         // Tells wgpu where to copy the pixel data
-    //         wgpu::ImageCopyTexture {
-    //             texture: &texture,
-    //             mip_level: 0,
-    //             origin: wgpu::Origin3d::ZERO,
-    //             aspect: wgpu::TextureAspect::All,
-    //         },
-    //         // The actual pixel data
-    //         &diffuse_rgba,
-    //         // The layout of the texture
-    //         wgpu::ImageDataLayout {
-    //             offset: 0,
-    //             bytes_per_row: Some(4 * dimensions.0),
-    //             rows_per_image: Some(dimensions.1),
-    //         },
-    //         texture_size,
-    //     );
-    // }
+    fn mark_dirty(&mut self, x: usize, y: usize) {
+        self.dirty = Some(match self.dirty {
+            None => (x, y, x + 1, y + 1),
+            Some((min_x, min_y, max_x, max_y)) => (
+                min_x.min(x),
+                min_y.min(y),
+                max_x.max(x + 1),
+                max_y.max(y + 1),
+            ),
+        });
+    }
 
     /// Get a mutable byte slice for the pixel buffer. The buffer is _not_ cleared for you; it will
     /// retain the previous frame's contents until you clear it yourself.
     #[allow(unused)]
     pub fn frame_mut(&mut self) -> &mut [u8] {
+        // (there's no telling what'll change)
+        self.dirty = Some((0, 0, self.width, self.height));
         &mut self.pixels
     }
 
@@ -336,27 +407,34 @@ impl WidgetTexture {
     ///
     /// This may be useful for operations that must sample the buffer, such as blending pixel
     /// colours directly into it.
+    #[allow(unused)]
     pub fn frame(&self) -> &[u8] {
         &self.pixels
     }
 
     pub fn width(&self) -> usize {
-        self.size.width as usize
+        self.width
     }
 
     pub fn height(&self) -> usize {
-        self.size.height as usize
+        self.height
     }
 
     pub fn set_pixel(&mut self, x: usize, y: usize, rgba: &[u8; 4]) {
         let offset = (y * self.width() + x) * 4;
 
-        self.pixels[offset..(offset + 4)].copy_from_slice(rgba);
+        // (widgets redraw everything every frame, so only what's actually different is uploaded)
+        if self.pixels[offset..(offset + 4)] != rgba[..] {
+            self.pixels[offset..(offset + 4)].copy_from_slice(rgba);
+            self.mark_dirty(x, y);
+        }
     }
 
     pub fn clear(&mut self, rgba: &[u8; 4]) {
-        for pixel in self.pixels.chunks_exact_mut(4) {
-            pixel.copy_from_slice(rgba);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                self.set_pixel(x, y, rgba);
+            }
         }
     }
 }
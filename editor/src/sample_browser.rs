@@ -0,0 +1,469 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::mpsc::{channel, Receiver},
+    thread,
+    time::{Duration, Instant},
+};
+
+use live_engine::{AudioNode, EngineHandle, Placement, Sampler};
+
+use crate::{
+    audio_cache::{decode_mono, AudioSummary},
+    dist,
+    invalidation::Invalidator,
+    panel::{text_y, List, ListHit, PANEL_MARGIN},
+    project::SamplePaths,
+    render::Overlay,
+    sample_packs::collect_audio_files,
+};
+
+const PANEL_WIDTH: f32 = 280.0;
+const PANEL_TOP: f32 = 64.0;
+const ROW_HEIGHT: f32 = 28.0;
+const MAX_ROWS: usize = 12;
+const FONT_SIZE: f32 = 14.0;
+const THUMBNAIL_WIDTH: f32 = 48.0;
+const THUMBNAIL_HEIGHT: f32 = 18.0;
+const THUMBNAIL_BARS: usize = 24;
+/// File names longer than this are cut off (from the start, the end is the interesting part)
+const MAX_NAME_CHARS: usize = 26;
+/// Mouse moves shorter than this are still a click (which auditions the file), longer ones are a drag (which inserts it)
+const DRAG_DISTANCE: f32 = 4.0;
+
+const PANEL_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.05];
+const ROW_HIGHLIGHT_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.07];
+const TEXT_COLOR: [f32; 4] = [0.02, 0.02, 0.02, 1.0];
+const DIM_TEXT_COLOR: [f32; 4] = [0.02, 0.02, 0.02, 0.45];
+const WAVE_COLOR: [f32; 4] = [0.02, 0.02, 0.02, 0.5];
+const DRAG_LABEL_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.9];
+
+/// The engine target that auditioned files play on
+const AUDITION_TARGET: &str = "audition";
+
+/**
+    Plays an audio file on its own (decoding it in the background), instead of whatever was auditioned before
+*/
+pub fn audition_file(engine: EngineHandle, path: PathBuf) {
+    thread::spawn(move || match decode_mono(&path) {
+        Ok((samples, sample_rate)) => {
+            audition_node(
+                &engine,
+                Box::new(Sampler::new(samples, sample_rate)),
+                Placement::Everywhere,
+            );
+        }
+        Err(e) => {
+            tracing::warn!("Could not read audio file at: {:?} ({})", path, e);
+        }
+    });
+}
+
+/**
+    Plays a node on its own, where it's placed (like a preset's code, compiled), instead of whatever was auditioned before
+*/
+pub fn audition_node(engine: &EngineHandle, node: Box<dyn AudioNode + Send>, placement: Placement) {
+    engine.place(AUDITION_TARGET, placement);
+    engine.play(AUDITION_TARGET, node);
+}
+
+pub fn stop_audition(engine: &EngineHandle) {
+    engine.stop(AUDITION_TARGET);
+}
+
+#[derive(Debug, Clone)]
+pub struct SampleFile {
+    /// as the project would refer to it
+    pub name: String,
+    pub path: PathBuf,
+}
+
+struct Thumbnail {
+    // (min, max) per bar, relative to the file's loudest sample
+    bars: Vec<(f32, f32)>,
+    duration: Duration,
+}
+
+enum Loaded {
+    Files(Vec<SampleFile>),
+    Thumbnail(PathBuf, Thumbnail),
+}
+
+/**
+    A file being dragged out of the sample browser, into the code
+*/
+pub struct SampleDrag {
+    pub index: usize,
+    from: (f32, f32),
+    pub mouse: (f32, f32),
+}
+
+impl SampleDrag {
+    pub fn new(index: usize, mouse: (f32, f32)) -> Self {
+        Self {
+            index,
+            from: mouse,
+            mouse,
+        }
+    }
+
+    /**
+        Whether the mouse moved far enough for this to be a drag, rather than a click
+    */
+    pub fn is_drag(&self) -> bool {
+        dist(self.from, self.mouse) >= DRAG_DISTANCE
+    }
+}
+
+/**
+    The collapsible sample browser side panel, on the left side of the window, listing the audio files in the project's sample directories (see `live.toml`) and sample packs. Clicking a file auditions it (clicking it again stops it), dragging it into the code inserts a sample widget for it, just like dropping a file onto the window does.
+*/
+pub struct SampleBrowser {
+    pub collapsed: bool,
+    files: Vec<SampleFile>,
+    // (the list and thumbnails are loaded in the background, every time the panel is expanded)
+    scanning: bool,
+    receiver: Option<Receiver<Loaded>>,
+    thumbnails: HashMap<PathBuf, Thumbnail>,
+    // the first row that's shown, in rows (fractional, for smooth scrolling with trackpads)
+    scroll: f32,
+    // the file that was auditioned last, and when
+    auditioning: Option<(usize, Instant)>,
+}
+
+impl SampleBrowser {
+    pub fn new() -> Self {
+        Self {
+            collapsed: true,
+            files: vec![],
+            scanning: false,
+            receiver: None,
+            thumbnails: HashMap::new(),
+            scroll: 0.0,
+            auditioning: None,
+        }
+    }
+
+    pub fn file(&self, index: usize) -> Option<&SampleFile> {
+        self.files.get(index)
+    }
+
+    /**
+        Expands or collapses it, rescanning the sample directories when it's expanded
+    */
+    pub fn toggle(&mut self, paths: SamplePaths, invalidator: Invalidator) {
+        self.collapsed = !self.collapsed;
+
+        if self.collapsed {
+            return;
+        }
+
+        let known = self.thumbnails.keys().cloned().collect::<Vec<_>>();
+        let (sender, receiver) = channel();
+
+        thread::spawn(move || {
+            let files = list_files(&paths);
+            let todo = files
+                .iter()
+                .map(|file| file.path.clone())
+                .filter(|path| !known.contains(path))
+                .collect::<Vec<_>>();
+
+            if sender.send(Loaded::Files(files)).is_err() {
+                return;
+            }
+            invalidator.invalidate();
+
+            for path in todo {
+                let thumbnail = match AudioSummary::load(&path) {
+                    Ok(summary) => thumbnail(&summary),
+                    Err(e) => {
+                        tracing::warn!("Could not read audio file at: {:?} ({})", path, e);
+                        continue;
+                    }
+                };
+
+                // (the panel was collapsed, or expanded again, in the meantime)
+                if sender.send(Loaded::Thumbnail(path, thumbnail)).is_err() {
+                    return;
+                }
+                invalidator.invalidate();
+            }
+        });
+
+        self.scanning = true;
+        self.receiver = Some(receiver);
+    }
+
+    /**
+        Picks up the files and thumbnails that were loaded in the background
+    */
+    pub fn poll(&mut self) {
+        let Some(receiver) = &self.receiver else {
+            return;
+        };
+
+        for loaded in receiver.try_iter() {
+            match loaded {
+                Loaded::Files(files) => {
+                    self.files = files;
+                    self.scanning = false;
+                    self.auditioning = None;
+                    self.scroll = self.scroll.min(self.max_scroll());
+                }
+                Loaded::Thumbnail(path, thumbnail) => {
+                    self.thumbnails.insert(path, thumbnail);
+                }
+            }
+        }
+    }
+
+    /**
+        The file that's being auditioned, if it's still playing (as far as we know)
+    */
+    fn playing(&self) -> Option<usize> {
+        let (index, started_at) = self.auditioning?;
+        let duration = self
+            .files
+            .get(index)
+            .and_then(|file| self.thumbnails.get(&file.path))
+            .map(|thumbnail| thumbnail.duration);
+
+        duration
+            .map_or(true, |duration| started_at.elapsed() < duration)
+            .then_some(index)
+    }
+
+    /**
+        Plays a file, or stops it if it's the one that's playing already
+    */
+    pub fn audition(&mut self, index: usize, engine: Option<&EngineHandle>) {
+        let Some(engine) = engine.cloned() else {
+            return;
+        };
+
+        if self.playing() == Some(index) {
+            stop_audition(&engine);
+            self.auditioning = None;
+            return;
+        }
+
+        let Some(file) = self.files.get(index) else {
+            return;
+        };
+
+        audition_file(engine, file.path.clone());
+        self.auditioning = Some((index, Instant::now()));
+    }
+
+    fn max_scroll(&self) -> f32 {
+        self.files.len().saturating_sub(MAX_ROWS) as f32
+    }
+
+    /**
+        Scrolls the list, if the mouse is over it, returning whether it was
+    */
+    pub fn scroll(&mut self, window_size: (f32, f32), mouse: (f32, f32), rows: f32) -> bool {
+        if self.collapsed || self.hit_test(window_size, mouse).is_none() {
+            return false;
+        }
+
+        self.scroll = (self.scroll - rows).clamp(0.0, self.max_scroll());
+        true
+    }
+
+    fn first_row(&self) -> usize {
+        self.scroll.floor() as usize
+    }
+
+    fn visible_rows(&self) -> usize {
+        (self.files.len() - self.first_row()).min(MAX_ROWS)
+    }
+
+    fn list(&self, _window_size: (f32, f32)) -> List {
+        List::below(
+            (PANEL_MARGIN, PANEL_TOP),
+            PANEL_WIDTH,
+            ROW_HEIGHT,
+            self.collapsed,
+            self.visible_rows(),
+        )
+    }
+
+    /// (the entries are files, not rows, it's scrolled)
+    pub fn hit_test(&self, window_size: (f32, f32), point: (f32, f32)) -> Option<ListHit> {
+        match self.list(window_size).hit_test(point)? {
+            ListHit::Entry(row) => Some(ListHit::Entry(self.first_row() + row)),
+            hit => Some(hit),
+        }
+    }
+
+    pub fn draw(
+        &self,
+        dragging: Option<&SampleDrag>,
+        window_size: (f32, f32),
+        overlay: &mut Overlay,
+    ) {
+        let list = self.list(window_size);
+        list.draw(
+            "Samples",
+            if self.scanning {
+                "(looking for samples…)"
+            } else {
+                "(no samples, see live.toml)"
+            },
+            FONT_SIZE,
+            [PANEL_COLOR, TEXT_COLOR, DIM_TEXT_COLOR],
+            overlay,
+        );
+
+        if self.collapsed {
+            return;
+        }
+
+        let (min_x, _, max_x, _) = list.bounds;
+
+        if self.files.len() > MAX_ROWS {
+            overlay.text(
+                (max_x - 70.0, list.header_text_y(FONT_SIZE)),
+                format!(
+                    "{}–{}/{}",
+                    self.first_row() + 1,
+                    self.first_row() + self.visible_rows(),
+                    self.files.len()
+                ),
+                FONT_SIZE,
+                DIM_TEXT_COLOR,
+            );
+        }
+
+        let playing = self.playing();
+
+        for row in 0..self.visible_rows() {
+            let i = self.first_row() + row;
+            let file = &self.files[i];
+            let top = list.row_top(row);
+
+            if playing == Some(i) || dragging.map_or(false, |drag| drag.index == i) {
+                overlay.quad((min_x, top, max_x, top + ROW_HEIGHT), ROW_HIGHLIGHT_COLOR);
+            }
+
+            if let Some(thumbnail) = self.thumbnails.get(&file.path) {
+                let bar_width = THUMBNAIL_WIDTH / THUMBNAIL_BARS as f32;
+                let mid_y = top + ROW_HEIGHT / 2.0;
+
+                for (j, &(min, max)) in thumbnail.bars.iter().enumerate() {
+                    let x = min_x + 10.0 + j as f32 * bar_width;
+
+                    overlay.quad(
+                        (
+                            x,
+                            mid_y - max * THUMBNAIL_HEIGHT / 2.0,
+                            x + bar_width * 0.7,
+                            // (at least a hairline, so that silence is visible too)
+                            (mid_y - min * THUMBNAIL_HEIGHT / 2.0)
+                                .max(mid_y - max * THUMBNAIL_HEIGHT / 2.0 + 1.0),
+                        ),
+                        WAVE_COLOR,
+                    );
+                }
+            }
+
+            overlay.text(
+                (
+                    min_x + 20.0 + THUMBNAIL_WIDTH,
+                    text_y(top, ROW_HEIGHT, FONT_SIZE),
+                ),
+                format!(
+                    "{}{}",
+                    if playing == Some(i) { "▶ " } else { "" },
+                    shorten(&file.name)
+                ),
+                FONT_SIZE,
+                TEXT_COLOR,
+            );
+        }
+
+        // the file that's being dragged, following the mouse
+        if let Some(drag) = dragging
+            && drag.is_drag()
+            && let Some(file) = self.files.get(drag.index)
+        {
+            let name = shorten(&file.name);
+            let (x, y) = (drag.mouse.0 + 12.0, drag.mouse.1 + 8.0);
+            let width = 16.0 + name.chars().count() as f32 * FONT_SIZE * 0.6;
+
+            overlay.quad((x, y, x + width, y + ROW_HEIGHT), DRAG_LABEL_COLOR);
+            overlay.text(
+                (x + 8.0, text_y(y, ROW_HEIGHT, FONT_SIZE)),
+                name,
+                FONT_SIZE,
+                TEXT_COLOR,
+            );
+        }
+    }
+}
+
+fn shorten(name: &str) -> String {
+    let chars = name.chars().count();
+    if chars <= MAX_NAME_CHARS {
+        return name.to_string();
+    }
+
+    format!(
+        "…{}",
+        name.chars()
+            .skip(chars - MAX_NAME_CHARS + 1)
+            .collect::<String>()
+    )
+}
+
+/**
+    All audio files in the project's sample directories and sample packs, sorted by how the project would refer to them
+*/
+fn list_files(paths: &SamplePaths) -> Vec<SampleFile> {
+    let mut found = vec![];
+
+    for dir in paths
+        .search_dirs
+        .iter()
+        .chain(paths.packs.iter().map(|(_, dir)| dir))
+    {
+        if let Err(e) = collect_audio_files(dir, &mut found) {
+            tracing::warn!("Could not list samples in {:?}: {}", dir, e);
+        }
+    }
+
+    let mut files = found
+        .into_iter()
+        .map(|path| SampleFile {
+            name: paths.relative(&path),
+            path,
+        })
+        .collect::<Vec<_>>();
+
+    files.sort_by(|a, b| a.name.cmp(&b.name));
+    files.dedup_by(|a, b| a.path == b.path);
+
+    files
+}
+
+fn thumbnail(summary: &AudioSummary) -> Thumbnail {
+    let scale = if summary.overall_max > 0.0 {
+        1.0 / summary.overall_max
+    } else {
+        0.0
+    };
+
+    let info = &summary.info;
+    let frames = info.num_samples / (info.channels.max(1) as usize);
+
+    Thumbnail {
+        bars: summary
+            .resample(THUMBNAIL_BARS)
+            .into_iter()
+            .map(|(min, max, _)| (min * scale, max * scale))
+            .collect(),
+        duration: Duration::from_secs_f32(frames as f32 / info.sample_rate.max(1) as f32),
+    }
+}
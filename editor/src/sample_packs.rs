@@ -0,0 +1,631 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use live_engine::Quantize;
+use rfd::{FileDialog, MessageButtons, MessageDialog, MessageLevel};
+use sha2::{Digest, Sha256};
+
+use crate::project::{find_project_root, ProjectFile, SamplePaths};
+
+/// Lists the sample packs that a workspace uses, one (workspace-relative) directory per line
+const WORKSPACE_PACKS_FILE: &str = "packs";
+
+/// Lives in the root of each sample pack directory
+const MANIFEST_FILE: &str = "pack.manifest";
+
+const AUDIO_EXTENSIONS: &[&str] = &["wav", "aif", "aiff", "flac", "mp3", "ogg"];
+
+pub fn checksum(path: &Path) -> Result<String, String> {
+    let bytes = fs::read(path).map_err(|e| e.to_string())?;
+    Ok(format!("{:x}", Sha256::digest(&bytes)))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackFile {
+    /// relative to the pack directory, always with forward slashes
+    pub path: String,
+    pub checksum: String,
+}
+
+/**
+    Describes a sample pack: its name, where it can be (re)downloaded from, and the files in it with their checksums.
+
+    The format is plain text, one `key<TAB>value` per line:
+
+    ```text
+    name	Drums vol. 1
+    source	https://example.com/packs/drums-vol-1
+    file	1e0c...f3a2	kicks/Kick 90s 1.wav
+    file	94ab...0c11	Cowbell LatinTing.wav
+    ```
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackManifest {
+    pub name: String,
+    pub source: Option<String>,
+    pub files: Vec<PackFile>,
+}
+
+impl PackManifest {
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let mut name = None;
+        let mut source = None;
+        let mut files = vec![];
+
+        for (i, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            match line.split('\t').collect::<Vec<_>>()[..] {
+                ["name", value] => name = Some(value.to_string()),
+                ["source", value] => source = Some(value.to_string()),
+                ["file", checksum, path] => files.push(PackFile {
+                    path: path.to_string(),
+                    checksum: checksum.to_string(),
+                }),
+                _ => return Err(format!("invalid manifest line {}: {:?}", i + 1, line)),
+            }
+        }
+
+        Ok(Self {
+            name: name.ok_or("manifest has no name")?,
+            source,
+            files,
+        })
+    }
+
+    /**
+        Creates a manifest for all audio files in (subdirectories of) the given directory
+    */
+    pub fn scan(dir: &Path, name: String) -> Result<Self, String> {
+        let mut paths = vec![];
+        collect_audio_files(dir, &mut paths)?;
+        paths.sort();
+
+        let files = paths
+            .into_iter()
+            .map(|path| {
+                Ok(PackFile {
+                    checksum: checksum(&path)?,
+                    path: path
+                        .strip_prefix(dir)
+                        .map_err(|e| e.to_string())?
+                        .to_string_lossy()
+                        .replace('\\', "/"),
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(Self {
+            name,
+            source: None,
+            files,
+        })
+    }
+
+    pub fn serialize(&self) -> String {
+        let mut lines = vec![format!("name\t{}", self.name)];
+
+        if let Some(source) = &self.source {
+            lines.push(format!("source\t{}", source));
+        }
+
+        for file in &self.files {
+            lines.push(format!("file\t{}\t{}", file.checksum, file.path));
+        }
+
+        lines.join("\n") + "\n"
+    }
+}
+
+pub fn is_audio_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map_or(false, |ext| {
+            AUDIO_EXTENSIONS.contains(&ext.to_lowercase().as_str())
+        })
+}
+
+pub fn collect_audio_files(dir: &Path, paths: &mut Vec<PathBuf>) -> Result<(), String> {
+    for entry in fs::read_dir(dir).map_err(|e| e.to_string())? {
+        let path = entry.map_err(|e| e.to_string())?.path();
+
+        if path.is_dir() {
+            collect_audio_files(&path, paths)?;
+        } else if is_audio_file(&path) {
+            paths.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileProblem {
+    Missing,
+    ChecksumMismatch,
+}
+
+#[derive(Debug, Clone)]
+pub struct SamplePack {
+    /// how the workspace refers to it (relative to the workspace root, if possible)
+    pub entry: String,
+    pub dir: PathBuf,
+    pub manifest: Option<PackManifest>,
+}
+
+impl SamplePack {
+    fn load(root: &Path, entry: String) -> Self {
+        let dir = root.join(&entry);
+
+        let manifest = fs::read_to_string(dir.join(MANIFEST_FILE))
+            .ok()
+            .and_then(|contents| match PackManifest::parse(&contents) {
+                Ok(manifest) => Some(manifest),
+                Err(e) => {
+                    tracing::warn!("Could not read manifest of sample pack {:?}: {}", entry, e);
+                    None
+                }
+            });
+
+        Self {
+            entry,
+            dir,
+            manifest,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        self.manifest
+            .as_ref()
+            .map_or(self.entry.as_str(), |manifest| manifest.name.as_str())
+    }
+
+    /**
+        Checks every file in the manifest, returning the ones that are missing or changed. If the pack directory itself is gone, that's just all of them.
+    */
+    pub fn verify(&self) -> Vec<(&PackFile, FileProblem)> {
+        let Some(manifest) = &self.manifest else {
+            return vec![];
+        };
+
+        manifest
+            .files
+            .iter()
+            .filter_map(|file| {
+                let path = self.dir.join(&file.path);
+
+                if !path.exists() {
+                    Some((file, FileProblem::Missing))
+                } else if checksum(&path).ok().as_ref() != Some(&file.checksum) {
+                    Some((file, FileProblem::ChecksumMismatch))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /**
+        Re-fetches the given files from the pack's source, checking that what we got is what the manifest says we should've gotten.
+    */
+    fn redownload(&self, files: &[&PackFile]) -> Result<(), String> {
+        let source = self
+            .manifest
+            .as_ref()
+            .and_then(|manifest| manifest.source.as_ref())
+            .ok_or("this pack has no source to download from")?;
+
+        for file in files {
+            let url = format!(
+                "{}/{}",
+                source.trim_end_matches('/'),
+                file.path.replace(' ', "%20")
+            );
+
+            let bytes = download(&url).map_err(|e| format!("{}: {}", file.path, e))?;
+
+            if format!("{:x}", Sha256::digest(&bytes)) != file.checksum {
+                return Err(format!("{}: downloaded file doesn't match checksum", file.path));
+            }
+
+            let path = self.dir.join(&file.path);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+
+            fs::write(&path, bytes).map_err(|e| e.to_string())?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn download(url: &str) -> Result<Vec<u8>, String> {
+    use std::io::Read;
+
+    let mut bytes = vec![];
+    ureq::get(url)
+        .set("User-Agent", "live_editor")
+        .call()
+        .map_err(|e| e.to_string())?
+        .into_reader()
+        .read_to_end(&mut bytes)
+        .map_err(|e| e.to_string())?;
+
+    Ok(bytes)
+}
+
+// (the browser only fetches asynchronously, and the pack's source would have to allow it, too)
+#[cfg(target_arch = "wasm32")]
+fn download(_url: &str) -> Result<Vec<u8>, String> {
+    Err("can't download sample packs in the browser (yet)".into())
+}
+
+/**
+    The directory the current project lives in. Sample packs are referenced relative to it, so that a project (plus its packs) can be moved around or shared as a whole.
+*/
+pub struct Workspace {
+    root: PathBuf,
+    pub packs: Vec<SamplePack>,
+    /// The project's sample search directories (from its `live.toml`)
+    pub search_dirs: Vec<PathBuf>,
+    /// How long hushing takes (also from its `live.toml`)
+    pub hush: Duration,
+    /// (also from its `live.toml`, and quantizing and swing can be changed while playing)
+    pub tempo: f64,
+    pub quantize: Quantize,
+    pub swing: f64,
+    pub scroll_margin: i32,
+    /// (how many bars a bounce is)
+    pub bounce_bars: f64,
+    /// (and freezing a definition, and whether that comments it out)
+    pub freeze_bars: f64,
+    pub freeze_comments_out: bool,
+    /// How long numbers glide to their new values when they're changed in the code
+    pub ease: Duration,
+    /// (the directories that searching the project skips)
+    pub exclude: Vec<PathBuf>,
+}
+
+impl Workspace {
+    pub fn open(root: PathBuf) -> Self {
+        let packs = fs::read_to_string(root.join(WORKSPACE_PACKS_FILE))
+            .map(|contents| {
+                contents
+                    .lines()
+                    .map(|line| line.trim())
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(|entry| SamplePack::load(&root, entry.to_string()))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let project = ProjectFile::load(&root);
+
+        let search_dirs = project.samples.iter().map(|dir| root.join(dir)).collect();
+        let exclude = project.exclude.iter().map(|dir| root.join(dir)).collect();
+
+        Self {
+            root,
+            packs,
+            search_dirs,
+            hush: project.hush(),
+            tempo: project.tempo(),
+            quantize: project.quantize(),
+            swing: project.swing(),
+            scroll_margin: project.scroll_margin(),
+            bounce_bars: project.bounce_bars(),
+            freeze_bars: project.freeze_bars(),
+            freeze_comments_out: project.freeze_comments_out(),
+            ease: project.ease(),
+            exclude,
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /**
+        Either `$LIVE_WORKSPACE`, or the project (the nearest directory with a `live.toml`) that the current directory is in, or else just the current directory
+    */
+    pub fn default_root() -> PathBuf {
+        std::env::var_os("LIVE_WORKSPACE")
+            .map(PathBuf::from)
+            .or_else(|| {
+                let dir = std::env::current_dir().ok()?;
+                Some(find_project_root(&dir).unwrap_or(dir))
+            })
+            .unwrap_or_else(|| PathBuf::from("."))
+    }
+
+    fn save(&self) {
+        let contents = self
+            .packs
+            .iter()
+            .map(|pack| pack.entry.clone())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        if let Err(e) = fs::write(self.root.join(WORKSPACE_PACKS_FILE), contents + "\n") {
+            tracing::warn!("Could not save workspace sample packs: {:?}", e);
+        }
+    }
+
+    /**
+        How sample paths are resolved in this workspace (a snapshot, for widgets to hold on to)
+    */
+    pub fn sample_paths(&self) -> SamplePaths {
+        SamplePaths {
+            root: self.root.clone(),
+            search_dirs: self.search_dirs.clone(),
+            packs: self
+                .packs
+                .iter()
+                .map(|pack| (pack.name().to_string(), pack.dir.clone()))
+                .collect(),
+        }
+    }
+
+    /**
+        All files of all (manifested) packs, as `<pack name>/<file>` with where they are on disk
+    */
+    pub fn pack_files(&self) -> Vec<(String, PathBuf)> {
+        self.packs
+            .iter()
+            .filter_map(|pack| Some((pack, pack.manifest.as_ref()?)))
+            .flat_map(|(pack, manifest)| {
+                manifest.files.iter().map(|file| {
+                    (
+                        format!("{}/{}", pack.name(), file.path),
+                        pack.dir.join(&file.path),
+                    )
+                })
+            })
+            .collect()
+    }
+
+    /**
+        Verifies all packs on load, given the outcome of `check_packs` (which does the slow part, in the background). When files are missing or changed (typically because a pack was moved), we ask the user to either point us to the pack's new location, or redownload the files, rather than just silently playing nothing.
+    */
+    pub fn verify_packs(&mut self, checked: Vec<(SamplePack, bool)>) {
+        for (checked, has_problems) in checked {
+            // (packs could've been relinked in the meantime)
+            let Some(i) = self
+                .packs
+                .iter()
+                .position(|pack| pack.entry == checked.entry)
+            else {
+                continue;
+            };
+
+            if self.packs[i].manifest.is_none() {
+                self.packs[i].manifest = checked.manifest;
+            }
+
+            if !has_problems {
+                continue;
+            }
+
+            loop {
+                let pack = &self.packs[i];
+                let problems = pack.verify();
+                if problems.is_empty() {
+                    break;
+                }
+
+                let description = format!(
+                    "{} file(s) of sample pack \"{}\" (at {}) are missing or changed:\n\n{}\n\nDo you want to point to the pack's new location?",
+                    problems.len(),
+                    pack.name(),
+                    pack.dir.display(),
+                    problems
+                        .iter()
+                        .take(10)
+                        .map(|(file, problem)| match problem {
+                            FileProblem::Missing => format!("- {} (missing)", file.path),
+                            FileProblem::ChecksumMismatch => format!("- {} (changed)", file.path),
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                );
+
+                let relink = MessageDialog::new()
+                    .set_level(MessageLevel::Warning)
+                    .set_title("Sample pack problem")
+                    .set_description(&description)
+                    .set_buttons(MessageButtons::YesNo)
+                    .show();
+
+                if relink {
+                    let Some(dir) = FileDialog::new()
+                        .set_title(&format!("Where is \"{}\"?", pack.name()))
+                        .set_directory(&self.root)
+                        .pick_folder()
+                    else {
+                        break;
+                    };
+
+                    let manifest = pack.manifest.clone();
+                    let entry = dir
+                        .strip_prefix(&self.root)
+                        .unwrap_or(&dir)
+                        .to_string_lossy()
+                        .to_string();
+
+                    self.packs[i] = SamplePack {
+                        entry,
+                        dir,
+                        // the manifest is what we're checking against, so it moves along (even if the new location doesn't have one)
+                        manifest,
+                    };
+                    self.save();
+
+                    // and check again
+                    continue;
+                }
+
+                let has_source = pack
+                    .manifest
+                    .as_ref()
+                    .map_or(false, |manifest| manifest.source.is_some());
+
+                if has_source
+                    && MessageDialog::new()
+                        .set_level(MessageLevel::Info)
+                        .set_title("Sample pack problem")
+                        .set_description(&format!(
+                            "Redownload the missing files of \"{}\"?",
+                            pack.name()
+                        ))
+                        .set_buttons(MessageButtons::YesNo)
+                        .show()
+                {
+                    let files = problems.iter().map(|(file, _)| *file).collect::<Vec<_>>();
+
+                    if let Err(e) = pack.redownload(&files) {
+                        MessageDialog::new()
+                            .set_level(MessageLevel::Error)
+                            .set_title("Download failed")
+                            .set_description(&e)
+                            .set_buttons(MessageButtons::Ok)
+                            .show();
+                    }
+                }
+
+                break;
+            }
+        }
+    }
+}
+
+/**
+    The slow part of verifying packs, which can run on another thread: packs that don't have a manifest yet get one (with the checksums of the files as they are now), and all other packs' files are checksummed. Returns the packs, with whether any of their files are missing or changed.
+*/
+pub fn check_packs(packs: Vec<SamplePack>) -> Vec<(SamplePack, bool)> {
+    packs
+        .into_iter()
+        .map(|mut pack| {
+            if pack.manifest.is_none() && pack.dir.is_dir() {
+                let name = pack.dir.file_name().map_or(pack.entry.clone(), |name| {
+                    name.to_string_lossy().to_string()
+                });
+
+                match PackManifest::scan(&pack.dir, name) {
+                    Ok(manifest) => {
+                        if let Err(e) =
+                            fs::write(pack.dir.join(MANIFEST_FILE), manifest.serialize())
+                        {
+                            tracing::warn!("Could not write sample pack manifest: {:?}", e);
+                        }
+                        pack.manifest = Some(manifest);
+                    }
+                    Err(e) => {
+                        tracing::warn!("Could not scan sample pack {:?}: {}", pack.entry, e);
+                    }
+                }
+            }
+
+            let has_problems = !pack.verify().is_empty();
+            (pack, has_problems)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    /// (a workspace in a temp dir, which is removed when it's dropped, with one pack of two samples and a text file)
+    fn workspace() -> TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+
+        for (file, contents) in [
+            ("drums/kicks/Kick 1.wav", "kick"),
+            ("drums/snare.wav", "snare"),
+            ("drums/readme.txt", "hi"),
+        ] {
+            let path = root.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        fs::write(root.join(WORKSPACE_PACKS_FILE), "drums\n").unwrap();
+
+        dir
+    }
+
+    #[test]
+    fn test_intact_pack() {
+        let dir = workspace();
+        let root = dir.path().to_path_buf();
+
+        // (the first time, the pack gets a manifest)
+        let checked = check_packs(Workspace::open(root.clone()).packs);
+        assert_eq!(checked.len(), 1);
+        assert!(!checked[0].1);
+
+        let workspace = Workspace::open(root.clone());
+        let manifest = workspace.packs[0].manifest.clone().unwrap();
+        assert_eq!(manifest.name, "drums");
+        assert_eq!(
+            manifest
+                .files
+                .iter()
+                .map(|file| file.path.as_str())
+                .collect::<Vec<_>>(),
+            vec!["kicks/Kick 1.wav", "snare.wav"]
+        );
+        assert_eq!(PackManifest::parse(&manifest.serialize()), Ok(manifest));
+
+        assert_eq!(workspace.packs[0].verify(), vec![]);
+        assert!(!check_packs(workspace.packs.clone())[0].1);
+        assert_eq!(
+            workspace.pack_files(),
+            vec![
+                (
+                    "drums/kicks/Kick 1.wav".into(),
+                    root.join("drums/kicks/Kick 1.wav")
+                ),
+                ("drums/snare.wav".into(), root.join("drums/snare.wav")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tampered_pack() {
+        let dir = workspace();
+        let root = dir.path().to_path_buf();
+        check_packs(Workspace::open(root.clone()).packs);
+
+        fs::write(root.join("drums/snare.wav"), "another snare").unwrap();
+        fs::remove_file(root.join("drums/kicks/Kick 1.wav")).unwrap();
+
+        let workspace = Workspace::open(root.clone());
+        let problems = workspace.packs[0]
+            .verify()
+            .into_iter()
+            .map(|(file, problem)| (file.path.as_str(), problem))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            problems,
+            vec![
+                ("kicks/Kick 1.wav", FileProblem::Missing),
+                ("snare.wav", FileProblem::ChecksumMismatch),
+            ]
+        );
+        assert!(check_packs(workspace.packs.clone())[0].1);
+
+        // (and a tampered manifest doesn't load at all)
+        fs::write(root.join("drums").join(MANIFEST_FILE), "file\tkick.wav\n").unwrap();
+        assert!(Workspace::open(root.clone()).packs[0].manifest.is_none());
+    }
+}
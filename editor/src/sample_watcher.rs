@@ -0,0 +1,131 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver},
+    thread,
+    time::Duration,
+};
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::invalidation::Invalidator;
+
+/// DAWs (and editors) write files in several steps, so changes are only reported once it's been quiet this long
+const DEBOUNCE: Duration = Duration::from_millis(250);
+
+/**
+    Watches the sample files that the code refers to, so that they can be reloaded when they change on disk (e.g. when they're re-exported from a DAW).
+
+    It watches the directories the files are in, rather than the files themselves, because files are often replaced (written elsewhere and moved into place) rather than changed.
+*/
+pub struct SampleWatcher {
+    watcher: Option<RecommendedWatcher>,
+    // what the code refers to (as resolved paths), to see whether anything changed
+    referenced: Vec<PathBuf>,
+    // per canonical path, how the code refers to it
+    files: HashMap<PathBuf, PathBuf>,
+    dirs: HashSet<PathBuf>,
+    changes: Receiver<HashSet<PathBuf>>,
+}
+
+impl SampleWatcher {
+    pub fn start(invalidator: Invalidator) -> Self {
+        let (raw_sender, raw) = channel();
+        let (sender, changes) = channel();
+
+        let watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            if let Ok(event) = event
+                && matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+            {
+                for path in event.paths {
+                    let _ = raw_sender.send(path);
+                }
+            }
+        })
+        .map_err(|e| tracing::warn!("Could not watch sample files: {}", e))
+        .ok();
+
+        thread::spawn(move || {
+            while let Ok(path) = raw.recv() {
+                let mut batch = HashSet::from([path]);
+                while let Ok(path) = raw.recv_timeout(DEBOUNCE) {
+                    batch.insert(path);
+                }
+
+                tracing::debug!(target: "files", "{} sample files changed on disk", batch.len());
+                if sender.send(batch).is_err() {
+                    break;
+                }
+                invalidator.invalidate();
+            }
+        });
+
+        Self {
+            watcher,
+            referenced: vec![],
+            files: HashMap::new(),
+            dirs: HashSet::new(),
+            changes,
+        }
+    }
+
+    /**
+        Watches these files (and stops watching the ones that aren't in there anymore). Cheap to call every frame.
+    */
+    pub fn watch(&mut self, referenced: Vec<PathBuf>) {
+        if referenced == self.referenced {
+            return;
+        }
+
+        self.files = referenced
+            .iter()
+            .map(|file| (canonical(file), file.clone()))
+            .collect();
+
+        let dirs = self
+            .files
+            .keys()
+            .filter_map(|file| file.parent().map(Path::to_path_buf))
+            .collect::<HashSet<_>>();
+
+        if let Some(watcher) = &mut self.watcher {
+            for dir in self.dirs.difference(&dirs) {
+                tracing::debug!(target: "files", "no longer watching {:?}", dir);
+                let _ = watcher.unwatch(dir);
+            }
+            for dir in dirs.difference(&self.dirs) {
+                tracing::debug!(target: "files", "watching {:?}", dir);
+                if let Err(e) = watcher.watch(dir, RecursiveMode::NonRecursive) {
+                    tracing::warn!("Could not watch {:?}: {}", dir, e);
+                }
+            }
+        }
+
+        self.dirs = dirs;
+        self.referenced = referenced;
+    }
+
+    /**
+        The files that changed since the last call, as the code refers to them
+    */
+    pub fn changed(&mut self) -> Vec<PathBuf> {
+        let mut changed = vec![];
+
+        while let Ok(batch) = self.changes.try_recv() {
+            for path in batch {
+                if let Some(file) = self.files.get(&canonical(&path))
+                    && !changed.contains(file)
+                {
+                    changed.push(file.clone());
+                }
+            }
+        }
+
+        changed
+    }
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
@@ -0,0 +1,334 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// The extension of the code files that are searched
+const CODE_EXTENSION: &str = "live";
+/// (more than this isn't much of a search)
+const MAX_HITS: usize = 500;
+
+/**
+    A code file to search through: the document that's open (from what's in the editor, not what's on disk), or one of the other ones in the project
+*/
+pub struct SearchFile {
+    pub path: PathBuf,
+    // (relative to the project root)
+    pub name: String,
+    pub source: String,
+    pub open: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchHit {
+    pub file: usize,
+    pub row: usize,
+    // (in chars, like the editor's columns)
+    pub col: usize,
+    pub line: String,
+}
+
+/**
+    All the code files in the project, except in hidden directories (like the backups) and the ones the project file excludes, in alphabetical order
+*/
+pub fn code_files(root: &Path, exclude: &[PathBuf]) -> Vec<PathBuf> {
+    fn collect(dir: &Path, exclude: &[PathBuf], files: &mut Vec<PathBuf>) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+
+        for path in entries.flatten().map(|entry| entry.path()) {
+            let hidden = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with('.'));
+
+            if hidden || exclude.contains(&path) {
+                continue;
+            }
+
+            if path.is_dir() {
+                collect(&path, exclude, files);
+            } else if path.extension().and_then(|ext| ext.to_str()) == Some(CODE_EXTENSION) {
+                files.push(path);
+            }
+        }
+    }
+
+    let mut files = vec![];
+    collect(root, exclude, &mut files);
+    files.sort();
+    files
+}
+
+/**
+    The files to search: the open document first (with what's in the editor), and then the project's other code files, read once, now
+*/
+pub fn project_files(
+    root: &Path,
+    exclude: &[PathBuf],
+    current: &Path,
+    source: String,
+) -> Vec<SearchFile> {
+    let name = |path: &Path| {
+        path.strip_prefix(root)
+            .unwrap_or(path)
+            .display()
+            .to_string()
+    };
+
+    let mut files = vec![SearchFile {
+        name: name(current),
+        source,
+        path: current.to_path_buf(),
+        open: true,
+    }];
+
+    files.extend(
+        code_files(root, exclude)
+            .into_iter()
+            .filter(|path| path != current)
+            .filter_map(|path| {
+                let source = fs::read_to_string(&path).ok()?;
+                Some(SearchFile {
+                    name: name(&path),
+                    source,
+                    path,
+                    open: false,
+                })
+            }),
+    );
+
+    files
+}
+
+/**
+    Where the query is in a line (as byte offsets), ignoring case unless there's an uppercase letter in it
+*/
+fn find(line: &str, query: &str) -> Vec<usize> {
+    if query.is_empty() {
+        return vec![];
+    }
+
+    let (line, query) = if query.chars().any(char::is_uppercase) {
+        (line.to_string(), query.to_string())
+    } else {
+        // (only ASCII, so that the offsets still fit the original)
+        (line.to_ascii_lowercase(), query.to_ascii_lowercase())
+    };
+
+    line.match_indices(&query).map(|(i, _)| i).collect()
+}
+
+/**
+    A line with every match of the query replaced
+*/
+pub fn replace_line(line: &str, query: &str, replacement: &str) -> String {
+    let mut replaced = String::new();
+    let mut rest = 0;
+
+    for i in find(line, query) {
+        replaced.push_str(&line[rest..i]);
+        replaced.push_str(replacement);
+        rest = i + query.len();
+    }
+
+    replaced.push_str(&line[rest..]);
+    replaced
+}
+
+/**
+    Every match of the query, file by file and line by line (up to a point)
+*/
+pub fn search(files: &[SearchFile], query: &str) -> Vec<SearchHit> {
+    files
+        .iter()
+        .enumerate()
+        .flat_map(|(file, search_file)| {
+            search_file
+                .source
+                .split('\n')
+                .enumerate()
+                .flat_map(move |(row, line)| {
+                    find(line, query).into_iter().map(move |i| SearchHit {
+                        file,
+                        row,
+                        col: line[..i].chars().count(),
+                        line: line.to_string(),
+                    })
+                })
+        })
+        .take(MAX_HITS)
+        .collect()
+}
+
+/**
+    Every file that has matches, with what it'll be when they're all replaced, and how many there are
+*/
+pub fn replace<'a>(
+    files: &'a [SearchFile],
+    query: &str,
+    replacement: &str,
+) -> Vec<(&'a SearchFile, String, usize)> {
+    files
+        .iter()
+        .filter_map(|file| {
+            let count = file
+                .source
+                .split('\n')
+                .map(|line| find(line, query).len())
+                .sum::<usize>();
+
+            let replaced = file
+                .source
+                .split('\n')
+                .map(|line| replace_line(line, query, replacement))
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            (count > 0).then_some((file, replaced, count))
+        })
+        .collect()
+}
+
+/// What writing the replaced files did
+#[derive(Debug, Default, PartialEq)]
+pub struct Written {
+    /// (what the open document becomes, which is up to the editor)
+    pub document: Option<String>,
+    pub matches: usize,
+    pub files: usize,
+    pub errors: Vec<String>,
+}
+
+/**
+    Writes the replaced files to disk, except the open document, which is handed back
+*/
+pub fn write_replaced(replaced: &[(&SearchFile, String, usize)]) -> Written {
+    let mut written = Written::default();
+
+    for (file, source, count) in replaced {
+        if file.open {
+            written.document = Some(source.clone());
+        } else if let Err(e) = fs::write(&file.path, source) {
+            written.errors.push(format!("{}: {}", file.name, e));
+            continue;
+        }
+        written.matches += count;
+        written.files += 1;
+    }
+
+    written
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tempfile::TempDir;
+
+    /// (a project in a temp dir, which is removed when it's dropped, with the open document as it's in the editor)
+    fn project() -> (TempDir, Vec<SearchFile>) {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+
+        for (file, source) in [
+            ("session.live", "play kick;"),
+            ("drums/kick.live", "let kick = 1;\nlet Kick = 2;"),
+            ("drums/notes.txt", "kick"),
+            (".backups/session.live", "play kick;"),
+            ("old/kick.live", "let kick = 0;"),
+        ] {
+            let path = root.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, source).unwrap();
+        }
+
+        let files = project_files(
+            root,
+            &[root.join("old")],
+            &root.join("session.live"),
+            "play kick * 2;".into(),
+        );
+        (dir, files)
+    }
+
+    #[test]
+    fn test_project_files() {
+        let (dir, files) = project();
+        let root = dir.path();
+
+        // (not the hidden ones, the excluded ones, or the ones that aren't code)
+        assert_eq!(
+            code_files(root, &[root.join("old")]),
+            vec![root.join("drums/kick.live"), root.join("session.live")]
+        );
+
+        let names = files.iter().map(|file| &file.name).collect::<Vec<_>>();
+        assert_eq!(names, vec!["session.live", "drums/kick.live"]);
+        assert!(files[0].open);
+        assert_eq!(files[0].source, "play kick * 2;");
+        assert!(!files[1].open);
+    }
+
+    #[test]
+    fn test_search() {
+        // (the files are read already, but they're kept until the end)
+        let (_dir, files) = project();
+
+        let at = |query: &str| {
+            search(&files, query)
+                .into_iter()
+                .map(|hit| (hit.file, hit.row, hit.col))
+                .collect::<Vec<_>>()
+        };
+
+        // (ignoring case, unless there's an uppercase letter in the query)
+        assert_eq!(at("kick"), vec![(0, 0, 5), (1, 0, 4), (1, 1, 4)]);
+        assert_eq!(at("Kick"), vec![(1, 1, 4)]);
+        assert_eq!(at("snare"), vec![]);
+        assert_eq!(at(""), vec![]);
+    }
+
+    #[test]
+    fn test_replace() {
+        let (dir, files) = project();
+        let root = dir.path();
+
+        let replaced = replace(&files, "kick", "bd");
+        assert_eq!(
+            replaced
+                .iter()
+                .map(|(file, source, count)| (file.name.as_str(), source.as_str(), *count))
+                .collect::<Vec<_>>(),
+            vec![
+                ("session.live", "play bd * 2;", 1),
+                ("drums/kick.live", "let bd = 1;\nlet bd = 2;", 2),
+            ]
+        );
+        assert_eq!(replace_line("Kick kick", "Kick", "bd"), "bd kick");
+
+        // (the open document is up to the editor, the rest is written)
+        assert_eq!(
+            write_replaced(&replaced),
+            Written {
+                document: Some("play bd * 2;".into()),
+                matches: 3,
+                files: 2,
+                errors: vec![],
+            }
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("drums/kick.live")).unwrap(),
+            "let bd = 1;\nlet bd = 2;"
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("session.live")).unwrap(),
+            "play kick;"
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("old/kick.live")).unwrap(),
+            "let kick = 0;"
+        );
+    }
+}
@@ -0,0 +1,503 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use live_engine::{Bounce, Quantize, BEATS_PER_BAR, SAMPLE_RATE};
+use live_language::{clips, evaluate_source_in, overlay_statements, statement_at, syntax_errors};
+
+use crate::{
+    bounce::{write, CHUNK},
+    commands::EditorCommand,
+    compile::{Compiler, Samples},
+    nudged_swing,
+    project::ProjectFile,
+    SWING_STEP,
+};
+
+/// Where sessions are recorded, in the workspace (hidden, like the backups, so that searching the project skips it)
+pub const SESSIONS_DIR: &str = ".sessions";
+pub const SESSION_EXTENSION: &str = "session";
+/// (the first line of a session file)
+const HEADER: &str = "live session";
+
+/// Something that happened while performing, which is replayed
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionEvent {
+    /// The code in between two (byte) offsets was replaced
+    Edit {
+        start: usize,
+        end: usize,
+        text: String,
+    },
+    /// The caret moved (to a byte offset)
+    Caret(usize),
+    Command(EditorCommand),
+    /// A widget changed a parameter of what's playing
+    Param(String, f32),
+}
+
+/// (so that an edit fits on a line)
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = text.chars();
+
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            unescaped.push(ch);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some(ch) => unescaped.push(ch),
+            None => {}
+        }
+    }
+
+    unescaped
+}
+
+impl SessionEvent {
+    /**
+        As a line of a session file, like `edit 12 14 440hz`, or `command evaluate block` (commands by their name in the command palette)
+    */
+    fn encode(&self) -> String {
+        match self {
+            SessionEvent::Edit { start, end, text } => {
+                format!("edit {} {} {}", start, end, escape(text))
+            }
+            SessionEvent::Caret(offset) => format!("caret {}", offset),
+            SessionEvent::Command(command) => format!("command {}", command.name()),
+            SessionEvent::Param(name, value) => format!("param {} {}", name, value),
+        }
+    }
+
+    fn decode(line: &str) -> Option<Self> {
+        let (kind, rest) = line.split_once(' ')?;
+
+        match kind {
+            "edit" => {
+                let mut parts = rest.splitn(3, ' ');
+                Some(SessionEvent::Edit {
+                    start: parts.next()?.parse().ok()?,
+                    end: parts.next()?.parse().ok()?,
+                    text: unescape(parts.next().unwrap_or_default()),
+                })
+            }
+            "caret" => Some(SessionEvent::Caret(rest.parse().ok()?)),
+            "command" => EditorCommand::all()
+                .into_iter()
+                .find(|command| command.name() == rest)
+                .map(SessionEvent::Command),
+            "param" => {
+                let (name, value) = rest.rsplit_once(' ')?;
+                Some(SessionEvent::Param(name.into(), value.parse().ok()?))
+            }
+            _ => None,
+        }
+    }
+
+    /**
+        The smallest edit that turns the old code into the new: replacing what's in between what they start and end with
+    */
+    fn edit(old: &str, new: &str) -> Option<Self> {
+        if old == new {
+            return None;
+        }
+
+        // (backing up to where a character starts, which is the same in both, since it's the same up to there)
+        let mut start = old
+            .bytes()
+            .zip(new.bytes())
+            .take_while(|(a, b)| a == b)
+            .count();
+        while !old.is_char_boundary(start) {
+            start -= 1;
+        }
+
+        let mut same_end = old[start..]
+            .bytes()
+            .rev()
+            .zip(new[start..].bytes().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        while !old.is_char_boundary(old.len() - same_end) {
+            same_end -= 1;
+        }
+
+        Some(SessionEvent::Edit {
+            start,
+            end: old.len() - same_end,
+            text: new[start..new.len() - same_end].into(),
+        })
+    }
+}
+
+/**
+    Whether a replay performs the command again: the ones that change what's heard (the others show up as the edits they made, if any)
+*/
+pub fn replays(command: EditorCommand) -> bool {
+    matches!(
+        command,
+        EditorCommand::Evaluate
+            | EditorCommand::CycleQuantize
+            | EditorCommand::SwingLess
+            | EditorCommand::SwingMore
+            | EditorCommand::Hush
+            | EditorCommand::Panic
+    )
+}
+
+/**
+    Applies an edit to the code, if it fits (it doesn't when the code isn't what it was when the edit was recorded)
+*/
+pub fn apply_edit(source: &mut String, start: usize, end: usize, text: &str) -> bool {
+    if start > end
+        || end > source.len()
+        || !source.is_char_boundary(start)
+        || !source.is_char_boundary(end)
+    {
+        return false;
+    }
+
+    source.replace_range(start..end, text);
+    true
+}
+
+/**
+    A recorded session: the tempo and swing it was performed at, and everything that happened, timestamped (since the recording started). Recorded as a text file, like
+
+    ```text
+    live session
+    tempo 120 swing 0.5
+    0 edit 0 0 play sin(440hz);\n
+    1520 caret 13
+    2100 command evaluate block
+    3480 param fx.f 1200
+    ```
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Session {
+    pub tempo: f64,
+    pub swing: f64,
+    pub events: Vec<(Duration, SessionEvent)>,
+}
+
+impl Session {
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let mut lines = contents.lines().enumerate();
+
+        if lines.next().map(|(_, line)| line) != Some(HEADER) {
+            return Err("not a recorded session".into());
+        }
+
+        let (tempo, swing) = match lines.next() {
+            Some((_, line)) => match line.split(' ').collect::<Vec<_>>()[..] {
+                ["tempo", tempo, "swing", swing] => (tempo.parse().ok(), swing.parse().ok()),
+                _ => (None, None),
+            },
+            None => (None, None),
+        };
+
+        let (Some(tempo), Some(swing)) = (tempo, swing) else {
+            return Err("the session doesn't say its tempo and swing".into());
+        };
+
+        let event = |line: &str| {
+            let (ms, event) = line.split_once(' ')?;
+            Some((
+                Duration::from_millis(ms.parse().ok()?),
+                SessionEvent::decode(event)?,
+            ))
+        };
+
+        let events = lines
+            .filter(|(_, line)| !line.is_empty())
+            .map(|(i, line)| {
+                event(line).ok_or_else(|| format!("invalid session line {}: {:?}", i + 1, line))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            tempo,
+            swing,
+            events,
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+
+        Self::parse(&contents)
+    }
+
+    /// (until the last thing that happened)
+    pub fn duration(&self) -> Duration {
+        self.events.last().map_or(Duration::ZERO, |(at, _)| *at)
+    }
+}
+
+/**
+    Records a session while performing (Cmd+Shift+Y starts and stops it), into the workspace's `.sessions` folder: every edit, where the caret goes, the commands, and what widgets change about what's playing
+*/
+pub struct SessionRecorder {
+    pub path: PathBuf,
+    file: File,
+    started_at: Instant,
+    // (what the code and caret were the last time, to record what changed)
+    source: String,
+    caret: Option<usize>,
+}
+
+impl SessionRecorder {
+    pub fn start(root: &Path, tempo: f64, swing: f64) -> Result<Self, String> {
+        let dir = root.join(SESSIONS_DIR);
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("could not create {}: {}", dir.display(), e))?;
+
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let path = dir.join(format!("{}.{}", secs, SESSION_EXTENSION));
+
+        let mut file = File::create(&path)
+            .map_err(|e| format!("could not create {}: {}", path.display(), e))?;
+        writeln!(file, "{}\ntempo {} swing {}", HEADER, tempo, swing)
+            .map_err(|e| format!("could not write {}: {}", path.display(), e))?;
+
+        Ok(Self {
+            path,
+            file,
+            started_at: Instant::now(),
+            // (so that the code it starts with is the first edit)
+            source: String::new(),
+            caret: None,
+        })
+    }
+
+    /**
+        Records how the code changed since the last time (if it did), and where the caret went
+    */
+    pub fn sync(&mut self, source: &str, caret: usize) {
+        if let Some(edit) = SessionEvent::edit(&self.source, source) {
+            self.record(edit);
+            self.source = source.to_string();
+        }
+
+        if self.caret != Some(caret) {
+            self.record(SessionEvent::Caret(caret));
+            self.caret = Some(caret);
+        }
+    }
+
+    pub fn record(&mut self, event: SessionEvent) {
+        let ms = self.started_at.elapsed().as_millis();
+
+        // (written right away, so that nothing's lost if the editor crashes mid-performance)
+        if let Err(e) = writeln!(self.file, "{} {}", ms, event.encode()) {
+            tracing::warn!("Could not record session: {}", e);
+        }
+    }
+}
+
+/**
+    Plays a recorded session back in the editor, in real time: the edits appear as they were made, and the commands (the ones that change what's heard, like evaluating) and widget changes are performed again, for the engine to play
+*/
+pub struct SessionReplay {
+    session: Session,
+    started_at: Instant,
+    next: usize,
+    /// What the code should be by now (when it's not, it was edited along, and the replay can't go on)
+    pub source: String,
+}
+
+impl SessionReplay {
+    pub fn start(session: Session) -> Self {
+        Self {
+            session,
+            started_at: Instant::now(),
+            next: 0,
+            source: String::new(),
+        }
+    }
+
+    pub fn tempo(&self) -> f64 {
+        self.session.tempo
+    }
+
+    pub fn swing(&self) -> f64 {
+        self.session.swing
+    }
+
+    /**
+        What happened by now (since the last time)
+    */
+    pub fn due(&mut self) -> Vec<SessionEvent> {
+        let elapsed = self.started_at.elapsed();
+
+        let due = self.session.events[self.next..]
+            .iter()
+            .take_while(|(at, _)| *at <= elapsed)
+            .map(|(_, event)| event.clone())
+            .collect::<Vec<_>>();
+
+        self.next += due.len();
+        due
+    }
+
+    pub fn due_at(&self) -> Option<Instant> {
+        let (at, _) = self.session.events.get(self.next)?;
+        Some(self.started_at + *at)
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.next >= self.session.events.len()
+    }
+}
+
+/**
+    Renders a recorded session into a WAV (or FLAC) file, offline (for `live render`), for as long as it lasted: the code is edited and evaluated again like it was (see `Performance`), and the widgets change what they changed, when they did
+*/
+pub fn render_session(
+    session: &Session,
+    root: &Path,
+    path: &Path,
+    mut progress: impl FnMut(f32),
+) -> Result<(), String> {
+    let seconds = session.duration().as_secs_f64();
+    let bars = seconds * session.tempo / 60.0 / BEATS_PER_BAR;
+
+    let mut bounce = Bounce::new(bars, session.tempo);
+    bounce.set_swing(session.swing);
+
+    let mut performance = Performance::new(root, session.swing);
+    let mut events = session.events.iter().peekable();
+
+    while !bounce.is_done() {
+        let rendered = Duration::from_secs_f64(bounce.samples().len() as f64 / SAMPLE_RATE as f64);
+        while let Some((_, event)) = events.next_if(|(at, _)| *at <= rendered) {
+            performance.perform(event, &mut bounce)?;
+        }
+
+        progress(bounce.render(CHUNK));
+    }
+
+    write(&bounce, path)
+}
+
+/**
+    What the code of a session that's rendered is by now, and what it plays, like it was in the editor (see `Editor::evaluate`). There are no widgets, so the code that uses them can't be played.
+*/
+struct Performance<'a> {
+    root: &'a Path,
+    source: String,
+    caret: usize,
+    // (the statements as they were last evaluated, see `overlay_statements`)
+    live: String,
+    played: Vec<String>,
+    quantize: Quantize,
+    swing: f64,
+    hush: Duration,
+    samples: Samples,
+}
+
+impl<'a> Performance<'a> {
+    fn new(root: &'a Path, swing: f64) -> Self {
+        Self {
+            root,
+            source: String::new(),
+            caret: 0,
+            live: String::new(),
+            played: vec![],
+            quantize: Quantize::Now,
+            swing,
+            hush: ProjectFile::load(root).hush(),
+            samples: Samples::default(),
+        }
+    }
+
+    /// (the commands that a replay performs, see `replays`)
+    fn perform(&mut self, event: &SessionEvent, bounce: &mut Bounce) -> Result<(), String> {
+        match event {
+            SessionEvent::Edit { start, end, text } => {
+                if !apply_edit(&mut self.source, *start, *end, text) {
+                    return Err("an edit doesn't fit the code".into());
+                }
+            }
+            SessionEvent::Caret(offset) => self.caret = *offset,
+            SessionEvent::Command(EditorCommand::Evaluate) => self.evaluate(bounce),
+            SessionEvent::Command(EditorCommand::CycleQuantize) => {
+                self.quantize = self.quantize.next();
+                bounce.set_quantize(self.quantize);
+            }
+            SessionEvent::Command(EditorCommand::SwingLess) => {
+                self.swing = nudged_swing(self.swing, -SWING_STEP);
+                bounce.set_swing(self.swing);
+            }
+            SessionEvent::Command(EditorCommand::SwingMore) => {
+                self.swing = nudged_swing(self.swing, SWING_STEP);
+                bounce.set_swing(self.swing);
+            }
+            SessionEvent::Command(EditorCommand::Hush) => bounce.hush(self.hush),
+            SessionEvent::Command(EditorCommand::Panic) => {
+                bounce.panic();
+                self.played.clear();
+            }
+            SessionEvent::Command(_) => {}
+            SessionEvent::Param(name, value) => bounce.set_param(name.as_str(), *value),
+        }
+
+        Ok(())
+    }
+
+    /// (the statement the caret is in, because what was selected isn't recorded)
+    fn evaluate(&mut self, bounce: &mut Bounce) {
+        let Some(span) = statement_at(&self.source, self.caret) else {
+            return;
+        };
+        if !syntax_errors(&self.source[span.range()]).is_empty() {
+            return;
+        }
+
+        self.live = overlay_statements(&self.live, &self.source, &[span.range()]);
+        let evaluation = evaluate_source_in(&self.live, 0, self.root);
+
+        let widgets = HashMap::new();
+        let (targets, errors) = Compiler::new(&*bounce, self.root, &widgets, &mut self.samples)
+            .compile(&evaluation, &self.live);
+        for (name, message) in errors {
+            tracing::warn!("Could not play {}: {}", name, message);
+        }
+
+        // (the clips are only heard while they're launched, which isn't recorded)
+        let clips = clips(&self.live);
+        let targets = targets
+            .into_iter()
+            .filter(|target| !clips.iter().any(|clip| clip.target == target.name))
+            .collect::<Vec<_>>();
+
+        for name in &self.played {
+            if !targets.iter().any(|target| target.name == *name) {
+                bounce.stop(name.as_str());
+            }
+        }
+        self.played = targets.iter().map(|target| target.name.clone()).collect();
+
+        for target in targets {
+            match self.quantize {
+                Quantize::Now => bounce.play(target.name, target.node),
+                _ => bounce.schedule(target.name, target.node),
+            }
+        }
+    }
+}
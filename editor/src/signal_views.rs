@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+
+use live_editor_state::{EditorState, LineData, Pos, Range, Token};
+use live_engine::EngineHandle;
+use live_language::{signal_views, SignalViewKind};
+
+use crate::{util::loc_to_pos, widget::WidgetManager, widgets::scope::ScopeWidget};
+
+/**
+    Keeps a live view widget right after every `scope(name)` and `spectrum(name)` call in the code: spawns one when a call appears, and replaces it when the call changes. (Widgets of calls that were removed are left alone, they just turn into regular tokens.)
+*/
+pub struct SignalViews {
+    source: Option<String>,
+    // what every view widget we spawned is showing
+    bound: HashMap<usize, (SignalViewKind, String)>,
+}
+
+/**
+    The view widget right after `pos` (with a space in between), and where it is
+*/
+fn widget_after(linedata: &LineData, pos: Pos) -> Option<(Pos, usize, usize)> {
+    let line = linedata.lines().get(pos.row as usize)?;
+
+    let mut col = 0;
+    let mut tokens = line.iter().skip_while(|token| {
+        let before = col < pos.col;
+        col += token.width() as i32;
+        before
+    });
+
+    match (tokens.next(), tokens.next()) {
+        (Some(Token::Char(' ')), Some(Token::Widget(info)))
+            if info.kind == SignalViewKind::Scope.name()
+                || info.kind == SignalViewKind::Spectrum.name() =>
+        {
+            Some((
+                Pos {
+                    row: pos.row,
+                    col: pos.col + 1,
+                },
+                info.id,
+                info.width,
+            ))
+        }
+        _ => None,
+    }
+}
+
+impl SignalViews {
+    pub fn new() -> Self {
+        Self {
+            source: None,
+            bound: HashMap::new(),
+        }
+    }
+
+    pub fn sync(
+        &mut self,
+        editor_state: &mut EditorState,
+        widget_manager: &mut WidgetManager,
+        engine: Option<&EngineHandle>,
+    ) {
+        let source = editor_state.linedata().to_string();
+        if self.source.as_ref() == Some(&source) {
+            return;
+        }
+
+        // back to front, so that spawning widgets doesn't move the calls we still have to look at
+        for view in signal_views(&source).into_iter().rev() {
+            let linedata = editor_state.linedata();
+            let end = loc_to_pos(linedata, view.range.end);
+            let wanted = (view.kind, view.target);
+
+            match widget_after(linedata, end) {
+                Some((_, id, _)) if self.bound.get(&id) == Some(&wanted) => continue,
+                Some((pos, _, width)) => {
+                    editor_state.remove(Range {
+                        start: pos,
+                        end: Pos {
+                            row: pos.row,
+                            col: pos.col + width as i32,
+                        },
+                    });
+                }
+                None => {
+                    editor_state.insert(end, " ".into(), false);
+                }
+            }
+
+            let (kind, target) = wanted;
+            let tap = engine.map(|engine| engine.tap(&target));
+            let info = widget_manager.add(Box::new(ScopeWidget::new(kind, &target, tap)));
+
+            editor_state.insert(
+                Pos {
+                    row: end.row,
+                    col: end.col + 1,
+                },
+                vec![Token::Widget(info)].into(),
+                false,
+            );
+
+            self.bound.insert(info.id, (kind, target));
+        }
+
+        self.source = Some(editor_state.linedata().to_string());
+    }
+}
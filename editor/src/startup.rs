@@ -0,0 +1,145 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::thread;
+use std::{
+    sync::mpsc::{channel, Receiver, TryRecvError},
+    time::{Duration, Instant},
+};
+
+use crate::invalidation::Invalidator;
+
+/**
+    Times the phases of starting up, up to the first frame, to see what keeps the window from appearing. Logged (to the console, under "startup") together with when each of the things that load in the background (see `Loading`) came in.
+*/
+pub struct StartupProfile {
+    started_at: Instant,
+    phase_started_at: Instant,
+    phases: Vec<(&'static str, Duration)>,
+    reported: bool,
+}
+
+impl StartupProfile {
+    pub fn start() -> Self {
+        let now = Instant::now();
+
+        Self {
+            started_at: now,
+            phase_started_at: now,
+            phases: vec![],
+            reported: false,
+        }
+    }
+
+    /**
+        Marks the end of a phase (which started where the previous one ended)
+    */
+    pub fn phase(&mut self, name: &'static str) {
+        let now = Instant::now();
+        self.phases.push((name, now - self.phase_started_at));
+        self.phase_started_at = now;
+    }
+
+    /**
+        Call after every frame, it only reports after the first one
+    */
+    pub fn first_frame(&mut self) {
+        if self.reported {
+            return;
+        }
+
+        self.phase("first frame");
+        self.reported = true;
+
+        for (name, duration) in &self.phases {
+            tracing::debug!(target: "startup", "{} took {:.1}ms", name, ms(*duration));
+        }
+        tracing::info!(
+            target: "startup",
+            "first frame after {:.1}ms",
+            ms(self.started_at.elapsed())
+        );
+    }
+}
+
+fn ms(duration: Duration) -> f32 {
+    duration.as_secs_f32() * 1000.0
+}
+
+/**
+    Something that's initialized on a background thread, so that startup doesn't have to wait for it. The event loop is woken up when it's done, and it's picked up with `poll`.
+*/
+pub struct Loading<T> {
+    // what's going on, for the status bar
+    label: &'static str,
+    started_at: Instant,
+    receiver: Option<Receiver<T>>,
+}
+
+impl<T: Send + 'static> Loading<T> {
+    pub fn spawn(
+        label: &'static str,
+        invalidator: Invalidator,
+        f: impl FnOnce() -> T + Send + 'static,
+    ) -> Self {
+        Self::spawn_with(label, invalidator, move |done| done(f()))
+    }
+
+    /**
+        For things that have to stay on the background thread once they're done (like the audio stream, which can't be moved between threads on every platform): `f` gets a function to hand over the result with, and can keep going after that
+
+        (In the browser, there are no threads to spawn, so `f` runs right away, and shouldn't keep going after it's handed over the result.)
+    */
+    pub fn spawn_with(
+        label: &'static str,
+        invalidator: Invalidator,
+        f: impl FnOnce(Box<dyn FnOnce(T)>) + Send + 'static,
+    ) -> Self {
+        let (sender, receiver) = channel();
+        let run = move || {
+            f(Box::new(move |value| {
+                let _ = sender.send(value);
+                invalidator.invalidate();
+            }))
+        };
+
+        #[cfg(not(target_arch = "wasm32"))]
+        thread::spawn(run);
+        #[cfg(target_arch = "wasm32")]
+        run();
+
+        Self {
+            label,
+            started_at: Instant::now(),
+            receiver: Some(receiver),
+        }
+    }
+
+    /**
+        What's still loading, if it is
+    */
+    pub fn label(&self) -> Option<&'static str> {
+        self.receiver.as_ref().map(|_| self.label)
+    }
+
+    /**
+        The result, the (first) time it's called after it came in
+    */
+    pub fn poll(&mut self) -> Option<T> {
+        let value = match self.receiver.as_ref()?.try_recv() {
+            Ok(value) => Some(value),
+            Err(TryRecvError::Empty) => return None,
+            // (the thread panicked)
+            Err(TryRecvError::Disconnected) => None,
+        };
+
+        self.receiver = None;
+
+        tracing::debug!(
+            target: "startup",
+            "{} done after {:.1}ms",
+            self.label,
+            ms(self.started_at.elapsed())
+        );
+
+        value
+    }
+}
@@ -0,0 +1,205 @@
+use std::time::{Duration, Instant};
+
+use live_engine::MasterLevel;
+
+use crate::render::Overlay;
+
+pub const STATUS_BAR_HEIGHT: f32 = 24.0;
+
+/// How long the clip warning stays up after the last clip
+const CLIP_WARNING_DURATION: Duration = Duration::from_secs(2);
+
+/// How long notices (like "hushed") stay up
+const NOTICE_DURATION: Duration = Duration::from_secs(2);
+
+/// The meter shows -60 dB .. 0 dB
+const METER_RANGE_DB: f32 = 60.0;
+const METER_WIDTH: f32 = 160.0;
+const METER_HEIGHT: f32 = 8.0;
+const MARGIN: f32 = 12.0;
+const FONT_SIZE: f32 = 13.0;
+
+const BAR_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.05];
+const TEXT_COLOR: [f32; 4] = [0.02, 0.02, 0.02, 1.0];
+const DIM_TEXT_COLOR: [f32; 4] = [0.02, 0.02, 0.02, 0.45];
+const METER_TRACK_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.1];
+const METER_COLOR: [f32; 4] = [0.0, 0.6, 0.3, 1.0];
+const METER_HOT_COLOR: [f32; 4] = [0.8, 0.45, 0.0, 1.0];
+const CLIP_COLOR: [f32; 4] = [0.8, 0.1, 0.1, 1.0];
+const CLIP_BAR_COLOR: [f32; 4] = [0.8, 0.1, 0.1, 0.15];
+
+fn to_db(amplitude: f32) -> f32 {
+    20.0 * amplitude.log10()
+}
+
+/**
+    The bar along the bottom of the window, showing the master output level (in dB, before the limiter), and a warning whenever the output clipped.
+*/
+pub struct StatusBar {
+    // `None` if there's no audio output at all
+    master: Option<MasterLevel>,
+    clips_seen: usize,
+    clipped_at: Option<Instant>,
+    // the musical typing octave, while that's on
+    octave: Option<i32>,
+    // what's still loading in the background (right after startup)
+    loading: Vec<String>,
+    // a confirmation of something that happened, like stopping all sound, and when
+    notice: Option<(String, Instant)>,
+}
+
+impl StatusBar {
+    pub fn new() -> Self {
+        Self {
+            master: None,
+            clips_seen: 0,
+            clipped_at: None,
+            octave: None,
+            loading: vec![],
+            notice: None,
+        }
+    }
+
+    pub fn update(&mut self, master: Option<MasterLevel>) {
+        if let Some(master) = master && master.clips > self.clips_seen {
+            self.clips_seen = master.clips;
+            self.clipped_at = Some(Instant::now());
+        }
+
+        self.master = master;
+    }
+
+    pub fn set_octave(&mut self, octave: Option<i32>) {
+        self.octave = octave;
+    }
+
+    pub fn set_loading(&mut self, loading: Vec<String>) {
+        self.loading = loading;
+    }
+
+    /**
+        Shows a message for a moment
+    */
+    pub fn notify(&mut self, notice: impl Into<String>) {
+        self.notice = Some((notice.into(), Instant::now()));
+    }
+
+    fn notice(&self) -> Option<&str> {
+        self.notice
+            .as_ref()
+            .filter(|(_, at)| at.elapsed() < NOTICE_DURATION)
+            .map(|(notice, _)| notice.as_str())
+    }
+
+    fn clipping(&self) -> bool {
+        self.clipped_at
+            .map_or(false, |t| t.elapsed() < CLIP_WARNING_DURATION)
+    }
+
+    /**
+        Whether the meter's moving (or the clip warning or a notice still has to disappear), which means we have to keep drawing
+    */
+    pub fn animating(&self) -> bool {
+        self.master.map_or(false, |master| master.level.peak > 0.0)
+            || self.clipping()
+            || self.notice().is_some()
+    }
+
+    pub fn draw(&self, (width, height): (f32, f32), overlay: &mut Overlay) {
+        let min_y = height - STATUS_BAR_HEIGHT;
+        let text_y = min_y + (STATUS_BAR_HEIGHT - FONT_SIZE) / 2.0;
+
+        overlay.quad((0.0, min_y, width, height), BAR_COLOR);
+
+        if let Some(octave) = self.octave {
+            overlay.bold_text((MARGIN, text_y), "♪", FONT_SIZE, TEXT_COLOR);
+            overlay.text(
+                (MARGIN + 18.0, text_y),
+                format!("musical typing  C{}  (-/= octave)", octave),
+                FONT_SIZE,
+                TEXT_COLOR,
+            );
+        }
+
+        let x = if self.octave.is_some() { 280.0 } else { MARGIN };
+
+        if let Some(notice) = self.notice() {
+            overlay.bold_text((x, text_y), notice, FONT_SIZE, TEXT_COLOR);
+        } else if !self.loading.is_empty() {
+            overlay.text(
+                (x, text_y),
+                format!("{}…", self.loading.join(", ")),
+                FONT_SIZE,
+                DIM_TEXT_COLOR,
+            );
+        }
+
+        let Some(master) = self.master else {
+            // (the audio might just not have started yet)
+            if !self.loading.is_empty() {
+                return;
+            }
+
+            overlay.text(
+                (width - MARGIN - METER_WIDTH, text_y),
+                "no audio output",
+                FONT_SIZE,
+                DIM_TEXT_COLOR,
+            );
+            return;
+        };
+
+        let clipping = self.clipping();
+        if clipping {
+            overlay.quad((0.0, min_y, width, height), CLIP_BAR_COLOR);
+        }
+
+        // the meter
+        let db = to_db(master.level.peak);
+        let fraction = ((db + METER_RANGE_DB) / METER_RANGE_DB).clamp(0.0, 1.0);
+
+        let meter_x = width - MARGIN - METER_WIDTH;
+        let meter_y = min_y + (STATUS_BAR_HEIGHT - METER_HEIGHT) / 2.0;
+
+        overlay.quad(
+            (
+                meter_x,
+                meter_y,
+                meter_x + METER_WIDTH,
+                meter_y + METER_HEIGHT,
+            ),
+            METER_TRACK_COLOR,
+        );
+
+        overlay.quad(
+            (
+                meter_x,
+                meter_y,
+                meter_x + fraction * METER_WIDTH,
+                meter_y + METER_HEIGHT,
+            ),
+            if db >= 0.0 {
+                CLIP_COLOR
+            } else if db >= -6.0 {
+                METER_HOT_COLOR
+            } else {
+                METER_COLOR
+            },
+        );
+
+        overlay.text(
+            (meter_x - 80.0, text_y),
+            if db > -METER_RANGE_DB {
+                format!("{:>5.1} dB", db)
+            } else {
+                "  -∞ dB".into()
+            },
+            FONT_SIZE,
+            TEXT_COLOR,
+        );
+
+        if clipping {
+            overlay.bold_text((meter_x - 130.0, text_y), "CLIP", FONT_SIZE, CLIP_COLOR);
+        }
+    }
+}
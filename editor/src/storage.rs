@@ -0,0 +1,108 @@
+use std::path::{Path, PathBuf};
+
+/**
+    Where the editor keeps its text files: the backups, and the config (see `config_dir`). On disk, or in the browser's local storage, by their paths, where there's no disk.
+*/
+pub trait Storage {
+    fn read(&self, path: &Path) -> Result<String, String>;
+
+    /// (making the directories it's in, if they're not there yet)
+    fn write(&self, path: &Path, contents: &str) -> Result<(), String>;
+
+    fn remove(&self, path: &Path) -> Result<(), String>;
+
+    /// The files that are right in a directory (none, when it's not there)
+    fn list(&self, dir: &Path) -> Vec<PathBuf>;
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub struct Disk;
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Storage for Disk {
+    fn read(&self, path: &Path) -> Result<String, String> {
+        std::fs::read_to_string(path).map_err(|e| e.to_string())
+    }
+
+    fn write(&self, path: &Path, contents: &str) -> Result<(), String> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+
+        std::fs::write(path, contents).map_err(|e| e.to_string())
+    }
+
+    fn remove(&self, path: &Path) -> Result<(), String> {
+        std::fs::remove_file(path).map_err(|e| e.to_string())
+    }
+
+    fn list(&self, dir: &Path) -> Vec<PathBuf> {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return vec![];
+        };
+
+        entries
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| path.is_file())
+            .collect()
+    }
+}
+
+/**
+    The browser's `localStorage`, with a file's path as its key (so there are no directories, really, just keys that look like they're in one)
+*/
+#[cfg(target_arch = "wasm32")]
+pub struct LocalStorage;
+
+#[cfg(target_arch = "wasm32")]
+impl LocalStorage {
+    fn storage(&self) -> Result<web_sys::Storage, String> {
+        web_sys::window()
+            .and_then(|window| window.local_storage().ok().flatten())
+            .ok_or_else(|| "there's no local storage".to_string())
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Storage for LocalStorage {
+    fn read(&self, path: &Path) -> Result<String, String> {
+        self.storage()?
+            .get_item(&path.to_string_lossy())
+            .ok()
+            .flatten()
+            .ok_or_else(|| format!("{} isn't there", path.display()))
+    }
+
+    fn write(&self, path: &Path, contents: &str) -> Result<(), String> {
+        // (it's refused when the storage is full, which is only a few megabytes)
+        self.storage()?
+            .set_item(&path.to_string_lossy(), contents)
+            .map_err(|_| format!("could not store {}", path.display()))
+    }
+
+    fn remove(&self, path: &Path) -> Result<(), String> {
+        self.storage()?
+            .remove_item(&path.to_string_lossy())
+            .map_err(|_| format!("could not remove {}", path.display()))
+    }
+
+    fn list(&self, dir: &Path) -> Vec<PathBuf> {
+        let Ok(storage) = self.storage() else {
+            return vec![];
+        };
+
+        (0..storage.length().unwrap_or(0))
+            .filter_map(|i| storage.key(i).ok().flatten())
+            .map(PathBuf::from)
+            .filter(|path| path.parent() == Some(dir))
+            .collect()
+    }
+}
+
+pub fn storage() -> &'static dyn Storage {
+    #[cfg(not(target_arch = "wasm32"))]
+    return &Disk;
+
+    #[cfg(target_arch = "wasm32")]
+    return &LocalStorage;
+}
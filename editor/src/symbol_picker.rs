@@ -0,0 +1,182 @@
+use live_editor_state::Pos;
+
+use crate::{
+    fuzzy::fuzzy_match,
+    outline::{kind_label, Outline},
+    render::Overlay,
+};
+
+const PICKER_WIDTH: f32 = 440.0;
+const PICKER_TOP: f32 = 64.0;
+const INPUT_HEIGHT: f32 = 36.0;
+const ROW_HEIGHT: f32 = 26.0;
+const MAX_ROWS: usize = 12;
+const FONT_SIZE: f32 = 15.0;
+
+const BACKDROP_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.08];
+const PICKER_COLOR: [f32; 4] = [0.99, 0.99, 0.98, 1.0];
+const SELECTED_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.08];
+const TEXT_COLOR: [f32; 4] = [0.02, 0.02, 0.02, 1.0];
+const DIM_TEXT_COLOR: [f32; 4] = [0.02, 0.02, 0.02, 0.45];
+
+/**
+    The Cmd+Shift+O "go to symbol" picker: fuzzy filters the outline by name.
+*/
+pub struct SymbolPicker {
+    open: bool,
+    query: String,
+    selected: usize,
+}
+
+impl SymbolPicker {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            query: String::new(),
+            selected: 0,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn open(&mut self) {
+        self.open = true;
+        self.query.clear();
+        self.selected = 0;
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+    }
+
+    pub fn type_str(&mut self, s: &str) {
+        self.query.push_str(s);
+        self.selected = 0;
+    }
+
+    pub fn backspace(&mut self) {
+        self.query.pop();
+        self.selected = 0;
+    }
+
+    pub fn move_selection(&mut self, outline: &Outline, delta: i32) {
+        let n = self.matches(outline).len() as i32;
+        if n > 0 {
+            self.selected = (self.selected as i32 + delta).rem_euclid(n) as usize;
+        }
+    }
+
+    /**
+        Indices into the outline's entries, best match first
+    */
+    fn matches(&self, outline: &Outline) -> Vec<usize> {
+        let mut matches = outline
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(i, entry)| Some((i, fuzzy_match(&self.query, &entry.symbol.name)?)))
+            .collect::<Vec<_>>();
+
+        // stable, so equally good matches stay in document order
+        matches.sort_by_key(|&(_, score)| -score);
+
+        matches.into_iter().map(|(i, _)| i).take(MAX_ROWS).collect()
+    }
+
+    pub fn selected_pos(&self, outline: &Outline) -> Option<Pos> {
+        let i = *self.matches(outline).get(self.selected)?;
+        Some(outline.entries[i].pos)
+    }
+
+    fn bounds(&self, outline: &Outline, (width, _): (f32, f32)) -> (f32, f32, f32, f32) {
+        let rows = self.matches(outline).len().max(1);
+        let min_x = ((width - PICKER_WIDTH) / 2.0).max(0.0);
+
+        (
+            min_x,
+            PICKER_TOP,
+            min_x + PICKER_WIDTH,
+            PICKER_TOP + INPUT_HEIGHT + rows as f32 * ROW_HEIGHT + 6.0,
+        )
+    }
+
+    /**
+        Which match was clicked, if any. (`None` if the click was outside of the picker.)
+    */
+    pub fn hit_test(
+        &self,
+        outline: &Outline,
+        window_size: (f32, f32),
+        (x, y): (f32, f32),
+    ) -> Option<Option<Pos>> {
+        let (min_x, min_y, max_x, max_y) = self.bounds(outline, window_size);
+        if x < min_x || x > max_x || y < min_y || y > max_y {
+            return None;
+        }
+
+        let i = ((y - min_y - INPUT_HEIGHT) / ROW_HEIGHT).floor();
+        if i < 0.0 {
+            return Some(None);
+        }
+
+        Some(
+            self.matches(outline)
+                .get(i as usize)
+                .map(|&i| outline.entries[i].pos),
+        )
+    }
+
+    pub fn draw(&self, outline: &Outline, window_size: (f32, f32), overlay: &mut Overlay) {
+        let (min_x, min_y, max_x, max_y) = self.bounds(outline, window_size);
+        let text_y = |top: f32, height: f32| top + (height - FONT_SIZE) / 2.0;
+
+        overlay.quad((0.0, 0.0, window_size.0, window_size.1), BACKDROP_COLOR);
+        overlay.quad((min_x, min_y, max_x, max_y), PICKER_COLOR);
+
+        overlay.text(
+            (min_x + 12.0, text_y(min_y, INPUT_HEIGHT)),
+            format!("@ {}", self.query),
+            FONT_SIZE,
+            TEXT_COLOR,
+        );
+
+        let matches = self.matches(outline);
+
+        if matches.is_empty() {
+            overlay.text(
+                (min_x + 12.0, text_y(min_y + INPUT_HEIGHT, ROW_HEIGHT)),
+                "no matching symbols",
+                FONT_SIZE,
+                DIM_TEXT_COLOR,
+            );
+        }
+
+        for (row, &i) in matches.iter().enumerate() {
+            let entry = &outline.entries[i];
+            let top = min_y + INPUT_HEIGHT + row as f32 * ROW_HEIGHT;
+            let y = text_y(top, ROW_HEIGHT);
+
+            if row == self.selected {
+                overlay.quad((min_x, top, max_x, top + ROW_HEIGHT), SELECTED_COLOR);
+            }
+
+            overlay.text(
+                (min_x + 12.0, y),
+                kind_label(entry.symbol.kind),
+                FONT_SIZE,
+                DIM_TEXT_COLOR,
+            );
+
+            overlay.text((min_x + 56.0, y), &entry.symbol.name, FONT_SIZE, TEXT_COLOR);
+
+            overlay.text(
+                (max_x - 48.0, y),
+                format!("{}", entry.pos.row + 1),
+                FONT_SIZE,
+                DIM_TEXT_COLOR,
+            );
+        }
+    }
+}
@@ -34,6 +34,79 @@ pub enum WidgetEvent {
         // todo add more
     },
     MouseUp,
+
+    // keyboard-driven manipulation: Enter (or clicking) focuses a widget, arrow keys adjust its primary value, Esc gives focus back to the text
+    Focus,
+    Unfocus,
+    Adjust {
+        // positive is up/right, and modifiers change the step size (shift = 10, alt = 0.1)
+        steps: f32,
+    },
+    // a key that's pressed while the widget is focused, for widgets that do more with the keyboard than adjusting one value (arrows that aren't handled fall back to `Adjust`)
+    Key {
+        key: WidgetKey,
+        shift: bool,
+        alt: bool,
+        meta_or_ctrl: bool,
+    },
+
+    // something picked from the widget's context menu (see `Widget::actions`)
+    Action(WidgetAction),
+}
+
+/**
+    What can be picked from a widget's context menu (right-clicking it). The widget does the ones it offers itself, the editor does the ones that change the code (see `WidgetManager::actions`).
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WidgetAction {
+    RevealFile,
+    ReplaceFile,
+    ConvertToReference,
+    Remove,
+}
+
+impl WidgetAction {
+    pub fn label(&self) -> &'static str {
+        match self {
+            #[cfg(target_os = "macos")]
+            Self::RevealFile => "reveal in Finder",
+            #[cfg(not(target_os = "macos"))]
+            Self::RevealFile => "show in folder",
+            Self::ReplaceFile => "replace file…",
+            Self::ConvertToReference => "convert to reference",
+            Self::Remove => "remove",
+        }
+    }
+}
+
+/**
+    The keys that a focused widget gets (Esc is the editor's, it takes the focus back)
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WidgetKey {
+    Up,
+    Down,
+    Left,
+    Right,
+    Enter,
+    Space,
+    Backspace,
+    Char(char),
+}
+
+impl WidgetKey {
+    pub fn is_arrow(&self) -> bool {
+        matches!(self, Self::Up | Self::Down | Self::Left | Self::Right)
+    }
+
+    // positive is up/right, like `Adjust`
+    pub fn direction(&self) -> f32 {
+        match self {
+            Self::Up | Self::Right => 1.0,
+            Self::Down | Self::Left => -1.0,
+            _ => 0.0,
+        }
+    }
 }
 
 impl WidgetEvent {
@@ -0,0 +1,427 @@
+use std::{
+    fs,
+    io::Read,
+    path::Path,
+    sync::mpsc::{channel, Receiver},
+    thread,
+};
+
+use rfd::{MessageButtons, MessageDialog, MessageLevel};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::{invalidation::Invalidator, util::config_dir};
+
+const LATEST_RELEASE_URL: &str =
+    "https://api.github.com/repos/kelleyvanevert/rust_live/releases/latest";
+/// Each build is published with its SHA-256 next to it, as `<build>.sha256`
+const CHECKSUM_EXTENSION: &str = ".sha256";
+/// (these would have to be unpacked, which is left to the user)
+const ARCHIVE_EXTENSIONS: [&str; 4] = [".zip", ".tar.gz", ".tgz", ".dmg"];
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Release {
+    pub tag_name: String,
+    pub html_url: String,
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub assets: Vec<ReleaseAsset>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReleaseAsset {
+    pub name: String,
+    pub browser_download_url: String,
+}
+
+impl Release {
+    pub fn version(&self) -> &str {
+        self.tag_name.trim_start_matches('v')
+    }
+
+    // the build for this machine, if there is one
+    fn asset(&self) -> Option<&ReleaseAsset> {
+        self.assets.iter().find(|asset| {
+            asset.name.contains(std::env::consts::OS)
+                && asset.name.contains(std::env::consts::ARCH)
+                && !asset.name.ends_with(CHECKSUM_EXTENSION)
+        })
+    }
+
+    // the published checksum of a build
+    fn checksum(&self, asset: &ReleaseAsset) -> Option<&ReleaseAsset> {
+        let name = format!("{}{}", asset.name, CHECKSUM_EXTENSION);
+        self.assets.iter().find(|checksum| checksum.name == name)
+    }
+}
+
+impl ReleaseAsset {
+    fn is_archive(&self) -> bool {
+        ARCHIVE_EXTENSIONS
+            .iter()
+            .any(|extension| self.name.ends_with(extension))
+    }
+}
+
+/**
+    Checks (once, in the background) whether there's a newer release than the one that's running.
+
+    This is opt-in, because we don't want to phone home by default: either set `LIVE_CHECK_FOR_UPDATES=1`, or create an empty `check_for_updates` file in the config dir.
+*/
+pub struct UpdateChecker {
+    receiver: Option<Receiver<Release>>,
+    available: Option<Release>,
+}
+
+impl UpdateChecker {
+    pub fn start(invalidator: Invalidator) -> Self {
+        if !Self::enabled() {
+            return Self {
+                receiver: None,
+                available: None,
+            };
+        }
+
+        let (sender, receiver) = channel();
+
+        thread::spawn(move || match fetch_latest_release() {
+            Ok(release) => {
+                if is_newer(release.version(), env!("CARGO_PKG_VERSION")) {
+                    let _ = sender.send(release);
+                    // so that the badge shows up, even if nothing else is happening
+                    invalidator.invalidate();
+                }
+            }
+            Err(e) => {
+                tracing::warn!("Could not check for updates: {}", e);
+            }
+        });
+
+        Self {
+            receiver: Some(receiver),
+            available: None,
+        }
+    }
+
+    fn enabled() -> bool {
+        std::env::var("LIVE_CHECK_FOR_UPDATES").is_ok_and(|v| v == "1")
+            || config_dir().is_some_and(|dir| dir.join("check_for_updates").exists())
+    }
+
+    // call every now and then (it doesn't block)
+    pub fn poll(&mut self) -> Option<&Release> {
+        if let Some(receiver) = &self.receiver && let Ok(release) = receiver.try_recv() {
+            self.available = Some(release);
+            self.receiver = None;
+        }
+
+        self.available.as_ref()
+    }
+
+    // the (non-intrusive) badge we show in the window title
+    pub fn badge(&self) -> Option<String> {
+        self.available
+            .as_ref()
+            .map(|release| format!("update available: v{}", release.version()))
+    }
+
+    /**
+        Shows the changelog, and offers to download and apply the update. Returns whether the update was applied, in which case the editor needs to be restarted.
+    */
+    pub fn show_changelog(&self) -> bool {
+        let Some(release) = &self.available else {
+            MessageDialog::new()
+                .set_level(MessageLevel::Info)
+                .set_title("No updates")
+                .set_description(&format!(
+                    "You're running the latest version (v{})",
+                    env!("CARGO_PKG_VERSION")
+                ))
+                .set_buttons(MessageButtons::Ok)
+                .show();
+
+            return false;
+        };
+
+        let changelog = release
+            .body
+            .clone()
+            .unwrap_or_else(|| "(no changelog)".into());
+
+        let Some(asset) = release.asset() else {
+            MessageDialog::new()
+                .set_level(MessageLevel::Info)
+                .set_title(&format!("Live v{} is available", release.version()))
+                .set_description(&format!(
+                    "{}\n\nThere's no build for this platform, see {}",
+                    changelog, release.html_url
+                ))
+                .set_buttons(MessageButtons::Ok)
+                .show();
+
+            return false;
+        };
+
+        if asset.is_archive() {
+            MessageDialog::new()
+                .set_level(MessageLevel::Info)
+                .set_title(&format!("Live v{} is available", release.version()))
+                .set_description(&format!(
+                    "{}\n\nThe build for this platform ({}) has to be unpacked, see {}",
+                    changelog, asset.name, release.html_url
+                ))
+                .set_buttons(MessageButtons::Ok)
+                .show();
+
+            return false;
+        }
+
+        let confirmed = MessageDialog::new()
+            .set_level(MessageLevel::Info)
+            .set_title(&format!("Live v{} is available", release.version()))
+            .set_description(&format!("{}\n\nDownload and install now?", changelog))
+            .set_buttons(MessageButtons::OkCancel)
+            .show();
+
+        if !confirmed {
+            return false;
+        }
+
+        match apply_update(release, asset) {
+            Ok(()) => {
+                MessageDialog::new()
+                    .set_level(MessageLevel::Info)
+                    .set_title("Update installed")
+                    .set_description("Restart the editor to use the new version")
+                    .set_buttons(MessageButtons::Ok)
+                    .show();
+
+                true
+            }
+            Err(e) => {
+                MessageDialog::new()
+                    .set_level(MessageLevel::Error)
+                    .set_title("Update failed")
+                    .set_description(&e)
+                    .set_buttons(MessageButtons::Ok)
+                    .show();
+
+                false
+            }
+        }
+    }
+}
+
+fn fetch_latest_release() -> Result<Release, String> {
+    ureq::get(LATEST_RELEASE_URL)
+        .set("User-Agent", "live_editor")
+        .call()
+        .map_err(|e| e.to_string())?
+        .into_json::<Release>()
+        .map_err(|e| e.to_string())
+}
+
+fn download(asset: &ReleaseAsset) -> Result<Vec<u8>, String> {
+    let mut bytes = vec![];
+    ureq::get(&asset.browser_download_url)
+        .set("User-Agent", "live_editor")
+        .call()
+        .map_err(|e| e.to_string())?
+        .into_reader()
+        .read_to_end(&mut bytes)
+        .map_err(|e| e.to_string())?;
+
+    Ok(bytes)
+}
+
+/**
+    Checks a download against its published checksum (the hex digest, optionally followed by the file name, like `sha256sum` writes it)
+*/
+fn verify(bytes: &[u8], published: &str) -> Result<(), String> {
+    let expected = published
+        .split_whitespace()
+        .next()
+        .ok_or("the published checksum is empty")?;
+
+    if format!("{:x}", Sha256::digest(bytes)) != expected.to_ascii_lowercase() {
+        return Err("the download doesn't match its published checksum".into());
+    }
+
+    Ok(())
+}
+
+/**
+    Downloads the new executable next to the current one, checks it, and then swaps them. The running process keeps its (now unlinked) executable, so this is safe to do while running.
+
+    (Windows doesn't let a running executable be replaced, but it does let it be renamed, so there it's moved aside first, and cleaned up by the next update.)
+*/
+fn apply_update(release: &Release, asset: &ReleaseAsset) -> Result<(), String> {
+    if asset.is_archive() {
+        return Err(format!("{} has to be unpacked by hand", asset.name));
+    }
+
+    let checksum = release
+        .checksum(asset)
+        .ok_or_else(|| format!("{} has no published checksum", asset.name))?;
+    let published = String::from_utf8(download(checksum)?).map_err(|e| e.to_string())?;
+
+    let bytes = download(asset)?;
+    verify(&bytes, &published)?;
+
+    let current_exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    let download_path = current_exe.with_extension("download");
+
+    fs::write(&download_path, bytes).map_err(|e| e.to_string())?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&download_path, fs::Permissions::from_mode(0o755))
+            .map_err(|e| e.to_string())?;
+    }
+
+    replace_exe(&download_path, &current_exe)
+}
+
+#[cfg(not(windows))]
+fn replace_exe(download_path: &Path, current_exe: &Path) -> Result<(), String> {
+    fs::rename(download_path, current_exe).map_err(|e| e.to_string())
+}
+
+#[cfg(windows)]
+fn replace_exe(download_path: &Path, current_exe: &Path) -> Result<(), String> {
+    let old_path = current_exe.with_extension("old");
+    // (left over from the last update, unless that one's still running)
+    let _ = fs::remove_file(&old_path);
+
+    fs::rename(current_exe, &old_path).map_err(|e| e.to_string())?;
+    if let Err(e) = fs::rename(download_path, current_exe) {
+        // (put it back, so there's still an editor to start)
+        let _ = fs::rename(&old_path, current_exe);
+        return Err(e.to_string());
+    }
+
+    Ok(())
+}
+
+/**
+    A version's precedence, like semver's, but lenient: parts that aren't numbers count as 0, and so do missing ones (so "1.2" is "1.2.0")
+*/
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Precedence {
+    numbers: Vec<u64>,
+    // (a release comes after its prereleases)
+    release: bool,
+    prerelease: Vec<Identifier>,
+}
+
+// (numbers come before text, like in semver)
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Identifier {
+    Number(u64),
+    Text(String),
+}
+
+impl Precedence {
+    fn parse(version: &str) -> Self {
+        // (build metadata doesn't count)
+        let version = version.split('+').next().unwrap_or(version);
+        let (numbers, prerelease) = match version.split_once('-') {
+            Some((numbers, prerelease)) => (numbers, Some(prerelease)),
+            None => (version, None),
+        };
+
+        let mut numbers = numbers
+            .split('.')
+            .map(|part| part.parse::<u64>().unwrap_or(0))
+            .collect::<Vec<_>>();
+        while numbers.last() == Some(&0) {
+            numbers.pop();
+        }
+
+        Self {
+            numbers,
+            release: prerelease.is_none(),
+            prerelease: prerelease
+                .into_iter()
+                .flat_map(|prerelease| prerelease.split('.'))
+                .map(|part| match part.parse::<u64>() {
+                    Ok(n) => Identifier::Number(n),
+                    Err(_) => Identifier::Text(part.to_string()),
+                })
+                .collect(),
+        }
+    }
+}
+
+// e.g. "0.10.1" > "0.9.3", and "1.2.0" > "1.2.0-rc.1" > "1.2.0-beta.10" > "1.2.0-beta.2"
+fn is_newer(version: &str, than: &str) -> bool {
+    Precedence::parse(version) > Precedence::parse(than)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_newer() {
+        assert!(is_newer("0.10.1", "0.9.3"));
+        assert!(!is_newer("0.9.3", "0.10.1"));
+        assert!(!is_newer("1.2", "1.2.0"));
+        assert!(!is_newer("1.2.0", "1.2"));
+
+        // (prereleases come before their release)
+        assert!(is_newer("1.2.0", "1.2.0-beta"));
+        assert!(!is_newer("1.2.0-beta", "1.2.0"));
+        assert!(is_newer("1.2.0-beta", "1.1.9"));
+        assert!(is_newer("1.2.0-beta.10", "1.2.0-beta.2"));
+        assert!(is_newer("1.2.0-rc.1", "1.2.0-beta.10"));
+        assert!(is_newer("1.2.0-beta.1", "1.2.0-beta"));
+        assert!(is_newer("1.2.0-beta", "1.2.0-1"));
+
+        // (and build metadata doesn't count)
+        assert!(!is_newer("1.2.0+linux", "1.2.0"));
+    }
+
+    #[test]
+    fn test_verify() {
+        let digest = format!("{:x}", Sha256::digest(b"live"));
+
+        assert!(verify(b"live", &digest).is_ok());
+        // (like `sha256sum` writes it)
+        let published = format!("{}  live-linux-x86_64\n", digest.to_uppercase());
+        assert!(verify(b"live", &published).is_ok());
+        assert!(verify(b"evil", &digest).is_err());
+        assert!(verify(b"live", "").is_err());
+    }
+
+    #[test]
+    fn test_assets() {
+        let asset = |name: &str| ReleaseAsset {
+            name: name.into(),
+            browser_download_url: format!("https://example.com/{}", name),
+        };
+        let build = format!("live-{}-{}", std::env::consts::OS, std::env::consts::ARCH);
+
+        let release = Release {
+            tag_name: "v1.2.0".into(),
+            html_url: "https://example.com".into(),
+            body: None,
+            assets: vec![
+                asset(&format!("{}.sha256", build)),
+                asset(&build),
+                asset("live-plan9-mips"),
+            ],
+        };
+
+        let found = release.asset().unwrap();
+        assert_eq!(found.name, build);
+        assert!(!found.is_archive());
+        assert_eq!(
+            release.checksum(found).unwrap().name,
+            format!("{}.sha256", build)
+        );
+        assert!(asset(&format!("{}.tar.gz", build)).is_archive());
+    }
+}
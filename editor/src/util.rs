@@ -16,10 +16,82 @@
 //     } * BALL_SPEED
 // }
 
+use live_editor_state::{LineData, Pos, Range};
+use live_language::{Loc, SourceSpan};
+
 pub fn size_of_slice<T: Sized>(slice: &[T]) -> usize {
     std::mem::size_of::<T>() * slice.len()
 }
 
+// Where something the language crate reports (about `linedata.to_string()`) is in the editor
+pub fn loc_to_pos(linedata: &LineData, loc: Loc) -> Pos {
+    linedata.line_col_to_pos(loc.line, loc.col)
+}
+
+pub fn span_to_range(linedata: &LineData, span: SourceSpan) -> Range {
+    Range {
+        start: loc_to_pos(linedata, span.start),
+        end: loc_to_pos(linedata, span.end),
+    }
+}
+
+// Where we keep editor-global state that should survive restarts (window placements, etc.)
+#[cfg(not(target_arch = "wasm32"))]
+pub fn config_dir() -> Option<std::path::PathBuf> {
+    let home = std::env::var_os("HOME")?;
+    let dir = std::path::PathBuf::from(home).join(".live_editor");
+    std::fs::create_dir_all(&dir).ok()?;
+    Some(dir)
+}
+
+// (in the browser, it's where the keys in local storage are, see `storage`)
+#[cfg(target_arch = "wasm32")]
+pub fn config_dir() -> Option<std::path::PathBuf> {
+    Some(std::path::PathBuf::from("/.live_editor"))
+}
+
+// Where we keep things that are expensive to compute, but can always be thrown away
+pub fn cache_dir() -> Option<std::path::PathBuf> {
+    let dir = config_dir()?.join("cache");
+    std::fs::create_dir_all(&dir).ok()?;
+    Some(dir)
+}
+
+// Shows a file in the system's file browser (selected, where that's possible)
+pub fn reveal_file(path: &std::path::Path) {
+    use std::process::Command;
+
+    #[cfg(target_os = "macos")]
+    let spawned = Command::new("open").arg("-R").arg(path).spawn();
+    #[cfg(target_os = "windows")]
+    let spawned = Command::new("explorer")
+        .arg(format!("/select,{}", path.display()))
+        .spawn();
+    // (there's no one way to select a file on linux, so just open its folder)
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let spawned = Command::new("xdg-open")
+        .arg(path.parent().unwrap_or(path))
+        .spawn();
+
+    if let Err(e) = spawned {
+        tracing::warn!("Could not reveal {:?} ({})", path, e);
+    }
+}
+
+pub fn format_ago(ago: std::time::Duration) -> String {
+    let secs = ago.as_secs();
+
+    if secs < 60 {
+        format!("{}s ago", secs)
+    } else if secs < 60 * 60 {
+        format!("{}m ago", secs / 60)
+    } else if secs < 60 * 60 * 24 {
+        format!("{}h ago", secs / 60 / 60)
+    } else {
+        format!("{}d ago", secs / 60 / 60 / 24)
+    }
+}
+
 // #[macro_export]
 // macro_rules! any {
 //     ($x:expr, $($y:expr),+ $(,)?) => {
@@ -0,0 +1,342 @@
+use std::{
+    collections::HashMap,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use live_engine::{EngineHandle, Tap, TAP_SIZE};
+use live_language::{evaluate_source_in, play_targets, Value};
+
+use crate::{
+    panel::{List, ListHit, PANEL_MARGIN},
+    render::Overlay,
+    status_bar::STATUS_BAR_HEIGHT,
+};
+
+/// How often live values are read from the engine (20Hz), which is plenty to follow them by
+const REFRESH_INTERVAL: Duration = Duration::from_millis(50);
+/// How many bars a sparkline has, each the peak of its part of the tap's samples
+const SPARKLINE_BARS: usize = 64;
+
+const PANEL_WIDTH: f32 = 360.0;
+const ROW_HEIGHT: f32 = 24.0;
+const MAX_ROWS: usize = 8;
+const FONT_SIZE: f32 = 14.0;
+const LABEL_WIDTH: f32 = 130.0;
+const SPARKLINE_WIDTH: f32 = 160.0;
+const METER_WIDTH: f32 = 24.0;
+
+const PANEL_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.05];
+const TEXT_COLOR: [f32; 4] = [0.02, 0.02, 0.02, 1.0];
+const DIM_TEXT_COLOR: [f32; 4] = [0.02, 0.02, 0.02, 0.45];
+const SPARKLINE_COLOR: [f32; 4] = [0.0, 0.6, 0.3, 1.0];
+const METER_TRACK_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.1];
+
+pub enum WatchValue {
+    /// What it evaluated to, like `2hz` or `[1, 2]`
+    Known(String),
+    /// A signal that's played by name, with the peaks of its latest samples, and how loud it is right now
+    Live {
+        tap: Tap,
+        peaks: Vec<f32>,
+        level: f32,
+    },
+    /// A signal that isn't played by name, so there's nothing to follow
+    Unplayed(String),
+    /// Not in the code (anymore), or it didn't evaluate
+    Missing,
+}
+
+pub struct Watch {
+    pub label: String,
+    /// (pinned from the editor, as opposed to a `watch(..)` in the code)
+    pub pinned: bool,
+    pub value: WatchValue,
+}
+
+/**
+    What the watch panel shows: every `watch(..)` in the code, plus the names that were pinned with Cmd+Shift+W. Values that are known when the code is evaluated (numbers, amounts, arrays) are shown as they are, and signals that are played by name are followed live, as a sparkline of their waveform and a level meter (which is also how a pattern shows that it's triggering).
+*/
+#[derive(Default)]
+pub struct Watches {
+    pinned: Vec<String>,
+    // (with its seed, and whether there was an engine to tap, then)
+    source: Option<(String, u64, bool)>,
+    pub entries: Vec<Watch>,
+    refreshed_at: Option<Instant>,
+}
+
+impl Watches {
+    /**
+        Pins a name, or unpins it if it was pinned already
+    */
+    pub fn toggle_pin(&mut self, name: &str) {
+        match self.pinned.iter().position(|pinned| pinned == name) {
+            Some(i) => {
+                self.pinned.remove(i);
+            }
+            None => self.pinned.push(name.to_string()),
+        }
+
+        // (so that the next sync picks it up)
+        self.source = None;
+    }
+
+    /**
+        Evaluates the code again when it changed, keeping the taps of what's still being watched
+    */
+    pub fn sync(&mut self, source: &str, seed: u64, root: &Path, engine: Option<&EngineHandle>) {
+        let key = (source.to_string(), seed, engine.is_some());
+        if self.source.as_ref() == Some(&key) {
+            return;
+        }
+
+        let evaluation = evaluate_source_in(source, seed, root);
+        let played = play_targets(source)
+            .into_iter()
+            .map(|target| target.name)
+            .collect::<Vec<_>>();
+
+        let mut taps = self
+            .entries
+            .drain(..)
+            .filter_map(|watch| match watch.value {
+                WatchValue::Live { tap, .. } => Some((watch.label, tap)),
+                _ => None,
+            })
+            .collect::<HashMap<_, _>>();
+
+        let pinned = self.pinned.iter().map(|name| {
+            let value = evaluation
+                .values
+                .iter()
+                .find(|(key, _)| key.to_string() == *name)
+                .map(|(_, value)| value.clone());
+            (name.clone(), true, value)
+        });
+
+        let watched = evaluation
+            .watched
+            .into_iter()
+            .map(|(label, value)| (label, false, Some(value)))
+            .chain(pinned)
+            .collect::<Vec<_>>();
+
+        self.entries = watched
+            .into_iter()
+            .map(|(label, pinned, value)| {
+                let value = match (value, engine) {
+                    (Some(Value::Node(..)), Some(engine)) if played.contains(&label) => {
+                        WatchValue::Live {
+                            tap: taps.remove(&label).unwrap_or_else(|| engine.tap(&label)),
+                            peaks: vec![],
+                            level: 0.0,
+                        }
+                    }
+                    (Some(value @ Value::Node(..)), _) => WatchValue::Unplayed(value.to_string()),
+                    (Some(value), _) => WatchValue::Known(value.to_string()),
+                    (None, _) => WatchValue::Missing,
+                };
+
+                Watch {
+                    label,
+                    pinned,
+                    value,
+                }
+            })
+            .collect();
+
+        self.source = Some(key);
+        self.refreshed_at = None;
+    }
+
+    /**
+        Shows what was watched since the code was evaluated (like by a timer's function, see `Timer::fire`), until the code changes
+    */
+    pub fn show(&mut self, watched: Vec<(String, Value)>) {
+        for (label, value) in watched {
+            let value = match value {
+                Value::Node(..) => WatchValue::Unplayed(value.to_string()),
+                value => WatchValue::Known(value.to_string()),
+            };
+
+            match self.entries.iter_mut().find(|watch| watch.label == label) {
+                // (what's followed live stays that way)
+                Some(watch) if matches!(watch.value, WatchValue::Live { .. }) => {}
+                Some(watch) => watch.value = value,
+                None => self.entries.push(Watch {
+                    label,
+                    pinned: false,
+                    value,
+                }),
+            }
+        }
+    }
+
+    /**
+        When the live values have to be read again, if there are any
+    */
+    pub fn due_at(&self) -> Option<Instant> {
+        let live = self
+            .entries
+            .iter()
+            .any(|watch| matches!(watch.value, WatchValue::Live { .. }));
+
+        if !live {
+            return None;
+        }
+
+        Some(
+            self.refreshed_at
+                .map_or_else(Instant::now, |at| at + REFRESH_INTERVAL),
+        )
+    }
+
+    pub fn needs_refresh(&self) -> bool {
+        self.due_at().is_some_and(|at| at <= Instant::now())
+    }
+
+    /**
+        Reads the latest samples and levels of the live values from the engine (at most every `REFRESH_INTERVAL`)
+    */
+    pub fn refresh(&mut self, engine: &EngineHandle) {
+        if !self.needs_refresh() {
+            return;
+        }
+
+        let levels = engine.levels();
+        for watch in &mut self.entries {
+            if let WatchValue::Live { tap, peaks, level } = &mut watch.value {
+                *peaks = sparkline(&tap.latest(TAP_SIZE));
+                *level = levels.get(&watch.label).map_or(0.0, |level| level.peak);
+            }
+        }
+
+        self.refreshed_at = Some(Instant::now());
+    }
+}
+
+/**
+    The peak (0..1) of every part of the samples, one per bar
+*/
+fn sparkline(samples: &[f32]) -> Vec<f32> {
+    if samples.is_empty() {
+        return vec![];
+    }
+
+    samples
+        .chunks(samples.len().div_ceil(SPARKLINE_BARS))
+        .map(|chunk| {
+            chunk
+                .iter()
+                .fold(0.0f32, |peak, s| peak.max(s.abs()))
+                .min(1.0)
+        })
+        .collect()
+}
+
+/**
+    The watch panel, in the bottom right corner of the window (right above the status bar). It's only there when something is being watched. Clicking a pinned entry unpins it.
+*/
+pub struct WatchPanel {
+    pub collapsed: bool,
+}
+
+impl WatchPanel {
+    pub fn new() -> Self {
+        Self { collapsed: false }
+    }
+
+    fn list(&self, watches: &Watches, (width, height): (f32, f32)) -> Option<List> {
+        if watches.entries.is_empty() {
+            return None;
+        }
+
+        Some(List::above(
+            (
+                width - PANEL_MARGIN - PANEL_WIDTH,
+                height - STATUS_BAR_HEIGHT - PANEL_MARGIN,
+            ),
+            PANEL_WIDTH,
+            ROW_HEIGHT,
+            self.collapsed,
+            watches.entries.len().min(MAX_ROWS),
+        ))
+    }
+
+    pub fn hit_test(
+        &self,
+        watches: &Watches,
+        window_size: (f32, f32),
+        point: (f32, f32),
+    ) -> Option<ListHit> {
+        self.list(watches, window_size)?.hit_test(point)
+    }
+
+    pub fn draw(&self, watches: &Watches, window_size: (f32, f32), overlay: &mut Overlay) {
+        let Some(list) = self.list(watches, window_size) else {
+            return;
+        };
+        list.draw(
+            &format!("Watch ({})", watches.entries.len()),
+            "",
+            FONT_SIZE,
+            [PANEL_COLOR, TEXT_COLOR, DIM_TEXT_COLOR],
+            overlay,
+        );
+
+        if self.collapsed {
+            return;
+        }
+
+        let (min_x, _, max_x, _) = list.bounds;
+        for (i, watch) in watches.entries.iter().take(MAX_ROWS).enumerate() {
+            let top = list.row_top(i);
+            let (x, y) = list.row_text(i, FONT_SIZE);
+            let value_x = min_x + LABEL_WIDTH;
+
+            overlay.text((x, y), &watch.label, FONT_SIZE, TEXT_COLOR);
+
+            match &watch.value {
+                WatchValue::Known(text) => {
+                    overlay.text((value_x, y), text, FONT_SIZE, TEXT_COLOR);
+                }
+                WatchValue::Unplayed(text) => {
+                    overlay.text((value_x, y), text, FONT_SIZE, DIM_TEXT_COLOR);
+                }
+                WatchValue::Missing => {
+                    overlay.text((value_x, y), "—", FONT_SIZE, DIM_TEXT_COLOR);
+                }
+                WatchValue::Live { peaks, level, .. } => {
+                    let mid = top + ROW_HEIGHT / 2.0;
+                    let max_half = ROW_HEIGHT / 2.0 - 4.0;
+                    let bar_width = SPARKLINE_WIDTH / SPARKLINE_BARS as f32;
+
+                    for (j, peak) in peaks.iter().enumerate() {
+                        let x = value_x + j as f32 * bar_width;
+                        // (at least a hairline, so silence still shows as a line)
+                        let half = (peak * max_half).max(0.5);
+                        overlay.quad((x, mid - half, x + bar_width, mid + half), SPARKLINE_COLOR);
+                    }
+
+                    let meter_x = value_x + SPARKLINE_WIDTH + 10.0;
+                    let meter = (meter_x, mid - 3.0, meter_x + METER_WIDTH, mid + 3.0);
+                    overlay.quad(meter, METER_TRACK_COLOR);
+                    overlay.quad(
+                        (
+                            meter_x,
+                            meter.1,
+                            meter_x + METER_WIDTH * level.clamp(0.0, 1.0),
+                            meter.3,
+                        ),
+                        SPARKLINE_COLOR,
+                    );
+                }
+            }
+
+            if watch.pinned {
+                overlay.text((max_x - 20.0, y), "×", FONT_SIZE, DIM_TEXT_COLOR);
+            }
+        }
+    }
+}
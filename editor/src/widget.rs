@@ -1,23 +1,109 @@
-use live_editor_state::WidgetInfo;
+use live_editor_state::{LineData, Pos, Token, WidgetInfo};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
-use crate::{render::WidgetTexture, ui::WidgetEvent};
+use crate::{
+    pattern::{NotePattern, Pattern},
+    render::WidgetTexture,
+    ui::{WidgetAction, WidgetEvent, WidgetKey},
+};
+
+/**
+    What a widget "is", as far as the code (and so the audio graph) is concerned
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub enum WidgetValue {
+    Number(f32),
+    Pattern(Pattern),
+    Notes(NotePattern),
+    Sample(PathBuf, SampleMarkers),
+    // a loop, cut up where its slices start (as fractions of its length)
+    Slices(PathBuf, Vec<f32>),
+}
+
+/**
+    Where a sample loops, and where it can be cued from, as set with the handles on its widget (as fractions of its length, for the sampler's `loop_start`/`loop_end` parameters and `set_cues`)
+*/
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SampleMarkers {
+    pub loop_region: Option<(f32, f32)>,
+    pub cues: Vec<f32>,
+}
 
 pub trait Widget {
     fn kind(&self) -> &'static str;
 
-    // Decide how big it should be in the code editor (only called once)
+    // Decide how big it should be in the code editor (which is checked every frame, so it can change, like when it's expanded, see `WidgetManager::resized_in`)
     fn column_width(&self) -> usize {
         5
     }
 
     // Receive events such as: suspend, update how many instances are used, mouse input stuff, etc.
+    // Returning true from a `MouseDown` means the widget captures the drag: it then gets all mouse moves (and the mouse up), instead of the editor starting a text selection
+    // Returning true from a `Key` means the widget handled it (otherwise arrows adjust its value instead)
+    // Returning true from a right-click `Press` means the widget used the click itself (otherwise it opens its context menu)
     fn event(&mut self, _event: WidgetEvent) -> bool {
         false
     }
 
-    // Draw to pixel frame
+    // The widget's current value, for the audio graph to consume
+    fn value(&self) -> Option<WidgetValue> {
+        None
+    }
+
+    // What you can do with the widget, as (gesture, what it does) pairs, shown when hovering it for a while
+    fn help(&self) -> &'static [(&'static str, &'static str)] {
+        &[]
+    }
+
+    // What it offers in its context menu (right-clicking it), which it then gets as `WidgetEvent::Action`s (converting to a reference and removing it are the editor's, see `WidgetManager::actions`)
+    fn actions(&self) -> &'static [WidgetAction] {
+        &[]
+    }
+
+    // What it could be written as in the code instead, if anything (like `path("kicks/1.wav")` for a sample)
+    fn reference(&self) -> Option<String> {
+        None
+    }
+
+    // How big it opens up over the code when it's double-clicked (in logical pixels), if it does, for widgets that are fiddly to edit at the size of a few characters (see `WidgetManager::toggle_expanded`)
+    fn expanded_size(&self) -> Option<(f32, f32)> {
+        None
+    }
+
+    // Draw to pixel frame (which is bigger when it's expanded)
     fn draw(&self, _frame: &mut WidgetTexture) {}
 
+    // Whether the widget wants to be redrawn every frame, regardless of events (e.g. for a playhead)
+    fn animating(&self) -> bool {
+        false
+    }
+
+    // Whether it's still loading something in the background (shown in the status bar)
+    fn loading(&self) -> bool {
+        false
+    }
+
+    // Why it doesn't work, like a sample file that can't be read (shown next to the line it's on, when the code's evaluated)
+    fn error(&self) -> Option<String> {
+        None
+    }
+
+    // Lets the user pick another file for it (for widgets that refer to one, like when it can't be found), returning whether they did
+    fn relocate(&mut self) -> bool {
+        false
+    }
+
+    // The function that the widget's passed to in the code, if any (like `slices`), which some widgets show (and are) something else for, returning whether that changed anything
+    fn wrapped_in(&mut self, _function: Option<&str>) -> bool {
+        false
+    }
+
+    // A file changed on disk (like a sample that was re-exported from a DAW), so widgets that show it should read it again
+    fn file_changed(&mut self, _path: &Path) {}
+
     // When the file is saved in "bundled" mode, this method is called
     fn bundle_resources(&self) {}
 
@@ -29,11 +115,21 @@ pub trait Widget {
 
 pub struct WidgetManager {
     widgets: Vec<Box<dyn Widget>>,
+    needs_redraw: bool,
+    // the widget that has the keyboard, if it's not the text
+    focused: Option<usize>,
+    // the widget that's opened up over the code (which has the keyboard too)
+    expanded: Option<usize>,
 }
 
 impl WidgetManager {
     pub fn new() -> Self {
-        Self { widgets: vec![] }
+        Self {
+            widgets: vec![],
+            needs_redraw: true,
+            focused: None,
+            expanded: None,
+        }
     }
 
     pub fn add(&mut self, widget: Box<dyn Widget>) -> WidgetInfo {
@@ -43,6 +139,7 @@ impl WidgetManager {
         let kind = widget.kind();
 
         self.widgets.push(widget);
+        self.needs_redraw = true;
 
         WidgetInfo { kind, id, width }
     }
@@ -55,9 +152,304 @@ impl WidgetManager {
 
     pub fn event(&mut self, id: usize, event: WidgetEvent) -> bool {
         if let Some(widget) = self.widgets.get_mut(id) {
+            // widgets' looks depend on pretty much every event they get (hover, press, ..)
+            self.needs_redraw = true;
             widget.event(event)
         } else {
             false
         }
     }
+
+    pub fn focused(&self) -> Option<usize> {
+        self.focused
+    }
+
+    /**
+        Gives a widget the keyboard (taking it from the one that had it)
+    */
+    pub fn focus(&mut self, id: usize) {
+        if self.focused == Some(id) || id >= self.widgets.len() {
+            return;
+        }
+
+        self.unfocus();
+        self.event(id, WidgetEvent::Focus);
+        self.focused = Some(id);
+    }
+
+    /**
+        Gives the keyboard back to the text (which collapses the expanded widget, if any)
+    */
+    pub fn unfocus(&mut self) {
+        if let Some(id) = self.focused.take() {
+            self.event(id, WidgetEvent::Unfocus);
+        }
+        if self.expanded.take().is_some() {
+            self.needs_redraw = true;
+        }
+    }
+
+    /**
+        The widget that's opened up over the code, with how big it is
+    */
+    pub fn expanded(&self) -> Option<(usize, (f32, f32))> {
+        let id = self.expanded?;
+        Some((id, self.widgets.get(id)?.expanded_size()?))
+    }
+
+    /**
+        Opens a widget up over the code, giving it the keyboard, or collapses it again, returning whether it can be expanded at all
+    */
+    pub fn toggle_expanded(&mut self, id: usize) -> bool {
+        if self
+            .widgets
+            .get(id)
+            .and_then(|widget| widget.expanded_size())
+            .is_none()
+        {
+            return false;
+        }
+
+        if self.expanded == Some(id) {
+            self.unfocus();
+        } else {
+            self.focus(id);
+            self.expanded = Some(id);
+            self.needs_redraw = true;
+        }
+
+        true
+    }
+
+    /**
+        Routes a key to the focused widget, returning which widget got it (if any), so its value can be sent along. Arrows that it doesn't handle itself adjust its value, by 1 step, or 10 with shift, or 0.1 with alt.
+    */
+    pub fn key(
+        &mut self,
+        key: WidgetKey,
+        shift: bool,
+        alt: bool,
+        meta_or_ctrl: bool,
+    ) -> Option<usize> {
+        let id = self.focused?;
+
+        let handled = self.event(
+            id,
+            WidgetEvent::Key {
+                key,
+                shift,
+                alt,
+                meta_or_ctrl,
+            },
+        );
+
+        if !handled && key.is_arrow() {
+            let step = if shift {
+                10.0
+            } else if alt {
+                0.1
+            } else {
+                1.0
+            };
+
+            self.event(
+                id,
+                WidgetEvent::Adjust {
+                    steps: key.direction() * step,
+                },
+            );
+        }
+
+        Some(id)
+    }
+
+    pub fn help(&self, id: usize) -> &'static [(&'static str, &'static str)] {
+        self.widgets.get(id).map_or(&[], |widget| widget.help())
+    }
+
+    /**
+        What the widget's context menu offers: what the widget does itself, then converting it to a reference (if it can be written as one), and removing it
+    */
+    pub fn actions(&self, id: usize) -> Vec<WidgetAction> {
+        let Some(widget) = self.widgets.get(id) else {
+            return vec![];
+        };
+
+        let mut actions = widget.actions().to_vec();
+        if widget.reference().is_some() {
+            actions.push(WidgetAction::ConvertToReference);
+        }
+        actions.push(WidgetAction::Remove);
+
+        actions
+    }
+
+    pub fn reference(&self, id: usize) -> Option<String> {
+        self.widgets.get(id)?.reference()
+    }
+
+    pub fn info(&self, id: usize) -> Option<WidgetInfo> {
+        let widget = self.widgets.get(id)?;
+
+        Some(WidgetInfo {
+            kind: widget.kind(),
+            id,
+            width: widget.column_width(),
+        })
+    }
+
+    pub fn value(&self, id: usize) -> Option<WidgetValue> {
+        self.widgets.get(id)?.value()
+    }
+
+    /**
+        The values of the widgets in the code, by how the code refers to them, like `sample#3`
+    */
+    pub fn values_in(&self, linedata: &LineData) -> HashMap<String, WidgetValue> {
+        linedata
+            .lines()
+            .iter()
+            .flatten()
+            .filter_map(|token| match token {
+                Token::Widget(info) => {
+                    let value = self.value(info.id)?;
+                    Some((format!("{}#{}", info.kind, info.id), value))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /**
+        The sample files that the widgets in the code refer to
+    */
+    pub fn samples_in(&self, linedata: &LineData) -> Vec<PathBuf> {
+        linedata
+            .lines()
+            .iter()
+            .flatten()
+            .filter_map(|token| match token {
+                Token::Widget(info) => match self.value(info.id) {
+                    Some(WidgetValue::Sample(path, _) | WidgetValue::Slices(path, _)) => Some(path),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect()
+    }
+
+    /**
+        The widgets in the code that don't work (see `Widget::error`), with where they are
+    */
+    pub fn errors_in(&self, linedata: &LineData) -> Vec<(Pos, usize, String)> {
+        let mut errors = vec![];
+
+        for (row, line) in linedata.lines().iter().enumerate() {
+            for (i, token) in line.iter().enumerate() {
+                if let Token::Widget(info) = token
+                    && let Some(error) = self.widgets.get(info.id).and_then(|w| w.error())
+                {
+                    let row = row as i32;
+                    let col = linedata.line_index_col(row, i);
+                    errors.push((Pos { row, col }, info.id, error));
+                }
+            }
+        }
+
+        errors
+    }
+
+    /**
+        The widgets in the code that want to be another width now (see `Widget::column_width`), with that width
+    */
+    pub fn resized_in(&self, linedata: &LineData) -> Vec<(usize, usize)> {
+        let mut resized = vec![];
+
+        for token in linedata.lines().iter().flatten() {
+            if let Token::Widget(info) = token
+                && let Some(widget) = self.widgets.get(info.id)
+                && widget.column_width() != info.width
+                && !resized.iter().any(|&(id, _)| id == info.id)
+            {
+                resized.push((info.id, widget.column_width()));
+            }
+        }
+
+        resized
+    }
+
+    /**
+        Tells every widget in the code which function it's passed to (see `Widget::wrapped_in`), like when a sample was just wrapped in `slices(...)`
+    */
+    pub fn sync_wrapping(&mut self, linedata: &LineData) {
+        for line in linedata.lines() {
+            for (i, token) in line.iter().enumerate() {
+                if let Token::Widget(info) = token
+                    && let Some(widget) = self.widgets.get_mut(info.id)
+                {
+                    let function = wrapping_function(&line[..i]);
+                    self.needs_redraw |= widget.wrapped_in(function.as_deref());
+                }
+            }
+        }
+    }
+
+    pub fn relocate(&mut self, id: usize) -> bool {
+        let relocated = self
+            .widgets
+            .get_mut(id)
+            .is_some_and(|widget| widget.relocate());
+
+        self.needs_redraw |= relocated;
+        relocated
+    }
+
+    pub fn file_changed(&mut self, path: &Path) {
+        for widget in &mut self.widgets {
+            widget.file_changed(path);
+        }
+        self.needs_redraw = true;
+    }
+
+    pub fn needs_redraw(&self) -> bool {
+        self.needs_redraw || self.animating()
+    }
+
+    pub fn animating(&self) -> bool {
+        self.widgets.iter().any(|widget| widget.animating())
+    }
+
+    /**
+        How many widgets are still loading
+    */
+    pub fn loading(&self) -> usize {
+        self.widgets
+            .iter()
+            .filter(|widget| widget.loading())
+            .count()
+    }
+
+    pub fn mark_drawn(&mut self) {
+        self.needs_redraw = false;
+    }
+}
+
+/**
+    The name of the function whose argument list starts right at the end of these tokens, like `slices` for `let drums = slices(`
+*/
+fn wrapping_function(before: &[Token]) -> Option<String> {
+    let mut chars = before.iter().rev().map_while(|token| match token {
+        Token::Char(c) => Some(*c),
+        Token::Widget(_) => None,
+    });
+
+    chars.find(|c| !c.is_whitespace()).filter(|&c| c == '(')?;
+
+    let mut name = chars
+        .skip_while(|c| c.is_whitespace())
+        .take_while(|&c| c.is_alphanumeric() || c == '_')
+        .collect::<Vec<_>>();
+    name.reverse();
+
+    (!name.is_empty()).then(|| name.into_iter().collect())
 }
@@ -0,0 +1,145 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    panel::{text_width, text_y, Bounds},
+    render::Overlay,
+};
+
+/// How long the mouse has to rest on a widget before we explain it
+const HOVER_DELAY: Duration = Duration::from_millis(800);
+
+const FONT_SIZE: f32 = 13.0;
+const ROW_HEIGHT: f32 = 20.0;
+const PADDING: f32 = 8.0;
+const GESTURE_WIDTH: f32 = 120.0;
+
+const PANEL_COLOR: [f32; 4] = [0.1, 0.1, 0.1, 0.92];
+const TEXT_COLOR: [f32; 4] = [0.98, 0.98, 0.98, 1.0];
+const DIM_TEXT_COLOR: [f32; 4] = [0.98, 0.98, 0.98, 0.6];
+
+struct Hover {
+    id: usize,
+    bounds: Bounds,
+    since: Instant,
+}
+
+/**
+    Long-hovering a widget shows a little overlay listing what you can do with it (see `Widget::help`), so that the custom UI is discoverable.
+*/
+pub struct WidgetHelp {
+    hover: Option<Hover>,
+    // clicking the widget means you know what you're doing, so we stop explaining until the next hover
+    dismissed: bool,
+    drawn: bool,
+}
+
+impl WidgetHelp {
+    pub fn new() -> Self {
+        Self {
+            hover: None,
+            dismissed: false,
+            drawn: false,
+        }
+    }
+
+    pub fn hover(&mut self, id: usize, bounds: (f32, f32, f32, f32)) {
+        if self.hover.as_ref().map(|hover| hover.id) != Some(id) {
+            self.hover = Some(Hover {
+                id,
+                bounds,
+                since: Instant::now(),
+            });
+            self.dismissed = false;
+            self.drawn = false;
+        }
+    }
+
+    pub fn unhover(&mut self) {
+        self.hover = None;
+    }
+
+    pub fn dismiss(&mut self) {
+        self.dismissed = true;
+    }
+
+    /**
+        The widget to explain right now, if any
+    */
+    pub fn visible(&self) -> Option<usize> {
+        let hover = self.hover.as_ref()?;
+
+        if self.dismissed || hover.since.elapsed() < HOVER_DELAY {
+            return None;
+        }
+
+        Some(hover.id)
+    }
+
+    /**
+        When the help will (have to) appear, so that the event loop can wake up for it
+    */
+    pub fn due_at(&self) -> Option<Instant> {
+        let hover = self.hover.as_ref()?;
+
+        if self.dismissed || self.drawn {
+            return None;
+        }
+
+        Some(hover.since + HOVER_DELAY)
+    }
+
+    pub fn needs_redraw(&self) -> bool {
+        self.visible().is_some() && !self.drawn
+    }
+
+    /**
+        Draws the help just below the widget (or above it, if there's no room)
+    */
+    pub fn draw(&mut self, help: &[(&str, &str)], window_size: (f32, f32), overlay: &mut Overlay) {
+        let Some(hover) = &self.hover else {
+            return;
+        };
+
+        self.drawn = true;
+
+        if help.is_empty() {
+            return;
+        }
+
+        let longest = help
+            .iter()
+            .map(|(_, description)| description.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        let width = PADDING * 2.0 + GESTURE_WIDTH + text_width(longest);
+        let height = PADDING * 2.0 + help.len() as f32 * ROW_HEIGHT;
+
+        let (widget_min_x, widget_min_y, _, widget_max_y) = hover.bounds;
+
+        let min_x = widget_min_x.min(window_size.0 - width).max(0.0);
+        let min_y = if widget_max_y + 6.0 + height <= window_size.1 {
+            widget_max_y + 6.0
+        } else {
+            (widget_min_y - 6.0 - height).max(0.0)
+        };
+
+        overlay.quad((min_x, min_y, min_x + width, min_y + height), PANEL_COLOR);
+
+        for (i, (gesture, description)) in help.iter().enumerate() {
+            let y = text_y(
+                min_y + PADDING + i as f32 * ROW_HEIGHT,
+                ROW_HEIGHT,
+                FONT_SIZE,
+            );
+
+            overlay.bold_text((min_x + PADDING, y), *gesture, FONT_SIZE, DIM_TEXT_COLOR);
+            overlay.text(
+                (min_x + PADDING + GESTURE_WIDTH, y),
+                *description,
+                FONT_SIZE,
+                TEXT_COLOR,
+            );
+        }
+    }
+}
@@ -5,6 +5,8 @@ use crate::{render::WidgetTexture, ui::WidgetEvent, widget::Widget};
 
 pub struct ColorSwatchWidget {
     hovering: bool,
+    focused: bool,
+    hue_shift: f32, // degrees
     // t0: Instant,
     colors: Vec<[u8; 4]>,
 }
@@ -32,12 +34,28 @@ impl ColorSwatchWidget {
 
         Self {
             hovering: false,
+            focused: false,
+            hue_shift: 0.0,
             // t0: Instant::now(),
             colors: vec![my_rgb, my_lch, my_hsl, orangish, blueish],
         }
     }
 }
 
+impl ColorSwatchWidget {
+    fn shifted(&self, rgba: [u8; 4]) -> [u8; 4] {
+        if self.hue_shift == 0.0 {
+            return rgba;
+        }
+
+        let color: Srgba = Srgba::<u8>::from(rgba).into_format();
+        let mut lch = Lcha::from_color(color);
+        lch.hue += self.hue_shift;
+
+        Srgba::from_color(lch).into_format().into()
+    }
+}
+
 impl Widget for ColorSwatchWidget {
     fn kind(&self) -> &'static str {
         "color"
@@ -47,10 +65,19 @@ impl Widget for ColorSwatchWidget {
         5
     }
 
+    fn help(&self) -> &'static [(&'static str, &'static str)] {
+        &[("enter, ←/→", "shift the hue")]
+    }
+
     fn event(&mut self, event: WidgetEvent) -> bool {
         match event {
             WidgetEvent::Hover { .. } => self.hovering = true,
             WidgetEvent::Unhover => self.hovering = false,
+            WidgetEvent::Focus => self.focused = true,
+            WidgetEvent::Unfocus => self.focused = false,
+            WidgetEvent::Adjust { steps } => {
+                self.hue_shift = (self.hue_shift + steps * 5.0).rem_euclid(360.0);
+            }
             _ => {}
         }
 
@@ -64,15 +91,32 @@ impl Widget for ColorSwatchWidget {
         if self.hovering {
             frame.clear(&[0, 0, 0, 0xff]);
         } else {
+            let colors = self
+                .colors
+                .iter()
+                .map(|&rgba| self.shifted(rgba))
+                .collect::<Vec<_>>();
+
             for y in 0..frame.height() {
                 for x in 0..frame.width() {
-                    let rgba = self.colors[((x as f32 / frame.width() as f32)
-                        * (self.colors.len() as f32))
+                    let rgba = colors[((x as f32 / frame.width() as f32) * (colors.len() as f32))
                         .floor() as usize];
 
                     frame.set_pixel(x, y, &rgba);
                 }
             }
+
+            if self.focused {
+                let (width, height) = (frame.width(), frame.height());
+                for x in 0..width {
+                    frame.set_pixel(x, 0, &[0, 0, 0, 0xff]);
+                    frame.set_pixel(x, height - 1, &[0, 0, 0, 0xff]);
+                }
+                for y in 0..height {
+                    frame.set_pixel(0, y, &[0, 0, 0, 0xff]);
+                    frame.set_pixel(width - 1, y, &[0, 0, 0, 0xff]);
+                }
+            }
         }
     }
 }
@@ -0,0 +1,213 @@
+use std::f32::consts::PI;
+
+use crate::{
+    render::WidgetTexture,
+    ui::WidgetEvent,
+    widget::{Widget, WidgetValue},
+};
+
+/// How far (in logical pixels) you have to drag a knob vertically to go from min to max
+const KNOB_DRAG_RANGE: f32 = 150.0;
+
+/// The knob's arc goes from 7:30 to 4:30, like on most hardware
+const KNOB_ANGLE_RANGE: f32 = 1.5 * PI;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KnobStyle {
+    /// small, drag vertically
+    Knob,
+    /// wider, click/drag to the position
+    Slider,
+}
+
+/**
+    A number in the code, that you can change by dragging. Placed right after a named parameter (`lowpass{f = ◉}`), the editor also sends its changes directly to the running audio node.
+*/
+pub struct KnobWidget {
+    style: KnobStyle,
+    min: f32,
+    max: f32,
+    default: f32,
+    value: f32,
+    hovering: bool,
+    focused: bool,
+    // mouse y and value at the start of a (knob) drag
+    dragging: Option<(f32, f32)>,
+}
+
+impl KnobWidget {
+    pub fn new(style: KnobStyle, value: f32) -> Self {
+        // (a guess at a sensible range, until we can configure it)
+        let (min, max) = if value < 0.0 {
+            (2.0 * value, -2.0 * value)
+        } else if value <= 1.0 {
+            (0.0, 1.0)
+        } else {
+            (0.0, 2.0 * value)
+        };
+
+        Self {
+            style,
+            min,
+            max,
+            default: value,
+            value,
+            hovering: false,
+            focused: false,
+            dragging: None,
+        }
+    }
+
+    fn fraction(&self) -> f32 {
+        (self.value - self.min) / (self.max - self.min)
+    }
+
+    fn set_fraction(&mut self, fraction: f32) {
+        self.value = self.min + fraction.max(0.0).min(1.0) * (self.max - self.min);
+    }
+
+    fn drag(&mut self, bounds: (f32, f32, f32, f32), mouse: (f32, f32)) {
+        match self.style {
+            KnobStyle::Knob => {
+                if let Some((start_y, start_value)) = self.dragging {
+                    let delta = (start_y - mouse.1) / KNOB_DRAG_RANGE;
+                    self.value = start_value;
+                    self.set_fraction(self.fraction() + delta);
+                }
+            }
+            KnobStyle::Slider => {
+                self.set_fraction(mouse.0 / (bounds.2 - bounds.0));
+            }
+        }
+    }
+
+    fn draw_knob(&self, frame: &mut WidgetTexture, color: [u8; 4], track: [u8; 4]) {
+        let (width, height) = (frame.width() as f32, frame.height() as f32);
+        let (cx, cy) = (width / 2.0, height / 2.0);
+        let radius = width.min(height) / 2.0 - 1.0;
+
+        // angles measured clockwise from straight down
+        let start = (2.0 * PI - KNOB_ANGLE_RANGE) / 2.0;
+        let end = start + self.fraction() * KNOB_ANGLE_RANGE;
+
+        for y in 0..frame.height() {
+            for x in 0..frame.width() {
+                let (dx, dy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+                let r = (dx * dx + dy * dy).sqrt();
+                if r > radius {
+                    continue;
+                }
+
+                let angle = (-dx).atan2(dy).rem_euclid(2.0 * PI);
+
+                if r >= radius * 0.6 {
+                    if angle >= start && angle <= end {
+                        frame.set_pixel(x, y, &color);
+                    } else if angle >= start && angle <= 2.0 * PI - start {
+                        frame.set_pixel(x, y, &track);
+                    }
+                } else if (angle - end).abs() < 0.25 {
+                    // the pointer
+                    frame.set_pixel(x, y, &color);
+                }
+            }
+        }
+    }
+
+    fn draw_slider(&self, frame: &mut WidgetTexture, color: [u8; 4], track: [u8; 4]) {
+        let (width, height) = (frame.width(), frame.height());
+        let filled = (self.fraction() * width as f32).round() as usize;
+
+        for y in (height / 4)..(height - height / 4) {
+            for x in 0..width {
+                frame.set_pixel(x, y, if x < filled { &color } else { &track });
+            }
+        }
+    }
+}
+
+impl Widget for KnobWidget {
+    fn kind(&self) -> &'static str {
+        match self.style {
+            KnobStyle::Knob => "knob",
+            KnobStyle::Slider => "slider",
+        }
+    }
+
+    fn column_width(&self) -> usize {
+        match self.style {
+            KnobStyle::Knob => 2,
+            KnobStyle::Slider => 6,
+        }
+    }
+
+    fn help(&self) -> &'static [(&'static str, &'static str)] {
+        match self.style {
+            KnobStyle::Knob => &[
+                ("drag up/down", "change the value"),
+                ("double-click", "reset"),
+                ("enter, ↑/↓", "change the value"),
+            ],
+            KnobStyle::Slider => &[
+                ("click/drag", "set the value"),
+                ("double-click", "reset"),
+                ("enter, ←/→", "change the value"),
+            ],
+        }
+    }
+
+    fn event(&mut self, event: WidgetEvent) -> bool {
+        match event {
+            WidgetEvent::Hover { .. } => self.hovering = true,
+            WidgetEvent::Unhover => self.hovering = false,
+            WidgetEvent::MouseDown { bounds, mouse, .. } => {
+                self.dragging = Some((mouse.1, self.value));
+                self.drag(bounds, mouse);
+
+                // we'll handle the drag ourselves, thank you
+                return true;
+            }
+            WidgetEvent::MouseMove { bounds, mouse } => {
+                self.drag(bounds, mouse);
+            }
+            WidgetEvent::MouseUp => self.dragging = None,
+            WidgetEvent::Press { double: true, .. } => {
+                self.value = self.default;
+            }
+            WidgetEvent::Focus => self.focused = true,
+            WidgetEvent::Unfocus => self.focused = false,
+            WidgetEvent::Adjust { steps } => {
+                self.set_fraction(self.fraction() + steps * 0.01);
+            }
+            _ => {}
+        }
+
+        false
+    }
+
+    fn value(&self) -> Option<WidgetValue> {
+        Some(WidgetValue::Number(self.value))
+    }
+
+    fn draw(&self, frame: &mut WidgetTexture) {
+        let active = self.hovering || self.focused || self.dragging.is_some();
+
+        let color = if active {
+            [0x00, 0x00, 0x00, 0xff]
+        } else {
+            [0x44, 0x44, 0x44, 0xff]
+        };
+        let track = [0xcc, 0xcc, 0xcc, 0xff];
+
+        frame.clear(&[0, 0, 0, 0]);
+
+        match self.style {
+            KnobStyle::Knob => self.draw_knob(frame, color, track),
+            KnobStyle::Slider => self.draw_slider(frame, color, track),
+        }
+    }
+
+    fn describe(&self) -> String {
+        format!("{:.2}", self.value)
+    }
+}
@@ -0,0 +1,217 @@
+use crate::{
+    pattern::Pattern,
+    render::WidgetTexture,
+    ui::{WidgetEvent, WidgetKey},
+    widget::{Widget, WidgetValue},
+};
+
+const LANES: usize = 4;
+const STEPS: usize = 16;
+
+/// Alt-clicking an active cell cycles through these
+const VELOCITIES: &[f32] = &[1.0, 0.75, 0.5, 0.25];
+
+pub struct MatrixWidget {
+    pattern: Pattern,
+    hovering: Option<(usize, usize)>, // (lane, step)
+    focused: bool,
+    // the cell that the keyboard steps through, while focused
+    cursor: (usize, usize),
+    // while dragging, every cell we pass over gets this value
+    painting: Option<Option<f32>>,
+}
+
+impl MatrixWidget {
+    pub fn new() -> Self {
+        Self {
+            pattern: Pattern::new(LANES, STEPS),
+            hovering: None,
+            focused: false,
+            cursor: (0, 0),
+            painting: None,
+        }
+    }
+
+    fn cell_at(&self, bounds: (f32, f32, f32, f32), mouse: (f32, f32)) -> Option<(usize, usize)> {
+        let (width, height) = (bounds.2 - bounds.0, bounds.3 - bounds.1);
+        if mouse.0 < 0.0 || mouse.1 < 0.0 || mouse.0 >= width || mouse.1 >= height {
+            return None;
+        }
+
+        let step = ((mouse.0 / width) * STEPS as f32).floor() as usize;
+        let lane = ((mouse.1 / height) * LANES as f32).floor() as usize;
+
+        Some((lane.min(LANES - 1), step.min(STEPS - 1)))
+    }
+
+    /**
+        What a cell becomes when it's clicked: on, or off again, or (with alt) the next velocity
+    */
+    fn toggled(&self, lane: usize, step: usize, alt: bool) -> Option<f32> {
+        match (self.pattern.get(lane, step), alt) {
+            (Some(velocity), true) => {
+                let i = VELOCITIES.iter().position(|&v| v <= velocity).unwrap_or(0);
+                Some(VELOCITIES[(i + 1) % VELOCITIES.len()])
+            }
+            (Some(_), false) => None,
+            (None, _) => Some(VELOCITIES[0]),
+        }
+    }
+
+    fn paint(&mut self, cell: Option<(usize, usize)>) {
+        if let (Some(value), Some((lane, step))) = (self.painting, cell) {
+            self.pattern.set(lane, step, value);
+        }
+    }
+}
+
+impl Widget for MatrixWidget {
+    fn kind(&self) -> &'static str {
+        "matrix"
+    }
+
+    fn column_width(&self) -> usize {
+        8
+    }
+
+    fn event(&mut self, event: WidgetEvent) -> bool {
+        match event {
+            WidgetEvent::Hover { bounds, mouse } => {
+                self.hovering = self.cell_at(bounds, mouse);
+            }
+            WidgetEvent::Unhover => self.hovering = None,
+            WidgetEvent::MouseDown {
+                bounds, mouse, alt, ..
+            } => {
+                let Some((lane, step)) = self.cell_at(bounds, mouse) else {
+                    return false;
+                };
+
+                let value = self.toggled(lane, step, alt);
+
+                // (the keyboard carries on from where it's clicked)
+                self.cursor = (lane, step);
+                self.painting = Some(value);
+                self.paint(Some((lane, step)));
+
+                // we'll handle the drag ourselves, thank you
+                return true;
+            }
+            WidgetEvent::MouseMove { bounds, mouse } => {
+                let cell = self.cell_at(bounds, mouse);
+                self.hovering = cell;
+                self.paint(cell);
+            }
+            WidgetEvent::MouseUp => self.painting = None,
+            WidgetEvent::Focus => self.focused = true,
+            WidgetEvent::Unfocus => self.focused = false,
+            WidgetEvent::Key { key, alt, .. } => {
+                let (lane, step) = self.cursor;
+
+                match key {
+                    WidgetKey::Up => self.cursor.0 = (lane + LANES - 1) % LANES,
+                    WidgetKey::Down => self.cursor.0 = (lane + 1) % LANES,
+                    WidgetKey::Left => self.cursor.1 = (step + STEPS - 1) % STEPS,
+                    WidgetKey::Right => self.cursor.1 = (step + 1) % STEPS,
+                    WidgetKey::Space | WidgetKey::Enter => {
+                        let value = self.toggled(lane, step, alt);
+                        self.pattern.set(lane, step, value);
+                    }
+                    WidgetKey::Backspace => self.pattern.set(lane, step, None),
+                    _ => return false,
+                }
+
+                return true;
+            }
+            _ => {}
+        }
+
+        false
+    }
+
+    fn expanded_size(&self) -> Option<(f32, f32)> {
+        Some((384.0, 128.0))
+    }
+
+    fn help(&self) -> &'static [(&'static str, &'static str)] {
+        &[
+            ("click", "toggle a step"),
+            ("drag", "paint (or erase) steps"),
+            ("alt-click", "cycle a step's velocity"),
+            ("arrows", "step through the cells (when focused)"),
+            ("space", "toggle the cell (alt: cycle its velocity)"),
+            ("double-click", "open it up bigger (esc closes it)"),
+        ]
+    }
+
+    fn value(&self) -> Option<WidgetValue> {
+        Some(WidgetValue::Pattern(self.pattern.clone()))
+    }
+
+    fn draw(&self, frame: &mut WidgetTexture) {
+        // physical pixels, btw
+        let width = frame.width();
+        let height = frame.height();
+
+        let background: [u8; 4] = if self.focused {
+            [0x00, 0x00, 0x00, 0xff]
+        } else {
+            [0xe5, 0xe5, 0xe5, 0xff]
+        };
+
+        frame.clear(&background);
+
+        for lane in 0..LANES {
+            for step in 0..STEPS {
+                let (x0, x1) = (step * width / STEPS, (step + 1) * width / STEPS);
+                let (y0, y1) = (lane * height / LANES, (lane + 1) * height / LANES);
+
+                // beats are a bit darker, so it's easier to count along
+                let off = if step % 4 == 0 { 0xbb } else { 0xcc };
+
+                let rgba = match self.pattern.get(lane, step) {
+                    Some(velocity) => {
+                        let c = (0xaa as f32 * (1.0 - velocity)).round() as u8;
+                        [c, c, c, 0xff]
+                    }
+                    None if self.hovering == Some((lane, step)) => [0x99, 0x99, 0x99, 0xff],
+                    None => [off, off, off, 0xff],
+                };
+
+                // (the keyboard's cell, while focused, is tinted blue)
+                let rgba = if self.focused && self.cursor == (lane, step) {
+                    [rgba[0] / 2, rgba[1] / 2 + 0x20, rgba[2] / 2 + 0x7f, 0xff]
+                } else {
+                    rgba
+                };
+
+                // leave a 1px gap between the cells
+                for y in (y0 + 1)..y1 {
+                    for x in (x0 + 1)..x1 {
+                        frame.set_pixel(x, y, &rgba);
+                    }
+                }
+            }
+        }
+    }
+
+    fn describe(&self) -> String {
+        let lanes = self
+            .pattern
+            .lanes
+            .iter()
+            .map(|cells| {
+                cells
+                    .iter()
+                    .map(|cell| match cell {
+                        Some(velocity) if *velocity >= 1.0 => 'X',
+                        Some(_) => 'x',
+                        None => '.',
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>();
+
+        format!("[{}]", lanes.join(" "))
+    }
+}
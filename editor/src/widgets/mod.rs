@@ -1,2 +1,6 @@
 pub mod color_swatch;
+pub mod knob;
+pub mod matrix;
+pub mod piano_roll;
 pub mod sample;
+pub mod scope;
@@ -0,0 +1,336 @@
+use crate::{
+    pattern::{Note, NotePattern},
+    render::WidgetTexture,
+    ui::WidgetEvent,
+    widget::{Widget, WidgetValue},
+};
+
+const STEPS: usize = 16;
+
+/// At least this many pitches are shown, even if the notes span fewer
+const MIN_PITCHES: u8 = 12;
+
+/// Dragging snaps to whole steps, or to quarter steps when the drag started with shift held
+const GRID: f32 = 1.0;
+const FINE_GRID: f32 = 0.25;
+
+/// How close (in logical pixels) to a note's end you have to grab it to resize instead of move it
+const RESIZE_HANDLE: f32 = 4.0;
+
+/// Alt-clicking a note cycles through these
+const VELOCITIES: &[f32] = &[1.0, 0.75, 0.5, 0.25];
+
+#[derive(Debug, Clone, Copy)]
+enum Drag {
+    Move { note: usize, offset: f32 },
+    Resize { note: usize },
+}
+
+/**
+    A note pattern (like `[c4@0:2 e4@2:1]`) as a little piano roll. Click to add notes, drag them around or by their end, and the result is written back into the pattern syntax.
+*/
+pub struct PianoRollWidget {
+    pattern: NotePattern,
+    // the lowest and highest pitch shown, which is refitted to the notes whenever an edit is done
+    low: u8,
+    high: u8,
+    hovering: Option<(u8, f32)>, // (pitch, step)
+    focused: bool,
+    dragging: Option<Drag>,
+    grid: f32,
+    // (whether the last right-click removed a note, in which case it doesn't open the context menu)
+    removed_note: bool,
+}
+
+impl PianoRollWidget {
+    pub fn new() -> Self {
+        Self::with_pattern(NotePattern::new(STEPS))
+    }
+
+    pub fn with_pattern(pattern: NotePattern) -> Self {
+        let mut widget = Self {
+            pattern,
+            low: 0,
+            high: 0,
+            hovering: None,
+            focused: false,
+            dragging: None,
+            grid: GRID,
+            removed_note: false,
+        };
+
+        widget.refit();
+        widget
+    }
+
+    fn refit(&mut self) {
+        let (low, high) = self
+            .pattern
+            .notes
+            .iter()
+            .fold(None, |range: Option<(u8, u8)>, note| match range {
+                Some((low, high)) => Some((low.min(note.pitch), high.max(note.pitch))),
+                None => Some((note.pitch, note.pitch)),
+            })
+            // (an octave from middle c, to start with)
+            .unwrap_or((60, 60 + MIN_PITCHES - 1));
+
+        // (widened around the notes, if they span less than the minimum)
+        let span = (high - low).max(MIN_PITCHES - 1);
+        let low = low
+            .saturating_sub((span - (high - low)) / 2)
+            .min(127 - span);
+
+        self.low = low;
+        self.high = low + span;
+    }
+
+    fn rows(&self) -> usize {
+        (self.high - self.low) as usize + 1
+    }
+
+    /**
+        The pitch and (fractional) step under the mouse
+    */
+    fn position_at(&self, bounds: (f32, f32, f32, f32), mouse: (f32, f32)) -> (u8, f32) {
+        let (width, height) = (bounds.2 - bounds.0, bounds.3 - bounds.1);
+
+        let step = (mouse.0 / width).clamp(0.0, 1.0) * self.pattern.steps as f32;
+        let row = ((mouse.1 / height).clamp(0.0, 0.999) * self.rows() as f32).floor() as u8;
+
+        (self.high - row, step)
+    }
+
+    fn contains(bounds: (f32, f32, f32, f32), mouse: (f32, f32)) -> bool {
+        let (width, height) = (bounds.2 - bounds.0, bounds.3 - bounds.1);
+        mouse.0 >= 0.0 && mouse.1 >= 0.0 && mouse.0 < width && mouse.1 < height
+    }
+
+    fn snap(&self, step: f32) -> f32 {
+        (step / self.grid).floor() * self.grid
+    }
+
+    fn drag(&mut self, pitch: u8, step: f32) {
+        let steps = self.pattern.steps as f32;
+        let grid = self.grid;
+
+        match self.dragging {
+            Some(Drag::Move { note, offset }) => {
+                let start = self.snap(step - offset);
+                if let Some(note) = self.pattern.notes.get_mut(note) {
+                    note.start = start.clamp(0.0, steps - note.length);
+                    note.pitch = pitch;
+                }
+            }
+            Some(Drag::Resize { note }) => {
+                let end = (step / grid).round() * grid;
+                if let Some(note) = self.pattern.notes.get_mut(note) {
+                    note.length = (end - note.start).clamp(grid, steps - note.start);
+                }
+            }
+            None => {}
+        }
+    }
+}
+
+impl Widget for PianoRollWidget {
+    fn kind(&self) -> &'static str {
+        "piano_roll"
+    }
+
+    fn column_width(&self) -> usize {
+        16
+    }
+
+    fn event(&mut self, event: WidgetEvent) -> bool {
+        match event {
+            WidgetEvent::Hover { bounds, mouse } => {
+                self.hovering =
+                    Self::contains(bounds, mouse).then(|| self.position_at(bounds, mouse));
+            }
+            WidgetEvent::Unhover => self.hovering = None,
+            WidgetEvent::MouseDown {
+                bounds,
+                mouse,
+                right_click,
+                shift,
+                alt,
+                meta_or_ctrl,
+            } => {
+                if !Self::contains(bounds, mouse) {
+                    return false;
+                }
+
+                let (pitch, step) = self.position_at(bounds, mouse);
+                self.grid = if shift { FINE_GRID } else { GRID };
+                self.removed_note = false;
+
+                match self.pattern.note_at(pitch, step) {
+                    Some(i) if right_click || meta_or_ctrl => {
+                        self.pattern.notes.remove(i);
+                        self.refit();
+                        self.removed_note = right_click;
+                    }
+                    Some(i) if alt => {
+                        let note = &mut self.pattern.notes[i];
+                        let j = VELOCITIES
+                            .iter()
+                            .position(|&v| v <= note.velocity)
+                            .unwrap_or(0);
+                        note.velocity = VELOCITIES[(j + 1) % VELOCITIES.len()];
+                    }
+                    Some(i) => {
+                        let note = self.pattern.notes[i];
+                        let px_per_step = (bounds.2 - bounds.0) / self.pattern.steps as f32;
+                        let from_end = (note.start + note.length - step) * px_per_step;
+
+                        self.dragging = Some(if from_end <= RESIZE_HANDLE {
+                            Drag::Resize { note: i }
+                        } else {
+                            Drag::Move {
+                                note: i,
+                                offset: step - note.start,
+                            }
+                        });
+
+                        return true;
+                    }
+                    None if right_click => {}
+                    None => {
+                        // a new note, which you can drag out to the length you want
+                        self.pattern.notes.push(Note {
+                            pitch,
+                            start: self.snap(step),
+                            length: self.grid,
+                            velocity: 1.0,
+                        });
+                        self.dragging = Some(Drag::Resize {
+                            note: self.pattern.notes.len() - 1,
+                        });
+
+                        return true;
+                    }
+                }
+            }
+            WidgetEvent::MouseMove { bounds, mouse } => {
+                let (pitch, step) = self.position_at(bounds, mouse);
+                self.hovering = Self::contains(bounds, mouse).then_some((pitch, step));
+                self.drag(pitch, step);
+            }
+            WidgetEvent::MouseUp => {
+                self.dragging = None;
+                self.refit();
+            }
+            WidgetEvent::Press {
+                right_click: true, ..
+            } => return self.removed_note,
+            WidgetEvent::Focus => self.focused = true,
+            WidgetEvent::Unfocus => self.focused = false,
+            WidgetEvent::Adjust { steps } => {
+                // transpose everything
+                let semitones = steps.round() as i32;
+                if self
+                    .pattern
+                    .notes
+                    .iter()
+                    .all(|n| (0..128).contains(&(n.pitch as i32 + semitones)))
+                {
+                    for note in &mut self.pattern.notes {
+                        note.pitch = (note.pitch as i32 + semitones) as u8;
+                    }
+                    self.refit();
+                }
+            }
+            _ => {}
+        }
+
+        false
+    }
+
+    fn expanded_size(&self) -> Option<(f32, f32)> {
+        Some((512.0, 256.0))
+    }
+
+    fn help(&self) -> &'static [(&'static str, &'static str)] {
+        &[
+            ("click", "add a note"),
+            ("drag", "move a note, or resize it by its end"),
+            ("shift-drag", "snap to quarter steps"),
+            ("alt-click", "cycle a note's velocity"),
+            ("right-click", "remove a note"),
+            ("arrows", "transpose (when focused)"),
+            ("double-click", "open it up bigger (esc closes it)"),
+        ]
+    }
+
+    fn value(&self) -> Option<WidgetValue> {
+        Some(WidgetValue::Notes(self.pattern.clone()))
+    }
+
+    fn draw(&self, frame: &mut WidgetTexture) {
+        // physical pixels, btw
+        let width = frame.width();
+        let height = frame.height();
+        let rows = self.rows();
+        let steps = self.pattern.steps;
+
+        let background: [u8; 4] = if self.focused {
+            [0x00, 0x00, 0x00, 0xff]
+        } else {
+            [0xe5, 0xe5, 0xe5, 0xff]
+        };
+
+        frame.clear(&background);
+
+        let x_at = |step: f32| ((step / steps as f32) * width as f32).round() as usize;
+        let row_y = |row: usize| (row * height / rows, (row + 1) * height / rows);
+
+        // the keys, black ones a bit darker, and beats marked, so it's easier to count along
+        for row in 0..rows {
+            let pitch = self.high as usize - row;
+            let black = matches!(pitch % 12, 1 | 3 | 6 | 8 | 10);
+            let (y0, y1) = row_y(row);
+
+            for step in 0..steps {
+                let off = match (black, step % 4 == 0) {
+                    (true, _) => 0xc4,
+                    (false, true) => 0xcc,
+                    (false, false) => 0xd8,
+                };
+                let rgba = match self.hovering {
+                    Some((p, s)) if p as usize == pitch && s.floor() as usize == step => {
+                        [0x99, 0x99, 0x99, 0xff]
+                    }
+                    _ => [off, off, off, 0xff],
+                };
+
+                for y in y0..y1 {
+                    for x in (x_at(step as f32) + 1)..x_at(step as f32 + 1.0) {
+                        frame.set_pixel(x, y, &rgba);
+                    }
+                }
+            }
+        }
+
+        for note in &self.pattern.notes {
+            if note.pitch < self.low || note.pitch > self.high {
+                continue;
+            }
+
+            let (y0, y1) = row_y((self.high - note.pitch) as usize);
+            let c = (0xaa as f32 * (1.0 - note.velocity)).round() as u8;
+
+            // (1px gap at the end, so consecutive notes stay apart)
+            let (x0, x1) = (x_at(note.start), x_at(note.start + note.length));
+            for y in y0..y1.max(y0 + 1) {
+                for x in x0..x1.saturating_sub(1).max(x0 + 1).min(width) {
+                    frame.set_pixel(x, y, &[c, c, c, 0xff]);
+                }
+            }
+        }
+    }
+
+    fn describe(&self) -> String {
+        self.pattern.to_string()
+    }
+}
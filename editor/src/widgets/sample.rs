@@ -1,8 +1,38 @@
-use creak;
 use rfd::FileDialog;
-use std::{cell::RefCell, time::Instant};
-
-use crate::{render::WidgetTexture, ui::WidgetEvent, widget::Widget};
+#[cfg(not(target_arch = "wasm32"))]
+use std::thread;
+use std::{
+    cell::RefCell,
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver, TryRecvError},
+};
+
+use crate::{
+    audio_cache::{decode_mono, AudioSummary},
+    project::SamplePaths,
+    render::WidgetTexture,
+    ui::{WidgetAction, WidgetEvent},
+    util::reveal_file,
+    widget::{SampleMarkers, Widget, WidgetValue},
+};
+
+/// How close (in logical pixels) a click has to be to a marker to grab it
+const MARKER_REACH: f32 = 3.0;
+/// (markers can't be dragged closer together than this, as a fraction of the length)
+const MIN_SLICE: f32 = 0.005;
+
+const MARKER: [u8; 4] = [0xff, 0x66, 0x00, 0xff];
+const LOOP_MARKER: [u8; 4] = [0x00, 0x99, 0x55, 0xff];
+const CUE_MARKER: [u8; 4] = [0x22, 0x66, 0xff, 0xff];
+
+/// The markers that can be dragged around on the waveform
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Handle {
+    Slice(usize),
+    LoopStart,
+    LoopEnd,
+    Cue(usize),
+}
 
 struct Theme {
     background: [u8; 4],
@@ -17,21 +47,51 @@ struct Summary {
 }
 
 pub struct SampleWidget {
-    filepath: Option<String>,
+    paths: SamplePaths,
+    // as the project refers to it, and where that is on this machine
+    filepath: Option<(String, PathBuf)>,
     selected: bool,
+    focused: bool,
     hovering: Option<f32>, // x within widget
-    samples: Option<Vec<f32>>,
+    start: f32,            // where playback starts, 0..1
+    audio: RefCell<Option<AudioSummary>>,
+    // while the audio is being decoded (or read from the cache) in the background
+    loading: RefCell<Option<Receiver<Result<AudioSummary, String>>>>,
+    // (why the audio couldn't be read, if it couldn't)
+    error: RefCell<Option<String>>,
     summary: RefCell<Option<Summary>>,
+    // whether it's wrapped in `slices(...)` in the code, and where its slices start (0..1, kept when it's unwrapped, in case it's wrapped again)
+    sliced: bool,
+    slices: RefCell<Option<Vec<f32>>>,
+    // while its hits are being detected, in the background
+    detecting: RefCell<Option<Receiver<Result<Vec<f32>, String>>>>,
+    // where it loops, and where it can be cued from (0..1, set when it's not sliced)
+    markers: SampleMarkers,
+    // (the marker that's being dragged)
+    dragging: Option<Handle>,
 }
 
 impl SampleWidget {
-    pub fn new(filepath: impl Into<String>) -> Self {
+    /**
+        A sample, by its path as written in the project (see `SamplePaths`)
+    */
+    pub fn new(filepath: impl Into<String>, paths: SamplePaths) -> Self {
         let mut widget = Self {
+            paths,
             filepath: None,
             selected: false,
+            focused: false,
             hovering: None,
-            samples: None,
+            start: 0.0,
+            audio: RefCell::new(None),
+            loading: RefCell::new(None),
+            error: RefCell::new(None),
             summary: RefCell::new(None),
+            sliced: false,
+            slices: RefCell::new(None),
+            detecting: RefCell::new(None),
+            markers: SampleMarkers::default(),
+            dragging: None,
         };
 
         widget.read(filepath.into());
@@ -39,41 +99,255 @@ impl SampleWidget {
         widget
     }
 
-    fn read(&mut self, filepath: String) -> bool {
-        let decoder = creak::Decoder::open(&filepath).ok();
+    /**
+        A sample file from somewhere on disk, which the project will refer to as portably as possible
+    */
+    pub fn from_file(file: &Path, paths: SamplePaths) -> Self {
+        let filepath = paths.relative(file);
+        Self::new(filepath, paths)
+    }
 
-        let Some(decoder) = decoder else {
-            println!("Could not read audio file at: {:?}", filepath);
-            return false;
+    fn read(&mut self, filepath: String) {
+        let resolved = self.paths.resolve(&filepath);
+        let (sender, receiver) = channel();
+
+        {
+            let filepath = filepath.clone();
+            let resolved = resolved.clone();
+
+            let load = move || {
+                let audio = AudioSummary::load(&resolved).map_err(|e| {
+                    format!(
+                        "Could not read audio file at: {:?} ({:?}) ({})",
+                        filepath, resolved, e
+                    )
+                });
+
+                let _ = sender.send(audio);
+            };
+
+            // (in the browser, there are no threads, but this doesn't block there either: it only starts fetching, see `decode_mono`)
+            #[cfg(not(target_arch = "wasm32"))]
+            thread::spawn(load);
+            #[cfg(target_arch = "wasm32")]
+            load();
+        }
+
+        self.loading.replace(Some(receiver));
+        self.filepath = Some((filepath, resolved));
+    }
+
+    /**
+        Picks up the audio, if it's done loading
+    */
+    fn poll_loading(&self) {
+        let mut loading = self.loading.borrow_mut();
+        let Some(receiver) = loading.as_ref() else {
+            return;
+        };
+
+        match receiver.try_recv() {
+            Ok(Ok(audio)) => {
+                tracing::debug!(
+                    "Format: {}; Channels: {}; Sample Rate: {}Hz",
+                    audio.info.format,
+                    audio.info.channels,
+                    audio.info.sample_rate
+                );
+
+                self.audio.replace(Some(audio));
+                self.summary.replace(None);
+                self.error.replace(None);
+            }
+            Ok(Err(e)) => {
+                tracing::warn!("{}", e);
+                self.audio.replace(None);
+                self.error.replace(Some(e));
+            }
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => {}
+        }
+
+        *loading = None;
+    }
+
+    /**
+        Finds where the hits are (decoding the whole file again, because the summary's too coarse for that), in the background
+    */
+    fn detect_slices(&mut self) {
+        let Some((filepath, resolved)) = self.filepath.clone() else {
+            return;
+        };
+
+        let (sender, receiver) = channel();
+
+        let detect = move || {
+            let slices = decode_mono(&resolved)
+                .map(|(samples, sample_rate)| live_engine::detect_slices(&samples, sample_rate))
+                .map_err(|e| format!("Could not slice {:?} ({})", filepath, e));
+
+            let _ = sender.send(slices);
         };
 
-        let info = decoder.info();
-        println!(
-            "Format: {}; Channels: {}; Sample Rate: {}Hz",
-            info.format(),
-            info.channels(),
-            info.sample_rate()
-        );
+        #[cfg(not(target_arch = "wasm32"))]
+        thread::spawn(detect);
+        #[cfg(target_arch = "wasm32")]
+        detect();
 
-        self.samples = decoder.into_samples().ok().and_then(|iter| {
-            let mut samples = vec![];
+        self.detecting.replace(Some(receiver));
+    }
+
+    /**
+        Picks up the slices, if they're done being detected
+    */
+    fn poll_detecting(&self) {
+        let mut detecting = self.detecting.borrow_mut();
+        let Some(receiver) = detecting.as_ref() else {
+            return;
+        };
 
-            for sample in iter {
-                samples.push(sample.ok()?);
+        match receiver.try_recv() {
+            Ok(Ok(slices)) => {
+                self.slices.replace(Some(slices));
+            }
+            Ok(Err(e)) => {
+                tracing::warn!("{}", e);
+                self.error.replace(Some(e));
             }
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => {}
+        }
 
-            Some(samples)
-        });
+        *detecting = None;
+    }
 
-        if self.samples.is_some() {
-            println!("  READ :)");
-            self.filepath = Some(filepath);
-            true
-        } else {
-            println!("  error reading samples :(");
-            false
+    /**
+        Where in the sample (0..1) a point in the widget is, horizontally (in logical pixels, relative to the widget)
+    */
+    fn position_at(bounds: (f32, f32, f32, f32), x: f32) -> f32 {
+        // (see `draw`, which leaves a margin of 2 physical pixels on the left, and 4 on the right)
+        let width = (bounds.2 - bounds.0) * 2.0;
+        ((x * 2.0 - 2.0) / (width - 6.0)).clamp(0.0, 1.0)
+    }
+
+    /**
+        The markers that are shown, and where they are: the slices when it's sliced (but not the first one, which is always at the start), and otherwise the loop points and cues
+    */
+    fn handles(&self) -> Vec<(Handle, f32)> {
+        if self.sliced {
+            return self.slices.borrow().as_ref().map_or(vec![], |slices| {
+                slices
+                    .iter()
+                    .enumerate()
+                    .skip(1)
+                    .map(|(i, &start)| (Handle::Slice(i), start))
+                    .collect()
+            });
+        }
+
+        let loop_region = self
+            .markers
+            .loop_region
+            .iter()
+            .flat_map(|&(start, end)| [(Handle::LoopStart, start), (Handle::LoopEnd, end)]);
+        let cues = self.markers.cues.iter().enumerate();
+        let cues = cues.map(|(i, &at)| (Handle::Cue(i), at));
+
+        loop_region.chain(cues).collect()
+    }
+
+    /**
+        The marker near a point, if there's one
+    */
+    fn handle_at(&self, bounds: (f32, f32, f32, f32), x: f32) -> Option<Handle> {
+        let reach = MARKER_REACH / (bounds.2 - bounds.0);
+        let position = Self::position_at(bounds, x);
+
+        let (handle, distance) = self
+            .handles()
+            .into_iter()
+            .map(|(handle, at)| (handle, (at - position).abs()))
+            .min_by(|a, b| a.1.total_cmp(&b.1))?;
+
+        (distance <= reach).then_some(handle)
+    }
+
+    /**
+        Starts a new slice there when it's sliced, or adds a cue point otherwise
+    */
+    fn add_marker(&mut self, position: f32) {
+        if !self.sliced {
+            let i = self.markers.cues.partition_point(|&at| at < position);
+            self.markers.cues.insert(i, position);
+        } else if let Some(slices) = self.slices.borrow_mut().as_mut() {
+            let i = slices.partition_point(|&start| start < position);
+            if i > 0 {
+                slices.insert(i, position);
+            }
+        }
+    }
+
+    fn remove_marker(&mut self, handle: Handle) {
+        match handle {
+            Handle::Slice(i) => {
+                if let Some(slices) = self.slices.borrow_mut().as_mut() {
+                    slices.remove(i);
+                }
+            }
+            Handle::LoopStart | Handle::LoopEnd => self.markers.loop_region = None,
+            Handle::Cue(i) => {
+                self.markers.cues.remove(i);
+            }
+        }
+    }
+
+    fn move_marker(&mut self, handle: Handle, position: f32) {
+        match handle {
+            Handle::Slice(i) => {
+                if let Some(slices) = self.slices.borrow_mut().as_mut() {
+                    keep_in_between(slices, i, position);
+                }
+            }
+            Handle::Cue(i) => keep_in_between(&mut self.markers.cues, i, position),
+            Handle::LoopStart | Handle::LoopEnd => {
+                let Some((start, end)) = &mut self.markers.loop_region else {
+                    return;
+                };
+
+                if handle == Handle::LoopStart {
+                    *start = position;
+                } else {
+                    *end = position;
+                }
+
+                // (dragging one past the other turns it into the other one)
+                if start > end {
+                    std::mem::swap(start, end);
+                    self.dragging = Some(match handle {
+                        Handle::LoopStart => Handle::LoopEnd,
+                        _ => Handle::LoopStart,
+                    });
+                }
+            }
         }
     }
+
+    /**
+        Asks for another audio file, returning whether one was picked
+    */
+    fn pick_file(&mut self) -> bool {
+        let Some(filepath) = FileDialog::new()
+            .add_filter("audio", &["wav", "mp3", "ogg", "flac"])
+            // .set_directory("~")
+            .pick_file()
+        else {
+            return false;
+        };
+
+        let filepath = self.paths.relative(&filepath);
+        self.read(filepath);
+        true
+    }
 }
 
 impl Widget for SampleWidget {
@@ -82,7 +356,34 @@ impl Widget for SampleWidget {
     }
 
     fn column_width(&self) -> usize {
-        6
+        // (wider when it's sliced, to make room for the slices)
+        if self.sliced {
+            12
+        } else {
+            6
+        }
+    }
+
+    fn help(&self) -> &'static [(&'static str, &'static str)] {
+        if self.sliced {
+            return &[
+                ("drag a marker", "move where a slice starts"),
+                ("shift-click", "start a new slice there"),
+                ("alt-click a marker", "remove it (joining two slices)"),
+                ("double-click", "pick another audio file"),
+            ];
+        }
+
+        &[
+            ("double-click", "pick another audio file"),
+            ("drop a file", "insert a new sample"),
+            ("enter, ←/→", "move the playback start"),
+            ("cmd-drag", "set where it loops"),
+            ("shift-click", "add a cue point"),
+            ("drag a marker", "move a loop point or cue"),
+            ("alt-click a marker", "remove it"),
+            ("slices(...)", "cut it up where its hits are"),
+        ]
     }
 
     fn event(&mut self, event: WidgetEvent) -> bool {
@@ -93,17 +394,54 @@ impl Widget for SampleWidget {
                 self.hovering = Some((mouse.0 - bounds.0) * 2.0)
             }
             WidgetEvent::Unhover => self.hovering = None,
-            WidgetEvent::MouseDown { .. } => {
-                self.selected = true;
+            WidgetEvent::MouseDown {
+                bounds,
+                mouse,
+                shift,
+                alt,
+                meta_or_ctrl,
+                ..
+            } => {
+                let position = Self::position_at(bounds, mouse.0);
+
+                match self.handle_at(bounds, mouse.0) {
+                    Some(handle) if alt => self.remove_marker(handle),
+                    Some(handle) => {
+                        self.dragging = Some(handle);
+                        return true;
+                    }
+                    None if shift && (!self.sliced || self.slices.borrow().is_some()) => {
+                        self.add_marker(position);
+                    }
+                    None if meta_or_ctrl && !self.sliced => {
+                        // (a new loop, from here to wherever it's dragged)
+                        self.markers.loop_region = Some((position, position));
+                        self.dragging = Some(Handle::LoopEnd);
+                        return true;
+                    }
+                    None => self.selected = true,
+                }
             }
-            WidgetEvent::Press { double, .. } => {
-                if double && let Some(filepath) = FileDialog::new()
-                    .add_filter("audio", &["wav", "mp3", "ogg", "flac"])
-                    // .set_directory("~")
-                    .pick_file()
+            WidgetEvent::MouseMove { bounds, mouse } => {
+                let Some(handle) = self.dragging else {
+                    return false;
+                };
+
+                self.move_marker(handle, Self::position_at(bounds, mouse.0));
+            }
+            WidgetEvent::MouseUp => {
+                // (a loop that's too short to hear is just a click, which removes it)
+                if let Some((start, end)) = self.markers.loop_region
+                    && end - start < MIN_SLICE
                 {
-                    let filepath = filepath.as_path().to_str().unwrap();
-                    self.read(filepath.into());
+                    self.markers.loop_region = None;
+                }
+
+                self.dragging = None;
+            }
+            WidgetEvent::Press { double, .. } => {
+                if double {
+                    self.pick_file();
                 }
 
                 return false;
@@ -113,77 +451,132 @@ impl Widget for SampleWidget {
                     self.selected = false;
                 }
             }
+            WidgetEvent::Focus => self.focused = true,
+            WidgetEvent::Unfocus => self.focused = false,
+            WidgetEvent::Adjust { steps } => {
+                self.start = (self.start + steps * 0.01).max(0.0).min(1.0);
+            }
+            WidgetEvent::Action(WidgetAction::RevealFile) => {
+                if let Some((_, resolved)) = &self.filepath {
+                    reveal_file(resolved);
+                }
+            }
+            WidgetEvent::Action(WidgetAction::ReplaceFile) => {
+                self.relocate();
+            }
             _ => {}
         }
 
         false
     }
 
-    fn draw(&self, frame: &mut WidgetTexture) {
-        // physical pixels, btw
-        let width = frame.width();
-        let height = frame.height();
+    fn actions(&self) -> &'static [WidgetAction] {
+        if self.filepath.is_some() {
+            &[WidgetAction::RevealFile, WidgetAction::ReplaceFile]
+        } else {
+            &[WidgetAction::ReplaceFile]
+        }
+    }
 
-        let Some(samples) = &self.samples else {
-            frame.clear(&[0xff, 0x00, 0x00, 0xff]);
-            return;
+    fn reference(&self) -> Option<String> {
+        let (_, resolved) = self.filepath.as_ref()?;
+
+        // (`path()` is relative to the project root, it doesn't know about sample packs or search directories)
+        let path = match resolved.strip_prefix(&self.paths.root) {
+            Ok(rest) => rest.to_string_lossy().replace('\\', "/"),
+            Err(_) => resolved.to_string_lossy().into_owned(),
         };
 
-        let mut summary = self.summary.borrow_mut();
-        let summary = summary.get_or_insert_with(|| {
-            let t0 = Instant::now();
+        Some(format!("path({:?})", path))
+    }
 
-            let num_samples = samples.len();
-            // physical pixels, btw
-            let samples_per_pixel = num_samples / (width - 4);
+    fn wrapped_in(&mut self, function: Option<&str>) -> bool {
+        let sliced = function == Some("slices");
+        if sliced == self.sliced {
+            return false;
+        }
+
+        if sliced && self.slices.borrow().is_none() {
+            self.detect_slices();
+        }
+
+        self.sliced = sliced;
+        true
+    }
 
-            // (min, max, rms)
-            let mut samples_overview: Vec<(f32, f32, f32)> = vec![];
+    fn file_changed(&mut self, path: &Path) {
+        if let Some((filepath, resolved)) = &self.filepath
+            && resolved == path
+        {
+            // (the old waveform stays up until the new one's in)
+            self.read(filepath.clone());
 
-            let (mut overall_min, mut overall_max) = (0.0, 0.0);
-            let (mut min, mut max) = (0.0, 0.0);
+            // (slices that were moved by hand won't fit the new audio anyway)
+            if self.slices.borrow().is_some() {
+                self.detect_slices();
+            }
+        }
+    }
 
-            let mut count = 0;
-            let mut rms_range = vec![];
+    fn animating(&self) -> bool {
+        // (to pick up the audio once it's loaded)
+        self.loading()
+    }
 
-            for i in 0..num_samples {
-                let sample = samples[i];
-                rms_range.push(sample);
+    fn loading(&self) -> bool {
+        self.poll_loading();
+        self.poll_detecting();
+        self.loading.borrow().is_some() || self.detecting.borrow().is_some()
+    }
 
-                if sample < min {
-                    min = sample;
-                }
-                if sample > max {
-                    max = sample;
-                }
-                if sample < overall_min {
-                    overall_min = sample;
-                }
-                if sample > overall_max {
-                    overall_max = sample;
-                }
+    fn error(&self) -> Option<String> {
+        self.poll_loading();
+        self.poll_detecting();
+        self.error.borrow().clone()
+    }
 
-                count += 1;
-                if count == samples_per_pixel {
-                    let rms = calculate_rms(&rms_range);
-                    // println!("[min ={} max= {}, rms = {}]", min, max, rms);
-                    samples_overview.push((min, max, rms));
-                    count = 0;
-                    min = 0.0;
-                    max = 0.0;
-                    rms_range = vec![];
-                }
-            }
+    fn relocate(&mut self) -> bool {
+        let picked = self.pick_file();
+        if picked && self.sliced {
+            self.detect_slices();
+        }
 
-            println!("Processed samples, took: {:?}", Instant::elapsed(&t0));
+        picked
+    }
 
-            Summary {
-                overall_max: overall_max.max(-overall_min),
-                samples_overview,
-            }
+    fn value(&self) -> Option<WidgetValue> {
+        let (_, resolved) = self.filepath.as_ref()?;
+
+        Some(match self.slices.borrow().as_ref() {
+            Some(slices) if self.sliced => WidgetValue::Slices(resolved.clone(), slices.clone()),
+            _ => WidgetValue::Sample(resolved.clone(), self.markers.clone()),
+        })
+    }
+
+    fn draw(&self, frame: &mut WidgetTexture) {
+        // physical pixels, btw
+        let width = frame.width();
+        let height = frame.height();
+
+        if self.loading() {
+            frame.clear(&[0xe5, 0xe5, 0xe5, 0xff]);
+            return;
+        }
+
+        let audio = self.audio.borrow();
+        let Some(audio) = audio.as_ref() else {
+            frame.clear(&[0xff, 0x00, 0x00, 0xff]);
+            return;
+        };
+
+        let mut summary = self.summary.borrow_mut();
+        let summary = summary.get_or_insert_with(|| Summary {
+            overall_max: audio.overall_max,
+            // physical pixels, btw
+            samples_overview: audio.resample(width),
         });
 
-        let theme = if self.selected {
+        let theme = if self.selected || self.focused {
             Theme {
                 background: [0x00, 0x00, 0x00, 0xff],
                 wave: [0xaa, 0xaa, 0xaa, 0xff],
@@ -225,6 +618,47 @@ impl Widget for SampleWidget {
             for y in 0..height {
                 frame.set_pixel(x, y, &theme.line);
             }
+        } else if self.focused || self.start > 0.0 {
+            let x = 2 + (self.start * (width - 6) as f32).round() as usize;
+            for y in 0..height {
+                frame.set_pixel(x, y, &theme.line);
+            }
+        }
+
+        let x_at = |position: f32| 2 + (position * (width - 6) as f32).round() as usize;
+
+        for (handle, at) in self.handles() {
+            let x = x_at(at);
+            let color = match handle {
+                Handle::Slice(_) => &MARKER,
+                Handle::LoopStart | Handle::LoopEnd => &LOOP_MARKER,
+                Handle::Cue(_) => &CUE_MARKER,
+            };
+
+            for y in 0..height {
+                frame.set_pixel(x, y, color);
+            }
+
+            // (with a little flag to grab it by: on top, pointing into the loop for loop points, and at the bottom for cues)
+            for dy in 0..4 {
+                for dx in 1..4 - dy {
+                    let (x, y) = match handle {
+                        Handle::LoopEnd => (x.saturating_sub(dx), dy),
+                        Handle::Cue(_) => ((x + dx).min(width - 1), height - 1 - dy),
+                        _ => ((x + dx).min(width - 1), dy),
+                    };
+                    frame.set_pixel(x, y, color);
+                }
+            }
+        }
+
+        // (and a bar across the top of the loop)
+        if let Some((start, end)) = self.markers.loop_region
+            && !self.sliced
+        {
+            for x in x_at(start)..=x_at(end) {
+                frame.set_pixel(x, 0, &LOOP_MARKER);
+            }
         }
 
         let empty: [u8; 4] = [0, 0, 0, 0];
@@ -261,13 +695,46 @@ impl Widget for SampleWidget {
         frame.set_pixel(width - 1 - 1, height - 1 - 1, &empty);
         frame.set_pixel(width - 1 - 0, height - 1 - 2, &empty);
     }
-}
 
-fn calculate_rms(samples: &Vec<f32>) -> f32 {
-    let sqr_sum = samples.iter().fold(0.0, |sqr_sum, s| {
-        let sample = *s as f32;
-        sqr_sum + sample * sample
-    });
+    fn describe(&self) -> String {
+        let Some((filepath, _)) = &self.filepath else {
+            return "sample[]".into();
+        };
+
+        let list = |positions: &[f32]| {
+            let positions = positions
+                .iter()
+                .map(|at| format!("{:.4}", at))
+                .collect::<Vec<_>>();
+            format!("[{}]", positions.join(", "))
+        };
+
+        let mut parts = vec![format!("{:?}", filepath)];
+        match self.slices.borrow().as_ref() {
+            Some(slices) if self.sliced => parts.push(format!("slices = {}", list(slices))),
+            _ => {
+                if let Some((start, end)) = self.markers.loop_region {
+                    parts.push(format!("loop = {}", list(&[start, end])));
+                }
+                if !self.markers.cues.is_empty() {
+                    parts.push(format!("cues = {}", list(&self.markers.cues)));
+                }
+            }
+        }
+
+        format!("sample[{}]", parts.join(", "))
+    }
+}
 
-    (sqr_sum / samples.len() as f32).sqrt()
+/**
+    Moves one of a (sorted) list of markers, keeping it in between its neighbours
+*/
+fn keep_in_between(markers: &mut [f32], i: usize, position: f32) {
+    let min = if i > 0 {
+        markers[i - 1] + MIN_SLICE
+    } else {
+        0.0
+    };
+    let max = markers.get(i + 1).map_or(1.0, |next| next - MIN_SLICE);
+    markers[i] = position.clamp(min, max.max(min));
 }
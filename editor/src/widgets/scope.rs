@@ -0,0 +1,207 @@
+use live_engine::{Tap, SAMPLE_RATE};
+use live_language::SignalViewKind;
+
+use crate::{render::WidgetTexture, widget::Widget};
+
+/// How many samples the scope shows (about 23ms), and the spectrum analyzes
+const WINDOW: usize = 1024;
+
+/// The spectrum's (logarithmic) frequency axis
+const MIN_FREQ: f32 = 30.0;
+const MAX_FREQ: f32 = 16_000.0;
+/// The spectrum shows -80 dB .. 0 dB
+const SPECTRUM_RANGE_DB: f32 = 80.0;
+
+/**
+    In-place radix-2 FFT (so `re.len()` has to be a power of two)
+*/
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+
+    // bit reversal permutation
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * std::f32::consts::PI / len as f32;
+
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (w_re, w_im) = ((angle * k as f32).cos(), (angle * k as f32).sin());
+
+                let (a, b) = (start + k, start + k + len / 2);
+                let t_re = re[b] * w_re - im[b] * w_im;
+                let t_im = re[b] * w_im + im[b] * w_re;
+
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+
+        len <<= 1;
+    }
+}
+
+/**
+    The magnitude (0..1, for a full scale sine) of every frequency bin of the (Hann windowed) samples
+*/
+fn magnitudes(samples: &[f32]) -> Vec<f32> {
+    let n = samples.len();
+
+    let mut re = samples
+        .iter()
+        .enumerate()
+        .map(|(i, sample)| {
+            let hann = 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / n as f32).cos();
+            sample * hann
+        })
+        .collect::<Vec<_>>();
+    let mut im = vec![0.0; n];
+
+    fft(&mut re, &mut im);
+
+    // (the Hann window halves the amplitude, and half of it ends up in the negative frequencies)
+    re.iter()
+        .zip(&im)
+        .take(n / 2)
+        .map(|(re, im)| (re * re + im * im).sqrt() * 4.0 / n as f32)
+        .collect()
+}
+
+/**
+    A live view of a play target's signal, spawned by the editor right after every `scope(name)` or `spectrum(name)` in the code: a rolling oscilloscope or an FFT magnitude view.
+*/
+pub struct ScopeWidget {
+    kind: SignalViewKind,
+    target: String,
+    // (`None` without an audio engine)
+    tap: Option<Tap>,
+}
+
+impl ScopeWidget {
+    pub fn new(kind: SignalViewKind, target: impl Into<String>, tap: Option<Tap>) -> Self {
+        Self {
+            kind,
+            target: target.into(),
+            tap,
+        }
+    }
+
+    fn draw_scope(&self, frame: &mut WidgetTexture, samples: &[f32], color: [u8; 4]) {
+        let (width, height) = (frame.width(), frame.height());
+
+        // start at a rising zero crossing (if there is one), so a periodic signal stands still
+        let trigger = samples
+            .windows(2)
+            .take(samples.len().saturating_sub(WINDOW))
+            .position(|w| w[0] <= 0.0 && w[1] > 0.0)
+            .unwrap_or(0);
+
+        let window = &samples[trigger..(trigger + WINDOW).min(samples.len())];
+        if window.is_empty() {
+            return;
+        }
+
+        let to_y = |sample: f32| {
+            let y = (1.0 - sample.clamp(-1.0, 1.0)) / 2.0 * (height - 1) as f32;
+            y.round() as usize
+        };
+
+        for x in 0..width {
+            let from = x * window.len() / width;
+            let to = ((x + 1) * window.len() / width)
+                .max(from + 1)
+                .min(window.len());
+
+            let (min, max) = window[from..to]
+                .iter()
+                .fold((f32::MAX, f32::MIN), |(min, max), &s| {
+                    (min.min(s), max.max(s))
+                });
+
+            for y in to_y(max)..=to_y(min) {
+                frame.set_pixel(x, y, &color);
+            }
+        }
+    }
+
+    fn draw_spectrum(&self, frame: &mut WidgetTexture, samples: &[f32], color: [u8; 4]) {
+        let (width, height) = (frame.width(), frame.height());
+
+        if samples.len() < WINDOW {
+            return;
+        }
+
+        let magnitudes = magnitudes(&samples[samples.len() - WINDOW..]);
+        let bin_width = SAMPLE_RATE as f32 / WINDOW as f32;
+
+        for x in 0..width {
+            let freq = MIN_FREQ * (MAX_FREQ / MIN_FREQ).powf(x as f32 / width as f32);
+            let next_freq = MIN_FREQ * (MAX_FREQ / MIN_FREQ).powf((x + 1) as f32 / width as f32);
+
+            let from = (freq / bin_width) as usize;
+            let to = ((next_freq / bin_width) as usize).max(from + 1);
+
+            let magnitude = magnitudes[from.min(magnitudes.len() - 1)..to.min(magnitudes.len())]
+                .iter()
+                .fold(0.0_f32, |max, &m| max.max(m));
+
+            let db = 20.0 * magnitude.log10();
+            let fraction = ((db + SPECTRUM_RANGE_DB) / SPECTRUM_RANGE_DB).clamp(0.0, 1.0);
+            let bar_height = (fraction * height as f32).round() as usize;
+
+            for y in (height - bar_height)..height {
+                frame.set_pixel(x, y, &color);
+            }
+        }
+    }
+}
+
+impl Widget for ScopeWidget {
+    fn kind(&self) -> &'static str {
+        self.kind.name()
+    }
+
+    fn column_width(&self) -> usize {
+        8
+    }
+
+    fn draw(&self, frame: &mut WidgetTexture) {
+        frame.clear(&[0x00, 0x00, 0x00, 0x0c]);
+
+        let Some(tap) = &self.tap else {
+            return;
+        };
+
+        let samples = tap.latest(WINDOW * 2);
+        let color = [0x00, 0x99, 0x4c, 0xff];
+
+        match self.kind {
+            SignalViewKind::Scope => self.draw_scope(frame, &samples, color),
+            SignalViewKind::Spectrum => self.draw_spectrum(frame, &samples, color),
+        }
+    }
+
+    fn animating(&self) -> bool {
+        self.tap.is_some()
+    }
+
+    fn describe(&self) -> String {
+        format!("{}({})", self.kind.name(), self.target)
+    }
+}
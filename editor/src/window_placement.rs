@@ -0,0 +1,161 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use winit::{
+    dpi::{LogicalPosition, LogicalSize, PhysicalPosition},
+    monitor::MonitorHandle,
+};
+
+use crate::{storage::storage, util::config_dir};
+
+const DEFAULT_SIZE: (f64, f64) = (900.0, 600.0);
+
+/**
+    Where the window was, in logical pixels, the last time we saw this monitor configuration.
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Placement {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Placement {
+    pub fn position(&self) -> LogicalPosition<f64> {
+        LogicalPosition::new(self.x, self.y)
+    }
+
+    pub fn size(&self) -> LogicalSize<f64> {
+        LogicalSize::new(self.width, self.height)
+    }
+}
+
+/**
+    Remembers the window's size and position per monitor configuration, so that e.g. plugging in a projector and then unplugging it again puts the window back where it was on the laptop screen.
+*/
+pub struct WindowPlacements {
+    file: Option<PathBuf>,
+    placements: HashMap<String, Placement>,
+}
+
+impl WindowPlacements {
+    pub fn load() -> Self {
+        let file = config_dir().map(|dir| dir.join("window_placements"));
+
+        let placements = file
+            .as_ref()
+            .and_then(|file| storage().read(file).ok())
+            .map(|contents| {
+                contents
+                    .lines()
+                    .filter_map(|line| {
+                        let (key, rest) = line.split_once('\t')?;
+                        let nums = rest
+                            .split_whitespace()
+                            .map(|n| n.parse::<f64>().ok())
+                            .collect::<Option<Vec<_>>>()?;
+
+                        let [x, y, width, height] = nums[..] else {
+                            return None;
+                        };
+
+                        Some((
+                            key.to_string(),
+                            Placement {
+                                x,
+                                y,
+                                width,
+                                height,
+                            },
+                        ))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self { file, placements }
+    }
+
+    pub fn get(&self, setup: &str) -> Option<Placement> {
+        self.placements.get(setup).copied()
+    }
+
+    pub fn remember(&mut self, setup: String, placement: Placement) {
+        self.placements.insert(setup, placement);
+    }
+
+    pub fn save(&self) {
+        let Some(file) = &self.file else {
+            return;
+        };
+
+        let contents = self
+            .placements
+            .iter()
+            .map(|(key, p)| format!("{}\t{} {} {} {}", key, p.x, p.y, p.width, p.height))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        if let Err(e) = storage().write(file, &contents) {
+            tracing::warn!("Could not save window placements: {}", e);
+        }
+    }
+}
+
+/**
+    A stable key describing the currently connected monitors (independent of their enumeration order).
+*/
+pub fn monitor_setup(monitors: impl Iterator<Item = MonitorHandle>) -> String {
+    let mut descriptions = monitors
+        .map(|m| {
+            let size = m.size();
+            let pos = m.position();
+            format!(
+                "{}:{}x{}@{},{}",
+                m.name().unwrap_or_else(|| "?".into()),
+                size.width,
+                size.height,
+                pos.x,
+                pos.y
+            )
+        })
+        .collect::<Vec<_>>();
+
+    descriptions.sort();
+    descriptions.join("|")
+}
+
+/**
+    When we've never seen this monitor setup before: center the default-sized window on the primary monitor, shrinking it if the monitor is smaller than that.
+*/
+pub fn default_placement(primary: Option<MonitorHandle>) -> Option<Placement> {
+    let monitor = primary?;
+
+    let sf = monitor.scale_factor();
+    let origin: LogicalPosition<f64> = monitor.position().to_logical(sf);
+    let size: LogicalSize<f64> = monitor.size().to_logical(sf);
+
+    let width = DEFAULT_SIZE.0.min(size.width * 0.9);
+    let height = DEFAULT_SIZE.1.min(size.height * 0.9);
+
+    Some(Placement {
+        x: origin.x + (size.width - width) / 2.0,
+        y: origin.y + (size.height - height) / 2.0,
+        width,
+        height,
+    })
+}
+
+pub fn placement_from_window(window: &winit::window::Window) -> Option<Placement> {
+    let sf = window.scale_factor();
+    let pos: PhysicalPosition<i32> = window.outer_position().ok()?;
+    let pos: LogicalPosition<f64> = pos.to_logical(sf);
+    let size: LogicalSize<f64> = window.inner_size().to_logical(sf);
+
+    Some(Placement {
+        x: pos.x,
+        y: pos.y,
+        width: size.width,
+        height: size.height,
+    })
+}
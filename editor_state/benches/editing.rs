@@ -0,0 +1,129 @@
+//! The hot editing path: what happens on every keystroke, on a document that's big for live code (a thousand lines).
+//!
+//! Run with `cargo +nightly bench`, and compare against a baseline with `--save-baseline main` / `--baseline main`.
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use live_editor_state::{EditorState, LineData, Pos};
+
+const ROWS: i32 = 1000;
+
+fn document() -> LineData {
+    let source = (0..ROWS)
+        .map(|row| match row % 4 {
+            0 => format!("def voice{} = osc(440hz * {}) * .2", row, row % 12),
+            1 => "  |> lowpass{f = sin(2hz) * 800hz + 1khz, q = 2}".to_string(),
+            2 => "  |> delay{time = 250ms, feedback = .4}".to_string(),
+            _ => String::new(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    LineData::from(source.as_str())
+}
+
+fn line_data(c: &mut Criterion) {
+    let doc = document();
+
+    c.bench_function("LineData::insert", |b| {
+        b.iter_batched(
+            || doc.clone(),
+            |mut doc| {
+                doc.insert(
+                    Pos {
+                        row: ROWS / 2,
+                        col: 0,
+                    },
+                    LineData::from("x"),
+                )
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    c.bench_function("LineData::remove", |b| {
+        b.iter_batched(
+            || doc.clone(),
+            |mut doc| {
+                doc.remove(
+                    Pos {
+                        row: ROWS / 2,
+                        col: 4,
+                    },
+                    Pos {
+                        row: ROWS / 2 + 10,
+                        col: 4,
+                    },
+                )
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    c.bench_function("LineData::snap_nearest", |b| {
+        b.iter(|| {
+            for row in (0..ROWS).step_by(10) {
+                black_box(doc.snap_nearest(Pos { row, col: 30 }));
+            }
+        })
+    });
+
+    // (the only occurrence is all the way at the end)
+    let needle = LineData::from(format!("voice{}", ROWS - 4).as_str());
+    c.bench_function("LineData::search_next_occurrence", |b| {
+        b.iter(|| doc.search_next_occurrence(Pos { row: 0, col: 0 }, &needle))
+    });
+}
+
+fn editor_state(c: &mut Criterion) {
+    let doc = document();
+
+    c.bench_function("EditorState select all + type", |b| {
+        b.iter_batched(
+            || EditorState::new().with_linedata(doc.clone()),
+            |mut editor_state| {
+                editor_state.select_all();
+                editor_state.write("x");
+                editor_state
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    let with_carets = || {
+        let mut editor_state = EditorState::new().with_linedata(doc.clone());
+        for row in 0..ROWS {
+            editor_state.add_caret(Pos { row, col: 0 });
+        }
+        editor_state
+    };
+
+    c.bench_function("EditorState type with 1000 carets", |b| {
+        b.iter_batched(
+            with_carets,
+            |mut editor_state| {
+                editor_state.write("x");
+                editor_state
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    c.bench_function("EditorState undo with 1000 carets", |b| {
+        b.iter_batched(
+            || {
+                let mut editor_state = with_carets();
+                editor_state.write("x");
+                editor_state.checkpoint();
+                editor_state
+            },
+            |mut editor_state| {
+                editor_state.undo();
+                editor_state
+            },
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+criterion_group!(benches, line_data, editor_state);
+criterion_main!(benches);
@@ -0,0 +1,223 @@
+use std::collections::BTreeSet;
+
+use crate::{EditResult, InsertionInfo, Pos, RemovalInfo};
+
+/// (older jumps are forgotten)
+const MAX_JUMPS: usize = 100;
+
+/**
+    Bookmarked lines, and the jump list: where the caret was before it jumped somewhere far away, to go back (and forward again) to. Both move along with edits, like the selections do.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct Bookmarks {
+    rows: BTreeSet<i32>,
+    back: Vec<Pos>,
+    forward: Vec<Pos>,
+}
+
+impl Bookmarks {
+    pub fn rows(&self) -> impl Iterator<Item = i32> + '_ {
+        self.rows.iter().copied()
+    }
+
+    pub fn contains(&self, row: i32) -> bool {
+        self.rows.contains(&row)
+    }
+
+    /**
+        Bookmarks the line, or removes its bookmark, returning whether it's bookmarked now
+    */
+    pub fn toggle(&mut self, row: i32) -> bool {
+        if self.rows.remove(&row) {
+            return false;
+        }
+
+        self.rows.insert(row);
+        true
+    }
+
+    /**
+        The next bookmarked line after this one (or the previous one before it, going `back`), wrapping around the document
+    */
+    pub fn next(&self, row: i32, back: bool) -> Option<i32> {
+        if back {
+            self.rows
+                .range(..row)
+                .next_back()
+                .or_else(|| self.rows.iter().next_back())
+                .copied()
+        } else {
+            self.rows
+                .range(row + 1..)
+                .next()
+                .or_else(|| self.rows.iter().next())
+                .copied()
+        }
+    }
+
+    /**
+        Remembers where the caret was, right before it jumps. (Which makes going forward again impossible, like in a browser.)
+    */
+    pub fn push_jump(&mut self, from: Pos) {
+        self.forward.clear();
+
+        // (jumping around on the same line is not worth going back to)
+        if self.back.last().is_some_and(|last| last.row == from.row) {
+            self.back.pop();
+        }
+
+        self.back.push(from);
+        if self.back.len() > MAX_JUMPS {
+            self.back.remove(0);
+        }
+    }
+
+    /**
+        Where to go back to, from where the caret is now (which is where going forward returns to)
+    */
+    pub fn jump_back(&mut self, current: Pos) -> Option<Pos> {
+        let to = self.back.pop()?;
+        self.forward.push(current);
+        Some(to)
+    }
+
+    pub fn jump_forward(&mut self, current: Pos) -> Option<Pos> {
+        let to = self.forward.pop()?;
+        self.back.push(current);
+        Some(to)
+    }
+
+    /**
+        Moves everything along with an edit. Bookmarks on lines that are removed entirely end up on the line they're joined into.
+    */
+    pub fn adjust(&mut self, res: EditResult) {
+        match res {
+            EditResult::Insertion {
+                info:
+                    InsertionInfo {
+                        start,
+                        delta,
+                        added_lines,
+                        ..
+                    },
+            } => {
+                if added_lines > 0 {
+                    // (a line break right at the start of a line pushes the whole line down)
+                    self.rows = self
+                        .rows
+                        .iter()
+                        .map(|&row| {
+                            if row > start.row || (row == start.row && start.col == 0) {
+                                row + added_lines
+                            } else {
+                                row
+                            }
+                        })
+                        .collect();
+                }
+
+                for pos in self.back.iter_mut().chain(self.forward.iter_mut()) {
+                    if *pos >= start {
+                        if pos.row == start.row {
+                            *pos = *pos + delta;
+                        } else {
+                            pos.row += added_lines;
+                        }
+                    }
+                }
+            }
+            EditResult::Removal {
+                info:
+                    RemovalInfo {
+                        start,
+                        end,
+                        delta,
+                        removed_lines,
+                    },
+            } => {
+                if removed_lines > 0 {
+                    self.rows = self
+                        .rows
+                        .iter()
+                        .map(|&row| {
+                            if row > end.row {
+                                row - removed_lines
+                            } else {
+                                row.min(start.row)
+                            }
+                        })
+                        .collect();
+                }
+
+                for pos in self.back.iter_mut().chain(self.forward.iter_mut()) {
+                    if *pos >= end {
+                        if pos.row == end.row {
+                            *pos = *pos + delta;
+                        } else {
+                            pos.row -= removed_lines;
+                        }
+                    } else if *pos > start {
+                        *pos = start;
+                    }
+                }
+            }
+        }
+    }
+
+    /**
+        Forgets the bookmarks past the end of the document (after it was replaced as a whole, like when undoing)
+    */
+    pub fn truncate(&mut self, len: usize) {
+        self.rows.retain(|&row| row < len as i32);
+    }
+}
+
+#[test]
+fn test_bookmarks() {
+    let mut bookmarks = Bookmarks::default();
+    assert!(bookmarks.toggle(2));
+    assert!(bookmarks.toggle(6));
+    assert!(bookmarks.toggle(9));
+    assert!(!bookmarks.toggle(9));
+
+    assert_eq!(bookmarks.next(2, false), Some(6));
+    assert_eq!(bookmarks.next(6, false), Some(2));
+    assert_eq!(bookmarks.next(4, true), Some(2));
+    assert_eq!(bookmarks.next(2, true), Some(6));
+
+    // two lines added in the middle of line 3
+    bookmarks.adjust(EditResult::Insertion {
+        info: InsertionInfo {
+            start: Pos { row: 3, col: 4 },
+            end: Pos { row: 5, col: 1 },
+            delta: Pos { row: 2, col: -3 },
+            added_lines: 2,
+        },
+    });
+    assert_eq!(bookmarks.rows().collect::<Vec<_>>(), vec![2, 8]);
+
+    // lines 1 through 4 joined into line 1
+    bookmarks.adjust(EditResult::Removal {
+        info: RemovalInfo {
+            start: Pos { row: 1, col: 2 },
+            end: Pos { row: 4, col: 0 },
+            delta: Pos { row: -3, col: 2 },
+            removed_lines: 3,
+        },
+    });
+    assert_eq!(bookmarks.rows().collect::<Vec<_>>(), vec![1, 5]);
+
+    let (a, b, c) = (
+        Pos { row: 0, col: 0 },
+        Pos { row: 10, col: 0 },
+        Pos { row: 20, col: 0 },
+    );
+    bookmarks.push_jump(a);
+    bookmarks.push_jump(b);
+    assert_eq!(bookmarks.jump_back(c), Some(b));
+    assert_eq!(bookmarks.jump_back(b), Some(a));
+    assert_eq!(bookmarks.jump_back(a), None);
+    assert_eq!(bookmarks.jump_forward(a), Some(b));
+    assert_eq!(bookmarks.jump_forward(b), Some(c));
+    assert_eq!(bookmarks.jump_forward(c), None);
+}
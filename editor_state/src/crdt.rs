@@ -0,0 +1,548 @@
+use std::{fmt, str::FromStr};
+
+use crate::{LineData, Token};
+
+/// What a widget is sent as, since the other side doesn't have its state (U+FFFC OBJECT REPLACEMENT CHARACTER)
+pub const WIDGET_PLACEHOLDER: char = '\u{FFFC}';
+
+/**
+    The id of something that was inserted into a replicated document: who inserted it (the `site`), and a Lamport clock (the `counter`), which is higher than anything the site had seen at that point. So every replica orders ids the same way, and whatever's inserted after (knowing about) a token has a higher id than that token.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct OpId {
+    pub counter: u64,
+    pub site: u32,
+}
+
+/// What a replicated document consists of: tokens, and line breaks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Elem {
+    Token(Token),
+    Newline,
+}
+
+/// An edit to a replicated document, as it's sent to the other replicas
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    /// (`after` is `None` at the very start of the document)
+    Insert {
+        id: OpId,
+        after: Option<OpId>,
+        elem: Elem,
+    },
+    Remove {
+        id: OpId,
+    },
+}
+
+/// What an op did to the document, by index (into the elements that aren't removed)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Insert { index: usize, elem: Elem },
+    Remove { index: usize },
+}
+
+/// A spot in a replicated document, right after a token (even when it's removed), or at the very start. It stays put while others edit around it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct After(pub Option<OpId>);
+
+/// A selection, by where its ends are in the replicated document
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SharedSelection {
+    pub caret: After,
+    pub anchor: Option<After>,
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    id: OpId,
+    elem: Elem,
+    removed: bool,
+}
+
+/**
+    A replica of the document, for editing it together over the network: an RGA (replicated growable array) of the document's tokens and line breaks. Everything is inserted right after something else (by id), and concurrent inserts right after the same thing are ordered by id, so replicas end up with the same document no matter in which order they receive each other's edits (as long as every op arrives after the insert it refers to, which is what `pending` is for).
+
+    Removed elements stay around as tombstones, because edits that were made concurrently may still refer to them.
+*/
+#[derive(Debug, Clone)]
+pub struct Replica {
+    site: u32,
+    counter: u64,
+    entries: Vec<Entry>,
+    // ops that arrived before the insert they refer to
+    pending: Vec<Op>,
+}
+
+impl Replica {
+    pub fn new(site: u32, linedata: &LineData) -> Self {
+        let mut replica = Self::empty(site);
+        replica.local_insert(0, elems(linedata));
+        replica
+    }
+
+    /**
+        A replica of someone else's document, from its `snapshot`
+    */
+    pub fn from_snapshot(site: u32, snapshot: Vec<Op>) -> Self {
+        let mut replica = Self::empty(site);
+        replica.apply(snapshot);
+        replica
+    }
+
+    fn empty(site: u32) -> Self {
+        Self {
+            site,
+            counter: 0,
+            entries: vec![],
+            pending: vec![],
+        }
+    }
+
+    pub fn site(&self) -> u32 {
+        self.site
+    }
+
+    /**
+        The ops that make a new replica of the document (including the tombstones, which edits that are still underway may refer to)
+    */
+    pub fn snapshot(&self) -> Vec<Op> {
+        let mut after = None;
+        let mut ops = vec![];
+
+        for entry in &self.entries {
+            ops.push(Op::Insert {
+                id: entry.id,
+                after,
+                elem: entry.elem,
+            });
+            after = Some(entry.id);
+        }
+
+        ops.extend(
+            self.entries
+                .iter()
+                .filter(|entry| entry.removed)
+                .map(|entry| Op::Remove { id: entry.id }),
+        );
+
+        ops
+    }
+
+    pub fn linedata(&self) -> LineData {
+        let mut lines = vec![vec![]];
+
+        for entry in self.entries.iter().filter(|entry| !entry.removed) {
+            match entry.elem {
+                Elem::Token(token) => lines.last_mut().unwrap().push(token),
+                Elem::Newline => lines.push(vec![]),
+            }
+        }
+
+        lines.into()
+    }
+
+    /**
+        Inserts elements at an index (into the elements that aren't removed), and returns the ops to send to the other replicas
+    */
+    pub fn local_insert(&mut self, index: usize, elems: Vec<Elem>) -> Vec<Op> {
+        let mut after = self.after(index).0;
+
+        elems
+            .into_iter()
+            .map(|elem| {
+                self.counter += 1;
+                let id = OpId {
+                    counter: self.counter,
+                    site: self.site,
+                };
+
+                let op = Op::Insert { id, after, elem };
+                self.integrate(op);
+                after = Some(id);
+
+                op
+            })
+            .collect()
+    }
+
+    /**
+        Removes `len` elements from an index (into the elements that aren't removed), and returns the ops to send to the other replicas
+    */
+    pub fn local_remove(&mut self, index: usize, len: usize) -> Vec<Op> {
+        let ops = self
+            .entries
+            .iter()
+            .filter(|entry| !entry.removed)
+            .skip(index)
+            .take(len)
+            .map(|entry| Op::Remove { id: entry.id })
+            .collect::<Vec<_>>();
+
+        for &op in &ops {
+            self.integrate(op);
+        }
+
+        ops
+    }
+
+    /**
+        Applies the ops from another replica (ignoring what was applied already), and returns what they changed, in order
+    */
+    pub fn apply(&mut self, ops: Vec<Op>) -> Vec<Change> {
+        let mut changes = vec![];
+
+        for op in ops {
+            self.pending.push(op);
+
+            // (each op that can be applied may be what others were waiting for)
+            while let Some(i) = self.pending.iter().position(|op| self.can_integrate(op)) {
+                let op = self.pending.remove(i);
+                changes.extend(self.integrate(op));
+            }
+        }
+
+        changes
+    }
+
+    /**
+        The spot right before the element at an index (into the elements that aren't removed)
+    */
+    pub fn after(&self, index: usize) -> After {
+        if index == 0 {
+            return After(None);
+        }
+
+        After(
+            self.entries
+                .iter()
+                .filter(|entry| !entry.removed)
+                .nth(index - 1)
+                .map(|entry| entry.id),
+        )
+    }
+
+    /**
+        Where a spot is now, as an index into the elements that aren't removed (or at the start, if it's not known)
+    */
+    pub fn index_of(&self, After(id): After) -> usize {
+        let Some(position) = id.and_then(|id| self.position(id)) else {
+            return 0;
+        };
+
+        self.entries[..=position]
+            .iter()
+            .filter(|entry| !entry.removed)
+            .count()
+    }
+
+    fn position(&self, id: OpId) -> Option<usize> {
+        self.entries.iter().position(|entry| entry.id == id)
+    }
+
+    fn can_integrate(&self, op: &Op) -> bool {
+        match *op {
+            Op::Insert { after: None, .. } => true,
+            Op::Insert {
+                after: Some(id), ..
+            }
+            | Op::Remove { id } => self.position(id).is_some(),
+        }
+    }
+
+    fn integrate(&mut self, op: Op) -> Option<Change> {
+        match op {
+            Op::Insert { id, after, elem } => {
+                if self.position(id).is_some() {
+                    return None;
+                }
+                self.counter = self.counter.max(id.counter);
+
+                let mut position = after.map_or(0, |after| self.position(after).unwrap() + 1);
+
+                // concurrent inserts after the same element go in order of their ids (and whatever was inserted after those has even higher ids, so it's skipped too)
+                while position < self.entries.len() && self.entries[position].id > id {
+                    position += 1;
+                }
+
+                self.entries.insert(
+                    position,
+                    Entry {
+                        id,
+                        elem,
+                        removed: false,
+                    },
+                );
+
+                Some(Change::Insert {
+                    index: self.visible_before(position),
+                    elem,
+                })
+            }
+            Op::Remove { id } => {
+                let position = self.position(id).unwrap();
+                if self.entries[position].removed {
+                    return None;
+                }
+
+                self.entries[position].removed = true;
+
+                Some(Change::Remove {
+                    index: self.visible_before(position),
+                })
+            }
+        }
+    }
+
+    fn visible_before(&self, position: usize) -> usize {
+        self.entries[..position]
+            .iter()
+            .filter(|entry| !entry.removed)
+            .count()
+    }
+}
+
+/**
+    A document's elements, in order
+*/
+pub(crate) fn elems(linedata: &LineData) -> Vec<Elem> {
+    let mut elems = vec![];
+
+    for (row, line) in linedata.lines().iter().enumerate() {
+        if row > 0 {
+            elems.push(Elem::Newline);
+        }
+        elems.extend(line.iter().map(|&token| Elem::Token(token)));
+    }
+
+    elems
+}
+
+// The wire format is a line per op, so that it's easy to read while debugging:
+//  `+ <id> <after or ^> <char as a number, or n for a line break>` and `- <id>`
+
+impl fmt::Display for OpId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.counter, self.site)
+    }
+}
+
+impl FromStr for OpId {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (counter, site) = s.split_once('.').ok_or(())?;
+
+        Ok(OpId {
+            counter: counter.parse().map_err(|_| ())?,
+            site: site.parse().map_err(|_| ())?,
+        })
+    }
+}
+
+impl fmt::Display for After {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(id) => write!(f, "{}", id),
+            None => write!(f, "^"),
+        }
+    }
+}
+
+impl FromStr for After {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "^" => Ok(After(None)),
+            _ => Ok(After(Some(s.parse()?))),
+        }
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Op::Insert { id, after, elem } => {
+                write!(f, "+ {} {} ", id, After(after))?;
+                match elem {
+                    Elem::Newline => write!(f, "n"),
+                    Elem::Token(Token::Char(ch)) => write!(f, "{}", ch as u32),
+                    Elem::Token(Token::Widget(_)) => write!(f, "{}", WIDGET_PLACEHOLDER as u32),
+                }
+            }
+            Op::Remove { id } => write!(f, "- {}", id),
+        }
+    }
+}
+
+impl FromStr for Op {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split(' ').collect::<Vec<_>>()[..] {
+            ["+", id, after, elem] => Ok(Op::Insert {
+                id: id.parse()?,
+                after: after.parse::<After>()?.0,
+                elem: match elem {
+                    "n" => Elem::Newline,
+                    _ => elem
+                        .parse()
+                        .ok()
+                        .and_then(char::from_u32)
+                        .map(|ch| Elem::Token(Token::Char(ch)))
+                        .ok_or(())?,
+                },
+            }),
+            ["-", id] => Ok(Op::Remove { id: id.parse()? }),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for SharedSelection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.anchor {
+            Some(anchor) => write!(f, "{} {}", self.caret, anchor),
+            None => write!(f, "{}", self.caret),
+        }
+    }
+}
+
+impl FromStr for SharedSelection {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split(' ').collect::<Vec<_>>()[..] {
+            [caret] => Ok(SharedSelection {
+                caret: caret.parse()?,
+                anchor: None,
+            }),
+            [caret, anchor] => Ok(SharedSelection {
+                caret: caret.parse()?,
+                anchor: Some(anchor.parse()?),
+            }),
+            _ => Err(()),
+        }
+    }
+}
+
+#[test]
+fn test_replicas_converge() {
+    let mut a = Replica::new(1, &"ac".into());
+    let mut b = Replica::from_snapshot(2, a.snapshot());
+    assert_eq!(b.linedata().to_string(), "ac");
+
+    // concurrently typing at the same spot, and removing what the other types after
+    let from_a = a.local_insert(1, elems(&"b".into()));
+    let mut from_b = b.local_insert(1, elems(&"x\ny".into()));
+    from_b.extend(b.local_remove(0, 1));
+
+    a.apply(from_b.clone());
+    b.apply(from_a.clone());
+    assert_eq!(a.linedata(), b.linedata());
+    assert_eq!(a.linedata().to_string(), "x\nybc");
+
+    // (applying ops twice doesn't do anything)
+    assert_eq!(a.apply(from_b), vec![]);
+
+    // ops that arrive before what they refer to wait for it
+    let mut c = Replica::from_snapshot(3, a.snapshot());
+    let ops = a.local_insert(5, elems(&"12".into()));
+    assert_eq!(c.apply(vec![ops[1]]), vec![]);
+    assert_eq!(
+        c.apply(vec![ops[0]]),
+        vec![
+            Change::Insert {
+                index: 5,
+                elem: Elem::Token(Token::Char('1'))
+            },
+            Change::Insert {
+                index: 6,
+                elem: Elem::Token(Token::Char('2'))
+            },
+        ]
+    );
+    assert_eq!(c.linedata().to_string(), "x\nybc12");
+}
+
+#[test]
+fn test_replica_spots() {
+    let mut a = Replica::new(1, &"abc".into());
+    let mut b = Replica::from_snapshot(2, a.snapshot());
+
+    // a spot right after the `b` stays there when things are inserted before it
+    let spot = b.after(2);
+    b.apply(a.local_insert(0, elems(&"__".into())));
+    assert_eq!(b.index_of(spot), 4);
+
+    // and when the `b` itself is removed, it's where the `b` was
+    b.apply(a.local_remove(3, 1));
+    assert_eq!(b.index_of(spot), 3);
+    assert_eq!(b.index_of(After(None)), 0);
+}
+
+#[test]
+fn test_wire_format() {
+    let ops = Replica::new(7, &"a\n".into()).snapshot();
+    for op in ops.iter().chain(&[Op::Remove {
+        id: OpId {
+            counter: 3,
+            site: 1,
+        },
+    }]) {
+        assert_eq!(op.to_string().parse::<Op>(), Ok(*op));
+    }
+    assert_eq!(ops[1].to_string(), "+ 2.7 1.7 n");
+
+    let selection = SharedSelection {
+        caret: After(None),
+        anchor: Some(After(Some(OpId {
+            counter: 2,
+            site: 7,
+        }))),
+    };
+    assert_eq!(selection.to_string(), "^ 2.7");
+    assert_eq!(
+        selection.to_string().parse::<SharedSelection>(),
+        Ok(selection)
+    );
+    assert_eq!("+ 1.1 ^".parse::<Op>(), Err(()));
+}
+
+#[test]
+fn test_editing_together() {
+    use crate::{EditorState, Pos};
+
+    let mut a = EditorState::new().with_linedata("def x = 1".into());
+    a.start_sharing(1);
+
+    let mut b = EditorState::new();
+    b.join_shared(2, a.shared_snapshot().unwrap());
+    assert_eq!(b.linedata().to_string(), "def x = 1");
+
+    a.set_single_caret((9, 0).into());
+    a.write("\ndef y = 2");
+    b.set_single_caret((4, 0).into());
+    b.write("my_");
+
+    b.apply_shared_ops(a.take_shared_ops());
+    a.apply_shared_ops(b.take_shared_ops());
+    assert_eq!(a.linedata(), b.linedata());
+    assert_eq!(a.linedata().to_string(), "def my_x = 1\ndef y = 2");
+
+    // (b's caret moved along with what a typed before it, which didn't happen)
+    assert_eq!(b.caret_positions(), vec![Pos { row: 0, col: 7 }]);
+
+    b.set_remote_selections(1, a.shared_selections());
+    assert_eq!(
+        b.remote_selections()[0].carets,
+        vec![Pos { row: 1, col: 9 }]
+    );
+
+    // undoing is replicated too
+    a.checkpoint();
+    a.undo();
+    b.apply_shared_ops(a.take_shared_ops());
+    assert_eq!(b.linedata().to_string(), a.linedata().to_string());
+}
@@ -0,0 +1,204 @@
+use std::ops::Range;
+
+use crate::{LineData, LineSelection, Token};
+
+/// (past this many cells, the middle of the documents is just taken to be changed as a whole, rather than spending ages on it)
+const MAX_TABLE_SIZE: usize = 4_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HunkKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+/**
+    A run of lines that differs between two versions of a document
+*/
+#[derive(Debug, Clone)]
+pub struct Hunk {
+    /// Where it is in the new version (empty when lines were only removed, at the row they were removed before)
+    pub rows: Range<i32>,
+    /// Where it was in the old version
+    pub old_rows: Range<i32>,
+    /// The tokens that are new, within the hunk's lines (in the new version)
+    pub changes: Vec<LineSelection>,
+}
+
+impl Hunk {
+    pub fn kind(&self) -> HunkKind {
+        if self.old_rows.is_empty() {
+            HunkKind::Added
+        } else if self.rows.is_empty() {
+            HunkKind::Removed
+        } else {
+            HunkKind::Modified
+        }
+    }
+}
+
+/**
+    Which lines changed between two versions of a document, and within lines that were modified, which tokens
+*/
+pub fn diff(old: &LineData, new: &LineData) -> Vec<Hunk> {
+    let (old, new) = (old.lines(), new.lines());
+    let mut hunks = vec![];
+
+    // (a sentinel match just past the end, to close off the last hunk)
+    let matches = common(old, new).into_iter().chain([(old.len(), new.len())]);
+
+    let (mut i, mut j) = (0, 0);
+    for (next_i, next_j) in matches {
+        if next_i > i || next_j > j {
+            hunks.push(Hunk {
+                rows: j as i32..next_j as i32,
+                old_rows: i as i32..next_i as i32,
+                changes: changes(&old[i..next_i], &new[j..next_j], j as i32),
+            });
+        }
+
+        (i, j) = (next_i + 1, next_j + 1);
+    }
+
+    hunks
+}
+
+/**
+    The new tokens in the lines of a hunk: the lines that replace old ones (in order) are compared token by token, and the rest is new altogether
+*/
+fn changes(old: &[Vec<Token>], new: &[Vec<Token>], first_row: i32) -> Vec<LineSelection> {
+    let mut changes = vec![];
+
+    if old.is_empty() {
+        return changes;
+    }
+
+    for (k, line) in new.iter().enumerate() {
+        let row = first_row + k as i32;
+
+        let matched = match old.get(k) {
+            Some(old_line) => common(old_line, line)
+                .into_iter()
+                .map(|(_, j)| j)
+                .collect::<Vec<_>>(),
+            None => vec![],
+        };
+
+        let mut col = 0;
+        let mut m = matched.iter().peekable();
+        for (t, token) in line.iter().enumerate() {
+            let width = token.width() as i32;
+
+            if m.next_if_eq(&&t).is_none() {
+                match changes.last_mut() {
+                    Some(LineSelection {
+                        row: last_row,
+                        col_end,
+                        ..
+                    }) if *last_row == row && *col_end == col => *col_end += width,
+                    _ => changes.push(LineSelection {
+                        row,
+                        col_start: col,
+                        col_end: col + width,
+                    }),
+                }
+            }
+
+            col += width;
+        }
+    }
+
+    changes
+}
+
+/**
+    A longest common subsequence of two lists, as pairs of indices into both
+*/
+fn common<T: PartialEq>(a: &[T], b: &[T]) -> Vec<(usize, usize)> {
+    // (the start and the end often didn't change at all, so those don't need the table)
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+
+    let (a_mid, b_mid) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+    let (n, m) = (a_mid.len(), b_mid.len());
+
+    let mut pairs = (0..prefix).map(|i| (i, i)).collect::<Vec<_>>();
+
+    if n > 0 && m > 0 && n * m <= MAX_TABLE_SIZE {
+        // lengths[i][j]: how long the common subsequence of a_mid[i..] and b_mid[j..] is
+        let mut lengths = vec![vec![0u32; m + 1]; n + 1];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lengths[i][j] = if a_mid[i] == b_mid[j] {
+                    lengths[i + 1][j + 1] + 1
+                } else {
+                    lengths[i + 1][j].max(lengths[i][j + 1])
+                };
+            }
+        }
+
+        let (mut i, mut j) = (0, 0);
+        while i < n && j < m {
+            if a_mid[i] == b_mid[j] {
+                pairs.push((prefix + i, prefix + j));
+                i += 1;
+                j += 1;
+            } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+    }
+
+    pairs.extend((0..suffix).map(|k| (a.len() - suffix + k, b.len() - suffix + k)));
+    pairs
+}
+
+#[test]
+fn test_diff() {
+    let hunks = |old: &str, new: &str| {
+        diff(&old.into(), &new.into())
+            .into_iter()
+            .map(|hunk| {
+                let changes = hunk
+                    .changes
+                    .iter()
+                    .map(|c| (c.row, c.col_start, c.col_end))
+                    .collect::<Vec<_>>();
+                (hunk.kind(), hunk.rows, hunk.old_rows, changes)
+            })
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(hunks("a\nb\nc", "a\nb\nc"), vec![]);
+
+    assert_eq!(
+        hunks("a\nc", "a\nb\nc"),
+        vec![(HunkKind::Added, 1..2, 1..1, vec![])]
+    );
+
+    assert_eq!(
+        hunks("a\nb\nc", "a\nc"),
+        vec![(HunkKind::Removed, 1..1, 1..2, vec![])]
+    );
+
+    // (only what's new within a modified line is highlighted)
+    assert_eq!(
+        hunks("x = 1\nplay x", "x = 12\nplay x"),
+        vec![(HunkKind::Modified, 0..1, 0..1, vec![(0, 5, 6)])]
+    );
+
+    assert_eq!(
+        hunks("a\nfoo\nb\nc", "a\nfox\nb\nd\ne"),
+        vec![
+            (HunkKind::Modified, 1..2, 1..2, vec![(1, 2, 3)]),
+            (HunkKind::Modified, 3..5, 3..4, vec![(3, 0, 1), (4, 0, 1)]),
+        ]
+    );
+}
@@ -1,20 +1,82 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 
 use tinyset::SetUsize;
 
-use crate::{selection::Selection, Direction, EditResult, LineData, MoveVariant, Pos, Range};
+use crate::{
+    bookmarks::Bookmarks,
+    crdt::{elems, Change, Elem, Op, Replica, SharedSelection},
+    history::{History, HistoryEntry},
+    reindent, selection::Selection, Direction, EditResult, InsertionInfo, LineData, MoveVariant,
+    Pos, Range, Token, WidgetInfo,
+};
 
+#[derive(Debug, Clone, Copy)]
 pub struct LineSelection {
     pub row: i32,
     pub col_start: i32,
     pub col_end: i32,
 }
 
+/**
+    Which lines changed since the last `clear_dirty_lines`, for things that cache per line (like the renderer).
+
+    - The `rows` have changed contents
+    - If lines were added or removed, all rows from `shifted_from` onwards may have moved, so should be considered dirty as well
+*/
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DirtyLines {
+    pub rows: BTreeSet<i32>,
+    pub shifted_from: Option<i32>,
+}
+
+impl DirtyLines {
+    pub fn is_clean(&self) -> bool {
+        self.rows.is_empty() && self.shifted_from.is_none()
+    }
+
+    pub fn is_dirty(&self, row: i32) -> bool {
+        self.rows.contains(&row) || self.shifted_from.map_or(false, |from| row >= from)
+    }
+
+    fn mark(&mut self, row: i32) {
+        self.rows.insert(row);
+    }
+
+    fn mark_shifted(&mut self, from: i32) {
+        self.shifted_from = Some(self.shifted_from.map_or(from, |prev| prev.min(from)));
+    }
+
+    fn mark_all(&mut self) {
+        self.rows.clear();
+        self.shifted_from = Some(0);
+    }
+}
+
+/**
+    Someone else's selections, while editing together, as where they are in this document now
+*/
+#[derive(Debug, Clone)]
+pub struct RemoteSelections {
+    pub site: u32,
+    pub carets: Vec<Pos>,
+    pub visual: Vec<LineSelection>,
+}
+
 pub struct EditorState {
     linedata: LineData,
     pub tab_width: usize,
     next_selection_id: usize,
     selections: Vec<Selection>,
+    dirty_lines: DirtyLines,
+    needs_redraw: bool,
+    history: History,
+    bookmarks: Bookmarks,
+    // whether the document was edited since the last checkpoint, and if so, whether it was just typing
+    pending_edit: Option<bool>,
+    // while editing together: the replicated document, the ops that weren't sent yet, and everyone else's selections (per site)
+    replica: Option<Replica>,
+    outgoing: Vec<Op>,
+    remote_selections: BTreeMap<u32, Vec<SharedSelection>>,
 }
 
 impl EditorState {
@@ -24,6 +86,14 @@ impl EditorState {
             tab_width: 2,
             next_selection_id: 0,
             selections: vec![],
+            dirty_lines: DirtyLines::default(),
+            needs_redraw: true,
+            history: History::new(LineData::new(), vec![]),
+            bookmarks: Bookmarks::default(),
+            pending_edit: None,
+            replica: None,
+            outgoing: vec![],
+            remote_selections: BTreeMap::new(),
         }
     }
 
@@ -33,7 +103,10 @@ impl EditorState {
     }
 
     pub fn with_linedata(mut self, linedata: LineData) -> Self {
+        self.history = History::new(linedata.clone(), vec![]);
         self.linedata = linedata;
+        self.dirty_lines.mark_all();
+        self.needs_redraw = true;
         self
     }
 
@@ -46,23 +119,32 @@ impl EditorState {
         let mut normalized = vec![];
 
         while let Some(mut next) = self.selections.pop() {
-            self.selections.retain(|other| {
-                if next.overlaps(other) {
-                    if selecting_id == Some(next.id) {
-                        // just kill the other
-                        // (noop)
-                    } else if selecting_id == Some(other.id) {
-                        // just kill self
-                        next = other.clone();
-                    } else {
-                        next.merge_with(other, prefer_caret_position);
+            // (merging can make it overlap selections that were already passed, so repeat until nothing changes)
+            loop {
+                let len = self.selections.len();
+
+                self.selections.retain(|other| {
+                    if next.overlaps(other) {
+                        if selecting_id == Some(next.id) {
+                            // just kill the other
+                            // (noop)
+                        } else if selecting_id == Some(other.id) {
+                            // just kill self
+                            next = other.clone();
+                        } else {
+                            next.merge_with(other, prefer_caret_position);
+                        }
+
+                        return false;
                     }
 
-                    return false;
-                }
+                    return true;
+                });
 
-                return true;
-            });
+                if self.selections.len() == len {
+                    break;
+                }
+            }
 
             normalized.push(next);
         }
@@ -70,20 +152,324 @@ impl EditorState {
         normalized.sort_by_key(|s| s.caret);
 
         self.selections = normalized;
+        self.needs_redraw = true;
+    }
+
+    /**
+        Asserts what all of the selection code relies on (in debug builds only): every caret and anchor is snapped to the document, ids are unique, and the selections are sorted without overlapping (or even touching)
+    */
+    fn check_selections(&self) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+
+        for s in &self.selections {
+            assert!(s.id < self.next_selection_id, "unknown selection id: {s:?}");
+            assert_eq!(
+                s.caret,
+                self.linedata.snap(s.caret),
+                "caret not snapped: {s:?}"
+            );
+            if let Some(anchor) = s.anchor {
+                assert_eq!(
+                    anchor,
+                    self.linedata.snap(anchor),
+                    "anchor not snapped: {s:?}"
+                );
+            }
+        }
+
+        for (i, a) in self.selections.iter().enumerate() {
+            for b in &self.selections[i + 1..] {
+                assert_ne!(a.id, b.id, "duplicate selection id: {a:?}");
+            }
+        }
+
+        for pair in self.selections.windows(2) {
+            assert!(
+                pair[0].range().end < pair[1].range().start,
+                "selections out of order or overlapping: {pair:?}"
+            );
+        }
     }
 
     pub fn linedata(&self) -> &LineData {
         &self.linedata
     }
 
+    pub fn dirty_lines(&self) -> &DirtyLines {
+        &self.dirty_lines
+    }
+
+    pub fn clear_dirty_lines(&mut self) {
+        self.dirty_lines = DirtyLines::default();
+    }
+
+    /**
+        Whether anything visible (text or selections) changed since the last `mark_drawn`
+    */
+    pub fn needs_redraw(&self) -> bool {
+        self.needs_redraw
+    }
+
+    pub fn mark_drawn(&mut self) {
+        self.needs_redraw = false;
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    /**
+        Records the edits since the last checkpoint (if any) in the undo history. Call this after handling a batch of input, so that whatever was done in response to a single key press is undone as a whole.
+    */
+    pub fn checkpoint(&mut self) {
+        if let Some(typing) = self.pending_edit.take() {
+            self.history
+                .record(self.linedata.clone(), self.selections.clone(), typing);
+        }
+    }
+
+    pub fn undo(&mut self) {
+        self.checkpoint();
+        if let Some(entry) = self.history.undo().cloned() {
+            self.restore(entry);
+        }
+    }
+
+    pub fn redo(&mut self) {
+        self.checkpoint();
+        if let Some(entry) = self.history.redo().cloned() {
+            self.restore(entry);
+        }
+    }
+
+    /**
+        Goes back (or forward) to any state in the undo tree
+    */
+    pub fn jump_to_history(&mut self, i: usize) {
+        self.checkpoint();
+        if let Some(entry) = self.history.jump(i).cloned() {
+            self.restore(entry);
+        }
+    }
+
+    fn restore(&mut self, entry: HistoryEntry) {
+        let old = std::mem::replace(&mut self.linedata, entry.linedata);
+        self.replicate_replacement(&old);
+
+        self.selections = entry.selections;
+        self.bookmarks.truncate(self.linedata.len());
+        self.dirty_lines.mark_all();
+        self.needs_redraw = true;
+        self.check_selections();
+    }
+
+    pub fn is_sharing(&self) -> bool {
+        self.replica.is_some()
+    }
+
+    /**
+        Starts editing together, as the one who has the document: from now on, every edit is replicated (see `take_shared_ops`)
+    */
+    pub fn start_sharing(&mut self, site: u32) {
+        self.replica = Some(Replica::new(site, &self.linedata));
+        self.outgoing = vec![];
+    }
+
+    /**
+        Joins someone else's document (from their `shared_snapshot`), instead of this one. The undo history starts over, because undoing to before would replace the shared document.
+    */
+    pub fn join_shared(&mut self, site: u32, snapshot: Vec<Op>) {
+        let replica = Replica::from_snapshot(site, snapshot);
+
+        self.linedata = replica.linedata();
+        self.replica = Some(replica);
+        self.outgoing = vec![];
+        self.history = History::new(self.linedata.clone(), vec![]);
+        self.pending_edit = None;
+        self.bookmarks.truncate(self.linedata.len());
+        self.set_single_caret((0, 0).into());
+        self.dirty_lines.mark_all();
+        self.needs_redraw = true;
+        self.check_selections();
+    }
+
+    pub fn stop_sharing(&mut self) {
+        self.replica = None;
+        self.outgoing = vec![];
+        self.remote_selections.clear();
+        self.needs_redraw = true;
+    }
+
+    /**
+        What someone needs to join the document
+    */
+    pub fn shared_snapshot(&self) -> Option<Vec<Op>> {
+        self.replica.as_ref().map(Replica::snapshot)
+    }
+
+    /**
+        The edits since the last time this was called, to send to the others
+    */
+    pub fn take_shared_ops(&mut self) -> Vec<Op> {
+        std::mem::take(&mut self.outgoing)
+    }
+
+    /**
+        Applies someone else's edits (which aren't part of the undo history by themselves, but will be in the next step that's recorded)
+    */
+    pub fn apply_shared_ops(&mut self, ops: Vec<Op>) {
+        let Some(replica) = &mut self.replica else {
+            return;
+        };
+
+        for change in replica.apply(ops) {
+            match change {
+                Change::Insert { index, elem } => {
+                    let pos = self.linedata.index_to_pos(index);
+                    let data = match elem {
+                        Elem::Token(token) => token.into(),
+                        Elem::Newline => '\n'.into(),
+                    };
+
+                    let info = self.apply_insert(pos, data);
+                    for s in &mut self.selections {
+                        s.adjust(EditResult::Insertion { info });
+                    }
+                }
+                Change::Remove { index } => {
+                    let start = self.linedata.index_to_pos(index);
+                    let end = self.linedata.index_to_pos(index + 1);
+                    self.apply_remove(Range { start, end });
+                }
+            }
+        }
+
+        self.check_selections();
+    }
+
+    /**
+        Where our selections are, for the others to draw
+    */
+    pub fn shared_selections(&self) -> Vec<SharedSelection> {
+        let Some(replica) = &self.replica else {
+            return vec![];
+        };
+
+        let after = |pos| replica.after(self.linedata.pos_to_index(pos));
+
+        self.selections
+            .iter()
+            .map(|s| SharedSelection {
+                caret: after(s.caret),
+                anchor: s.anchor.map(after),
+            })
+            .collect()
+    }
+
+    pub fn set_remote_selections(&mut self, site: u32, selections: Vec<SharedSelection>) {
+        self.remote_selections.insert(site, selections);
+        self.needs_redraw = true;
+    }
+
+    pub fn clear_remote_selections(&mut self) {
+        self.remote_selections.clear();
+        self.needs_redraw = true;
+    }
+
+    pub fn remote_selections(&self) -> Vec<RemoteSelections> {
+        let Some(replica) = &self.replica else {
+            return vec![];
+        };
+
+        let pos = |after| self.linedata.index_to_pos(replica.index_of(after));
+
+        self.remote_selections
+            .iter()
+            .map(|(&site, selections)| RemoteSelections {
+                site,
+                carets: selections.iter().map(|s| pos(s.caret)).collect(),
+                visual: selections
+                    .iter()
+                    .filter_map(|s| {
+                        let (caret, anchor) = (pos(s.caret), pos(s.anchor?));
+                        (caret != anchor).then(|| Pos::order(caret, anchor))
+                    })
+                    .flat_map(|range| self.linedata.line_selections(range))
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /**
+        Replicates going from one version of the whole document to the current one (like when undoing), as a single replacement of what's in between the parts that stayed the same
+    */
+    fn replicate_replacement(&mut self, old: &LineData) {
+        let Some(replica) = &mut self.replica else {
+            return;
+        };
+
+        let (old, new) = (elems(old), elems(&self.linedata));
+        let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+        let suffix = old[prefix..]
+            .iter()
+            .rev()
+            .zip(new[prefix..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        self.outgoing
+            .extend(replica.local_remove(prefix, old.len() - prefix - suffix));
+        self.outgoing
+            .extend(replica.local_insert(prefix, new[prefix..new.len() - suffix].to_vec()));
+    }
+
     pub fn caret_positions(&self) -> Vec<Pos> {
         self.selections.iter().map(|s| s.caret).collect()
     }
 
+    /// (just the selections that select something, not the carets)
+    pub fn selected_ranges(&self) -> Vec<Range> {
+        self.selections
+            .iter()
+            .filter_map(|s| s.has_selection())
+            .collect()
+    }
+
+    /**
+        The widget that's selected, if there's just one selection, and it spans exactly one widget token
+    */
+    pub fn selected_widget(&self) -> Option<WidgetInfo> {
+        let [s] = &self.selections[..] else {
+            return None;
+        };
+
+        let data = self.linedata.copy_range(s.has_selection()?);
+
+        match data.lines()[..] {
+            [ref line] => match line[..] {
+                [Token::Widget(info)] => Some(info),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
     pub fn has_selections(&self) -> bool {
         self.selections.len() > 0
     }
 
+    /**
+        Whether a position is inside one of the selections (and not just at a caret)
+    */
+    pub fn is_selected(&self, pos: Pos) -> bool {
+        self.selections
+            .iter()
+            .filter_map(|s| s.has_selection())
+            .any(|Range { start, end }| pos.within(start, end))
+    }
+
     pub fn visual_selections(&self) -> Vec<LineSelection> {
         let mut line_selections = vec![];
 
@@ -124,28 +510,108 @@ impl EditorState {
         SelectionBuilder::new(self)
     }
 
+    /**
+        Adds a caret (which is merged into whatever selection it lands on, keeping the new id)
+    */
     pub fn add_caret(&mut self, pos: Pos) -> usize {
         let caret = self.linedata.snap(pos);
-        self.selection().caret(caret).add()
+        let id = self.selection().caret(caret).add();
+        self.normalize_selections(None, None);
+        self.check_selections();
+        id
     }
 
     pub fn set_single_caret(&mut self, pos: Pos) -> usize {
         let caret = self.linedata.snap(pos);
-        self.selection().caret(caret).set_only()
+        let id = self.selection().caret(caret).set_only();
+        self.check_selections();
+        id
+    }
+
+    pub fn bookmarks(&self) -> &Bookmarks {
+        &self.bookmarks
+    }
+
+    // (where bookmarks and jumps go from: the last caret)
+    fn last_caret(&self) -> Option<Pos> {
+        self.selections.last().map(|s| s.caret)
+    }
+
+    /**
+        Bookmarks the line the caret is on, or removes its bookmark, returning whether it's bookmarked now
+    */
+    pub fn toggle_bookmark(&mut self) -> Option<bool> {
+        let caret = self.last_caret()?;
+        self.needs_redraw = true;
+        Some(self.bookmarks.toggle(caret.row))
+    }
+
+    /**
+        Moves the caret to the start of the next bookmarked line (or the previous one, going `back`), as a jump that can be gone back from
+    */
+    pub fn jump_to_bookmark(&mut self, back: bool) -> bool {
+        let Some(caret) = self.last_caret() else {
+            return false;
+        };
+        let Some(row) = self.bookmarks.next(caret.row, back) else {
+            return false;
+        };
+
+        self.record_jump();
+        let col = self.linedata.line_indent(row as usize) as i32;
+        self.set_single_caret(Pos { row, col });
+        true
+    }
+
+    /**
+        Remembers where the caret is, before moving it somewhere far away (see `jump_back`)
+    */
+    pub fn record_jump(&mut self) {
+        if let Some(caret) = self.last_caret() {
+            self.bookmarks.push_jump(caret);
+        }
+    }
+
+    pub fn jump_back(&mut self) -> bool {
+        let Some(caret) = self.last_caret() else {
+            return false;
+        };
+        let Some(to) = self.bookmarks.jump_back(caret) else {
+            return false;
+        };
+
+        self.set_single_caret(to);
+        true
+    }
+
+    pub fn jump_forward(&mut self) -> bool {
+        let Some(caret) = self.last_caret() else {
+            return false;
+        };
+        let Some(to) = self.bookmarks.jump_forward(caret) else {
+            return false;
+        };
+
+        self.set_single_caret(to);
+        true
     }
 
     pub fn deselect(&mut self) {
         self.selections = vec![];
+        self.needs_redraw = true;
     }
 
     pub fn select_all(&mut self) -> usize {
         let end = self.linedata.end();
-        self.selection()
+        let id = self
+            .selection()
             .for_range(Range {
                 start: (0, 0).into(),
                 end,
             })
-            .set_only()
+            .set_only();
+        self.check_selections();
+        id
     }
 
     pub fn select_word_at(&mut self, pos: Pos) {
@@ -154,6 +620,8 @@ impl EditorState {
             let id = self.selection().for_range(range).add();
             self.normalize_selections(Some(id), Some(Direction::Right));
         }
+
+        self.check_selections();
     }
 
     /**
@@ -217,7 +685,9 @@ impl EditorState {
                         search_from = found_range.end;
                         // continue search for next
                     } else {
+                        // (the next occurrence might touch the previous one, e.g. in "aaa")
                         self.selection().for_range(found_range).add();
+                        self.normalize_selections(None, Some(Direction::Right));
                         break;
                     }
                 } else {
@@ -225,6 +695,8 @@ impl EditorState {
                 }
             }
         }
+
+        self.check_selections();
     }
 
     // pub fn get_
@@ -237,13 +709,16 @@ impl EditorState {
         self.selections.retain_mut(|s| {
             if s.id == first_selection_id {
                 s.desired_col = None;
-                s.caret = self.linedata.snap(pos);
+                // (a lone caret becomes the anchor)
+                s.move_caret_to(self.linedata.snap(pos), true);
+                self.needs_redraw = true;
                 true
             } else {
                 false
             }
         });
 
+        self.check_selections();
         Some(first_selection_id)
     }
 
@@ -263,7 +738,21 @@ impl EditorState {
         copied
     }
 
-    pub fn paste(&mut self, mut data: Vec<LineData>) {
+    /**
+        Pastes, reindenting multi-line data to fit in wherever it's pasted
+    */
+    pub fn paste(&mut self, data: Vec<LineData>) {
+        self.paste_data(data, true);
+    }
+
+    /**
+        Pastes exactly what's on the clipboard, without reindenting
+    */
+    pub fn paste_verbatim(&mut self, data: Vec<LineData>) {
+        self.paste_data(data, false);
+    }
+
+    fn paste_data(&mut self, mut data: Vec<LineData>, reindent: bool) {
         if data.len() == 0 {
             return;
         }
@@ -295,12 +784,21 @@ impl EditorState {
                 continue;
             };
 
-            if let Some(range) = s.has_selection() {
-                self.remove(range);
-                self.insert(range.start, data, false);
+            let pos = match s.has_selection() {
+                Some(range) => {
+                    self.remove(range);
+                    range.start
+                }
+                None => s.caret,
+            };
+
+            let data = if reindent {
+                reindent::reindent(data, &self.linedata, pos, self.tab_width)
             } else {
-                self.insert(s.caret, data, false);
-            }
+                data
+            };
+
+            self.insert(pos, data, false);
         }
     }
 
@@ -314,6 +812,7 @@ impl EditorState {
         }
 
         self.normalize_selections(Some(id), None);
+        self.check_selections();
     }
 
     pub fn add_caret_vertically(&mut self, dir: Direction) {
@@ -339,7 +838,8 @@ impl EditorState {
                 .add();
         }
 
-        self.normalize_selections(None, Some(dir))
+        self.normalize_selections(None, Some(dir));
+        self.check_selections();
     }
 
     pub fn move_caret(&mut self, dir: Direction, selecting: bool, variant: MoveVariant) {
@@ -348,16 +848,35 @@ impl EditorState {
                 .move_selection_caret(s, dir, selecting, variant);
         }
 
-        self.normalize_selections(None, Some(dir))
+        self.normalize_selections(None, Some(dir));
+        self.check_selections();
     }
 
     pub fn clear(&mut self) {
-        self.linedata = LineData::new()
+        let old = std::mem::replace(&mut self.linedata, LineData::new());
+        self.replicate_replacement(&old);
+
+        self.pending_edit = Some(false);
+        self.bookmarks.truncate(self.linedata.len());
+        self.dirty_lines.mark_all();
+        self.needs_redraw = true;
+
+        // (whatever was selected is gone now)
+        if !self.selections.is_empty() {
+            self.set_single_caret((0, 0).into());
+        }
     }
 
     pub fn insert(&mut self, pos: Pos, data: LineData, set_single_caret_after: bool) {
         let pos = self.linedata.snap(pos);
-        let info = self.linedata.insert(pos, data);
+        if let Some(replica) = &mut self.replica {
+            let index = self.linedata.pos_to_index(pos);
+            self.outgoing
+                .extend(replica.local_insert(index, elems(&data)));
+        }
+
+        let info = self.apply_insert(pos, data);
+        self.pending_edit = Some(false);
 
         if set_single_caret_after {
             self.set_single_caret(info.end);
@@ -366,9 +885,60 @@ impl EditorState {
                 s.adjust(EditResult::Insertion { info });
             }
         }
+
+        self.check_selections();
+    }
+
+    pub fn remove(&mut self, range: Range) {
+        if let Some(replica) = &mut self.replica {
+            let index = self.linedata.pos_to_index(range.start);
+            let len = self.linedata.pos_to_index(range.end) - index;
+            self.outgoing.extend(replica.local_remove(index, len));
+        }
+
+        self.apply_remove(range);
+        self.pending_edit = Some(false);
+        self.check_selections();
     }
 
-    pub fn remove(&mut self, Range { start, end }: Range) {
+    /**
+        Changes the width of a widget (like when it's expanded), moving the selections after it along. It's not an edit: it's not undone, and not sent to the others (for whom widgets are placeholders anyway).
+    */
+    pub fn resize_widget(&mut self, id: usize, width: usize) -> bool {
+        let resized = self.linedata.resize_widget(id, width);
+
+        // (from the back, so that each shift is relative to where things were before resizing)
+        for &(at, delta) in resized.iter().rev() {
+            for s in &mut self.selections {
+                s.shift_after(at, delta);
+            }
+            self.dirty_lines.mark(at.row);
+        }
+
+        self.needs_redraw |= !resized.is_empty();
+        self.check_selections();
+
+        !resized.is_empty()
+    }
+
+    // (what inserting does to the document, whether it's our edit or someone else's)
+    fn apply_insert(&mut self, pos: Pos, data: LineData) -> InsertionInfo {
+        let info = self.linedata.insert(pos, data);
+
+        for row in info.start.row..=info.end.row {
+            self.dirty_lines.mark(row);
+        }
+        if info.added_lines > 0 {
+            self.dirty_lines.mark_shifted(info.end.row + 1);
+        }
+        self.bookmarks.adjust(EditResult::Insertion { info });
+        self.needs_redraw = true;
+
+        info
+    }
+
+    // (what removing does to the document and the selections, whether it's our edit or someone else's)
+    fn apply_remove(&mut self, Range { start, end }: Range) {
         self.selections.retain(|s| {
             let contained_entirely = start < s.caret
                 && s.caret < end
@@ -381,6 +951,13 @@ impl EditorState {
 
         let info = self.linedata.remove(start, end);
 
+        self.dirty_lines.mark(start.row);
+        if info.removed_lines > 0 {
+            self.dirty_lines.mark_shifted(start.row + 1);
+        }
+        self.bookmarks.adjust(EditResult::Removal { info });
+        self.needs_redraw = true;
+
         for s in &mut self.selections {
             s.adjust(EditResult::Removal { info });
         }
@@ -465,6 +1042,11 @@ impl EditorState {
     }
 
     pub fn write(&mut self, text: &str) {
+        // (typing a word is undone as a whole, but anything else is a separate step)
+        let typing = self.pending_edit != Some(false)
+            && text.chars().count() == 1
+            && Token::Char(text.chars().next().unwrap()).is_part_of_word();
+
         let mut done = SetUsize::new();
         while let Some(s) = self.selections.iter().find(|s| !done.contains(s.id)) {
             done.insert(s.id);
@@ -476,6 +1058,10 @@ impl EditorState {
                 self.insert(s.caret, LineData::from(text), false);
             }
         }
+
+        if typing && self.pending_edit.is_some() {
+            self.pending_edit = Some(true);
+        }
     }
 
     pub fn backspace(&mut self, variant: MoveVariant) {
@@ -615,6 +1201,7 @@ impl<'a> SelectionBuilder<'a, Caret> {
         self.state.next_selection_id += 1;
 
         self.state.selections.push(selection);
+        self.state.needs_redraw = true;
 
         id
     }
@@ -624,3 +1211,233 @@ impl<'a> SelectionBuilder<'a, Caret> {
         self.add()
     }
 }
+
+#[test]
+fn test_merging_selections() {
+    let mut state = EditorState::new().with_linedata("hello world\nfoo bar".into());
+
+    state.set_single_caret((3, 0).into());
+    state.add_caret((1, 1).into());
+    state.add_caret((6, 0).into());
+    assert_eq!(
+        state.caret_positions(),
+        vec![(3, 0).into(), (6, 0).into(), (1, 1).into()]
+    );
+
+    // selecting right until the carets on the first line run into each other
+    for _ in 0..3 {
+        state.move_caret(Direction::Right, true, MoveVariant::ByToken);
+    }
+    assert_eq!(
+        state.selected_ranges(),
+        vec![
+            Range {
+                start: (3, 0).into(),
+                end: (9, 0).into()
+            },
+            Range {
+                start: (1, 1).into(),
+                end: (4, 1).into()
+            },
+        ]
+    );
+
+    // a caret added inside of a selection is merged into it
+    state.add_caret((5, 0).into());
+    assert_eq!(state.selected_ranges().len(), 2);
+
+    // selections that just touch are merged too
+    state.set_single_caret((0, 0).into());
+    state.move_caret(Direction::Right, true, MoveVariant::ByToken);
+    state.add_caret((1, 0).into());
+    state.move_caret(Direction::Right, true, MoveVariant::ByToken);
+    assert_eq!(
+        state.selected_ranges(),
+        vec![Range {
+            start: (0, 0).into(),
+            end: (2, 0).into()
+        }]
+    );
+}
+
+#[test]
+fn test_selections_around_widgets() {
+    let widget = Token::Widget(WidgetInfo {
+        kind: "sample",
+        id: 1,
+        width: 4,
+    });
+    let linedata = LineData::from(vec![
+        Token::Char('a'),
+        Token::Char(' '),
+        widget,
+        Token::Char(' '),
+        Token::Char('b'),
+    ]);
+    let mut state = EditorState::new().with_linedata(linedata);
+
+    // carets can't end up inside of a widget
+    state.set_single_caret((3, 0).into());
+    assert_eq!(state.caret_positions(), vec![(2, 0).into()]);
+    state.set_single_caret((5, 0).into());
+    assert_eq!(state.caret_positions(), vec![(6, 0).into()]);
+
+    // and a selection across one selects it as a whole
+    state.set_single_caret((0, 0).into());
+    for _ in 0..3 {
+        state.move_caret(Direction::Right, true, MoveVariant::ByToken);
+    }
+    assert_eq!(
+        state.selected_ranges(),
+        vec![Range {
+            start: (0, 0).into(),
+            end: (6, 0).into()
+        }]
+    );
+    assert_eq!(state.selected_widget(), None);
+
+    // removing it moves whatever's after it back by its width
+    state.add_caret((8, 0).into());
+    state.remove_selections();
+    assert_eq!(state.linedata().to_string(), " b");
+    assert_eq!(state.caret_positions(), vec![(0, 0).into(), (2, 0).into()]);
+}
+
+#[test]
+fn test_editing_inside_other_selections() {
+    let mut state = EditorState::new().with_linedata("one two three".into());
+
+    // a selection of "two", and a caret in "three"
+    state.set_single_caret((4, 0).into());
+    state.extend_selection_to((7, 0).into());
+    state.add_caret((10, 0).into());
+
+    // removing "o thre" (as someone else might) removes the caret, and the end of the selection is clamped to where it was
+    state.remove(Range {
+        start: (6, 0).into(),
+        end: (12, 0).into(),
+    });
+    assert_eq!(state.linedata().to_string(), "one twe");
+    assert_eq!(
+        state.selected_ranges(),
+        vec![Range {
+            start: (4, 0).into(),
+            end: (6, 0).into()
+        }]
+    );
+    assert_eq!(state.caret_positions().len(), 1);
+
+    // the same goes for the caret end of a selection
+    state.set_single_caret((7, 0).into());
+    state.extend_selection_to((2, 0).into());
+    state.remove(Range {
+        start: (0, 0).into(),
+        end: (4, 0).into(),
+    });
+    assert_eq!(state.linedata().to_string(), "twe");
+    assert_eq!(
+        state.selected_ranges(),
+        vec![Range {
+            start: (0, 0).into(),
+            end: (3, 0).into()
+        }]
+    );
+    assert_eq!(state.caret_positions(), vec![(0, 0).into()]);
+}
+
+#[test]
+fn test_editing_with_multiple_carets() {
+    let mut state = EditorState::new().with_linedata("ab\ncd\nef".into());
+
+    state.set_single_caret((1, 0).into());
+    state.add_caret((1, 1).into());
+    state.add_caret((1, 2).into());
+    state.write("x\n");
+    assert_eq!(state.linedata().to_string(), "ax\nb\ncx\nd\nex\nf");
+    assert_eq!(
+        state.caret_positions(),
+        vec![(0, 1).into(), (0, 3).into(), (0, 5).into()]
+    );
+
+    // backspacing joins the lines again
+    state.backspace(MoveVariant::ByToken);
+    assert_eq!(state.linedata().to_string(), "axb\ncxd\nexf");
+    for _ in 0..3 {
+        state.backspace(MoveVariant::ByToken);
+    }
+    assert_eq!(state.linedata().to_string(), "bdf");
+    assert_eq!(
+        state.caret_positions(),
+        vec![(0, 0).into(), (1, 0).into(), (2, 0).into()]
+    );
+
+    // ...until the carets run into each other
+    state.backspace(MoveVariant::ByToken);
+    assert_eq!(state.linedata().to_string(), "f");
+    assert_eq!(state.caret_positions(), vec![(0, 0).into()]);
+}
+
+#[test]
+fn test_extending_a_caret() {
+    let mut state = EditorState::new().with_linedata("hello world".into());
+
+    // (shift-clicking somewhere else selects from the caret to there)
+    state.set_single_caret((2, 0).into());
+    state.add_caret((9, 0).into());
+    state.extend_selection_to((5, 0).into());
+    assert_eq!(
+        state.selected_ranges(),
+        vec![Range {
+            start: (2, 0).into(),
+            end: (5, 0).into()
+        }]
+    );
+
+    // and back again, leaving just a caret
+    state.extend_selection_to((2, 0).into());
+    assert_eq!(state.selected_ranges(), vec![]);
+    assert_eq!(state.caret_positions(), vec![(2, 0).into()]);
+}
+
+#[test]
+fn test_resizing_widgets() {
+    let widget = |width| {
+        Token::Widget(WidgetInfo {
+            kind: "sample",
+            id: 3,
+            width,
+        })
+    };
+    let linedata = LineData::from(vec![
+        vec![widget(4), Token::Char(' '), widget(4), Token::Char('a')],
+        vec![Token::Char('b')],
+    ]);
+    let mut state = EditorState::new().with_linedata(linedata);
+
+    // (the widget is in there twice, like when it was copy-pasted)
+    // a selection of the space between them, and carets after them
+    state.set_single_caret((4, 0).into());
+    state.extend_selection_to((5, 0).into());
+    state.add_caret((10, 0).into());
+    state.add_caret((1, 1).into());
+    state.clear_dirty_lines();
+
+    assert!(state.resize_widget(3, 6));
+    assert_eq!(state.linedata().lines()[0][2], widget(6));
+    assert_eq!(state.linedata().line_width(0), 14);
+    assert_eq!(
+        state.selected_ranges(),
+        vec![Range {
+            start: (6, 0).into(),
+            end: (7, 0).into()
+        }]
+    );
+    assert_eq!(
+        state.caret_positions(),
+        vec![(7, 0).into(), (14, 0).into(), (1, 1).into()]
+    );
+    assert_eq!(state.dirty_lines().rows, BTreeSet::from([0]));
+
+    // (nothing to do if it already is that wide)
+    assert!(!state.resize_widget(3, 6));
+}
@@ -0,0 +1,205 @@
+use std::time::{Duration, Instant};
+
+use crate::{LineData, Selection};
+
+/// Typing that follows the previous keystroke within this time is undone as a whole
+const COALESCE_TYPING: Duration = Duration::from_secs(1);
+
+/**
+    A snapshot of the document in the undo tree
+*/
+#[derive(Debug, Clone)]
+pub struct HistoryEntry {
+    pub parent: Option<usize>,
+    /// In the order they were created
+    pub children: Vec<usize>,
+    pub linedata: LineData,
+    pub selections: Vec<Selection>,
+    pub at: Instant,
+    /// The first row that differs from the parent
+    pub changed_row: Option<i32>,
+    /// How many characters were added (or removed, if negative) compared to the parent
+    pub delta: i64,
+    typing: bool,
+}
+
+/**
+    The full undo tree: undoing and then editing starts a new branch, instead of throwing away what was undone. So every state the document has ever been in can be recovered.
+
+    Entries are never removed, so their indices are also the order in which they were created.
+*/
+#[derive(Debug, Clone)]
+pub struct History {
+    entries: Vec<HistoryEntry>,
+    current: usize,
+    // which child redo goes to, per entry (the one we last came from)
+    redo_to: Vec<Option<usize>>,
+}
+
+fn char_count(linedata: &LineData) -> i64 {
+    linedata
+        .lines()
+        .iter()
+        .map(|line| line.len() as i64)
+        .sum::<i64>()
+        + linedata.len() as i64
+}
+
+impl History {
+    pub fn new(linedata: LineData, selections: Vec<Selection>) -> Self {
+        Self {
+            entries: vec![HistoryEntry {
+                parent: None,
+                children: vec![],
+                linedata,
+                selections,
+                at: Instant::now(),
+                changed_row: None,
+                delta: 0,
+                typing: false,
+            }],
+            current: 0,
+            redo_to: vec![None],
+        }
+    }
+
+    pub fn entries(&self) -> &[HistoryEntry] {
+        &self.entries
+    }
+
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    pub fn current_entry(&self) -> &HistoryEntry {
+        &self.entries[self.current]
+    }
+
+    /**
+        Records a new state of the document as a child of the current one. Consecutive typing is merged into a single entry, as long as that entry wasn't branched from yet.
+    */
+    pub(crate) fn record(&mut self, linedata: LineData, selections: Vec<Selection>, typing: bool) {
+        let parent = &self.entries[self.current];
+
+        let changed_row = parent
+            .linedata
+            .lines()
+            .iter()
+            .zip(linedata.lines())
+            .position(|(a, b)| a != b)
+            .or(Some(parent.linedata.len().min(linedata.len())))
+            .map(|row| row as i32);
+
+        if typing
+            && parent.typing
+            && parent.children.is_empty()
+            && parent.at.elapsed() < COALESCE_TYPING
+            && let Some(grandparent) = parent.parent
+        {
+            let delta = char_count(&linedata) - char_count(&self.entries[grandparent].linedata);
+
+            let entry = &mut self.entries[self.current];
+            entry.changed_row = entry.changed_row.min(changed_row);
+            entry.delta = delta;
+            entry.linedata = linedata;
+            entry.selections = selections;
+            entry.at = Instant::now();
+            return;
+        }
+
+        let delta = char_count(&linedata) - char_count(&parent.linedata);
+
+        let i = self.entries.len();
+        self.entries.push(HistoryEntry {
+            parent: Some(self.current),
+            children: vec![],
+            linedata,
+            selections,
+            at: Instant::now(),
+            changed_row,
+            delta,
+            typing,
+        });
+        self.redo_to.push(None);
+
+        self.entries[self.current].children.push(i);
+        self.redo_to[self.current] = Some(i);
+        self.current = i;
+    }
+
+    /**
+        Moves to the parent state, if any, and returns it
+    */
+    pub(crate) fn undo(&mut self) -> Option<&HistoryEntry> {
+        let parent = self.entries[self.current].parent?;
+        self.redo_to[parent] = Some(self.current);
+        self.current = parent;
+
+        Some(&self.entries[parent])
+    }
+
+    /**
+        Moves to the child state we last came from (or else the newest one), if any, and returns it
+    */
+    pub(crate) fn redo(&mut self) -> Option<&HistoryEntry> {
+        let child =
+            self.redo_to[self.current].or(self.entries[self.current].children.last().copied())?;
+        self.current = child;
+
+        Some(&self.entries[child])
+    }
+
+    /**
+        Moves to any state in the tree, and returns it
+    */
+    pub(crate) fn jump(&mut self, i: usize) -> Option<&HistoryEntry> {
+        if i >= self.entries.len() {
+            return None;
+        }
+
+        // remember the way back down, so that redo follows the branch we jumped to
+        let mut child = i;
+        while let Some(parent) = self.entries[child].parent {
+            self.redo_to[parent] = Some(child);
+            child = parent;
+        }
+
+        self.current = i;
+
+        Some(&self.entries[i])
+    }
+}
+
+#[test]
+fn test_history_branches() {
+    let mut history = History::new("a".into(), vec![]);
+
+    history.record("ab".into(), vec![], false);
+    history.record("abc".into(), vec![], false);
+
+    assert_eq!(history.undo().unwrap().linedata.to_string(), "ab");
+
+    // editing after undo starts a branch, and doesn't lose "abc"
+    history.record("abd".into(), vec![], false);
+    assert_eq!(history.entries().len(), 4);
+    assert_eq!(history.entries()[1].children, vec![2, 3]);
+
+    assert_eq!(history.jump(2).unwrap().linedata.to_string(), "abc");
+    assert_eq!(history.undo().unwrap().linedata.to_string(), "ab");
+    assert_eq!(history.redo().unwrap().linedata.to_string(), "abc");
+}
+
+#[test]
+fn test_history_coalesces_typing() {
+    let mut history = History::new("".into(), vec![]);
+
+    history.record("h".into(), vec![], true);
+    history.record("hi".into(), vec![], true);
+    history.record("hi!".into(), vec![], false);
+
+    assert_eq!(history.entries().len(), 3);
+    assert_eq!(history.entries()[1].linedata.to_string(), "hi");
+    assert_eq!(history.entries()[1].delta, 2);
+    assert_eq!(history.undo().unwrap().linedata.to_string(), "hi");
+    assert_eq!(history.undo().unwrap().linedata.to_string(), "");
+}
@@ -1,14 +1,20 @@
-#![feature(let_chains)]
-#![feature(if_let_guard)]
-
+mod bookmarks;
+mod crdt;
+mod diff;
 mod direction;
 mod editor_state;
+mod history;
 mod line_data;
 mod pos;
+mod reindent;
 mod selection;
 
+pub use self::bookmarks::*;
+pub use self::crdt::*;
+pub use self::diff::*;
 pub use self::direction::*;
 pub use self::editor_state::*;
+pub use self::history::*;
 pub use self::line_data::*;
 pub use self::pos::*;
 pub use self::selection::*;
@@ -1,15 +1,15 @@
 use debug_unreachable::debug_unreachable;
 
-use crate::{Direction, Pos, Range, Selection};
+use crate::{Direction, LineSelection, Pos, Range, Selection};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct WidgetInfo {
     pub kind: &'static str,
     pub id: usize,
     pub width: usize,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Token {
     Char(char),
     Widget(WidgetInfo),
@@ -49,6 +49,26 @@ impl Token {
     }
 }
 
+/**
+    How long a token is in the source text (a widget is written as `kind#id`)
+*/
+fn text_len(token: &Token) -> usize {
+    match token {
+        Token::Char(ch) => ch.len_utf8(),
+        Token::Widget(WidgetInfo { kind, id, .. }) => kind.len() + 1 + id.to_string().len(),
+    }
+}
+
+/// (the same, in characters)
+fn text_chars(token: &Token) -> usize {
+    match token {
+        Token::Char(_) => 1,
+        Token::Widget(WidgetInfo { kind, id, .. }) => {
+            kind.chars().count() + 1 + id.to_string().len()
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MoveVariant {
     ByToken,
@@ -120,6 +140,23 @@ impl LineData {
         self.0[row as usize].iter().map(Token::width).sum::<usize>() as i32
     }
 
+    /**
+        A range, as the spans it covers per line, for highlighting it
+    */
+    pub fn line_selections(&self, Range { start, end }: Range) -> Vec<LineSelection> {
+        (start.row..=end.row)
+            .map(|row| LineSelection {
+                row,
+                col_start: if row == start.row { start.col } else { 0 },
+                col_end: if row == end.row {
+                    end.col
+                } else {
+                    self.line_width(row)
+                },
+            })
+            .collect()
+    }
+
     pub fn line_index_col(&self, row: i32, i: usize) -> i32 {
         if row < 0 || row >= self.0.len() as i32 {
             return 0;
@@ -167,6 +204,124 @@ impl LineData {
         }
     }
 
+    /**
+        Translates a byte offset into `self.to_string()` (which is what gets handed to the language crate) back into a position. Offsets that fall inside a widget's textual representation snap to the widget's start.
+    */
+    pub fn offset_to_pos(&self, offset: usize) -> Pos {
+        let mut remaining = offset;
+
+        for (row, line) in self.0.iter().enumerate() {
+            let mut col = 0;
+
+            for token in line {
+                let len = text_len(token);
+
+                if remaining < len {
+                    return Pos { row: row as i32, col };
+                }
+
+                remaining -= len;
+                col += token.width() as i32;
+            }
+
+            if remaining == 0 {
+                return Pos { row: row as i32, col };
+            }
+
+            // the newline
+            remaining -= 1;
+        }
+
+        self.end()
+    }
+
+    /**
+        Like `offset_to_pos`, but for a line and column in `self.to_string()` (both counting from 1, and the column in characters, which is how the language crate reports where things are), so that only that one line needs to be gone through
+    */
+    pub fn line_col_to_pos(&self, line: usize, col: usize) -> Pos {
+        let row = line.saturating_sub(1);
+        let Some(tokens) = self.0.get(row) else {
+            return self.end();
+        };
+
+        let mut remaining = col.saturating_sub(1);
+        let mut pos = Pos {
+            row: row as i32,
+            col: 0,
+        };
+
+        for token in tokens {
+            let len = text_chars(token);
+
+            if remaining < len {
+                return pos;
+            }
+
+            remaining -= len;
+            pos.col += token.width() as i32;
+        }
+
+        pos
+    }
+
+    /**
+        The inverse of `offset_to_pos`: where a position is in `self.to_string()`
+    */
+    pub fn pos_to_offset(&self, pos: Pos) -> usize {
+        let mut offset = 0;
+
+        for (row, line) in self.0.iter().enumerate() {
+            let mut col = 0;
+
+            for token in line {
+                if row as i32 == pos.row && col >= pos.col {
+                    return offset;
+                }
+
+                offset += text_len(token);
+                col += token.width() as i32;
+            }
+
+            if row as i32 == pos.row {
+                return offset;
+            }
+
+            // the newline
+            offset += 1;
+        }
+
+        offset.saturating_sub(1)
+    }
+
+    /**
+        Where a position is among the tokens, counting a line break as one (which is how a `Replica` indexes the document)
+    */
+    pub fn pos_to_index(&self, pos: Pos) -> usize {
+        let (r, i) = self.snap_indices(self.snap(pos));
+
+        self.0[..r].iter().map(|line| line.len() + 1).sum::<usize>() + i
+    }
+
+    /**
+        The inverse of `pos_to_index`
+    */
+    pub fn index_to_pos(&self, index: usize) -> Pos {
+        let mut remaining = index;
+
+        for (row, line) in self.0.iter().enumerate() {
+            if remaining <= line.len() {
+                return Pos {
+                    row: row as i32,
+                    col: self.line_index_col(row as i32, remaining),
+                };
+            }
+
+            remaining -= line.len() + 1;
+        }
+
+        self.end()
+    }
+
     pub fn joined(datas: Vec<LineData>) -> LineData {
         LineData(datas.into_iter().map(|d| d.0).flatten().collect())
     }
@@ -448,6 +603,36 @@ impl LineData {
         }
     }
 
+    /**
+        Changes the width of a widget (wherever it is in the code), returning where it is (before resizing) and how much wider it got
+    */
+    pub fn resize_widget(&mut self, id: usize, width: usize) -> Vec<(Pos, i32)> {
+        let mut resized = vec![];
+
+        for (row, line) in self.0.iter_mut().enumerate() {
+            let mut col = 0;
+            for token in line.iter_mut() {
+                let token_width = token.width();
+                if let Token::Widget(info) = token
+                    && info.id == id
+                    && info.width != width
+                {
+                    resized.push((
+                        Pos {
+                            row: row as i32,
+                            col,
+                        },
+                        width as i32 - info.width as i32,
+                    ));
+                    info.width = width;
+                }
+                col += token_width as i32;
+            }
+        }
+
+        resized
+    }
+
     pub fn copy_range(&self, Range { start, end }: Range) -> LineData {
         debug_assert_eq!(start, self.snap(start));
         debug_assert_eq!(end, self.snap(end));
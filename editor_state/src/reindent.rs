@@ -0,0 +1,170 @@
+use crate::{LineData, Pos, Token};
+
+fn bracket_depth(line: &[Token]) -> i32 {
+    line.iter()
+        .map(|token| match token {
+            Token::Char('(' | '[' | '{') => 1,
+            Token::Char(')' | ']' | '}') => -1,
+            _ => 0,
+        })
+        .sum()
+}
+
+fn starts_with_closing_bracket(line: &[Token]) -> bool {
+    matches!(
+        line.iter().find(|token| !token.is_whitespace()),
+        Some(Token::Char(')' | ']' | '}'))
+    )
+}
+
+fn is_blank(line: &[Token]) -> bool {
+    line.iter().all(Token::is_whitespace)
+}
+
+fn indent(line: &[Token]) -> i32 {
+    line.iter()
+        .take_while(|token| token.is_whitespace())
+        .count() as i32
+}
+
+fn with_indent(line: &[Token], indent: i32) -> Vec<Token> {
+    (0..indent.max(0))
+        .map(|_| Token::Char(' '))
+        .chain(line.iter().copied().skip_while(Token::is_whitespace))
+        .collect()
+}
+
+/**
+    The indentation a new line at `row` would get: the same as the (non-blank) line above, or one level deeper if that line leaves a bracket open
+*/
+fn auto_indent(dest: &LineData, row: i32, tab_width: i32) -> i32 {
+    dest.lines()
+        .iter()
+        .take(row.max(0) as usize)
+        .rev()
+        .find(|line| !is_blank(line))
+        .map_or(0, |line| {
+            indent(line)
+                + if bracket_depth(line) > 0 {
+                    tab_width
+                } else {
+                    0
+                }
+        })
+}
+
+/**
+    Reindents multi-line `data`, that's about to be inserted at `pos` in `dest`, to fit in at the destination.
+
+    The pasted lines keep their indentation relative to each other, but the whole block is shifted so that it lines up with the brackets around it. (So it doesn't matter from which indentation level, or from which column of the first line, the snippet was copied.)
+*/
+pub fn reindent(data: LineData, dest: &LineData, pos: Pos, tab_width: usize) -> LineData {
+    if data.len() < 2 {
+        return data;
+    }
+
+    let tab_width = tab_width as i32;
+    let mut lines = data.lines().clone();
+
+    let dest_line = dest
+        .lines()
+        .get(pos.row as usize)
+        .map_or(&[][..], |line| &line[..]);
+
+    let prefix = dest_line
+        .iter()
+        .scan(0, |col, token| {
+            let token_col = *col;
+            *col += token.width() as i32;
+            Some((token_col, token))
+        })
+        .take_while(|&(col, _)| col < pos.col)
+        .map(|(_, &token)| token)
+        .collect::<Vec<_>>();
+
+    // the indentation level of the first pasted line, to which all the others are aligned
+    let first_level = if is_blank(&prefix) {
+        // the first line will be on a line of its own, so indent it like a new line
+        let level = auto_indent(dest, pos.row, tab_width)
+            - if starts_with_closing_bracket(&lines[0]) {
+                tab_width
+            } else {
+                0
+            };
+
+        let level = level.max(pos.col);
+        lines[0] = with_indent(&lines[0], level - pos.col);
+        level
+    } else {
+        indent(dest_line)
+            + if bracket_depth(&prefix) > 0 {
+                tab_width
+            } else {
+                0
+            }
+    };
+
+    // how much further the pasted lines are indented than their bracket depth (relative to the first line) asks for
+    let mut depth = bracket_depth(&lines[0]);
+    let mut excess = None;
+
+    for line in &lines[1..] {
+        if !is_blank(line) {
+            let expected_depth = depth
+                - if starts_with_closing_bracket(line) {
+                    1
+                } else {
+                    0
+                };
+            let line_excess = indent(line) - expected_depth * tab_width;
+            excess = Some(excess.map_or(line_excess, |e: i32| e.min(line_excess)));
+        }
+
+        depth += bracket_depth(line);
+    }
+
+    let Some(excess) = excess else {
+        return lines.into();
+    };
+
+    for line in &mut lines[1..] {
+        if !is_blank(line) {
+            *line = with_indent(line, first_level + indent(line) - excess);
+        }
+    }
+
+    lines.into()
+}
+
+#[test]
+fn test_reindent_into_block() {
+    let dest = LineData::from("def beat = {\n  \n}");
+    let data = LineData::from("    let a = [\n        1,\n    ];\n    a");
+
+    assert_eq!(
+        reindent(data, &dest, Pos { row: 1, col: 2 }, 2).to_string(),
+        "let a = [\n      1,\n  ];\n  a"
+    );
+}
+
+#[test]
+fn test_reindent_after_code() {
+    let dest = LineData::from("  def x = ");
+    let data = LineData::from("[\n      1,\n      2,\n    ]");
+
+    assert_eq!(
+        reindent(data, &dest, Pos { row: 0, col: 10 }, 2).to_string(),
+        "[\n    1,\n    2,\n  ]"
+    );
+}
+
+#[test]
+fn test_reindent_single_line() {
+    let dest = LineData::from("  {\n    ");
+    let data = LineData::from("   [1, 2]");
+
+    assert_eq!(
+        reindent(data, &dest, Pos { row: 1, col: 4 }, 2).to_string(),
+        "   [1, 2]"
+    );
+}
@@ -86,7 +86,7 @@ impl Selection {
             EditResult::Removal {
                 info:
                     RemovalInfo {
-                        start: _,
+                        start,
                         end,
                         delta,
                         removed_lines,
@@ -103,6 +103,9 @@ impl Selection {
                     } else {
                         self.caret.row -= removed_lines;
                     }
+                } else if self.caret > start {
+                    // (it was in the removed part, so it ends up where that was)
+                    self.caret = start;
                 }
 
                 if let Some(anchor) = self.anchor.as_mut() {
@@ -112,12 +115,35 @@ impl Selection {
                         } else {
                             anchor.row -= removed_lines;
                         }
+                    } else if *anchor > start {
+                        *anchor = start;
                     }
                 }
+
+                // keep invariant: anchor != caret
+                if self.anchor == Some(self.caret) {
+                    self.anchor = None;
+                }
             }
         }
     }
 
+    /**
+        Moves whatever's after `at` on its line along by `delta` columns (for when the widget at `at` was resized)
+    */
+    pub fn shift_after(&mut self, at: Pos, delta: i32) {
+        if self.caret.row == at.row && self.caret.col > at.col {
+            self.caret.col += delta;
+        }
+
+        if let Some(anchor) = self.anchor.as_mut()
+            && anchor.row == at.row
+            && anchor.col > at.col
+        {
+            anchor.col += delta;
+        }
+    }
+
     pub fn move_caret_to(&mut self, caret: Pos, selecting: bool) {
         if !selecting {
             self.anchor = None;
@@ -164,3 +190,115 @@ impl Ord for Selection {
         }
     }
 }
+
+#[cfg(test)]
+fn selection(anchor: Option<(i32, i32)>, caret: (i32, i32)) -> Selection {
+    Selection {
+        id: 0,
+        anchor: anchor.map(Into::into),
+        caret: caret.into(),
+        desired_col: None,
+    }
+}
+
+#[test]
+fn test_adjust_to_insertion() {
+    // "ab" inserted at 2.1
+    let insertion = EditResult::Insertion {
+        info: InsertionInfo {
+            start: Pos { row: 1, col: 2 },
+            end: Pos { row: 1, col: 4 },
+            delta: Pos { row: 0, col: 2 },
+            added_lines: 0,
+        },
+    };
+
+    // before it, nothing happens
+    let mut s = selection(Some((0, 0)), (1, 1));
+    s.adjust(insertion);
+    assert_eq!(s, selection(Some((0, 0)), (1, 1)));
+
+    // right at it, or after it on the same line, it moves along
+    let mut s = selection(Some((2, 1)), (5, 1));
+    s.adjust(insertion);
+    assert_eq!(s, selection(Some((4, 1)), (7, 1)));
+
+    // a selection around it grows
+    let mut s = selection(Some((1, 1)), (3, 1));
+    s.adjust(insertion);
+    assert_eq!(s, selection(Some((1, 1)), (5, 1)));
+
+    // on later lines, only the row changes (and here it doesn't)
+    let mut s = selection(None, (6, 2));
+    s.adjust(insertion);
+    assert_eq!(s, selection(None, (6, 2)));
+
+    // "x\nyz" inserted at 2.1
+    let insertion = EditResult::Insertion {
+        info: InsertionInfo {
+            start: Pos { row: 1, col: 2 },
+            end: Pos { row: 2, col: 2 },
+            delta: Pos { row: 1, col: 0 },
+            added_lines: 1,
+        },
+    };
+
+    let mut s = selection(Some((5, 1)), (6, 2));
+    s.adjust(insertion);
+    assert_eq!(s, selection(Some((5, 2)), (6, 3)));
+}
+
+#[test]
+fn test_adjust_to_removal() {
+    // 2.1 through 5.1 removed
+    let removal = EditResult::Removal {
+        info: RemovalInfo {
+            start: Pos { row: 1, col: 2 },
+            end: Pos { row: 1, col: 5 },
+            delta: Pos { row: 0, col: -3 },
+            removed_lines: 0,
+        },
+    };
+
+    // before it, and at its start, nothing happens
+    let mut s = selection(Some((0, 0)), (2, 1));
+    s.adjust(removal);
+    assert_eq!(s, selection(Some((0, 0)), (2, 1)));
+
+    // after it, on the same line, it moves back
+    let mut s = selection(Some((5, 1)), (8, 1));
+    s.adjust(removal);
+    assert_eq!(s, selection(Some((2, 1)), (5, 1)));
+
+    // inside of it, it's clamped to where it was
+    let mut s = selection(Some((0, 1)), (3, 1));
+    s.adjust(removal);
+    assert_eq!(s, selection(Some((0, 1)), (2, 1)));
+
+    let mut s = selection(Some((4, 1)), (7, 1));
+    s.adjust(removal);
+    assert_eq!(s, selection(Some((2, 1)), (4, 1)));
+
+    // ...and if all of it was removed, only a caret remains
+    let mut s = selection(Some((3, 1)), (4, 1));
+    s.adjust(removal);
+    assert_eq!(s, selection(None, (2, 1)));
+
+    // 3.1 through 1.3 removed (joining lines 1 and 3)
+    let removal = EditResult::Removal {
+        info: RemovalInfo {
+            start: Pos { row: 1, col: 3 },
+            end: Pos { row: 3, col: 1 },
+            delta: Pos { row: -2, col: 2 },
+            removed_lines: 2,
+        },
+    };
+
+    let mut s = selection(Some((0, 2)), (4, 3));
+    s.adjust(removal);
+    assert_eq!(s, selection(Some((3, 1)), (6, 1)));
+
+    let mut s = selection(None, (0, 5));
+    s.adjust(removal);
+    assert_eq!(s, selection(None, (0, 3)));
+}
@@ -0,0 +1,106 @@
+//! The nodes there are the most of, rendered a sample at a time (like before blocks) and a block at a time, for a second of audio, which has to take well under a second for dozens of voices to play on a laptop.
+//!
+//! Run with `cargo +nightly bench`, and compare against a baseline with `--save-baseline main` / `--baseline main`.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use live_engine::{AudioNode, Effect, MidiEvent, Mix, Osc, Poly, BLOCK_SIZE, SAMPLE_RATE};
+
+const VOICES: u8 = 32;
+
+fn per_sample(node: &mut dyn AudioNode) -> f32 {
+    let mut sum = 0.0;
+    for _ in 0..SAMPLE_RATE {
+        node.tick();
+        sum += node.get_next_sample();
+    }
+    sum
+}
+
+fn per_block(node: &mut dyn AudioNode) -> f32 {
+    let mut block = [0.0; BLOCK_SIZE];
+    let mut sum = 0.0;
+    for _ in 0..SAMPLE_RATE as usize / BLOCK_SIZE {
+        node.process(&mut block);
+        sum += block[0];
+    }
+    sum
+}
+
+fn osc(frequency: f32) -> Box<dyn AudioNode + Send> {
+    let mut osc = Osc::default();
+    osc.apply("frequency", frequency);
+    Box::new(osc)
+}
+
+fn mix() -> Mix {
+    (0..VOICES).fold(Mix::default(), |mix, i| {
+        mix.add(osc(110.0 * (1.0 + i as f32 / 4.0)))
+    })
+}
+
+fn lowpass() -> Effect {
+    Effect::new("lowpass", osc(220.0))
+        .unwrap()
+        .with("f", 800.0)
+        .with("q", 2.0)
+}
+
+fn poly() -> Poly {
+    let mut poly = Poly::new(|| {
+        let mut osc = Osc::default();
+        osc.map("freq".into(), "frequency".into());
+        let osc: Box<dyn AudioNode + Send> = Box::new(osc);
+        Box::new(Effect::new("lowpass", osc).unwrap().with("f", 1200.0))
+    })
+    .max_voices(VOICES as usize);
+
+    for note in 0..VOICES {
+        poly.note(MidiEvent::NoteOn {
+            note: 40 + note,
+            velocity: 1.0,
+        });
+    }
+    poly
+}
+
+fn dsp(c: &mut Criterion) {
+    let mut group = c.benchmark_group("a second of");
+
+    let mut node = osc(440.0);
+    group.bench_function("osc, per sample", |b| {
+        b.iter(|| black_box(per_sample(node.as_mut())))
+    });
+    group.bench_function("osc, per block", |b| {
+        b.iter(|| black_box(per_block(node.as_mut())))
+    });
+
+    let mut node = mix();
+    group.bench_function("32 oscs mixed, per sample", |b| {
+        b.iter(|| black_box(per_sample(&mut node)))
+    });
+    group.bench_function("32 oscs mixed, per block", |b| {
+        b.iter(|| black_box(per_block(&mut node)))
+    });
+
+    let mut node = lowpass();
+    group.bench_function("lowpass, per sample", |b| {
+        b.iter(|| black_box(per_sample(&mut node)))
+    });
+    group.bench_function("lowpass, per block", |b| {
+        b.iter(|| black_box(per_block(&mut node)))
+    });
+
+    // (held notes, which sustain for as long as it takes)
+    let mut node = poly();
+    group.bench_function("32 filtered voices, per sample", |b| {
+        b.iter(|| black_box(per_sample(&mut node)))
+    });
+    group.bench_function("32 filtered voices, per block", |b| {
+        b.iter(|| black_box(per_block(&mut node)))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, dsp);
+criterion_main!(benches);
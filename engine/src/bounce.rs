@@ -0,0 +1,251 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    time::Duration,
+};
+
+use crate::{
+    bus::{BusReturn, BusSend, Buses},
+    engine::{queues, Command, Commands, Processor},
+    flac::write_flac,
+    node::AudioNode,
+    transport::{clamp_swing, clamp_tempo, Quantize, BEATS_PER_BAR},
+    SAMPLE_RATE,
+};
+
+/// (24 bits is what a bounce is mastered from, more than that is just bigger)
+const BITS_PER_SAMPLE: u16 = 24;
+
+/**
+    Renders offline, into a file instead of to the audio device: the same graph (through the same master bus) as when it's played live, except that it's clocked by rendering itself, so it goes as fast as the CPU allows.
+
+    Play what's to be rendered first, and then render it a chunk at a time, which is how far along it is.
+*/
+pub struct Bounce {
+    commands: Commands,
+    processor: Processor,
+    // (its own, so that it doesn't mix with what's playing live)
+    buses: Buses,
+    length: usize,
+    samples: Vec<f32>,
+}
+
+impl Bounce {
+    /**
+        A bounce of this many bars, at the tempo (in bpm)
+    */
+    pub fn new(bars: f64, tempo: f64) -> Self {
+        let (mut commands, inbox) = queues();
+        let processor = Processor::new(
+            inbox,
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+        );
+
+        let tempo = clamp_tempo(tempo);
+        let _ = commands.send(Command::SetTempo { tempo });
+
+        let seconds = bars.max(0.0) * BEATS_PER_BAR * 60.0 / tempo;
+        let length = (seconds * SAMPLE_RATE as f64) as usize;
+
+        Self {
+            commands,
+            processor,
+            buses: Buses::default(),
+            length,
+            samples: Vec::with_capacity(length),
+        }
+    }
+
+    /// (like `EngineHandle::play`)
+    pub fn play(&mut self, target: impl Into<String>, node: Box<dyn AudioNode + Send>) {
+        let _ = self.commands.send(Command::Play {
+            target: target.into(),
+            node,
+        });
+    }
+
+    /// (like `EngineHandle::schedule`)
+    pub fn schedule(&mut self, target: impl Into<String>, node: Box<dyn AudioNode + Send>) {
+        let _ = self.commands.send(Command::Schedule {
+            target: target.into(),
+            node,
+        });
+    }
+
+    /// (like `EngineHandle::stop`)
+    pub fn stop(&mut self, target: impl Into<String>) {
+        let _ = self.commands.send(Command::Stop {
+            target: target.into(),
+        });
+    }
+
+    /// (like `EngineHandle::hush`)
+    pub fn hush(&mut self, fade: Duration) {
+        let _ = self.commands.send(Command::Hush {
+            samples: ((fade.as_secs_f64() * SAMPLE_RATE as f64) as usize).max(1),
+        });
+    }
+
+    /// (like `EngineHandle::panic`)
+    pub fn panic(&mut self) {
+        let _ = self.commands.send(Command::Panic);
+    }
+
+    /// (like `EngineHandle::bus`)
+    pub fn bus(&self, name: &str) -> BusReturn {
+        BusReturn::new(self.buses.get(name))
+    }
+
+    /// (like `EngineHandle::send`)
+    pub fn send(&self, node: Box<dyn AudioNode + Send>, name: &str, gain: f32) -> BusSend {
+        BusSend::new(node, self.buses.get(name), gain)
+    }
+
+    /// (like `EngineHandle::set_param`)
+    pub fn set_param(&mut self, name: impl Into<String>, value: f32) {
+        let _ = self.commands.set_param(name.into(), value, None);
+    }
+
+    /// (like `EngineHandle::set_quantize`)
+    pub fn set_quantize(&mut self, quantize: Quantize) {
+        let _ = self.commands.send(Command::SetQuantize { quantize });
+    }
+
+    /// (like `EngineHandle::set_swing`)
+    pub fn set_swing(&mut self, swing: f64) {
+        let _ = self.commands.send(Command::SetSwing {
+            swing: clamp_swing(swing),
+        });
+    }
+
+    /**
+        Renders (up to) the next `chunk` samples, and returns how far along it is, from 0 to 1
+    */
+    pub fn render(&mut self, chunk: usize) -> f32 {
+        let end = (self.samples.len() + chunk).min(self.length);
+
+        while self.samples.len() < end {
+            self.samples.push(self.processor.next_sample());
+        }
+        // (it's its own audio thread, so it frees what that's done with too)
+        self.commands.take_out_garbage();
+
+        self.progress()
+    }
+
+    pub fn progress(&self) -> f32 {
+        if self.length == 0 {
+            return 1.0;
+        }
+
+        self.samples.len() as f32 / self.length as f32
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.samples.len() >= self.length
+    }
+
+    /// What's rendered so far
+    pub fn samples(&self) -> &[f32] {
+        &self.samples
+    }
+
+    /**
+        Writes what's rendered so far as a (mono, 24 bit) WAV file
+    */
+    pub fn write_wav(&self, path: &Path) -> io::Result<()> {
+        save_wav(path, &self.samples, SAMPLE_RATE)
+    }
+
+    /**
+        Writes what's rendered so far as a (mono, 24 bit) FLAC file, which is the same, but smaller
+    */
+    pub fn write_flac(&self, path: &Path) -> io::Result<()> {
+        let samples = self
+            .samples
+            .iter()
+            .map(|&s| quantize(s))
+            .collect::<Vec<_>>();
+
+        let mut file = BufWriter::new(File::create(path)?);
+        write_flac(&mut file, &samples, SAMPLE_RATE, BITS_PER_SAMPLE as u32)?;
+        file.flush()
+    }
+}
+
+/**
+    Writes samples (at any sample rate) as a mono, 24 bit WAV file, like a bounce, for other audio that's made offline (like a time-stretched sample)
+*/
+pub fn save_wav(path: &Path, samples: &[f32], sample_rate: u32) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    write_wav(&mut file, samples, sample_rate)?;
+    file.flush()
+}
+
+fn write_wav(writer: &mut impl Write, samples: &[f32], sample_rate: u32) -> io::Result<()> {
+    let bytes_per_sample = BITS_PER_SAMPLE as u32 / 8;
+    let data_size = samples.len() as u32 * bytes_per_sample;
+
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(36 + data_size).to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+
+    writer.write_all(b"fmt ")?;
+    writer.write_all(&16u32.to_le_bytes())?;
+    // (PCM, mono)
+    writer.write_all(&1u16.to_le_bytes())?;
+    writer.write_all(&1u16.to_le_bytes())?;
+    writer.write_all(&sample_rate.to_le_bytes())?;
+    writer.write_all(&(sample_rate * bytes_per_sample).to_le_bytes())?;
+    writer.write_all(&(bytes_per_sample as u16).to_le_bytes())?;
+    writer.write_all(&BITS_PER_SAMPLE.to_le_bytes())?;
+
+    writer.write_all(b"data")?;
+    writer.write_all(&data_size.to_le_bytes())?;
+
+    for &sample in samples {
+        writer.write_all(&quantize(sample).to_le_bytes()[..3])?;
+    }
+
+    Ok(())
+}
+
+/// (to 24 bits)
+fn quantize(sample: f32) -> i32 {
+    let max = ((1 << (BITS_PER_SAMPLE - 1)) - 1) as f32;
+    // (the master bus limits it already, this is just so a NaN can't wrap around)
+    let sample = if sample.is_finite() { sample } else { 0.0 };
+    (sample.clamp(-1.0, 1.0) * max).round() as i32
+}
+
+#[test]
+fn test_bounce() {
+    use crate::node::Sampler;
+
+    // (a bar at 240 bpm is a second)
+    let mut bounce = Bounce::new(1.0, 240.0);
+    bounce.play(
+        "kick",
+        Box::new(Sampler::new(vec![0.5; 100_000], SAMPLE_RATE)),
+    );
+
+    assert_eq!(bounce.render(SAMPLE_RATE as usize / 2), 0.5);
+    assert!(!bounce.is_done());
+    assert_eq!(bounce.render(SAMPLE_RATE as usize), 1.0);
+    assert!(bounce.is_done());
+    assert_eq!(bounce.samples().len(), SAMPLE_RATE as usize);
+    assert!(bounce.samples()[1000] > 0.0);
+
+    let mut wav = vec![];
+    write_wav(&mut wav, &[0.0, 1.0, -1.0], SAMPLE_RATE).unwrap();
+    assert_eq!(&wav[0..4], b"RIFF");
+    assert_eq!(&wav[8..12], b"WAVE");
+    assert_eq!(wav.len(), 44 + 3 * 3);
+    assert_eq!(&wav[44..], &[0, 0, 0, 0xff, 0xff, 0x7f, 0x01, 0x00, 0x80]);
+}
@@ -0,0 +1,284 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use crate::{
+    midi::{MidiEvent, Tuning},
+    node::AudioNode,
+    realtime,
+};
+
+/**
+    A named bus, like `bus("drums")` in the language: what's sent to it is summed, one sample at a time, for the nodes that read it. Every sample starts out silent again (the processor clears the buses it routes before rendering).
+
+    Like a `Tap`, the sum is an atomic (f32 bits), but it's only ever touched on the audio thread, in the order the processor renders the targets in.
+*/
+#[derive(Clone)]
+pub struct Bus {
+    name: Arc<str>,
+    sum: Arc<AtomicU32>,
+}
+
+impl Bus {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.into(),
+            sum: Arc::new(AtomicU32::new(0f32.to_bits())),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(crate) fn is(&self, other: &Bus) -> bool {
+        Arc::ptr_eq(&self.sum, &other.sum)
+    }
+
+    fn add(&self, sample: f32) {
+        let sum = f32::from_bits(self.sum.load(Ordering::Relaxed));
+        self.sum.store((sum + sample).to_bits(), Ordering::Relaxed);
+    }
+
+    fn get(&self) -> f32 {
+        f32::from_bits(self.sum.load(Ordering::Relaxed))
+    }
+
+    pub(crate) fn clear(&self) {
+        self.sum.store(0f32.to_bits(), Ordering::Relaxed);
+    }
+}
+
+/**
+    The buses by name, so that sends and returns that are made separately (from different evaluations, even) end up on the same one
+*/
+#[derive(Clone, Default)]
+pub(crate) struct Buses(Arc<Mutex<HashMap<String, Bus>>>);
+
+impl Buses {
+    pub(crate) fn get(&self, name: &str) -> Bus {
+        let mut buses = realtime::lock(&self.0).unwrap();
+        buses
+            .entry(name.to_string())
+            .or_insert_with(|| Bus::new(name))
+            .clone()
+    }
+}
+
+/**
+    Which buses a node (and whatever it contains) sends to and reads from, so that the processor can render the senders of a bus before its readers
+*/
+#[derive(Clone, Default)]
+pub struct Routing {
+    pub sends: Vec<Bus>,
+    pub reads: Vec<Bus>,
+}
+
+impl Routing {
+    pub fn of(node: &dyn AudioNode) -> Self {
+        let mut routing = Self::default();
+        node.route(&mut routing);
+        routing
+    }
+
+    fn feeds(&self, other: &Routing) -> bool {
+        self.sends
+            .iter()
+            .any(|bus| other.reads.iter().any(|read| read.is(bus)))
+    }
+
+    /// (every bus it sends to or reads from)
+    pub(crate) fn buses(&self) -> impl Iterator<Item = &Bus> {
+        self.sends.iter().chain(&self.reads)
+    }
+}
+
+/**
+    In which order to render targets (by index), so that everything that sends to a bus comes before what reads it, and otherwise in the order they're given. When buses feed back into each other, there is no such order, and what's left is rendered in the order it's given (reading what was sent so far, that sample).
+*/
+pub(crate) fn processing_order(routings: &[Routing]) -> Vec<usize> {
+    let mut order = Vec::with_capacity(routings.len());
+    let mut done = vec![false; routings.len()];
+
+    while order.len() < routings.len() {
+        // (the first one that nothing that's left feeds)
+        let next = (0..routings.len())
+            .filter(|&i| !done[i])
+            .find(|&i| {
+                (0..routings.len()).all(|j| done[j] || j == i || !routings[j].feeds(&routings[i]))
+            })
+            .or_else(|| (0..routings.len()).find(|&i| !done[i]));
+
+        let Some(next) = next else {
+            break;
+        };
+
+        done[next] = true;
+        order.push(next);
+    }
+
+    order
+}
+
+/**
+    Sends its input to a bus, like `send(kick, "reverb", -6db)` in the language, instead of playing it: it's silent itself, and what reads the bus plays it. (For a parallel send, play the input as well.)
+*/
+pub struct BusSend {
+    input: Box<dyn AudioNode + Send>,
+    bus: Bus,
+    gain: f32,
+}
+
+impl BusSend {
+    pub(crate) fn new(input: Box<dyn AudioNode + Send>, bus: Bus, gain: f32) -> Self {
+        Self { input, bus, gain }
+    }
+}
+
+impl AudioNode for BusSend {
+    fn parameters(&self) -> Vec<String> {
+        vec![]
+    }
+
+    fn map(&mut self, _name: String, _parameter: String) {}
+
+    fn apply(&mut self, param: &str, value: f32) {
+        self.input.apply(param, value);
+    }
+
+    fn note(&mut self, event: MidiEvent) {
+        self.input.note(event);
+    }
+
+    fn retune(&mut self, tuning: &Tuning) {
+        self.input.retune(tuning);
+    }
+
+    fn route(&self, routing: &mut Routing) {
+        routing.sends.push(self.bus.clone());
+        self.input.route(routing);
+    }
+
+    fn economize(&mut self, economize: bool) {
+        self.input.economize(economize);
+    }
+
+    fn tick(&mut self) {
+        self.input.tick();
+        self.bus.add(self.input.get_next_sample() * self.gain);
+    }
+
+    fn get_next_sample(&self) -> f32 {
+        0.0
+    }
+}
+
+/**
+    What's sent to a bus (this sample), like `bus("drums")` in the language, to process and play like any other node. Its `volume` is a parameter, like an input's.
+*/
+pub struct BusReturn {
+    bus: Bus,
+
+    // parameters
+    volume: f32,
+
+    // audio node helper stuff
+    named_parameters: HashMap<String, String>,
+
+    // state
+    sample: f32,
+}
+
+impl BusReturn {
+    pub(crate) fn new(bus: Bus) -> Self {
+        Self {
+            bus,
+            volume: 1.0,
+            named_parameters: HashMap::new(),
+            sample: 0.0,
+        }
+    }
+}
+
+impl AudioNode for BusReturn {
+    fn parameters(&self) -> Vec<String> {
+        vec!["volume".into()]
+    }
+
+    fn map(&mut self, name: String, parameter: String) {
+        self.named_parameters.insert(name, parameter);
+    }
+
+    fn apply(&mut self, param: &str, value: f32) {
+        let param = self
+            .named_parameters
+            .get(param)
+            .map_or(param, |actual| actual.as_str());
+
+        if param == "volume" {
+            self.volume = value;
+        }
+    }
+
+    fn route(&self, routing: &mut Routing) {
+        routing.reads.push(self.bus.clone());
+    }
+
+    fn tick(&mut self) {
+        self.sample = self.bus.get();
+    }
+
+    fn get_next_sample(&self) -> f32 {
+        self.sample * self.volume
+    }
+}
+
+#[test]
+fn test_processing_order() {
+    let buses = Buses::default();
+    let routing = |sends: &[&str], reads: &[&str]| Routing {
+        sends: sends.iter().map(|name| buses.get(name)).collect(),
+        reads: reads.iter().map(|name| buses.get(name)).collect(),
+    };
+
+    // (the reverb return reads what the drums bus sends it, which reads the kick)
+    let routings = [
+        routing(&[], &["reverb"]),
+        routing(&["reverb"], &["drums"]),
+        routing(&[], &[]),
+        routing(&["drums"], &[]),
+    ];
+    assert_eq!(processing_order(&routings), vec![2, 3, 1, 0]);
+
+    // (feedback still renders everything, in the order it's given)
+    let routings = [routing(&["a"], &["b"]), routing(&["b"], &["a"])];
+    assert_eq!(processing_order(&routings), vec![0, 1]);
+}
+
+#[test]
+fn test_buses() {
+    use crate::node::Sampler;
+    use crate::SAMPLE_RATE;
+
+    let buses = Buses::default();
+    let constant = |value: f32| Box::new(Sampler::new(vec![value; 100], SAMPLE_RATE));
+
+    let mut kick = BusSend::new(constant(0.5), buses.get("drums"), 0.5);
+    let mut snare = BusSend::new(constant(0.2), buses.get("drums"), 1.0);
+    let mut drums = BusReturn::new(buses.get("drums"));
+
+    assert_eq!(Routing::of(&drums).reads[0].name(), "drums");
+    assert!(Routing::of(&kick).feeds(&Routing::of(&drums)));
+
+    buses.get("drums").clear();
+    kick.tick();
+    snare.tick();
+    drums.tick();
+
+    assert_eq!(kick.get_next_sample(), 0.0);
+    assert!((drums.get_next_sample() - 0.45).abs() < 1e-6);
+}
@@ -0,0 +1,93 @@
+use std::f32::consts::FRAC_PI_2;
+
+/**
+    Where a target is heard, of the output channels (however many there are, two for stereo, four for a quad rig, or more for an installation). By default, it's heard the same on all of them, which is what mono is.
+*/
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Placement {
+    #[default]
+    Everywhere,
+    /// Panned from the first channel (-1) to the last one (1), at equal power in between two neighbouring channels (which for stereo is the usual pan)
+    Pan(f32),
+    /// On just one channel, counting from 1 (and if there isn't such a channel, nowhere)
+    Channel(usize),
+    /// At a gain per channel, from the first one on (and not at all on the channels after those)
+    Gains(Vec<f32>),
+}
+
+impl Placement {
+    /**
+        The gain on every channel, when there are this many. (When there's only one, everything's heard on it, wherever it's placed, like when bouncing.)
+    */
+    pub fn gains(&self, channels: usize) -> Vec<f32> {
+        if channels <= 1 {
+            return vec![1.0; channels];
+        }
+
+        let mut gains = vec![0.0; channels];
+
+        match self {
+            Placement::Everywhere => gains.fill(1.0),
+            Placement::Pan(position) => {
+                let at = (position.clamp(-1.0, 1.0) + 1.0) / 2.0 * (channels - 1) as f32;
+                let i = (at.floor() as usize).min(channels - 2);
+                let between = (at - i as f32) * FRAC_PI_2;
+
+                gains[i] = between.cos();
+                gains[i + 1] = between.sin();
+            }
+            Placement::Channel(channel) => {
+                if let Some(gain) = channel.checked_sub(1).and_then(|i| gains.get_mut(i)) {
+                    *gain = 1.0;
+                }
+            }
+            Placement::Gains(placed) => {
+                for (gain, placed) in gains.iter_mut().zip(placed) {
+                    *gain = *placed;
+                }
+            }
+        }
+
+        gains
+    }
+}
+
+/**
+    Which of the output device's channels (counting from 1) every one of the engine's channels goes to: the ones in `map`, or else the first `channels` of them, in order
+*/
+pub(crate) fn channel_map(map: &[u16], channels: u16) -> Vec<u16> {
+    if map.is_empty() {
+        (1..=channels.max(1)).collect()
+    } else {
+        map.iter().map(|&channel| channel.max(1)).collect()
+    }
+}
+
+#[test]
+fn test_placement() {
+    let close = |a: Vec<f32>, b: Vec<f32>| a.iter().zip(&b).all(|(a, b)| (a - b).abs() < 0.001);
+
+    assert_eq!(Placement::Everywhere.gains(2), vec![1.0, 1.0]);
+
+    // (in the middle of two channels, at equal power)
+    assert!(close(Placement::Pan(0.0).gains(2), vec![0.707, 0.707]));
+    assert!(close(Placement::Pan(-1.0).gains(2), vec![1.0, 0.0]));
+    assert!(close(
+        Placement::Pan(1.0).gains(4),
+        vec![0.0, 0.0, 0.0, 1.0]
+    ));
+    assert!(close(Placement::Pan(0.0).gains(3), vec![0.0, 1.0, 0.0]));
+
+    assert_eq!(Placement::Channel(3).gains(4), vec![0.0, 0.0, 1.0, 0.0]);
+    assert_eq!(Placement::Channel(5).gains(4), vec![0.0; 4]);
+    assert_eq!(
+        Placement::Gains(vec![0.5, 0.25]).gains(3),
+        vec![0.5, 0.25, 0.0]
+    );
+
+    // (mono hears everything)
+    assert_eq!(Placement::Channel(3).gains(1), vec![1.0]);
+
+    assert_eq!(channel_map(&[], 2), vec![1, 2]);
+    assert_eq!(channel_map(&[3, 4], 2), vec![3, 4]);
+}
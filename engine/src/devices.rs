@@ -0,0 +1,225 @@
+use std::{
+    sync::{
+        atomic::{AtomicU32, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use cpal::{
+    traits::{DeviceTrait, HostTrait},
+    SupportedBufferSize, SupportedStreamConfigRange,
+};
+
+/// The sample rates that are offered, of the ones a device supports
+const SAMPLE_RATES: [u32; 5] = [44_100, 48_000, 88_200, 96_000, 192_000];
+/// The buffer sizes (in frames) that are offered, of the ones a device supports
+const BUFFER_SIZES: [u32; 7] = [32, 64, 128, 256, 512, 1024, 2048];
+/// How quickly the measured load follows along, per callback
+const LOAD_SMOOTHING: f32 = 0.05;
+
+/**
+    Which audio devices the engine plays to and records from (by name), and how. `None` is the system's default (and for the sample rate, the engine's own, `SAMPLE_RATE`).
+*/
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeviceSettings {
+    pub output: Option<String>,
+    pub input: Option<String>,
+    pub sample_rate: Option<u32>,
+    /// (in frames)
+    pub buffer_size: Option<u32>,
+    /// How many output channels the engine renders (`None` is as many as the device has by default, which is usually two)
+    pub channels: Option<u16>,
+    /// Which of the output device's channels (counting from 1) every one of the engine's channels goes to, like `[3, 4]` for the second pair of outputs of an audio interface. When it's empty, they go to the first ones, in order.
+    pub channel_map: Vec<u16>,
+}
+
+/**
+    An audio device that's plugged in, and the sample rates and buffer sizes it can do (of the usual ones), and how many channels it has at most
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceInfo {
+    pub name: String,
+    pub is_default: bool,
+    pub sample_rates: Vec<u32>,
+    pub buffer_sizes: Vec<u32>,
+    pub channels: u16,
+}
+
+/**
+    What the engine is actually playing to and recording from, which isn't what was asked for when that didn't work out
+*/
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Devices {
+    pub output: Option<String>,
+    pub input: Option<String>,
+    pub sample_rate: u32,
+    pub buffer_size: Option<u32>,
+    /// The output device's channels that the engine's channels go to (see `DeviceSettings::channel_map`)
+    pub channels: Vec<u16>,
+    /// (why the last switch didn't work out, if it didn't)
+    pub error: Option<String>,
+}
+
+pub(crate) type SharedDevices = Arc<Mutex<Devices>>;
+
+pub fn output_devices() -> Vec<DeviceInfo> {
+    let host = cpal::default_host();
+    let default = host.default_output_device().and_then(|d| d.name().ok());
+
+    let Ok(devices) = host.output_devices() else {
+        return vec![];
+    };
+
+    devices
+        .filter_map(|device| {
+            let ranges = device.supported_output_configs().ok()?;
+            device_info(&device, ranges.collect(), default.as_deref())
+        })
+        .collect()
+}
+
+pub fn input_devices() -> Vec<DeviceInfo> {
+    let host = cpal::default_host();
+    let default = host.default_input_device().and_then(|d| d.name().ok());
+
+    let Ok(devices) = host.input_devices() else {
+        return vec![];
+    };
+
+    devices
+        .filter_map(|device| {
+            let ranges = device.supported_input_configs().ok()?;
+            device_info(&device, ranges.collect(), default.as_deref())
+        })
+        .collect()
+}
+
+fn device_info(
+    device: &cpal::Device,
+    ranges: Vec<SupportedStreamConfigRange>,
+    default: Option<&str>,
+) -> Option<DeviceInfo> {
+    let name = device.name().ok()?;
+
+    let sample_rates = SAMPLE_RATES
+        .into_iter()
+        .filter(|&rate| {
+            ranges
+                .iter()
+                .any(|range| range.min_sample_rate().0 <= rate && rate <= range.max_sample_rate().0)
+        })
+        .collect();
+
+    // (when the device doesn't say, they're all worth a try)
+    let buffer_sizes = BUFFER_SIZES
+        .into_iter()
+        .filter(|&size| {
+            ranges.iter().any(|range| match range.buffer_size() {
+                SupportedBufferSize::Range { min, max } => *min <= size && size <= *max,
+                SupportedBufferSize::Unknown => true,
+            })
+        })
+        .collect();
+
+    let channels = ranges
+        .iter()
+        .map(|range| range.channels())
+        .max()
+        .unwrap_or(0);
+
+    Some(DeviceInfo {
+        is_default: default == Some(name.as_str()),
+        name,
+        sample_rates,
+        buffer_sizes,
+        channels,
+    })
+}
+
+/**
+    The output device with that name, or the default one
+*/
+pub(crate) fn find_output_device(name: Option<&str>) -> Result<cpal::Device, String> {
+    let host = cpal::default_host();
+
+    match name {
+        None => host
+            .default_output_device()
+            .ok_or_else(|| "no audio output device".to_string()),
+        Some(name) => host
+            .output_devices()
+            .map_err(|e| e.to_string())?
+            .find(|device| device.name().is_ok_and(|n| n == name))
+            .ok_or_else(|| format!("{} isn't there", name)),
+    }
+}
+
+/**
+    The input device with that name, or the default one
+*/
+pub(crate) fn find_input_device(name: Option<&str>) -> Result<cpal::Device, String> {
+    let host = cpal::default_host();
+
+    match name {
+        None => host
+            .default_input_device()
+            .ok_or_else(|| "no audio input device".to_string()),
+        Some(name) => host
+            .input_devices()
+            .map_err(|e| e.to_string())?
+            .find(|device| device.name().is_ok_and(|n| n == name))
+            .ok_or_else(|| format!("{} isn't there", name)),
+    }
+}
+
+/**
+    How much of the time it has the output callback needs to render a block, smoothed: at 1.0 (or over), it can't keep up anymore, and there are dropouts. And how many blocks it didn't render in time (the overruns, which are those dropouts).
+*/
+#[derive(Clone, Default)]
+pub(crate) struct CallbackLoad {
+    // (f32 bits, because there's no atomic float, like in a `Tap`)
+    load: Arc<AtomicU32>,
+    overruns: Arc<AtomicUsize>,
+}
+
+impl CallbackLoad {
+    pub(crate) fn measure(&self, took: Duration, block: Duration) {
+        if block.is_zero() {
+            return;
+        }
+
+        if took > block {
+            self.overruns.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let load = took.as_secs_f32() / block.as_secs_f32();
+        let smoothed = self.get() + (load - self.get()) * LOAD_SMOOTHING;
+        self.load.store(smoothed.to_bits(), Ordering::Relaxed);
+    }
+
+    pub(crate) fn get(&self) -> f32 {
+        f32::from_bits(self.load.load(Ordering::Relaxed))
+    }
+
+    pub(crate) fn overruns(&self) -> usize {
+        self.overruns.load(Ordering::Relaxed)
+    }
+}
+
+#[test]
+fn test_callback_load() {
+    let load = CallbackLoad::default();
+    assert_eq!(load.get(), 0.0);
+
+    for _ in 0..500 {
+        load.measure(Duration::from_millis(5), Duration::from_millis(10));
+    }
+    assert!((load.get() - 0.5).abs() < 0.01);
+    assert_eq!(load.overruns(), 0);
+
+    // (one slow block doesn't make it jump, but it is an overrun)
+    load.measure(Duration::from_millis(20), Duration::from_millis(10));
+    assert!(load.get() < 0.6);
+    assert_eq!(load.overruns(), 1);
+}
@@ -0,0 +1,662 @@
+use std::{collections::HashMap, f32::consts::PI};
+
+use crate::{
+    block::BLOCK_SIZE,
+    bus::Routing,
+    midi::{MidiEvent, Tuning},
+    modulation::Modulation,
+    node::AudioNode,
+    SAMPLE_RATE,
+};
+
+/// (no effect has more parameters than this, so they fit in an array on the audio thread)
+const MAX_PARAMS: usize = 5;
+
+/// The longest a delay can be, in seconds
+const MAX_DELAY: f32 = 2.0;
+
+/**
+    The effects that are built into the language, like `lowpass{f = 800hz, q = 2}`, with their parameters and what those default to. (The language crate lists the same ones, for the editor.)
+*/
+pub const EFFECTS: &[(&str, &[(&str, f32)])] = &[
+    ("lowpass", &[("f", 1000.0), ("q", 0.707)]),
+    ("highpass", &[("f", 1000.0), ("q", 0.707)]),
+    ("bandpass", &[("f", 1000.0), ("q", 0.707)]),
+    ("delay", &[("time", 0.25), ("feedback", 0.4), ("mix", 0.5)]),
+    ("reverb", &[("room", 0.5), ("damp", 0.5), ("mix", 0.3)]),
+    ("distortion", &[("drive", 2.0), ("mix", 1.0)]),
+    (
+        "compressor",
+        &[
+            ("threshold", -18.0),
+            ("ratio", 4.0),
+            ("attack", 0.01),
+            ("release", 0.1),
+            ("makeup", 0.0),
+        ],
+    ),
+];
+
+/**
+    The signal processing of an effect, one sample at a time, with the current parameter values (in the order of `EFFECTS`)
+*/
+trait Dsp {
+    fn process(&mut self, x: f32, params: &[f32]) -> f32;
+
+    /**
+        Like `process`, but listening to another signal (the sidechain) for how to process this one. Only dynamics care, the rest just process.
+    */
+    fn process_keyed(&mut self, x: f32, _key: f32, params: &[f32]) -> f32 {
+        self.process(x, params)
+    }
+
+    /// (see `AudioNode::economize`)
+    fn economize(&mut self, _economize: bool) {}
+
+    /**
+        Processes a block in place, with the parameter values for every sample in it. Only the filters do better than processing every sample.
+    */
+    fn process_block(&mut self, block: &mut [f32], params: &[[f32; BLOCK_SIZE]]) {
+        for (i, x) in block.iter_mut().enumerate() {
+            let mut values = [0.0; MAX_PARAMS];
+            for (value, param) in values.iter_mut().zip(params) {
+                *value = param[i];
+            }
+            *x = self.process(*x, &values[..params.len()]);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum FilterMode {
+    Lowpass,
+    Highpass,
+    Bandpass,
+}
+
+/// A state variable filter (the "trapezoidal" kind, which stays stable while its cutoff is modulated)
+struct Svf {
+    mode: FilterMode,
+    ic1eq: f32,
+    ic2eq: f32,
+}
+
+/// (k, a1, a2 and a3, which only change with the cutoff and the resonance)
+type Coefficients = [f32; 4];
+
+impl Svf {
+    fn coefficients(f: f32, q: f32) -> Coefficients {
+        let f = f.clamp(10.0, SAMPLE_RATE as f32 * 0.49);
+        let q = q.max(0.05);
+
+        let g = (PI * f / SAMPLE_RATE as f32).tan();
+        let k = 1.0 / q;
+        let a1 = 1.0 / (1.0 + g * (g + k));
+        let a2 = g * a1;
+        let a3 = g * a2;
+
+        [k, a1, a2, a3]
+    }
+
+    fn filter(&mut self, x: f32, [k, a1, a2, a3]: Coefficients) -> f32 {
+        let v3 = x - self.ic2eq;
+        let v1 = a1 * self.ic1eq + a2 * v3;
+        let v2 = self.ic2eq + a2 * self.ic1eq + a3 * v3;
+        self.ic1eq = 2.0 * v1 - self.ic1eq;
+        self.ic2eq = 2.0 * v2 - self.ic2eq;
+
+        match self.mode {
+            FilterMode::Lowpass => v2,
+            FilterMode::Highpass => x - k * v1 - v2,
+            FilterMode::Bandpass => v1,
+        }
+    }
+}
+
+impl Dsp for Svf {
+    fn process(&mut self, x: f32, params: &[f32]) -> f32 {
+        self.filter(x, Self::coefficients(params[0], params[1]))
+    }
+
+    /// (a filter depends on the sample before, so it can't be vectorized, but when the cutoff and the resonance aren't modulated, there's just one `tan` per block, instead of one per sample)
+    fn process_block(&mut self, block: &mut [f32], params: &[[f32; BLOCK_SIZE]]) {
+        let n = block.len();
+        let constant = |param: &[f32; BLOCK_SIZE]| param[..n].iter().all(|v| *v == param[0]);
+
+        if constant(&params[0]) && constant(&params[1]) {
+            let coefficients = Self::coefficients(params[0][0], params[1][0]);
+            for x in block {
+                *x = self.filter(*x, coefficients);
+            }
+        } else {
+            for (i, x) in block.iter_mut().enumerate() {
+                *x = self.filter(*x, Self::coefficients(params[0][i], params[1][i]));
+            }
+        }
+    }
+}
+
+struct Delay {
+    buffer: Vec<f32>,
+    write: usize,
+}
+
+impl Dsp for Delay {
+    fn process(&mut self, x: f32, params: &[f32]) -> f32 {
+        let len = self.buffer.len();
+        let (time, feedback, mix) = (params[0], params[1], params[2]);
+
+        // (fractional, so that modulating the time doesn't step)
+        let delay = (time * SAMPLE_RATE as f32).clamp(1.0, (len - 2) as f32);
+        let read = (self.write + len) as f32 - delay;
+        let i = read as usize;
+        let t = read.fract();
+        let a = self.buffer[i % len];
+        let b = self.buffer[(i + 1) % len];
+        let delayed = a + (b - a) * t;
+
+        // (a feedback of 1 or more would only keep getting louder)
+        self.buffer[self.write] = x + delayed * feedback.clamp(-0.99, 0.99);
+        self.write = (self.write + 1) % len;
+
+        x * (1.0 - mix) + delayed * mix
+    }
+}
+
+/// Freeverb's (mono) tunings, in samples at 44.1kHz
+const COMB_TUNINGS: [usize; 8] = [1116, 1188, 1277, 1356, 1422, 1491, 1557, 1617];
+const ALLPASS_TUNINGS: [usize; 4] = [556, 441, 341, 225];
+
+struct Comb {
+    buffer: Vec<f32>,
+    index: usize,
+    filtered: f32,
+}
+
+struct Allpass {
+    buffer: Vec<f32>,
+    index: usize,
+}
+
+/// Freeverb: parallel damped comb filters, into allpasses in series
+struct Reverb {
+    combs: Vec<Comb>,
+    allpasses: Vec<Allpass>,
+    // (only every other comb filter runs then, which is less dense, but half the work)
+    economizing: bool,
+}
+
+impl Reverb {
+    fn new() -> Self {
+        let scale = SAMPLE_RATE as f32 / 44_100.0;
+        let len = |tuning: usize| ((tuning as f32 * scale) as usize).max(1);
+
+        Self {
+            combs: COMB_TUNINGS
+                .iter()
+                .map(|&tuning| Comb {
+                    buffer: vec![0.0; len(tuning)],
+                    index: 0,
+                    filtered: 0.0,
+                })
+                .collect(),
+            allpasses: ALLPASS_TUNINGS
+                .iter()
+                .map(|&tuning| Allpass {
+                    buffer: vec![0.0; len(tuning)],
+                    index: 0,
+                })
+                .collect(),
+            economizing: false,
+        }
+    }
+}
+
+impl Dsp for Reverb {
+    fn process(&mut self, x: f32, params: &[f32]) -> f32 {
+        let feedback = params[0].clamp(0.0, 1.0) * 0.28 + 0.7;
+        let damp = params[1].clamp(0.0, 1.0) * 0.4;
+        let mix = params[2];
+
+        let input = x * 0.015;
+        let mut wet = 0.0;
+
+        let (step, gain) = if self.economizing { (2, 2.0) } else { (1, 1.0) };
+        for comb in self.combs.iter_mut().step_by(step) {
+            let out = comb.buffer[comb.index];
+            comb.filtered = out * (1.0 - damp) + comb.filtered * damp;
+            comb.buffer[comb.index] = input + comb.filtered * feedback;
+            comb.index = (comb.index + 1) % comb.buffer.len();
+            wet += out * gain;
+        }
+
+        for allpass in &mut self.allpasses {
+            let out = allpass.buffer[allpass.index];
+            allpass.buffer[allpass.index] = wet + out * 0.5;
+            allpass.index = (allpass.index + 1) % allpass.buffer.len();
+            wet = out - wet;
+        }
+
+        x * (1.0 - mix) + wet * 3.0 * mix
+    }
+
+    fn economize(&mut self, economize: bool) {
+        self.economizing = economize;
+    }
+}
+
+/// Soft clipping, driven into harder and harder
+struct Distortion;
+
+impl Dsp for Distortion {
+    fn process(&mut self, x: f32, params: &[f32]) -> f32 {
+        let (drive, mix) = (params[0].max(0.0), params[1]);
+        x * (1.0 - mix) + (x * drive).tanh() * mix
+    }
+}
+
+fn db_to_amplitude(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+/// A feed-forward compressor, with threshold and makeup gain in dB, and attack and release in seconds
+struct Compressor {
+    // how much it's currently turning down, in dB
+    reduction: f32,
+}
+
+impl Dsp for Compressor {
+    fn process(&mut self, x: f32, params: &[f32]) -> f32 {
+        self.process_keyed(x, x, params)
+    }
+
+    /// (turning down `x` by how loud `key` is, like pumping a pad with the kick)
+    fn process_keyed(&mut self, x: f32, key: f32, params: &[f32]) -> f32 {
+        let (threshold, ratio, attack, release, makeup) = (
+            params[0],
+            params[1].max(1.0),
+            params[2],
+            params[3],
+            params[4],
+        );
+
+        let level = 20.0 * key.abs().max(1e-6).log10();
+        let over = (level - threshold).max(0.0);
+        let target = over * (1.0 - 1.0 / ratio);
+
+        let time = if target > self.reduction {
+            attack
+        } else {
+            release
+        };
+        let coefficient = (-1.0 / (time.max(1e-4) * SAMPLE_RATE as f32)).exp();
+        self.reduction = target + (self.reduction - target) * coefficient;
+
+        x * db_to_amplitude(makeup - self.reduction)
+    }
+}
+
+/**
+    One of the built-in effects (see `EFFECTS`), processing the output of another node
+*/
+pub struct Effect {
+    input: Box<dyn AudioNode + Send>,
+    // (what the compressor listens to instead of its input, if anything)
+    sidechain: Option<Box<dyn AudioNode + Send>>,
+    dsp: Box<dyn Dsp + Send>,
+    names: &'static [(&'static str, f32)],
+    params: Vec<Modulation>,
+
+    // audio node helper stuff
+    named_parameters: HashMap<String, String>,
+
+    // state
+    out: f32,
+}
+
+impl Effect {
+    /**
+        The built-in effect with this name, with its parameters at their defaults
+    */
+    pub fn new(name: &str, input: Box<dyn AudioNode + Send>) -> Option<Self> {
+        let &(_, names) = EFFECTS.iter().find(|(effect, _)| *effect == name)?;
+
+        let dsp: Box<dyn Dsp + Send> = match name {
+            "lowpass" | "highpass" | "bandpass" => Box::new(Svf {
+                mode: match name {
+                    "lowpass" => FilterMode::Lowpass,
+                    "highpass" => FilterMode::Highpass,
+                    _ => FilterMode::Bandpass,
+                },
+                ic1eq: 0.0,
+                ic2eq: 0.0,
+            }),
+            "delay" => Box::new(Delay {
+                buffer: vec![0.0; (MAX_DELAY * SAMPLE_RATE as f32) as usize],
+                write: 0,
+            }),
+            "reverb" => Box::new(Reverb::new()),
+            "distortion" => Box::new(Distortion),
+            "compressor" => Box::new(Compressor { reduction: 0.0 }),
+            _ => return None,
+        };
+
+        Some(Self {
+            input,
+            sidechain: None,
+            dsp,
+            names,
+            params: names
+                .iter()
+                .map(|&(_, value)| Modulation::Constant(value))
+                .collect(),
+            named_parameters: HashMap::new(),
+            out: 0.0,
+        })
+    }
+
+    /**
+        Sets a parameter to a value, or modulates it. Unknown parameters are ignored, like with `apply`.
+    */
+    pub fn with(mut self, param: &str, modulation: impl Into<Modulation>) -> Self {
+        if let Some(i) = self.names.iter().position(|&(name, _)| name == param) {
+            self.params[i] = modulation.into();
+        }
+        self
+    }
+
+    /**
+        Listens to another signal for how to process the input (sidechaining), like `compressor{ratio = 8}(pad, bus("kick"))` in the language. Only the compressor does anything with it.
+    */
+    pub fn with_sidechain(mut self, sidechain: Box<dyn AudioNode + Send>) -> Self {
+        self.sidechain = Some(sidechain);
+        self
+    }
+}
+
+impl AudioNode for Effect {
+    fn parameters(&self) -> Vec<String> {
+        self.names.iter().map(|&(name, _)| name.into()).collect()
+    }
+
+    fn map(&mut self, name: String, parameter: String) {
+        self.named_parameters.insert(name, parameter);
+    }
+
+    fn apply(&mut self, param: &str, value: f32) {
+        // (the input, the sidechain and the modulations might know it)
+        self.input.apply(param, value);
+        if let Some(sidechain) = &mut self.sidechain {
+            sidechain.apply(param, value);
+        }
+        for modulation in &mut self.params {
+            modulation.apply(param, value);
+        }
+
+        let param = self
+            .named_parameters
+            .get(param)
+            .map_or(param, |actual| actual.as_str());
+
+        if let Some(i) = self.names.iter().position(|&(name, _)| name == param) {
+            self.params[i] = Modulation::Constant(value);
+        }
+    }
+
+    fn note(&mut self, event: MidiEvent) {
+        self.input.note(event);
+        for modulation in &mut self.params {
+            if let Modulation::Signal(node) = modulation {
+                node.note(event);
+            }
+        }
+    }
+
+    fn retune(&mut self, tuning: &Tuning) {
+        self.input.retune(tuning);
+        for modulation in &mut self.params {
+            if let Modulation::Signal(node) = modulation {
+                node.retune(tuning);
+            }
+        }
+    }
+
+    fn route(&self, routing: &mut Routing) {
+        self.input.route(routing);
+        if let Some(sidechain) = &self.sidechain {
+            sidechain.route(routing);
+        }
+        for modulation in &self.params {
+            modulation.route(routing);
+        }
+    }
+
+    fn economize(&mut self, economize: bool) {
+        self.dsp.economize(economize);
+        self.input.economize(economize);
+        if let Some(sidechain) = &mut self.sidechain {
+            sidechain.economize(economize);
+        }
+        for modulation in &mut self.params {
+            modulation.economize(economize);
+        }
+    }
+
+    fn tick(&mut self) {
+        self.input.tick();
+
+        let mut values = [0.0; MAX_PARAMS];
+        for (value, modulation) in values.iter_mut().zip(&mut self.params) {
+            *value = modulation.tick();
+        }
+
+        let x = self.input.get_next_sample();
+        let params = &values[..self.params.len()];
+
+        self.out = match &mut self.sidechain {
+            Some(sidechain) => {
+                sidechain.tick();
+                self.dsp
+                    .process_keyed(x, sidechain.get_next_sample(), params)
+            }
+            None => self.dsp.process(x, params),
+        };
+    }
+
+    fn get_next_sample(&self) -> f32 {
+        self.out
+    }
+
+    fn process(&mut self, out: &mut [f32]) {
+        let n = out.len();
+        if n == 0 {
+            return;
+        }
+
+        self.input.process(out);
+
+        let mut values = [[0.0; BLOCK_SIZE]; MAX_PARAMS];
+        for (values, modulation) in values.iter_mut().zip(&mut self.params) {
+            modulation.process(&mut values[..n]);
+        }
+        let params = &values[..self.params.len()];
+
+        match &mut self.sidechain {
+            Some(sidechain) => {
+                let mut key = [0.0; BLOCK_SIZE];
+                sidechain.process(&mut key[..n]);
+
+                for (i, x) in out.iter_mut().enumerate() {
+                    let mut values = [0.0; MAX_PARAMS];
+                    for (value, param) in values.iter_mut().zip(params) {
+                        *value = param[i];
+                    }
+                    *x = self.dsp.process_keyed(*x, key[i], &values[..params.len()]);
+                }
+            }
+            None => self.dsp.process_block(out, params),
+        }
+
+        self.out = out[n - 1];
+    }
+}
+
+#[cfg(test)]
+use crate::golden::{assert_golden, render};
+
+#[cfg(test)]
+fn peak(samples: &[f32]) -> f32 {
+    samples.iter().fold(0.0, |peak, s| peak.max(s.abs()))
+}
+
+#[test]
+fn test_filters() {
+    use crate::node::Osc;
+
+    let sine = |frequency: f32| {
+        let mut osc = Osc::default();
+        osc.apply("frequency", frequency);
+        osc.apply("volume", 1.0);
+        Box::new(osc)
+    };
+
+    let mut low = Effect::new("lowpass", sine(100.0))
+        .unwrap()
+        .with("f", 500.0);
+    let mut high = Effect::new("lowpass", sine(10_000.0))
+        .unwrap()
+        .with("f", 500.0);
+
+    // (after it settled)
+    assert!(peak(&render(&mut low, 4410)[2205..]) > 0.9);
+    assert!(peak(&render(&mut high, 4410)[2205..]) < 0.05);
+
+    let mut high = Effect::new("highpass", sine(100.0))
+        .unwrap()
+        .with("f", 5000.0);
+    assert!(peak(&render(&mut high, 4410)[2205..]) < 0.05);
+}
+
+#[test]
+fn test_modulated_parameter() {
+    use crate::node::{Osc, Sampler};
+
+    // a cutoff that sweeps between 0 and 1000hz
+    let mut lfo = Osc::default();
+    lfo.apply("frequency", 4.0);
+    lfo.apply("volume", 1000.0);
+
+    let input = Box::new(Sampler::new(vec![1.0; 10_000], SAMPLE_RATE));
+    let lfo: Box<dyn AudioNode + Send> = Box::new(lfo);
+    let mut filter = Effect::new("lowpass", input).unwrap().with("f", lfo);
+
+    let out = render(&mut filter, 5000);
+    assert!(out.iter().all(|s| s.is_finite()));
+    assert!(peak(&out) > 0.5);
+}
+
+#[test]
+fn test_delay_and_reverb() {
+    use crate::node::Sampler;
+
+    let click = || {
+        let mut samples = vec![0.0; 20_000];
+        // (the sampler moves before it's first read)
+        samples[1] = 1.0;
+        Box::new(Sampler::new(samples, SAMPLE_RATE))
+    };
+
+    let mut delay = Effect::new("delay", click())
+        .unwrap()
+        .with("time", 0.1)
+        .with("feedback", 0.5)
+        .with("mix", 1.0);
+    let out = render(&mut delay, 10_000);
+    let echo = (0.1 * SAMPLE_RATE as f32) as usize;
+    assert!((out[echo] - 1.0).abs() < 0.01);
+    assert!((out[2 * echo] - 0.5).abs() < 0.01);
+    assert!(peak(&out[echo + 1..2 * echo]) < 0.01);
+
+    let mut reverb = Effect::new("reverb", click()).unwrap().with("mix", 1.0);
+    let out = render(&mut reverb, 10_000);
+    assert!(peak(&out[5000..]) > 0.0);
+    assert!(peak(&out) < 1.0);
+
+    // (with half the comb filters, there's still a tail)
+    let mut economical = Effect::new("reverb", click()).unwrap().with("mix", 1.0);
+    economical.economize(true);
+    let out = render(&mut economical, 10_000);
+    assert!(peak(&out[5000..]) > 0.0);
+    assert!(peak(&out) < 1.0);
+}
+
+#[test]
+fn test_dynamics() {
+    use crate::node::Sampler;
+
+    let loud = || Box::new(Sampler::new(vec![0.9; 20_000], SAMPLE_RATE));
+
+    let mut compressor = Effect::new("compressor", loud()).unwrap();
+    let out = render(&mut compressor, 10_000);
+    assert!(out[9_999] < 0.5);
+
+    let mut distortion = Effect::new("distortion", loud())
+        .unwrap()
+        .with("drive", 10.0);
+    let out = render(&mut distortion, 10);
+    assert!(out[5] > 0.99 && out[5] <= 1.0);
+
+    // (ducked by something else that's loud, while itself it's quiet)
+    let quiet = Box::new(Sampler::new(vec![0.1; 20_000], SAMPLE_RATE));
+    let mut ducked = Effect::new("compressor", quiet)
+        .unwrap()
+        .with_sidechain(loud());
+    let out = render(&mut ducked, 10_000);
+    assert!(out[9_999] < 0.05);
+
+    assert!(Effect::new("flanger", loud()).is_none());
+}
+
+#[test]
+fn test_filters_golden() {
+    use crate::{golden::render_blocks, node::Osc};
+
+    for render in [render, render_blocks] {
+        // (a square-ish wave at 220hz, so there's something above and below the cutoff)
+        for name in ["lowpass", "highpass", "bandpass"] {
+            let mut osc = Osc::default();
+            osc.apply("frequency", 220.0);
+            osc.apply("squareness", 0.9);
+
+            let mut filter = Effect::new(name, Box::new(osc))
+                .unwrap()
+                .with("f", 880.0)
+                .with("q", 2.0);
+            assert_golden(name, &render(&mut filter, SAMPLE_RATE as usize / 20));
+        }
+    }
+}
+
+#[test]
+fn test_modulated_filter_golden() {
+    use crate::{
+        golden::render_blocks,
+        node::{Osc, Sampler},
+    };
+
+    for render in [render, render_blocks] {
+        let mut osc = Osc::default();
+        osc.apply("frequency", 110.0);
+        osc.apply("squareness", 0.9);
+
+        // (a cutoff that sweeps up from 200hz to 2khz)
+        let n = SAMPLE_RATE as usize / 20;
+        let sweep = (0..n).map(|i| 200.0 + 1800.0 * i as f32 / n as f32);
+        let sweep: Box<dyn AudioNode + Send> = Box::new(Sampler::new(sweep.collect(), SAMPLE_RATE));
+
+        let mut filter = Effect::new("lowpass", Box::new(osc))
+            .unwrap()
+            .with("f", sweep);
+        assert_golden("lowpass_modulated", &render(&mut filter, n));
+    }
+}
@@ -0,0 +1,2193 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+// (std's `Instant` panics in the browser)
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::plugin::{Plugin, Plugins};
+use crate::{
+    block::{Blocks, BLOCK_SIZE},
+    bus::{processing_order, Bus, BusReturn, BusSend, Buses, Routing},
+    channels::Placement,
+    devices::{CallbackLoad, DeviceSettings, Devices, SharedDevices},
+    guard::{EventRate, Runaway, Runaways, MAX_EVENTS_PER_SECOND, MAX_VOICES, RUNAWAY_PEAK},
+    input::{start_input, Input, LiveInput, Recorder},
+    launcher::Launcher,
+    master::{Master, MASTER_VOLUME},
+    meter::{Level, Levels, MasterLevel, Meter, SharedMasterLevel},
+    midi::{
+        MidiEvent, MidiIn, Tuning, MIDI_FREQ, MIDI_GATE, MIDI_LATENCY, MIDI_PITCH, MIDI_VELOCITY,
+    },
+    morph::{Morph, MORPH},
+    node::AudioNode,
+    output::start_output,
+    profile::{Costs, Profiler, SharedCosts, ECONOMIZE_LOAD, RELAXED_LOAD},
+    realtime::{self, permit, queue, Consumer, Producer},
+    smoothing::Smoothed,
+    tap::Tap,
+    timers::{Fired, SharedFired, Timers, Timing},
+    transport::{
+        clamp_swing, clamp_tempo, Quantize, SharedTransport, Transport, TransportState,
+        TRANSPORT_BEAT, TRANSPORT_SWING, TRANSPORT_TEMPO,
+    },
+    SAMPLE_RATE,
+};
+
+/// Replacing what a target plays (when the code changes, or a sample is reloaded) crossfades over this many samples (20ms), so it doesn't click
+const CROSSFADE_SAMPLES: usize = (SAMPLE_RATE / 50) as usize;
+
+/// How many commands (or parameter changes, or things to free) fit in a queue, which is way more than come in between two blocks
+const QUEUE_SIZE: usize = 1024;
+
+pub(crate) enum Command {
+    Play {
+        target: String,
+        node: Box<dyn AudioNode + Send>,
+    },
+    Stop {
+        target: String,
+    },
+    /// (like `Play`, but at the next boundary of the transport)
+    Schedule {
+        target: String,
+        node: Box<dyn AudioNode + Send>,
+    },
+    /// (an eased parameter change, which lands with what's scheduled)
+    ScheduleEase {
+        name: String,
+        value: f32,
+        ease: usize,
+    },
+    SetTempo {
+        tempo: f64,
+    },
+    SetQuantize {
+        quantize: Quantize,
+    },
+    SetSwing {
+        swing: f64,
+    },
+    Tap {
+        target: String,
+        tap: Tap,
+    },
+    Midi {
+        event: MidiEvent,
+        at: Instant,
+    },
+    Hush {
+        samples: usize,
+    },
+    Panic,
+    MuteSolo {
+        muted: Vec<String>,
+        soloed: Vec<String>,
+    },
+    Economize {
+        allowed: bool,
+    },
+    SetTuning {
+        tuning: Box<Tuning>,
+    },
+    SetTimer {
+        id: String,
+        timing: Timing,
+    },
+    RetimeTimer {
+        id: String,
+        timing: Timing,
+    },
+    ClearTimer {
+        id: String,
+    },
+    Place {
+        target: String,
+        placement: Placement,
+    },
+    SetMorph {
+        params: Vec<(String, f32, f32)>,
+    },
+    SetClips {
+        clips: Vec<(String, String)>,
+    },
+    /// (at the next bar, where no clip stops the track)
+    Launch {
+        track: String,
+        clip: Option<String>,
+    },
+}
+
+/**
+    A parameter change, which has a queue of its own (see `Commands`)
+*/
+pub(crate) struct Param {
+    name: String,
+    value: f32,
+    /// (over how many samples it glides there, instead of the usual smoothing)
+    ease: Option<usize>,
+}
+
+/**
+    What the audio thread is done with, on its way back to be freed on another thread (see `realtime`)
+*/
+// (it's only ever dropped, nothing's read from it, and a target isn't boxed, since that would allocate)
+#[allow(unused, clippy::large_enum_variant)]
+enum Garbage {
+    Node(Box<dyn AudioNode + Send>),
+    Target(Target),
+    Name(String),
+    Morph(Vec<(String, f32, f32)>),
+    Clips(Vec<(String, String)>),
+    Names(Vec<String>),
+    Tuning(Box<Tuning>),
+}
+
+/**
+    The other threads' ends of the queues to the audio thread (see `realtime::queue`): one for commands, and one for parameter changes, which come in much faster (for every mouse move of a drag), so that they never crowd out the commands. What the audio thread is done with comes back through a third one, and is freed here, whenever something's sent.
+*/
+pub(crate) struct Commands {
+    commands: Producer<Command>,
+    params: Producer<Param>,
+    garbage: Consumer<Garbage>,
+}
+
+/// (and the audio thread's ends)
+pub(crate) struct Inbox {
+    commands: Consumer<Command>,
+    params: Consumer<Param>,
+    garbage: Producer<Garbage>,
+}
+
+pub(crate) fn queues() -> (Commands, Inbox) {
+    let (commands, received_commands) = queue(QUEUE_SIZE);
+    let (params, received_params) = queue(QUEUE_SIZE);
+    let (thrown, garbage) = queue(QUEUE_SIZE);
+
+    (
+        Commands {
+            commands,
+            params,
+            garbage,
+        },
+        Inbox {
+            commands: received_commands,
+            params: received_params,
+            garbage: thrown,
+        },
+    )
+}
+
+impl Commands {
+    /// (which fails when the queue is full, because the audio thread isn't keeping up, and hands the command back)
+    pub fn send(&mut self, command: Command) -> Result<(), Command> {
+        self.take_out_garbage();
+        self.commands.push(command)
+    }
+
+    pub fn set_param(
+        &mut self,
+        name: String,
+        value: f32,
+        ease: Option<usize>,
+    ) -> Result<(), String> {
+        self.take_out_garbage();
+        self.params
+            .push(Param { name, value, ease })
+            .map_err(|param| param.name)
+    }
+
+    /// Frees what the audio thread is done with, returning how much that was
+    pub fn take_out_garbage(&mut self) -> usize {
+        let mut freed = 0;
+        while self.garbage.pop().is_some() {
+            freed += 1;
+        }
+        freed
+    }
+}
+
+impl Inbox {
+    /**
+        Sends something back to be freed, or frees it right here after all when the garbage is full (when nothing's been sent for a long while), which is better than keeping it forever
+    */
+    fn throw(&mut self, garbage: Garbage) {
+        if let Err(garbage) = self.garbage.push(garbage) {
+            permit(|| drop(garbage));
+        }
+    }
+}
+
+/**
+    Something that's being played, like `play beat;` in the code
+*/
+struct Target {
+    name: String,
+    node: Box<dyn AudioNode + Send>,
+    // (what it rendered ahead)
+    blocks: Blocks,
+    meter: Meter,
+    taps: Vec<Tap>,
+    // (after it ran away, until it's replaced)
+    muted: bool,
+    // what it played before it was replaced, while that's fading out, with how many samples to go
+    fading_out: Option<(Box<dyn AudioNode + Send>, Blocks, usize)>,
+    // (after a hush, until it's replaced) how many samples it still has to fade out, of how many
+    hushing: Option<(usize, usize)>,
+    placement: Placement,
+    // (what that comes down to, per output channel, for the engine's `channels`)
+    gains: Vec<f32>,
+}
+
+/**
+    The audio thread's side of the engine: renders the play targets (through the master bus) one sample at a time, picking up commands in between, where the nodes themselves render a block at a time (see `BLOCK_SIZE`).
+*/
+pub(crate) struct Processor {
+    targets: Vec<Target>,
+    master: Master,
+    inbox: Inbox,
+    levels: Levels,
+    master_level: SharedMasterLevel,
+    // (per target name, also for targets that aren't playing (yet))
+    taps: Vec<(String, Tap)>,
+    // parameters that are still gliding towards their new value
+    params: HashMap<String, Smoothed>,
+    // the last value we applied, per parameter, to glide from next time
+    applied: HashMap<String, f32>,
+    morph: Morph,
+    launcher: Launcher,
+    midi: MidiIn,
+    // (what the MIDI notes play at)
+    tuning: Tuning,
+    // (due sample, event), in order
+    scheduled_midi: VecDeque<(u64, MidiEvent)>,
+    // how many samples we rendered, and when the current output block started (at which sample), to place timestamped events
+    clock: u64,
+    block_started: Option<(Instant, u64)>,
+    event_rate: EventRate,
+    // what we last published, and whether that's outdated
+    runaways: HashMap<String, Runaway>,
+    runaways_changed: bool,
+    shared_runaways: Runaways,
+    // targets that are muted from the editor, and the ones that are soloed (if any are, only those are heard)
+    muted: Vec<String>,
+    soloed: Vec<String>,
+    transport: Transport,
+    // (beat it lands on, target, node) of what was scheduled, and whether that's changed since we last published it
+    scheduled: Vec<(f64, String, Box<dyn AudioNode + Send>)>,
+    scheduled_changed: bool,
+    // (beat it lands on, name, value, ease) of the parameter changes that land with it
+    scheduled_params: Vec<(f64, String, f32, usize)>,
+    shared_transport: SharedTransport,
+    // the buses the targets send to and read from (to clear every sample), and whether that might have changed, so the targets have to be put in order again
+    buses: Vec<Bus>,
+    routing_changed: bool,
+    // how long the targets take, and whether they're economizing because the engine couldn't keep up (and whether they may)
+    profiler: Profiler,
+    shared_costs: SharedCosts,
+    may_economize: bool,
+    economizing: bool,
+    // where the targets are heard, per target name, also for targets that aren't playing (yet)
+    placements: HashMap<String, Placement>,
+    timers: Timers,
+    shared_fired: SharedFired,
+    // (the targets that ran away this sample, by index, with their peak)
+    ran_away: Vec<(usize, f32)>,
+    // how many output channels there were last sample (which only changes with the device)
+    channels: usize,
+}
+
+impl Processor {
+    pub(crate) fn new(
+        inbox: Inbox,
+        levels: Levels,
+        master_level: SharedMasterLevel,
+        shared_runaways: Runaways,
+        shared_transport: SharedTransport,
+        shared_costs: SharedCosts,
+        shared_fired: SharedFired,
+    ) -> Self {
+        Self {
+            // (so that playing something new doesn't have to make room)
+            targets: Vec::with_capacity(MAX_VOICES),
+            master: Master::new(),
+            inbox,
+            levels,
+            master_level,
+            taps: vec![],
+            params: HashMap::new(),
+            applied: HashMap::new(),
+            morph: Morph::new(),
+            launcher: Launcher::default(),
+            midi: MidiIn::default(),
+            tuning: Tuning::default(),
+            scheduled_midi: VecDeque::new(),
+            clock: 0,
+            block_started: None,
+            event_rate: EventRate::default(),
+            runaways: HashMap::new(),
+            runaways_changed: false,
+            shared_runaways,
+            muted: vec![],
+            soloed: vec![],
+            transport: Transport::default(),
+            scheduled: vec![],
+            scheduled_changed: false,
+            scheduled_params: vec![],
+            shared_transport,
+            buses: vec![],
+            routing_changed: false,
+            profiler: Profiler::default(),
+            shared_costs,
+            may_economize: false,
+            economizing: false,
+            placements: HashMap::new(),
+            timers: Timers::default(),
+            shared_fired,
+            ran_away: Vec::with_capacity(MAX_VOICES),
+            channels: 0,
+        }
+    }
+
+    fn set_runaway(&mut self, name: &str, runaway: Option<Runaway>) {
+        // (which is rare, only when something ran away or calms down, and then it's fine to allocate for the warning)
+        let changed = permit(|| match runaway {
+            Some(runaway) => self.runaways.insert(name.to_string(), runaway) != Some(runaway),
+            None => self.runaways.remove(name).is_some(),
+        });
+
+        self.runaways_changed |= changed;
+    }
+
+    fn publish_runaways(&mut self) {
+        // (never block the audio thread, we'll just try again next sample)
+        if let Ok(mut shared) = self.shared_runaways.try_lock() {
+            *shared = self.runaways.clone();
+            self.runaways_changed = false;
+        }
+    }
+
+    /**
+        Called by the output after every block, with the callback load (see `CallbackLoad`), so that the nodes economize while the engine can't keep up (if they may)
+    */
+    pub(crate) fn set_load(&mut self, load: f32) {
+        let economize = self.may_economize
+            && if self.economizing {
+                load > RELAXED_LOAD
+            } else {
+                load > ECONOMIZE_LOAD
+            };
+
+        self.set_economizing(economize);
+    }
+
+    fn set_economizing(&mut self, economizing: bool) {
+        if economizing == self.economizing {
+            return;
+        }
+
+        self.economizing = economizing;
+        for target in &mut self.targets {
+            target.node.economize(economizing);
+            if let Some((previous, _, _)) = &mut target.fading_out {
+                previous.economize(economizing);
+            }
+        }
+    }
+
+    fn publish_costs(&mut self, per_target: HashMap<String, f32>) {
+        // (never block the audio thread, there's another second coming)
+        if let Ok(mut shared) = self.shared_costs.try_lock() {
+            shared.per_target = per_target;
+            shared.economizing = self.economizing;
+        }
+    }
+
+    /**
+        Called by the output at the start of every block, so that we know which sample is being rendered when
+    */
+    pub fn start_block(&mut self) {
+        self.block_started = Some((Instant::now(), self.clock));
+        self.publish_transport();
+    }
+
+    fn publish_transport(&mut self) {
+        // (never block the audio thread, we'll just try again next block)
+        if let Ok(mut shared) = self.shared_transport.try_lock() {
+            shared.tempo = self.transport.tempo;
+            shared.quantize = self.transport.quantize;
+            shared.swing = self.transport.swing;
+            shared.beat = self.transport.beat;
+
+            // (only when something's scheduled or lands, which is rare enough to allocate for)
+            if self.scheduled_changed {
+                permit(|| {
+                    shared.pending = self
+                        .scheduled
+                        .iter()
+                        .map(|(_, target, _)| target.clone())
+                        .collect()
+                });
+                self.scheduled_changed = false;
+            }
+
+            // (and what the clip launcher does, when a clip's launched, or lands)
+            if self.launcher.is_changed() {
+                permit(|| shared.launcher = self.launcher.state());
+            }
+        }
+    }
+
+    /**
+        Queues a MIDI event to be played `MIDI_LATENCY` after it happened
+    */
+    fn schedule_midi(&mut self, event: MidiEvent, at: Instant) {
+        let due = match self.block_started {
+            Some((started, clock)) => {
+                let delay = (at + MIDI_LATENCY).saturating_duration_since(started);
+                clock + (delay.as_secs_f64() * SAMPLE_RATE as f64) as u64
+            }
+            None => self.clock,
+        };
+
+        // (it can't be in the past, but if it is, play it now)
+        let due = due.max(self.clock);
+        let i = self.scheduled_midi.partition_point(|&(d, _)| d <= due);
+        self.scheduled_midi.insert(i, (due, event));
+    }
+
+    /**
+        Sets a parameter right away, without smoothing
+    */
+    fn apply_now(&mut self, name: &str, value: f32) {
+        apply(&mut self.targets, &mut self.applied, name, value);
+    }
+
+    fn play_midi(&mut self, event: MidiEvent) {
+        // (polyphonic nodes play every note on a voice of its own)
+        for target in &mut self.targets {
+            target.node.note(event);
+        }
+
+        // (and the rest hear the single `midi_in` signal)
+        match self.midi.handle(event) {
+            Some((note, velocity)) => {
+                self.apply_now(MIDI_PITCH, note as f32);
+                // (a note that isn't tuned keeps the frequency of the one before)
+                if let Some(freq) = self.tuning.freq(note) {
+                    self.apply_now(MIDI_FREQ, freq);
+                }
+                self.apply_now(MIDI_VELOCITY, velocity);
+                self.apply_now(MIDI_GATE, 1.0);
+            }
+            None => {
+                self.apply_now(MIDI_GATE, 0.0);
+            }
+        }
+    }
+
+    /**
+        Hands every target the taps that are reading from it, and lets go of the taps that nobody reads anymore
+    */
+    fn connect_taps(&mut self) {
+        for target in &mut self.targets {
+            target.taps.clear();
+        }
+
+        self.taps.retain(|(_, tap)| !tap.is_orphaned());
+
+        for (name, tap) in &self.taps {
+            if let Some(target) = self.targets.iter_mut().find(|t| t.name == *name) {
+                target.taps.push(tap.clone());
+            }
+        }
+    }
+
+    /**
+        Starts playing the node as the given target, or replaces (crossfading) what it played, and returns whether the target is new (so its taps have to be connected)
+    */
+    fn play(&mut self, target: String, mut node: Box<dyn AudioNode + Send>) -> bool {
+        // (re)apply the parameters that were set from the outside, the new node doesn't know about them yet
+        for (name, value) in &self.applied {
+            node.apply(name, *value);
+        }
+        // (and its patterns start where the transport is)
+        node.apply(TRANSPORT_BEAT, self.transport.beat as f32);
+        if self.economizing {
+            node.economize(true);
+        }
+        node.retune(&self.tuning);
+
+        let voices = self.targets.len();
+        self.routing_changed = true;
+
+        // (new code gets a new chance)
+        self.set_runaway(&target, None);
+
+        match self.targets.iter_mut().find(|t| t.name == target) {
+            Some(existing) => {
+                let previous = std::mem::replace(&mut existing.node, node);
+                let blocks = std::mem::take(&mut existing.blocks);
+                // (something that was muted stays silent)
+                let fading_out = match existing.muted {
+                    true => {
+                        self.inbox.throw(Garbage::Node(previous));
+                        None
+                    }
+                    false => Some((previous, blocks, CROSSFADE_SAMPLES)),
+                };
+                // (and what was still fading out from before is cut short)
+                if let Some((replaced, _, _)) =
+                    std::mem::replace(&mut existing.fading_out, fading_out)
+                {
+                    self.inbox.throw(Garbage::Node(replaced));
+                }
+                existing.muted = false;
+                existing.hushing = None;
+                self.inbox.throw(Garbage::Name(target));
+                false
+            }
+            None if voices >= MAX_VOICES => {
+                self.set_runaway(&target, Some(Runaway::TooManyVoices));
+                self.inbox.throw(Garbage::Node(node));
+                self.inbox.throw(Garbage::Name(target));
+                false
+            }
+            None => {
+                // (a new target, which is what may allocate)
+                permit(|| {
+                    let placement = self.placements.get(&target).cloned().unwrap_or_default();
+                    self.targets.push(Target {
+                        node,
+                        blocks: Blocks::default(),
+                        meter: Meter::default(),
+                        taps: vec![],
+                        muted: false,
+                        fading_out: None,
+                        hushing: None,
+                        gains: placement.gains(self.channels),
+                        placement,
+                        name: target,
+                    })
+                });
+                true
+            }
+        }
+    }
+
+    /**
+        Puts the targets in the order in which they have to be rendered, so that what's sent to a bus is there (that same sample) when it's read, and renders the ones that use buses a sample at a time, for that same reason
+    */
+    fn route(&mut self) {
+        let routings = self
+            .targets
+            .iter_mut()
+            .map(|target| {
+                let mut routing = Routing::of(target.node.as_ref());
+                // (what's fading out still sends, for a bit)
+                if let Some((previous, _, _)) = &target.fading_out {
+                    previous.route(&mut routing);
+                }
+
+                let size = match routing.buses().next() {
+                    Some(_) => 1,
+                    None => BLOCK_SIZE,
+                };
+                target.blocks.set_size(size);
+                if let Some((_, blocks, _)) = &mut target.fading_out {
+                    blocks.set_size(size);
+                }
+
+                routing
+            })
+            .collect::<Vec<_>>();
+
+        self.buses.clear();
+        for bus in routings.iter().flat_map(|routing| routing.buses()) {
+            if !self.buses.iter().any(|known| known.is(bus)) {
+                self.buses.push(bus.clone());
+            }
+        }
+
+        let mut targets = std::mem::take(&mut self.targets)
+            .into_iter()
+            .map(Some)
+            .collect::<Vec<_>>();
+        self.targets = processing_order(&routings)
+            .into_iter()
+            .filter_map(|i| targets[i].take())
+            .collect();
+
+        self.routing_changed = false;
+    }
+
+    /**
+        Plays what was scheduled to land on the current beat (or before)
+    */
+    fn land_scheduled(&mut self) {
+        let beat = self.transport.beat;
+
+        while let Some(i) = self
+            .scheduled_params
+            .iter()
+            .position(|(at, ..)| *at <= beat)
+        {
+            let (_, name, value, ease) = self.scheduled_params.swap_remove(i);
+            self.glide(name, value, Some(ease));
+        }
+
+        if !self.scheduled.iter().any(|(at, _, _)| *at <= beat) {
+            return;
+        }
+
+        // (like when a command comes in, it's on a boundary at most)
+        permit(|| {
+            let (due, later) = std::mem::take(&mut self.scheduled)
+                .into_iter()
+                .partition::<Vec<_>, _>(|(at, _, _)| *at <= beat);
+            self.scheduled = later;
+            self.scheduled_changed = true;
+
+            let mut reconnect = false;
+            for (_, target, node) in due {
+                reconnect |= self.play(target, node);
+            }
+
+            if reconnect {
+                self.connect_taps();
+            }
+        });
+    }
+
+    /**
+        Plays (or stops) the clips that were launched for the current beat (or before)
+    */
+    fn land_launched(&mut self) {
+        let beat = self.transport.beat;
+        if self.launcher.is_due(beat) {
+            // (it's on a bar at most)
+            permit(|| self.launcher.land(beat));
+        }
+    }
+
+    /// (and returns whether anything was scheduled for it)
+    fn unschedule(&mut self, target: &str) -> bool {
+        let mut unscheduled = false;
+        for (_, _, node) in self
+            .scheduled
+            .extract_if(.., |(_, scheduled, _)| scheduled == target)
+        {
+            self.inbox.throw(Garbage::Node(node));
+            unscheduled = true;
+        }
+        unscheduled
+    }
+
+    fn receive_params(&mut self) {
+        while let Some(Param { name, value, ease }) = self.inbox.params.pop() {
+            if name == MASTER_VOLUME {
+                self.master.volume.set_target(value);
+                self.inbox.throw(Garbage::Name(name));
+                continue;
+            }
+            if name == MORPH {
+                self.morph.set_position(value, ease);
+                self.inbox.throw(Garbage::Name(name));
+                continue;
+            }
+
+            // (`fx.f` comes from `def fx`)
+            let source = name.split('.').next().unwrap_or(&name);
+            if !self.event_rate.count(source) {
+                // (only when it's flooding, and then it's fine to allocate for the warning)
+                permit(|| {
+                    self.set_runaway(
+                        source,
+                        Some(Runaway::EventFlood {
+                            per_second: MAX_EVENTS_PER_SECOND,
+                        }),
+                    )
+                });
+                self.inbox.throw(Garbage::Name(name));
+                continue;
+            }
+
+            self.glide(name, value, ease);
+        }
+    }
+
+    /// (smoothed, or over so many samples)
+    fn glide(&mut self, name: String, value: f32, ease: Option<usize>) {
+        if !self.params.contains_key(&name) {
+            // the first time we hear of a parameter, there's nothing to glide from
+            let from = self.applied.get(&name).copied().unwrap_or(value);
+            // (and this is the first change since it last settled)
+            permit(|| self.params.insert(name.clone(), Smoothed::new(from)));
+        }
+
+        if let Some(param) = self.params.get_mut(&name) {
+            match ease {
+                Some(samples) => param.set_target_over(value, samples as f32),
+                None => param.set_target(value),
+            }
+        }
+        self.inbox.throw(Garbage::Name(name));
+    }
+
+    fn receive_commands(&mut self) {
+        let mut reconnect = false;
+
+        // (what comes with a command goes back in the garbage, only the few that make something new may allocate, like a new target)
+        while let Some(command) = self.inbox.commands.pop() {
+            match command {
+                Command::Play { target, node } => {
+                    reconnect |= self.play(target, node);
+                }
+                Command::Schedule { target, node } if self.transport.quantize == Quantize::Now => {
+                    reconnect |= self.play(target, node);
+                }
+                Command::Schedule { target, node } => {
+                    let at = self.transport.next_boundary();
+
+                    // (only the latest change to a target lands)
+                    self.unschedule(&target);
+                    permit(|| self.scheduled.push((at, target, node)));
+                    self.scheduled_changed = true;
+                }
+                Command::ScheduleEase { name, value, ease }
+                    if self.transport.quantize == Quantize::Now =>
+                {
+                    self.glide(name, value, Some(ease));
+                }
+                Command::ScheduleEase { name, value, ease } => {
+                    let at = self.transport.next_boundary();
+
+                    // (only the latest change to a parameter lands, too)
+                    for (_, replaced, _, _) in self
+                        .scheduled_params
+                        .extract_if(.., |(_, scheduled, _, _)| *scheduled == name)
+                    {
+                        self.inbox.throw(Garbage::Name(replaced));
+                    }
+                    permit(|| self.scheduled_params.push((at, name, value, ease)));
+                }
+                Command::SetTempo { tempo } => {
+                    self.transport.tempo = tempo;
+                    // (which the patterns keep time by)
+                    self.apply_now(TRANSPORT_TEMPO, tempo as f32);
+                }
+                Command::SetQuantize { quantize } => {
+                    self.transport.quantize = quantize;
+                }
+                Command::SetSwing { swing } => {
+                    self.transport.swing = swing;
+                    self.apply_now(TRANSPORT_SWING, swing as f32);
+                }
+                Command::SetMorph { params } => {
+                    let replaced = self.morph.set(params);
+                    self.inbox.throw(Garbage::Morph(replaced));
+                }
+                Command::SetClips { clips } => {
+                    // (which lets go of what's playing or queued that isn't a clip anymore)
+                    let replaced = permit(|| self.launcher.set_clips(clips));
+                    self.inbox.throw(Garbage::Clips(replaced));
+                }
+                Command::Launch { track, clip } => {
+                    let at = self.transport.next_bar();
+                    permit(|| self.launcher.launch(track, clip, at));
+                }
+                Command::Stop { target } => {
+                    for stopped in self.targets.extract_if(.., |t| t.name == target) {
+                        self.inbox.throw(Garbage::Target(stopped));
+                    }
+                    self.routing_changed = true;
+                    self.set_runaway(&target, None);
+
+                    // (stopping it also cancels what was going to replace it)
+                    self.scheduled_changed |= self.unschedule(&target);
+
+                    let metered = (self.levels.try_lock().ok())
+                        .and_then(|mut levels| levels.remove_entry(&target));
+                    if let Some((name, _)) = metered {
+                        self.inbox.throw(Garbage::Name(name));
+                    }
+                    self.inbox.throw(Garbage::Name(target));
+                }
+                Command::Tap { target, tap } => {
+                    permit(|| self.taps.push((target, tap)));
+                    reconnect = true;
+                }
+                Command::Midi { event, at } => {
+                    // (a new voice)
+                    permit(|| self.schedule_midi(event, at));
+                }
+                Command::Hush { samples } => {
+                    for target in &mut self.targets {
+                        // (hushing again doesn't make it take longer)
+                        if target.hushing.is_none() {
+                            target.hushing = Some((samples, samples));
+                        }
+                    }
+                }
+                Command::MuteSolo { muted, soloed } => {
+                    let muted = std::mem::replace(&mut self.muted, muted);
+                    let soloed = std::mem::replace(&mut self.soloed, soloed);
+                    self.inbox.throw(Garbage::Names(muted));
+                    self.inbox.throw(Garbage::Names(soloed));
+                }
+                Command::Economize { allowed } => {
+                    self.may_economize = allowed;
+                    if !allowed {
+                        self.set_economizing(false);
+                    }
+                }
+                Command::SetTuning { mut tuning } => {
+                    std::mem::swap(&mut self.tuning, &mut *tuning);
+                    self.inbox.throw(Garbage::Tuning(tuning));
+
+                    for target in &mut self.targets {
+                        target.node.retune(&self.tuning);
+                        if let Some((previous, _, _)) = &mut target.fading_out {
+                            previous.retune(&self.tuning);
+                        }
+                    }
+                }
+                // (a new timer, or what's left of the one it replaces)
+                Command::SetTimer { id, timing } => {
+                    permit(|| self.timers.set(id, timing, &self.transport));
+                }
+                Command::RetimeTimer { id, timing } => {
+                    permit(|| self.timers.retime(id, timing, &self.transport));
+                }
+                Command::ClearTimer { id } => {
+                    permit(|| self.timers.clear(&id));
+                    self.inbox.throw(Garbage::Name(id));
+                }
+                Command::Place { target, placement } => {
+                    // (like a new target)
+                    permit(|| {
+                        if let Some(existing) = self.targets.iter_mut().find(|t| t.name == target) {
+                            existing.gains = placement.gains(self.channels);
+                            existing.placement = placement.clone();
+                        }
+                        self.placements.insert(target, placement);
+                    });
+                }
+                Command::Panic => {
+                    for target in self.targets.drain(..) {
+                        self.inbox.throw(Garbage::Target(target));
+                    }
+                    self.routing_changed = true;
+                    self.scheduled_midi.clear();
+                    for (_, _, node) in self.scheduled.drain(..) {
+                        self.inbox.throw(Garbage::Node(node));
+                    }
+                    for (_, name, _, _) in self.scheduled_params.drain(..) {
+                        self.inbox.throw(Garbage::Name(name));
+                    }
+                    self.scheduled_changed = true;
+                    self.apply_now(MIDI_GATE, 0.0);
+
+                    // (which is rare, and then it's fine to free right here)
+                    permit(|| {
+                        self.launcher.stop_all();
+                        self.timers.clear_all();
+
+                        if let Ok(mut levels) = self.levels.try_lock() {
+                            levels.clear();
+                        }
+                    });
+                }
+            }
+        }
+
+        // (so orphaned taps are only cleaned up when there's a new one, which is good enough)
+        if reconnect {
+            permit(|| self.connect_taps());
+        }
+    }
+
+    /**
+        Renders the next sample in mono (where everything that's played is heard, however it's placed), like for a bounce
+    */
+    pub fn next_sample(&mut self) -> f32 {
+        let mut frame = [0.0];
+        self.next_frame(&mut frame);
+        frame[0]
+    }
+
+    /**
+        Renders the next sample for every output channel (as many as there are in the frame), where every target is heard as it's placed
+    */
+    pub fn next_frame(&mut self, frame: &mut [f32]) {
+        frame.fill(0.0);
+
+        // (only the first sample, or on another device, so it's fine to allocate the gains then)
+        if frame.len() != self.channels {
+            self.channels = frame.len();
+            permit(|| {
+                for target in &mut self.targets {
+                    target.gains = target.placement.gains(self.channels);
+                }
+            });
+        }
+
+        self.receive_commands();
+        self.receive_params();
+        self.land_scheduled();
+        self.land_launched();
+        self.timers
+            .tick(self.transport.beat, self.clock, &self.shared_fired);
+
+        while let Some(&(due, event)) = self.scheduled_midi.front() {
+            if due > self.clock {
+                break;
+            }
+
+            self.scheduled_midi.pop_front();
+            self.play_midi(event);
+        }
+
+        // (once a second, which is rare enough to allocate the names of the ones that calmed down)
+        if self.event_rate.is_due(self.clock) {
+            permit(|| {
+                let flooding = self.event_rate.tick(self.clock);
+
+                // (only the ones that are still at it keep their warning)
+                let calmed_down = self
+                    .runaways
+                    .iter()
+                    .filter(|(name, runaway)| {
+                        matches!(runaway, Runaway::EventFlood { .. }) && !flooding.contains(name)
+                    })
+                    .map(|(name, _)| name.clone())
+                    .collect::<Vec<_>>();
+
+                for name in calmed_down {
+                    self.set_runaway(&name, None);
+                }
+            });
+        }
+
+        self.clock += 1;
+        self.transport.tick();
+
+        if !self.params.is_empty() {
+            for (name, param) in self.params.iter_mut() {
+                apply(&mut self.targets, &mut self.applied, name, param.next());
+            }
+
+            for (name, _) in self.params.extract_if(|_, param| param.is_settled()) {
+                self.inbox.throw(Garbage::Name(name));
+            }
+        }
+
+        // (while it's morphing, that's where the parameters in the snapshots are, over whatever else set them)
+        if let Some(position) = self.morph.next() {
+            for (name, value) in self.morph.values(position) {
+                apply(&mut self.targets, &mut self.applied, name, value);
+            }
+        }
+
+        // (only when what's playing changed, which is rare enough to allocate the new order)
+        if self.routing_changed {
+            permit(|| self.route());
+        }
+        for bus in &self.buses {
+            bus.clear();
+        }
+
+        // (once a second too, when the costs are published)
+        if self.profiler.is_due(self.clock) {
+            permit(|| {
+                let costs = self.profiler.tick(self.clock);
+                self.publish_costs(costs);
+            });
+        }
+        let profiling = self.profiler.profiling(self.clock);
+
+        for (i, target) in self.targets.iter_mut().enumerate() {
+            // (a muted target doesn't even get rendered, so it can't take the engine down with it)
+            if target.muted {
+                continue;
+            }
+
+            let started = profiling.then(Instant::now);
+
+            let mut sample = target.blocks.next(target.node.as_mut());
+
+            if let Some((previous, blocks, remaining)) = &mut target.fading_out {
+                let fade = *remaining as f32 / CROSSFADE_SAMPLES as f32;
+                sample = sample * (1.0 - fade) + blocks.next(previous.as_mut()) * fade;
+                *remaining -= 1;
+            }
+            if matches!(target.fading_out, Some((_, _, 0))) {
+                if let Some((previous, _, _)) = target.fading_out.take() {
+                    self.inbox.throw(Garbage::Node(previous));
+                }
+                self.routing_changed = true;
+            }
+
+            if let Some(started) = started {
+                self.profiler.spent(&target.name, started.elapsed());
+            }
+
+            if let Some((remaining, total)) = &mut target.hushing {
+                sample *= *remaining as f32 / *total as f32;
+                *remaining = remaining.saturating_sub(1);
+            }
+
+            // (they're still rendered and metered, so they're in time and safe when they're heard again, and so are clips that aren't launched)
+            let audible = !self.launcher.is_silent(&target.name)
+                && !self.muted.contains(&target.name)
+                && (self.soloed.is_empty() || self.soloed.contains(&target.name));
+            if audible {
+                for (out, gain) in frame.iter_mut().zip(&target.gains) {
+                    *out += sample * gain;
+                }
+            }
+
+            for tap in &target.taps {
+                tap.push(sample);
+            }
+
+            if let Some(level) = target.meter.measure(sample) {
+                // (NaNs don't show up in the peak, but they do in the RMS)
+                if level.peak >= RUNAWAY_PEAK || !level.peak.is_finite() || !level.rms.is_finite() {
+                    target.muted = true;
+                    self.ran_away.push((i, level.peak));
+                }
+
+                // never block the audio thread, if someone's reading the levels right now, we'll just publish the next block
+                if let Ok(mut levels) = self.levels.try_lock() {
+                    match levels.get_mut(&target.name) {
+                        Some(known) => *known = level,
+                        // (only the first time it's measured, once per target, to share it by name)
+                        None => {
+                            permit(|| levels.insert(target.name.clone(), level));
+                        }
+                    }
+                }
+            }
+        }
+
+        // (before the hushed ones go, while the indices are right)
+        if !self.ran_away.is_empty() {
+            // (which is rare, and then it's fine to allocate for the warning)
+            permit(|| {
+                for j in 0..self.ran_away.len() {
+                    let (i, peak) = self.ran_away[j];
+                    let name = self.targets[i].name.clone();
+                    if let Ok(mut levels) = self.levels.try_lock() {
+                        levels.remove(&name);
+                    }
+
+                    self.set_runaway(&name, Some(Runaway::Feedback { peak }));
+                }
+            });
+            self.ran_away.clear();
+        }
+
+        // (the ones that are done fading out are stopped, like any other)
+        for hushed in self
+            .targets
+            .extract_if(.., |target| matches!(target.hushing, Some((0, _))))
+        {
+            self.routing_changed = true;
+
+            // (and its meter's name goes too)
+            let metered = (self.levels.try_lock().ok())
+                .and_then(|mut levels| levels.remove_entry(&hushed.name));
+            if let Some((name, _)) = metered {
+                self.inbox.throw(Garbage::Name(name));
+            }
+            self.inbox.throw(Garbage::Target(hushed));
+        }
+
+        // (only when something ran away, or calmed down, which is rare enough to allocate the warnings)
+        if self.runaways_changed {
+            permit(|| self.publish_runaways());
+        }
+
+        let master_level = self.master.process(frame);
+
+        if let Some(master_level) = master_level {
+            // (again, never block the audio thread)
+            if let Ok(mut shared) = self.master_level.try_lock() {
+                *shared = master_level;
+            }
+        }
+    }
+}
+
+/// Sets a parameter on every target, and remembers it for what's played later
+fn apply(targets: &mut [Target], applied: &mut HashMap<String, f32>, name: &str, value: f32) {
+    for target in targets {
+        target.node.apply(name, value);
+    }
+    match applied.get_mut(name) {
+        Some(applied) => *applied = value,
+        // (only the first time it's set, once per parameter, to remember it by name)
+        None => {
+            permit(|| applied.insert(name.to_string(), value));
+        }
+    }
+}
+
+/**
+    Sends commands to a running engine, from any thread
+*/
+#[derive(Clone)]
+pub struct EngineHandle {
+    commands: Arc<Mutex<Commands>>,
+    levels: Levels,
+    master_level: SharedMasterLevel,
+    runaways: Runaways,
+    transport: SharedTransport,
+    input: LiveInput,
+    buses: Buses,
+    devices: SharedDevices,
+    load: CallbackLoad,
+    costs: SharedCosts,
+    fired: SharedFired,
+    #[cfg(not(target_arch = "wasm32"))]
+    plugins: Plugins,
+}
+
+impl EngineHandle {
+    /**
+        Sets a (named) parameter anywhere in the graph. The change is smoothed on the audio thread, so this can be called for every mouse move of a drag.
+    */
+    pub fn set_param(&self, name: impl Into<String>, value: f32) {
+        self.param(name.into(), value, None);
+    }
+
+    /**
+        Like `set_param`, but it glides from the old value over the given time, for when the code that sets the parameter changed (see `DEFAULT_EASE`)
+    */
+    pub fn ease_param(&self, name: impl Into<String>, value: f32, ease: Duration) {
+        let ease = (ease.as_secs_f64() * SAMPLE_RATE as f64) as usize;
+        self.param(name.into(), value, Some(ease));
+    }
+
+    /**
+        Like `ease_param`, but the glide starts when what's scheduled lands (see `schedule`), so that the numbers change along with the code that was evaluated
+    */
+    pub fn schedule_ease(&self, name: impl Into<String>, value: f32, ease: Duration) {
+        self.command(Command::ScheduleEase {
+            name: name.into(),
+            value,
+            ease: (ease.as_secs_f64() * SAMPLE_RATE as f64) as usize,
+        });
+    }
+
+    fn param(&self, name: String, value: f32, ease: Option<usize>) {
+        let Ok(mut commands) = realtime::lock(&self.commands) else {
+            return;
+        };
+        if commands.set_param(name, value, ease).is_err() {
+            tracing::warn!("the audio thread isn't keeping up, a parameter change was dropped");
+        }
+    }
+
+    fn command(&self, command: Command) {
+        let Ok(mut commands) = realtime::lock(&self.commands) else {
+            return;
+        };
+        if commands.send(command).is_err() {
+            tracing::warn!("the audio thread isn't keeping up, a command was dropped");
+        }
+    }
+
+    /**
+        Starts playing the node as the given target, replacing (crossfading from) whatever was playing as that target before
+    */
+    #[allow(unused)]
+    pub fn play(&self, target: impl Into<String>, node: Box<dyn AudioNode + Send>) {
+        self.command(Command::Play {
+            target: target.into(),
+            node,
+        });
+    }
+
+    /**
+        Like `play`, except that it lands exactly on the next boundary of the transport (see `set_quantize`). Until then it's pending, and if something else is scheduled for the same target in the meantime, that lands instead.
+    */
+    pub fn schedule(&self, target: impl Into<String>, node: Box<dyn AudioNode + Send>) {
+        self.command(Command::Schedule {
+            target: target.into(),
+            node,
+        });
+    }
+
+    /**
+        In beats per minute (which is how long the bars and phrases that changes are quantized to are)
+    */
+    pub fn set_tempo(&self, tempo: f64) {
+        self.command(Command::SetTempo {
+            tempo: clamp_tempo(tempo),
+        });
+    }
+
+    /**
+        Whether what's scheduled lands right away, or at the next bar or phrase
+    */
+    pub fn set_quantize(&self, quantize: Quantize) {
+        self.command(Command::SetQuantize { quantize });
+    }
+
+    /**
+        How far every second 16th of the patterns comes late (see `Groove::swing`), for the patterns that don't swing on their own
+    */
+    pub fn set_swing(&self, swing: f64) {
+        self.command(Command::SetSwing {
+            swing: clamp_swing(swing),
+        });
+    }
+
+    /**
+        The two snapshots to morph between, as (parameter name, value in the first, value in the second). Where it is in between is the `MORPH` parameter, from 0 (the first) to 1 (the second), which is set (and eased) like any other.
+    */
+    pub fn set_morph(&self, params: Vec<(String, f32, f32)>) {
+        self.command(Command::SetMorph { params });
+    }
+
+    /**
+        Which targets are clips in the launcher's grid, as (target, track), replacing what was set before. A clip is only heard while it's launched (see `launch`). Like muting, this can be set before the targets play.
+    */
+    pub fn set_clips(&self, clips: Vec<(String, String)>) {
+        self.command(Command::SetClips { clips });
+    }
+
+    /**
+        Plays a clip on its track from the next bar on, instead of what the track played before, or stops the track (without a clip). What the launcher does is in the `TransportState`.
+    */
+    pub fn launch(&self, track: impl Into<String>, clip: Option<String>) {
+        self.command(Command::Launch {
+            track: track.into(),
+            clip,
+        });
+    }
+
+    #[allow(unused)]
+    pub fn stop(&self, target: impl Into<String>) {
+        self.command(Command::Stop {
+            target: target.into(),
+        });
+    }
+
+    /**
+        Fades out everything that's playing over the given time, and then stops it. What's played after this isn't affected.
+    */
+    pub fn hush(&self, fade: Duration) {
+        self.command(Command::Hush {
+            // (at least one sample, so it doesn't divide by zero)
+            samples: ((fade.as_secs_f64() * SAMPLE_RATE as f64) as usize).max(1),
+        });
+    }
+
+    /**
+        Stops everything right away, also notes that were still scheduled. For when hushing isn't fast enough.
+    */
+    pub fn panic(&self) {
+        self.command(Command::Panic);
+    }
+
+    /**
+        Plays MIDI notes in another tuning (see `Tuning`), from the next note on
+    */
+    pub fn set_tuning(&self, tuning: Tuning) {
+        self.command(Command::SetTuning {
+            tuning: Box::new(tuning),
+        });
+    }
+
+    /**
+        Starts a timer (see `Timing`), which fires sample-accurately on the transport, for `fired_timers` to tell. Setting one that's running already with the same timing leaves it be, so it keeps its count, and otherwise it starts over.
+    */
+    pub fn set_timer(&self, id: impl Into<String>, timing: Timing) {
+        self.command(Command::SetTimer {
+            id: id.into(),
+            timing,
+        });
+    }
+
+    /**
+        Like `set_timer`, except that a timer that's running already keeps where it is: how far along it is to firing stays the same part of the way (like when the tempo changes, and its seconds are another number of beats), and one that fired already doesn't fire again
+    */
+    pub fn retime_timer(&self, id: impl Into<String>, timing: Timing) {
+        self.command(Command::RetimeTimer {
+            id: id.into(),
+            timing,
+        });
+    }
+
+    pub fn clear_timer(&self, id: impl Into<String>) {
+        self.command(Command::ClearTimer { id: id.into() });
+    }
+
+    /**
+        The timers that fired since the last time this was asked, in order
+    */
+    pub fn fired_timers(&self) -> Vec<Fired> {
+        realtime::lock(&self.fired)
+            .map(|mut fired| std::mem::take(&mut *fired))
+            .unwrap_or_default()
+    }
+
+    /**
+        Which targets are muted and soloed, replacing what was set before. This doesn't depend on what's playing, so it can be set before the targets are.
+    */
+    pub fn set_mute_solo(&self, muted: Vec<String>, soloed: Vec<String>) {
+        self.command(Command::MuteSolo { muted, soloed });
+    }
+
+    /**
+        Where a target is heard, of the output channels (like `play pan(pad, -.5)` or `play channel(click, 3)` in the code). Like muting, this can be set before the target plays, and it stays until it's placed somewhere else.
+    */
+    pub fn place(&self, target: impl Into<String>, placement: Placement) {
+        self.command(Command::Place {
+            target: target.into(),
+            placement,
+        });
+    }
+
+    /**
+        Feeds a note into the `midi_in` source (`midi.pitch`, `midi.gate` etc.). It's timestamped now, and played a fixed latency later, so that the rhythm it was played in is kept.
+    */
+    pub fn midi(&self, event: MidiEvent) {
+        self.command(Command::Midi {
+            event,
+            at: Instant::now(),
+        });
+    }
+
+    /**
+        Starts streaming the samples of a play target (which doesn't have to be playing yet) into a new tap
+    */
+    pub fn tap(&self, target: impl Into<String>) -> Tap {
+        let tap = Tap::new();
+
+        self.command(Command::Tap {
+            target: target.into(),
+            tap: tap.clone(),
+        });
+
+        tap
+    }
+
+    /**
+        A channel of the input device (counting from 1), to play like any other node. It's silent when there's no input device, or no such channel.
+    */
+    pub fn input(&self, channel: usize) -> Input {
+        Input::new(self.input.clone(), channel)
+    }
+
+    /**
+        Records `duration` of a channel of the input device (counting from 1), and then loops it, like a sample
+    */
+    pub fn record_buffer(&self, channel: usize, duration: Duration) -> Recorder {
+        Recorder::new(self.input(channel), duration)
+    }
+
+    /**
+        What's sent to the bus with this name (this sample), to play or process like any other node. A bus that nothing is sent to is silent.
+    */
+    pub fn bus(&self, name: &str) -> BusReturn {
+        BusReturn::new(self.buses.get(name))
+    }
+
+    /**
+        Sends the node to the bus with this name (at a gain, as an amplitude), instead of playing it. Whatever reads the bus is rendered after it, so it's heard right away.
+    */
+    pub fn send(&self, node: Box<dyn AudioNode + Send>, name: &str, gain: f32) -> BusSend {
+        BusSend::new(node, self.buses.get(name), gain)
+    }
+
+    /**
+        The most recent peak/RMS level of every play target
+    */
+    pub fn levels(&self) -> HashMap<String, Level> {
+        realtime::lock(&self.levels)
+            .map(|levels| levels.clone())
+            .unwrap_or_default()
+    }
+
+    /**
+        What's currently being throttled, per declaration name
+    */
+    pub fn runaways(&self) -> HashMap<String, Runaway> {
+        realtime::lock(&self.runaways)
+            .map(|runaways| runaways.clone())
+            .unwrap_or_default()
+    }
+
+    /**
+        Where the transport is, and what's still waiting for the next boundary
+    */
+    pub fn transport(&self) -> TransportState {
+        realtime::lock(&self.transport)
+            .map(|transport| transport.clone())
+            .unwrap_or_default()
+    }
+
+    /**
+        The most recent level of the master bus, and whether it clipped
+    */
+    pub fn master_level(&self) -> MasterLevel {
+        realtime::lock(&self.master_level)
+            .map(|level| *level)
+            .unwrap_or_default()
+    }
+
+    /**
+        The devices it's playing to and recording from now
+    */
+    pub fn devices(&self) -> Devices {
+        realtime::lock(&self.devices)
+            .map(|devices| devices.clone())
+            .unwrap_or_default()
+    }
+
+    /**
+        How much of the time it has the output callback needs (see `CallbackLoad`), where 1.0 means dropouts
+    */
+    pub fn callback_load(&self) -> f32 {
+        self.load.get()
+    }
+
+    /**
+        What every target costs, how many dropouts there were, and whether the nodes are economizing
+    */
+    pub fn costs(&self) -> Costs {
+        let costs = realtime::lock(&self.costs)
+            .map(|costs| costs.clone())
+            .unwrap_or_default();
+
+        Costs {
+            overruns: self.load.overruns(),
+            ..costs
+        }
+    }
+
+    /**
+        Whether the nodes may economize (see `AudioNode::economize`, the reverb gets less dense) while the engine can't keep up, instead of it glitching. (They don't, by default.)
+    */
+    pub fn set_economize(&self, allowed: bool) {
+        self.command(Command::Economize { allowed });
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl EngineHandle {
+    /**
+        An installed CLAP plugin (by name, or id) processing the node, like `plugin("TAL Reverb", pad)` in the language. Making it loads and activates the plugin, which can take a while, so it's best done before it's played. Where (`key`, like `program.play[0]`) the same plugin is playing already, that one's played on instead.
+    */
+    pub fn plugin(
+        &self,
+        key: Option<&str>,
+        name: &str,
+        node: Box<dyn AudioNode + Send>,
+    ) -> Result<Plugin, String> {
+        Plugin::new(key, name, node, &self.plugins)
+    }
+
+    /**
+        The names of the plugins that are playing (or were made to), for `show_plugin_editor`
+    */
+    pub fn running_plugins(&self) -> Vec<String> {
+        self.plugins.names()
+    }
+
+    /**
+        Opens the editor of one of the `running_plugins` in a window of its own. (Only from the main thread, where plugins want everything that has to do with their UI.)
+    */
+    pub fn show_plugin_editor(&self, i: usize) -> Result<(), String> {
+        self.plugins.show_editor(i)
+    }
+}
+
+// (there are no plugins in the browser)
+#[cfg(target_arch = "wasm32")]
+impl EngineHandle {
+    pub fn running_plugins(&self) -> Vec<String> {
+        vec![]
+    }
+
+    pub fn show_plugin_editor(&self, _i: usize) -> Result<(), String> {
+        Err("there are no plugins in the browser".into())
+    }
+}
+
+/**
+    The running audio engine, playing to an output device (the default one, unless it's told otherwise). Dropping it stops the audio.
+
+    The devices can be switched while it's playing (when plugging in an audio interface right before a set, say), and everything that's playing just carries on, on the new device.
+*/
+pub struct Engine {
+    // (shared with the output stream's callback, so that it outlives the stream when switching devices)
+    processor: Arc<Mutex<Processor>>,
+    stream: Option<cpal::Stream>,
+    // (there might not be an input device, which is fine, inputs are just silent then)
+    input_stream: Option<cpal::Stream>,
+    handle: EngineHandle,
+}
+
+impl Engine {
+    /**
+        Starts playing on the given devices, or on the default ones when those don't work out (which `EngineHandle::devices` tells about). It only fails when there's no way to play anything at all.
+    */
+    pub fn start(settings: &DeviceSettings) -> Result<Self, String> {
+        let (commands, inbox) = queues();
+        let levels = Levels::default();
+        let master_level = SharedMasterLevel::default();
+        let runaways = Runaways::default();
+        let transport = SharedTransport::default();
+
+        let costs = SharedCosts::default();
+        let fired = SharedFired::default();
+
+        let processor = Processor::new(
+            inbox,
+            levels.clone(),
+            master_level.clone(),
+            runaways.clone(),
+            transport.clone(),
+            costs.clone(),
+            fired.clone(),
+        );
+
+        let mut engine = Self {
+            processor: Arc::new(Mutex::new(processor)),
+            stream: None,
+            input_stream: None,
+            handle: EngineHandle {
+                commands: Arc::new(Mutex::new(commands)),
+                levels,
+                master_level,
+                runaways,
+                transport,
+                input: LiveInput::new(),
+                buses: Buses::default(),
+                devices: SharedDevices::default(),
+                load: CallbackLoad::default(),
+                costs,
+                fired,
+                #[cfg(not(target_arch = "wasm32"))]
+                plugins: Plugins::default(),
+            },
+        };
+
+        match engine.switch_devices(settings) {
+            Err(e) if engine.stream.is_none() => Err(e),
+            _ => Ok(engine),
+        }
+    }
+
+    pub fn handle(&self) -> EngineHandle {
+        self.handle.clone()
+    }
+
+    /**
+        Plays to (and records from) other devices from now on, without interrupting what's playing for longer than it takes to open them. When they don't work out, it goes back to the default ones, and the error is returned (and kept, for `EngineHandle::devices`).
+    */
+    #[tracing::instrument(skip_all)]
+    pub fn switch_devices(&mut self, settings: &DeviceSettings) -> Result<(), String> {
+        let result = self.open_devices(settings);
+
+        let devices = match &result {
+            Ok(devices) => {
+                tracing::info!(
+                    "playing on {} ({}Hz)",
+                    devices.output.as_deref().unwrap_or("the default output"),
+                    devices.sample_rate
+                );
+                devices.clone()
+            }
+            Err(e) => {
+                tracing::warn!("Could not open the audio devices: {}", e);
+                let fallback = (*settings != DeviceSettings::default())
+                    .then(|| self.open_devices(&DeviceSettings::default()).ok())
+                    .flatten();
+                Devices {
+                    error: Some(e.clone()),
+                    ..fallback.unwrap_or_default()
+                }
+            }
+        };
+
+        if let Ok(mut shared) = self.handle.devices.lock() {
+            *shared = devices;
+        }
+
+        result.map(|_| ())
+    }
+
+    fn open_devices(&mut self, settings: &DeviceSettings) -> Result<Devices, String> {
+        // (the old streams stop first, so that the processor is never played by two at once)
+        self.stream = None;
+        self.input_stream = None;
+
+        let (stream, output, sample_rate, channels) =
+            start_output(self.processor.clone(), settings, self.handle.load.clone())?;
+        self.stream = Some(stream);
+
+        let input = match start_input(self.handle.input.clone(), settings.input.as_deref()) {
+            Ok((stream, input)) => {
+                self.input_stream = Some(stream);
+                Some(input)
+            }
+            // (only an input that was asked for by name is missed)
+            Err(e) if settings.input.is_some() => return Err(e),
+            Err(_) => None,
+        };
+
+        Ok(Devices {
+            output: Some(output),
+            input,
+            sample_rate,
+            buffer_size: settings.buffer_size,
+            channels,
+            error: None,
+        })
+    }
+}
+
+#[test]
+fn test_replacing_crossfades() {
+    use crate::node::Sampler;
+
+    let (mut sender, receiver) = queues();
+    let mut processor = Processor::new(
+        receiver,
+        Levels::default(),
+        SharedMasterLevel::default(),
+        Runaways::default(),
+        SharedTransport::default(),
+        SharedCosts::default(),
+        SharedFired::default(),
+    );
+
+    let constant = |value: f32| Box::new(Sampler::new(vec![value; 10_000], SAMPLE_RATE));
+
+    let _ = sender.send(Command::Play {
+        target: "kick".into(),
+        node: constant(0.5),
+    });
+    let before = processor.next_sample();
+    assert!(before > 0.1);
+
+    let _ = sender.send(Command::Play {
+        target: "kick".into(),
+        node: constant(0.0),
+    });
+    // (no jump right away)
+    let right_after = processor.next_sample();
+    assert!((right_after - before).abs() < 0.01);
+
+    for _ in 0..CROSSFADE_SAMPLES {
+        processor.next_sample();
+    }
+    assert_eq!(processor.next_sample(), 0.0);
+}
+
+#[test]
+fn test_hush_and_panic() {
+    use crate::node::Sampler;
+
+    let (mut sender, receiver) = queues();
+    let mut processor = Processor::new(
+        receiver,
+        Levels::default(),
+        SharedMasterLevel::default(),
+        Runaways::default(),
+        SharedTransport::default(),
+        SharedCosts::default(),
+        SharedFired::default(),
+    );
+
+    let constant = |value: f32| Box::new(Sampler::new(vec![value; 10_000], SAMPLE_RATE));
+
+    let _ = sender.send(Command::Play {
+        target: "kick".into(),
+        node: constant(0.5),
+    });
+    let before = processor.next_sample();
+
+    let _ = sender.send(Command::Hush { samples: 100 });
+    let halfway = (0..50).map(|_| processor.next_sample()).last().unwrap();
+    assert!(halfway < before && halfway > 0.0);
+
+    for _ in 0..50 {
+        processor.next_sample();
+    }
+    assert_eq!(processor.next_sample(), 0.0);
+    assert!(processor.targets.is_empty());
+
+    // (and then it's possible to play again)
+    let _ = sender.send(Command::Play {
+        target: "kick".into(),
+        node: constant(0.5),
+    });
+    assert!(processor.next_sample() > 0.1);
+
+    let _ = sender.send(Command::Panic);
+    assert_eq!(processor.next_sample(), 0.0);
+    assert!(processor.targets.is_empty());
+}
+
+#[test]
+fn test_mute_and_solo() {
+    use crate::node::Sampler;
+
+    let (mut sender, receiver) = queues();
+    let mut processor = Processor::new(
+        receiver,
+        Levels::default(),
+        SharedMasterLevel::default(),
+        Runaways::default(),
+        SharedTransport::default(),
+        SharedCosts::default(),
+        SharedFired::default(),
+    );
+
+    let constant = |value: f32| Box::new(Sampler::new(vec![value; 10_000], SAMPLE_RATE));
+
+    let _ = sender.send(Command::Play {
+        target: "kick".into(),
+        node: constant(0.25),
+    });
+    let _ = sender.send(Command::Play {
+        target: "bass".into(),
+        node: constant(0.5),
+    });
+    let both = processor.next_sample();
+
+    let _ = sender.send(Command::MuteSolo {
+        muted: vec!["bass".into()],
+        soloed: vec![],
+    });
+    let kick = processor.next_sample();
+    assert!(kick > 0.0 && kick < both);
+
+    let _ = sender.send(Command::MuteSolo {
+        muted: vec![],
+        soloed: vec!["bass".into()],
+    });
+    let bass = processor.next_sample();
+    assert!(bass > kick && bass < both);
+
+    let _ = sender.send(Command::MuteSolo {
+        muted: vec![],
+        soloed: vec![],
+    });
+    assert_eq!(processor.next_sample(), both);
+}
+
+#[test]
+fn test_placing() {
+    use crate::node::Sampler;
+
+    let (mut sender, receiver) = queues();
+    let mut processor = Processor::new(
+        receiver,
+        Levels::default(),
+        SharedMasterLevel::default(),
+        Runaways::default(),
+        SharedTransport::default(),
+        SharedCosts::default(),
+        SharedFired::default(),
+    );
+
+    let constant = |value: f32| Box::new(Sampler::new(vec![value; 10_000], SAMPLE_RATE));
+
+    // (placed before it plays)
+    let _ = sender.send(Command::Place {
+        target: "click".into(),
+        placement: Placement::Channel(3),
+    });
+    let _ = sender.send(Command::Play {
+        target: "click".into(),
+        node: constant(0.25),
+    });
+    let _ = sender.send(Command::Play {
+        target: "pad".into(),
+        node: constant(0.5),
+    });
+
+    let mut frame = [0.0; 4];
+    processor.next_frame(&mut frame);
+    assert!(frame[0] > 0.0 && frame[1] == frame[0] && frame[3] == frame[0]);
+    assert!(frame[2] > frame[0]);
+
+    let _ = sender.send(Command::Place {
+        target: "pad".into(),
+        placement: Placement::Pan(-1.0),
+    });
+    processor.next_frame(&mut frame);
+    assert!(frame[0] > 0.0 && frame[2] > 0.0);
+    assert_eq!((frame[1], frame[3]), (0.0, 0.0));
+
+    // (and in mono, everything's heard)
+    assert!(processor.next_sample() > frame[0]);
+}
+
+#[test]
+fn test_scheduled_changes_land_on_the_bar() {
+    use crate::node::Sampler;
+
+    let (mut sender, receiver) = queues();
+    let transport = SharedTransport::default();
+    let mut processor = Processor::new(
+        receiver,
+        Levels::default(),
+        SharedMasterLevel::default(),
+        Runaways::default(),
+        transport.clone(),
+        SharedCosts::default(),
+        SharedFired::default(),
+    );
+
+    let constant = |value: f32| Box::new(Sampler::new(vec![value; 10_000], SAMPLE_RATE));
+
+    // (a beat every 100 samples, so a bar is 400)
+    let _ = sender.send(Command::SetTempo {
+        tempo: 60.0 * SAMPLE_RATE as f64 / 100.0,
+    });
+    let _ = sender.send(Command::SetQuantize {
+        quantize: Quantize::Bar,
+    });
+    processor.next_sample();
+
+    let _ = sender.send(Command::Schedule {
+        target: "kick".into(),
+        node: constant(0.5),
+    });
+    // (and the numbers that changed with it)
+    let _ = sender.send(Command::ScheduleEase {
+        name: "kick.f".into(),
+        value: 800.0,
+        ease: 10,
+    });
+    let silent = (0..390)
+        .map(|_| processor.next_sample())
+        .collect::<Vec<_>>();
+    assert!(silent.iter().all(|&sample| sample == 0.0));
+    assert_eq!(processor.applied.get("kick.f"), None);
+
+    processor.start_block();
+    assert_eq!(transport.lock().unwrap().pending, vec!["kick".to_string()]);
+
+    let landed = (0..20).position(|_| processor.next_sample() > 0.0);
+    assert!(matches!(landed, Some(8..=10)));
+    for _ in 0..20 {
+        processor.next_sample();
+    }
+    assert_eq!(processor.applied.get("kick.f"), Some(&800.0));
+
+    processor.start_block();
+    assert!(transport.lock().unwrap().pending.is_empty());
+}
+
+#[test]
+fn test_buses_are_read_after_what_is_sent() {
+    use crate::node::Sampler;
+
+    let processor = || {
+        let (sender, receiver) = queues();
+        let processor = Processor::new(
+            receiver,
+            Levels::default(),
+            SharedMasterLevel::default(),
+            Runaways::default(),
+            SharedTransport::default(),
+            SharedCosts::default(),
+            SharedFired::default(),
+        );
+        (sender, processor)
+    };
+
+    let constant = |value: f32| Box::new(Sampler::new(vec![value; 10_000], SAMPLE_RATE));
+
+    let (mut sender, mut direct) = processor();
+    let _ = sender.send(Command::Play {
+        target: "kick".into(),
+        node: constant(0.5),
+    });
+
+    // (the bus is played before the kick is sent to it, and still hears it the same sample)
+    let buses = Buses::default();
+    let (mut sender, mut bussed) = processor();
+    let _ = sender.send(Command::Play {
+        target: "drums".into(),
+        node: Box::new(BusReturn::new(buses.get("drums"))),
+    });
+    let _ = sender.send(Command::Play {
+        target: "kick".into(),
+        node: Box::new(BusSend::new(constant(0.5), buses.get("drums"), 1.0)),
+    });
+
+    for _ in 0..10 {
+        assert_eq!(bussed.next_sample(), direct.next_sample());
+    }
+    assert_eq!(bussed.targets[0].name, "kick");
+}
+
+#[test]
+fn test_economizing() {
+    let (mut sender, receiver) = queues();
+    let mut processor = Processor::new(
+        receiver,
+        Levels::default(),
+        SharedMasterLevel::default(),
+        Runaways::default(),
+        SharedTransport::default(),
+        SharedCosts::default(),
+        SharedFired::default(),
+    );
+
+    // (not unless it's allowed)
+    processor.set_load(0.95);
+    assert!(!processor.economizing);
+
+    let _ = sender.send(Command::Economize { allowed: true });
+    processor.next_sample();
+    processor.set_load(0.95);
+    assert!(processor.economizing);
+
+    // (and it takes a clear drop in load to stop again)
+    processor.set_load(0.7);
+    assert!(processor.economizing);
+    processor.set_load(0.5);
+    assert!(!processor.economizing);
+}
+
+#[test]
+fn test_morphing() {
+    let (mut sender, receiver) = queues();
+    let mut processor = Processor::new(
+        receiver,
+        Levels::default(),
+        SharedMasterLevel::default(),
+        Runaways::default(),
+        SharedTransport::default(),
+        SharedCosts::default(),
+        SharedFired::default(),
+    );
+
+    let _ = sender.send(Command::SetMorph {
+        params: vec![("fx.f".into(), 800.0, 1600.0), ("fx.q".into(), 2.0, 2.0)],
+    });
+    // (it's applied where it is right away, at the first snapshot)
+    processor.next_sample();
+    assert_eq!(processor.applied.get("fx.f"), Some(&800.0));
+    assert_eq!(processor.applied.get("fx.q"), Some(&2.0));
+
+    // (and glides over as long as it's eased)
+    let _ = sender.set_param(MORPH.into(), 1.0, Some(100));
+    for _ in 0..51 {
+        processor.next_sample();
+    }
+    let halfway = processor.applied["fx.f"];
+    assert!((halfway - 1200.0).abs() < 20.0, "{}", halfway);
+    for _ in 0..100 {
+        processor.next_sample();
+    }
+    assert_eq!(processor.applied.get("fx.f"), Some(&1600.0));
+
+    // (other snapshots take over where it is)
+    let _ = sender.send(Command::SetMorph {
+        params: vec![("fx.f".into(), 100.0, 200.0)],
+    });
+    processor.next_sample();
+    assert_eq!(processor.applied.get("fx.f"), Some(&200.0));
+    // (the snapshots that were replaced, which are freed here)
+    assert_eq!(sender.take_out_garbage(), 1);
+}
+
+#[test]
+fn test_launching_clips() {
+    use crate::node::Sampler;
+
+    let (mut sender, receiver) = queues();
+    let transport = SharedTransport::default();
+    let mut processor = Processor::new(
+        receiver,
+        Levels::default(),
+        SharedMasterLevel::default(),
+        Runaways::default(),
+        transport.clone(),
+        SharedCosts::default(),
+        SharedFired::default(),
+    );
+
+    let constant = |value: f32| Box::new(Sampler::new(vec![value; 10_000], SAMPLE_RATE));
+
+    // (a beat every 100 samples, so a bar is 400, and it's not quantized otherwise)
+    let _ = sender.send(Command::SetTempo {
+        tempo: 60.0 * SAMPLE_RATE as f64 / 100.0,
+    });
+    let _ = sender.send(Command::SetClips {
+        clips: vec![
+            ("beat".into(), "drums".into()),
+            ("fill".into(), "drums".into()),
+        ],
+    });
+    let _ = sender.send(Command::Play {
+        target: "beat".into(),
+        node: constant(0.25),
+    });
+    let _ = sender.send(Command::Play {
+        target: "fill".into(),
+        node: constant(0.5),
+    });
+    let _ = sender.send(Command::Play {
+        target: "pad".into(),
+        node: constant(0.125),
+    });
+    let pad = processor.next_sample();
+    assert!(pad > 0.0);
+
+    // (clips only come in on the next bar)
+    let _ = sender.send(Command::Launch {
+        track: "drums".into(),
+        clip: Some("beat".into()),
+    });
+    let waiting = (0..390)
+        .map(|_| processor.next_sample())
+        .collect::<Vec<_>>();
+    assert!(waiting.iter().all(|&sample| sample == pad));
+
+    processor.start_block();
+    assert_eq!(
+        transport.lock().unwrap().launcher.queued,
+        vec![("drums".to_string(), Some("beat".to_string()))]
+    );
+
+    let landed = (0..20).position(|_| processor.next_sample() > pad);
+    assert!(matches!(landed, Some(8..=10)));
+    let beat = processor.next_sample();
+
+    processor.start_block();
+    let launcher = transport.lock().unwrap().launcher.clone();
+    assert!(launcher.queued.is_empty());
+    assert_eq!(
+        launcher.playing,
+        vec![("drums".to_string(), "beat".to_string())]
+    );
+
+    // (a track plays one clip at a time)
+    let _ = sender.send(Command::Launch {
+        track: "drums".into(),
+        clip: Some("fill".into()),
+    });
+    for _ in 0..400 {
+        processor.next_sample();
+    }
+    let fill = processor.next_sample();
+    assert!(fill > beat);
+
+    // (and stops at the next bar too)
+    let _ = sender.send(Command::Launch {
+        track: "drums".into(),
+        clip: None,
+    });
+    for _ in 0..400 {
+        processor.next_sample();
+    }
+    assert_eq!(processor.next_sample(), pad);
+
+    // (what's not a clip anymore is heard, like anything else)
+    let _ = sender.send(Command::SetClips { clips: vec![] });
+    assert!(processor.next_sample() > fill);
+}
+
+#[test]
+fn test_rendering_is_realtime_safe() {
+    use crate::{
+        node::{Osc, Sampler},
+        voices::Poly,
+    };
+
+    let (mut sender, receiver) = queues();
+    let mut processor = Processor::new(
+        receiver,
+        Levels::default(),
+        SharedMasterLevel::default(),
+        Runaways::default(),
+        SharedTransport::default(),
+        SharedCosts::default(),
+        SharedFired::default(),
+    );
+
+    let constant = |value: f32| Box::new(Sampler::new(vec![value; 10_000], SAMPLE_RATE));
+    // (as if it were the audio thread, which asserts that it doesn't allocate or free, other than what's permitted)
+    let render = |processor: &mut Processor, samples: usize| {
+        let _realtime = realtime::realtime();
+        for _ in 0..samples {
+            processor.next_sample();
+        }
+    };
+
+    let _ = sender.send(Command::Play {
+        target: "kick".into(),
+        node: constant(0.25),
+    });
+    let _ = sender.send(Command::Play {
+        target: "keys".into(),
+        node: Box::new(Poly::new(|| Box::new(Osc::default()))),
+    });
+    let _ = sender.set_param("kick.volume".into(), 0.5, Some(100));
+    let _ = sender.send(Command::Midi {
+        event: MidiEvent::NoteOn {
+            note: 60,
+            velocity: 1.0,
+        },
+        at: Instant::now(),
+    });
+    render(&mut processor, 5_000);
+
+    // (replacing crossfades, after which what was replaced goes in the garbage)
+    let _ = sender.send(Command::Play {
+        target: "kick".into(),
+        node: constant(0.5),
+    });
+    let _ = sender.set_param("kick.volume".into(), 0.25, None);
+    let _ = sender.send(Command::Midi {
+        event: MidiEvent::NoteOff { note: 60 },
+        at: Instant::now(),
+    });
+    render(&mut processor, CROSSFADE_SAMPLES + 5_000);
+    // (the old kick and its name, and the parameter's name, twice: from the change, and when it settled)
+    assert_eq!(sender.take_out_garbage(), 4);
+
+    // (and so does whatever else comes with a command)
+    let _ = sender.send(Command::MuteSolo {
+        muted: vec!["kick".into()],
+        soloed: vec![],
+    });
+    let _ = sender.send(Command::SetTuning {
+        tuning: Box::new(Tuning::default()),
+    });
+    let _ = sender.send(Command::SetTimer {
+        id: "every".into(),
+        timing: Timing::Every(1.0),
+    });
+    let _ = sender.send(Command::ClearTimer { id: "every".into() });
+    render(&mut processor, 10);
+    // (the empty lists it had before, the tuning, and the timer's name)
+    assert_eq!(sender.take_out_garbage(), 4);
+
+    let _ = sender.send(Command::Stop {
+        target: "keys".into(),
+    });
+    render(&mut processor, 10);
+    // (the target, and its name, both the one of its meter and the one it was stopped by)
+    assert_eq!(sender.take_out_garbage(), 3);
+}
@@ -0,0 +1,340 @@
+use std::io::{self, Write};
+
+/// How many samples go in a frame (like the reference encoder does at 44.1kHz and up)
+const FRAME_SIZE: usize = 4096;
+/// (the highest order of FLAC's fixed predictors)
+const MAX_ORDER: usize = 4;
+/// (15 means the residual isn't Rice coded, which never wins over verbatim here)
+const MAX_RICE_PARAMETER: u32 = 14;
+
+/**
+    Writes samples (already quantized to `bits` bits) as a mono FLAC file. Every frame is predicted with whichever of the fixed predictors leaves the least to code, or else stored verbatim when that's smaller, which is most of what FLAC's compression is, without its (slower) LPC.
+*/
+pub(crate) fn write_flac(
+    writer: &mut impl Write,
+    samples: &[i32],
+    sample_rate: u32,
+    bits: u32,
+) -> io::Result<()> {
+    let mut header = Bits::default();
+    header.write(u32::from_be_bytes(*b"fLaC") as u64, 32);
+
+    // (STREAMINFO, the last metadata block)
+    header.write(1, 1);
+    header.write(0, 7);
+    header.write(34, 24);
+    let frame_size = FRAME_SIZE.min(samples.len()).max(16) as u64;
+    header.write(frame_size, 16);
+    header.write(frame_size, 16);
+    // (unknown frame sizes)
+    header.write(0, 24);
+    header.write(0, 24);
+    header.write(sample_rate as u64, 20);
+    header.write(0, 3);
+    header.write(bits as u64 - 1, 5);
+    header.write(samples.len() as u64, 36);
+    // (no MD5 of the audio, which is allowed)
+    header.write(0, 64);
+    header.write(0, 64);
+    writer.write_all(&header.bytes)?;
+
+    for (i, chunk) in samples.chunks(FRAME_SIZE).enumerate() {
+        writer.write_all(&frame(i as u64, chunk, bits))?;
+    }
+
+    Ok(())
+}
+
+fn frame(number: u64, samples: &[i32], bits: u32) -> Vec<u8> {
+    let mut frame = Bits::default();
+
+    // (sync code, fixed frame sizes, the size at the end of the header, the sample rate from STREAMINFO, mono, and how many bits)
+    frame.write(0b11111111111110, 14);
+    frame.write(0, 1);
+    frame.write(0, 1);
+    frame.write(0b0111, 4);
+    frame.write(0b0000, 4);
+    frame.write(0b0000, 4);
+    frame.write(sample_size_code(bits), 3);
+    frame.write(0, 1);
+    frame.write_utf8(number);
+    frame.write(samples.len() as u64 - 1, 16);
+    let crc = crc8(&frame.bytes);
+    frame.write(crc as u64, 8);
+
+    subframe(&mut frame, samples, bits);
+
+    frame.align();
+    let crc = crc16(&frame.bytes);
+    frame.write(crc as u64, 16);
+    frame.bytes
+}
+
+fn sample_size_code(bits: u32) -> u64 {
+    match bits {
+        8 => 0b001,
+        12 => 0b010,
+        16 => 0b100,
+        20 => 0b101,
+        24 => 0b110,
+        _ => 0b000,
+    }
+}
+
+fn subframe(frame: &mut Bits, samples: &[i32], bits: u32) {
+    let verbatim = samples.len() as u64 * bits as u64;
+
+    // (every order's residual is the difference of the one before, so they're worked out in place)
+    let mut residual = samples.iter().map(|&x| x as i64).collect::<Vec<_>>();
+    let mut best: Option<(u64, usize, u32, Vec<i64>)> = None;
+
+    for order in 0..=MAX_ORDER.min(samples.len() - 1) {
+        if order > 0 {
+            for i in (order..residual.len()).rev() {
+                residual[i] -= residual[i - 1];
+            }
+        }
+
+        let parameter = rice_parameter(&residual[order..]);
+        let size = order as u64 * bits as u64 + 10 + rice_size(&residual[order..], parameter);
+
+        if best.as_ref().is_none_or(|(best, ..)| size < *best) {
+            best = Some((size, order, parameter, residual[order..].to_vec()));
+        }
+    }
+
+    match best {
+        Some((size, order, parameter, residual)) if size < verbatim => {
+            frame.write(0, 1);
+            frame.write(0b001000 | order as u64, 6);
+            frame.write(0, 1);
+            for &sample in &samples[..order] {
+                frame.write_signed(sample as i64, bits);
+            }
+
+            // (Rice coded with a 4-bit parameter, in a single partition)
+            frame.write(0b00, 2);
+            frame.write(0, 4);
+            frame.write(parameter as u64, 4);
+            for &r in &residual {
+                let u = zigzag(r);
+                frame.write_unary(u >> parameter);
+                frame.write(u & ((1 << parameter) - 1), parameter);
+            }
+        }
+        _ => {
+            frame.write(0, 1);
+            frame.write(0b000001, 6);
+            frame.write(0, 1);
+            for &sample in samples {
+                frame.write_signed(sample as i64, bits);
+            }
+        }
+    }
+}
+
+/// (where the mean folds in, which is close enough to the best one)
+fn rice_parameter(residual: &[i64]) -> u32 {
+    let sum = residual.iter().map(|&r| zigzag(r)).sum::<u64>();
+    let mean = sum / residual.len().max(1) as u64;
+
+    (64 - mean.leading_zeros()).min(MAX_RICE_PARAMETER)
+}
+
+fn rice_size(residual: &[i64], parameter: u32) -> u64 {
+    residual
+        .iter()
+        .map(|&r| (zigzag(r) >> parameter) + 1 + parameter as u64)
+        .sum()
+}
+
+/// (0, -1, 1, -2, 2, … as 0, 1, 2, 3, 4, …)
+fn zigzag(r: i64) -> u64 {
+    ((r << 1) ^ (r >> 63)) as u64
+}
+
+fn crc8(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0, |mut crc, &byte| {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+fn crc16(bytes: &[u8]) -> u16 {
+    bytes.iter().fold(0, |mut crc, &byte| {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x8005
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+/**
+    Bits, written most significant first
+*/
+#[derive(Default)]
+struct Bits {
+    bytes: Vec<u8>,
+    current: u8,
+    filled: u32,
+}
+
+impl Bits {
+    fn write(&mut self, value: u64, bits: u32) {
+        for i in (0..bits).rev() {
+            self.current = (self.current << 1) | ((value >> i) & 1) as u8;
+            self.filled += 1;
+            if self.filled == 8 {
+                self.bytes.push(self.current);
+                self.current = 0;
+                self.filled = 0;
+            }
+        }
+    }
+
+    /// (two's complement, in so many bits)
+    fn write_signed(&mut self, value: i64, bits: u32) {
+        self.write(value as u64 & ((1 << bits) - 1), bits);
+    }
+
+    fn write_unary(&mut self, n: u64) {
+        for _ in 0..n {
+            self.write(0, 1);
+        }
+        self.write(1, 1);
+    }
+
+    /// (how FLAC numbers its frames: like UTF-8, but up to 36 bits)
+    fn write_utf8(&mut self, n: u64) {
+        if n < 0x80 {
+            self.write(n, 8);
+            return;
+        }
+
+        let mut len = 2;
+        while n >= 1 << (5 * len + 1) {
+            len += 1;
+        }
+
+        self.write((0xff << (8 - len)) & 0xff | (n >> (6 * (len - 1))), 8);
+        for i in (0..len - 1).rev() {
+            self.write(0x80 | ((n >> (6 * i)) & 0x3f), 8);
+        }
+    }
+
+    fn align(&mut self) {
+        while self.filled != 0 {
+            self.write(0, 1);
+        }
+    }
+}
+
+#[test]
+fn test_flac() {
+    // (just enough of a decoder for what's written above)
+    fn decode(flac: &[u8]) -> Vec<i32> {
+        struct Reader<'a>(&'a [u8], usize);
+
+        impl Reader<'_> {
+            fn read(&mut self, bits: usize) -> u64 {
+                (0..bits).fold(0, |value, _| {
+                    let bit = (self.0[self.1 / 8] >> (7 - self.1 % 8)) & 1;
+                    self.1 += 1;
+                    (value << 1) | bit as u64
+                })
+            }
+
+            fn read_signed(&mut self, bits: usize) -> i64 {
+                let value = self.read(bits) as i64;
+                (value << (64 - bits)) >> (64 - bits)
+            }
+        }
+
+        assert_eq!(&flac[0..4], b"fLaC");
+        let mut reader = Reader(flac, 8 * 8);
+        reader.read(16 * 2 + 24 * 2 + 20 + 3 + 5);
+        let total = reader.read(36) as usize;
+        reader.read(128);
+
+        let mut samples = vec![];
+        while samples.len() < total {
+            let start = reader.1 / 8;
+            assert_eq!(reader.read(14), 0b11111111111110);
+            reader.read(18);
+            assert!(reader.read(8) < 0x80);
+            let n = reader.read(16) as usize + 1;
+            assert_eq!(reader.read(8) as u8, crc8(&flac[start..reader.1 / 8 - 1]));
+
+            reader.read(1);
+            let kind = reader.read(6);
+            reader.read(1);
+            if kind == 0b000001 {
+                samples.extend((0..n).map(|_| reader.read_signed(24) as i32));
+            } else {
+                let order = (kind & 0b111) as usize;
+                let mut frame = (0..order)
+                    .map(|_| reader.read_signed(24))
+                    .collect::<Vec<_>>();
+                reader.read(6);
+                let parameter = reader.read(4) as usize;
+
+                let coefficients: &[i64] = match order {
+                    0 => &[],
+                    1 => &[1],
+                    2 => &[2, -1],
+                    3 => &[3, -3, 1],
+                    _ => &[4, -6, 4, -1],
+                };
+                for i in order..n {
+                    let mut q = 0;
+                    while reader.read(1) == 0 {
+                        q += 1;
+                    }
+                    let u = (q << parameter) | reader.read(parameter);
+                    let r = (u >> 1) as i64 ^ -((u & 1) as i64);
+                    let predicted = (coefficients.iter().enumerate())
+                        .map(|(j, c)| c * frame[i - 1 - j])
+                        .sum::<i64>();
+                    frame.push(predicted + r);
+                }
+                samples.extend(frame.iter().map(|&x| x as i32));
+            }
+
+            reader.1 = reader.1.next_multiple_of(8);
+            let end = reader.1 / 8;
+            assert_eq!(reader.read(16) as u16, crc16(&flac[start..end]));
+        }
+        samples
+    }
+
+    let max = (1 << 23) - 1;
+    let sine = (0..10_000)
+        .map(|i| ((i as f32 * 0.01).sin() * max as f32) as i32)
+        .collect::<Vec<_>>();
+    // (which doesn't predict, and so is stored as it is)
+    let noise = (0..5000u32)
+        .map(|i| (i.wrapping_mul(2654435761) >> 8) as i32 - (1 << 23))
+        .collect::<Vec<_>>();
+
+    for samples in [sine.clone(), noise, vec![0; 100], vec![max, -max - 1, 0]] {
+        let mut flac = vec![];
+        write_flac(&mut flac, &samples, 48_000, 24).unwrap();
+        assert_eq!(decode(&flac), samples);
+    }
+
+    let mut flac = vec![];
+    write_flac(&mut flac, &sine, 48_000, 24).unwrap();
+    assert!(flac.len() < sine.len() * 3 / 2);
+}
@@ -0,0 +1,200 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use crate::{block::BLOCK_SIZE, node::AudioNode, SAMPLE_RATE};
+
+/// Set this (to anything) to write what's rendered as the new golden files, instead of comparing against them
+const UPDATE: &str = "UPDATE_GOLDEN";
+
+/// How far off a sample may be (-80dB): `sin` and `tan` on `f32` don't round exactly the same on every platform, but nothing that's audible should get through
+const TOLERANCE: f32 = 1e-4;
+
+/**
+    Renders a node, one sample at a time like the audio thread does (but without the device, the master bus or the engine)
+*/
+pub(crate) fn render(node: &mut dyn AudioNode, n: usize) -> Vec<f32> {
+    (0..n)
+        .map(|_| {
+            node.tick();
+            node.get_next_sample()
+        })
+        .collect()
+}
+
+/**
+    Renders a node a block at a time instead, like the processor does, which should sound the same
+*/
+pub(crate) fn render_blocks(node: &mut dyn AudioNode, n: usize) -> Vec<f32> {
+    let mut samples = vec![0.0; n];
+    for block in samples.chunks_mut(BLOCK_SIZE) {
+        node.process(block);
+    }
+    samples
+}
+
+/**
+    Compares what's rendered against `tests/golden/{name}.wav`, to catch anything that makes a node sound different than it did. When it's off, what it sounds like now is written next to it (as `{name}.actual.wav`) to listen to, and when that's how it should sound, run the tests with `UPDATE_GOLDEN=1` and check in the new golden file.
+*/
+pub(crate) fn assert_golden(name: &str, samples: &[f32]) {
+    let path = golden_dir().join(format!("{}.wav", name));
+
+    if std::env::var_os(UPDATE).is_some() {
+        fs::create_dir_all(golden_dir()).unwrap();
+        write_wav(&mut fs::File::create(&path).unwrap(), samples).unwrap();
+        return;
+    }
+
+    let Ok(bytes) = fs::read(&path) else {
+        panic!(
+            "there's no golden `{}` yet (run the tests with {}=1 to write it, and then listen to it)",
+            name, UPDATE
+        );
+    };
+    let expected = read_wav(&bytes).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+
+    if let Some(difference) = difference(samples, &expected) {
+        let actual = golden_dir().join(format!("{}.actual.wav", name));
+        let _ = fs::File::create(&actual).and_then(|mut file| write_wav(&mut file, samples));
+        panic!(
+            "`{}` doesn't sound like it did: {} (it sounds like {} now)",
+            name,
+            difference,
+            actual.display()
+        );
+    }
+}
+
+fn golden_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+/// (where it's off the most, if anywhere)
+fn difference(actual: &[f32], expected: &[f32]) -> Option<String> {
+    if actual.len() != expected.len() {
+        return Some(format!(
+            "it's {} samples long, instead of {}",
+            actual.len(),
+            expected.len()
+        ));
+    }
+
+    let (i, off) = actual
+        .iter()
+        .zip(expected)
+        .map(|(a, b)| {
+            if a.is_nan() || b.is_nan() {
+                f32::INFINITY
+            } else {
+                (a - b).abs()
+            }
+        })
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+
+    (off > TOLERANCE).then(|| format!("sample {} is {}, instead of {}", i, actual[i], expected[i]))
+}
+
+/**
+    Writes a mono, 32 bit float WAV file (not like `Bounce` does, in 24 bits, which clips and rounds away exactly what a regression test should see)
+*/
+fn write_wav(writer: &mut impl Write, samples: &[f32]) -> io::Result<()> {
+    let data_size = samples.len() as u32 * 4;
+
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(36 + data_size).to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+
+    writer.write_all(b"fmt ")?;
+    writer.write_all(&16u32.to_le_bytes())?;
+    // (IEEE float, mono)
+    writer.write_all(&3u16.to_le_bytes())?;
+    writer.write_all(&1u16.to_le_bytes())?;
+    writer.write_all(&SAMPLE_RATE.to_le_bytes())?;
+    writer.write_all(&(SAMPLE_RATE * 4).to_le_bytes())?;
+    writer.write_all(&4u16.to_le_bytes())?;
+    writer.write_all(&32u16.to_le_bytes())?;
+
+    writer.write_all(b"data")?;
+    writer.write_all(&data_size.to_le_bytes())?;
+    for sample in samples {
+        writer.write_all(&sample.to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
+/// (only what `write_wav` writes, but skipping any other chunks, in case an editor added some)
+fn read_wav(bytes: &[u8]) -> Result<Vec<f32>, String> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err("not a WAV file".into());
+    }
+
+    let mut format = None;
+    let mut chunks = &bytes[12..];
+
+    while chunks.len() >= 8 {
+        let id = &chunks[0..4];
+        let size = u32::from_le_bytes(chunks[4..8].try_into().unwrap()) as usize;
+        let body = chunks
+            .get(8..8 + size)
+            .ok_or("a chunk goes past the end of the file")?;
+
+        match id {
+            // (the format, the channels, the sample rate, and the bits per sample)
+            b"fmt " if size >= 16 => {
+                format = Some((
+                    u16::from_le_bytes([body[0], body[1]]),
+                    u16::from_le_bytes([body[2], body[3]]),
+                    u32::from_le_bytes(body[4..8].try_into().unwrap()),
+                    u16::from_le_bytes([body[14], body[15]]),
+                ));
+            }
+            b"data" => {
+                if format != Some((3, 1, SAMPLE_RATE, 32)) {
+                    return Err(format!(
+                        "expected mono, 32 bit float samples, at {}Hz",
+                        SAMPLE_RATE
+                    ));
+                }
+                return Ok(body
+                    .chunks_exact(4)
+                    .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+                    .collect());
+            }
+            _ => {}
+        }
+
+        // (chunks are padded to an even size)
+        chunks = &chunks[(8 + size + size % 2).min(chunks.len())..];
+    }
+
+    Err("there are no samples in it".into())
+}
+
+#[test]
+fn test_golden() {
+    let samples = vec![0.0, 0.5, -1.5, 1e-7];
+
+    let mut wav = vec![];
+    write_wav(&mut wav, &samples).unwrap();
+    assert_eq!(read_wav(&wav), Ok(samples.clone()));
+    assert_eq!(
+        read_wav(&wav[..50]),
+        Err("a chunk goes past the end of the file".into())
+    );
+
+    assert_eq!(difference(&samples, &samples), None);
+    assert_eq!(difference(&samples, &[0.0, 0.50001, -1.5, 0.0]), None);
+    assert_eq!(
+        difference(&samples, &[0.0, 0.5, -1.4, 0.0]),
+        Some("sample 2 is -1.5, instead of -1.4".into())
+    );
+    assert_eq!(
+        difference(&samples, &[0.0]),
+        Some("it's 4 samples long, instead of 1".into())
+    );
+    assert!(difference(&[f32::NAN], &[0.0]).is_some());
+}
@@ -0,0 +1,113 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use crate::{realtime::permit, SAMPLE_RATE};
+
+/// Play targets beyond this many are refused
+pub(crate) const MAX_VOICES: usize = 256;
+/// A signal this loud (about +24 dB) isn't music anymore, it's feedback blowing up
+pub(crate) const RUNAWAY_PEAK: f32 = 16.0;
+/// Events (parameter changes) beyond this many per second, per declaration, are dropped
+pub(crate) const MAX_EVENTS_PER_SECOND: usize = 2000;
+
+/**
+    A pathological situation the engine ran into, and throttled, so that it stays responsive
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Runaway {
+    /// It wasn't started, because `MAX_VOICES` things were playing already
+    TooManyVoices,
+    /// Its output kept growing (like feedback with a gain over 1), so it was muted
+    Feedback { peak: f32 },
+    /// It sent more events than this in a second, so the rest of them were dropped
+    EventFlood { per_second: usize },
+}
+
+impl Runaway {
+    pub fn message(&self, name: &str) -> String {
+        match self {
+            Self::TooManyVoices => format!(
+                "{} wasn't started, because {} things are playing already",
+                name, MAX_VOICES
+            ),
+            Self::Feedback { peak } if peak.is_finite() => format!(
+                "{} was muted, because its output blew up to {:.0} dB (feedback?)",
+                name,
+                20.0 * peak.log10()
+            ),
+            Self::Feedback { .. } => {
+                format!("{} was muted, because its output blew up (feedback?)", name)
+            }
+            Self::EventFlood { per_second } => format!(
+                "{} sends {}+ events per second, the rest are dropped",
+                name, per_second
+            ),
+        }
+    }
+}
+
+/**
+    The current runaways, per declaration name, shared between the audio thread and whoever wants to show them
+*/
+pub(crate) type Runaways = Arc<Mutex<HashMap<String, Runaway>>>;
+
+/**
+    Counts events per declaration, in windows of a second, on the audio thread
+*/
+#[derive(Debug, Default)]
+pub(crate) struct EventRate {
+    window_started: u64,
+    counts: HashMap<String, usize>,
+}
+
+impl EventRate {
+    /**
+        Counts an event from `source`, and returns whether it's still within the limit
+    */
+    pub fn count(&mut self, source: &str) -> bool {
+        let count = match self.counts.get_mut(source) {
+            Some(count) => count,
+            // (the first time a source is counted this second)
+            None => permit(|| self.counts.entry(source.to_string()).or_insert(0)),
+        };
+        *count += 1;
+
+        *count <= MAX_EVENTS_PER_SECOND
+    }
+
+    /// Whether the window's over (every second)
+    pub fn is_due(&self, clock: u64) -> bool {
+        clock - self.window_started >= SAMPLE_RATE as u64
+    }
+
+    /**
+        Starts a new window, returning the sources that went over the limit in the one that ended (which allocates, so only when it's due)
+    */
+    pub fn tick(&mut self, clock: u64) -> Vec<String> {
+        self.window_started = clock;
+
+        self.counts
+            .drain()
+            .filter(|&(_, count)| count > MAX_EVENTS_PER_SECOND)
+            .map(|(source, _)| source)
+            .collect()
+    }
+}
+
+#[test]
+fn test_event_rate() {
+    let mut rate = EventRate::default();
+
+    let allowed = (0..MAX_EVENTS_PER_SECOND + 10)
+        .filter(|_| rate.count("fx"))
+        .count();
+    assert_eq!(allowed, MAX_EVENTS_PER_SECOND);
+    assert!(!rate.is_due(100));
+
+    // a new second starts with a clean slate, and reports who was flooding
+    assert!(rate.is_due(SAMPLE_RATE as u64));
+    assert_eq!(rate.tick(SAMPLE_RATE as u64), vec!["fx".to_string()]);
+    assert!(rate.count("fx"));
+}
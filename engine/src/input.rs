@@ -0,0 +1,352 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU32, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use cpal::{
+    traits::{DeviceTrait, StreamTrait},
+    BufferSize, SampleRate, StreamConfig,
+};
+
+use crate::{
+    devices::find_input_device, midi::MidiEvent, node::AudioNode, realtime, Sampler, SAMPLE_RATE,
+};
+
+/// Input channels past this many are ignored (that's a big audio interface already)
+const MAX_INPUT_CHANNELS: usize = 8;
+/// How many samples of every input channel are kept around (about 370ms)
+const INPUT_BUFFER_SIZE: usize = 16_384;
+/// How far (about 12ms) behind the input stream an `Input` reads, so that it doesn't run out when the input and output callbacks don't line up
+const INPUT_LATENCY: usize = 512;
+
+struct Channel {
+    // (f32 bits, because there's no atomic float, like in a `Tap`)
+    samples: Box<[AtomicU32]>,
+    written: AtomicUsize,
+}
+
+impl Channel {
+    fn push(&self, sample: f32) {
+        let i = self.written.load(Ordering::Relaxed);
+        self.samples[i % INPUT_BUFFER_SIZE].store(sample.to_bits(), Ordering::Relaxed);
+        self.written.store(i + 1, Ordering::Release);
+    }
+
+    fn get(&self, i: usize) -> f32 {
+        f32::from_bits(self.samples[i % INPUT_BUFFER_SIZE].load(Ordering::Relaxed))
+    }
+}
+
+/**
+    What comes in on the input device (microphone, line-in), per channel, for `Input`s to read.
+
+    Like a `Tap`, it's a lock-free ring buffer per channel: the input stream's callback just keeps writing, and every `Input` follows along at its own pace, on the audio thread.
+*/
+#[derive(Clone)]
+pub(crate) struct LiveInput {
+    channels: Arc<[Channel]>,
+}
+
+impl LiveInput {
+    pub(crate) fn new() -> Self {
+        Self {
+            channels: (0..MAX_INPUT_CHANNELS)
+                .map(|_| Channel {
+                    samples: (0..INPUT_BUFFER_SIZE).map(|_| AtomicU32::new(0)).collect(),
+                    written: AtomicUsize::new(0),
+                })
+                .collect(),
+        }
+    }
+
+    /**
+        Writes a block of interleaved samples, as they come from the input device
+    */
+    pub(crate) fn write(&self, data: &[f32], channels: usize) {
+        for frame in data.chunks(channels.max(1)) {
+            for (channel, &sample) in self.channels.iter().zip(frame) {
+                channel.push(sample);
+            }
+        }
+    }
+}
+
+/**
+    Starts capturing the input device (with that name, or the default one) into `input`, returning the stream and the device's name. Without an input device this fails, and inputs are just silent.
+
+    (It always records at `SAMPLE_RATE`, whatever the output plays at, because that's the pace `Input`s read at.)
+*/
+pub(crate) fn start_input(
+    input: LiveInput,
+    name: Option<&str>,
+) -> Result<(cpal::Stream, String), String> {
+    let device = find_input_device(name)?;
+    let name = device.name().map_err(|e| e.to_string())?;
+
+    let channels = device
+        .default_input_config()
+        .map_err(|e| e.to_string())?
+        .channels();
+
+    let config = StreamConfig {
+        channels,
+        sample_rate: SampleRate(SAMPLE_RATE),
+        buffer_size: BufferSize::Default,
+    };
+
+    let stream = device
+        .build_input_stream(
+            &config,
+            move |data: &[f32], _: &cpal::InputCallbackInfo| {
+                // (it's an audio thread too)
+                let _realtime = realtime::realtime();
+                input.write(data, channels as usize);
+            },
+            |err| tracing::error!("an error occurred on input stream: {}", err),
+            None,
+        )
+        .map_err(|e| e.to_string())?;
+
+    stream.play().map_err(|e| e.to_string())?;
+
+    Ok((stream, name))
+}
+
+/**
+    One channel of the input device, live: `input(1)` in the language. Its `volume` is a parameter, like an oscillator's.
+*/
+pub struct Input {
+    // parameters
+    volume: f32,
+
+    // audio node helper stuff
+    named_parameters: HashMap<String, String>,
+
+    live: LiveInput,
+    // (counting from 0, and `None` for channels the engine doesn't keep)
+    channel: Option<usize>,
+
+    // state
+    // (the next sample to read, once it started reading)
+    read: Option<usize>,
+    sample: f32,
+}
+
+impl Input {
+    /**
+        (Channels count from 1, like on an audio interface.)
+    */
+    pub(crate) fn new(live: LiveInput, channel: usize) -> Self {
+        Self {
+            volume: 1.0,
+            named_parameters: HashMap::new(),
+            live,
+            channel: channel
+                .checked_sub(1)
+                .filter(|&channel| channel < MAX_INPUT_CHANNELS),
+            read: None,
+            sample: 0.0,
+        }
+    }
+}
+
+impl AudioNode for Input {
+    fn parameters(&self) -> Vec<String> {
+        vec!["volume".into()]
+    }
+
+    fn map(&mut self, name: String, parameter: String) {
+        self.named_parameters.insert(name, parameter);
+    }
+
+    fn apply(&mut self, param: &str, value: f32) {
+        let param = self
+            .named_parameters
+            .get(param)
+            .map_or(param, |actual| actual.as_str());
+
+        if param == "volume" {
+            self.volume = value;
+        }
+    }
+
+    fn tick(&mut self) {
+        let Some(channel) = self.channel.map(|i| &self.live.channels[i]) else {
+            return;
+        };
+
+        let written = channel.written.load(Ordering::Acquire);
+
+        // (starting out, or so far behind that it's been overwritten)
+        let read = match self.read {
+            Some(read) if read + INPUT_BUFFER_SIZE > written => read,
+            _ => written.saturating_sub(INPUT_LATENCY),
+        };
+
+        // (when it caught up with the input, it holds the last sample, which clicks less than silence)
+        if read < written {
+            self.sample = channel.get(read);
+            self.read = Some(read + 1);
+        } else {
+            self.read = Some(read);
+        }
+    }
+
+    fn get_next_sample(&self) -> f32 {
+        self.sample * self.volume
+    }
+}
+
+/**
+    Records a stretch of input, and then plays it like a sample: `record_buffer(4s)` in the language. Until it's done recording it's silent (play the `input` itself to hear what's coming in), and then the recording loops, for live-looping. It takes the same parameters as a `Sampler` (so `[loop = 0]` plays it just once), and a note records it again.
+*/
+pub struct Recorder {
+    input: Input,
+    length: usize,
+    recording: Vec<f32>,
+
+    // audio node helper stuff
+    named_parameters: HashMap<String, String>,
+    // (what was applied while recording, for the sampler that plays it)
+    applied: HashMap<String, f32>,
+
+    playing: Option<Sampler>,
+}
+
+impl Recorder {
+    pub(crate) fn new(input: Input, duration: Duration) -> Self {
+        let length = ((duration.as_secs_f64() * SAMPLE_RATE as f64) as usize).max(1);
+
+        Self {
+            input,
+            length,
+            recording: Vec::with_capacity(length),
+            named_parameters: HashMap::new(),
+            applied: HashMap::new(),
+            playing: None,
+        }
+    }
+
+    fn play_recording(&mut self) {
+        let mut sampler = Sampler::new(std::mem::take(&mut self.recording), SAMPLE_RATE);
+
+        sampler.apply("loop", 1.0);
+        for (name, parameter) in &self.named_parameters {
+            sampler.map(name.clone(), parameter.clone());
+        }
+        for (param, value) in &self.applied {
+            sampler.apply(param, *value);
+        }
+
+        self.playing = Some(sampler);
+    }
+}
+
+impl AudioNode for Recorder {
+    fn parameters(&self) -> Vec<String> {
+        // (a sampler's, which it's going to be)
+        Sampler::new(vec![], SAMPLE_RATE).parameters()
+    }
+
+    fn map(&mut self, name: String, parameter: String) {
+        if let Some(sampler) = &mut self.playing {
+            sampler.map(name.clone(), parameter.clone());
+        }
+        self.named_parameters.insert(name, parameter);
+    }
+
+    fn apply(&mut self, param: &str, value: f32) {
+        if let Some(sampler) = &mut self.playing {
+            sampler.apply(param, value);
+        }
+        self.applied.insert(param.to_string(), value);
+    }
+
+    fn note(&mut self, event: MidiEvent) {
+        if let MidiEvent::NoteOn { .. } = event {
+            self.playing = None;
+            self.recording.clear();
+        }
+    }
+
+    fn tick(&mut self) {
+        // (the input keeps up, also while playing, so that recording again starts right away)
+        self.input.tick();
+
+        if let Some(sampler) = &mut self.playing {
+            sampler.tick();
+            return;
+        }
+
+        self.recording.push(self.input.get_next_sample());
+
+        if self.recording.len() >= self.length {
+            self.play_recording();
+        }
+    }
+
+    fn get_next_sample(&self) -> f32 {
+        self.playing
+            .as_ref()
+            .map_or(0.0, |sampler| sampler.get_next_sample())
+    }
+}
+
+#[test]
+fn test_input() {
+    let live = LiveInput::new();
+    let mut input = Input::new(live.clone(), 2);
+    let mut missing = Input::new(live.clone(), 0);
+
+    let stereo = (0..INPUT_LATENCY * 2)
+        .flat_map(|i| [0.0, i as f32])
+        .collect::<Vec<_>>();
+    live.write(&stereo, 2);
+
+    // (it starts reading a bit behind what came in)
+    input.tick();
+    assert_eq!(input.get_next_sample(), INPUT_LATENCY as f32);
+    input.tick();
+    assert_eq!(input.get_next_sample(), INPUT_LATENCY as f32 + 1.0);
+
+    missing.tick();
+    assert_eq!(missing.get_next_sample(), 0.0);
+
+    // (and holds on when it catches up)
+    for _ in 0..INPUT_LATENCY * 2 {
+        input.tick();
+    }
+    assert_eq!(input.get_next_sample(), (INPUT_LATENCY * 2 - 1) as f32);
+}
+
+#[test]
+fn test_recorder() {
+    let live = LiveInput::new();
+    let length = Duration::from_secs_f64(4.0 / SAMPLE_RATE as f64);
+    let mut recorder = Recorder::new(Input::new(live.clone(), 1), length);
+
+    live.write(&[0.1, 0.2, 0.3, 0.4], 1);
+    live.write(&[0.0; INPUT_LATENCY - 4], 1);
+
+    // (silent while recording)
+    for _ in 0..4 {
+        assert_eq!(recorder.get_next_sample(), 0.0);
+        recorder.tick();
+    }
+
+    let played = (0..8)
+        .map(|_| {
+            let sample = recorder.get_next_sample();
+            recorder.tick();
+            sample
+        })
+        .collect::<Vec<_>>();
+
+    // (the recording loops)
+    assert!((played[1] - 0.2).abs() < 1e-6);
+    assert!((played[5] - 0.2).abs() < 1e-6);
+}
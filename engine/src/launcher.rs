@@ -0,0 +1,121 @@
+use std::mem;
+
+/**
+    What the clip launcher is doing, as last published by the audio thread (see `TransportState`)
+*/
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LauncherState {
+    /// (track, clip) of the clips that are playing, one per track at most
+    pub playing: Vec<(String, String)>,
+    /// (track, clip) of what's launched at the next bar, where `None` stops the track
+    pub queued: Vec<(String, Option<String>)>,
+}
+
+/**
+    The clip launcher's grid, as far as the engine is concerned: which targets are clips (on which track), and which of those are launched. A clip is only heard while it's launched, but like a muted target, it's still rendered in the meantime, so that it's in time when it comes in. Launching (or stopping) lands on the next bar, and a track plays one clip at a time.
+*/
+#[derive(Default)]
+pub(crate) struct Launcher {
+    // (target, track) of every clip
+    clips: Vec<(String, String)>,
+    // (track, target)
+    playing: Vec<(String, String)>,
+    // (beat it lands on, track, the clip it plays, if any)
+    queued: Vec<(f64, String, Option<String>)>,
+    // (so that it's only published when something changed)
+    changed: bool,
+}
+
+impl Launcher {
+    /**
+        Which targets are clips, returning the ones before (to be freed elsewhere). What was playing (or launched) keeps playing, as long as it's still a clip on the same track.
+    */
+    pub fn set_clips(&mut self, clips: Vec<(String, String)>) -> Vec<(String, String)> {
+        let replaced = mem::replace(&mut self.clips, clips);
+
+        let clips = &self.clips;
+        let is_clip =
+            |track: &str, target: &str| clips.iter().any(|(t, tr)| t == target && tr == track);
+        self.playing
+            .retain(|(track, target)| is_clip(track, target));
+        self.queued.retain(|(_, track, target)| match target {
+            Some(target) => is_clip(track, target),
+            None => true,
+        });
+
+        self.changed = true;
+        replaced
+    }
+
+    /**
+        Plays a clip on its track from the given beat on, instead of whatever the track played (or stops the track, without a clip). Until then it's queued, and launching something else on the same track in the meantime replaces it.
+    */
+    pub fn launch(&mut self, track: String, clip: Option<String>, at: f64) {
+        // (only what's in the grid, on that track)
+        let in_grid = |clip: &String| self.clips.iter().any(|(t, tr)| t == clip && *tr == track);
+        if clip.as_ref().is_some_and(|clip| !in_grid(clip)) {
+            return;
+        }
+
+        self.queued.retain(|(_, queued, _)| *queued != track);
+        self.queued.push((at, track, clip));
+        self.changed = true;
+    }
+
+    /// (whether anything's due at this beat, which doesn't allocate, unlike landing it)
+    pub fn is_due(&self, beat: f64) -> bool {
+        self.queued.iter().any(|(at, _, _)| *at <= beat)
+    }
+
+    /**
+        Plays (and stops) what's due at this beat
+    */
+    pub fn land(&mut self, beat: f64) {
+        let (due, later) = mem::take(&mut self.queued)
+            .into_iter()
+            .partition::<Vec<_>, _>(|(at, _, _)| *at <= beat);
+        self.queued = later;
+
+        for (_, track, clip) in due {
+            self.playing.retain(|(playing, _)| *playing != track);
+            if let Some(clip) = clip {
+                self.playing.push((track, clip));
+            }
+        }
+
+        self.changed = true;
+    }
+
+    /// (like after a panic)
+    pub fn stop_all(&mut self) {
+        self.playing.clear();
+        self.queued.clear();
+        self.changed = true;
+    }
+
+    /**
+        Whether a target is a clip that isn't launched, so it isn't heard
+    */
+    pub fn is_silent(&self, target: &str) -> bool {
+        self.clips.iter().any(|(clip, _)| clip == target)
+            && !self.playing.iter().any(|(_, clip)| clip == target)
+    }
+
+    pub fn is_changed(&self) -> bool {
+        self.changed
+    }
+
+    /// (to publish, which allocates)
+    pub fn state(&mut self) -> LauncherState {
+        self.changed = false;
+
+        LauncherState {
+            playing: self.playing.clone(),
+            queued: self
+                .queued
+                .iter()
+                .map(|(_, track, clip)| (track.clone(), clip.clone()))
+                .collect(),
+        }
+    }
+}
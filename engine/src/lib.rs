@@ -0,0 +1,76 @@
+#![feature(portable_simd)]
+
+mod block;
+mod bounce;
+mod bus;
+mod channels;
+mod devices;
+mod effects;
+mod engine;
+mod flac;
+#[cfg(test)]
+mod golden;
+mod guard;
+mod input;
+mod launcher;
+mod master;
+mod meter;
+mod midi;
+mod modulation;
+mod morph;
+mod node;
+mod output;
+#[cfg(not(target_arch = "wasm32"))]
+mod plugin;
+mod profile;
+mod realtime;
+mod sequencer;
+mod slices;
+mod smoothing;
+mod switch;
+mod tap;
+mod tempo;
+mod timers;
+mod transport;
+mod voices;
+
+pub use block::BLOCK_SIZE;
+pub use bounce::{save_wav, Bounce};
+pub use bus::{Bus, BusReturn, BusSend, Routing};
+pub use channels::Placement;
+pub use devices::{input_devices, output_devices, DeviceInfo, DeviceSettings, Devices};
+pub use effects::{Effect, EFFECTS};
+pub use engine::{Engine, EngineHandle};
+pub use guard::Runaway;
+pub use input::{Input, Recorder};
+pub use launcher::LauncherState;
+pub use master::MASTER_VOLUME;
+pub use meter::{Level, MasterLevel};
+pub use midi::{note_freq, MidiEvent, Tuning, MIDI_FREQ, MIDI_GATE, MIDI_PITCH, MIDI_VELOCITY};
+pub use modulation::Modulation;
+pub use morph::MORPH;
+pub use node::{AudioNode, Dc, Gain, Mix, Osc, Sampler};
+#[cfg(not(target_arch = "wasm32"))]
+pub use plugin::{installed_plugins, Plugin, PluginInfo};
+pub use profile::Costs;
+pub use realtime::RealtimeAllocator;
+pub use sequencer::{Hit, Sequencer};
+pub use slices::{detect_slices, slice};
+pub use smoothing::DEFAULT_EASE;
+pub use switch::{Compare, Comparison, Switch, Switching};
+pub use tap::{Tap, TAP_SIZE};
+pub use tempo::{detect_tempo, time_stretch, TapTempo};
+pub use timers::{Fired, Timing};
+pub use transport::{
+    clamp_swing, Groove, Humanize, Quantize, Sometimes, TransportState, BARS_PER_PHRASE,
+    BEATS_PER_BAR, DEFAULT_TEMPO, STEPS_PER_BEAT, STRAIGHT, TRANSPORT_BEAT, TRANSPORT_SWING,
+    TRANSPORT_TEMPO,
+};
+pub use voices::{Adsr, Poly, Stealing, VOICE_FREQ, VOICE_PITCH, VOICE_VELOCITY};
+
+pub const SAMPLE_RATE: u32 = 44_100;
+
+// (so that the tests catch what's allocated on the audio thread, see `realtime`)
+#[cfg(test)]
+#[global_allocator]
+static ALLOCATOR: RealtimeAllocator<std::alloc::System> = RealtimeAllocator(std::alloc::System);
@@ -0,0 +1,124 @@
+use crate::{meter::Meter, realtime::permit, smoothing::Smoothed, MasterLevel, SAMPLE_RATE};
+
+/// The engine parameter that controls the master volume, like any other parameter, e.g. with a knob in `def master = { volume = ◉ }`
+pub const MASTER_VOLUME: &str = "master.volume";
+
+/// The limiter never lets anything louder than this through (about -0.2 dBFS)
+const CEILING: f32 = 0.977;
+
+/// How long the limiter takes to go back from full reduction to unity gain
+const RELEASE_MS: f32 = 100.0;
+
+/**
+    A brick-wall limiter: instant attack (so nothing ever gets through above the ceiling), linear release.
+*/
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Limiter {
+    gain: f32,
+    release_step: f32,
+}
+
+impl Limiter {
+    pub fn new() -> Self {
+        Self {
+            gain: 1.0,
+            release_step: 1000.0 / (RELEASE_MS * SAMPLE_RATE as f32),
+        }
+    }
+
+    pub fn process(&mut self, sample: f32) -> f32 {
+        // whatever happened upstream, it's not going to the speakers
+        if !sample.is_finite() {
+            return 0.0;
+        }
+
+        let max_gain = if sample.abs() > CEILING {
+            CEILING / sample.abs()
+        } else {
+            1.0
+        };
+
+        self.gain = (self.gain + self.release_step).min(max_gain);
+
+        sample * self.gain
+    }
+}
+
+/**
+    Everything that's played is summed onto the master bus, which applies the master volume and protects ears and PA with a limiter
+*/
+pub(crate) struct Master {
+    pub volume: Smoothed,
+    // (one per channel)
+    limiters: Vec<Limiter>,
+    meter: Meter,
+    clips: usize,
+    // whether the current meter block clipped
+    clipping: bool,
+}
+
+impl Master {
+    pub fn new() -> Self {
+        Self {
+            volume: Smoothed::new(1.0),
+            limiters: vec![],
+            meter: Meter::default(),
+            clips: 0,
+            clipping: false,
+        }
+    }
+
+    /**
+        Limits a frame (a sample for every channel) in place, returning the master level whenever a meter block is complete
+    */
+    pub fn process(&mut self, frame: &mut [f32]) -> Option<MasterLevel> {
+        // (only the first frame, or on another device)
+        if self.limiters.len() != frame.len() {
+            permit(|| self.limiters.resize(frame.len(), Limiter::new()));
+        }
+
+        let volume = self.volume.next();
+        for sample in frame.iter_mut() {
+            *sample *= volume;
+        }
+        // (what's metered is the loudest channel, because that's the one that clips)
+        let loudest = frame.iter().map(|sample| sample.abs()).fold(0.0, f32::max);
+
+        // (it's the level before limiting that's interesting, because that's what you'd have to fix)
+        self.clipping |= loudest > 1.0;
+
+        let level = self.meter.measure(loudest).map(|level| {
+            if self.clipping {
+                self.clips += 1;
+                self.clipping = false;
+            }
+
+            MasterLevel {
+                level,
+                clips: self.clips,
+            }
+        });
+
+        for (sample, limiter) in frame.iter_mut().zip(&mut self.limiters) {
+            *sample = limiter.process(*sample);
+        }
+
+        level
+    }
+}
+
+#[test]
+fn test_limiter() {
+    let mut limiter = Limiter::new();
+
+    let samples = (0..10_000)
+        .map(|i| limiter.process(if i < 500 { 4.0 } else { 0.5 }))
+        .collect::<Vec<_>>();
+
+    assert!(samples.iter().all(|sample| sample.abs() <= CEILING));
+
+    // it lets go again
+    assert_eq!(samples[9_999], 0.5);
+
+    assert_eq!(limiter.process(f32::NAN), 0.0);
+}
@@ -0,0 +1,82 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+/// How many samples we measure before publishing a new level (about 23ms)
+const METER_BLOCK: usize = 1024;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Level {
+    pub peak: f32,
+    pub rms: f32,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MasterLevel {
+    /// (before the limiter)
+    pub level: Level,
+    /// How many meter blocks clipped (before the limiter) since the engine started, so you can tell whether there were new clips
+    pub clips: usize,
+}
+
+/**
+    The latest levels, per play target, shared between the audio thread and whoever wants to show them
+*/
+pub(crate) type Levels = Arc<Mutex<HashMap<String, Level>>>;
+
+pub(crate) type SharedMasterLevel = Arc<Mutex<MasterLevel>>;
+
+/**
+    Measures a signal on the audio thread, one block at a time
+*/
+#[derive(Debug, Default)]
+pub(crate) struct Meter {
+    peak: f32,
+    sqr_sum: f32,
+    num_samples: usize,
+}
+
+impl Meter {
+    /**
+        Returns the level when a block is complete
+    */
+    pub fn measure(&mut self, sample: f32) -> Option<Level> {
+        self.peak = self.peak.max(sample.abs());
+        self.sqr_sum += sample * sample;
+        self.num_samples += 1;
+
+        if self.num_samples < METER_BLOCK {
+            return None;
+        }
+
+        let level = Level {
+            peak: self.peak,
+            rms: (self.sqr_sum / self.num_samples as f32).sqrt(),
+        };
+
+        *self = Self::default();
+
+        Some(level)
+    }
+}
+
+#[test]
+fn test_meter() {
+    let mut meter = Meter::default();
+
+    let levels = (0..METER_BLOCK * 2)
+        .filter_map(|i| meter.measure(if i % 2 == 0 { 0.5 } else { -0.5 }))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        levels,
+        vec![
+            Level {
+                peak: 0.5,
+                rms: 0.5
+            };
+            2
+        ]
+    );
+}
@@ -0,0 +1,113 @@
+use std::time::Duration;
+
+/// The note that's playing, as a MIDI note number
+pub const MIDI_PITCH: &str = "midi.pitch";
+/// The note that's playing, in Hz
+pub const MIDI_FREQ: &str = "midi.freq";
+/// How hard the note was struck, 0..1
+pub const MIDI_VELOCITY: &str = "midi.velocity";
+/// 1 while a note is held, 0 otherwise
+pub const MIDI_GATE: &str = "midi.gate";
+
+/**
+    Notes are played this long after they were timestamped. Commands are only picked up once per output block, so playing them "as soon as possible" would shift every note by a random fraction of a block. Delaying all of them by a bit more than a block instead keeps the timing between them intact.
+*/
+pub(crate) const MIDI_LATENCY: Duration = Duration::from_millis(15);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MidiEvent {
+    NoteOn { note: u8, velocity: f32 },
+    NoteOff { note: u8 },
+}
+
+pub fn note_freq(note: f32) -> f32 {
+    440.0 * 2.0_f32.powf((note - 69.0) / 12.0)
+}
+
+/**
+    The frequency that every MIDI note plays at, or none, for the ones that a tuning leaves out (which then don't play). It's equal temperament, unless the code says otherwise, with `tuning(..)`.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tuning([Option<f32>; 128]);
+
+impl Default for Tuning {
+    fn default() -> Self {
+        Self::new(|note| Some(note_freq(note as f32)))
+    }
+}
+
+impl Tuning {
+    pub fn new(freq: impl Fn(u8) -> Option<f32>) -> Self {
+        Self(std::array::from_fn(|note| freq(note as u8)))
+    }
+
+    pub fn freq(&self, note: u8) -> Option<f32> {
+        self.0.get(note as usize).copied().flatten()
+    }
+}
+
+/**
+    The `midi_in` source: monophonic, with last note priority (so releasing a note falls back to the one that's still held before it)
+*/
+#[derive(Debug)]
+pub(crate) struct MidiIn {
+    // (note, velocity), in the order they were struck
+    held: Vec<(u8, f32)>,
+}
+
+impl Default for MidiIn {
+    fn default() -> Self {
+        Self {
+            // (room for every note at once, so that it never grows on the audio thread)
+            held: Vec::with_capacity(128),
+        }
+    }
+}
+
+impl MidiIn {
+    /**
+        Handles an event, returning the note that's playing after it, if any
+    */
+    pub fn handle(&mut self, event: MidiEvent) -> Option<(u8, f32)> {
+        match event {
+            MidiEvent::NoteOn { note, velocity } => {
+                self.held.retain(|&(n, _)| n != note);
+                self.held.push((note, velocity));
+            }
+            MidiEvent::NoteOff { note } => {
+                self.held.retain(|&(n, _)| n != note);
+            }
+        }
+
+        self.held.last().copied()
+    }
+}
+
+#[test]
+fn test_midi_in_last_note_priority() {
+    let mut midi = MidiIn::default();
+
+    assert_eq!(
+        midi.handle(MidiEvent::NoteOn {
+            note: 60,
+            velocity: 0.5
+        }),
+        Some((60, 0.5))
+    );
+    assert_eq!(
+        midi.handle(MidiEvent::NoteOn {
+            note: 64,
+            velocity: 1.0
+        }),
+        Some((64, 1.0))
+    );
+
+    // releasing the top note falls back to the one below
+    assert_eq!(
+        midi.handle(MidiEvent::NoteOff { note: 64 }),
+        Some((60, 0.5))
+    );
+    assert_eq!(midi.handle(MidiEvent::NoteOff { note: 60 }), None);
+
+    assert_eq!(note_freq(69.0), 440.0);
+}
@@ -0,0 +1,109 @@
+use crate::{bus::Routing, node::AudioNode, smoothing::Smoothed};
+
+/**
+    What a "maybe modulated" parameter is set to. A plain number is lifted to a constant, a value that's set from the outside (a knob, `midi.freq`) only changes when commands come in, once per block, and another node (like `sin(4hz)`) changes every sample.
+*/
+pub enum Modulation {
+    Constant(f32),
+    /// (glided to, so that it doesn't step from block to block)
+    Control(String, Smoothed),
+    Signal(Box<dyn AudioNode + Send>),
+}
+
+impl From<f32> for Modulation {
+    fn from(value: f32) -> Self {
+        Self::Constant(value)
+    }
+}
+
+impl From<Box<dyn AudioNode + Send>> for Modulation {
+    fn from(node: Box<dyn AudioNode + Send>) -> Self {
+        Self::Signal(node)
+    }
+}
+
+impl Modulation {
+    /**
+        Follows a value that's applied from the outside, starting at `initial` until it is
+    */
+    pub fn control(name: impl Into<String>, initial: f32) -> Self {
+        Self::Control(name.into(), Smoothed::new(initial))
+    }
+
+    /**
+        Passes on an applied value, to the control it's for or to the modulating node
+    */
+    pub fn apply(&mut self, param: &str, value: f32) {
+        match self {
+            Self::Constant(_) => {}
+            Self::Control(name, smoothed) => {
+                if name == param {
+                    smoothed.set_target(value);
+                }
+            }
+            Self::Signal(node) => node.apply(param, value),
+        }
+    }
+
+    /// (a modulating node could read a bus, like anything else)
+    pub fn route(&self, routing: &mut Routing) {
+        if let Self::Signal(node) = self {
+            node.route(routing);
+        }
+    }
+
+    /// (or economize)
+    pub fn economize(&mut self, economize: bool) {
+        if let Self::Signal(node) = self {
+            node.economize(economize);
+        }
+    }
+
+    /**
+        Advances one sample, returning the current value
+    */
+    pub fn tick(&mut self) -> f32 {
+        match self {
+            Self::Constant(value) => *value,
+            Self::Control(_, smoothed) => smoothed.next(),
+            Self::Signal(node) => {
+                node.tick();
+                node.get_next_sample()
+            }
+        }
+    }
+
+    /**
+        Advances a block of samples, like `tick` for every one of them
+    */
+    pub fn process(&mut self, out: &mut [f32]) {
+        match self {
+            Self::Constant(value) => out.fill(*value),
+            Self::Control(_, smoothed) => out.fill_with(|| smoothed.next()),
+            Self::Signal(node) => node.process(out),
+        }
+    }
+}
+
+#[test]
+fn test_modulation() {
+    use crate::node::Osc;
+
+    let mut constant = Modulation::from(3.0);
+    constant.apply("f", 4.0);
+    assert_eq!(constant.tick(), 3.0);
+
+    let mut control = Modulation::control("midi.freq", 100.0);
+    assert_eq!(control.tick(), 100.0);
+    control.apply("midi.freq", 200.0);
+    let glide = (0..2000).map(|_| control.tick()).collect::<Vec<_>>();
+    assert!(glide[0] > 100.0 && glide[0] < 110.0);
+    assert_eq!(glide[1999], 200.0);
+
+    let mut lfo = Osc::default();
+    lfo.apply("frequency", 4.0);
+    let lfo: Box<dyn AudioNode + Send> = Box::new(lfo);
+    let mut signal = Modulation::from(lfo);
+    let samples = (0..100).map(|_| signal.tick()).collect::<Vec<_>>();
+    assert!(samples.windows(2).all(|w| w[1] > w[0]));
+}
@@ -0,0 +1,61 @@
+use std::mem;
+
+use crate::smoothing::Smoothed;
+
+/// The engine parameter that morphs between two snapshots (see `EngineHandle::set_morph`), from 0 (the first) to 1 (the second), like any other parameter, so it's smoothed (or eased)
+pub const MORPH: &str = "morph.position";
+
+/**
+    Two snapshots of parameter values (like knobs), and where it is in between them. While that's moving, it sets every parameter in them, interpolated, and otherwise it leaves them alone, so they can still be changed some other way.
+*/
+pub(crate) struct Morph {
+    // (name, value in the first snapshot, in the second)
+    params: Vec<(String, f32, f32)>,
+    position: Smoothed,
+    // (so that other snapshots are applied where it is, even when it's not moving)
+    changed: bool,
+}
+
+impl Morph {
+    pub fn new() -> Self {
+        Self {
+            params: vec![],
+            position: Smoothed::new(0.0),
+            changed: false,
+        }
+    }
+
+    /**
+        Morphs between other snapshots, returning the ones before (to be freed elsewhere)
+    */
+    pub fn set(&mut self, params: Vec<(String, f32, f32)>) -> Vec<(String, f32, f32)> {
+        self.changed = true;
+        mem::replace(&mut self.params, params)
+    }
+
+    /// (over how many samples, instead of the usual smoothing)
+    pub fn set_position(&mut self, position: f32, ease: Option<usize>) {
+        let position = position.clamp(0.0, 1.0);
+        match ease {
+            Some(samples) => self.position.set_target_over(position, samples as f32),
+            None => self.position.set_target(position),
+        }
+    }
+
+    /**
+        Advances one sample, and returns where it is when the parameters have to be set (when it's moving, or the snapshots changed)
+    */
+    pub fn next(&mut self) -> Option<f32> {
+        let moving = !self.position.is_settled();
+        let position = self.position.next();
+
+        (moving || mem::take(&mut self.changed)).then_some(position)
+    }
+
+    /// (what the parameters are, at a position)
+    pub fn values(&self, position: f32) -> impl Iterator<Item = (&str, f32)> + '_ {
+        self.params
+            .iter()
+            .map(move |(name, a, b)| (name.as_str(), a + (b - a) * position))
+    }
+}
@@ -0,0 +1,796 @@
+use std::{collections::HashMap, f32::consts::TAU};
+
+use crate::{
+    block::{self, Lanes, BLOCK_SIZE, LANES},
+    bus::Routing,
+    midi::{MidiEvent, Tuning},
+    modulation::Modulation,
+    SAMPLE_RATE,
+};
+
+pub trait AudioNode {
+    fn parameters(&self) -> Vec<String>;
+
+    /**
+        Makes a parameter available under another name, so that it can be modulated from the outside (e.g. `lowpass{f = ..}` in `def fx` is reachable as `fx.f`)
+    */
+    fn map(&mut self, name: String, parameter: String);
+
+    /**
+        Sets a parameter, either by its own name or by a name it was mapped to. Nodes just ignore parameters they don't know.
+    */
+    fn apply(&mut self, param: &str, value: f32);
+
+    /**
+        Plays a note (from MIDI, or triggered by a pattern). Nodes that play voices start and release them, nodes with inputs pass it on, and the rest ignore it.
+    */
+    fn note(&mut self, _event: MidiEvent) {}
+
+    /**
+        Plays the notes after it in another tuning. Nodes that play voices keep it, nodes with inputs pass it on, and the rest ignore it.
+    */
+    fn retune(&mut self, _tuning: &Tuning) {}
+
+    /**
+        Adds the buses it sends to and reads from (see `Routing`). Only sends and bus returns use buses, so nodes with inputs pass it on, and the rest ignore it.
+    */
+    fn route(&self, _routing: &mut Routing) {}
+
+    /**
+        Asks it to save CPU (or not anymore), when the engine can't keep up otherwise (see `EngineHandle::set_economize`). Nodes that have a cheaper way of sounding about the same (the reverb) switch to it, nodes with inputs pass it on, and the rest ignore it.
+    */
+    fn economize(&mut self, _economize: bool) {}
+
+    fn tick(&mut self);
+
+    fn get_next_sample(&self) -> f32;
+
+    /**
+        Renders a block of (at most `BLOCK_SIZE`) samples at once, just like ticking for every one of them would. The nodes that there are the most of (oscillators, mixes, filters and voices) do it faster, and the rest just tick.
+    */
+    fn process(&mut self, out: &mut [f32]) {
+        for sample in out {
+            self.tick();
+            *sample = self.get_next_sample();
+        }
+    }
+}
+
+pub struct Osc {
+    // parameters
+    volume: f32,
+    frequency: f32,
+    squareness: f32,
+
+    // audio node helper stuff
+    named_parameters: HashMap<String, String>,
+
+    // state
+    rad: f32,
+}
+
+impl Default for Osc {
+    fn default() -> Self {
+        Self {
+            volume: 0.3,
+            frequency: 440.0,
+            squareness: 0.0,
+            named_parameters: HashMap::new(),
+            rad: 0.0,
+        }
+    }
+}
+
+impl AudioNode for Osc {
+    fn parameters(&self) -> Vec<String> {
+        vec!["volume".into(), "frequency".into(), "squareness".into()]
+    }
+
+    fn map(&mut self, name: String, parameter: String) {
+        self.named_parameters.insert(name, parameter);
+    }
+
+    fn apply(&mut self, param: &str, value: f32) {
+        let param = self
+            .named_parameters
+            .get(param)
+            .map_or(param, |actual| actual.as_str());
+
+        match param {
+            "volume" => self.volume = value,
+            "frequency" => self.frequency = value,
+            "squareness" => self.squareness = value,
+            _ => {}
+        }
+    }
+
+    fn tick(&mut self) {
+        self.rad += self.frequency * (TAU / SAMPLE_RATE as f32);
+        self.rad %= TAU;
+    }
+
+    fn get_next_sample(&self) -> f32 {
+        let sin = self.rad.sin();
+
+        if self.squareness <= 0.0 {
+            return sin * self.volume;
+        }
+
+        // as a smoothed square
+        let d = 1.0 - self.squareness.min(0.99); // between 0 and 1
+        let smooth_sq = (sin / d).atan() / (1.0 / d).atan();
+
+        smooth_sq * self.volume
+    }
+
+    fn process(&mut self, out: &mut [f32]) {
+        let step = self.frequency * (TAU / SAMPLE_RATE as f32);
+        let d = 1.0 - self.squareness.min(0.99);
+
+        // (the phases are added up one by one, exactly like ticking does, since multiplying drifts away from that, and on the steep edges of a square that's audible)
+        // (and `%` calls `fmodf`, but when it only ever goes up by less than a turn, subtracting one is exactly the same)
+        let wraps = self.rad >= 0.0 && (0.0..TAU).contains(&step);
+        for rad in out.iter_mut() {
+            self.rad += step;
+            if !wraps {
+                self.rad %= TAU;
+            } else if self.rad >= TAU {
+                self.rad -= TAU;
+            }
+            *rad = self.rad;
+        }
+
+        for chunk in out.chunks_mut(LANES) {
+            let sin = block::sin(Lanes::load_or_default(chunk));
+
+            let samples = if self.squareness <= 0.0 {
+                sin * Lanes::splat(self.volume)
+            } else {
+                // (there's no `atan` for SIMD)
+                Lanes::from_array(
+                    sin.to_array()
+                        .map(|sin| (sin / d).atan() / (1.0 / d).atan() * self.volume),
+                )
+            };
+            chunk.copy_from_slice(&samples.as_array()[..chunk.len()]);
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Mix {
+    inputs: Vec<Box<dyn AudioNode + Send>>,
+}
+
+impl Mix {
+    pub fn add(mut self, node: Box<dyn AudioNode + Send>) -> Self {
+        self.inputs.push(node);
+        self
+    }
+}
+
+impl AudioNode for Mix {
+    fn parameters(&self) -> Vec<String> {
+        vec![]
+    }
+
+    fn map(&mut self, _name: String, _parameter: String) {}
+
+    fn apply(&mut self, param: &str, value: f32) {
+        for input in &mut self.inputs {
+            input.apply(param, value);
+        }
+    }
+
+    fn note(&mut self, event: MidiEvent) {
+        for input in &mut self.inputs {
+            input.note(event);
+        }
+    }
+
+    fn retune(&mut self, tuning: &Tuning) {
+        for input in &mut self.inputs {
+            input.retune(tuning);
+        }
+    }
+
+    fn route(&self, routing: &mut Routing) {
+        for input in &self.inputs {
+            input.route(routing);
+        }
+    }
+
+    fn economize(&mut self, economize: bool) {
+        for input in &mut self.inputs {
+            input.economize(economize);
+        }
+    }
+
+    fn tick(&mut self) {
+        for input in &mut self.inputs {
+            input.tick();
+        }
+    }
+
+    fn get_next_sample(&self) -> f32 {
+        self.inputs.iter().map(|n| n.get_next_sample()).sum()
+    }
+
+    fn process(&mut self, out: &mut [f32]) {
+        out.fill(0.0);
+
+        let mut input = [0.0; BLOCK_SIZE];
+        for node in &mut self.inputs {
+            let input = &mut input[..out.len()];
+            node.process(input);
+            block::add(out, input);
+        }
+    }
+}
+
+/**
+    A signal times something else, which may be a number, a control or another signal (see `Modulation`), like `pad * .5` or `pad * midi.velocity` in the language
+*/
+pub struct Gain {
+    input: Box<dyn AudioNode + Send>,
+    gain: Modulation,
+
+    // state
+    out: f32,
+}
+
+impl Gain {
+    pub fn new(input: Box<dyn AudioNode + Send>, gain: impl Into<Modulation>) -> Self {
+        Self {
+            input,
+            gain: gain.into(),
+            out: 0.0,
+        }
+    }
+}
+
+impl AudioNode for Gain {
+    fn parameters(&self) -> Vec<String> {
+        vec![]
+    }
+
+    fn map(&mut self, _name: String, _parameter: String) {}
+
+    fn apply(&mut self, param: &str, value: f32) {
+        self.input.apply(param, value);
+        self.gain.apply(param, value);
+    }
+
+    fn note(&mut self, event: MidiEvent) {
+        self.input.note(event);
+    }
+
+    fn retune(&mut self, tuning: &Tuning) {
+        self.input.retune(tuning);
+    }
+
+    fn route(&self, routing: &mut Routing) {
+        self.input.route(routing);
+        self.gain.route(routing);
+    }
+
+    fn economize(&mut self, economize: bool) {
+        self.input.economize(economize);
+        self.gain.economize(economize);
+    }
+
+    fn tick(&mut self) {
+        self.input.tick();
+        self.out = self.input.get_next_sample() * self.gain.tick();
+    }
+
+    fn get_next_sample(&self) -> f32 {
+        self.out
+    }
+
+    fn process(&mut self, out: &mut [f32]) {
+        self.input.process(out);
+
+        let mut gain = [0.0; BLOCK_SIZE];
+        let gain = &mut gain[..out.len()];
+        self.gain.process(gain);
+        for (sample, gain) in out.iter_mut().zip(gain) {
+            *sample *= *gain;
+        }
+    }
+}
+
+/**
+    A number (or a control, like `midi.gate`) as a signal of its own, like the `.2` in `sin(4hz) * .1 + .2`
+*/
+pub struct Dc {
+    value: Modulation,
+
+    // state
+    out: f32,
+}
+
+impl Dc {
+    pub fn new(value: impl Into<Modulation>) -> Self {
+        Self {
+            value: value.into(),
+            out: 0.0,
+        }
+    }
+}
+
+impl AudioNode for Dc {
+    fn parameters(&self) -> Vec<String> {
+        vec![]
+    }
+
+    fn map(&mut self, _name: String, _parameter: String) {}
+
+    fn apply(&mut self, param: &str, value: f32) {
+        self.value.apply(param, value);
+    }
+
+    fn route(&self, routing: &mut Routing) {
+        self.value.route(routing);
+    }
+
+    fn economize(&mut self, economize: bool) {
+        self.value.economize(economize);
+    }
+
+    fn tick(&mut self) {
+        self.out = self.value.tick();
+    }
+
+    fn get_next_sample(&self) -> f32 {
+        self.out
+    }
+
+    fn process(&mut self, out: &mut [f32]) {
+        self.value.process(out);
+    }
+}
+
+/// Grains (in granular mode) are this many output samples long (about 46ms), and overlap by half
+const GRAIN_SIZE: f32 = 2048.0;
+
+/**
+    Plays a (mono) buffer of samples, resampled from the rate it was recorded at. By default it plays the whole buffer once, and its parameters (which the language sets with modifiers, like `kick%[rate = 1.5, loop = 1]`) change that:
+
+    - `rate`: how fast it plays, which also changes the pitch (like a record player)
+    - `pitch`: in semitones, on top of the rate
+    - `start`, `end`: which part of the buffer plays, as fractions (0 to 1)
+    - `loop`: whether that part loops (when > 0.5)
+    - `loop_start`, `loop_end`: which part of it loops, if not all of it, as fractions of the whole buffer (it plays up to the loop end, and then jumps back to the loop start, like a sustain loop)
+    - `cue`: jumps to one of its cue points (see `set_cues`), by number, like a pattern of `[0, 2, 1]`
+    - `granular`: whether the pitch is shifted with overlapping grains instead of by resampling (when > 0.5), so that shifting the pitch doesn't change how long it takes
+
+    A note (from a pattern, or MIDI) plays it again from the start, as hard as it's struck.
+*/
+pub struct Sampler {
+    // parameters
+    volume: f32,
+    rate: f32,
+    pitch: f32,
+    start: f32,
+    end: f32,
+    looping: bool,
+    loop_start: f32,
+    loop_end: f32,
+    granular: bool,
+    // (as fractions of the whole buffer)
+    cues: Vec<f32>,
+
+    // audio node helper stuff
+    named_parameters: HashMap<String, String>,
+
+    samples: Vec<f32>,
+    // how far to move through `samples` per output sample, at rate 1
+    step: f32,
+
+    // state
+    // (relative to `start`, so that moving the start point doesn't restart it)
+    position: f32,
+    // (granular mode) where the current two grains started reading, and how far along the newer one is
+    grains: [f32; 2],
+    grain_age: f32,
+    // (of the note that started it)
+    velocity: f32,
+}
+
+impl Sampler {
+    pub fn new(samples: Vec<f32>, sample_rate: u32) -> Self {
+        Self {
+            volume: 1.0,
+            rate: 1.0,
+            pitch: 0.0,
+            start: 0.0,
+            end: 1.0,
+            looping: false,
+            loop_start: 0.0,
+            loop_end: 1.0,
+            granular: false,
+            cues: vec![],
+            named_parameters: HashMap::new(),
+            samples,
+            step: sample_rate as f32 / SAMPLE_RATE as f32,
+            position: 0.0,
+            grains: [0.0; 2],
+            grain_age: 0.0,
+            velocity: 1.0,
+        }
+    }
+
+    /// The part of the buffer that plays, in samples
+    fn region(&self) -> (f32, f32) {
+        let len = self.samples.len() as f32;
+        let start = self.start.clamp(0.0, 1.0) * len;
+        let end = self.end.clamp(0.0, 1.0) * len;
+
+        (start, end.max(start))
+    }
+
+    /// The part of the region that loops, relative to its start (all of it, unless the loop points are inside it)
+    fn loop_region(&self) -> (f32, f32) {
+        let (start, end) = self.region();
+        let len = self.samples.len() as f32;
+        let loop_start = (self.loop_start.clamp(0.0, 1.0) * len).clamp(start, end) - start;
+        let loop_end = (self.loop_end.clamp(0.0, 1.0) * len).clamp(start, end) - start;
+
+        if loop_end > loop_start {
+            (loop_start, loop_end)
+        } else {
+            (0.0, end - start)
+        }
+    }
+
+    /// Where a position (relative to the start) ends up, once it's past the loop end
+    fn wrap(&self, position: f32) -> f32 {
+        let (loop_start, loop_end) = self.loop_region();
+        if position < loop_end || loop_end <= loop_start {
+            return position;
+        }
+
+        loop_start + (position - loop_start).rem_euclid(loop_end - loop_start)
+    }
+
+    /**
+        Where it can jump to with the `cue` parameter (as fractions of the whole buffer), like the cue points that are set on a sample widget
+    */
+    pub fn set_cues(&mut self, cues: Vec<f32>) {
+        self.cues = cues;
+    }
+
+    fn cue(&mut self, i: f32) {
+        let Some(&at) = self.cues.get(i.round().max(0.0) as usize) else {
+            return;
+        };
+
+        let (start, _) = self.region();
+        self.position = (at.clamp(0.0, 1.0) * self.samples.len() as f32 - start).max(0.0);
+        self.grains = [self.position; 2];
+        self.grain_age = 0.0;
+    }
+
+    fn pitch_ratio(&self) -> f32 {
+        2f32.powf(self.pitch / 12.0)
+    }
+
+    fn done(&self) -> bool {
+        let (start, end) = self.region();
+        !self.looping && self.position >= end - start
+    }
+
+    /**
+        The (linearly interpolated) sample at a position relative to the start, wrapped around if it's looping, or silence past the end
+    */
+    fn read(&self, position: f32) -> f32 {
+        let (start, end) = self.region();
+        let len = end - start;
+        if len <= 0.0 {
+            return 0.0;
+        }
+
+        let position = if self.looping {
+            self.wrap(position)
+        } else if position < len {
+            position
+        } else {
+            return 0.0;
+        };
+
+        let at = start + position;
+        let i = at as usize;
+        let Some(&a) = self.samples.get(i) else {
+            return 0.0;
+        };
+
+        // (interpolated, for when the rates don't match)
+        let b = self.samples.get(i + 1).copied().unwrap_or(0.0);
+        let t = at.fract();
+
+        a + (b - a) * t
+    }
+}
+
+impl AudioNode for Sampler {
+    fn parameters(&self) -> Vec<String> {
+        [
+            "volume",
+            "rate",
+            "pitch",
+            "start",
+            "end",
+            "loop",
+            "loop_start",
+            "loop_end",
+            "granular",
+            "cue",
+        ]
+        .map(String::from)
+        .to_vec()
+    }
+
+    fn map(&mut self, name: String, parameter: String) {
+        self.named_parameters.insert(name, parameter);
+    }
+
+    fn apply(&mut self, param: &str, value: f32) {
+        let param = self
+            .named_parameters
+            .get(param)
+            .map_or(param, |actual| actual.as_str());
+
+        match param {
+            "volume" => self.volume = value,
+            // (it doesn't play backwards)
+            "rate" => self.rate = value.max(0.0),
+            "pitch" => self.pitch = value,
+            "start" => self.start = value,
+            "end" => self.end = value,
+            "loop" => self.looping = value > 0.5,
+            "loop_start" => self.loop_start = value,
+            "loop_end" => self.loop_end = value,
+            "granular" => self.granular = value > 0.5,
+            "cue" => self.cue(value),
+            _ => {}
+        }
+    }
+
+    fn note(&mut self, event: MidiEvent) {
+        if let MidiEvent::NoteOn { velocity, .. } = event {
+            self.position = 0.0;
+            self.grains = [0.0; 2];
+            self.grain_age = 0.0;
+            self.velocity = velocity;
+        }
+    }
+
+    fn tick(&mut self) {
+        if self.done() {
+            return;
+        }
+
+        if !self.granular {
+            self.position += self.step * self.rate * self.pitch_ratio();
+            return;
+        }
+
+        // the playhead moves at the rate, while the grains read at the rate plus pitch
+        self.position += self.step * self.rate;
+        self.grain_age += 1.0;
+
+        if self.grain_age >= GRAIN_SIZE / 2.0 {
+            self.grain_age -= GRAIN_SIZE / 2.0;
+            self.grains = [self.grains[1], self.position];
+        }
+
+        if self.looping {
+            self.position = self.wrap(self.position);
+        }
+    }
+
+    fn get_next_sample(&self) -> f32 {
+        if self.done() {
+            return 0.0;
+        }
+
+        if !self.granular {
+            return self.read(self.position) * self.volume * self.velocity;
+        }
+
+        let speed = self.step * self.rate * self.pitch_ratio();
+
+        // two grains, half a grain apart, with triangular windows that add up to 1
+        let sample = [
+            (self.grains[0], self.grain_age + GRAIN_SIZE / 2.0),
+            (self.grains[1], self.grain_age),
+        ]
+        .iter()
+        .map(|&(start, age)| {
+            let window = 1.0 - (2.0 * age / GRAIN_SIZE - 1.0).abs();
+            self.read(start + age * speed) * window
+        })
+        .sum::<f32>();
+
+        sample * self.volume * self.velocity
+    }
+}
+
+#[test]
+fn test_sampler_plays_once() {
+    let mut sampler = Sampler::new(vec![0.5, 1.0], SAMPLE_RATE / 2);
+
+    let mut played = vec![];
+    for _ in 0..6 {
+        played.push(sampler.get_next_sample());
+        sampler.tick();
+    }
+
+    // at half the rate, every other sample is interpolated, and then it stays silent
+    assert_eq!(played, vec![0.5, 0.75, 1.0, 0.5, 0.0, 0.0]);
+
+    // (until a note plays it again, as hard as it's struck)
+    sampler.note(MidiEvent::NoteOn {
+        note: 60,
+        velocity: 0.5,
+    });
+    assert_eq!(sampler.get_next_sample(), 0.25);
+}
+
+#[test]
+fn test_sampler_rate_and_region() {
+    let ramp = (0..8).map(|i| i as f32).collect::<Vec<_>>();
+
+    let play = |sampler: &mut Sampler, n: usize| {
+        (0..n)
+            .map(|_| {
+                let sample = sampler.get_next_sample();
+                sampler.tick();
+                sample
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // twice as fast, or an octave up, skips every other sample
+    let mut sampler = Sampler::new(ramp.clone(), SAMPLE_RATE);
+    sampler.apply("rate", 2.0);
+    assert_eq!(play(&mut sampler, 5), vec![0.0, 2.0, 4.0, 6.0, 0.0]);
+
+    let mut sampler = Sampler::new(ramp.clone(), SAMPLE_RATE);
+    sampler.apply("pitch", 12.0);
+    assert_eq!(play(&mut sampler, 5), vec![0.0, 2.0, 4.0, 6.0, 0.0]);
+
+    // only the middle half, looped
+    let mut sampler = Sampler::new(ramp.clone(), SAMPLE_RATE);
+    sampler.apply("start", 0.25);
+    sampler.apply("end", 0.75);
+    sampler.apply("loop", 1.0);
+    assert_eq!(
+        play(&mut sampler, 10),
+        vec![2.0, 3.0, 4.0, 5.0, 2.0, 3.0, 4.0, 5.0, 2.0, 3.0]
+    );
+}
+
+#[test]
+fn test_sampler_loop_points_and_cues() {
+    let ramp = (0..8).map(|i| i as f32).collect::<Vec<_>>();
+
+    let play = |sampler: &mut Sampler, n: usize| {
+        (0..n)
+            .map(|_| {
+                let sample = sampler.get_next_sample();
+                sampler.tick();
+                sample
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // it plays up to the loop end, and then keeps going around the loop
+    let mut sampler = Sampler::new(ramp.clone(), SAMPLE_RATE);
+    sampler.apply("loop", 1.0);
+    sampler.apply("loop_start", 0.5);
+    sampler.apply("loop_end", 0.75);
+    assert_eq!(
+        play(&mut sampler, 9),
+        vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 4.0, 5.0, 4.0]
+    );
+
+    // (loop points outside of the region that plays are moved into it)
+    let mut sampler = Sampler::new(ramp.clone(), SAMPLE_RATE);
+    sampler.apply("end", 0.5);
+    sampler.apply("loop", 1.0);
+    sampler.apply("loop_start", 0.25);
+    assert_eq!(
+        play(&mut sampler, 7),
+        vec![0.0, 1.0, 2.0, 3.0, 2.0, 3.0, 2.0]
+    );
+
+    // cues jump there, wherever it's at, and unknown ones are ignored
+    let mut sampler = Sampler::new(ramp.clone(), SAMPLE_RATE);
+    sampler.set_cues(vec![0.0, 0.5]);
+    assert_eq!(play(&mut sampler, 2), vec![0.0, 1.0]);
+    sampler.apply("cue", 1.0);
+    assert_eq!(play(&mut sampler, 2), vec![4.0, 5.0]);
+    sampler.apply("cue", 0.0);
+    sampler.apply("cue", 5.0);
+    assert_eq!(play(&mut sampler, 2), vec![0.0, 1.0]);
+}
+
+#[test]
+fn test_granular_pitch_keeps_length() {
+    let len = 10_000;
+    let mut sampler = Sampler::new(vec![0.5; len], SAMPLE_RATE);
+    sampler.apply("granular", 1.0);
+    sampler.apply("pitch", 12.0);
+
+    // (past the first grain, the windows add up to the original level)
+    let mut played = vec![];
+    for _ in 0..len + 10 {
+        played.push(sampler.get_next_sample());
+        sampler.tick();
+    }
+
+    assert!((played[4000] - 0.5).abs() < 0.01);
+    assert!(played[len - 2000] > 0.0);
+    assert_eq!(played[len + 5], 0.0);
+}
+
+#[test]
+fn test_osc_golden() {
+    use crate::golden::{assert_golden, render, render_blocks};
+
+    for render in [render, render_blocks] {
+        let osc = |squareness: f32| {
+            let mut osc = Osc::default();
+            osc.apply("frequency", 220.0);
+            osc.apply("squareness", squareness);
+            render(&mut osc, SAMPLE_RATE as usize / 20)
+        };
+
+        assert_golden("osc_sine", &osc(0.0));
+        assert_golden("osc_square", &osc(0.9));
+    }
+}
+
+#[test]
+fn test_mix_golden() {
+    use crate::golden::{assert_golden, render, render_blocks};
+
+    for render in [render, render_blocks] {
+        let mut mix = Mix::default();
+        for frequency in [220.0, 277.18, 329.63] {
+            let mut osc = Osc::default();
+            osc.apply("frequency", frequency);
+            mix = mix.add(Box::new(osc));
+        }
+
+        assert_golden("mix", &render(&mut mix, SAMPLE_RATE as usize / 20));
+    }
+}
+
+#[test]
+fn test_gain() {
+    use crate::golden::{render, render_blocks};
+
+    for render in [render, render_blocks] {
+        let mut osc = Osc::default();
+        osc.apply("frequency", 220.0);
+        let dry = render(&mut osc, 300);
+
+        let mut osc = Osc::default();
+        osc.apply("frequency", 220.0);
+        let mut gain = Gain::new(Box::new(osc), Modulation::control("velocity", 0.5));
+        let wet = render(&mut gain, 300);
+        for (dry, wet) in dry.iter().zip(&wet) {
+            assert!((dry * 0.5 - wet).abs() < 1e-6);
+        }
+
+        // (the gain is a control, which follows what's applied)
+        gain.apply("velocity", 0.0);
+        assert_eq!(render(&mut gain, 5000)[4999], 0.0);
+
+        let mut dc = Dc::new(0.25);
+        assert_eq!(render(&mut dc, 10), vec![0.25; 10]);
+    }
+}
@@ -0,0 +1,171 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+// (std's `Instant` panics in the browser)
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
+
+use cpal::{
+    traits::{DeviceTrait, StreamTrait},
+    BufferSize, SampleRate, StreamConfig,
+};
+
+use crate::{
+    channels::channel_map,
+    devices::{find_output_device, CallbackLoad, DeviceSettings},
+    engine::Processor,
+    realtime, SAMPLE_RATE,
+};
+
+/**
+    Plays frames rendered at `SAMPLE_RATE` at another sample rate, interpolating linearly (which is fine for the usual 44.1 to 48kHz)
+*/
+struct Resampler {
+    // (how far the processor gets per output frame)
+    step: f64,
+    pos: f64,
+    prev: Vec<f32>,
+    next: Vec<f32>,
+}
+
+impl Resampler {
+    fn new(sample_rate: u32, channels: usize) -> Self {
+        Self {
+            step: SAMPLE_RATE as f64 / sample_rate as f64,
+            pos: 0.0,
+            prev: vec![0.0; channels],
+            next: vec![0.0; channels],
+        }
+    }
+
+    fn next_frame(&mut self, frame: &mut [f32], mut render: impl FnMut(&mut [f32])) {
+        self.pos += self.step;
+        while self.pos >= 1.0 {
+            self.pos -= 1.0;
+            std::mem::swap(&mut self.prev, &mut self.next);
+            render(&mut self.next);
+        }
+
+        for ((out, prev), next) in frame.iter_mut().zip(&self.prev).zip(&self.next) {
+            *out = prev + (next - prev) * self.pos as f32;
+        }
+    }
+}
+
+/**
+    Starts playing whatever the processor renders on the output device, every one of the engine's channels on the device channel it's mapped to (and the others silent), returning the stream, the device's name, the sample rate it plays at, and the device channels it plays on.
+
+    The processor is shared, so that it can be handed to another stream when switching devices, without losing what's playing. Only one stream is ever playing it at a time, but its callback still never waits for the lock, and just stays silent for a block when it can't get it.
+*/
+pub(crate) fn start_output(
+    processor: Arc<Mutex<Processor>>,
+    settings: &DeviceSettings,
+    load: CallbackLoad,
+) -> Result<(cpal::Stream, String, u32, Vec<u16>), String> {
+    let device = find_output_device(settings.output.as_deref())?;
+    let name = device.name().map_err(|e| e.to_string())?;
+
+    let default_channels = device
+        .default_output_config()
+        .map_err(|e| e.to_string())?
+        .channels();
+
+    // (the device is opened with as many channels as it takes to reach the last one that's mapped to)
+    let map = channel_map(
+        &settings.channel_map,
+        settings.channels.unwrap_or(default_channels),
+    );
+    let channels = map.iter().copied().max().unwrap_or(1).max(default_channels);
+
+    let sample_rate = settings.sample_rate.unwrap_or(SAMPLE_RATE);
+
+    let config = StreamConfig {
+        channels,
+        sample_rate: SampleRate(sample_rate),
+        buffer_size: match settings.buffer_size {
+            Some(frames) => BufferSize::Fixed(frames),
+            None => BufferSize::Default,
+        },
+    };
+
+    let mut resampler =
+        (sample_rate != SAMPLE_RATE).then(|| Resampler::new(sample_rate, map.len()));
+    let mut rendered = vec![0.0; map.len()];
+    let mapped = map.clone();
+
+    let stream = device
+        .build_output_stream(
+            &config,
+            move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
+                // (see `realtime` for the rules, which are checked in debug builds)
+                let _realtime = realtime::realtime();
+
+                let Ok(mut processor) = processor.try_lock() else {
+                    data.fill(0.0);
+                    return;
+                };
+
+                let started = Instant::now();
+                processor.start_block();
+
+                for frame in data.chunks_mut(channels as usize) {
+                    match &mut resampler {
+                        Some(resampler) => {
+                            resampler.next_frame(&mut rendered, |frame| processor.next_frame(frame))
+                        }
+                        None => processor.next_frame(&mut rendered),
+                    }
+
+                    frame.fill(0.0);
+                    for (sample, &to) in rendered.iter().zip(&mapped) {
+                        if let Some(out) = frame.get_mut(to as usize - 1) {
+                            *out = *sample;
+                        }
+                    }
+                }
+
+                let frames = data.len() / channels.max(1) as usize;
+                load.measure(
+                    started.elapsed(),
+                    Duration::from_secs_f64(frames as f64 / sample_rate as f64),
+                );
+                processor.set_load(load.get());
+            },
+            |err| tracing::error!("an error occurred on stream: {}", err),
+            None,
+        )
+        .map_err(|e| e.to_string())?;
+
+    stream.play().map_err(|e| e.to_string())?;
+
+    Ok((stream, name, sample_rate, map))
+}
+
+#[test]
+fn test_resampler() {
+    // (44.1kHz played at 88.2kHz is every sample, and one in between, a sample late to have something to interpolate towards)
+    let mut resampler = Resampler::new(SAMPLE_RATE * 2, 2);
+    let mut i = 0.0;
+    let mut render = |frame: &mut [f32]| {
+        i += 1.0;
+        frame.copy_from_slice(&[i, -i]);
+    };
+
+    let played = (0..6)
+        .map(|_| {
+            let mut frame = [0.0; 2];
+            resampler.next_frame(&mut frame, &mut render);
+            frame[0]
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(played, vec![0.0, 0.0, 0.5, 1.0, 1.5, 2.0]);
+
+    // (and every channel on its own)
+    let mut frame = [0.0; 2];
+    resampler.next_frame(&mut frame, &mut render);
+    assert_eq!(frame, [2.5, -2.5]);
+}
@@ -0,0 +1,881 @@
+use std::{
+    collections::HashMap,
+    ffi::{c_char, c_void, CStr, CString},
+    path::{Path, PathBuf},
+    ptr,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex, OnceLock, Weak,
+    },
+};
+
+use clap_sys::{
+    audio_buffer::clap_audio_buffer,
+    entry::clap_plugin_entry,
+    events::{
+        clap_event_header, clap_event_param_value, clap_input_events, clap_output_events,
+        CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_PARAM_VALUE,
+    },
+    ext::{
+        audio_ports::{clap_audio_port_info, clap_plugin_audio_ports, CLAP_EXT_AUDIO_PORTS},
+        gui::{clap_host_gui, clap_plugin_gui, CLAP_EXT_GUI},
+        params::{clap_param_info, clap_plugin_params, CLAP_EXT_PARAMS},
+    },
+    factory::plugin_factory::{clap_plugin_factory, CLAP_PLUGIN_FACTORY_ID},
+    host::clap_host,
+    id::clap_id,
+    plugin::clap_plugin,
+    process::clap_process,
+    version::CLAP_VERSION,
+};
+use libloading::Library;
+
+use crate::{
+    bus::Routing,
+    midi::{MidiEvent, Tuning},
+    modulation::Modulation,
+    node::AudioNode,
+    realtime, SAMPLE_RATE,
+};
+
+/// Plugins process blocks of this many samples, so what they play is this much later (about 1.5ms)
+const BLOCK: usize = 64;
+/// (no plugin gets more input or output channels than this, so they fit in an array on the audio thread)
+const MAX_CHANNELS: usize = 8;
+
+#[cfg(target_os = "macos")]
+const WINDOW_API: &CStr = clap_sys::ext::gui::CLAP_WINDOW_API_COCOA;
+#[cfg(target_os = "windows")]
+const WINDOW_API: &CStr = clap_sys::ext::gui::CLAP_WINDOW_API_WIN32;
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+const WINDOW_API: &CStr = clap_sys::ext::gui::CLAP_WINDOW_API_X11;
+
+/**
+    A CLAP plugin that's installed
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PluginInfo {
+    pub id: String,
+    pub name: String,
+    pub vendor: String,
+    /// (the `.clap` file, or bundle, that it's in)
+    pub path: PathBuf,
+}
+
+/**
+    All CLAP plugins that are installed where CLAP says to look (and in the `CLAP_PATH` directories), as they were the first time this was asked, since finding out loads every one of them
+*/
+pub fn installed_plugins() -> Vec<PluginInfo> {
+    static INSTALLED: OnceLock<Vec<PluginInfo>> = OnceLock::new();
+    INSTALLED.get_or_init(scan).clone()
+}
+
+fn scan() -> Vec<PluginInfo> {
+    let mut paths = vec![];
+    for dir in search_paths() {
+        find_bundles(&dir, &mut paths);
+    }
+
+    paths
+        .into_iter()
+        .flat_map(|path| {
+            Bundle::load(&path)
+                .map(|bundle| bundle.descriptors(&path))
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+fn search_paths() -> Vec<PathBuf> {
+    let mut dirs = std::env::var_os("CLAP_PATH")
+        .map(|paths| std::env::split_paths(&paths).collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+
+    if cfg!(target_os = "macos") {
+        dirs.extend(home.map(|home| home.join("Library/Audio/Plug-Ins/CLAP")));
+        dirs.push("/Library/Audio/Plug-Ins/CLAP".into());
+    } else if cfg!(target_os = "windows") {
+        dirs.extend(
+            std::env::var_os("COMMONPROGRAMFILES").map(|dir| PathBuf::from(dir).join("CLAP")),
+        );
+        dirs.extend(
+            std::env::var_os("LOCALAPPDATA")
+                .map(|dir| PathBuf::from(dir).join("Programs/Common/CLAP")),
+        );
+    } else {
+        dirs.extend(home.map(|home| home.join(".clap")));
+        dirs.push("/usr/lib/clap".into());
+    }
+
+    dirs
+}
+
+fn find_bundles(dir: &Path, found: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for path in entries.flatten().map(|entry| entry.path()) {
+        if path.extension().is_some_and(|ext| ext == "clap") {
+            found.push(path);
+        } else if path.is_dir() {
+            find_bundles(&path, found);
+        }
+    }
+}
+
+/**
+    A `.clap` file (or on macOS, bundle) that's loaded and initialized, once, for however many plugins are made from it
+*/
+struct Bundle {
+    entry: *const clap_plugin_entry,
+    // (last, so that it's unloaded after the entry's deinitialized)
+    _library: Library,
+}
+
+// (the entry's functions can be called from any thread)
+unsafe impl Send for Bundle {}
+unsafe impl Sync for Bundle {}
+
+/// (so that a bundle isn't initialized again while it's still in use)
+static BUNDLES: Mutex<Vec<(PathBuf, Weak<Bundle>)>> = Mutex::new(vec![]);
+
+impl Bundle {
+    fn load(path: &Path) -> Result<Arc<Self>, String> {
+        let mut bundles = realtime::lock(&BUNDLES).unwrap();
+        bundles.retain(|(_, bundle)| bundle.strong_count() > 0);
+
+        if let Some(bundle) = bundles
+            .iter()
+            .find(|(loaded, _)| loaded == path)
+            .and_then(|(_, bundle)| bundle.upgrade())
+        {
+            return Ok(bundle);
+        }
+
+        // (on macOS, the library is in the bundle)
+        let binary = match path.file_stem() {
+            Some(name) if path.is_dir() => path.join("Contents/MacOS").join(name),
+            _ => path.to_path_buf(),
+        };
+
+        let library = unsafe { Library::new(&binary) }.map_err(|e| e.to_string())?;
+        let entry = unsafe { library.get::<*const clap_plugin_entry>(b"clap_entry\0") }
+            .map(|symbol| *symbol)
+            .map_err(|e| e.to_string())?;
+
+        let path_str =
+            CString::new(path.to_string_lossy().as_bytes()).map_err(|e| e.to_string())?;
+        let initialized = unsafe { (*entry).init.is_some_and(|init| init(path_str.as_ptr())) };
+        if !initialized {
+            return Err(format!("{} didn't initialize", path.display()));
+        }
+
+        let bundle = Arc::new(Self {
+            entry,
+            _library: library,
+        });
+        bundles.push((path.to_path_buf(), Arc::downgrade(&bundle)));
+
+        Ok(bundle)
+    }
+
+    fn factory(&self) -> Option<&clap_plugin_factory> {
+        let get_factory = unsafe { (*self.entry).get_factory }?;
+        let factory =
+            unsafe { get_factory(CLAP_PLUGIN_FACTORY_ID.as_ptr()) } as *const clap_plugin_factory;
+        unsafe { factory.as_ref() }
+    }
+
+    fn descriptors(&self, path: &Path) -> Vec<PluginInfo> {
+        let Some(factory) = self.factory() else {
+            return vec![];
+        };
+        let (Some(count), Some(descriptor)) =
+            (factory.get_plugin_count, factory.get_plugin_descriptor)
+        else {
+            return vec![];
+        };
+
+        (0..unsafe { count(factory) })
+            .filter_map(|i| unsafe { descriptor(factory, i).as_ref() })
+            .map(|descriptor| PluginInfo {
+                id: c_string(descriptor.id),
+                name: c_string(descriptor.name),
+                vendor: c_string(descriptor.vendor),
+                path: path.to_path_buf(),
+            })
+            .collect()
+    }
+}
+
+impl Drop for Bundle {
+    fn drop(&mut self) {
+        if let Some(deinit) = unsafe { (*self.entry).deinit } {
+            unsafe { deinit() };
+        }
+    }
+}
+
+fn c_string(ptr: *const c_char) -> String {
+    if ptr.is_null() {
+        return String::new();
+    }
+    unsafe { CStr::from_ptr(ptr) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+/**
+    A parameter name the way the language can write it: "Room Size" is `room_size`, and "Dry/Wet" is `dry_wet`
+*/
+fn param_name(name: &str) -> String {
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+static HOST_GUI: clap_host_gui = clap_host_gui {
+    resize_hints_changed: Some(host_nothing),
+    request_resize: Some(host_request_resize),
+    request_show: Some(host_no),
+    request_hide: Some(host_no),
+    closed: Some(host_gui_closed),
+};
+
+unsafe extern "C" fn host_extension(_: *const clap_host, id: *const c_char) -> *const c_void {
+    if !id.is_null() && unsafe { CStr::from_ptr(id) } == CLAP_EXT_GUI {
+        &HOST_GUI as *const clap_host_gui as *const c_void
+    } else {
+        ptr::null()
+    }
+}
+
+// (the engine processes every plugin all the time anyway, and doesn't restart them or call them back on the main thread)
+unsafe extern "C" fn host_nothing(_: *const clap_host) {}
+
+unsafe extern "C" fn host_no(_: *const clap_host) -> bool {
+    false
+}
+
+unsafe extern "C" fn host_request_resize(_: *const clap_host, _: u32, _: u32) -> bool {
+    // (its window is its own, so it can just resize it)
+    true
+}
+
+unsafe extern "C" fn host_gui_closed(host: *const clap_host, was_destroyed: bool) {
+    if was_destroyed {
+        let editor = unsafe { &*((*host).host_data as *const AtomicBool) };
+        editor.store(false, Ordering::Relaxed);
+    }
+}
+
+/**
+    A plugin instance, shared by the node that plays it (on the audio thread) and whoever opens its editor (on the main thread), which CLAP allows at the same time. It's activated for the engine's sample rate as soon as it's made, and destroyed with the last node that played it, wherever that happens.
+*/
+pub(crate) struct Instance {
+    plugin: *const clap_plugin,
+    id: String,
+    name: String,
+    activated: bool,
+    processing: AtomicBool,
+    // (which of the nodes that played it plays it now, see `Plugin::new`)
+    owner: AtomicU64,
+    // (whether its editor was made, and not destroyed since, which the plugin tells the host through `host_data`)
+    editor: Box<AtomicBool>,
+    // (the plugin keeps pointing at these)
+    _host: Box<clap_host>,
+    _bundle: Arc<Bundle>,
+}
+
+unsafe impl Send for Instance {}
+unsafe impl Sync for Instance {}
+
+impl Instance {
+    fn new(info: &PluginInfo) -> Result<Self, String> {
+        let bundle = Bundle::load(&info.path)?;
+        let factory = bundle
+            .factory()
+            .ok_or_else(|| format!("{} has no plugins", info.path.display()))?;
+
+        let editor = Box::new(AtomicBool::new(false));
+        let host = Box::new(clap_host {
+            clap_version: CLAP_VERSION,
+            host_data: &*editor as *const AtomicBool as *mut c_void,
+            name: c"live".as_ptr(),
+            vendor: c"".as_ptr(),
+            url: c"".as_ptr(),
+            version: c"0.1.0".as_ptr(),
+            get_extension: Some(host_extension),
+            request_restart: Some(host_nothing),
+            request_process: Some(host_nothing),
+            request_callback: Some(host_nothing),
+        });
+
+        let id = CString::new(info.id.as_str()).map_err(|e| e.to_string())?;
+        let plugin = match factory.create_plugin {
+            Some(create) => unsafe { create(factory, &*host, id.as_ptr()) },
+            None => ptr::null(),
+        };
+        if plugin.is_null() {
+            return Err(format!("couldn't make a {}", info.name));
+        }
+
+        // (from here on, dropping it cleans up)
+        let mut instance = Self {
+            plugin,
+            id: info.id.clone(),
+            name: info.name.clone(),
+            activated: false,
+            processing: AtomicBool::new(false),
+            owner: AtomicU64::new(0),
+            editor,
+            _host: host,
+            _bundle: bundle,
+        };
+
+        let plugin = instance.plugin();
+        if !unsafe { plugin.init.is_some_and(|init| init(plugin)) } {
+            return Err(format!("{} didn't initialize", info.name));
+        }
+
+        instance.activated = unsafe {
+            plugin
+                .activate
+                .is_some_and(|activate| activate(plugin, SAMPLE_RATE as f64, 1, BLOCK as u32))
+        };
+        if !instance.activated {
+            return Err(format!("{} didn't activate", info.name));
+        }
+
+        Ok(instance)
+    }
+
+    fn plugin(&self) -> &clap_plugin {
+        unsafe { &*self.plugin }
+    }
+
+    fn extension<T>(&self, id: &CStr) -> Option<&T> {
+        let plugin = self.plugin();
+        let extension = unsafe { plugin.get_extension?(plugin, id.as_ptr()) } as *const T;
+        unsafe { extension.as_ref() }
+    }
+
+    /**
+        How many channels its main input and output have (none, for instruments), which can be more than the engine has room for
+    */
+    fn channels(&self) -> (usize, usize) {
+        let Some(ports) = self.extension::<clap_plugin_audio_ports>(CLAP_EXT_AUDIO_PORTS) else {
+            return (2, 2);
+        };
+
+        let main = |is_input: bool| {
+            let plugin = self.plugin();
+            let count = ports
+                .count
+                .map_or(0, |count| unsafe { count(plugin, is_input) });
+            if count == 0 {
+                return 0;
+            }
+
+            let mut info = clap_audio_port_info {
+                id: 0,
+                name: [0; clap_sys::string_sizes::CLAP_NAME_SIZE],
+                flags: 0,
+                channel_count: 0,
+                port_type: ptr::null(),
+                in_place_pair: 0,
+            };
+            let got = ports
+                .get
+                .is_some_and(|get| unsafe { get(plugin, 0, is_input, &mut info) });
+
+            if got {
+                info.channel_count as usize
+            } else {
+                0
+            }
+        };
+
+        (main(true), main(false))
+    }
+
+    /**
+        Its parameters, by the names the language knows them by (see `param_name`), with their id, range and default
+    */
+    fn params(&self) -> Vec<Param> {
+        let Some(params) = self.extension::<clap_plugin_params>(CLAP_EXT_PARAMS) else {
+            return vec![];
+        };
+        let (Some(count), Some(get_info)) = (params.count, params.get_info) else {
+            return vec![];
+        };
+
+        let plugin = self.plugin();
+        (0..unsafe { count(plugin) })
+            .filter_map(|i| {
+                let mut info = clap_param_info {
+                    id: 0,
+                    flags: 0,
+                    cookie: ptr::null_mut(),
+                    name: [0; clap_sys::string_sizes::CLAP_NAME_SIZE],
+                    module: [0; clap_sys::string_sizes::CLAP_PATH_SIZE],
+                    min_value: 0.0,
+                    max_value: 0.0,
+                    default_value: 0.0,
+                };
+                unsafe { get_info(plugin, i, &mut info) }.then(|| Param {
+                    name: param_name(&c_string(info.name.as_ptr())),
+                    id: info.id,
+                    range: (info.min_value, info.max_value),
+                    modulation: Modulation::Constant(info.default_value as f32),
+                    value: info.default_value,
+                    sent: info.default_value,
+                })
+            })
+            .collect()
+    }
+
+    /**
+        Opens its editor in a window of its own (on the main thread, like everything that has to do with its UI)
+    */
+    fn show_editor(&self) -> Result<(), String> {
+        let gui = self
+            .extension::<clap_plugin_gui>(CLAP_EXT_GUI)
+            .ok_or_else(|| format!("{} has no editor", self.name))?;
+        let plugin = self.plugin();
+
+        if !self.editor.load(Ordering::Relaxed) {
+            let floating = gui
+                .is_api_supported
+                .is_some_and(|supported| unsafe { supported(plugin, WINDOW_API.as_ptr(), true) });
+            if !floating {
+                return Err(format!(
+                    "{}'s editor can't open in a window of its own",
+                    self.name
+                ));
+            }
+
+            let created = gui
+                .create
+                .is_some_and(|create| unsafe { create(plugin, WINDOW_API.as_ptr(), true) });
+            if !created {
+                return Err(format!("{}'s editor didn't open", self.name));
+            }
+            self.editor.store(true, Ordering::Relaxed);
+
+            if let (Some(suggest_title), Ok(title)) =
+                (gui.suggest_title, CString::new(self.name.as_str()))
+            {
+                unsafe { suggest_title(plugin, title.as_ptr()) };
+            }
+        }
+
+        if gui.show.is_some_and(|show| unsafe { show(plugin) }) {
+            Ok(())
+        } else {
+            Err(format!("{}'s editor didn't show", self.name))
+        }
+    }
+}
+
+impl Drop for Instance {
+    fn drop(&mut self) {
+        let plugin = self.plugin();
+
+        let destroy_editor = self
+            .extension::<clap_plugin_gui>(CLAP_EXT_GUI)
+            .and_then(|gui| gui.destroy)
+            .filter(|_| self.editor.load(Ordering::Relaxed));
+        if let Some(destroy_editor) = destroy_editor {
+            unsafe { destroy_editor(plugin) };
+        }
+        if let Some(stop) = plugin
+            .stop_processing
+            .filter(|_| self.processing.load(Ordering::Relaxed))
+        {
+            unsafe { stop(plugin) };
+        }
+        if let Some(deactivate) = plugin.deactivate.filter(|_| self.activated) {
+            unsafe { deactivate(plugin) };
+        }
+        if let Some(destroy) = plugin.destroy {
+            unsafe { destroy(plugin) };
+        }
+    }
+}
+
+/**
+    The plugins that are playing, so that their editors can be opened, and by where they're played in the code, if they are, so that they keep playing there when the code changes. (They're only referred to weakly, so that a plugin is destroyed when what plays it is.)
+*/
+#[derive(Clone, Default)]
+pub(crate) struct Plugins(Arc<Mutex<Vec<Played>>>);
+
+/// (where it's played, if it's known, and the plugin)
+type Played = (Option<String>, Weak<Instance>);
+
+impl Plugins {
+    /**
+        The plugin that's played where it's played, if that's still the same one, or otherwise a new one
+    */
+    fn instance(&self, key: Option<&str>, info: &PluginInfo) -> Result<Arc<Instance>, String> {
+        let mut plugins = realtime::lock(&self.0).unwrap();
+        plugins.retain(|(_, plugin)| plugin.strong_count() > 0);
+
+        let playing = plugins
+            .iter()
+            .filter(|(played, _)| key.is_some() && played.as_deref() == key)
+            .filter_map(|(_, plugin)| plugin.upgrade())
+            .find(|instance| instance.id == info.id);
+        if let Some(instance) = playing {
+            return Ok(instance);
+        }
+
+        let instance = Arc::new(Instance::new(info)?);
+        plugins.push((key.map(String::from), Arc::downgrade(&instance)));
+        Ok(instance)
+    }
+
+    fn running(&self) -> Vec<Arc<Instance>> {
+        let plugins = realtime::lock(&self.0).unwrap();
+        plugins
+            .iter()
+            .filter_map(|(_, plugin)| plugin.upgrade())
+            .collect()
+    }
+
+    /**
+        The names of the plugins that are playing, in the order they were made
+    */
+    pub(crate) fn names(&self) -> Vec<String> {
+        self.running()
+            .iter()
+            .map(|instance| instance.name.clone())
+            .collect()
+    }
+
+    pub(crate) fn show_editor(&self, i: usize) -> Result<(), String> {
+        let instance = self
+            .running()
+            .get(i)
+            .cloned()
+            .ok_or_else(|| "that plugin isn't playing anymore".to_string())?;
+
+        instance.show_editor()
+    }
+}
+
+struct Param {
+    name: String,
+    id: clap_id,
+    range: (f64, f64),
+    modulation: Modulation,
+    // (what it is this sample, and what the plugin was last told)
+    value: f64,
+    sent: f64,
+}
+
+/**
+    A CLAP plugin processing the output of another node, like `plugin{size = .8}("TAL Reverb", pad)` in the language. Its parameters are set and modulated like those of the built-in effects, by name (see `param_name`), in the plugin's own units.
+
+    It plays in mono, like the rest of the engine: the input goes to every input channel, and the output channels are mixed down.
+*/
+pub struct Plugin {
+    instance: Arc<Instance>,
+    // (see `Instance::owner`)
+    owner: u64,
+    input: Box<dyn AudioNode + Send>,
+    params: Vec<Param>,
+
+    // audio node helper stuff
+    named_parameters: HashMap<String, String>,
+
+    // state: the block that's being filled with input, the one the plugin processed last (which is what's played now), and where in them we are
+    inputs: Vec<[f32; BLOCK]>,
+    outputs: Vec<[f32; BLOCK]>,
+    pos: usize,
+    events: Vec<clap_event_param_value>,
+    steady_time: i64,
+    out: f32,
+}
+
+impl Plugin {
+    /**
+        Makes the installed plugin with that name (or id), activated and all, and adds it to what's playing, for its editor. That's what takes long, so it's best done before it's played.
+
+        Where (`key`) the same plugin was played already, that one plays on instead, with its state, and its editor open if it was, and the node that played it before goes quiet.
+    */
+    pub(crate) fn new(
+        key: Option<&str>,
+        name: &str,
+        input: Box<dyn AudioNode + Send>,
+        plugins: &Plugins,
+    ) -> Result<Self, String> {
+        let info = installed_plugins()
+            .into_iter()
+            .find(|info| info.name.eq_ignore_ascii_case(name) || info.id == name)
+            .ok_or_else(|| format!("{} isn't installed", name))?;
+
+        let instance = plugins.instance(key, &info)?;
+
+        let (inputs, outputs) = instance.channels();
+        if inputs > MAX_CHANNELS || outputs > MAX_CHANNELS {
+            return Err(format!(
+                "{} has more channels than can be played ({})",
+                info.name, MAX_CHANNELS
+            ));
+        }
+        let params = instance.params();
+        let owner = instance.owner.fetch_add(1, Ordering::Relaxed) + 1;
+
+        Ok(Self {
+            owner,
+            input,
+            named_parameters: HashMap::new(),
+            inputs: vec![[0.0; BLOCK]; inputs],
+            outputs: vec![[0.0; BLOCK]; outputs],
+            pos: 0,
+            events: Vec::with_capacity(params.len()),
+            params,
+            steady_time: 0,
+            out: 0.0,
+            instance,
+        })
+    }
+
+    /**
+        Sets a parameter to a value, or modulates it. Unknown parameters are ignored, like with `apply`.
+    */
+    pub fn with(mut self, param: &str, modulation: impl Into<Modulation>) -> Self {
+        if let Some(param) = self.params.iter_mut().find(|p| p.name == param) {
+            param.modulation = modulation.into();
+        }
+        self
+    }
+
+    fn process(&mut self) {
+        let plugin = self.instance.plugin();
+
+        // (a node that plays it since is what processes it now)
+        if self.instance.owner.load(Ordering::Relaxed) != self.owner {
+            for channel in &mut self.outputs {
+                channel.fill(0.0);
+            }
+            return;
+        }
+
+        if !self.instance.processing.load(Ordering::Relaxed) {
+            let started = plugin
+                .start_processing
+                .is_some_and(|start| unsafe { start(plugin) });
+            self.instance.processing.store(started, Ordering::Relaxed);
+            if !started {
+                return;
+            }
+        }
+
+        // (what the parameters are at the start of the block, if that's news)
+        self.events.clear();
+        for param in &mut self.params {
+            if param.value != param.sent {
+                param.sent = param.value;
+                self.events.push(clap_event_param_value {
+                    header: clap_event_header {
+                        size: std::mem::size_of::<clap_event_param_value>() as u32,
+                        time: 0,
+                        space_id: CLAP_CORE_EVENT_SPACE_ID,
+                        type_: CLAP_EVENT_PARAM_VALUE,
+                        flags: 0,
+                    },
+                    param_id: param.id,
+                    cookie: ptr::null_mut(),
+                    note_id: -1,
+                    port_index: -1,
+                    channel: -1,
+                    key: -1,
+                    value: param.value,
+                });
+            }
+        }
+
+        let in_events = clap_input_events {
+            ctx: &self.events as *const Vec<clap_event_param_value> as *mut c_void,
+            size: Some(events_size),
+            get: Some(events_get),
+        };
+        let out_events = clap_output_events {
+            ctx: ptr::null_mut(),
+            try_push: Some(events_ignore),
+        };
+
+        let mut inputs = [ptr::null_mut(); MAX_CHANNELS];
+        for (ptr, channel) in inputs.iter_mut().zip(&mut self.inputs) {
+            *ptr = channel.as_mut_ptr();
+        }
+        let mut outputs = [ptr::null_mut(); MAX_CHANNELS];
+        for (ptr, channel) in outputs.iter_mut().zip(&mut self.outputs) {
+            *ptr = channel.as_mut_ptr();
+        }
+
+        let audio_input = clap_audio_buffer {
+            data32: inputs.as_mut_ptr(),
+            data64: ptr::null_mut(),
+            channel_count: self.inputs.len() as u32,
+            latency: 0,
+            constant_mask: 0,
+        };
+        let mut audio_output = clap_audio_buffer {
+            data32: outputs.as_mut_ptr(),
+            data64: ptr::null_mut(),
+            channel_count: self.outputs.len() as u32,
+            latency: 0,
+            constant_mask: 0,
+        };
+
+        let process = clap_process {
+            steady_time: self.steady_time,
+            frames_count: BLOCK as u32,
+            transport: ptr::null(),
+            audio_inputs: &audio_input,
+            audio_outputs: &mut audio_output,
+            audio_inputs_count: (!self.inputs.is_empty()) as u32,
+            audio_outputs_count: (!self.outputs.is_empty()) as u32,
+            in_events: &in_events,
+            out_events: &out_events,
+        };
+
+        if let Some(process_block) = plugin.process {
+            unsafe { process_block(plugin, &process) };
+        }
+        self.steady_time += BLOCK as i64;
+    }
+}
+
+unsafe extern "C" fn events_size(list: *const clap_input_events) -> u32 {
+    let events = unsafe { &*((*list).ctx as *const Vec<clap_event_param_value>) };
+    events.len() as u32
+}
+
+unsafe extern "C" fn events_get(
+    list: *const clap_input_events,
+    i: u32,
+) -> *const clap_event_header {
+    let events = unsafe { &*((*list).ctx as *const Vec<clap_event_param_value>) };
+    events.get(i as usize).map_or(ptr::null(), |event| {
+        &event.header as *const clap_event_header
+    })
+}
+
+// (what the plugin says back, like parameter changes from its editor, isn't used)
+unsafe extern "C" fn events_ignore(
+    _: *const clap_output_events,
+    _: *const clap_event_header,
+) -> bool {
+    true
+}
+
+impl AudioNode for Plugin {
+    fn parameters(&self) -> Vec<String> {
+        self.params.iter().map(|param| param.name.clone()).collect()
+    }
+
+    fn map(&mut self, name: String, parameter: String) {
+        self.named_parameters.insert(name, parameter);
+    }
+
+    fn apply(&mut self, param: &str, value: f32) {
+        // (the input and the modulations might know it)
+        self.input.apply(param, value);
+        for p in &mut self.params {
+            p.modulation.apply(param, value);
+        }
+
+        let param = self
+            .named_parameters
+            .get(param)
+            .map_or(param, |actual| actual.as_str());
+
+        if let Some(p) = self.params.iter_mut().find(|p| p.name == param) {
+            p.modulation = Modulation::Constant(value);
+        }
+    }
+
+    fn note(&mut self, event: MidiEvent) {
+        self.input.note(event);
+        for param in &mut self.params {
+            if let Modulation::Signal(node) = &mut param.modulation {
+                node.note(event);
+            }
+        }
+    }
+
+    fn retune(&mut self, tuning: &Tuning) {
+        self.input.retune(tuning);
+        for param in &mut self.params {
+            if let Modulation::Signal(node) = &mut param.modulation {
+                node.retune(tuning);
+            }
+        }
+    }
+
+    fn route(&self, routing: &mut Routing) {
+        self.input.route(routing);
+        for param in &self.params {
+            param.modulation.route(routing);
+        }
+    }
+
+    fn economize(&mut self, economize: bool) {
+        self.input.economize(economize);
+        for param in &mut self.params {
+            param.modulation.economize(economize);
+        }
+    }
+
+    fn tick(&mut self) {
+        self.input.tick();
+
+        for param in &mut self.params {
+            let (min, max) = param.range;
+            param.value = (param.modulation.tick() as f64).clamp(min, max.max(min));
+        }
+
+        let x = self.input.get_next_sample();
+        for channel in &mut self.inputs {
+            channel[self.pos] = x;
+        }
+
+        // (what it processed a block ago)
+        self.out = match self.outputs.len() {
+            0 => 0.0,
+            channels => {
+                self.outputs
+                    .iter()
+                    .map(|channel| channel[self.pos])
+                    .sum::<f32>()
+                    / channels as f32
+            }
+        };
+
+        self.pos += 1;
+        if self.pos == BLOCK {
+            self.pos = 0;
+            self.process();
+        }
+    }
+
+    fn get_next_sample(&self) -> f32 {
+        self.out
+    }
+}
+
+#[test]
+fn test_param_name() {
+    assert_eq!(param_name("Room Size"), "room_size");
+    assert_eq!(param_name("Dry/Wet"), "dry_wet");
+    assert_eq!(param_name(" Size "), "size");
+}
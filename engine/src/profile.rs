@@ -0,0 +1,120 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::{realtime::permit, SAMPLE_RATE};
+
+/// Only every this many samples is it measured how long every target takes (measuring every sample would cost more than some of them do)
+const PROFILE_EVERY: u64 = 16;
+/// Above this much callback load, nodes are asked to economize (when that's turned on)
+pub(crate) const ECONOMIZE_LOAD: f32 = 0.9;
+/// And once they are, they only stop below this much (so it doesn't flip back and forth)
+pub(crate) const RELAXED_LOAD: f32 = 0.6;
+
+/**
+    What the engine spends its time on, and whether it's keeping up
+*/
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Costs {
+    /// The share of the time there is to render a sample that every target takes, per declaration name (so together they'd be 1.0, at the engine's limit), over the last second
+    pub per_target: HashMap<String, f32>,
+    /// How many blocks the output callback didn't render in time since the engine started (every one of them is a dropout)
+    pub overruns: usize,
+    /// Whether the nodes are economizing (see `AudioNode::economize`) because the engine couldn't keep up
+    pub economizing: bool,
+}
+
+impl Costs {
+    /**
+        The most expensive targets first
+    */
+    pub fn most_expensive(&self) -> Vec<(&str, f32)> {
+        let mut costs = self
+            .per_target
+            .iter()
+            .map(|(name, &cost)| (name.as_str(), cost))
+            .collect::<Vec<_>>();
+
+        costs.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(b.0)));
+        costs
+    }
+}
+
+pub(crate) type SharedCosts = Arc<Mutex<Costs>>;
+
+/**
+    Adds up how long every target takes, on the audio thread, in windows of a second (like `EventRate`)
+*/
+#[derive(Debug, Default)]
+pub(crate) struct Profiler {
+    window_started: u64,
+    spent: HashMap<String, Duration>,
+    // (how many samples were measured in this window, and which is the next one to measure)
+    profiled: u64,
+    next_profiled: u64,
+}
+
+impl Profiler {
+    /**
+        Whether to measure this sample, counting it if so
+    */
+    pub fn profiling(&mut self, clock: u64) -> bool {
+        let profiling = clock >= self.next_profiled;
+        if profiling {
+            self.profiled += 1;
+            self.next_profiled = clock + PROFILE_EVERY;
+        }
+        profiling
+    }
+
+    pub fn spent(&mut self, target: &str, took: Duration) {
+        match self.spent.get_mut(target) {
+            Some(spent) => *spent += took,
+            // (the first time a target is profiled this second)
+            None => {
+                permit(|| self.spent.insert(target.to_string(), took));
+            }
+        }
+    }
+
+    /// Whether the window's over (every second)
+    pub fn is_due(&self, clock: u64) -> bool {
+        clock - self.window_started >= SAMPLE_RATE as u64
+    }
+
+    /**
+        Starts a new window, returning what every target cost in the one that ended (which allocates, so only when it's due)
+    */
+    pub fn tick(&mut self, clock: u64) -> HashMap<String, f32> {
+        self.window_started = clock;
+        let budget = std::mem::take(&mut self.profiled) as f32 / SAMPLE_RATE as f32;
+
+        self.spent
+            .drain()
+            .filter(|_| budget > 0.0)
+            .map(|(target, spent)| (target, spent.as_secs_f32() / budget))
+            .collect()
+    }
+}
+
+#[test]
+fn test_profiler() {
+    let mut profiler = Profiler::default();
+
+    for clock in 0..SAMPLE_RATE as u64 {
+        if profiler.profiling(clock) {
+            // (that's a tenth of the time there is per sample)
+            profiler.spent("pad", Duration::from_secs_f64(0.1 / SAMPLE_RATE as f64));
+        }
+        assert!(!profiler.is_due(clock));
+    }
+
+    assert!(profiler.is_due(SAMPLE_RATE as u64));
+    let costs = profiler.tick(SAMPLE_RATE as u64);
+    assert!((costs["pad"] - 0.1).abs() < 0.001);
+
+    // (and a new window starts from scratch)
+    assert_eq!(profiler.tick(SAMPLE_RATE as u64 * 2), HashMap::new());
+}
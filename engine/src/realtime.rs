@@ -0,0 +1,245 @@
+use std::{
+    alloc::{GlobalAlloc, Layout},
+    cell::{Cell, UnsafeCell},
+    mem::MaybeUninit,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, LockResult, Mutex, MutexGuard,
+    },
+};
+
+/**
+    A queue from one thread to one other (a single producer and a single consumer), that doesn't lock or allocate once it's made: both ends only agree through two atomic counters, of how many items were pushed and how many were popped. It holds at most `capacity` items, after which pushing fails, and the item is handed back.
+*/
+pub(crate) fn queue<T: Send>(capacity: usize) -> (Producer<T>, Consumer<T>) {
+    let ring = Arc::new(Ring {
+        slots: (0..capacity.max(1))
+            .map(|_| UnsafeCell::new(MaybeUninit::uninit()))
+            .collect(),
+        pushed: AtomicUsize::new(0),
+        popped: AtomicUsize::new(0),
+    });
+
+    (Producer { ring: ring.clone() }, Consumer { ring })
+}
+
+struct Ring<T> {
+    slots: Box<[UnsafeCell<MaybeUninit<T>>]>,
+    pushed: AtomicUsize,
+    popped: AtomicUsize,
+}
+
+// (a slot is only ever touched by the producer before it's pushed, and by the consumer after, never both)
+unsafe impl<T: Send> Sync for Ring<T> {}
+
+impl<T> Drop for Ring<T> {
+    fn drop(&mut self) {
+        let pushed = *self.pushed.get_mut();
+        let popped = *self.popped.get_mut();
+
+        for i in popped..pushed {
+            let slot = &mut self.slots[i % self.slots.len()];
+            unsafe { slot.get_mut().assume_init_drop() };
+        }
+    }
+}
+
+pub(crate) struct Producer<T> {
+    ring: Arc<Ring<T>>,
+}
+
+impl<T> Producer<T> {
+    pub fn push(&mut self, item: T) -> Result<(), T> {
+        let ring = &self.ring;
+        let pushed = ring.pushed.load(Ordering::Relaxed);
+        if pushed - ring.popped.load(Ordering::Acquire) == ring.slots.len() {
+            return Err(item);
+        }
+
+        unsafe { (*ring.slots[pushed % ring.slots.len()].get()).write(item) };
+        ring.pushed.store(pushed + 1, Ordering::Release);
+        Ok(())
+    }
+}
+
+pub(crate) struct Consumer<T> {
+    ring: Arc<Ring<T>>,
+}
+
+impl<T> Consumer<T> {
+    pub fn pop(&mut self) -> Option<T> {
+        let ring = &self.ring;
+        let popped = ring.popped.load(Ordering::Relaxed);
+        if popped == ring.pushed.load(Ordering::Acquire) {
+            return None;
+        }
+
+        let item = unsafe { (*ring.slots[popped % ring.slots.len()].get()).assume_init_read() };
+        ring.popped.store(popped + 1, Ordering::Release);
+        Some(item)
+    }
+}
+
+thread_local! {
+    static REALTIME: Cell<bool> = const { Cell::new(false) };
+    // (allocations and frees since it became realtime, that weren't permitted)
+    static VIOLATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+/**
+    Wraps an allocator (like `std::alloc::System`) to count what's allocated and freed on the audio thread, so that it can be asserted that nothing is (see `realtime`). It's only worth it in debug builds:
+
+    ```ignore
+    #[cfg(debug_assertions)]
+    #[global_allocator]
+    static ALLOCATOR: live_engine::RealtimeAllocator<std::alloc::System> =
+        live_engine::RealtimeAllocator(std::alloc::System);
+    ```
+*/
+pub struct RealtimeAllocator<A>(pub A);
+
+fn count() {
+    // (`try_with`, since the allocator is also called while the thread's locals are torn down)
+    let _ = REALTIME.try_with(|realtime| {
+        if realtime.get() {
+            VIOLATIONS.with(|violations| violations.set(violations.get() + 1));
+        }
+    });
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for RealtimeAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count();
+        unsafe { self.0.alloc(layout) }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        count();
+        unsafe { self.0.alloc_zeroed(layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count();
+        unsafe { self.0.realloc(ptr, layout, new_size) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        count();
+        unsafe { self.0.dealloc(ptr, layout) }
+    }
+}
+
+/**
+    The rules of the audio thread: it never waits for a lock (it only ever `try_lock`s), and it never allocates or frees (which can take a lock in the allocator, or just take long). What it gets from other threads comes in through lock-free queues, and what it's done with goes back out through one, to be freed elsewhere.
+
+    This marks this thread as the audio thread until it's dropped, when it asserts (in debug builds) that nothing was allocated or freed in the meantime, other than what was `permit`ted. (Only with the `RealtimeAllocator`, otherwise nobody's counting.)
+*/
+pub(crate) struct Realtime {
+    was: bool,
+}
+
+pub(crate) fn realtime() -> Realtime {
+    Realtime {
+        was: REALTIME.with(|realtime| realtime.replace(true)),
+    }
+}
+
+impl Drop for Realtime {
+    fn drop(&mut self) {
+        REALTIME.with(|realtime| realtime.set(self.was));
+        if self.was {
+            return;
+        }
+
+        let violations = VIOLATIONS.with(|violations| violations.replace(0));
+        // (not while it's panicking already, that would abort)
+        if !std::thread::panicking() {
+            debug_assert!(
+                violations == 0,
+                "allocated or freed {} times on the audio thread",
+                violations
+            );
+        }
+    }
+}
+
+pub(crate) fn is_realtime() -> bool {
+    REALTIME.with(|realtime| realtime.get())
+}
+
+/**
+    Allows the audio thread to allocate (and free), for what's known to, where it's rare enough to get away with it (like when a command comes in, or once a second), and it's not worth the trouble to do without. Every use says why.
+*/
+pub(crate) fn permit<T>(f: impl FnOnce() -> T) -> T {
+    let was = REALTIME.with(|realtime| realtime.replace(false));
+    let result = f();
+    REALTIME.with(|realtime| realtime.set(was));
+    result
+}
+
+/**
+    Locks a mutex, asserting (in debug builds) that it's not on the audio thread, which only ever `try_lock`s
+*/
+pub(crate) fn lock<T>(mutex: &Mutex<T>) -> LockResult<MutexGuard<'_, T>> {
+    debug_assert!(!is_realtime(), "locked a mutex on the audio thread");
+    mutex.lock()
+}
+
+#[test]
+fn test_queue() {
+    let (mut producer, mut consumer) = queue(2);
+    assert_eq!(consumer.pop(), None);
+
+    assert_eq!(producer.push("a".to_string()), Ok(()));
+    assert_eq!(producer.push("b".to_string()), Ok(()));
+    assert_eq!(producer.push("c".to_string()), Err("c".to_string()));
+    assert_eq!(consumer.pop().as_deref(), Some("a"));
+    assert_eq!(producer.push("c".to_string()), Ok(()));
+    assert_eq!(consumer.pop().as_deref(), Some("b"));
+    assert_eq!(consumer.pop().as_deref(), Some("c"));
+    assert_eq!(consumer.pop(), None);
+
+    // (what's still in it when it's dropped is dropped with it)
+    let item = Arc::new(());
+    let (mut producer, consumer) = queue(4);
+    producer.push(item.clone()).unwrap();
+    drop((producer, consumer));
+    assert_eq!(Arc::strong_count(&item), 1);
+
+    // (and across threads, everything arrives, in order)
+    let (mut producer, mut consumer) = queue(16);
+    let sender = std::thread::spawn(move || {
+        for i in 0..10_000 {
+            let mut item = i;
+            while let Err(back) = producer.push(item) {
+                item = back;
+                std::thread::yield_now();
+            }
+        }
+    });
+    let mut received = 0;
+    while received < 10_000 {
+        if let Some(i) = consumer.pop() {
+            assert_eq!(i, received);
+            received += 1;
+        }
+    }
+    sender.join().unwrap();
+}
+
+#[test]
+fn test_realtime() {
+    let guard = realtime();
+    assert!(is_realtime());
+    let permitted = permit(|| vec![1, 2, 3]);
+    assert!(is_realtime());
+    permit(|| drop(permitted));
+    drop(guard);
+    assert!(!is_realtime());
+
+    let result = std::panic::catch_unwind(|| {
+        let _realtime = realtime();
+        let _ = std::hint::black_box(vec![1, 2, 3]);
+    });
+    assert!(result.is_err());
+}
@@ -0,0 +1,375 @@
+use crate::{
+    bus::Routing,
+    midi::{MidiEvent, Tuning, MIDI_FREQ, MIDI_GATE, MIDI_PITCH, MIDI_VELOCITY},
+    node::AudioNode,
+    transport::{
+        Groove, Sometimes, TransportState, STEPS_PER_BEAT, TRANSPORT_BEAT, TRANSPORT_SWING,
+        TRANSPORT_TEMPO,
+    },
+    SAMPLE_RATE,
+};
+
+/**
+    A note that a pattern plays, placed on its step grid
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hit {
+    /// (in steps from the start of the pattern, which doesn't have to be on a step exactly)
+    pub start: f64,
+    /// (in steps)
+    pub length: f64,
+    pub note: u8,
+    pub velocity: f32,
+    /// Which of the pattern's signals it plays, or all of them
+    pub target: Option<usize>,
+}
+
+/**
+    Plays a pattern (a step sequence, or a melody) on the signals it's applied to, as notes (see `AudioNode::note`), looping, in time with the transport. That's how a sampler is triggered, and how a `Poly` plays its voices.
+
+    It keeps time itself, one sample at a time, from where the transport was when it started playing (see `TRANSPORT_BEAT`), at the transport's tempo and swing, which the engine applies to everything that's played. What it hears is the mix of its signals, from their first note on (so that a sample doesn't play by itself when it starts).
+
+    A signal that doesn't play notes itself hears them as the `midi.*` parameters, like it would from `midi_in`, so that `sin(midi.freq) * midi.gate` plays a melody (monophonically, where a `Poly` plays every note on a voice of its own).
+*/
+pub struct Sequencer {
+    steps: usize,
+    hits: Vec<Hit>,
+    targets: Vec<Box<dyn AudioNode + Send>>,
+    // (per hit)
+    grooves: Vec<Groove>,
+
+    // state
+    // (its tempo and swing, and where it is)
+    transport: TransportState,
+    // (when every hit starts and ends, in beats from the start of the pattern, and how hard it plays)
+    placed: Vec<(f64, f64, f32)>,
+    // (per signal, and the note it's holding, if any)
+    heard: Vec<bool>,
+    held: Vec<Option<u8>>,
+    // (what frequency every note is)
+    tuning: Tuning,
+    out: f32,
+}
+
+impl Sequencer {
+    /**
+        A pattern of so many steps, with its hits, and the signals they play
+    */
+    pub fn new(steps: usize, hits: Vec<Hit>, targets: Vec<Box<dyn AudioNode + Send>>) -> Self {
+        let mut sequencer = Self {
+            steps,
+            placed: vec![(0.0, 0.0, 0.0); hits.len()],
+            grooves: vec![Groove::default(); hits.len()],
+            hits,
+            heard: vec![false; targets.len()],
+            held: vec![None; targets.len()],
+            targets,
+            transport: TransportState::default(),
+            tuning: Tuning::default(),
+            out: 0.0,
+        };
+        sequencer.place();
+        sequencer
+    }
+
+    /// (a swing of its own, or humanized, see `Groove`)
+    pub fn groove(mut self, groove: Groove) -> Self {
+        self.grooves.fill(groove);
+        self.place();
+        self
+    }
+
+    /**
+        Plays some of its steps (see `Sometimes`) as another pattern does instead, like `beat.sometimes(.3, |p| p.humanize(20ms, .3))`, where that one plays on signals of its own
+    */
+    pub fn sometimes(self, other: Sequencer, sometimes: Sometimes) -> Self {
+        let applies = |hit: &Hit| sometimes.applies(hit.start.max(0.0) as usize);
+
+        // (a hit that plays all of either pattern's signals plays each of them, now that they're together)
+        let spread = |hit: Hit, targets: std::ops::Range<usize>| match hit.target {
+            Some(target) => vec![Hit {
+                target: Some(targets.start + target),
+                ..hit
+            }],
+            None => targets
+                .map(|target| Hit {
+                    target: Some(target),
+                    ..hit
+                })
+                .collect(),
+        };
+
+        let (own, others) = (
+            0..self.targets.len(),
+            self.targets.len()..self.targets.len() + other.targets.len(),
+        );
+        let (mut hits, mut grooves) = (vec![], vec![]);
+        for (hit, groove) in self.hits.into_iter().zip(self.grooves) {
+            if !applies(&hit) {
+                for hit in spread(hit, own.clone()) {
+                    hits.push(hit);
+                    grooves.push(groove);
+                }
+            }
+        }
+        for (hit, groove) in other.hits.into_iter().zip(other.grooves) {
+            if applies(&hit) {
+                for hit in spread(hit, others.clone()) {
+                    hits.push(hit);
+                    grooves.push(groove);
+                }
+            }
+        }
+
+        let mut targets = self.targets;
+        targets.extend(other.targets);
+        let mut sequencer = Sequencer::new(self.steps, hits, targets);
+        sequencer.grooves = grooves;
+        sequencer.place();
+        sequencer
+    }
+
+    /// (in beats)
+    fn length(&self) -> f64 {
+        self.steps as f64 / STEPS_PER_BEAT
+    }
+
+    /**
+        Places the hits in time (again, when the tempo or the swing changed), where they start as the groove places the step they're on, and last as long as they are
+    */
+    fn place(&mut self) {
+        let length = self.length();
+        if length <= 0.0 {
+            return;
+        }
+
+        for ((hit, groove), placed) in self.hits.iter().zip(&self.grooves).zip(&mut self.placed) {
+            let step = hit.start.max(0.0).floor();
+            let (beat, velocity) = groove.place(step as usize, hit.velocity, &self.transport);
+
+            let on = beat + (hit.start - step) / STEPS_PER_BEAT;
+            let off = on + hit.length.max(0.0) / STEPS_PER_BEAT;
+            *placed = (on.rem_euclid(length), off.rem_euclid(length), velocity);
+        }
+    }
+
+    fn play(&mut self, target: Option<usize>, event: MidiEvent) {
+        for (i, node) in self.targets.iter_mut().enumerate() {
+            if target.is_some_and(|target| target != i) {
+                continue;
+            }
+
+            node.note(event);
+            match event {
+                MidiEvent::NoteOn { note, velocity } => {
+                    node.apply(MIDI_PITCH, note as f32);
+                    if let Some(freq) = self.tuning.freq(note) {
+                        node.apply(MIDI_FREQ, freq);
+                    }
+                    node.apply(MIDI_VELOCITY, velocity);
+                    node.apply(MIDI_GATE, 1.0);
+                    self.heard[i] = true;
+                    self.held[i] = Some(note);
+                }
+                // (only the last note it played lets go, so that notes can overlap)
+                MidiEvent::NoteOff { note } if self.held[i] == Some(note) => {
+                    node.apply(MIDI_GATE, 0.0);
+                    self.held[i] = None;
+                }
+                MidiEvent::NoteOff { .. } => {}
+            }
+        }
+    }
+}
+
+impl AudioNode for Sequencer {
+    fn parameters(&self) -> Vec<String> {
+        vec![]
+    }
+
+    fn map(&mut self, _name: String, _parameter: String) {}
+
+    fn apply(&mut self, param: &str, value: f32) {
+        match param {
+            TRANSPORT_TEMPO => {
+                self.transport.tempo = value as f64;
+                self.place();
+            }
+            TRANSPORT_SWING => {
+                self.transport.swing = value as f64;
+                self.place();
+            }
+            TRANSPORT_BEAT => self.transport.beat = value as f64,
+            // (its signals hear its own notes instead of the ones from `midi_in`)
+            MIDI_FREQ | MIDI_PITCH | MIDI_VELOCITY | MIDI_GATE => return,
+            _ => {}
+        }
+
+        for target in &mut self.targets {
+            target.apply(param, value);
+        }
+    }
+
+    // (what its signals play is what the pattern plays, not the notes that come in)
+    fn note(&mut self, _event: MidiEvent) {}
+
+    fn retune(&mut self, tuning: &Tuning) {
+        self.tuning = tuning.clone();
+        for target in &mut self.targets {
+            target.retune(tuning);
+        }
+    }
+
+    fn route(&self, routing: &mut Routing) {
+        for target in &self.targets {
+            target.route(routing);
+        }
+    }
+
+    fn economize(&mut self, economize: bool) {
+        for target in &mut self.targets {
+            target.economize(economize);
+        }
+    }
+
+    fn tick(&mut self) {
+        let length = self.length();
+        let from = self.transport.beat;
+        self.transport.beat += self.transport.tempo / 60.0 / SAMPLE_RATE as f64;
+
+        if length > 0.0 {
+            let (from, to) = (
+                from.rem_euclid(length),
+                self.transport.beat.rem_euclid(length),
+            );
+            let crossed = |at: f64| match from <= to {
+                true => from <= at && at < to,
+                // (it looped around)
+                false => at >= from || at < to,
+            };
+
+            // (the notes that end first, so that a note that's as long as the pattern is played again)
+            for i in 0..self.hits.len() {
+                let (hit, (_, off, _)) = (self.hits[i], self.placed[i]);
+                if crossed(off) {
+                    self.play(hit.target, MidiEvent::NoteOff { note: hit.note });
+                }
+            }
+            for i in 0..self.hits.len() {
+                let (hit, (on, _, velocity)) = (self.hits[i], self.placed[i]);
+                if crossed(on) {
+                    self.play(
+                        hit.target,
+                        MidiEvent::NoteOn {
+                            note: hit.note,
+                            velocity,
+                        },
+                    );
+                }
+            }
+        }
+
+        let mut sum = 0.0;
+        for (target, heard) in self.targets.iter_mut().zip(&self.heard) {
+            target.tick();
+            if *heard {
+                sum += target.get_next_sample();
+            }
+        }
+        self.out = sum;
+    }
+
+    fn get_next_sample(&self) -> f32 {
+        self.out
+    }
+}
+
+/// (what it's played, by the sample it was played at)
+#[cfg(test)]
+#[derive(Default)]
+pub(crate) struct Notes {
+    ticks: usize,
+    pub played: std::sync::Arc<std::sync::Mutex<Vec<(usize, MidiEvent)>>>,
+}
+
+#[cfg(test)]
+impl AudioNode for Notes {
+    fn parameters(&self) -> Vec<String> {
+        vec![]
+    }
+
+    fn map(&mut self, _name: String, _parameter: String) {}
+
+    fn apply(&mut self, _param: &str, _value: f32) {}
+
+    fn note(&mut self, event: MidiEvent) {
+        self.played.lock().unwrap().push((self.ticks, event));
+    }
+
+    fn tick(&mut self) {
+        self.ticks += 1;
+    }
+
+    fn get_next_sample(&self) -> f32 {
+        0.0
+    }
+}
+
+#[test]
+fn test_sequencer() {
+    // (at 120bpm, a 16th is 5512.5 samples, and a bar of 16 of them is 88200)
+    let hits = vec![
+        Hit {
+            start: 0.0,
+            length: 1.0,
+            note: 60,
+            velocity: 1.0,
+            target: Some(0),
+        },
+        Hit {
+            start: 4.0,
+            length: 2.0,
+            note: 64,
+            velocity: 0.5,
+            target: Some(1),
+        },
+    ];
+    let (kick, snare) = (Notes::default(), Notes::default());
+    let (kicks, snares) = (kick.played.clone(), snare.played.clone());
+    let mut sequencer = Sequencer::new(16, hits.clone(), vec![Box::new(kick), Box::new(snare)]);
+    for _ in 0..88200 * 2 - 100 {
+        sequencer.tick();
+    }
+
+    let at = |played: &[(usize, MidiEvent)]| played.iter().map(|(at, _)| *at).collect::<Vec<_>>();
+    let near = |a: Vec<usize>, b: Vec<usize>| {
+        a.len() == b.len() && a.iter().zip(&b).all(|(a, b)| a.abs_diff(*b) <= 1)
+    };
+    let kicks = kicks.lock().unwrap();
+    assert!(near(at(&kicks), vec![0, 5512, 88200, 93712]), "{:?}", kicks);
+    assert_eq!(
+        kicks[0].1,
+        MidiEvent::NoteOn {
+            note: 60,
+            velocity: 1.0
+        }
+    );
+    assert_eq!(kicks[1].1, MidiEvent::NoteOff { note: 60 });
+    let snares = snares.lock().unwrap();
+    assert!(
+        near(at(&snares), vec![22050, 33075, 110250, 121275]),
+        "{:?}",
+        snares
+    );
+
+    // (starting where the transport is, at twice the tempo)
+    let kick = Notes::default();
+    let kicks = kick.played.clone();
+    let mut sequencer = Sequencer::new(16, hits, vec![Box::new(kick)]);
+    sequencer.apply(TRANSPORT_TEMPO, 240.0);
+    sequencer.apply(TRANSPORT_BEAT, 2.0);
+    for _ in 0..44100 {
+        sequencer.tick();
+    }
+    assert!(near(at(&kicks.lock().unwrap()), vec![22050, 24806]));
+}
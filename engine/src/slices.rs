@@ -0,0 +1,103 @@
+/// How often the loudness is measured (10ms hops)
+const HOPS_PER_SECOND: u32 = 100;
+/// Hits that come closer together than this (in hops) count as one
+const MIN_GAP: usize = 5;
+/// How many hops around an onset its loudness rise is compared with
+const NEIGHBOURHOOD: usize = 10;
+/// How much louder it has to get than what's usual around it (and at least, in dB)
+const SENSITIVITY: f32 = 1.5;
+const MIN_RISE: f32 = 6.0;
+/// (anything quieter than this is silence, in dB)
+const FLOOR: f32 = -60.0;
+
+/**
+    Where the hits in a loop start (like the kicks, snares and hats of a drum break), as fractions of its length, from 0 (which is always the first one) on. The loudness is measured every 10ms, and wherever it rises sharply compared to what's usual around there, a new slice starts.
+*/
+pub fn detect_slices(samples: &[f32], sample_rate: u32) -> Vec<f32> {
+    let hop = (sample_rate / HOPS_PER_SECOND).max(1) as usize;
+    if samples.len() < hop * 2 {
+        return vec![0.0];
+    }
+
+    let loudness = samples
+        .chunks(hop)
+        .map(|chunk| {
+            let power = chunk.iter().map(|s| s * s).sum::<f32>() / chunk.len() as f32;
+            (10.0 * power.max(1e-12).log10()).max(FLOOR)
+        })
+        .collect::<Vec<_>>();
+
+    // (how much louder every hop is than the one before, rising only)
+    let rise = (0..loudness.len())
+        .map(|i| match i {
+            0 => 0.0,
+            _ => (loudness[i] - loudness[i - 1]).max(0.0),
+        })
+        .collect::<Vec<_>>();
+
+    let mut starts = vec![0.0];
+    let mut last = 0;
+
+    for i in 1..rise.len() {
+        let around =
+            &rise[i.saturating_sub(NEIGHBOURHOOD)..(i + NEIGHBOURHOOD + 1).min(rise.len())];
+        let usual = around.iter().sum::<f32>() / around.len() as f32;
+        let peak = around.iter().copied().fold(0.0, f32::max);
+
+        if rise[i] >= peak && rise[i] >= MIN_RISE.max(usual * SENSITIVITY) && i - last >= MIN_GAP {
+            // (it starts somewhere in the hop before the one that's louder)
+            starts.push(((i - 1) * hop) as f32 / samples.len() as f32);
+            last = i;
+        }
+    }
+
+    starts
+}
+
+/**
+    The samples of one slice: from where it starts, up to where the next one does (or the end). The starts are fractions of the length, like `detect_slices` gives.
+*/
+pub fn slice(samples: &[f32], starts: &[f32], i: usize) -> Option<Vec<f32>> {
+    let at = |fraction: f32| (fraction.clamp(0.0, 1.0) * samples.len() as f32) as usize;
+
+    let start = at(*starts.get(i)?);
+    let end = starts.get(i + 1).map_or(samples.len(), |&next| at(next));
+
+    Some(samples[start..end.max(start)].to_vec())
+}
+
+#[test]
+fn test_detect_slices() {
+    let sample_rate = 44_100;
+
+    // four hits, half a second apart, that each die out
+    let mut samples = vec![0.0; sample_rate as usize * 2];
+    for hit in 0..4 {
+        let at = hit * sample_rate as usize / 2 + 1000;
+        for i in 0..8000 {
+            let decay = (-(i as f32) / 1500.0).exp();
+            samples[at + i] = (i as f32 * 0.3).sin() * decay;
+        }
+    }
+
+    // (the first one's close enough to the start to be the first slice)
+    let starts = detect_slices(&samples, sample_rate);
+    assert_eq!(starts.len(), 4);
+    assert_eq!(starts[0], 0.0);
+
+    for (hit, start) in starts.iter().enumerate().skip(1) {
+        let expected = (hit as f32 * 0.5 * sample_rate as f32 + 1000.0) / samples.len() as f32;
+        assert!((start - expected).abs() < 0.01, "{} vs {}", start, expected);
+    }
+
+    // (steady sound has no hits in it)
+    let steady = (0..sample_rate)
+        .map(|i| (i as f32 * 0.05).sin())
+        .collect::<Vec<_>>();
+    assert_eq!(detect_slices(&steady, sample_rate), vec![0.0]);
+
+    let ramp = (0..8).map(|i| i as f32).collect::<Vec<_>>();
+    assert_eq!(slice(&ramp, &[0.0, 0.5], 1), Some(vec![4.0, 5.0, 6.0, 7.0]));
+    assert_eq!(slice(&ramp, &[0.0, 0.25], 0), Some(vec![0.0, 1.0]));
+    assert_eq!(slice(&ramp, &[0.0, 0.25], 2), None);
+}
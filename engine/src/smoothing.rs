@@ -0,0 +1,88 @@
+use std::time::Duration;
+
+use crate::SAMPLE_RATE;
+
+/// How long it takes for a parameter change to fully come through
+const SMOOTHING_MS: f32 = 20.0;
+
+/// How long a parameter glides from its old value to its new one when the code that sets it changed (unless the code says otherwise, with `ease(..)`)
+pub const DEFAULT_EASE: Duration = Duration::from_millis(30);
+
+/**
+    A parameter value that glides (linearly) to its target, instead of jumping there, so that e.g. dragging a knob doesn't produce zipper noise.
+*/
+#[derive(Debug, Clone, Copy)]
+pub struct Smoothed {
+    current: f32,
+    target: f32,
+    step: f32,
+}
+
+impl Smoothed {
+    pub fn new(value: f32) -> Self {
+        Self {
+            current: value,
+            target: value,
+            step: 0.0,
+        }
+    }
+
+    pub fn set_target(&mut self, target: f32) {
+        self.set_target_over(target, SMOOTHING_MS / 1000.0 * SAMPLE_RATE as f32);
+    }
+
+    /**
+        Glides to the target over a number of samples instead (where less than one is right away, on the next sample)
+    */
+    pub fn set_target_over(&mut self, target: f32, num_samples: f32) {
+        self.target = target;
+        self.step = (target - self.current) / num_samples.max(1.0);
+    }
+
+    pub fn is_settled(&self) -> bool {
+        self.current == self.target
+    }
+
+    /**
+        Advances one sample
+    */
+    pub fn next(&mut self) -> f32 {
+        if (self.target - self.current).abs() <= self.step.abs() {
+            self.current = self.target;
+        } else {
+            self.current += self.step;
+        }
+
+        self.current
+    }
+}
+
+#[test]
+fn test_smoothing() {
+    let mut value = Smoothed::new(0.0);
+    value.set_target(1.0);
+
+    let samples = (0..2000).map(|_| value.next()).collect::<Vec<_>>();
+
+    // no sudden jumps
+    assert!(samples.windows(2).all(|w| w[1] - w[0] < 0.01));
+
+    // but it does get there, in about 20ms
+    assert!(!samples[800].eq(&1.0));
+    assert_eq!(samples[1000], 1.0);
+    assert!(value.is_settled());
+}
+
+#[test]
+fn test_easing() {
+    let mut value = Smoothed::new(100.0);
+    value.set_target_over(200.0, 441.0);
+
+    let samples = (0..1000).map(|_| value.next()).collect::<Vec<_>>();
+    assert_eq!(samples[219].round(), 150.0);
+    assert_eq!(samples[440], 200.0);
+
+    // (and without an ease, it just jumps)
+    value.set_target_over(0.0, 0.0);
+    assert_eq!(value.next(), 0.0);
+}
@@ -0,0 +1,313 @@
+use std::{collections::HashMap, f32::consts::FRAC_PI_2};
+
+use crate::{
+    bus::Routing,
+    midi::{MidiEvent, Tuning},
+    modulation::Modulation,
+    node::AudioNode,
+    SAMPLE_RATE,
+};
+
+/// How a `Switch` goes over from one branch to the other, when its condition changes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Switching {
+    /// From one sample to the next (which can click)
+    Hard,
+    /// Fading one branch out while the other fades in, over this many seconds
+    Crossfade(f32),
+}
+
+impl Default for Switching {
+    fn default() -> Self {
+        Self::Crossfade(0.005)
+    }
+}
+
+/**
+    What `if cond { a } else { b }` is when the condition is a signal (like `midi.gate > .5`), instead of something that's known when the program is evaluated. Both branches keep playing (so that an envelope or a delay in the one that's not heard is where it would've been), and the output follows whichever branch the condition picks, where anything from 0.5 up counts as true. By default it crossfades (equal power) when the condition changes, so that it doesn't click. The crossfade time is its `fade` parameter, in seconds, where 0 is a hard switch.
+*/
+pub struct Switch {
+    condition: Modulation,
+    then: Box<dyn AudioNode + Send>,
+    otherwise: Box<dyn AudioNode + Send>,
+
+    // parameters
+    switching: Switching,
+
+    // audio node helper stuff
+    named_parameters: HashMap<String, String>,
+
+    // state
+    // (0 is the `else` branch, 1 the `then` branch, and anything in between is fading)
+    position: f32,
+    started: bool,
+    out: f32,
+}
+
+impl Switch {
+    pub fn new(
+        condition: impl Into<Modulation>,
+        then: Box<dyn AudioNode + Send>,
+        otherwise: Box<dyn AudioNode + Send>,
+    ) -> Self {
+        Self {
+            condition: condition.into(),
+            then,
+            otherwise,
+            switching: Switching::default(),
+            named_parameters: HashMap::new(),
+            position: 0.0,
+            started: false,
+            out: 0.0,
+        }
+    }
+
+    pub fn switching(mut self, switching: Switching) -> Self {
+        self.switching = switching;
+        self
+    }
+}
+
+impl AudioNode for Switch {
+    fn parameters(&self) -> Vec<String> {
+        vec!["fade".into()]
+    }
+
+    fn map(&mut self, name: String, parameter: String) {
+        self.named_parameters.insert(name, parameter);
+    }
+
+    fn apply(&mut self, param: &str, value: f32) {
+        let actual = self
+            .named_parameters
+            .get(param)
+            .map_or(param, |actual| actual.as_str());
+
+        if actual == "fade" {
+            self.switching = if value > 0.0 {
+                Switching::Crossfade(value)
+            } else {
+                Switching::Hard
+            };
+            return;
+        }
+
+        self.condition.apply(param, value);
+        self.then.apply(param, value);
+        self.otherwise.apply(param, value);
+    }
+
+    fn note(&mut self, event: MidiEvent) {
+        self.then.note(event);
+        self.otherwise.note(event);
+    }
+
+    fn retune(&mut self, tuning: &Tuning) {
+        self.then.retune(tuning);
+        self.otherwise.retune(tuning);
+    }
+
+    fn route(&self, routing: &mut Routing) {
+        self.condition.route(routing);
+        self.then.route(routing);
+        self.otherwise.route(routing);
+    }
+
+    fn economize(&mut self, economize: bool) {
+        self.condition.economize(economize);
+        self.then.economize(economize);
+        self.otherwise.economize(economize);
+    }
+
+    fn tick(&mut self) {
+        let target = if self.condition.tick() >= 0.5 {
+            1.0
+        } else {
+            0.0
+        };
+
+        self.position = match self.switching {
+            // (and there's nothing to fade from at the very start)
+            _ if !self.started => target,
+            Switching::Hard => target,
+            Switching::Crossfade(seconds) => {
+                let step = 1.0 / (seconds * SAMPLE_RATE as f32).max(1.0);
+                if target > self.position {
+                    (self.position + step).min(target)
+                } else {
+                    (self.position - step).max(target)
+                }
+            }
+        };
+        self.started = true;
+
+        self.then.tick();
+        self.otherwise.tick();
+
+        let angle = self.position * FRAC_PI_2;
+        self.out = self.then.get_next_sample() * angle.sin()
+            + self.otherwise.get_next_sample() * angle.cos();
+    }
+
+    fn get_next_sample(&self) -> f32 {
+        self.out
+    }
+}
+
+/// What a `Compare` does with its operands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+    /// (of the first operand only)
+    Not,
+}
+
+impl Comparison {
+    /// (by how it's written in the code, like `>=`)
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        Some(match symbol {
+            "==" => Self::Eq,
+            "!=" => Self::Ne,
+            "<" => Self::Lt,
+            "<=" => Self::Le,
+            ">" => Self::Gt,
+            ">=" => Self::Ge,
+            "&&" => Self::And,
+            "||" => Self::Or,
+            "!" => Self::Not,
+            _ => return None,
+        })
+    }
+}
+
+/**
+    What a comparison (or `&&`, `||` and `!`) of signals is, like `midi.gate > .5`: 1 while it's true, and 0 otherwise, where (like for a `Switch`) anything from 0.5 up counts as true for the logical ones
+*/
+pub struct Compare {
+    comparison: Comparison,
+    a: Modulation,
+    b: Modulation,
+
+    // state
+    out: f32,
+}
+
+impl Compare {
+    pub fn new(comparison: Comparison, a: impl Into<Modulation>, b: impl Into<Modulation>) -> Self {
+        Self {
+            comparison,
+            a: a.into(),
+            b: b.into(),
+            out: 0.0,
+        }
+    }
+}
+
+impl AudioNode for Compare {
+    fn parameters(&self) -> Vec<String> {
+        vec![]
+    }
+
+    fn map(&mut self, _name: String, _parameter: String) {}
+
+    fn apply(&mut self, param: &str, value: f32) {
+        self.a.apply(param, value);
+        self.b.apply(param, value);
+    }
+
+    fn route(&self, routing: &mut Routing) {
+        self.a.route(routing);
+        self.b.route(routing);
+    }
+
+    fn economize(&mut self, economize: bool) {
+        self.a.economize(economize);
+        self.b.economize(economize);
+    }
+
+    fn tick(&mut self) {
+        let (a, b) = (self.a.tick(), self.b.tick());
+        let (x, y) = (a >= 0.5, b >= 0.5);
+
+        let result = match self.comparison {
+            Comparison::Eq => a == b,
+            Comparison::Ne => a != b,
+            Comparison::Lt => a < b,
+            Comparison::Le => a <= b,
+            Comparison::Gt => a > b,
+            Comparison::Ge => a >= b,
+            Comparison::And => x && y,
+            Comparison::Or => x || y,
+            Comparison::Not => !x,
+        };
+        self.out = if result { 1.0 } else { 0.0 };
+    }
+
+    fn get_next_sample(&self) -> f32 {
+        self.out
+    }
+}
+
+#[test]
+fn test_switch() {
+    use crate::node::Dc;
+
+    let run = |switch: &mut Switch, n: usize| {
+        (0..n)
+            .map(|_| {
+                switch.tick();
+                switch.get_next_sample()
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut switch = Switch::new(
+        Modulation::control("gate", 1.0),
+        Box::new(Dc::new(1.0)),
+        Box::new(Dc::new(-1.0)),
+    );
+    assert_eq!(run(&mut switch, 10), vec![1.0; 10]);
+
+    // (the control glides, and counts as false from halfway)
+    switch.apply("gate", 0.0);
+    let fade = run(&mut switch, 2000);
+    assert!(fade.windows(2).all(|w| w[1] <= w[0]));
+    assert!(fade[..400].iter().all(|&s| s == 1.0));
+    assert!(fade[400..800].iter().any(|&s| s > -1.0 && s < 1.0));
+    assert!((fade[1999] + 1.0).abs() < 1e-6);
+
+    let mut switch = Switch::new(0.0, Box::new(Dc::new(1.0)), Box::new(Dc::new(-1.0)))
+        .switching(Switching::Hard);
+    assert_eq!(run(&mut switch, 2), vec![-1.0; 2]);
+    switch.condition = Modulation::from(1.0);
+    assert_eq!(run(&mut switch, 1), vec![1.0]);
+}
+
+#[test]
+fn test_compare() {
+    let mut compare = Compare::new(Comparison::Gt, Modulation::control("gate", 0.0), 0.5);
+    compare.tick();
+    assert_eq!(compare.get_next_sample(), 0.0);
+
+    // (once the control has glided over)
+    compare.apply("gate", 1.0);
+    for _ in 0..1000 {
+        compare.tick();
+    }
+    assert_eq!(compare.get_next_sample(), 1.0);
+
+    let mut not = Compare::new(
+        Comparison::Not,
+        Box::new(compare) as Box<dyn AudioNode + Send>,
+        0.0,
+    );
+    not.tick();
+    assert_eq!(not.get_next_sample(), 0.0);
+}
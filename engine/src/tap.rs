@@ -0,0 +1,73 @@
+use std::sync::{
+    atomic::{AtomicU32, AtomicUsize, Ordering},
+    Arc,
+};
+
+/// How many samples a tap keeps around (about 90ms)
+pub const TAP_SIZE: usize = 4096;
+
+struct Ring {
+    // (f32 bits, because there's no atomic float)
+    samples: Box<[AtomicU32]>,
+    written: AtomicUsize,
+}
+
+/**
+    The most recent samples of a play target, for scopes and such.
+
+    It's a lock-free ring buffer: the audio thread just keeps writing, and readers copy out whatever's there. So a reader can get a few torn samples if it's slow, which is fine for showing them.
+*/
+#[derive(Clone)]
+pub struct Tap {
+    ring: Arc<Ring>,
+}
+
+impl Tap {
+    pub(crate) fn new() -> Self {
+        Self {
+            ring: Arc::new(Ring {
+                samples: (0..TAP_SIZE).map(|_| AtomicU32::new(0)).collect(),
+                written: AtomicUsize::new(0),
+            }),
+        }
+    }
+
+    pub(crate) fn push(&self, sample: f32) {
+        let i = self.ring.written.load(Ordering::Relaxed);
+        self.ring.samples[i % TAP_SIZE].store(sample.to_bits(), Ordering::Relaxed);
+        self.ring.written.store(i + 1, Ordering::Release);
+    }
+
+    /**
+        Whether nobody's reading anymore (the audio thread can let go of it then)
+    */
+    pub(crate) fn is_orphaned(&self) -> bool {
+        Arc::strong_count(&self.ring) == 1
+    }
+
+    /**
+        The last `n` samples (at most `TAP_SIZE`), oldest first
+    */
+    pub fn latest(&self, n: usize) -> Vec<f32> {
+        let end = self.ring.written.load(Ordering::Acquire);
+        let start = end.saturating_sub(n.min(TAP_SIZE));
+
+        (start..end)
+            .map(|i| f32::from_bits(self.ring.samples[i % TAP_SIZE].load(Ordering::Relaxed)))
+            .collect()
+    }
+}
+
+#[test]
+fn test_tap() {
+    let tap = Tap::new();
+    assert_eq!(tap.latest(4), vec![]);
+
+    for i in 0..(TAP_SIZE + 10) {
+        tap.push(i as f32);
+    }
+
+    assert_eq!(tap.latest(3), vec![4103.0, 4104.0, 4105.0]);
+    assert_eq!(tap.latest(TAP_SIZE * 2).len(), TAP_SIZE);
+    assert!(tap.is_orphaned());
+}
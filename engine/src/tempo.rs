@@ -0,0 +1,317 @@
+use std::{collections::VecDeque, f64::consts::PI, time::Duration};
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
+
+use crate::transport::clamp_tempo;
+
+/// How often the onsets are measured (about 6ms), finer than for slicing, since being a bit off every beat adds up over a loop
+const HOPS_PER_SECOND: f64 = 172.0;
+/// The tempos a loop can be at (anything faster or slower is heard as double or half one of these)
+const MIN_BPM: f64 = 70.0;
+const MAX_BPM: f64 = 180.0;
+/// (when it's not clear whether it's at 85 or at 170, it's probably the one nearer to this)
+const LIKELY_BPM: f64 = 120.0;
+/// A loop that's this close (relatively) to a whole number of beats at the tempo it sounds like is at exactly that tempo
+const LOOP_SNAP: f64 = 0.02;
+/// (how much loudness is compressed before measuring how much it rises)
+const COMPRESSION: f64 = 100.0;
+/// (a rise in compressed loudness that's about a hat, so anything with less than that doesn't have a beat)
+const MIN_ONSET: f64 = 0.1;
+/// (how well the onsets have to line up with themselves a beat later, relative to with themselves)
+const MIN_CORRELATION: f64 = 0.1;
+
+/**
+    The tempo (in bpm) a loop sounds like it's at, or `None` when there's no beat to it (or it's too short to tell). The onsets (where it gets louder, like for `detect_slices`) are measured every 6ms, and the tempo is where they line up best with themselves a beat later (their autocorrelation), between 70 and 180 bpm.
+
+    Loops are usually cut to a whole number of beats, so when its length fits a whole number of beats at about that tempo, that's how it's pinned down exactly.
+*/
+pub fn detect_tempo(samples: &[f32], sample_rate: u32) -> Option<f64> {
+    let hop = (sample_rate as f64 / HOPS_PER_SECOND).round().max(1.0) as usize;
+    let hops_per_second = sample_rate as f64 / hop as f64;
+    let seconds = samples.len() as f64 / sample_rate as f64;
+
+    // (at least a couple of beats at the slowest tempo)
+    if seconds < 2.0 * 60.0 / MIN_BPM {
+        return None;
+    }
+
+    let loudness = samples
+        .chunks_exact(hop)
+        .map(|chunk| {
+            let power =
+                chunk.iter().map(|&s| s as f64 * s as f64).sum::<f64>() / chunk.len() as f64;
+            // (compressed, but not all the way to dB, so a kick still counts for more than a hat)
+            (1.0 + COMPRESSION * power.sqrt()).ln()
+        })
+        .collect::<Vec<_>>();
+
+    // (how much louder every hop is than the one before, rising only, a bit smeared out so onsets in between hops still line up, around the average)
+    let rise = loudness
+        .windows(2)
+        .map(|pair| (pair[1] - pair[0]).max(0.0))
+        .collect::<Vec<_>>();
+    let rise = (0..rise.len())
+        .map(|i| {
+            let at = |j: usize| rise.get(j).copied().unwrap_or(0.0);
+            0.25 * at(i.wrapping_sub(1)) + 0.5 * at(i) + 0.25 * at(i + 1)
+        })
+        .collect::<Vec<_>>();
+    let average = rise.iter().sum::<f64>() / rise.len() as f64;
+    let onsets = rise.iter().map(|r| r - average).collect::<Vec<_>>();
+    let variance = onsets.iter().map(|o| o * o).sum::<f64>() / onsets.len() as f64;
+
+    // (it doesn't ever really get louder)
+    if rise.iter().all(|&r| r < MIN_ONSET) {
+        return None;
+    }
+
+    let lag_of = |bpm: f64| 60.0 * hops_per_second / bpm;
+    let correlation = |lag: usize| {
+        let pairs = onsets.len().checked_sub(lag).filter(|&n| n > 0)?;
+        Some((0..pairs).map(|i| onsets[i] * onsets[i + lag]).sum::<f64>() / pairs as f64)
+    };
+
+    let lags = lag_of(MAX_BPM).floor() as usize..=lag_of(MIN_BPM).ceil() as usize;
+    let scores = lags
+        .clone()
+        .map(|lag| {
+            let bpm = 60.0 * hops_per_second / lag as f64;
+            let octaves = (bpm / LIKELY_BPM).log2();
+            correlation(lag).unwrap_or(0.0) * (-0.5 * octaves * octaves).exp()
+        })
+        .collect::<Vec<_>>();
+
+    let (i, &best) = scores
+        .iter()
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(b.1))?;
+    if best < MIN_CORRELATION * variance {
+        return None;
+    }
+
+    // (in between lags, where the peak's really at)
+    let before = scores.get(i.wrapping_sub(1)).copied().unwrap_or(best);
+    let after = scores.get(i + 1).copied().unwrap_or(best);
+    let curvature = before - 2.0 * best + after;
+    let offset = if curvature < 0.0 {
+        (0.5 * (before - after) / curvature).clamp(-0.5, 0.5)
+    } else {
+        0.0
+    };
+    let lag = (lags.start() + i) as f64 + offset;
+    let bpm = 60.0 * hops_per_second / lag;
+
+    let beats = (seconds * bpm / 60.0).round();
+    let fitting = beats * 60.0 / seconds;
+    Some(if beats >= 1.0 && (fitting / bpm - 1.0).abs() < LOOP_SNAP {
+        fitting
+    } else {
+        bpm
+    })
+}
+
+/**
+    Makes audio longer (`ratio` > 1) or shorter, without changing its pitch, like for playing a loop at another tempo (at `detected / tempo`). It's cut up into overlapping grains of 40ms, and every grain is taken from about where it would be, but shifted (by up to 10ms) to where it continues the one before most smoothly (WSOLA), which keeps transients pretty sharp.
+*/
+pub fn time_stretch(samples: &[f32], sample_rate: u32, ratio: f64) -> Vec<f32> {
+    let length = (samples.len() as f64 * ratio).round() as usize;
+    if samples.is_empty() || !ratio.is_finite() || ratio <= 0.0 {
+        return vec![0.0; length.min(samples.len())];
+    }
+
+    let grain = ((sample_rate as f64 * 0.04) as usize).max(4) & !1;
+    let hop = grain / 2;
+    let tolerance = (sample_rate as f64 * 0.01) as usize;
+
+    // (Hann, which adds up to 1 at half overlap)
+    let window = (0..grain)
+        .map(|i| (0.5 - 0.5 * (2.0 * PI * i as f64 / grain as f64).cos()) as f32)
+        .collect::<Vec<_>>();
+    let at = |i: usize| samples.get(i).copied().unwrap_or(0.0);
+
+    let mut out = vec![0.0; length + grain];
+    let mut weights = vec![0.0; length + grain];
+    let mut previous: Option<usize> = None;
+
+    for start in (0..length).step_by(hop) {
+        let nominal = (start as f64 / ratio) as usize;
+
+        let from = match previous {
+            None => nominal,
+            Some(previous) => {
+                // (what would naturally come next after the grain before)
+                let next = previous + hop;
+                // (normalized, so it's not just the loudest candidate, and it's exactly where it'd be when that's already the best fit)
+                let similarity = |candidate: usize| {
+                    let (product, energy) = (0..hop).fold((0.0, 0.0), |(product, energy), i| {
+                        let sample = at(candidate + i) as f64;
+                        (
+                            product + sample * at(next + i) as f64,
+                            energy + sample * sample,
+                        )
+                    });
+                    product / energy.max(1e-12).sqrt()
+                };
+
+                (nominal.saturating_sub(tolerance)..=nominal + tolerance)
+                    .max_by(|&a, &b| similarity(a).total_cmp(&similarity(b)))
+                    .unwrap_or(nominal)
+            }
+        };
+
+        for (i, w) in window.iter().enumerate() {
+            out[start + i] += at(from + i) * w;
+            weights[start + i] += w;
+        }
+        previous = Some(from);
+    }
+
+    out.truncate(length);
+    for (sample, &weight) in out.iter_mut().zip(&weights) {
+        // (the very start is only covered by the rising half of a window, but that's still just as loud then)
+        if weight > 0.0 {
+            *sample /= weight;
+        }
+    }
+
+    out
+}
+
+/// Taps further apart than this (30 bpm) start over
+const TAP_TIMEOUT: Duration = Duration::from_secs(2);
+/// (the tempo is the average over the last this many taps)
+const TAPS: usize = 8;
+
+/**
+    Tap tempo: the tempo of a key that's hit on the beat, over and over
+*/
+#[derive(Debug, Default)]
+pub struct TapTempo {
+    taps: VecDeque<Instant>,
+}
+
+impl TapTempo {
+    /**
+        Counts a tap, and returns the tempo the taps are at so far (from the second one on)
+    */
+    pub fn tap(&mut self, at: Instant) -> Option<f64> {
+        if self
+            .taps
+            .back()
+            .is_some_and(|&last| at.saturating_duration_since(last) > TAP_TIMEOUT)
+        {
+            self.taps.clear();
+        }
+
+        if self.taps.len() == TAPS {
+            self.taps.pop_front();
+        }
+        self.taps.push_back(at);
+
+        let beats = self.taps.len() - 1;
+        let seconds = at
+            .saturating_duration_since(*self.taps.front()?)
+            .as_secs_f64();
+        (beats > 0 && seconds > 0.0).then(|| clamp_tempo(60.0 * beats as f64 / seconds))
+    }
+}
+
+#[cfg(test)]
+fn beats(bpm: f64, seconds: f64, sample_rate: u32) -> Vec<f32> {
+    let mut samples = vec![0.0; (seconds * sample_rate as f64) as usize];
+    let beat = 60.0 / bpm * sample_rate as f64;
+
+    // (a kick on every beat, and a quieter hat in between)
+    let mut at = 0.0;
+    let mut i = 0;
+    while (at as usize) < samples.len() {
+        let (length, gain) = if i % 2 == 0 { (4000, 0.8) } else { (800, 0.3) };
+        for (j, sample) in samples[at as usize..].iter_mut().take(length).enumerate() {
+            let decay = (-(j as f32) / (length as f32 / 5.0)).exp();
+            *sample = (j as f32 * 0.05).sin() * decay * gain;
+        }
+        at += beat / 2.0;
+        i += 1;
+    }
+
+    samples
+}
+
+#[test]
+fn test_detect_tempo() {
+    let sample_rate = 44_100;
+
+    // (a loop of 8 beats, which pins it down exactly)
+    let samples = beats(125.0, 8.0 * 60.0 / 125.0, sample_rate);
+    let bpm = detect_tempo(&samples, sample_rate).unwrap();
+    assert!((bpm - 125.0).abs() < 1e-6, "{}", bpm);
+
+    // (and one that isn't a loop, which is still close)
+    let samples = beats(93.0, 7.5, sample_rate);
+    let bpm = detect_tempo(&samples, sample_rate).unwrap();
+    assert!((bpm - 93.0).abs() < 1.5, "{}", bpm);
+
+    // (steady sound doesn't have a tempo, and neither does a one-shot)
+    let steady = (0..sample_rate * 4)
+        .map(|i| (i as f32 * 0.05).sin())
+        .collect::<Vec<_>>();
+    assert_eq!(detect_tempo(&steady, sample_rate), None);
+    assert_eq!(
+        detect_tempo(&samples[..sample_rate as usize], sample_rate),
+        None
+    );
+}
+
+#[test]
+fn test_time_stretch() {
+    let sample_rate = 44_100;
+    let sine = (0..sample_rate)
+        .map(|i| (i as f32 * 440.0 * std::f32::consts::TAU / sample_rate as f32).sin())
+        .collect::<Vec<_>>();
+
+    // (as it is, it's the same, other than at the very end, where the last grain fades out)
+    let same = time_stretch(&sine, sample_rate, 1.0);
+    assert_eq!(same.len(), sine.len());
+    assert!(same[..40_000]
+        .iter()
+        .zip(&sine)
+        .all(|(a, b)| (a - b).abs() < 1e-3));
+
+    // (longer, at the same pitch, by counting upward zero crossings in the middle)
+    let longer = time_stretch(&sine, sample_rate, 1.25);
+    assert_eq!(longer.len(), 55_125);
+    let crossings = longer[10_000..10_000 + sample_rate as usize / 4]
+        .windows(2)
+        .filter(|pair| pair[0] < 0.0 && pair[1] >= 0.0)
+        .count();
+    assert!((crossings as i32 - 110).abs() <= 2, "{}", crossings);
+
+    let clicks = beats(100.0, 4.8, sample_rate);
+    let faster = time_stretch(&clicks, sample_rate, 100.0 / 120.0);
+    assert_eq!(faster.len(), 4 * sample_rate as usize);
+    let bpm = detect_tempo(&faster, sample_rate).unwrap();
+    assert!((bpm - 120.0).abs() < 1e-6, "{}", bpm);
+}
+
+#[test]
+fn test_tap_tempo() {
+    let mut taps = TapTempo::default();
+    let start = Instant::now();
+    let at = |seconds: f64| start + Duration::from_secs_f64(seconds);
+
+    assert_eq!(taps.tap(at(0.0)), None);
+    let bpm = taps.tap(at(0.5)).unwrap();
+    assert!((bpm - 120.0).abs() < 1e-6);
+    // (it averages out)
+    taps.tap(at(1.02));
+    let bpm = taps.tap(at(1.5)).unwrap();
+    assert!((bpm - 120.0).abs() < 1e-6);
+
+    // (and starts over after a while)
+    assert_eq!(taps.tap(at(5.0)), None);
+    let bpm = taps.tap(at(5.6)).unwrap();
+    assert!((bpm - 100.0).abs() < 1e-6);
+}
@@ -0,0 +1,276 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    realtime::permit,
+    transport::{Transport, BEATS_PER_BAR},
+};
+
+/**
+    When a timer fires, on the transport (in beats)
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Timing {
+    /// Every so many beats, starting at the next boundary (which is right away, when it's not quantized)
+    Every(f64),
+    /// Once, so many beats from now
+    After(f64),
+    /// Once, at the start of a bar, counting from 1 for the next one
+    At(f64),
+}
+
+/**
+    A timer that fired: which one, how many times it did before, and at which sample (counting from when the engine started)
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fired {
+    pub id: String,
+    pub count: usize,
+    pub sample: u64,
+}
+
+pub(crate) type SharedFired = Arc<Mutex<Vec<Fired>>>;
+
+struct Timer {
+    id: String,
+    timing: Timing,
+    // (the beat it fires at next, if it still does)
+    next: Option<f64>,
+    count: usize,
+}
+
+/**
+    Fires the timers (like `every(2s, || ..)` in the code) on the audio thread, in the sample that their beat comes around, and publishes that for the editor to call them back
+*/
+#[derive(Default)]
+pub(crate) struct Timers {
+    timers: Vec<Timer>,
+    // (what fired, but couldn't be published yet)
+    fired: Vec<Fired>,
+}
+
+impl Timers {
+    /**
+        Starts a timer, unless it's running already with the same timing (like when the same code is evaluated again), so that it keeps its count and doesn't fire again
+    */
+    pub fn set(&mut self, id: String, timing: Timing, transport: &Transport) {
+        if self
+            .timers
+            .iter()
+            .any(|timer| timer.id == id && timer.timing == timing)
+        {
+            return;
+        }
+
+        self.clear(&id);
+
+        let beat = transport.beat;
+        let next = match timing {
+            Timing::Every(interval) if interval > 0.0 => transport.next_boundary(),
+            Timing::After(delay) if delay >= 0.0 => beat + delay,
+            Timing::At(bar) if bar >= 1.0 => {
+                (beat / BEATS_PER_BAR).ceil() * BEATS_PER_BAR + (bar - 1.0) * BEATS_PER_BAR
+            }
+            // (it would never, or all the time)
+            _ => return,
+        };
+
+        self.timers.push(Timer {
+            id,
+            timing,
+            next: Some(next),
+            count: 0,
+        });
+    }
+
+    /**
+        Changes how long a running timer takes, keeping how far along it is (see `EngineHandle::retime_timer`), or starts it if it isn't running, or its timing is of another kind
+    */
+    pub fn retime(&mut self, id: String, timing: Timing, transport: &Transport) {
+        let Some(timer) = self.timers.iter_mut().find(|timer| timer.id == id) else {
+            return self.set(id, timing, transport);
+        };
+
+        let beat = transport.beat;
+        match (timer.timing, timing) {
+            (Timing::Every(old), Timing::Every(new)) | (Timing::After(old), Timing::After(new))
+                if old > 0.0 && new > 0.0 =>
+            {
+                // (one that's waiting for its first boundary still starts on it)
+                let started = matches!(timing, Timing::After(_)) || timer.count > 0;
+                if started {
+                    timer.next = timer
+                        .next
+                        .map(|next| beat + (next - beat).max(0.0) * new / old);
+                }
+                timer.timing = timing;
+            }
+            _ => self.set(id, timing, transport),
+        }
+    }
+
+    pub fn clear(&mut self, id: &str) {
+        self.timers.retain(|timer| timer.id != id);
+    }
+
+    pub fn clear_all(&mut self) {
+        self.timers.clear();
+    }
+
+    /**
+        Fires what's due at this beat, as the given sample
+    */
+    pub fn tick(&mut self, beat: f64, sample: u64, shared: &SharedFired) {
+        for timer in &mut self.timers {
+            let Some(next) = timer.next.filter(|next| *next <= beat) else {
+                continue;
+            };
+
+            // (which is a few times a beat at most)
+            permit(|| {
+                self.fired.push(Fired {
+                    id: timer.id.clone(),
+                    count: timer.count,
+                    sample,
+                })
+            });
+            timer.count += 1;
+            timer.next = match timer.timing {
+                // (from when it should have fired, so that it doesn't drift)
+                Timing::Every(interval) => Some(next + interval),
+                Timing::After(_) | Timing::At(_) => None,
+            };
+        }
+
+        if self.fired.is_empty() {
+            return;
+        }
+
+        // (never block the audio thread, we'll just try again next sample)
+        if let Ok(mut shared) = shared.try_lock() {
+            permit(|| shared.append(&mut self.fired));
+        }
+    }
+}
+
+#[test]
+fn test_timers() {
+    use crate::SAMPLE_RATE;
+
+    let shared = SharedFired::default();
+    let mut timers = Timers::default();
+    // (a beat every 100 samples)
+    let mut transport = Transport {
+        tempo: 60.0 * SAMPLE_RATE as f64 / 100.0,
+        ..Default::default()
+    };
+
+    timers.set("every".into(), Timing::Every(2.0), &transport);
+    timers.set("after".into(), Timing::After(3.0), &transport);
+    timers.set("at".into(), Timing::At(2.0), &transport);
+
+    for sample in 0..1000 {
+        timers.tick(transport.beat, sample, &shared);
+        transport.tick();
+
+        // (setting it again doesn't restart it)
+        if sample == 450 {
+            timers.set("every".into(), Timing::Every(2.0), &transport);
+        }
+    }
+
+    let fired = std::mem::take(&mut *shared.lock().unwrap());
+    let at = |id: &str| {
+        fired
+            .iter()
+            .filter(|fired| fired.id == id)
+            .map(|fired| (fired.count, fired.sample))
+            .collect::<Vec<_>>()
+    };
+
+    // (give or take a sample, for the beats that don't add up exactly)
+    let near = |actual: Vec<(usize, u64)>, expected: Vec<(usize, u64)>| {
+        actual.len() == expected.len()
+            && actual
+                .iter()
+                .zip(&expected)
+                .all(|(a, b)| a.0 == b.0 && a.1.abs_diff(b.1) <= 1)
+    };
+
+    assert!(near(
+        at("every"),
+        vec![(0, 0), (1, 200), (2, 400), (3, 600), (4, 800)]
+    ));
+    assert!(near(at("after"), vec![(0, 300)]));
+    // (the bar that started right away is the first one, so the second is the one after that)
+    assert!(near(at("at"), vec![(0, 400)]));
+
+    timers.set("every".into(), Timing::Every(1.0), &transport);
+    timers.clear("after");
+    timers.tick(transport.beat, 1000, &shared);
+    assert_eq!(
+        std::mem::take(&mut *shared.lock().unwrap()),
+        vec![Fired {
+            id: "every".into(),
+            count: 0,
+            sample: 1000,
+        }]
+    );
+}
+
+#[test]
+fn test_retimed_timers() {
+    use crate::SAMPLE_RATE;
+
+    let shared = SharedFired::default();
+    let mut timers = Timers::default();
+    // (a beat every 100 samples)
+    let mut transport = Transport {
+        tempo: 60.0 * SAMPLE_RATE as f64 / 100.0,
+        ..Default::default()
+    };
+
+    // (as in `every(2s, ..)` and so on, at 60 bpm)
+    timers.set("every".into(), Timing::Every(2.0), &transport);
+    timers.set("after".into(), Timing::After(1.0), &transport);
+    timers.set("later".into(), Timing::After(8.0), &transport);
+
+    for sample in 0..1000 {
+        timers.tick(transport.beat, sample, &shared);
+        transport.tick();
+
+        // (the tempo doubles halfway, so the same seconds are twice as many beats)
+        if sample == 499 {
+            transport.tempo *= 2.0;
+            timers.retime("every".into(), Timing::Every(4.0), &transport);
+            timers.retime("after".into(), Timing::After(2.0), &transport);
+            timers.retime("later".into(), Timing::After(16.0), &transport);
+        }
+    }
+
+    let fired = std::mem::take(&mut *shared.lock().unwrap());
+    let at = |id: &str| {
+        fired
+            .iter()
+            .filter(|fired| fired.id == id)
+            .map(|fired| (fired.count, fired.sample))
+            .collect::<Vec<_>>()
+    };
+
+    // (give or take a sample, for the beats that don't add up exactly)
+    let near = |actual: Vec<(usize, u64)>, expected: Vec<(usize, u64)>| {
+        actual.len() == expected.len()
+            && actual
+                .iter()
+                .zip(&expected)
+                .all(|(a, b)| a.0 == b.0 && a.1.abs_diff(b.1) <= 1)
+    };
+
+    // (so they still fire when they would have, in seconds)
+    assert!(near(
+        at("every"),
+        vec![(0, 0), (1, 200), (2, 400), (3, 600), (4, 800)]
+    ));
+    assert!(near(at("later"), vec![(0, 800)]));
+    // (and what fired already doesn't again)
+    assert!(near(at("after"), vec![(0, 100)]));
+}
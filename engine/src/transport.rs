@@ -0,0 +1,343 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::{launcher::LauncherState, SAMPLE_RATE};
+
+pub const BEATS_PER_BAR: f64 = 4.0;
+pub const BARS_PER_PHRASE: f64 = 4.0;
+/// The tempo, unless it's set otherwise
+pub const DEFAULT_TEMPO: f64 = 120.0;
+/// (patterns step in 16th notes)
+pub const STEPS_PER_BEAT: f64 = 4.0;
+/// No swing: every step right on the grid
+pub const STRAIGHT: f64 = 0.5;
+/// (past this, the swung step would bump into the next one)
+const MAX_SWING: f64 = 0.75;
+
+/// The transport's tempo (in bpm) and swing, which the engine applies to everything that's played, for the patterns to keep time by (see `Sequencer`)
+pub const TRANSPORT_TEMPO: &str = "transport.tempo";
+pub const TRANSPORT_SWING: &str = "transport.swing";
+/// Where the transport is (in beats), which what starts playing gets, so that its patterns are in time
+pub const TRANSPORT_BEAT: &str = "transport.beat";
+
+/**
+    When scheduled changes (like code that was evaluated) land: right away, or exactly at the start of the next bar or phrase, so that swapping a pattern doesn't throw off the groove
+*/
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Quantize {
+    #[default]
+    Now,
+    Bar,
+    Phrase,
+}
+
+impl Quantize {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "now" => Some(Self::Now),
+            "bar" => Some(Self::Bar),
+            "phrase" => Some(Self::Phrase),
+            _ => None,
+        }
+    }
+
+    /// (the one after it, round and round)
+    pub fn next(self) -> Self {
+        match self {
+            Self::Now => Self::Bar,
+            Self::Bar => Self::Phrase,
+            Self::Phrase => Self::Now,
+        }
+    }
+
+    /// (in beats)
+    fn length(&self) -> Option<f64> {
+        match self {
+            Self::Now => None,
+            Self::Bar => Some(BEATS_PER_BAR),
+            Self::Phrase => Some(BEATS_PER_BAR * BARS_PER_PHRASE),
+        }
+    }
+}
+
+/**
+    Where the transport is, as last published by the audio thread, with the play targets that have a change waiting for the next boundary
+*/
+#[derive(Debug, Clone)]
+pub struct TransportState {
+    pub tempo: f64,
+    pub quantize: Quantize,
+    /// (see `Groove::swing`)
+    pub swing: f64,
+    /// (since the engine started)
+    pub beat: f64,
+    pub pending: Vec<String>,
+    /// (the clips that are playing, and launched)
+    pub launcher: LauncherState,
+}
+
+impl Default for TransportState {
+    fn default() -> Self {
+        Self {
+            tempo: DEFAULT_TEMPO,
+            quantize: Quantize::Now,
+            swing: STRAIGHT,
+            beat: 0.0,
+            pending: vec![],
+            launcher: LauncherState::default(),
+        }
+    }
+}
+
+impl TransportState {
+    /**
+        How long until the next boundary (of the current quantization), which is when the pending changes land
+    */
+    pub fn until_boundary(&self) -> Duration {
+        let beats = next_boundary(self.beat, self.quantize) - self.beat;
+        Duration::from_secs_f64((beats * 60.0 / self.tempo).max(0.0))
+    }
+}
+
+pub(crate) type SharedTransport = Arc<Mutex<TransportState>>;
+
+/**
+    The first boundary from `beat` on (which is `beat` itself, when it's not quantized)
+*/
+fn next_boundary(beat: f64, quantize: Quantize) -> f64 {
+    match quantize.length() {
+        Some(length) => (beat / length).ceil() * length,
+        None => beat,
+    }
+}
+
+/**
+    Keeps time on the audio thread, counting beats (at the tempo) one sample at a time
+*/
+#[derive(Debug)]
+pub(crate) struct Transport {
+    pub tempo: f64,
+    pub quantize: Quantize,
+    pub swing: f64,
+    pub beat: f64,
+}
+
+impl Default for Transport {
+    fn default() -> Self {
+        Self {
+            tempo: DEFAULT_TEMPO,
+            quantize: Quantize::Now,
+            swing: STRAIGHT,
+            beat: 0.0,
+        }
+    }
+}
+
+impl Transport {
+    pub fn tick(&mut self) {
+        self.beat += self.tempo / 60.0 / SAMPLE_RATE as f64;
+    }
+
+    /**
+        The beat at which something that's scheduled now should land
+    */
+    pub fn next_boundary(&self) -> f64 {
+        next_boundary(self.beat, self.quantize)
+    }
+
+    /// (where clips are launched, whatever the quantization)
+    pub fn next_bar(&self) -> f64 {
+        next_boundary(self.beat, Quantize::Bar)
+    }
+}
+
+/**
+    (It has to keep moving, or nothing would ever land)
+*/
+pub(crate) fn clamp_tempo(tempo: f64) -> f64 {
+    if tempo.is_finite() {
+        tempo.max(1.0)
+    } else {
+        DEFAULT_TEMPO
+    }
+}
+
+/**
+    (Keeps swing where it makes sense, see `Groove::swing`)
+*/
+pub fn clamp_swing(swing: f64) -> f64 {
+    if swing.is_finite() {
+        swing.clamp(STRAIGHT, MAX_SWING)
+    } else {
+        STRAIGHT
+    }
+}
+
+/**
+    Random, but reproducible, timing and velocity jitter, so that a pattern sounds played rather than programmed: `pattern.humanize(10ms, .1)` in the language. The same seed jitters the same steps the same way, every time it loops.
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Humanize {
+    /// (at most this early or late, in seconds)
+    pub timing: f64,
+    /// (at most this much softer or harder, relative to the velocity)
+    pub velocity: f32,
+    pub seed: u64,
+}
+
+impl Humanize {
+    /**
+        How much earlier or later (in seconds), and how much harder, a step plays
+    */
+    pub fn jitter(&self, step: usize) -> (f64, f32) {
+        let timing = random(self.seed, step as u64 * 2) * self.timing;
+        let velocity = random(self.seed, step as u64 * 2 + 1) as f32 * self.velocity;
+        (timing, 1.0 + velocity)
+    }
+}
+
+/**
+    Plays some of a pattern's steps as another pattern (the one that a function in the code made of it) does, randomly, but the same every time: `pattern.sometimes(.3, fx)` in the language, whose seed comes from where it is in the code.
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sometimes {
+    /// (how often, from 0 to 1)
+    pub probability: f64,
+    pub seed: u64,
+}
+
+impl Sometimes {
+    /**
+        Whether a step plays as the other pattern
+    */
+    pub fn applies(&self, step: usize) -> bool {
+        (random(self.seed, step as u64) + 1.0) / 2.0 < self.probability
+    }
+}
+
+/**
+    A random number in [-1, 1), the same for the same seed and index (it's SplitMix64)
+*/
+fn random(seed: u64, i: u64) -> f64 {
+    let mut z = seed.wrapping_add(i.wrapping_add(1).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^= z >> 31;
+
+    (z >> 11) as f64 / (1u64 << 53) as f64 * 2.0 - 1.0
+}
+
+/**
+    How a pattern's steps are placed in time: swung (every second 16th comes late), and humanized. Patterns follow the transport's swing, unless they have one of their own, like `pattern.swing(.56)`.
+*/
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Groove {
+    /// Where the second step of every pair lands, as a part of the pair: .5 is straight, and about .66 is a triplet shuffle
+    pub swing: Option<f64>,
+    pub humanize: Option<Humanize>,
+}
+
+impl Groove {
+    /**
+        When (in beats, from the start of the pattern) and how hard a step plays
+    */
+    pub fn place(&self, step: usize, velocity: f32, transport: &TransportState) -> (f64, f32) {
+        let swing = clamp_swing(self.swing.unwrap_or(transport.swing));
+
+        // (the first step of every pair is on the grid, and the second is pushed back)
+        let pair = (step / 2) as f64 * 2.0;
+        let offbeat = (step % 2) as f64;
+        let steps = pair + offbeat * 2.0 * swing;
+        let mut beat = steps / STEPS_PER_BEAT;
+        let mut velocity = velocity;
+
+        if let Some(humanize) = &self.humanize {
+            let (timing, factor) = humanize.jitter(step);
+            beat += timing * transport.tempo / 60.0;
+            velocity = (velocity * factor).clamp(0.0, 1.0);
+        }
+
+        (beat.max(0.0), velocity)
+    }
+}
+
+#[test]
+fn test_boundaries() {
+    assert_eq!(next_boundary(0.5, Quantize::Bar), 4.0);
+    assert_eq!(next_boundary(5.5, Quantize::Bar), 8.0);
+    // (right on a boundary, it lands right away)
+    assert_eq!(next_boundary(8.0, Quantize::Bar), 8.0);
+    assert_eq!(next_boundary(5.5, Quantize::Phrase), 16.0);
+    assert_eq!(next_boundary(5.5, Quantize::Now), 5.5);
+
+    let state = TransportState {
+        tempo: 120.0,
+        quantize: Quantize::Bar,
+        swing: STRAIGHT,
+        beat: 3.0,
+        pending: vec![],
+        launcher: LauncherState::default(),
+    };
+    assert_eq!(state.until_boundary(), Duration::from_millis(500));
+}
+
+#[test]
+fn test_groove() {
+    let transport = TransportState::default();
+
+    let straight = Groove::default();
+    assert_eq!(straight.place(1, 1.0, &transport), (0.25, 1.0));
+
+    let swung = Groove {
+        swing: Some(0.75),
+        humanize: None,
+    };
+    assert_eq!(swung.place(2, 1.0, &transport), (0.5, 1.0));
+    assert_eq!(swung.place(3, 1.0, &transport), (0.875, 1.0));
+
+    // (patterns without a swing of their own follow the transport's, and too much is too much)
+    let transport = TransportState {
+        swing: 2.0,
+        ..TransportState::default()
+    };
+    assert_eq!(straight.place(1, 1.0, &transport), (0.375, 1.0));
+
+    let humanize = Humanize {
+        timing: 0.01,
+        velocity: 0.1,
+        seed: 7,
+    };
+    let (timing, velocity) = humanize.jitter(5);
+    assert_eq!(humanize.jitter(5), (timing, velocity));
+    assert!(timing.abs() <= 0.01 && (0.9..=1.1).contains(&velocity));
+    let reseeded = Humanize {
+        seed: 8,
+        ..humanize
+    };
+    assert_ne!(reseeded.jitter(5), (timing, velocity));
+}
+
+#[test]
+fn test_sometimes() {
+    let sometimes = Sometimes {
+        probability: 0.3,
+        seed: 7,
+    };
+    let applied = (0..1000)
+        .filter(|&step| sometimes.applies(step))
+        .collect::<Vec<_>>();
+    assert!((250..350).contains(&applied.len()));
+    assert!(applied.iter().all(|&step| sometimes.applies(step)));
+
+    let never = Sometimes {
+        probability: 0.0,
+        ..sometimes
+    };
+    let always = Sometimes {
+        probability: 1.0,
+        ..sometimes
+    };
+    assert!((0..1000).all(|step| !never.applies(step) && always.applies(step)));
+}
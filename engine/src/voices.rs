@@ -0,0 +1,609 @@
+use std::collections::HashMap;
+
+use crate::{
+    block::{self, BLOCK_SIZE},
+    bus::Routing,
+    midi::{MidiEvent, Tuning, MIDI_FREQ, MIDI_GATE, MIDI_PITCH, MIDI_VELOCITY},
+    node::AudioNode,
+    realtime::permit,
+    SAMPLE_RATE,
+};
+
+/// Every voice gets its own note, as these parameters (and as the `midi.*` ones, so that a synth that was written for `midi_in` also plays polyphonically)
+pub const VOICE_FREQ: &str = "freq";
+pub const VOICE_PITCH: &str = "pitch";
+pub const VOICE_VELOCITY: &str = "velocity";
+
+/// A voice that's stolen is faded out this fast (5ms), instead of cut off, so it doesn't click
+const STEAL_SAMPLES: f32 = SAMPLE_RATE as f32 / 200.0;
+
+/// Which voice makes room for a new note, when all of them are playing already
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Stealing {
+    /// The one that started first (preferring the ones that were released already)
+    #[default]
+    Oldest,
+    /// The one whose envelope is lowest
+    Quietest,
+    /// None of them, the new note is dropped instead
+    Off,
+}
+
+/// Attack, decay and release in seconds, and the sustain level (0..1)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Adsr {
+    pub attack: f32,
+    pub decay: f32,
+    pub sustain: f32,
+    pub release: f32,
+}
+
+impl Default for Adsr {
+    fn default() -> Self {
+        Self {
+            attack: 0.005,
+            decay: 0.1,
+            sustain: 0.7,
+            release: 0.2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Stage {
+    Attack,
+    Decay,
+    Sustain,
+    // (going down by this much per sample)
+    Release(f32),
+    Done,
+}
+
+struct Voice {
+    note: u8,
+    node: Box<dyn AudioNode + Send>,
+    // (when it started, to tell which one's the oldest)
+    started: u64,
+    stage: Stage,
+    level: f32,
+    stolen: bool,
+}
+
+impl Voice {
+    fn release(&mut self, samples: f32) {
+        if !self.released() {
+            self.stage = Stage::Release(self.level / samples.max(1.0));
+            self.node.apply(MIDI_GATE, 0.0);
+        }
+    }
+
+    fn released(&self) -> bool {
+        matches!(self.stage, Stage::Release(_) | Stage::Done)
+    }
+
+    /**
+        Advances the envelope one sample
+    */
+    fn envelope(&mut self, adsr: &Adsr) -> f32 {
+        let per_sample = |seconds: f32| 1.0 / (seconds * SAMPLE_RATE as f32).max(1.0);
+
+        match self.stage {
+            Stage::Attack => {
+                self.level += per_sample(adsr.attack);
+                if self.level >= 1.0 {
+                    self.level = 1.0;
+                    self.stage = Stage::Decay;
+                }
+            }
+            Stage::Decay => {
+                self.level -= (1.0 - adsr.sustain) * per_sample(adsr.decay);
+                if self.level <= adsr.sustain {
+                    self.level = adsr.sustain;
+                    self.stage = Stage::Sustain;
+                }
+            }
+            Stage::Sustain => {
+                // (so changing the sustain while a note is held still does something)
+                self.level = adsr.sustain;
+            }
+            Stage::Release(step) => {
+                self.level -= step;
+                if self.level <= 0.0 {
+                    self.level = 0.0;
+                    self.stage = Stage::Done;
+                }
+            }
+            Stage::Done => {}
+        }
+
+        self.level
+    }
+}
+
+/**
+    Plays every note (from MIDI, or triggered by a pattern) on a voice of its own, made from a synth definition, with its own envelope and its own note parameters (`freq`, `pitch`, `velocity`). When all voices are playing, one of them is stolen for the new note.
+
+    The voices are either made per note, or (see `Poly::pooled`) made up front and played again and again, like the ones the editor compiles.
+*/
+pub struct Poly {
+    synth: Option<Box<dyn Fn() -> Box<dyn AudioNode + Send> + Send>>,
+    voices: Vec<Voice>,
+    // (the pooled voices that aren't playing)
+    spare: Vec<Box<dyn AudioNode + Send>>,
+
+    // parameters
+    adsr: Adsr,
+    max_voices: usize,
+    stealing: Stealing,
+
+    // audio node helper stuff
+    named_parameters: HashMap<String, String>,
+    // (what was applied from the outside, so new voices get it too)
+    applied: HashMap<String, f32>,
+    // (and whether they have to economize)
+    economizing: bool,
+    // (and what frequency every note is)
+    tuning: Tuning,
+
+    // state
+    notes_played: u64,
+    out: f32,
+}
+
+impl Poly {
+    pub fn new(synth: impl Fn() -> Box<dyn AudioNode + Send> + Send + 'static) -> Self {
+        Self {
+            synth: Some(Box::new(synth)),
+            voices: vec![],
+            spare: vec![],
+            adsr: Adsr::default(),
+            max_voices: 8,
+            stealing: Stealing::default(),
+            named_parameters: HashMap::new(),
+            applied: HashMap::new(),
+            economizing: false,
+            tuning: Tuning::default(),
+            notes_played: 0,
+            out: 0.0,
+        }
+    }
+
+    /**
+        Plays the nodes, which are all there is to play (so that nothing's made on the audio thread). With a few more of them than `max_voices`, the stolen voices fade out, and otherwise they're cut off when a new note needs them.
+    */
+    pub fn pooled(nodes: Vec<Box<dyn AudioNode + Send>>) -> Self {
+        let mut poly = Self::new(|| -> Box<dyn AudioNode + Send> { unreachable!() });
+        poly.synth = None;
+        poly.voices = Vec::with_capacity(nodes.len());
+        poly.spare = nodes;
+        poly
+    }
+
+    pub fn max_voices(mut self, max_voices: usize) -> Self {
+        self.max_voices = max_voices.max(1);
+        self
+    }
+
+    pub fn stealing(mut self, stealing: Stealing) -> Self {
+        self.stealing = stealing;
+        self
+    }
+
+    pub fn envelope(mut self, adsr: Adsr) -> Self {
+        self.adsr = adsr;
+        self
+    }
+
+    fn note_on(&mut self, note: u8, velocity: f32) {
+        // (playing a note that's still held restarts it)
+        self.note_off(note);
+
+        // (and one that isn't tuned doesn't play)
+        let Some(freq) = self.tuning.freq(note) else {
+            return;
+        };
+
+        let playing = self.voices.iter().filter(|v| !v.stolen).count();
+        if playing >= self.max_voices {
+            let candidates = self.voices.iter_mut().filter(|v| !v.stolen);
+            let victim = match self.stealing {
+                Stealing::Oldest => candidates.min_by_key(|v| (!v.released(), v.started)),
+                Stealing::Quietest => candidates.min_by(|a, b| a.level.total_cmp(&b.level)),
+                Stealing::Off => return,
+            };
+
+            if let Some(victim) = victim {
+                victim.stolen = true;
+                victim.release(STEAL_SAMPLES);
+            }
+        }
+
+        let Some(mut node) = self.voice() else {
+            return;
+        };
+        for (name, value) in &self.applied {
+            node.apply(name, *value);
+        }
+        node.economize(self.economizing);
+
+        for (name, value) in [
+            (VOICE_FREQ, freq),
+            (VOICE_PITCH, note as f32),
+            (VOICE_VELOCITY, velocity),
+            (MIDI_FREQ, freq),
+            (MIDI_PITCH, note as f32),
+            (MIDI_VELOCITY, velocity),
+            (MIDI_GATE, 1.0),
+        ] {
+            node.apply(name, value);
+        }
+
+        self.notes_played += 1;
+        // (and every now and then the voices need more room)
+        permit(|| {
+            self.voices.push(Voice {
+                note,
+                node,
+                started: self.notes_played,
+                stage: Stage::Attack,
+                level: 0.0,
+                stolen: false,
+            })
+        });
+    }
+
+    /// (a new voice is a new node, made right here on the audio thread, once per note, or a spare one)
+    fn voice(&mut self) -> Option<Box<dyn AudioNode + Send>> {
+        if let Some(synth) = &self.synth {
+            return Some(permit(synth));
+        }
+
+        if self.spare.is_empty() {
+            let stolen = (self.voices.iter())
+                .enumerate()
+                .filter(|(_, v)| v.stolen)
+                .min_by_key(|(_, v)| v.started)
+                .map(|(i, _)| i)?;
+            let voice = self.voices.remove(stolen);
+            return Some(voice.node);
+        }
+
+        self.spare.pop()
+    }
+
+    fn note_off(&mut self, note: u8) {
+        let release = self.adsr.release * SAMPLE_RATE as f32;
+        for voice in &mut self.voices {
+            if voice.note == note && !voice.stolen {
+                voice.release(release);
+            }
+        }
+    }
+
+    /// (voices that are done are freed right here too, once per note, since a node can't throw anything in the processor's garbage, or they're spare again)
+    fn forget_done(&mut self) {
+        if self.synth.is_some() {
+            permit(|| self.voices.retain(|voice| voice.stage != Stage::Done));
+            return;
+        }
+
+        while let Some(i) = self.voices.iter().position(|v| v.stage == Stage::Done) {
+            let voice = self.voices.remove(i);
+            self.spare.push(voice.node);
+        }
+    }
+}
+
+impl AudioNode for Poly {
+    fn parameters(&self) -> Vec<String> {
+        ["attack", "decay", "sustain", "release", "voices"]
+            .map(String::from)
+            .to_vec()
+    }
+
+    fn map(&mut self, name: String, parameter: String) {
+        self.named_parameters.insert(name, parameter);
+    }
+
+    fn apply(&mut self, param: &str, value: f32) {
+        let param = self
+            .named_parameters
+            .get(param)
+            .map_or(param, |actual| actual.as_str());
+
+        match param {
+            "attack" => self.adsr.attack = value.max(0.0),
+            "decay" => self.adsr.decay = value.max(0.0),
+            "sustain" => self.adsr.sustain = value.clamp(0.0, 1.0),
+            "release" => self.adsr.release = value.max(0.0),
+            "voices" => self.max_voices = (value as usize).max(1),
+            // (those are per voice, the single `midi_in` signal doesn't apply)
+            MIDI_FREQ | MIDI_PITCH | MIDI_VELOCITY | MIDI_GATE => {}
+            _ => {
+                match self.applied.get_mut(param) {
+                    Some(applied) => *applied = value,
+                    None => {
+                        permit(|| self.applied.insert(param.to_string(), value));
+                    }
+                }
+                for voice in &mut self.voices {
+                    voice.node.apply(param, value);
+                }
+                for node in &mut self.spare {
+                    node.apply(param, value);
+                }
+            }
+        }
+    }
+
+    fn note(&mut self, event: MidiEvent) {
+        match event {
+            MidiEvent::NoteOn { note, velocity } => self.note_on(note, velocity),
+            MidiEvent::NoteOff { note } => self.note_off(note),
+        }
+    }
+
+    fn retune(&mut self, tuning: &Tuning) {
+        // (the notes that are playing keep their frequency)
+        self.tuning = tuning.clone();
+    }
+
+    fn route(&self, routing: &mut Routing) {
+        match &self.synth {
+            // (the voices that will play don't exist yet, but they'll route like a new one does)
+            Some(synth) => synth().route(routing),
+            None => {
+                for voice in &self.voices {
+                    voice.node.route(routing);
+                }
+                for node in &self.spare {
+                    node.route(routing);
+                }
+            }
+        }
+    }
+
+    fn economize(&mut self, economize: bool) {
+        self.economizing = economize;
+        for voice in &mut self.voices {
+            voice.node.economize(economize);
+        }
+        for node in &mut self.spare {
+            node.economize(economize);
+        }
+    }
+
+    fn tick(&mut self) {
+        let adsr = self.adsr;
+        let mut sum = 0.0;
+
+        for voice in &mut self.voices {
+            let level = voice.envelope(&adsr);
+            voice.node.tick();
+            sum += voice.node.get_next_sample() * level;
+        }
+
+        self.forget_done();
+        self.out = sum;
+    }
+
+    fn get_next_sample(&self) -> f32 {
+        self.out
+    }
+
+    fn process(&mut self, out: &mut [f32]) {
+        let n = out.len();
+        if n == 0 {
+            return;
+        }
+        out.fill(0.0);
+
+        let adsr = self.adsr;
+        let mut samples = [0.0; BLOCK_SIZE];
+        let mut levels = [0.0; BLOCK_SIZE];
+
+        for voice in &mut self.voices {
+            for level in &mut levels[..n] {
+                *level = voice.envelope(&adsr);
+            }
+            voice.node.process(&mut samples[..n]);
+            block::add_scaled(out, &samples[..n], &levels[..n]);
+        }
+
+        self.forget_done();
+        self.out = out[n - 1];
+    }
+}
+
+#[cfg(test)]
+fn synth() -> Box<dyn AudioNode + Send> {
+    use crate::node::Osc;
+
+    let mut osc = Osc::default();
+    osc.map(VOICE_FREQ.into(), "frequency".into());
+    Box::new(osc)
+}
+
+#[test]
+fn test_voice_allocation() {
+    let mut poly = Poly::new(synth).max_voices(2);
+
+    poly.note(MidiEvent::NoteOn {
+        note: 60,
+        velocity: 1.0,
+    });
+    poly.note(MidiEvent::NoteOn {
+        note: 64,
+        velocity: 1.0,
+    });
+    assert_eq!(poly.voices.len(), 2);
+
+    // the oldest one makes room
+    poly.note(MidiEvent::NoteOn {
+        note: 67,
+        velocity: 1.0,
+    });
+    assert_eq!(
+        poly.voices
+            .iter()
+            .map(|v| (v.note, v.stolen))
+            .collect::<Vec<_>>(),
+        vec![(60, true), (64, false), (67, false)]
+    );
+
+    for _ in 0..1000 {
+        poly.tick();
+    }
+    assert_eq!(
+        poly.voices.iter().map(|v| v.note).collect::<Vec<_>>(),
+        vec![64, 67]
+    );
+
+    // and released notes fade out and are gone
+    poly.note(MidiEvent::NoteOff { note: 64 });
+    poly.note(MidiEvent::NoteOff { note: 67 });
+    for _ in 0..(SAMPLE_RATE / 4) {
+        poly.tick();
+    }
+    assert!(poly.voices.is_empty());
+    assert_eq!(poly.get_next_sample(), 0.0);
+
+    let mut poly = Poly::new(synth).max_voices(1).stealing(Stealing::Off);
+    poly.note(MidiEvent::NoteOn {
+        note: 60,
+        velocity: 1.0,
+    });
+    poly.note(MidiEvent::NoteOn {
+        note: 64,
+        velocity: 1.0,
+    });
+    assert_eq!(
+        poly.voices.iter().map(|v| v.note).collect::<Vec<_>>(),
+        vec![60]
+    );
+}
+
+#[test]
+fn test_voices_have_their_own_note() {
+    let voice = |note: u8| {
+        let mut poly = Poly::new(synth);
+        poly.note(MidiEvent::NoteOn {
+            note,
+            velocity: 1.0,
+        });
+        (0..SAMPLE_RATE as usize / 10)
+            .map(|_| {
+                poly.tick();
+                poly.get_next_sample()
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // (an octave up crosses zero twice as often)
+    let crossings = |samples: Vec<f32>| {
+        samples
+            .windows(2)
+            .filter(|w| w[0] < 0.0 && w[1] >= 0.0)
+            .count()
+    };
+    let low = crossings(voice(57));
+    let high = crossings(voice(69));
+    assert!(low.abs_diff(22) <= 1, "{}", low);
+    assert!(high.abs_diff(44) <= 1, "{}", high);
+}
+
+#[test]
+fn test_pooled_voices() {
+    let mut poly = Poly::pooled(vec![synth(), synth(), synth()]).max_voices(2);
+    for note in [60, 64, 67] {
+        poly.note(MidiEvent::NoteOn {
+            note,
+            velocity: 1.0,
+        });
+    }
+    // (the oldest one makes room, and while it fades out, there's no voice for another one)
+    assert_eq!(
+        poly.voices
+            .iter()
+            .map(|v| (v.note, v.stolen))
+            .collect::<Vec<_>>(),
+        vec![(60, true), (64, false), (67, false)]
+    );
+    assert!(poly.spare.is_empty());
+    poly.note(MidiEvent::NoteOn {
+        note: 72,
+        velocity: 1.0,
+    });
+    assert_eq!(
+        poly.voices
+            .iter()
+            .map(|v| (v.note, v.stolen))
+            .collect::<Vec<_>>(),
+        vec![(64, true), (67, false), (72, false)]
+    );
+
+    // (and released notes are spare again)
+    poly.note(MidiEvent::NoteOff { note: 67 });
+    poly.note(MidiEvent::NoteOff { note: 72 });
+    for _ in 0..(SAMPLE_RATE / 4) {
+        poly.tick();
+    }
+    assert!(poly.voices.is_empty());
+    assert_eq!(poly.spare.len(), 3);
+}
+
+#[test]
+fn test_tuning() {
+    // (everything a fifth lower, without `a4`)
+    let tuning =
+        Tuning::new(|note| (note != 69).then(|| 440.0 * 2f32.powf((note as f32 - 76.0) / 12.0)));
+
+    let mut poly = Poly::new(synth);
+    poly.retune(&tuning);
+    poly.note(MidiEvent::NoteOn {
+        note: 69,
+        velocity: 1.0,
+    });
+    assert!(poly.voices.is_empty());
+
+    poly.note(MidiEvent::NoteOn {
+        note: 76,
+        velocity: 1.0,
+    });
+    let samples = (0..SAMPLE_RATE as usize / 10)
+        .map(|_| {
+            poly.tick();
+            poly.get_next_sample()
+        })
+        .collect::<Vec<_>>();
+    let crossings = samples
+        .windows(2)
+        .filter(|w| w[0] < 0.0 && w[1] >= 0.0)
+        .count();
+    assert!(crossings.abs_diff(44) <= 1, "{}", crossings);
+}
+
+#[test]
+fn test_envelope_golden() {
+    use crate::golden::{assert_golden, render, render_blocks};
+
+    for render in [render, render_blocks] {
+        // (short enough that all of it fits in a tenth of a second)
+        let mut poly = Poly::new(synth).envelope(Adsr {
+            attack: 0.01,
+            decay: 0.02,
+            sustain: 0.5,
+            release: 0.03,
+        });
+
+        poly.note(MidiEvent::NoteOn {
+            note: 57,
+            velocity: 1.0,
+        });
+        let mut samples = render(&mut poly, SAMPLE_RATE as usize / 20);
+        poly.note(MidiEvent::NoteOff { note: 57 });
+        samples.extend(render(&mut poly, SAMPLE_RATE as usize / 20));
+
+        assert_golden("envelope", &samples);
+    }
+}
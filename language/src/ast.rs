@@ -3,23 +3,26 @@ use std::{
     ops::Range,
 };
 
-use crate::parse::{span_range, Span};
+use crate::{
+    color::format_color,
+    span::{Loc, SourceSpan},
+};
 
 #[derive(Clone, PartialEq, Eq)]
 pub struct SyntaxNode<T> {
-    range: Option<Range<usize>>,
+    span: Option<SourceSpan>,
     pub node: Option<Box<T>>,
 }
 
 impl<T> SyntaxNode<T> {
     pub const MISSING: SyntaxNode<T> = SyntaxNode {
-        range: None,
+        span: None,
         node: None,
     };
 
-    pub fn new(range: Option<Range<usize>>, node: Option<T>) -> Self {
+    pub fn new(span: Option<SourceSpan>, node: Option<T>) -> Self {
         Self {
-            range,
+            span,
             node: node.map(Box::new),
         }
     }
@@ -29,13 +32,18 @@ impl<T> SyntaxNode<T> {
         F: FnOnce(T) -> U,
     {
         SyntaxNode {
-            range: self.range.clone(),
+            span: self.span,
             node: self.node.map(|box x| f(x)).map(Box::new),
         }
     }
 
+    pub fn span(&self) -> Option<SourceSpan> {
+        self.span
+    }
+
+    /// (just the bytes of the span)
     pub fn range(&self) -> Option<Range<usize>> {
-        self.range.clone()
+        self.span.map(|span| span.range())
     }
 
     // pub fn boxify(self) -> SyntaxNode<Box<T>> {
@@ -53,41 +61,29 @@ impl<T> SyntaxNode<T> {
 //     // fn children(&self) -> Vec<dyn Syntax>
 // }
 
-pub fn cover_ranges(a: Option<Range<usize>>, b: Option<Range<usize>>) -> Option<Range<usize>> {
+pub fn cover_ranges(a: Option<SourceSpan>, b: Option<SourceSpan>) -> Option<SourceSpan> {
     match (a, b) {
         (None, None) => None,
         (Some(a), None) => Some(a),
         (None, Some(b)) => Some(b),
-        (Some(a), Some(b)) => Some(Range {
-            start: a.start.min(b.start),
-            end: a.end.max(b.end),
-        }),
+        (Some(a), Some(b)) => Some(a.cover(b)),
     }
 }
 
-pub fn extend_range_end(a: Option<Range<usize>>, end: usize) -> Option<Range<usize>> {
+pub fn extend_range_end(a: Option<SourceSpan>, end: Loc) -> Option<SourceSpan> {
     match a {
         None => None,
-        Some(a) => Some(Range {
+        Some(a) => Some(SourceSpan {
             start: a.start,
             end,
         }),
     }
 }
 
-impl<'a, T> From<(Option<Range<usize>>, T)> for SyntaxNode<T> {
-    fn from((range, node): (Option<Range<usize>>, T)) -> Self {
-        Self {
-            range,
-            node: Some(Box::new(node)),
-        }
-    }
-}
-
-impl<'a, T> From<(Span<'a>, T)> for SyntaxNode<T> {
-    fn from((span, node): (Span<'a>, T)) -> Self {
+impl<T> From<(Option<SourceSpan>, T)> for SyntaxNode<T> {
+    fn from((span, node): (Option<SourceSpan>, T)) -> Self {
         Self {
-            range: Some(span_range(&span)),
+            span,
             node: Some(Box::new(node)),
         }
     }
@@ -96,7 +92,7 @@ impl<'a, T> From<(Span<'a>, T)> for SyntaxNode<T> {
 impl<T> From<T> for SyntaxNode<T> {
     fn from(node: T) -> Self {
         Self {
-            range: None,
+            span: None,
             node: Some(Box::new(node)),
         }
     }
@@ -131,6 +127,7 @@ pub enum Unit {
     S,
     Khz,
     Hz,
+    Db,
 }
 
 #[derive(Clone, PartialEq)]
@@ -140,6 +137,10 @@ pub enum Primitive {
     Int(i64),
     Quantity((f64, SyntaxNode<Unit>)),
     Str(String),
+    // (RGBA)
+    Color([u8; 4]),
+    // (as it's written, like `a#4`)
+    Note(String),
 }
 
 #[derive(Clone, PartialEq, Eq)]
@@ -151,6 +152,26 @@ pub enum Op {
     Sub,
     Mul,
     Div,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+}
+
+impl Op {
+    /// `==`, `<` etc., which are true or false
+    pub fn is_comparison(&self) -> bool {
+        matches!(self, Op::Eq | Op::Ne | Op::Lt | Op::Le | Op::Gt | Op::Ge)
+    }
+
+    /// `&&` and `||`
+    pub fn is_logical(&self) -> bool {
+        matches!(self, Op::And | Op::Or)
+    }
 }
 
 #[derive(Clone, PartialEq)]
@@ -212,17 +233,43 @@ pub struct CallExpr {
     pub args: Vec<SyntaxNode<Expr>>,
 }
 
+/// One of the modifiers in `expr%[..]`, or the settings in `builtin{..}`
+#[derive(Clone, PartialEq)]
+pub enum Modifier {
+    Arg(SyntaxNode<Expr>),
+    Setting(SyntaxNode<Identifier>, SyntaxNode<Expr>),
+}
+
+/// A piece of an interpolated string, like `"kicks/${name}.wav"`
+#[derive(Clone, PartialEq)]
+pub enum StrPart {
+    Text(String),
+    Expr(SyntaxNode<Expr>),
+}
+
 #[derive(Clone, PartialEq)]
 pub enum Expr {
     Prim(SyntaxNode<Primitive>),
     Call(CallExpr),
     Var(SyntaxNode<Identifier>),
     BinOp(SyntaxNode<Expr>, Op, SyntaxNode<Expr>),
+    Not(SyntaxNode<Expr>),
+    // (the `else` is either a block, or another `if`)
+    If(
+        SyntaxNode<Expr>,
+        SyntaxNode<Block>,
+        Option<SyntaxNode<Expr>>,
+    ),
     Paren(SyntaxNode<Expr>),
     Block(SyntaxNode<Block>),
     AnonymousFn(SyntaxNode<AnonymousFn>),
     Index(SyntaxNode<Expr>, SyntaxNode<Expr>),
     Member(SyntaxNode<Expr>, SyntaxNode<Identifier>),
+    Modify(SyntaxNode<Expr>, Vec<Modifier>),
+    Settings(SyntaxNode<Expr>, Vec<Modifier>),
+    Interpolated(Vec<StrPart>),
+    Array(Vec<SyntaxNode<Expr>>),
+    Tuple(Vec<SyntaxNode<Expr>>),
 }
 
 // impl GetChildRanges for Expr {
@@ -248,6 +295,7 @@ impl Display for Unit {
             S => write!(f, "s"),
             Khz => write!(f, "khz"),
             Hz => write!(f, "hz"),
+            Db => write!(f, "db"),
         }
     }
 }
@@ -260,6 +308,7 @@ impl From<&str> for Unit {
             "s" => Self::S,
             "khz" => Self::Khz,
             "hz" => Self::Hz,
+            "db" => Self::Db,
             _ => panic!(),
         }
     }
@@ -296,6 +345,8 @@ impl Debug for Primitive {
             Quantity((val, unit)) => write!(f, "{val}{unit}"),
             // TODO improve (?) not actually necessary for debug, but, technically it's incorrect for "real" code generation purposes
             Str(val) => write!(f, r#""{val}""#),
+            Color(rgba) => write!(f, "{}", format_color(*rgba)),
+            Note(name) => write!(f, "{name}"),
         }
     }
 }
@@ -346,11 +397,73 @@ impl Display for Expr {
             Call(call) => write!(f, "{}", call),
             Var(id) => write!(f, "{}", id),
             BinOp(left, op, right) => write!(f, "{} {} {}", left, op, right),
+            Not(expr) => write!(f, "!{}", expr),
+            If(cond, then, otherwise) => {
+                write!(f, "if {} {}", cond, then)?;
+                if let Some(otherwise) = otherwise {
+                    write!(f, " else {}", otherwise)?;
+                }
+                Ok(())
+            }
             Paren(expr) => write!(f, "({})", expr),
             Block(block) => write!(f, "{}", block),
             AnonymousFn(fun) => write!(f, "{}", fun),
             Index(a, b) => write!(f, "{}[{}]", a, b),
             Member(a, b) => write!(f, "{}.{}", a, b),
+            Modify(a, modifiers) => {
+                write!(f, "{}%[", a)?;
+                for (i, modifier) in modifiers.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", modifier)?;
+                }
+                write!(f, "]")
+            }
+            Settings(a, settings) => {
+                write!(f, "{}{{", a)?;
+                for (i, setting) in settings.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", setting)?;
+                }
+                write!(f, "}}")
+            }
+            Interpolated(parts) => {
+                write!(f, "\"")?;
+                for part in parts {
+                    match part {
+                        StrPart::Text(text) => write!(f, "{}", text)?,
+                        StrPart::Expr(expr) => write!(f, "${{{}}}", expr)?,
+                    }
+                }
+                write!(f, "\"")
+            }
+            Array(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+            Tuple(items) => {
+                write!(f, "(")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                // (so that it's not just parenthesized)
+                if items.len() == 1 {
+                    write!(f, ",")?;
+                }
+                write!(f, ")")
+            }
         }
     }
 }
@@ -363,11 +476,91 @@ impl Debug for Expr {
             Call(call) => write!(f, "{}", call),
             Var(id) => write!(f, "{}", id),
             BinOp(left, op, right) => write!(f, "({:?} {} {:?})", left, op, right),
+            Not(expr) => write!(f, "!{:?}", expr),
+            If(cond, then, otherwise) => {
+                write!(f, "if {:?} {:?}", cond, then)?;
+                if let Some(otherwise) = otherwise {
+                    write!(f, " else {:?}", otherwise)?;
+                }
+                Ok(())
+            }
             Paren(expr) => write!(f, "({:?})", expr),
             Block(block) => write!(f, "{:?}", block),
             AnonymousFn(fun) => write!(f, "{:?}", fun),
             Index(a, b) => write!(f, "{:?}[{:?}]", a, b),
             Member(a, b) => write!(f, "{:?}.{:?}", a, b),
+            Modify(a, modifiers) => {
+                write!(f, "{:?}%[", a)?;
+                for (i, modifier) in modifiers.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{:?}", modifier)?;
+                }
+                write!(f, "]")
+            }
+            Settings(a, settings) => {
+                write!(f, "{:?}{{", a)?;
+                for (i, setting) in settings.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{:?}", setting)?;
+                }
+                write!(f, "}}")
+            }
+            Interpolated(parts) => {
+                write!(f, "\"")?;
+                for part in parts {
+                    match part {
+                        StrPart::Text(text) => write!(f, "{}", text)?,
+                        StrPart::Expr(expr) => write!(f, "${{{:?}}}", expr)?,
+                    }
+                }
+                write!(f, "\"")
+            }
+            Array(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{:?}", item)?;
+                }
+                write!(f, "]")
+            }
+            Tuple(items) => {
+                write!(f, "(")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{:?}", item)?;
+                }
+                // (so that it's not just parenthesized)
+                if items.len() == 1 {
+                    write!(f, ",")?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+impl Display for Modifier {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Modifier::Arg(expr) => write!(f, "{}", expr),
+            Modifier::Setting(name, value) => write!(f, "{} = {}", name, value),
+        }
+    }
+}
+
+impl Debug for Modifier {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Modifier::Arg(expr) => write!(f, "{:?}", expr),
+            Modifier::Setting(name, value) => write!(f, "{:?} = {:?}", name, value),
         }
     }
 }
@@ -408,6 +601,14 @@ impl Display for Op {
             Sub => write!(f, "-"),
             Mul => write!(f, "*"),
             Div => write!(f, "/"),
+            Eq => write!(f, "=="),
+            Ne => write!(f, "!="),
+            Lt => write!(f, "<"),
+            Le => write!(f, "<="),
+            Gt => write!(f, ">"),
+            Ge => write!(f, ">="),
+            And => write!(f, "&&"),
+            Or => write!(f, "||"),
         }
     }
 }
@@ -420,6 +621,14 @@ impl Debug for Op {
             Sub => write!(f, "-"),
             Mul => write!(f, "*"),
             Div => write!(f, "/"),
+            Eq => write!(f, "=="),
+            Ne => write!(f, "!="),
+            Lt => write!(f, "<"),
+            Le => write!(f, "<="),
+            Gt => write!(f, ">"),
+            Ge => write!(f, ">="),
+            And => write!(f, "&&"),
+            Or => write!(f, "||"),
         }
     }
 }
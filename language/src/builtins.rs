@@ -0,0 +1,402 @@
+use crate::check::Dimension::{self, Frequency, Level, Ratio, Time};
+
+/// A built-in node that's configured with settings, like `lowpass{f = 800hz, q = 2}`, and then applied to a signal
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Builtin {
+    pub name: &'static str,
+    pub doc: &'static str,
+    pub settings: &'static [Setting],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Setting {
+    pub name: &'static str,
+    /// What it is when it's not set (in seconds, Hz or dB)
+    pub default: f64,
+    pub dimension: Dimension,
+    pub doc: &'static str,
+}
+
+const fn setting(
+    name: &'static str,
+    default: f64,
+    dimension: Dimension,
+    doc: &'static str,
+) -> Setting {
+    Setting {
+        name,
+        default,
+        dimension,
+        doc,
+    }
+}
+
+impl Builtin {
+    pub fn setting(&self, name: &str) -> Option<(f64, Dimension)> {
+        self.settings
+            .iter()
+            .find(|setting| setting.name == name)
+            .map(|setting| (setting.default, setting.dimension))
+    }
+}
+
+/// (These are the effects the audio engine implements, with the same defaults.)
+pub const BUILTINS: &[Builtin] = &[
+    Builtin {
+        name: "lowpass",
+        doc: "Lets through what's below the cutoff frequency `f`, with resonance `q`",
+        settings: &[
+            setting("f", 1000.0, Frequency, "the cutoff frequency"),
+            setting("q", 0.707, Ratio, "the resonance, a peak right at the cutoff"),
+        ],
+    },
+    Builtin {
+        name: "highpass",
+        doc: "Lets through what's above the cutoff frequency `f`, with resonance `q`",
+        settings: &[
+            setting("f", 1000.0, Frequency, "the cutoff frequency"),
+            setting("q", 0.707, Ratio, "the resonance, a peak right at the cutoff"),
+        ],
+    },
+    Builtin {
+        name: "bandpass",
+        doc: "Lets through what's around the frequency `f`, narrower with a higher `q`",
+        settings: &[
+            setting("f", 1000.0, Frequency, "the frequency in the middle"),
+            setting("q", 0.707, Ratio, "how narrow the band is"),
+        ],
+    },
+    Builtin {
+        name: "delay",
+        doc: "Echoes after `time` seconds, feeding `feedback` of it back in",
+        settings: &[
+            setting("time", 0.25, Time, "how long until the echo"),
+            setting("feedback", 0.4, Ratio, "how much of the echo echoes again"),
+            setting("mix", 0.5, Ratio, "how much of the echoes is heard"),
+        ],
+    },
+    Builtin {
+        name: "reverb",
+        doc: "Freeverb, in a `room` between 0 and 1, with high frequencies `damp`ed",
+        settings: &[
+            setting("room", 0.5, Ratio, "how big the room is, from 0 to 1"),
+            setting("damp", 0.5, Ratio, "how much the walls soak up the high frequencies"),
+            setting("mix", 0.3, Ratio, "how much of the reverb is heard"),
+        ],
+    },
+    Builtin {
+        name: "distortion",
+        doc: "Soft clipping, harder with more `drive`",
+        settings: &[
+            setting("drive", 2.0, Ratio, "how hard it's pushed into clipping"),
+            setting("mix", 1.0, Ratio, "how much of the distorted signal is heard"),
+        ],
+    },
+    Builtin {
+        name: "compressor",
+        doc: "Turns down what's over the `threshold` (in dB) by the `ratio`, with `attack` and `release` in seconds and `makeup` gain in dB. Given a second signal, it listens to that one instead (sidechaining), like `compressor{ratio = 8}(pad, bus(\"kick\"))`",
+        settings: &[
+            setting("threshold", -18.0, Level, "the level above which it turns down"),
+            setting("ratio", 4.0, Ratio, "by how much, like 4 for a quarter of what's over"),
+            setting("attack", 0.01, Time, "how quickly it turns down"),
+            setting("release", 0.1, Time, "how quickly it turns back up"),
+            setting("makeup", 0.0, Level, "how much louder it makes everything afterwards"),
+        ],
+    },
+];
+
+pub fn builtin(name: &str) -> Option<&'static Builtin> {
+    BUILTINS.iter().find(|builtin| builtin.name == name)
+}
+
+/// A built-in function, like `path("kicks/1.wav")`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Function {
+    pub name: &'static str,
+    pub doc: &'static str,
+    pub params: &'static [Param],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Param {
+    pub name: &'static str,
+    /// What it measures, if it's an amount (where a plain number is in seconds or Hz)
+    pub dimension: Option<Dimension>,
+    pub doc: &'static str,
+}
+
+const fn param(name: &'static str, doc: &'static str) -> Param {
+    Param {
+        name,
+        dimension: None,
+        doc,
+    }
+}
+
+const fn amount(name: &'static str, dimension: Dimension, doc: &'static str) -> Param {
+    Param {
+        name,
+        dimension: Some(dimension),
+        doc,
+    }
+}
+
+/// (See `paths` for what the file ones do, and `eval` for the array, random, music and pattern ones, which can also be called like methods: `xs.map(f)` is `map(xs, f)`, and for `watch`, `ease`, `perform` and the timers.)
+pub const FUNCTIONS: &[Function] = &[
+    Function {
+        name: "sin",
+        doc: "A sine wave at a frequency, like `sin(440hz)`, or following a control, like `sin(midi.freq)`",
+        params: &[amount("frequency", Frequency, "")],
+    },
+    Function {
+        name: "square",
+        doc: "A (slightly rounded) square wave at a frequency, like `square(110hz) * .3`, or following a control, like `square(midi.freq)`",
+        params: &[amount("frequency", Frequency, "")],
+    },
+    Function {
+        name: "path",
+        doc: "The file at a path relative to the project root, like `path(\"kicks/${name}.wav\")`",
+        params: &[param("path", "relative to the project root")],
+    },
+    Function {
+        name: "samples",
+        doc: "All files matching a pattern (relative to the project root), in alphabetical order, like `samples(\"kicks/*.wav\")`",
+        params: &[param("pattern", "with `*` for any part of a name, relative to the project root")],
+    },
+    Function {
+        name: "slices",
+        doc: "A loop, cut up where its hits are (which you can move around on its sample widget), as an array of one-shot samples, like `let drums = slices(break)` and then `drums[3]`",
+        params: &[param("sample", "the loop to cut up")],
+    },
+    Function {
+        name: "map",
+        doc: "Applies a function to every element of an array, like `[1, 2, 3].map(_ * .2s)`",
+        params: &[param("array", ""), param("f", "what's done with each element")],
+    },
+    Function {
+        name: "filter",
+        doc: "The elements of an array for which a function is true",
+        params: &[param("array", ""), param("f", "whether to keep an element")],
+    },
+    Function {
+        name: "sum",
+        doc: "Adds up the elements of an array, like `partials.sum()`",
+        params: &[param("array", "")],
+    },
+    Function {
+        name: "zip",
+        doc: "Pairs up the elements of two arrays, like `zip(freqs, gains)`, up to the shortest one",
+        params: &[param("array", ""), param("other", "")],
+    },
+    Function {
+        name: "rand",
+        doc: "A random number from 0 to 1, like `400hz + rand() * 100hz`. It's the same every time the code is evaluated (where other code changing doesn't matter), until it's reseeded (with Cmd+;).",
+        params: &[],
+    },
+    Function {
+        name: "choose",
+        doc: "A random element of an array, like `[220hz, 330hz, 440hz].choose()`, the same every time until it's reseeded (like `rand`)",
+        params: &[param("array", "what's chosen from")],
+    },
+    Function {
+        name: "shuffle",
+        doc: "An array in a random order, like `notes.shuffle()`, the same every time until it's reseeded (like `rand`)",
+        params: &[param("array", "")],
+    },
+    Function {
+        name: "scale",
+        doc: "The notes of a scale from a root note, as an array of frequencies, like `scale(\"minor\", a3)`. There's \"major\", \"minor\", \"harmonic minor\", \"melodic minor\", the modes (like \"dorian\"), \"pentatonic\", \"minor pentatonic\", \"blues\", \"whole tone\" and \"chromatic\".",
+        params: &[
+            param("name", "like \"minor\""),
+            amount("root", Frequency, "the note it starts from, like `a3`"),
+        ],
+    },
+    Function {
+        name: "chord",
+        doc: "The notes of a chord from a root note, as an array of frequencies, like `chord(\"min7\", d3)`. There's \"major\", \"minor\", \"dim\", \"aug\", \"sus2\", \"sus4\", \"7\", \"maj7\", \"min7\", \"dim7\" and \"9\".",
+        params: &[
+            param("name", "like \"maj7\""),
+            amount("root", Frequency, "its lowest note, like `c4`"),
+        ],
+    },
+    Function {
+        name: "degree",
+        doc: "A note of a scale, counting from 1 for its root, and going on into the octaves above (and below, from 0), like `notes.degree(5)`",
+        params: &[
+            param("scale", "like `scale(\"major\", c4)`"),
+            amount("n", Ratio, "which note, counting from 1"),
+        ],
+    },
+    Function {
+        name: "mtof",
+        doc: "The frequency of a MIDI note number, like `mtof(60)` for middle C (which is `c4`)",
+        params: &[amount("note", Ratio, "69 is `a4`, at 440Hz")],
+    },
+    Function {
+        name: "ftom",
+        doc: "The MIDI note number of a frequency (with a fraction, when it's in between), like `ftom(440hz)` for 69",
+        params: &[amount("freq", Frequency, "")],
+    },
+    Function {
+        name: "tuning",
+        doc: "Tunes the notes after it (like `c3`, and the ones of scales and chords) as a Scala file says, like `tuning(\"tunings/just.scl\")`, and maybe a keyboard mapping, for which note is where, like `tuning(\"just.scl\", \"c.kbm\")`. MIDI notes are played in it, too.",
+        params: &[
+            param("scl", "the Scala file, relative to the project root"),
+            param("kbm", "the keyboard mapping (by default, `c4` is the first note of the scale, and `a4` is at 440Hz)"),
+        ],
+    },
+    Function {
+        name: "watch",
+        doc: "Shows what a value is (as it's playing) in the watch panel, and is just that value otherwise, like `lowpass{f = watch(sin(2hz) * 800hz)}`",
+        params: &[param("value", "what's shown")],
+    },
+    Function {
+        name: "ease",
+        doc: "Is just that number, except that when it's changed in the code, it glides from its old value to the new one over `duration` (instead of 30ms), like `lowpass{f = ease(800hz, 200ms)}`",
+        params: &[
+            param("value", "what glides"),
+            amount("duration", Time, "how long the glide takes"),
+        ],
+    },
+    Function {
+        name: "perform",
+        doc: "Is just that number (which may be eased), except that it's captured into the editor's snapshots, to morph between, like `lowpass{f = perform(800hz)}`",
+        params: &[param("value", "what's captured")],
+    },
+    Function {
+        name: "every",
+        doc: "Calls a function every so often, on the transport's time, like `every(8s, |n| ..)`, where it gets how many times it was called before (if it takes an argument)",
+        params: &[
+            amount("interval", Time, "how often"),
+            param("f", "what's called"),
+        ],
+    },
+    Function {
+        name: "after",
+        doc: "Calls a function once, so long from now (on the transport's time), like `after(4s, || ..)`",
+        params: &[amount("delay", Time, "how long from now"), param("f", "what's called")],
+    },
+    Function {
+        name: "at",
+        doc: "Calls a function once, at the start of a bar, counting from 1 for the next one, like `at(2, || ..)` for the one after that",
+        params: &[
+            amount("bar", Ratio, "counting from 1 for the next one"),
+            param("f", "what's called"),
+        ],
+    },
+    Function {
+        name: "input",
+        doc: "A channel of the audio input (microphone, line-in), counting from 1, like `input(1) * .5`",
+        params: &[amount("channel", Ratio, "counting from 1")],
+    },
+    Function {
+        name: "record_buffer",
+        doc: "Records a stretch of the audio input (its first channel), and then loops it like a sample, like `record_buffer(4s)%[rate = .5]` (a note records it again)",
+        params: &[amount("duration", Time, "how much is recorded")],
+    },
+    Function {
+        name: "swing",
+        doc: "Plays every second 16th of a pattern late, where `amount` is how far into the pair it lands (.5 is straight, about .66 a triplet shuffle), like `beat.swing(.56)`. Without it, patterns follow the transport's swing.",
+        params: &[
+            param("pattern", ""),
+            amount("amount", Ratio, "how far into each pair of 16ths the second one lands"),
+        ],
+    },
+    Function {
+        name: "humanize",
+        doc: "Plays a pattern's steps a bit early or late (by up to `timing`) and a bit softer or harder (by up to `velocity`), randomly, but the same every time, like `beat.humanize(10ms, .1)`",
+        params: &[
+            param("pattern", ""),
+            amount("timing", Time, "how early or late a step is, at most"),
+            amount("velocity", Ratio, "how much softer or harder a step is, at most"),
+        ],
+    },
+    Function {
+        name: "sometimes",
+        doc: "Plays some of a pattern's steps (randomly, but the same every time until it's reseeded, like `rand`) as a function makes them, like `beat.sometimes(.3, |p| p.humanize(20ms, .3))`",
+        params: &[
+            param("pattern", ""),
+            amount("probability", Ratio, "how often, from 0 to 1"),
+            param("f", "what the pattern is made into"),
+        ],
+    },
+    Function {
+        name: "poly",
+        doc: "Plays every note (from MIDI, or a pattern it's applied to) on a voice of its own, as a function makes it, like `poly{voices = 8}(|| sin(midi.freq) * midi.gate)`, with an envelope per voice (`attack`, `decay`, `sustain` and `release`)",
+        params: &[param("synth", "what makes a voice, from its `midi.*` note")],
+    },
+    Function {
+        name: "bus",
+        doc: "Everything that's sent to the bus with this name, to process and play together, like `play compressor(bus(\"drums\"))`",
+        params: &[param("name", "")],
+    },
+    Function {
+        name: "send",
+        doc: "Sends a signal to a bus (at a gain) instead of playing it, like `play send(kick, \"drums\", -6db)`. For a parallel send, play the signal as well.",
+        params: &[
+            param("signal", ""),
+            param("bus", "the name of the bus"),
+            amount("gain", Level, "how much of it is sent, like `-6db` (or a plain gain, like .5)"),
+        ],
+    },
+    Function {
+        name: "pan",
+        doc: "Places what's played between the output channels, from the first (-1) to the last (1), like `play pan(pad, -.5)` (for stereo, that's left of the middle), or at a gain per channel, like `play pan(pad, [1, 0, .5, .5])` for a quad rig",
+        params: &[
+            param("signal", ""),
+            amount(
+                "position",
+                Ratio,
+                "from -1 (the first channel) to 1 (the last), or an array of gains per channel",
+            ),
+        ],
+    },
+    Function {
+        name: "channel",
+        doc: "Plays a signal on just one output channel, counting from 1, like `play channel(click, 3)` for a click track on its own output",
+        params: &[param("signal", ""), amount("channel", Ratio, "counting from 1")],
+    },
+    Function {
+        name: "plugin",
+        doc: "An installed CLAP plugin processing a signal, like `plugin{size = .8}(\"TAL Reverb\", pad)`, where its parameters are set (and modulated) by name, like `room_size` for \"Room Size\", in its own units. Cmd+Shift+I opens its editor.",
+        params: &[
+            param("name", "the plugin's name, as it's installed"),
+            param("signal", "what it processes"),
+        ],
+    },
+];
+
+pub fn function(name: &str) -> Option<&'static Function> {
+    FUNCTIONS.iter().find(|function| function.name == name)
+}
+
+#[test]
+fn test_builtins() {
+    assert_eq!(
+        builtin("lowpass").and_then(|b| b.setting("q")),
+        Some((0.707, Ratio))
+    );
+    assert_eq!(
+        builtin("lowpass").and_then(|b| b.setting("f")),
+        Some((1000.0, Frequency))
+    );
+    assert_eq!(builtin("delay").and_then(|b| b.setting("q")), None);
+    assert!(builtin("flanger").is_none());
+
+    assert_eq!(
+        builtin("compressor").and_then(|b| b.setting("threshold")),
+        Some((-18.0, Level))
+    );
+
+    assert_eq!(
+        function("humanize").map(|f| f.params.iter().map(|p| p.name).collect::<Vec<_>>()),
+        Some(vec!["pattern", "timing", "velocity"])
+    );
+    assert!(function("lowpass").is_none());
+
+    assert_eq!(
+        function("sin").map(|f| f.params[0].dimension),
+        Some(Some(Frequency))
+    );
+    assert!(function("every").is_some());
+}
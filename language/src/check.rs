@@ -1,5 +1,888 @@
-use crate::ast::Document;
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug, Display, Formatter},
+};
 
-pub fn check_document(doc: Document) -> Document {
-    doc
+use crate::{
+    ast::{
+        Block, Decl, Document, Expr, FnDecl, Identifier, Modifier, Op, ParamList, Primitive, Stmt,
+        StrPart, SyntaxNode, Unit,
+    },
+    builtins::{builtin, Builtin},
+    music::{midi_to_freq, note_number},
+    span::SourceSpan,
+    visit::{walk_block, walk_expr, walk_params, walk_stmt, Visitor},
+};
+
+/// What a number measures
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dimension {
+    /// (plain numbers, like gains and factors)
+    Ratio,
+    Time,
+    Frequency,
+    /// (in dB, which is turned into a gain where a signal is scaled by it, like in `send(kick, "drums", -6db)`)
+    Level,
+}
+
+impl Display for Dimension {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Dimension::Ratio => write!(f, "a number"),
+            Dimension::Time => write!(f, "a time"),
+            Dimension::Frequency => write!(f, "a frequency"),
+            Dimension::Level => write!(f, "a level"),
+        }
+    }
+}
+
+impl Dimension {
+    /**
+        What combining two numbers results in: dividing inverts (`1/250ms` is a frequency), times and frequencies cancel out (`2s * 4hz` is a number), and plain numbers take on the dimension of what they're combined with (`440 + 5hz` is a frequency). Comparing is like adding (`1s < 500ms`), and results in a condition, which is a plain number, as are what `&&` and `||` combine. Anything else, like `5hz + 3s` or `2s * 2s`, doesn't make sense.
+    */
+    pub fn combine(self, op: Op, other: Dimension) -> Result<Dimension, String> {
+        use Dimension::*;
+
+        match (op, self, other) {
+            (Op::Add | Op::Sub, a, b) if a == b => Ok(a),
+            (Op::Add | Op::Sub, Ratio, b) => Ok(b),
+            (Op::Add | Op::Sub, a, Ratio) => Ok(a),
+            (Op::Mul, Ratio, b) => Ok(b),
+            (Op::Mul | Op::Div, a, Ratio) => Ok(a),
+            (Op::Mul, Time, Frequency) | (Op::Mul, Frequency, Time) => Ok(Ratio),
+            (Op::Div, a, b) if a == b => Ok(Ratio),
+            (Op::Div, Ratio, Time) => Ok(Frequency),
+            (Op::Div, Ratio, Frequency) => Ok(Time),
+            (op, a, b) if op.is_comparison() && (a == b || a == Ratio || b == Ratio) => Ok(Ratio),
+            (Op::And | Op::Or, Ratio, Ratio) => Ok(Ratio),
+            (op, a, b) => Err(cant_combine(op, a, b)),
+        }
+    }
+}
+
+pub(crate) fn cant_combine(op: Op, a: impl Display, b: impl Display) -> String {
+    match op {
+        Op::Add => format!("can't add {} to {}", b, a),
+        Op::Sub => format!("can't subtract {} from {}", b, a),
+        Op::Mul => format!("can't multiply {} by {}", a, b),
+        Op::Div => format!("can't divide {} by {}", a, b),
+        Op::Eq | Op::Ne | Op::Lt | Op::Le | Op::Gt | Op::Ge => {
+            format!("can't compare {} to {}", a, b)
+        }
+        Op::And | Op::Or => format!("`{}` needs conditions, not {} and {}", op, a, b),
+    }
+}
+
+/// A number in the base unit of its dimension: seconds for times, and Hz for frequencies
+#[derive(Clone, Copy, PartialEq)]
+pub struct Quantity {
+    pub value: f64,
+    pub dimension: Dimension,
+}
+
+impl Quantity {
+    pub fn new(value: f64, dimension: Dimension) -> Self {
+        Self { value, dimension }
+    }
+
+    pub fn of(value: f64, unit: &Unit) -> Self {
+        match unit {
+            Unit::Min => Self::new(value * 60.0, Dimension::Time),
+            Unit::S => Self::new(value, Dimension::Time),
+            Unit::Ms => Self::new(value / 1000.0, Dimension::Time),
+            Unit::Khz => Self::new(value * 1000.0, Dimension::Frequency),
+            Unit::Hz => Self::new(value, Dimension::Frequency),
+            Unit::Db => Self::new(value, Dimension::Level),
+        }
+    }
+
+    /// As what a signal is scaled by, where levels are turned into amplitudes (so `-6db` is about .5)
+    pub fn gain(&self) -> f64 {
+        match self.dimension {
+            Dimension::Level => 10f64.powf(self.value / 20.0),
+            _ => self.value,
+        }
+    }
+
+    /// As a condition
+    pub fn is_true(&self) -> bool {
+        self.value >= 0.5
+    }
+
+    /// (Conditions are 1 when they're true, and 0 when they're not, and any number from 0.5 up counts as true, like it does for a signal that's used as a condition.)
+    pub fn combine(self, op: Op, other: Quantity) -> Result<Quantity, String> {
+        let dimension = self.dimension.combine(op, other.dimension)?;
+        let condition = |b: bool| if b { 1.0 } else { 0.0 };
+        let value = match op {
+            Op::Add => self.value + other.value,
+            Op::Sub => self.value - other.value,
+            Op::Mul => self.value * other.value,
+            Op::Div => self.value / other.value,
+            Op::Eq => condition(self.value == other.value),
+            Op::Ne => condition(self.value != other.value),
+            Op::Lt => condition(self.value < other.value),
+            Op::Le => condition(self.value <= other.value),
+            Op::Gt => condition(self.value > other.value),
+            Op::Ge => condition(self.value >= other.value),
+            Op::And => condition(self.is_true() && other.is_true()),
+            Op::Or => condition(self.is_true() || other.is_true()),
+        };
+
+        Ok(Self::new(value, dimension))
+    }
+}
+
+impl Display for Quantity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.dimension {
+            Dimension::Ratio => write!(f, "{}", self.value),
+            Dimension::Time => write!(f, "{}s", self.value),
+            Dimension::Frequency => write!(f, "{}hz", self.value),
+            Dimension::Level => write!(f, "{}db", self.value),
+        }
+    }
+}
+
+impl Debug for Quantity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+/// What a "maybe modulated" parameter, like a built-in's setting, is set to
+#[derive(Debug, Clone, PartialEq)]
+pub enum Modulation {
+    /// A plain number, lifted to a modulation that never changes
+    Constant(Quantity),
+    /// A value that's set from the outside, by its dotted name, like `midi.freq` or `fx.f`. These change once per block.
+    Control(String),
+    /// Another signal, like `sin(4hz)`. These change every sample.
+    Signal(SyntaxNode<Expr>),
+}
+
+/// Lifts an expression into a modulation, folding whatever's constant
+pub fn modulation(expr: &SyntaxNode<Expr>) -> Result<Modulation, String> {
+    let Some(node) = expr.node.as_deref() else {
+        return Err("missing value".into());
+    };
+
+    match node {
+        Expr::Prim(prim) => match prim.node.as_deref() {
+            Some(&Primitive::Int(n)) => Ok(Modulation::Constant(Quantity::new(
+                n as f64,
+                Dimension::Ratio,
+            ))),
+            Some(&Primitive::Float(x)) => {
+                Ok(Modulation::Constant(Quantity::new(x, Dimension::Ratio)))
+            }
+            Some(Primitive::Quantity((x, unit))) => match unit.node.as_deref() {
+                Some(unit) => Ok(Modulation::Constant(Quantity::of(*x, unit))),
+                None => Err("missing unit".into()),
+            },
+            // (in equal temperament, since what it's tuned to is only known when it's evaluated)
+            Some(Primitive::Note(name)) => Ok(Modulation::Constant(Quantity::new(
+                note_number(name).map_or(f64::NAN, midi_to_freq),
+                Dimension::Frequency,
+            ))),
+            Some(prim) => Err(format!("{} can't be modulated", prim)),
+            None => Err("missing value".into()),
+        },
+        Expr::Paren(inner) => modulation(inner),
+        Expr::Interpolated(_) => Err("a string can't be modulated".into()),
+        Expr::Array(_) => Err("an array can't be modulated".into()),
+        Expr::Tuple(_) => Err("a tuple can't be modulated".into()),
+        Expr::Member(..) => match control_name(expr) {
+            Some(name) => Ok(Modulation::Control(name)),
+            None => Ok(Modulation::Signal(expr.clone())),
+        },
+        Expr::BinOp(left, op, right) => match (modulation(left)?, modulation(right)?) {
+            (Modulation::Constant(a), Modulation::Constant(b)) => {
+                Ok(Modulation::Constant(a.combine(*op, b)?))
+            }
+            _ => Ok(Modulation::Signal(expr.clone())),
+        },
+        _ => Ok(Modulation::Signal(expr.clone())),
+    }
+}
+
+/// `a.b.c`, if it's just names
+fn control_name(expr: &SyntaxNode<Expr>) -> Option<String> {
+    match expr.node.as_deref()? {
+        Expr::Var(id) => Some(id.node.as_deref()?.0.clone()),
+        Expr::Member(a, b) => Some(format!("{}.{}", control_name(a)?, b.node.as_deref()?.0)),
+        _ => None,
+    }
+}
+
+/**
+    Checks the settings of a built-in, like the `f = 800hz, q = 2` in `lowpass{f = 800hz, q = 2}`, returning every one of its settings (in order) with what it's set to. Unnamed settings go in order, whatever's not set is its default, and plain numbers are taken to be in the setting's base unit (seconds or Hz).
+*/
+pub fn check_settings(
+    builtin: &Builtin,
+    settings: &[Modifier],
+) -> Result<Vec<(&'static str, Modulation)>, String> {
+    let mut checked: Vec<(&'static str, Option<Modulation>)> = builtin
+        .settings
+        .iter()
+        .map(|setting| (setting.name, None))
+        .collect();
+
+    for (i, setting) in settings.iter().enumerate() {
+        let (slot, value) = match setting {
+            Modifier::Arg(value) => (
+                checked.get_mut(i).ok_or_else(|| {
+                    format!(
+                        "`{}` only has {} settings",
+                        builtin.name,
+                        builtin.settings.len()
+                    )
+                })?,
+                value,
+            ),
+            Modifier::Setting(name, value) => {
+                let name = name.node.as_deref().map_or("", |id| id.0.as_str());
+                (
+                    checked
+                        .iter_mut()
+                        .find(|(setting, _)| *setting == name)
+                        .ok_or_else(|| format!("`{}` has no setting `{}`", builtin.name, name))?,
+                    value,
+                )
+            }
+        };
+
+        if slot.1.is_some() {
+            return Err(format!("`{}` is set more than once", slot.0));
+        }
+
+        let (_, dimension) = builtin.setting(slot.0).unwrap();
+        slot.1 = Some(match modulation(value)? {
+            Modulation::Constant(quantity) if quantity.dimension == Dimension::Ratio => {
+                Modulation::Constant(Quantity::new(quantity.value, dimension))
+            }
+            Modulation::Constant(quantity) if quantity.dimension != dimension => {
+                return Err(setting_mismatch(slot.0, dimension, quantity.dimension));
+            }
+            modulation => modulation,
+        });
+    }
+
+    Ok(checked
+        .into_iter()
+        .zip(builtin.settings)
+        .map(|((name, modulation), setting)| {
+            (
+                name,
+                modulation.unwrap_or(Modulation::Constant(Quantity::new(
+                    setting.default,
+                    setting.dimension,
+                ))),
+            )
+        })
+        .collect())
+}
+
+fn setting_mismatch(name: &str, expected: Dimension, actual: Dimension) -> String {
+    format!("`{}` should be {}, not {}", name, expected, actual)
+}
+
+/**
+    Checks that the units in a document add up, returning where they don't (like `5hz + 3s`) and why. What a name (from a `let` or `def`) measures is known after it's defined, and whatever can't be known (like what a function returns) isn't checked.
+*/
+pub fn check_units(doc: &Document) -> Vec<(SourceSpan, String)> {
+    let mut checker = UnitChecker { errors: vec![] };
+    checker.stmts(&doc.stmts, &mut HashMap::new());
+    checker.errors
+}
+
+// (`None` for names whose dimension isn't known, so that they shadow the outer ones)
+type Scope = HashMap<String, Option<Dimension>>;
+
+struct UnitChecker {
+    errors: Vec<(SourceSpan, String)>,
+}
+
+impl UnitChecker {
+    fn error<T>(&mut self, node: &SyntaxNode<T>, message: String) {
+        if let Some(span) = node.span() {
+            self.errors.push((span, message));
+        }
+    }
+
+    fn stmts(&mut self, stmts: &[Stmt], scope: &mut Scope) {
+        for stmt in stmts {
+            match stmt {
+                Stmt::Skip => {}
+                Stmt::Expr(expr) | Stmt::Play(expr) | Stmt::Return(Some(expr)) => {
+                    self.expr(expr, scope);
+                }
+                Stmt::Return(None) => {}
+                Stmt::Let((name, expr)) => {
+                    let dimension = self.expr(expr, scope);
+                    if let Some(name) = name.node.as_deref() {
+                        scope.insert(name.0.clone(), dimension);
+                    }
+                }
+                Stmt::Decl(decl) => {
+                    if let Some(Decl::FnDecl(fn_decl)) = decl.node.as_deref()
+                        && let Some(fn_decl) = fn_decl.node.as_deref()
+                    {
+                        let mut scope = self.params(&fn_decl.params, scope);
+                        self.block(&fn_decl.body, &mut scope);
+                    }
+                }
+            }
+        }
+    }
+
+    fn params(&self, params: &ParamList, scope: &Scope) -> Scope {
+        let mut scope = scope.clone();
+        for param in &params.0 {
+            if let Some(name) = param.node.as_deref().and_then(|p| p.name.node.as_deref()) {
+                scope.insert(name.0.clone(), None);
+            }
+        }
+        scope
+    }
+
+    fn block(&mut self, block: &SyntaxNode<Block>, scope: &mut Scope) -> Option<Dimension> {
+        let block = block.node.as_deref()?;
+        self.stmts(&block.stmts, scope);
+        block.expr.as_ref().and_then(|expr| self.expr(expr, scope))
+    }
+
+    fn expr(&mut self, expr: &SyntaxNode<Expr>, scope: &Scope) -> Option<Dimension> {
+        match expr.node.as_deref()? {
+            Expr::Prim(prim) => match prim.node.as_deref()? {
+                Primitive::Int(_) | Primitive::Float(_) => Some(Dimension::Ratio),
+                Primitive::Quantity((_, unit)) => {
+                    Some(Quantity::of(0.0, unit.node.as_deref()?).dimension)
+                }
+                Primitive::Note(_) => Some(Dimension::Frequency),
+                _ => None,
+            },
+            Expr::Var(id) => scope.get(&id.node.as_deref()?.0).copied().flatten(),
+            Expr::Paren(inner) => self.expr(inner, scope),
+            Expr::Not(inner) => {
+                self.condition(inner, "`!`", scope);
+                Some(Dimension::Ratio)
+            }
+            Expr::If(cond, then, otherwise) => {
+                self.condition(cond, "`if`", scope);
+                let then = self.block(then, &mut scope.clone());
+                let otherwise = otherwise.as_ref().and_then(|e| self.expr(e, scope));
+
+                // (both branches should measure the same, or be plain numbers)
+                match then?.combine(Op::Add, otherwise?) {
+                    Ok(dimension) => Some(dimension),
+                    Err(_) => {
+                        self.error(
+                            expr,
+                            format!("one branch is {} and the other {}", then?, otherwise?),
+                        );
+                        None
+                    }
+                }
+            }
+            Expr::BinOp(left, op, right) => {
+                let left = self.expr(left, scope);
+                let right = self.expr(right, scope);
+
+                match left?.combine(*op, right?) {
+                    Ok(dimension) => Some(dimension),
+                    Err(message) => {
+                        self.error(expr, message);
+                        None
+                    }
+                }
+            }
+            Expr::Call(call) => {
+                let (function, method) = match call.fun.node.as_deref() {
+                    Some(Expr::Var(id)) => (id.node.as_deref().map(|id| id.0.as_str()), false),
+                    Some(Expr::Member(_, name)) => {
+                        (name.node.as_deref().map(|id| id.0.as_str()), true)
+                    }
+                    _ => (None, false),
+                };
+                // (what the functions that take amounts expect them to measure, where plain numbers are in their unit, like for settings)
+                let expected: &[Dimension] = match (function, method) {
+                    (Some("input"), false) => &[Dimension::Ratio],
+                    (Some("record_buffer"), false) => &[Dimension::Time],
+                    (Some("ease"), false) => &[Dimension::Time],
+                    (Some("every" | "after"), false) => &[Dimension::Time],
+                    (Some("at"), false) => &[Dimension::Ratio],
+                    (Some("swing"), _) => &[Dimension::Ratio],
+                    (Some("humanize"), _) => &[Dimension::Time, Dimension::Ratio],
+                    (Some("sometimes"), _) => &[Dimension::Ratio],
+                    (Some("send"), false) => &[Dimension::Level],
+                    (Some("pan" | "channel"), false) => &[Dimension::Ratio],
+                    (Some("scale" | "chord"), false) => &[Dimension::Frequency],
+                    (Some("degree"), _) => &[Dimension::Ratio],
+                    (Some("mtof"), false) => &[Dimension::Ratio],
+                    (Some("ftom"), false) => &[Dimension::Frequency],
+                    _ => &[],
+                };
+                // (the pattern ones take the pattern first, unless they're called like methods)
+                let skip = match (function, method) {
+                    (Some("swing" | "humanize" | "sometimes"), false) => 1,
+                    // (and what to send where, before how much)
+                    (Some("send"), false) => 2,
+                    // (and what's placed, before where)
+                    (Some("pan" | "channel"), false) => 1,
+                    // (and what's eased, before how long)
+                    (Some("ease"), false) => 1,
+                    // (and the name of the scale or chord, before its root, or the scale before the degree)
+                    (Some("scale" | "chord" | "degree"), false) => 1,
+                    _ => 0,
+                };
+
+                self.expr(&call.fun, scope);
+                let mut first = None;
+                for (i, arg) in call.args.iter().enumerate() {
+                    let dimension = self.expr(arg, scope);
+                    if i == 0 {
+                        first = dimension;
+                    }
+
+                    if let Some(dimension) = dimension
+                        && let Some(&expected) = i.checked_sub(skip).and_then(|i| expected.get(i))
+                        && dimension != expected
+                        && dimension != Dimension::Ratio
+                    {
+                        self.error(
+                            arg,
+                            format!(
+                                "`{}` needs {}, not {}",
+                                function.unwrap_or_default(),
+                                expected,
+                                dimension
+                            ),
+                        );
+                    }
+                }
+
+                // (`ease(800hz, 200ms)` measures what it eases, and `perform(800hz)` what it is)
+                match (function, method) {
+                    (Some("ease" | "perform"), false) => first,
+                    (Some("mtof"), false) => Some(Dimension::Frequency),
+                    (Some("ftom"), false) => Some(Dimension::Ratio),
+                    _ => None,
+                }
+            }
+            Expr::Block(block) => self.block(block, &mut scope.clone()),
+            Expr::AnonymousFn(fun) => {
+                if let Some(fun) = fun.node.as_deref() {
+                    let scope = self.params(&fun.params, scope);
+                    self.expr(&fun.body, &scope);
+                }
+                None
+            }
+            Expr::Index(a, b) => {
+                self.expr(a, scope);
+                self.expr(b, scope);
+                None
+            }
+            Expr::Member(a, _) => {
+                self.expr(a, scope);
+                None
+            }
+            Expr::Modify(a, modifiers) => {
+                self.expr(a, scope);
+                self.modifiers(modifiers, None, scope);
+                None
+            }
+            Expr::Interpolated(parts) => {
+                for part in parts {
+                    if let StrPart::Expr(expr) = part {
+                        self.expr(expr, scope);
+                    }
+                }
+                None
+            }
+            Expr::Array(items) | Expr::Tuple(items) => {
+                for item in items {
+                    self.expr(item, scope);
+                }
+                None
+            }
+            Expr::Settings(a, settings) => {
+                let builtin = match a.node.as_deref() {
+                    Some(Expr::Var(id)) => id.node.as_deref().and_then(|id| builtin(&id.0)),
+                    _ => None,
+                };
+                self.expr(a, scope);
+                self.modifiers(settings, builtin, scope);
+                None
+            }
+        }
+    }
+
+    fn condition(&mut self, cond: &SyntaxNode<Expr>, what: &str, scope: &Scope) {
+        if let Some(dimension) = self.expr(cond, scope)
+            && dimension != Dimension::Ratio
+        {
+            self.error(
+                cond,
+                format!("{} needs a condition, not {}", what, dimension),
+            );
+        }
+    }
+
+    /// (checking the settings of a built-in against what they measure)
+    fn modifiers(&mut self, modifiers: &[Modifier], builtin: Option<&Builtin>, scope: &Scope) {
+        for (i, modifier) in modifiers.iter().enumerate() {
+            let (name, value) = match modifier {
+                Modifier::Arg(value) => (
+                    builtin.and_then(|b| b.settings.get(i)).map(|s| s.name),
+                    value,
+                ),
+                Modifier::Setting(name, value) => {
+                    (name.node.as_deref().map(|id| id.0.as_str()), value)
+                }
+            };
+
+            let actual = self.expr(value, scope);
+
+            if let Some(name) = name
+                && let Some((_, expected)) = builtin.and_then(|b| b.setting(name))
+                && let Some(actual) = actual
+                && actual != expected
+                && actual != Dimension::Ratio
+            {
+                self.error(value, setting_mismatch(name, expected, actual));
+            }
+        }
+    }
+}
+
+/// A name, where it's declared or used, and which declaration it is (as an index into the occurrences, so a declaration points to itself)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Occurrence {
+    pub name: String,
+    pub span: SourceSpan,
+    /// (`None` for builtins, and names that aren't defined, or not yet)
+    pub declaration: Option<usize>,
+}
+
+/**
+    Works out which declaration each name in the document refers to, with the same scoping as the evaluator: a `let` or `def` is known after it (so `let x = x + 1` uses the `x` from before), a function also in its own body, and parameters and what's declared in a block only inside.
+*/
+pub fn resolve_names(doc: &Document) -> Vec<Occurrence> {
+    let mut resolver = Resolver {
+        occurrences: vec![],
+        scopes: vec![HashMap::new()],
+    };
+    resolver.visit_document(doc);
+    resolver.occurrences
+}
+
+struct Resolver {
+    occurrences: Vec<Occurrence>,
+    scopes: Vec<HashMap<String, usize>>,
+}
+
+impl Resolver {
+    fn scoped(&mut self, f: impl FnOnce(&mut Self)) {
+        self.scopes.push(HashMap::new());
+        f(self);
+        self.scopes.pop();
+    }
+
+    /// (missing names aren't anywhere, so they're left out)
+    fn occurrence(&mut self, name: &SyntaxNode<Identifier>, declaration: Option<usize>) -> bool {
+        let (Some(id), Some(span)) = (name.node.as_deref(), name.span()) else {
+            return false;
+        };
+
+        self.occurrences.push(Occurrence {
+            name: id.0.clone(),
+            span,
+            declaration,
+        });
+        true
+    }
+}
+
+impl Visitor for Resolver {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Let((name, expr)) => {
+                self.visit_expr(expr);
+                self.visit_binding(name);
+            }
+            _ => walk_stmt(self, stmt),
+        }
+    }
+
+    fn visit_fn_decl(&mut self, decl: &SyntaxNode<FnDecl>) {
+        let Some(FnDecl { name, params, body }) = decl.node.as_deref() else {
+            return;
+        };
+
+        self.visit_binding(name);
+        self.scoped(|resolver| {
+            walk_params(resolver, params);
+            resolver.visit_block(body);
+        });
+    }
+
+    fn visit_block(&mut self, block: &SyntaxNode<Block>) {
+        self.scoped(|resolver| walk_block(resolver, block));
+    }
+
+    fn visit_expr(&mut self, expr: &SyntaxNode<Expr>) {
+        match expr.node.as_deref() {
+            Some(Expr::AnonymousFn(_)) => self.scoped(|resolver| walk_expr(resolver, expr)),
+            _ => walk_expr(self, expr),
+        }
+    }
+
+    fn visit_binding(&mut self, name: &SyntaxNode<Identifier>) {
+        let i = self.occurrences.len();
+
+        if self.occurrence(name, Some(i)) {
+            let name = self.occurrences[i].name.clone();
+            self.scopes.last_mut().unwrap().insert(name, i);
+        }
+    }
+
+    fn visit_var(&mut self, name: &SyntaxNode<Identifier>) {
+        let declaration = name.node.as_deref().and_then(|id| {
+            self.scopes
+                .iter()
+                .rev()
+                .find_map(|scope| scope.get(&id.0).copied())
+        });
+
+        self.occurrence(name, declaration);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_v2::{lower::lower_document, parse_syntax_tree};
+
+    fn check(source: &str) -> Result<Vec<(&'static str, String)>, String> {
+        let (tree, _) = parse_syntax_tree(source);
+        let Some(Stmt::Expr(expr)) = lower_document(&tree).stmts.into_iter().next() else {
+            panic!("not an expression: {:?}", source);
+        };
+
+        let Some(Expr::Settings(name, settings)) = expr.node.as_deref() else {
+            panic!("not settings: {:?}", expr);
+        };
+
+        let name = name.to_string();
+        let builtin = builtin(&name).unwrap();
+
+        check_settings(builtin, settings).map(|checked| {
+            checked
+                .into_iter()
+                .map(|(name, modulation)| (name, format!("{:?}", modulation)))
+                .collect()
+        })
+    }
+
+    #[test]
+    fn test_check_settings() {
+        assert_eq!(
+            check("lowpass{f = 2khz / 2}"),
+            Ok(vec![
+                ("f", "Constant(1000hz)".into()),
+                ("q", "Constant(0.707)".into())
+            ])
+        );
+
+        assert_eq!(
+            check("delay{100ms, mix = midi.velocity, feedback = sin(4hz)}"),
+            Ok(vec![
+                ("time", "Constant(0.1s)".into()),
+                ("feedback", "Signal(sin(4hz))".into()),
+                ("mix", "Control(\"midi.velocity\")".into())
+            ])
+        );
+
+        assert_eq!(
+            check("lowpass{q = 2, g = 1}"),
+            Err("`lowpass` has no setting `g`".into())
+        );
+        assert_eq!(
+            check("lowpass{1, f = 2}"),
+            Err("`f` is set more than once".into())
+        );
+        assert_eq!(
+            check("lowpass{f = \"loud\"}"),
+            Err("\"loud\" can't be modulated".into())
+        );
+
+        // (plain numbers are in the setting's unit)
+        assert_eq!(
+            check("lowpass{f = 1 / 2ms}"),
+            Ok(vec![
+                ("f", "Constant(500hz)".into()),
+                ("q", "Constant(0.707)".into())
+            ])
+        );
+        assert_eq!(
+            check("delay{time = 2hz}"),
+            Err("`time` should be a time, not a frequency".into())
+        );
+        assert_eq!(
+            check("compressor{threshold = -24db, makeup = 3}"),
+            Ok(vec![
+                ("threshold", "Constant(-24db)".into()),
+                ("ratio", "Constant(4)".into()),
+                ("attack", "Constant(0.01s)".into()),
+                ("release", "Constant(0.1s)".into()),
+                ("makeup", "Constant(3db)".into())
+            ])
+        );
+        assert_eq!(
+            check("compressor{threshold = 10ms}"),
+            Err("`threshold` should be a level, not a time".into())
+        );
+        assert_eq!(
+            check("delay{time = 5hz + 3s}"),
+            Err("can't add a time to a frequency".into())
+        );
+    }
+
+    fn check_units(source: &str) -> Vec<(&str, String)> {
+        let (tree, _) = parse_syntax_tree(source);
+        super::check_units(&lower_document(&tree))
+            .into_iter()
+            .map(|(span, message)| (&source[span.range()], message))
+            .collect()
+    }
+
+    #[test]
+    fn test_check_units() {
+        assert_eq!(
+            check_units("let a = 5hz * 2; let b = 1 / 500ms; let c = a + b + 440; c * 2s;"),
+            vec![]
+        );
+
+        assert_eq!(
+            check_units("def beat = 500ms;\nlet f = 1 / beat;\nplay f + beat;"),
+            vec![("f + beat", "can't add a time to a frequency".into())]
+        );
+
+        assert_eq!(
+            check_units("let a = (2s * 3s) + 1hz; { let a = 2; a + 1hz };"),
+            vec![("2s * 3s", "can't multiply a time by a time".into())]
+        );
+
+        // (parameters shadow what they're named after)
+        assert_eq!(check_units("let t = 1s; fn f(t) { t + 1hz }"), vec![]);
+
+        assert_eq!(
+            check_units("lowpass{2s, q = 1hz * 1s}(x);"),
+            vec![("2s", "`f` should be a frequency, not a time".into())]
+        );
+
+        assert_eq!(
+            check_units("let x = if 1s > 500ms && !(2 < 1) { 1hz } else { 440 }; x * 1s;"),
+            vec![]
+        );
+
+        assert_eq!(
+            check_units("play input(1) + record_buffer(4s) + record_buffer(2); play input(2s) + record_buffer(2hz);"),
+            vec![
+                ("2s", "`input` needs a number, not a time".into()),
+                ("2hz", "`record_buffer` needs a time, not a frequency".into()),
+            ]
+        );
+
+        assert_eq!(
+            check_units("play beat.swing(.56) + swing(beat, 2s) + beat.humanize(10ms, .1) + beat.humanize(.01, 10ms);"),
+            vec![
+                ("2s", "`swing` needs a number, not a time".into()),
+                ("10ms", "`humanize` needs a number, not a time".into()),
+            ]
+        );
+
+        assert_eq!(
+            check_units("play beat.sometimes(.3, |p| p) + sometimes(beat, 2s, |p| p);"),
+            vec![("2s", "`sometimes` needs a number, not a time".into())]
+        );
+
+        assert_eq!(
+            check_units("play send(kick, \"drums\", -6db) + send(snare, \"drums\", 100ms);"),
+            vec![("100ms", "`send` needs a level, not a time".into())]
+        );
+
+        assert_eq!(
+            check_units("let f = ease(800hz, 200ms); play f + 1s; ease(1, 2hz);"),
+            vec![
+                ("f + 1s", "can't add a time to a frequency".into()),
+                ("2hz", "`ease` needs a time, not a frequency".into()),
+            ]
+        );
+
+        assert_eq!(
+            check_units("every(2s, || 1) + after(500ms, || 2) + every(2hz, || 3) + at(2s, || 4);"),
+            vec![
+                ("2hz", "`every` needs a time, not a frequency".into()),
+                ("2s", "`at` needs a number, not a time".into()),
+            ]
+        );
+
+        assert_eq!(
+            check_units("let root = a3 + 1s; scale(\"minor\", 2s).degree(3s) + chord(\"7\", c4); mtof(60) + 1s; ftom(2s);"),
+            vec![
+                ("a3 + 1s", "can't add a time to a frequency".into()),
+                ("2s", "`scale` needs a frequency, not a time".into()),
+                ("3s", "`degree` needs a number, not a time".into()),
+                ("mtof(60) + 1s", "can't add a time to a frequency".into()),
+                ("2s", "`ftom` needs a frequency, not a time".into()),
+            ]
+        );
+
+        assert_eq!(
+            check_units("if 1s > 1hz { 1s } else if 1hz { 2hz } else { 3s };"),
+            vec![
+                ("1s > 1hz", "can't compare a time to a frequency".into()),
+                ("1hz", "`if` needs a condition, not a frequency".into()),
+                (
+                    "if 1hz { 2hz } else { 3s }",
+                    "one branch is a frequency and the other a time".into()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve_names() {
+        let source = "let x = 1;\nlet x = x + 1;\nfn f(x) { f(x) }\nplay sin(x) + f(2) + |x| x;";
+        let (tree, _) = parse_syntax_tree(source);
+        let occurrences = resolve_names(&lower_document(&tree));
+
+        assert_eq!(
+            occurrences
+                .iter()
+                .map(|occurrence| (
+                    occurrence.name.as_str(),
+                    occurrence.span.start.offset,
+                    occurrence
+                        .declaration
+                        .map(|i| occurrences[i].span.start.offset)
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                ("x", 4, Some(4)),
+                ("x", 19, Some(4)),
+                ("x", 15, Some(15)),
+                ("f", 29, Some(29)),
+                ("x", 31, Some(31)),
+                ("f", 36, Some(29)),
+                ("x", 38, Some(31)),
+                ("sin", 48, None),
+                ("x", 52, Some(15)),
+                ("f", 57, Some(29)),
+                ("x", 65, Some(65)),
+                ("x", 68, Some(65)),
+            ]
+        );
+    }
 }
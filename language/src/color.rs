@@ -0,0 +1,53 @@
+/**
+    Reads a color literal, like `#f80`, `#ff8800` or `#ff880080` (with alpha), as RGBA
+*/
+pub fn parse_color(text: &str) -> Option<[u8; 4]> {
+    let hex = text.strip_prefix('#')?;
+    if !hex.chars().all(|ch| ch.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let channel = |i: usize, len: usize| u8::from_str_radix(&hex[i * len..(i + 1) * len], 16).ok();
+
+    match hex.len() {
+        // (every digit doubled: `#f80` is `#ff8800`)
+        3 => Some([
+            channel(0, 1)? * 17,
+            channel(1, 1)? * 17,
+            channel(2, 1)? * 17,
+            255,
+        ]),
+        6 => Some([channel(0, 2)?, channel(1, 2)?, channel(2, 2)?, 255]),
+        8 => Some([
+            channel(0, 2)?,
+            channel(1, 2)?,
+            channel(2, 2)?,
+            channel(3, 2)?,
+        ]),
+        _ => None,
+    }
+}
+
+/**
+    Writes a color as a literal, leaving out the alpha when it's opaque
+*/
+pub fn format_color([r, g, b, a]: [u8; 4]) -> String {
+    if a == 255 {
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    } else {
+        format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+    }
+}
+
+#[test]
+fn test_color() {
+    assert_eq!(parse_color("#f80"), Some([255, 136, 0, 255]));
+    assert_eq!(parse_color("#FF8800"), Some([255, 136, 0, 255]));
+    assert_eq!(parse_color("#ff880080"), Some([255, 136, 0, 128]));
+    assert_eq!(parse_color("#ff88"), None);
+    assert_eq!(parse_color("#gg8800"), None);
+    assert_eq!(parse_color("ff8800"), None);
+
+    assert_eq!(format_color([255, 136, 0, 255]), "#ff8800");
+    assert_eq!(format_color([255, 136, 0, 128]), "#ff880080");
+}
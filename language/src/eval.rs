@@ -0,0 +1,2090 @@
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug, Display, Formatter},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    ast::{
+        Block, Decl, Document, Expr, Modifier, Op, ParamList, Primitive, Stmt, StrPart, SyntaxNode,
+    },
+    check::{cant_combine, Dimension, Quantity},
+    color::format_color,
+    music::{degree, freq_to_midi, intervals, midi_to_freq, parse_note, CHORDS, SCALES},
+    parse_v2::{lower::lower_document, parse_syntax_tree},
+    paths::resolve_path,
+    random::{random, seed},
+    span::SourceSpan,
+    tuning::Tuning,
+};
+
+/// (so that a function that calls itself forever is an error, and not a stack overflow)
+const MAX_DEPTH: usize = 64;
+
+/**
+    What a value is recognized by when the document is evaluated again: the name it's bound to, like `f`, or otherwise where it is in the program, like `program.play[1]`. The elements of an array each have a key of their own, like `partials[2]`, which they keep when the array is mapped over or filtered, so that re-evaluating it can be diffed element by element.
+*/
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Key(String);
+
+impl Key {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self(name.into())
+    }
+
+    pub fn index(&self, i: usize) -> Self {
+        Self(format!("{}[{}]", self.0, i))
+    }
+
+    pub fn member(&self, name: &str) -> Self {
+        Self(format!("{}.{}", self.0, name))
+    }
+
+    fn is_within(&self, outer: &Key) -> bool {
+        self.0 == outer.0
+            || self
+                .0
+                .strip_prefix(&outer.0)
+                .is_some_and(|rest| rest.starts_with('[') || rest.starts_with('.'))
+    }
+}
+
+impl Display for Key {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Debug for Key {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub enum Value {
+    Num(Quantity),
+    Bool(bool),
+    Str(String),
+    // (RGBA)
+    Color([u8; 4]),
+    Array(Vec<(Key, Value)>),
+    Tuple(Vec<Value>),
+    Fn(Box<Closure>),
+    /// An audio node, like `sin(440hz)` or `lowpass{f = 800hz}(x)`, with its arguments (and settings) evaluated, for the engine to build
+    Node(String, Vec<(Option<String>, Value)>),
+}
+
+fn is_infix(op: &str) -> bool {
+    matches!(
+        op,
+        "+" | "-" | "*" | "/" | "==" | "!=" | "<" | "<=" | ">" | ">=" | "&&" | "||"
+    )
+}
+
+#[derive(Clone, PartialEq)]
+pub struct Closure {
+    /// (so that a declared function can call itself)
+    name: Option<String>,
+    params: Vec<String>,
+    body: Body,
+    scope: Scope,
+}
+
+#[derive(Clone, PartialEq)]
+enum Body {
+    Expr(SyntaxNode<Expr>),
+    Block(SyntaxNode<Block>),
+}
+
+impl Value {
+    fn describe(&self) -> String {
+        match self {
+            Value::Num(quantity) => quantity.dimension.to_string(),
+            Value::Bool(_) => "true or false".into(),
+            Value::Str(_) => "a string".into(),
+            Value::Color(_) => "a color".into(),
+            Value::Array(_) => "an array".into(),
+            Value::Tuple(_) => "a tuple".into(),
+            Value::Fn(_) => "a function".into(),
+            Value::Node(..) => "a signal".into(),
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Num(quantity) => write!(f, "{}", quantity),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Str(str) => write!(f, "{:?}", str),
+            Value::Color(rgba) => write!(f, "{}", format_color(*rgba)),
+            Value::Array(items) => {
+                write!(f, "[")?;
+                for (i, (_, item)) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+            Value::Tuple(items) => {
+                write!(f, "(")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                if items.len() == 1 {
+                    write!(f, ",")?;
+                }
+                write!(f, ")")
+            }
+            Value::Fn(closure) => write!(f, "|{}| ..", closure.params.join(", ")),
+            Value::Node(op, args) if args.len() == 2 && is_infix(op) => {
+                write!(f, "({} {} {})", args[0].1, op, args[1].1)
+            }
+            Value::Node(op, args) if args.len() == 1 && op == "!" => write!(f, "!{}", args[0].1),
+            Value::Node(op, args) if args.len() == 2 && op == "[]" => {
+                write!(f, "{}[{}]", args[0].1, args[1].1)
+            }
+            Value::Node(name, args) if args.is_empty() => write!(f, "{}", name),
+            Value::Node(name, args) => {
+                write!(f, "{}(", name)?;
+                for (i, (setting, arg)) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    if let Some(setting) = setting {
+                        write!(f, "{} = ", setting)?;
+                    }
+                    write!(f, "{}", arg)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+impl Debug for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+/// The values of a document: everything that's bound to a name (by `let`, `def` or `fn`), and everything that's played, in order
+#[derive(Debug, Clone, PartialEq)]
+pub struct Evaluation {
+    pub values: Vec<(Key, Value)>,
+    pub errors: Vec<(SourceSpan, String)>,
+    /// What every `watch(..)` was (the last time, if it's in a function), by the text of what it watches
+    pub watched: Vec<(String, Value)>,
+    /// The callbacks that `every`, `after` and `at` schedule, for the engine to time
+    pub timers: Vec<Timer>,
+    /// What `tuning(..)` last said (or equal temperament), for the engine to play MIDI notes in, too
+    pub tuning: Tuning,
+}
+
+/**
+    Evaluates a document, statement by statement. A statement that fails is reported, and whatever depends on it is left out, without more errors. Names that aren't bound in the document (built-ins like `sin`, and the editor's widgets) are left for the engine, as audio nodes.
+*/
+pub fn evaluate(doc: &Document) -> Evaluation {
+    evaluate_with_seed(doc, 0)
+}
+
+/**
+    Like `evaluate`, where everything that's random (like `rand()`) comes out differently for another seed. For the same seed, it comes out the same, every time (see `Evaluator::draw`).
+*/
+pub fn evaluate_with_seed(doc: &Document, seed: u64) -> Evaluation {
+    evaluate_in(doc, seed, Path::new(""))
+}
+
+/**
+    Like `evaluate_with_seed`, where the files that the code reads (like the ones `tuning` does) are relative to the project root
+*/
+pub fn evaluate_in(doc: &Document, seed: u64, root: &Path) -> Evaluation {
+    let mut evaluator = Evaluator {
+        seed,
+        root: root.to_path_buf(),
+        ..Evaluator::default()
+    };
+    let mut evaluation = Evaluation {
+        values: vec![],
+        errors: vec![],
+        watched: vec![],
+        timers: vec![],
+        tuning: Tuning::default(),
+    };
+
+    let mut scope = Scope::new();
+    let mut played = 0;
+    // (what's played, and where, to check how it's routed)
+    let mut routed = vec![];
+
+    for (i, stmt) in doc.stmts.iter().enumerate() {
+        let key = match stmt {
+            Stmt::Play(_) => {
+                played += 1;
+                Key::new("program.play").index(played - 1)
+            }
+            _ => Key::new("program").index(i),
+        };
+
+        if let Some((key, value)) = evaluator.stmt(stmt, &key, &mut scope) {
+            if let Stmt::Play(expr) = stmt
+                && let Some(span) = expr.span()
+            {
+                routed.push((span, value.clone()));
+            }
+            evaluation.values.push((key, value));
+        }
+    }
+
+    evaluator.errors.extend(route(&routed));
+    evaluator.errors.extend(place(&routed));
+    evaluation.errors = evaluator.errors;
+    evaluation.watched = evaluator.watched;
+    evaluation.timers = evaluator.timers;
+    evaluation.tuning = evaluator.tuning;
+    evaluation
+}
+
+/// Parses and evaluates the source, for when there's no AST at hand
+pub fn evaluate_source(source: &str) -> Evaluation {
+    evaluate_source_with_seed(source, 0)
+}
+
+pub fn evaluate_source_with_seed(source: &str, seed: u64) -> Evaluation {
+    evaluate_source_in(source, seed, Path::new(""))
+}
+
+pub fn evaluate_source_in(source: &str, seed: u64, root: &Path) -> Evaluation {
+    let (tree, _) = parse_syntax_tree(source);
+    evaluate_in(&lower_document(&tree), seed, root)
+}
+
+// (`None` for names whose value failed to evaluate, which was reported already)
+type Scope = HashMap<String, Option<Value>>;
+
+enum Exit {
+    Error(Option<SourceSpan>, String),
+    /// (already reported)
+    Failed,
+    Return(Value),
+}
+
+type Eval = Result<Value, Exit>;
+
+#[derive(Default)]
+struct Evaluator {
+    errors: Vec<(SourceSpan, String)>,
+    watched: Vec<(String, Value)>,
+    timers: Vec<Timer>,
+    depth: usize,
+    seed: u64,
+    // (how many times every random call came up, by key and code, see `draw`)
+    draws: HashMap<(Key, String), usize>,
+    // (what the notes after a `tuning(..)` are in)
+    tuning: Tuning,
+    root: PathBuf,
+}
+
+impl Evaluator {
+    fn report(&mut self, exit: Exit, fallback: Option<SourceSpan>) {
+        match exit {
+            Exit::Error(range, message) => {
+                if let Some(range) = range.or(fallback) {
+                    self.errors.push((range, message));
+                }
+            }
+            Exit::Return(_) => {
+                if let Some(range) = fallback {
+                    self.errors
+                        .push((range, "`return` outside of a function".into()));
+                }
+            }
+            Exit::Failed => {}
+        }
+    }
+
+    /// A top-level statement, returning what it binds or plays
+    fn stmt(&mut self, stmt: &Stmt, key: &Key, scope: &mut Scope) -> Option<(Key, Value)> {
+        match stmt {
+            Stmt::Let((name, expr)) => {
+                let name = name.node.as_deref()?.0.clone();
+                let key = Key::new(&name);
+                let value = self.expr(expr, &key, scope);
+                let value = self.reported(value, expr);
+                scope.insert(name, value.clone());
+                Some((key, value?))
+            }
+            Stmt::Decl(decl) => {
+                let (name, value) = self.decl(decl, scope)?;
+                scope.insert(name.clone(), Some(value.clone()));
+                Some((Key::new(name), value))
+            }
+            Stmt::Play(expr) => {
+                let value = self.expr(expr, key, scope);
+                Some((key.clone(), self.reported(value, expr)?))
+            }
+            Stmt::Expr(expr) | Stmt::Return(Some(expr)) => {
+                let value = self.expr(expr, key, scope);
+                let value = match (stmt, value) {
+                    (Stmt::Return(_), Ok(value)) => Err(Exit::Return(value)),
+                    (_, value) => value,
+                };
+                self.reported(value, expr);
+                None
+            }
+            Stmt::Return(None) | Stmt::Skip => None,
+        }
+    }
+
+    fn reported(&mut self, value: Eval, expr: &SyntaxNode<Expr>) -> Option<Value> {
+        match value {
+            Ok(value) => Some(value),
+            Err(exit) => {
+                self.report(exit, expr.span());
+                None
+            }
+        }
+    }
+
+    fn decl(&mut self, decl: &SyntaxNode<Decl>, scope: &Scope) -> Option<(String, Value)> {
+        let Decl::FnDecl(fn_decl) = decl.node.as_deref()?;
+        let fn_decl = fn_decl.node.as_deref()?;
+        let name = fn_decl.name.node.as_deref()?.0.clone();
+
+        let closure = Closure {
+            name: Some(name.clone()),
+            params: param_names(&fn_decl.params),
+            body: Body::Block(fn_decl.body.clone()),
+            scope: scope.clone(),
+        };
+
+        Some((name, Value::Fn(Box::new(closure))))
+    }
+
+    /// The statements of a block, and its value
+    fn block(&mut self, block: &SyntaxNode<Block>, key: &Key, scope: &Scope) -> Eval {
+        let Some(block) = block.node.as_deref() else {
+            return Err(Exit::Failed);
+        };
+
+        let mut scope = scope.clone();
+        for stmt in &block.stmts {
+            match stmt {
+                Stmt::Let((name, expr)) => {
+                    let Some(name) = name.node.as_deref() else {
+                        continue;
+                    };
+                    let value = self.expr(expr, &key.member(&name.0), &scope)?;
+                    scope.insert(name.0.clone(), Some(value));
+                }
+                Stmt::Decl(decl) => {
+                    if let Some((name, value)) = self.decl(decl, &scope) {
+                        scope.insert(name, Some(value));
+                    }
+                }
+                Stmt::Expr(expr) | Stmt::Play(expr) => {
+                    self.expr(expr, key, &scope)?;
+                }
+                Stmt::Return(Some(expr)) => {
+                    return Err(Exit::Return(self.expr(expr, key, &scope)?));
+                }
+                Stmt::Return(None) => {
+                    return Err(Exit::Error(None, "`return` needs a value".into()));
+                }
+                Stmt::Skip => {}
+            }
+        }
+
+        match &block.expr {
+            Some(expr) => self.expr(expr, key, &scope),
+            None => Err(Exit::Error(None, "a block needs a value".into())),
+        }
+    }
+
+    fn expr(&mut self, expr: &SyntaxNode<Expr>, key: &Key, scope: &Scope) -> Eval {
+        let error = |message: String| Err(Exit::Error(expr.span(), message));
+
+        let Some(node) = expr.node.as_deref() else {
+            return Err(Exit::Failed);
+        };
+
+        match node {
+            Expr::Prim(prim) => match prim.node.as_deref() {
+                Some(&Primitive::Bool(b)) => Ok(Value::Bool(b)),
+                Some(&Primitive::Int(n)) => {
+                    Ok(Value::Num(Quantity::new(n as f64, Dimension::Ratio)))
+                }
+                Some(&Primitive::Float(x)) => Ok(Value::Num(Quantity::new(x, Dimension::Ratio))),
+                Some(Primitive::Quantity((x, unit))) => match unit.node.as_deref() {
+                    Some(unit) => Ok(Value::Num(Quantity::of(*x, unit))),
+                    None => Err(Exit::Failed),
+                },
+                Some(Primitive::Str(str)) => Ok(Value::Str(str.clone())),
+                Some(&Primitive::Color(rgba)) => Ok(Value::Color(rgba)),
+                Some(Primitive::Note(name)) => {
+                    // (the parser only lets valid ones through, but a tuning doesn't have to map every note)
+                    let freq = parse_note(name).and_then(|(note, cents)| {
+                        Some(self.tuning.freq(note)? * 2f64.powf(cents / 1200.0))
+                    });
+                    match freq {
+                        Some(freq) => Ok(Value::Num(Quantity::new(freq, Dimension::Frequency))),
+                        None => error(format!("the tuning has no `{}`", name)),
+                    }
+                }
+                None => Err(Exit::Failed),
+            },
+            Expr::Var(id) => {
+                let Some(id) = id.node.as_deref() else {
+                    return Err(Exit::Failed);
+                };
+                match scope.get(&id.0) {
+                    Some(Some(value)) => Ok(value.clone()),
+                    Some(None) => Err(Exit::Failed),
+                    None => Ok(Value::Node(id.0.clone(), vec![])),
+                }
+            }
+            Expr::Paren(inner) => self.expr(inner, key, scope),
+            Expr::Block(block) => self.block(block, key, scope),
+            Expr::AnonymousFn(fun) => {
+                let Some(fun) = fun.node.as_deref() else {
+                    return Err(Exit::Failed);
+                };
+                Ok(Value::Fn(Box::new(Closure {
+                    name: None,
+                    params: param_names(&fun.params),
+                    body: Body::Expr(fun.body.clone()),
+                    scope: scope.clone(),
+                })))
+            }
+            Expr::Not(inner) => match self.expr(inner, key, scope)? {
+                Value::Bool(b) => Ok(Value::Bool(!b)),
+                node @ Value::Node(..) => Ok(Value::Node("!".into(), vec![(None, node)])),
+                value => error(format!("`!` needs a condition, not {}", value.describe())),
+            },
+            Expr::If(cond, then, otherwise) => {
+                // (whichever branch is taken, there has to be a value)
+                let Some(otherwise) = otherwise else {
+                    return error("`if` needs an `else`".into());
+                };
+
+                match self.expr(cond, key, scope)? {
+                    Value::Bool(true) => self.block(then, key, scope),
+                    Value::Bool(false) => self.expr(otherwise, key, scope),
+                    // (a signal, like `midi.gate > .5`, switches in between both branches while they're playing, see the engine's `Switch`)
+                    cond @ Value::Node(..) => {
+                        let then = self.block(then, key, scope)?;
+                        let otherwise = self.expr(otherwise, key, scope)?;
+                        Ok(Value::Node(
+                            "if".into(),
+                            vec![(None, cond), (None, then), (None, otherwise)],
+                        ))
+                    }
+                    value => Err(Exit::Error(
+                        cond.span(),
+                        format!("`if` needs a condition, not {}", value.describe()),
+                    )),
+                }
+            }
+            Expr::BinOp(left, op, right) => {
+                let left = self.expr(left, key, scope)?;
+                let right = self.expr(right, key, scope)?;
+                binop(*op, left, right).or_else(error)
+            }
+            Expr::Array(items) => Ok(Value::Array(
+                items
+                    .iter()
+                    .enumerate()
+                    .map(|(i, item)| {
+                        let key = key.index(i);
+                        Ok((key.clone(), self.expr(item, &key, scope)?))
+                    })
+                    .collect::<Result<_, _>>()?,
+            )),
+            Expr::Tuple(items) => Ok(Value::Tuple(
+                items
+                    .iter()
+                    .map(|item| self.expr(item, key, scope))
+                    .collect::<Result<_, _>>()?,
+            )),
+            Expr::Interpolated(parts) => {
+                let mut str = String::new();
+                for part in parts {
+                    match part {
+                        StrPart::Text(text) => str.push_str(text),
+                        StrPart::Expr(expr) => match self.expr(expr, key, scope)? {
+                            Value::Str(text) => str.push_str(&text),
+                            value => str.push_str(&value.to_string()),
+                        },
+                    }
+                }
+                Ok(Value::Str(str))
+            }
+            Expr::Index(a, i) => match (self.expr(a, key, scope)?, self.expr(i, key, scope)?) {
+                (Value::Array(items), Value::Num(i)) => {
+                    element(items.into_iter().map(|(_, item)| item).collect(), i).or_else(error)
+                }
+                (Value::Tuple(items), Value::Num(i)) => element(items, i).or_else(error),
+                // (slices are counted like array elements, but which ones there are is up to the engine)
+                (Value::Node(op, _), i)
+                    if op == "slices" && !matches!(&i, Value::Num(i) if is_index(i)) =>
+                {
+                    error(format!("can't index slices with {}", i))
+                }
+                (node @ Value::Node(..), i) => {
+                    Ok(Value::Node("[]".into(), vec![(None, node), (None, i)]))
+                }
+                (a, _) => error(format!("can't index {}", a.describe())),
+            },
+            Expr::Member(a, name) => {
+                let Some(name) = name.node.as_deref() else {
+                    return Err(Exit::Failed);
+                };
+                match self.expr(a, key, scope)? {
+                    // (like `midi.freq`)
+                    Value::Node(node, args) if args.is_empty() => {
+                        Ok(Value::Node(format!("{}.{}", node, name.0), vec![]))
+                    }
+                    a => error(format!("{} has no `{}`", a.describe(), name.0)),
+                }
+            }
+            Expr::Call(call) => {
+                let args = call
+                    .args
+                    .iter()
+                    .map(|arg| self.expr(arg, key, scope))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                let spanned = |exit| match exit {
+                    Exit::Error(None, message) => Exit::Error(expr.span(), message),
+                    exit => exit,
+                };
+
+                // (`xs.map(f)` is `map(xs, f)`)
+                if let Some(Expr::Member(a, name)) = call.fun.node.as_deref()
+                    && let Some(name) = name.node.as_deref()
+                    && ARRAY_FUNCTIONS.contains(&name.0.as_str())
+                {
+                    let mut args = args;
+                    args.insert(0, self.expr(a, key, scope)?);
+                    return self.array_function(&name.0, args).map_err(spanned);
+                }
+
+                // (and `xs.shuffle()` is `shuffle(xs)`)
+                if let Some(Expr::Member(a, name)) = call.fun.node.as_deref()
+                    && let Some(name) = name.node.as_deref()
+                    && RANDOM_FUNCTIONS.contains(&name.0.as_str())
+                {
+                    let mut args = args;
+                    args.insert(0, self.expr(a, key, scope)?);
+                    let seed = self.draw(key, expr);
+                    return self.random_function(&name.0, args, seed).map_err(spanned);
+                }
+
+                // (and `notes.degree(3)` is `degree(notes, 3)`)
+                if let Some(Expr::Member(a, name)) = call.fun.node.as_deref()
+                    && let Some(name) = name.node.as_deref()
+                    && name.0 == "degree"
+                {
+                    let mut args = args;
+                    args.insert(0, self.expr(a, key, scope)?);
+                    return music_function(&name.0, args, key, &self.tuning).map_err(spanned);
+                }
+
+                // (and `beat.swing(.56)` is `swing(beat, .56)`)
+                if let Some(Expr::Member(a, name)) = call.fun.node.as_deref()
+                    && let Some(name) = name.node.as_deref()
+                    && PATTERN_FUNCTIONS.contains(&name.0.as_str())
+                {
+                    let mut args = args;
+                    args.insert(0, self.expr(a, key, scope)?);
+                    if name.0 == "sometimes" {
+                        let seed = self.draw(key, expr);
+                        return self.sometimes(args, key, seed).map_err(spanned);
+                    }
+                    let config = args.into_iter().map(|arg| (None, arg)).collect();
+                    return Ok(Value::Node(name.0.clone(), config));
+                }
+
+                match self.expr(&call.fun, key, scope)? {
+                    // (`watch(x)` is just `x`, which the editor shows in its watch panel)
+                    Value::Node(name, config) if name == "watch" && config.is_empty() => {
+                        match <[Value; 1]>::try_from(args) {
+                            Ok([value]) => {
+                                self.watch(call.args[0].to_string(), value.clone());
+                                Ok(value)
+                            }
+                            Err(_) => error("`watch` expects 1 argument".into()),
+                        }
+                    }
+                    // (`ease(x, 200ms)` is `x`, which glides to its new value when it's changed, see `latches`)
+                    Value::Node(name, config) if name == "ease" && config.is_empty() => {
+                        match <[Value; 2]>::try_from(args) {
+                            Ok([value @ Value::Num(_), Value::Num(ease)])
+                                if matches!(ease.dimension, Dimension::Time | Dimension::Ratio) =>
+                            {
+                                Ok(Value::Node(
+                                    name,
+                                    vec![(None, value), (None, Value::Num(ease))],
+                                ))
+                            }
+                            _ => error(
+                                "`ease` expects a number and how long it glides, like `ease(800hz, 200ms)`"
+                                    .into(),
+                            ),
+                        }
+                    }
+                    // (`perform(x)` is `x`, which the editor captures into snapshots, see `performables`)
+                    Value::Node(name, config) if name == "perform" && config.is_empty() => {
+                        match <[Value; 1]>::try_from(args) {
+                            Ok([value @ Value::Num(_)]) => Ok(Value::Node(name, vec![(None, value)])),
+                            Ok([Value::Node(eased, config)]) if eased == "ease" => Ok(Value::Node(
+                                name,
+                                vec![(None, Value::Node(eased, config))],
+                            )),
+                            _ => error(
+                                "`perform` expects a number, like `perform(800hz)`".into(),
+                            ),
+                        }
+                    }
+                    // (`every(1s, || ..)` schedules a callback, and is otherwise just what it is, like a node)
+                    Value::Node(name, config)
+                        if TIMER_FUNCTIONS.contains(&name.as_str()) && config.is_empty() =>
+                    {
+                        self.timer(name, args, key)
+                    }
+                    Value::Node(name, _) if ARRAY_FUNCTIONS.contains(&name.as_str()) => {
+                        self.array_function(&name, args)
+                    }
+                    Value::Node(name, config)
+                        if RANDOM_FUNCTIONS.contains(&name.as_str()) && config.is_empty() =>
+                    {
+                        let seed = self.draw(key, expr);
+                        self.random_function(&name, args, seed)
+                    }
+                    Value::Node(name, config) if name == "sometimes" && config.is_empty() => {
+                        let seed = self.draw(key, expr);
+                        self.sometimes(args, key, seed)
+                    }
+                    Value::Node(name, config)
+                        if MUSIC_FUNCTIONS.contains(&name.as_str()) && config.is_empty() =>
+                    {
+                        music_function(&name, args, key, &self.tuning)
+                    }
+                    Value::Node(name, config) if name == "tuning" && config.is_empty() => {
+                        self.tune(args)
+                    }
+                    Value::Node(name, config) if name == "poly" => self.poly(config, args, key),
+                    Value::Node(name, mut config) => {
+                        config.extend(args.into_iter().map(|arg| (None, arg)));
+                        Ok(Value::Node(name, config))
+                    }
+                    Value::Fn(closure) => self.call(&closure, args, key),
+                    fun => error(format!("can't call {}", fun.describe())),
+                }
+                .map_err(spanned)
+            }
+            Expr::Modify(a, modifiers) | Expr::Settings(a, modifiers) => {
+                match self.expr(a, key, scope)? {
+                    Value::Node(name, mut config) => {
+                        for modifier in modifiers {
+                            config.push(match modifier {
+                                Modifier::Arg(value) => (None, self.expr(value, key, scope)?),
+                                Modifier::Setting(name, value) => (
+                                    name.node.as_deref().map(|id| id.0.clone()),
+                                    self.expr(value, key, scope)?,
+                                ),
+                            });
+                        }
+                        Ok(Value::Node(name, config))
+                    }
+                    a => error(format!("can't configure {}", a.describe())),
+                }
+            }
+        }
+    }
+
+    fn watch(&mut self, label: String, value: Value) {
+        match self.watched.iter_mut().find(|(watched, _)| *watched == label) {
+            Some((_, watched)) => *watched = value,
+            None => self.watched.push((label, value)),
+        }
+    }
+
+    /**
+        Where the random stream of a call (like `rand()`) starts: by its key and its code, and how many times that same call came up under that key before (like for `rand() + rand()`). So it's the same every time the document is evaluated (with the same seed), also when other code changed, and a function that's called for different keys (like by `map`) is random differently every time.
+    */
+    fn draw(&mut self, key: &Key, call: &SyntaxNode<Expr>) -> u64 {
+        let call = call.to_string();
+        let n = self.draws.entry((key.clone(), call.clone())).or_default();
+        *n += 1;
+
+        seed(self.seed, &[&key.0, &call, &n.to_string()])
+    }
+
+    fn random_function(&mut self, name: &str, args: Vec<Value>, seed: u64) -> Eval {
+        match (name, &args[..]) {
+            ("rand", []) => Ok(Value::Num(Quantity::new(random(seed, 0), Dimension::Ratio))),
+            ("rand", _) => Err(Exit::Error(None, "`rand` expects no arguments".into())),
+            ("choose", [Value::Array(items)]) if items.is_empty() => {
+                Err(Exit::Error(None, "can't choose from an empty array".into()))
+            }
+            ("choose", [Value::Array(items)]) => {
+                let i = (random(seed, 0) * items.len() as f64) as usize;
+                Ok(items[i.min(items.len() - 1)].1.clone())
+            }
+            // (the elements keep their keys, so that they're diffed as the same ones, just elsewhere)
+            ("shuffle", [Value::Array(items)]) => {
+                let mut items = items.clone();
+                for i in (1..items.len()).rev() {
+                    let j = (random(seed, i as u64) * (i + 1) as f64) as usize;
+                    items.swap(i, j.min(i));
+                }
+                Ok(Value::Array(items))
+            }
+            (_, [value]) => Err(Exit::Error(
+                None,
+                format!("`{}` works on an array, not {}", name, value.describe()),
+            )),
+            _ => Err(Exit::Error(None, format!("`{}` expects 1 argument", name))),
+        }
+    }
+
+    /**
+        (`beat.sometimes(.3, fx)` is the pattern and `fx(beat)` both, with how often the second one plays a step instead, and a seed, for the engine to pick per step, see its `Sometimes`)
+    */
+    fn sometimes(&mut self, args: Vec<Value>, key: &Key, seed: u64) -> Eval {
+        match <[Value; 3]>::try_from(args) {
+            Ok([pattern @ Value::Node(..), Value::Num(probability), Value::Fn(f)])
+                if probability.dimension == Dimension::Ratio
+                    && (0.0..=1.0).contains(&probability.value) =>
+            {
+                let other = self.call(&f, vec![pattern.clone()], key)?;
+                // (a seed that fits in a number exactly)
+                let seed = Quantity::new((seed >> 32) as f64, Dimension::Ratio);
+
+                Ok(Value::Node(
+                    "sometimes".into(),
+                    vec![
+                        (None, pattern),
+                        (None, Value::Num(probability)),
+                        (None, other),
+                        (None, Value::Num(seed)),
+                    ],
+                ))
+            }
+            _ => Err(Exit::Error(
+                None,
+                "`sometimes` expects a pattern, how often (from 0 to 1), and a function, like `beat.sometimes(.3, |p| ..)`".into(),
+            )),
+        }
+    }
+
+    /**
+        (`poly(|| sin(midi.freq) * midi.gate)` is what the function makes, as the voice that the engine plays every note on, a few of them at once)
+    */
+    fn poly(
+        &mut self,
+        mut config: Vec<(Option<String>, Value)>,
+        args: Vec<Value>,
+        key: &Key,
+    ) -> Eval {
+        for arg in args {
+            let arg = match arg {
+                Value::Fn(f) if f.params.is_empty() => self.call(&f, vec![], key)?,
+                Value::Fn(_) => {
+                    return Err(Exit::Error(
+                        None,
+                        "the function that `poly` plays takes nothing, like `poly(|| sin(midi.freq))`".into(),
+                    ))
+                }
+                arg => arg,
+            };
+            config.push((None, arg));
+        }
+
+        Ok(Value::Node("poly".into(), config))
+    }
+
+    /**
+        (`tuning("just.scl")`, or with a keyboard mapping, `tuning("just.scl", "c.kbm")`, tunes the notes after it)
+    */
+    fn tune(&mut self, args: Vec<Value>) -> Eval {
+        let read = |path: &str| {
+            fs::read_to_string(resolve_path(&self.root, path))
+                .map_err(|e| Exit::Error(None, format!("can't read {}: {}", path, e)))
+        };
+
+        let (scl, kbm) = match &args[..] {
+            [Value::Str(scl)] => (scl, None),
+            [Value::Str(scl), Value::Str(kbm)] => (scl, Some(kbm)),
+            _ => {
+                return Err(Exit::Error(
+                    None,
+                    "`tuning` expects a Scala file, and maybe a keyboard mapping, like `tuning(\"just.scl\", \"c.kbm\")`".into(),
+                ))
+            }
+        };
+
+        let kbm = kbm.map(|kbm| read(kbm)).transpose()?;
+        self.tuning = Tuning::from_scala(&read(scl)?, kbm.as_deref())
+            .map_err(|message| Exit::Error(None, message))?;
+
+        Ok(Value::Node(
+            "tuning".into(),
+            args.into_iter().map(|arg| (None, arg)).collect(),
+        ))
+    }
+
+    fn timer(&mut self, name: String, args: Vec<Value>, key: &Key) -> Eval {
+        let usage = match name.as_str() {
+            "every" => "`every` expects how often, and a function, like `every(2s, || ..)`",
+            "after" => "`after` expects how long, and a function, like `after(2s, || ..)`",
+            _ => "`at` expects a bar (1 is the next one), and a function, like `at(2, || ..)`",
+        };
+
+        let [Value::Num(amount), Value::Fn(closure)] = &args[..] else {
+            return Err(Exit::Error(None, usage.into()));
+        };
+        if closure.params.len() > 1 {
+            return Err(Exit::Error(
+                None,
+                format!("the function that `{}` calls takes how many times it was called before, or nothing", name),
+            ));
+        }
+
+        let timing = match name.as_str() {
+            "every" if amount.value > 0.0 => Timing::Every(amount.value),
+            "every" => {
+                return Err(Exit::Error(
+                    None,
+                    format!("can't do something every {}", amount),
+                ))
+            }
+            "after" if amount.value >= 0.0 => Timing::After(amount.value),
+            "at" if amount.value >= 1.0 && amount.value.fract() == 0.0 => Timing::At(amount.value),
+            _ => return Err(Exit::Error(None, usage.into())),
+        };
+
+        // (a statement with more than one of them keeps them apart by order)
+        let n = self
+            .timers
+            .iter()
+            .filter(|timer| timer.key.is_within(key))
+            .count();
+        self.timers.push(Timer {
+            key: if n == 0 { key.clone() } else { key.index(n) },
+            timing,
+            callback: args[1].clone(),
+            seed: self.seed,
+            tuning: self.tuning.clone(),
+        });
+
+        Ok(Value::Node(
+            name,
+            args.into_iter().map(|arg| (None, arg)).collect(),
+        ))
+    }
+
+    fn call(&mut self, closure: &Closure, args: Vec<Value>, key: &Key) -> Eval {
+        if args.len() != closure.params.len() {
+            return Err(Exit::Error(
+                None,
+                format!(
+                    "expected {} argument{}, not {}",
+                    closure.params.len(),
+                    if closure.params.len() == 1 { "" } else { "s" },
+                    args.len()
+                ),
+            ));
+        }
+
+        if self.depth >= MAX_DEPTH {
+            return Err(Exit::Error(None, "too much recursion".into()));
+        }
+
+        let mut scope = closure.scope.clone();
+        if let Some(name) = &closure.name {
+            scope.insert(name.clone(), Some(Value::Fn(Box::new(closure.clone()))));
+        }
+        for (param, arg) in closure.params.iter().zip(args) {
+            scope.insert(param.clone(), Some(arg));
+        }
+
+        self.depth += 1;
+        let value = match &closure.body {
+            Body::Expr(body) => self.expr(body, key, &scope),
+            Body::Block(body) => self.block(body, key, &scope),
+        };
+        self.depth -= 1;
+
+        match value {
+            Err(Exit::Return(value)) => Ok(value),
+            value => value,
+        }
+    }
+
+    fn array_function(&mut self, name: &str, args: Vec<Value>) -> Eval {
+        let expects = |n: usize| {
+            Exit::Error(
+                None,
+                format!(
+                    "`{}` expects {} argument{}",
+                    name,
+                    n,
+                    if n == 1 { "" } else { "s" }
+                ),
+            )
+        };
+
+        let mut args = args.into_iter();
+        let items = match args.next() {
+            Some(Value::Array(items)) => items,
+            Some(value) => {
+                return Err(Exit::Error(
+                    None,
+                    format!("`{}` works on an array, not {}", name, value.describe()),
+                ));
+            }
+            None => return Err(expects(2)),
+        };
+        let rest = args.collect::<Vec<_>>();
+
+        match (name, &rest[..]) {
+            ("map", [Value::Fn(f)]) => Ok(Value::Array(
+                items
+                    .into_iter()
+                    .map(|(key, item)| Ok((key.clone(), self.call(f, vec![item], &key)?)))
+                    .collect::<Result<_, _>>()?,
+            )),
+            ("filter", [Value::Fn(f)]) => {
+                let mut kept = vec![];
+                for (key, item) in items {
+                    match self.call(f, vec![item.clone()], &key)? {
+                        Value::Bool(true) => kept.push((key, item)),
+                        Value::Bool(false) => {}
+                        value => {
+                            return Err(Exit::Error(
+                                None,
+                                format!("`filter` needs true or false, not {}", value.describe()),
+                            ));
+                        }
+                    }
+                }
+                Ok(Value::Array(kept))
+            }
+            ("sum", []) => items
+                .into_iter()
+                .try_fold(
+                    Value::Num(Quantity::new(0.0, Dimension::Ratio)),
+                    |sum, (_, item)| binop(Op::Add, sum, item),
+                )
+                .map_err(|message| Exit::Error(None, message)),
+            ("zip", [Value::Array(other)]) => Ok(Value::Array(
+                items
+                    .into_iter()
+                    .zip(other.iter().map(|(_, item)| item.clone()))
+                    .map(|((key, a), b)| (key, Value::Tuple(vec![a, b])))
+                    .collect(),
+            )),
+            ("map" | "filter", [_]) => Err(Exit::Error(
+                None,
+                format!("`{}` needs a function, like `{}(|x| ..)`", name, name),
+            )),
+            ("zip", [value]) => Err(Exit::Error(
+                None,
+                format!("can't zip an array with {}", value.describe()),
+            )),
+            ("sum", _) => Err(expects(0)),
+            _ => Err(expects(1)),
+        }
+    }
+}
+
+fn param_names(params: &ParamList) -> Vec<String> {
+    params
+        .0
+        .iter()
+        .map(|param| {
+            let name = param.node.as_deref().and_then(|p| p.name.node.as_deref());
+            name.map_or(String::new(), |id| id.0.clone())
+        })
+        .collect()
+}
+
+/**
+    Checks how what's played is routed through buses (`send(kick, "drums")` and `bus("drums")`): what's read from a bus has to be sent to it by something that's played, and a bus can't feed back into what sends to it, because then there's no order in which the engine can render them
+*/
+fn route(played: &[(SourceSpan, Value)]) -> Vec<(SourceSpan, String)> {
+    let mut errors = vec![];
+    let mut routings = vec![];
+
+    for (range, value) in played {
+        let (mut sends, mut reads) = (vec![], vec![]);
+        if let Err(message) = buses(value, &mut sends, &mut reads) {
+            errors.push((*range, message));
+        }
+        routings.push((sends, reads));
+    }
+
+    // (whether what's played as `i` ends up in what's played as `j`)
+    let feeds = |i: usize, j: usize| {
+        let mut seen = vec![false; routings.len()];
+        let mut todo = vec![i];
+        while let Some(k) = todo.pop() {
+            for (next, (_, reads)) in routings.iter().enumerate() {
+                if !seen[next] && routings[k].0.iter().any(|bus| reads.contains(bus)) {
+                    if next == j {
+                        return true;
+                    }
+                    seen[next] = true;
+                    todo.push(next);
+                }
+            }
+        }
+        false
+    };
+
+    for (i, (range, _)) in played.iter().enumerate() {
+        for bus in &routings[i].1 {
+            let senders = (0..routings.len())
+                .filter(|&j| routings[j].0.contains(bus))
+                .collect::<Vec<_>>();
+
+            if senders.is_empty() {
+                errors.push((
+                    *range,
+                    format!("nothing that's played is sent to the {:?} bus", bus),
+                ));
+            } else if senders.iter().any(|&j| j == i || feeds(i, j)) {
+                errors.push((*range, format!("the {:?} bus feeds back into itself", bus)));
+            }
+        }
+    }
+
+    // (in the order of the document)
+    errors.sort_by_key(|(range, _)| range.start);
+    errors
+}
+
+/// The names of the buses that a signal sends to and reads from
+fn buses(value: &Value, sends: &mut Vec<String>, reads: &mut Vec<String>) -> Result<(), String> {
+    match value {
+        Value::Node(name, args) => {
+            match (name.as_str(), args.as_slice()) {
+                ("bus", [(None, Value::Str(bus))]) => reads.push(bus.clone()),
+                ("bus", _) => {
+                    return Err("`bus` needs the name of a bus, like `bus(\"drums\")`".into());
+                }
+                ("send", [_, (None, Value::Str(bus)), ..]) => sends.push(bus.clone()),
+                ("send", _) => {
+                    return Err(
+                        "`send` needs the name of a bus to send to, like `send(kick, \"drums\")`"
+                            .into(),
+                    );
+                }
+                _ => {}
+            }
+
+            for (_, arg) in args {
+                buses(arg, sends, reads)?;
+            }
+            Ok(())
+        }
+        Value::Array(items) => items
+            .iter()
+            .try_for_each(|(_, item)| buses(item, sends, reads)),
+        Value::Tuple(items) => items.iter().try_for_each(|item| buses(item, sends, reads)),
+        _ => Ok(()),
+    }
+}
+
+/**
+    Checks where what's played is placed on the output channels (`pan(pad, -.5)` and `channel(click, 3)`): the engine places a target as a whole, so that has to be the outermost thing that's played, and somewhere it can put it
+*/
+fn place(played: &[(SourceSpan, Value)]) -> Vec<(SourceSpan, String)> {
+    played
+        .iter()
+        .filter_map(|(range, value)| {
+            placement(value, true)
+                .err()
+                .map(|message| (*range, message))
+        })
+        .collect()
+}
+
+fn placement(value: &Value, outermost: bool) -> Result<(), String> {
+    let ratio = |value: &Value| matches!(value, Value::Num(q) if q.dimension == Dimension::Ratio);
+
+    match value {
+        Value::Node(name, args) => {
+            match (name.as_str(), args.as_slice()) {
+                ("pan", _) if !outermost => {
+                    return Err(
+                        "`pan` places what's played as a whole, like `play pan(pad, -.5)`".into(),
+                    );
+                }
+                ("channel", _) if !outermost => {
+                    return Err(
+                        "`channel` places what's played as a whole, like `play channel(click, 3)`"
+                            .into(),
+                    );
+                }
+                ("pan", [_, (None, Value::Num(position))])
+                    if ratio(&args[1].1) && (-1.0..=1.0).contains(&position.value) => {}
+                ("pan", [_, (None, Value::Array(gains))])
+                    if gains.iter().all(|(_, gain)| ratio(gain)) => {}
+                ("pan", _) => {
+                    return Err("`pan` needs a position from -1 (the first channel) to 1 (the last), or a gain per channel, like `pan(pad, [1, 0, .5, .5])`".into());
+                }
+                ("channel", [_, (None, Value::Num(channel))])
+                    if ratio(&args[1].1)
+                        && channel.value >= 1.0
+                        && channel.value.fract() == 0.0 => {}
+                ("channel", _) => {
+                    return Err(
+                        "`channel` needs a channel, counting from 1, like `channel(click, 3)`"
+                            .into(),
+                    );
+                }
+                _ => {}
+            }
+
+            args.iter().try_for_each(|(_, arg)| placement(arg, false))
+        }
+        Value::Array(items) => items
+            .iter()
+            .try_for_each(|(_, item)| placement(item, false)),
+        Value::Tuple(items) => items.iter().try_for_each(|item| placement(item, false)),
+        _ => Ok(()),
+    }
+}
+
+/// (see `FUNCTIONS`)
+const ARRAY_FUNCTIONS: &[&str] = &["map", "filter", "sum", "zip"];
+/// (which the engine applies to the pattern's steps)
+const PATTERN_FUNCTIONS: &[&str] = &["swing", "humanize", "sometimes"];
+/// (see `Evaluator::draw`)
+const RANDOM_FUNCTIONS: &[&str] = &["rand", "choose", "shuffle"];
+/// (see `Timer`)
+const TIMER_FUNCTIONS: &[&str] = &["every", "after", "at"];
+/// (see `music`)
+const MUSIC_FUNCTIONS: &[&str] = &["scale", "chord", "degree", "mtof", "ftom"];
+
+/**
+    Scales and chords are arrays of frequencies (keyed like array literals, so that they're diffed note by note), and the conversions work on numbers, or otherwise on signals, which the engine converts as they play
+*/
+fn music_function(name: &str, args: Vec<Value>, key: &Key, tuning: &Tuning) -> Eval {
+    let error = |message: String| Err(Exit::Error(None, message));
+    let freq = |value: f64| Value::Num(Quantity::new(value, Dimension::Frequency));
+
+    match (name, &args[..]) {
+        ("scale" | "chord", [Value::Str(kind), Value::Num(root)])
+            if root.dimension == Dimension::Frequency =>
+        {
+            let table = if name == "scale" { SCALES } else { CHORDS };
+            match intervals(table, kind, root.value, tuning) {
+                Ok(notes) => Ok(Value::Array(
+                    notes
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, note)| (key.index(i), freq(note)))
+                        .collect(),
+                )),
+                Err(known) => error(format!(
+                    "there's no {} called \"{}\" (there's {})",
+                    name, kind, known
+                )),
+            }
+        }
+        ("scale" | "chord", _) => error(format!(
+            "`{}` expects a name and a root note, like `{}(\"minor\", a3)`",
+            name, name
+        )),
+        ("degree", [Value::Array(items), Value::Num(n)])
+            if n.dimension == Dimension::Ratio && n.value.fract() == 0.0 =>
+        {
+            let Some((_, Value::Num(first))) = items.first() else {
+                return error("`degree` needs a scale with notes in it".into());
+            };
+
+            // (the notes all measure the same, like the frequencies of a scale)
+            let mut notes = vec![];
+            for (_, item) in items {
+                match item {
+                    Value::Num(note) if note.dimension == first.dimension => notes.push(note.value),
+                    _ => return error("`degree` needs a scale with notes in it".into()),
+                }
+            }
+
+            Ok(Value::Num(Quantity::new(
+                degree(&notes, n.value as i64, tuning),
+                first.dimension,
+            )))
+        }
+        ("degree", _) => error(
+            "`degree` expects a scale and which note of it (counting from 1), like `scale(\"minor\", a3).degree(5)`"
+                .into(),
+        ),
+        ("mtof", [Value::Num(n)]) if n.dimension == Dimension::Ratio => {
+            Ok(freq(midi_to_freq(n.value)))
+        }
+        ("ftom", [Value::Num(f)]) if f.dimension == Dimension::Frequency => Ok(Value::Num(
+            Quantity::new(freq_to_midi(f.value), Dimension::Ratio),
+        )),
+        ("mtof" | "ftom", [Value::Node(..)]) => Ok(Value::Node(
+            name.into(),
+            args.into_iter().map(|arg| (None, arg)).collect(),
+        )),
+        ("mtof", _) => error("`mtof` expects a MIDI note number, like `mtof(60)`".into()),
+        _ => error("`ftom` expects a frequency, like `ftom(440hz)`".into()),
+    }
+}
+
+fn is_index(i: &Quantity) -> bool {
+    i.dimension == Dimension::Ratio && i.value.fract() == 0.0 && i.value >= 0.0
+}
+
+fn element(items: Vec<Value>, i: Quantity) -> Result<Value, String> {
+    let len = items.len();
+    if !is_index(&i) {
+        return Err(format!("can't index with {}", i));
+    }
+
+    items
+        .into_iter()
+        .nth(i.value as usize)
+        .ok_or_else(|| format!("index {} is out of bounds, there are {}", i, len))
+}
+
+fn binop(op: Op, left: Value, right: Value) -> Result<Value, String> {
+    let symbol = op.to_string();
+    match (left, right) {
+        (Value::Num(a), Value::Num(b)) if op.is_comparison() => {
+            Ok(Value::Bool(a.combine(op, b)?.is_true()))
+        }
+        (Value::Num(a), Value::Num(b)) if !op.is_logical() => Ok(Value::Num(a.combine(op, b)?)),
+        (Value::Bool(a), Value::Bool(b)) if op.is_logical() || matches!(op, Op::Eq | Op::Ne) => {
+            Ok(Value::Bool(match op {
+                Op::And => a && b,
+                Op::Or => a || b,
+                Op::Eq => a == b,
+                _ => a != b,
+            }))
+        }
+        (Value::Str(a), Value::Str(b)) if op == Op::Add => Ok(Value::Str(a + &b)),
+        (Value::Str(a), Value::Str(b)) if matches!(op, Op::Eq | Op::Ne) => {
+            Ok(Value::Bool((a == b) == (op == Op::Eq)))
+        }
+        // (signals, which are combined while playing)
+        (a @ (Value::Num(_) | Value::Node(..)), b @ (Value::Num(_) | Value::Node(..)))
+            if !op.is_logical() =>
+        {
+            Ok(Value::Node(symbol, vec![(None, a), (None, b)]))
+        }
+        (a @ (Value::Bool(_) | Value::Node(..)), b @ (Value::Bool(_) | Value::Node(..)))
+            if op.is_logical() =>
+        {
+            Ok(Value::Node(symbol, vec![(None, a), (None, b)]))
+        }
+        (a, b) => Err(cant_combine(op, a.describe(), b.describe())),
+    }
+}
+
+/// When a timer fires
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Timing {
+    /// Every so many seconds, starting right away (or at the next boundary, when the transport is quantized)
+    Every(f64),
+    /// Once, so many seconds from now
+    After(f64),
+    /// Once, at the start of a bar, counting from 1 for the next one
+    At(f64),
+}
+
+/**
+    A function that's called on the transport's time, by `every(2s, || ..)`, `after(2s, || ..)` or `at(2, || ..)`, so that rhythmic logic (like changing a pattern every 8 bars) can live in the code. The editor has the engine time it (sample-accurately), and calls it when it fired, where the function gets how many times that happened before, if it takes an argument. A timer is recognized by its key when the document is evaluated again, like the values are, so it keeps its count.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Timer {
+    pub key: Key,
+    pub timing: Timing,
+    // (a `Value::Fn`)
+    callback: Value,
+    // (of the document, see `evaluate_with_seed`)
+    seed: u64,
+    // (and what it was tuned to, when it was scheduled)
+    tuning: Tuning,
+}
+
+impl Timer {
+    /**
+        Calls the function, with the values that it evaluated to under the timer's key, and what it watched
+    */
+    pub fn fire(&self, count: usize) -> Evaluation {
+        // (so that it's random differently every time it fires, but the same way every time the code is played)
+        let mut evaluator = Evaluator {
+            seed: seed(self.seed, &[&count.to_string()]),
+            tuning: self.tuning.clone(),
+            ..Evaluator::default()
+        };
+        let mut evaluation = Evaluation {
+            values: vec![],
+            errors: vec![],
+            watched: vec![],
+            timers: vec![],
+            tuning: self.tuning.clone(),
+        };
+
+        if let Value::Fn(closure) = &self.callback {
+            let args = match closure.params.len() {
+                0 => vec![],
+                _ => vec![Value::Num(Quantity::new(count as f64, Dimension::Ratio))],
+            };
+            let body = match &closure.body {
+                Body::Expr(body) => body.span(),
+                Body::Block(body) => body.span(),
+            };
+
+            match evaluator.call(closure, args, &self.key) {
+                Ok(value) => evaluation.values.push((self.key.clone(), value)),
+                Err(exit) => evaluator.report(exit, body),
+            }
+        }
+
+        evaluation.errors = evaluator.errors;
+        evaluation.watched = evaluator.watched;
+        evaluation.timers = evaluator.timers;
+        evaluation.tuning = evaluator.tuning;
+        evaluation
+    }
+}
+
+/// What changed in between two evaluations of a document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    Added(Key),
+    Removed(Key),
+    Changed(Key),
+}
+
+/**
+    Compares two evaluations by key, and element by element for arrays, so that (for example) an array that's filtered differently only changes the elements that are now in or out.
+*/
+pub fn diff(old: &[(Key, Value)], new: &[(Key, Value)]) -> Vec<Change> {
+    let mut old_leaves = vec![];
+    for (key, value) in old {
+        leaves(key, key, value, &mut old_leaves);
+    }
+    let mut new_leaves = vec![];
+    for (key, value) in new {
+        leaves(key, key, value, &mut new_leaves);
+    }
+
+    let old_map = old_leaves.iter().cloned().collect::<HashMap<_, _>>();
+    let new_map = new_leaves.iter().cloned().collect::<HashMap<_, _>>();
+
+    let mut changes = vec![];
+    for (key, value) in &new_leaves {
+        match old_map.get(key) {
+            None => changes.push(Change::Added(key.clone())),
+            Some(old) if *old != *value => changes.push(Change::Changed(key.clone())),
+            Some(_) => {}
+        }
+    }
+    for (key, _) in &old_leaves {
+        if !new_map.contains_key(key) {
+            changes.push(Change::Removed(key.clone()));
+        }
+    }
+
+    changes
+}
+
+/**
+    A number that changed in between two evaluations of a document, which (instead of jumping to its new value) glides there from the old one. That's "latching": the number is recognized by its key, like `fx.f` for the `f` setting of `def fx = lowpass{f = 800hz}`, and the glide is `ease` seconds when it says so, like `ease(800hz, 200ms)`.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Latch {
+    pub key: Key,
+    pub from: f64,
+    pub to: f64,
+    pub ease: Option<f64>,
+}
+
+/**
+    The numbers (and settings of nodes) that changed in between two evaluations, to latch
+*/
+pub fn latches(old: &[(Key, Value)], new: &[(Key, Value)]) -> Vec<Latch> {
+    let mut old_params = vec![];
+    for (key, value) in old {
+        params(key, value, &mut old_params);
+    }
+    let mut new_params = vec![];
+    for (key, value) in new {
+        params(key, value, &mut new_params);
+    }
+
+    let old_map = old_params
+        .into_iter()
+        .map(|(key, from, _)| (key, from))
+        .collect::<HashMap<_, _>>();
+
+    new_params
+        .into_iter()
+        .filter_map(|(key, to, ease)| {
+            let from = *old_map.get(&key)?;
+            (from != to).then_some(Latch {
+                key,
+                from,
+                to,
+                ease,
+            })
+        })
+        .collect()
+}
+
+/**
+    The numbers that are marked with `perform(..)`, by key (like `fx.f` for `def fx = lowpass{f = perform(800hz)}`), which the editor captures into snapshots, to morph between
+*/
+pub fn performables(values: &[(Key, Value)]) -> Vec<(Key, f64)> {
+    let mut out = vec![];
+    for (key, value) in values {
+        performed(key, value, &mut out);
+    }
+    out
+}
+
+fn performed(key: &Key, value: &Value, out: &mut Vec<(Key, f64)>) {
+    match value {
+        Value::Node(name, _) if name == "perform" => {
+            let mut numbers = vec![];
+            params(key, value, &mut numbers);
+            out.extend(numbers.into_iter().map(|(key, value, _)| (key, value)));
+        }
+        Value::Node(_, config) => {
+            for (setting, value) in config {
+                if let Some(setting) = setting {
+                    performed(&key.member(setting), value, out);
+                }
+            }
+        }
+        Value::Array(items) => {
+            for (item_key, item) in items {
+                performed(item_key, item, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// (the numbers in a value, by key, with how long they glide if they say so)
+fn params(key: &Key, value: &Value, out: &mut Vec<(Key, f64, Option<f64>)>) {
+    match value {
+        Value::Num(quantity) => out.push((key.clone(), quantity.value, None)),
+        // (it's just what it performs)
+        Value::Node(name, config) if name == "perform" => {
+            if let [(None, value)] = config.as_slice() {
+                params(key, value, out);
+            }
+        }
+        Value::Node(name, config) if name == "ease" => {
+            if let [(None, Value::Num(quantity)), (None, Value::Num(ease))] = config.as_slice() {
+                out.push((key.clone(), quantity.value, Some(ease.value)));
+            }
+        }
+        Value::Node(_, config) => {
+            for (setting, value) in config {
+                if let Some(setting) = setting {
+                    params(&key.member(setting), value, out);
+                }
+            }
+        }
+        Value::Array(items) => {
+            for (item_key, item) in items {
+                params(item_key, item, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// (the elements of an array that's bound to another name than where it's from, like `let ys = xs.map(..)`, are `ys:xs[0]` etc.)
+fn leaves<'v>(binding: &Key, key: &Key, value: &'v Value, out: &mut Vec<(Key, &'v Value)>) {
+    match value {
+        Value::Array(items) => {
+            for (item_key, item) in items {
+                let item_key = if item_key.is_within(binding) {
+                    item_key.clone()
+                } else {
+                    Key(format!("{}:{}", binding, item_key))
+                };
+                leaves(binding, &item_key, item, out);
+            }
+        }
+        value => out.push((key.clone(), value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(source: &str) -> Evaluation {
+        evaluate_source(source)
+    }
+
+    fn values(source: &str) -> Vec<String> {
+        let evaluation = eval(source);
+        assert_eq!(evaluation.errors, vec![]);
+        evaluation
+            .values
+            .into_iter()
+            .map(|(key, value)| format!("{} = {}", key, value))
+            .collect()
+    }
+
+    fn errors(source: &str) -> Vec<(&str, String)> {
+        eval(source)
+            .errors
+            .into_iter()
+            .map(|(span, message)| (&source[span.range()], message))
+            .collect()
+    }
+
+    #[test]
+    fn test_evaluate() {
+        assert_eq!(
+            values("let beat = 500ms; def f = 1 / beat; let t = (1, \"two\", [f * 2]);"),
+            vec!["beat = 0.5s", "f = 2hz", "t = (1, \"two\", [4hz])"]
+        );
+
+        assert_eq!(
+            values("fn twice(x) { let y = x * 2; y } play twice(220hz) + sin(4hz);"),
+            vec!["twice = |x| ..", "program.play[0] = (440hz + sin(4hz))"]
+        );
+
+        assert_eq!(
+            values("let f = lowpass{f = 800hz}; play f(midi.freq);"),
+            vec![
+                "f = lowpass(f = 800hz)",
+                "program.play[0] = lowpass(f = 800hz, midi.freq)"
+            ]
+        );
+
+        assert_eq!(
+            errors("let a = 5hz + 3s; let b = a * 2; let c = [1, 2][2]; fn f() { f() } f();"),
+            vec![
+                ("5hz + 3s", "can't add a time to a frequency".into()),
+                ("[1, 2][2]", "index 2 is out of bounds, there are 2".into()),
+                ("f()", "too much recursion".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_conditionals() {
+        assert_eq!(
+            values(
+                "fn abs(x) { if x < 0s { x * -1 } else { x } } let a = abs(-2s); let b = 1 == 1 && !(\"a\" != \"a\");"
+            ),
+            vec!["abs = |x| ..", "a = 2s", "b = true"]
+        );
+
+        assert_eq!(
+            values("play if midi.gate > .5 { saw(midi.freq) } else { 0 };"),
+            vec!["program.play[0] = if((midi.gate > 0.5), saw(midi.freq), 0)"]
+        );
+
+        assert_eq!(
+            errors(
+                "let a = if 1s { 1 } else { 2 }; let b = if true { 1 }; let c = 1s < 2hz || true;"
+            ),
+            vec![
+                ("1s", "`if` needs a condition, not a time".into()),
+                ("if true { 1 }", "`if` needs an `else`".into()),
+                ("1s < 2hz", "can't compare a time to a frequency".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_watch() {
+        let evaluation = eval(
+            "let f = watch(2hz) * 2; fn g(x) { watch(x * 2) } g(1); g(3); play watch(sin(f));",
+        );
+        assert_eq!(evaluation.errors, vec![]);
+        assert_eq!(
+            evaluation
+                .watched
+                .iter()
+                .map(|(label, value)| format!("{} = {}", label, value))
+                .collect::<Vec<_>>(),
+            vec!["2hz = 2hz", "x * 2 = 6", "sin(f) = sin(4hz)"]
+        );
+
+        assert_eq!(
+            errors("watch(1, 2);"),
+            vec![("watch(1, 2)", "`watch` expects 1 argument".into())]
+        );
+    }
+
+    #[test]
+    fn test_timers() {
+        let evaluation = eval(
+            "let n = 4; every(2s, |i| watch(i * n)); let later = after(500ms, || sin(440hz)); at(2, || 1) + at(3, || 2);",
+        );
+        assert_eq!(evaluation.errors, vec![]);
+        assert_eq!(
+            evaluation
+                .timers
+                .iter()
+                .map(|timer| (timer.key.to_string(), timer.timing))
+                .collect::<Vec<_>>(),
+            vec![
+                ("program[1]".into(), Timing::Every(2.0)),
+                ("later".into(), Timing::After(0.5)),
+                ("program[3]".into(), Timing::At(2.0)),
+                ("program[3][1]".into(), Timing::At(3.0)),
+            ]
+        );
+
+        let fired = evaluation.timers[0].fire(3);
+        assert_eq!(fired.errors, vec![]);
+        assert_eq!(
+            fired
+                .watched
+                .iter()
+                .map(|(label, value)| format!("{} = {}", label, value))
+                .collect::<Vec<_>>(),
+            vec!["i * n = 12"]
+        );
+        assert_eq!(
+            evaluation.timers[1].fire(0).values,
+            vec![(
+                Key::new("later"),
+                eval("play sin(440hz);").values[0].1.clone()
+            )]
+        );
+
+        assert_eq!(
+            errors("every(0s, || 1); after(1s, 2); at(1.5, || 1); every(1s, |a, b| a);"),
+            vec![
+                ("every(0s, || 1)", "can't do something every 0s".into()),
+                (
+                    "after(1s, 2)",
+                    "`after` expects how long, and a function, like `after(2s, || ..)`".into()
+                ),
+                (
+                    "at(1.5, || 1)",
+                    "`at` expects a bar (1 is the next one), and a function, like `at(2, || ..)`"
+                        .into()
+                ),
+                (
+                    "every(1s, |a, b| a)",
+                    "the function that `every` calls takes how many times it was called before, or nothing".into()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_array_functions() {
+        assert_eq!(
+            values("let xs = [1, 2, 3].map(_ *= .2s); let s = xs.sum();"),
+            vec![
+                "xs = [0.2s, 0.4s, 0.6000000000000001s]",
+                "s = 1.2000000000000002s"
+            ]
+        );
+
+        assert_eq!(
+            values("play [(1, 1), (3, .5)].map(|p| sin(440hz * p[0]) * p[1]).sum();"),
+            vec!["program.play[0] = ((0 + (sin(440hz) * 1)) + (sin(1320hz) * 0.5))"]
+        );
+
+        assert_eq!(
+            values("let odd = [true, false, true]; let xs = zip([1, 2, 3], odd).filter(_[1]).map(_[0]);"),
+            vec!["odd = [true, false, true]", "xs = [1, 3]"]
+        );
+
+        assert_eq!(
+            errors("let a = [1, 2].map(1); let n = 5; let b = n.sum(); let c = [1, \"a\"].sum();"),
+            vec![
+                (
+                    "[1, 2].map(1)",
+                    "`map` needs a function, like `map(|x| ..)`".into()
+                ),
+                ("n.sum()", "`sum` works on an array, not a number".into()),
+                ("[1, \"a\"].sum()", "can't add a string to a number".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pattern_functions() {
+        assert_eq!(
+            values("play beat.swing(.56) + swing(beat, .56); play beat.humanize(10ms, .1);"),
+            vec![
+                "program.play[0] = (swing(beat, 0.56) + swing(beat, 0.56))",
+                "program.play[1] = humanize(beat, 0.01s, 0.1)"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_randomness() {
+        let source = "let a = rand(); let b = [rand(), rand() + rand()]; let c = [1, 2, 3].map(|_| rand()); let d = [1, 2, 3].choose(); let e = [1, 2, 3, 4].shuffle();";
+        let (once, again) = (values(source), values(source));
+        assert_eq!(once, again);
+
+        let numbers = |evaluation: &Evaluation| {
+            let mut numbers = vec![];
+            for (key, value) in &evaluation.values {
+                params(key, value, &mut numbers);
+            }
+            numbers.into_iter().map(|(_, x, _)| x).collect::<Vec<_>>()
+        };
+
+        let evaluation = eval(source);
+        let xs = numbers(&evaluation);
+        // (every call is random on its own, also the same call twice, and one in a function that's called for every element)
+        let randoms = &xs[..6];
+        assert!(randoms.iter().all(|x| (0.0..1.0).contains(x)));
+        assert!((1..6).all(|i| !randoms[..i].contains(&randoms[i])));
+        assert!([1.0, 2.0, 3.0].contains(&xs[6]));
+        let mut shuffled = xs[7..].to_vec();
+        shuffled.sort_by(f64::total_cmp);
+        assert_eq!(shuffled, vec![1.0, 2.0, 3.0, 4.0]);
+
+        // (other code changing doesn't change what's random)
+        let edited = eval(&format!("let z = rand() * 2; {}", source));
+        assert_eq!(numbers(&edited)[1..], xs[..]);
+
+        // (but reseeding does)
+        let reseeded = evaluate_source_with_seed(source, 1);
+        assert_ne!(numbers(&reseeded)[..6], xs[..6]);
+
+        assert_eq!(
+            errors("let a = rand(1); let b = [].choose(); let n = 5; let c = n.shuffle();"),
+            vec![
+                ("rand(1)", "`rand` expects no arguments".into()),
+                ("[].choose()", "can't choose from an empty array".into()),
+                (
+                    "n.shuffle()",
+                    "`shuffle` works on an array, not a number".into()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sometimes() {
+        let evaluation = eval(
+            "play beat.sometimes(.3, |p| p.swing(.6)); play sometimes(beat, .3, |p| p.swing(.6));",
+        );
+        assert_eq!(evaluation.errors, vec![]);
+        let played = evaluation
+            .values
+            .iter()
+            .map(|(_, value)| value.to_string())
+            .collect::<Vec<_>>();
+        assert!(played[0].starts_with("sometimes(beat, 0.3, swing(beat, 0.6), "));
+        // (the same call, elsewhere in the code, has a seed of its own)
+        assert_ne!(played[0], played[1]);
+
+        assert_eq!(
+            errors("play beat.sometimes(2, |p| p);"),
+            vec![(
+                "beat.sometimes(2, |p| p)",
+                "`sometimes` expects a pattern, how often (from 0 to 1), and a function, like `beat.sometimes(.3, |p| ..)`".into()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_music() {
+        assert_eq!(
+            values("let a = a4 / 2; let b = scale(\"minor\", a3).degree(8); let c = degree(chord(\"major\", a4), -2); let d = mtof(69); let e = ftom(a5); let f = mtof(midi.note);"),
+            vec![
+                "a = 220hz",
+                "b = 440hz",
+                "c = 220hz",
+                "d = 440hz",
+                "e = 81",
+                "f = mtof(midi.note)"
+            ]
+        );
+
+        let evaluation = eval("let notes = scale(\"pentatonic\", c4);");
+        let Value::Array(notes) = &evaluation.values[0].1 else {
+            panic!();
+        };
+        // (keyed like any array, note by note)
+        assert_eq!(notes[1].0, Key::new("notes").index(1));
+        assert!(notes
+            .iter()
+            .zip([261.63, 293.66, 329.63, 392.0, 440.0])
+            .all(|((_, note), freq)| matches!(note, Value::Num(note) if (note.value - freq).abs() < 0.01)));
+
+        assert_eq!(
+            errors("scale(\"jazzy\", c4); chord(\"major\", 60); [].degree(1); mtof(440hz);"),
+            vec![
+                (
+                    "scale(\"jazzy\", c4)",
+                    "there's no scale called \"jazzy\" (there's \"major\", \"minor\", \"harmonic minor\", \"melodic minor\", \"dorian\", \"phrygian\", \"lydian\", \"mixolydian\", \"locrian\", \"pentatonic\", \"minor pentatonic\", \"blues\", \"whole tone\", \"chromatic\")".into()
+                ),
+                (
+                    "chord(\"major\", 60)",
+                    "`chord` expects a name and a root note, like `chord(\"minor\", a3)`".into()
+                ),
+                ("[].degree(1)", "`degree` needs a scale with notes in it".into()),
+                (
+                    "mtof(440hz)",
+                    "`mtof` expects a MIDI note number, like `mtof(60)`".into()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tuning() {
+        let root =
+            std::env::temp_dir().join(format!("live_language_tuning_{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(
+            root.join("just.scl"),
+            "! just.scl\nJust intonation\n12\n16/15\n9/8\n6/5\n5/4\n4/3\n45/32\n3/2\n8/5\n5/3\n9/5\n15/8\n2/1\n",
+        )
+        .unwrap();
+        // (c4 at 256hz, without a#)
+        fs::write(
+            root.join("c.kbm"),
+            "12\n0\n127\n60\n60\n256\n12\n0\n1\n2\n3\n4\n5\n6\n7\n8\n9\nx\n11\n",
+        )
+        .unwrap();
+
+        let source = "let a = a4; tuning(\"just.scl\", \"c.kbm\"); let b = g4; let c = scale(\"major\", c4).degree(10); let d = c4+1200ct;";
+        let evaluation = evaluate_source_in(source, 0, &root);
+        assert_eq!(evaluation.errors, vec![]);
+        let mut numbers = vec![];
+        for (key, value) in &evaluation.values {
+            params(key, value, &mut numbers);
+        }
+        // (the notes before it aren't tuned)
+        let expected = [440.0, 384.0, 640.0, 512.0];
+        assert_eq!(numbers.len(), expected.len());
+        assert!(numbers
+            .iter()
+            .zip(expected)
+            .all(|((_, x, _), expected)| (x - expected).abs() < 1e-9));
+        assert_eq!(evaluation.tuning.freq(60), Some(256.0));
+
+        let errors = |source: &str| {
+            evaluate_source_in(source, 0, &root)
+                .errors
+                .into_iter()
+                .map(|(span, message)| (source[span.range()].to_string(), message))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            errors("tuning(\"just.scl\", \"c.kbm\"); let x = a#4; tuning(4);"),
+            vec![
+                ("a#4".into(), "the tuning has no `a#4`".into()),
+                (
+                    "tuning(4)".into(),
+                    "`tuning` expects a Scala file, and maybe a keyboard mapping, like `tuning(\"just.scl\", \"c.kbm\")`".into()
+                ),
+            ]
+        );
+        assert!(errors("tuning(\"missing.scl\");")[0]
+            .1
+            .starts_with("can't read missing.scl: "));
+    }
+
+    #[test]
+    fn test_buses() {
+        assert_eq!(
+            values(
+                "play send(kick, \"drums\", 0db); play compressor{ratio = 8}(pad, bus(\"drums\"));"
+            ),
+            vec![
+                "program.play[0] = send(kick, \"drums\", 0db)",
+                "program.play[1] = compressor(ratio = 8, pad, bus(\"drums\"))"
+            ]
+        );
+
+        assert_eq!(
+            errors("play bus(\"verb\"); play send(bus(\"a\"), \"b\") + send(bus(\"b\"), \"a\"); play bus(4);"),
+            vec![
+                ("bus(\"verb\")", "nothing that's played is sent to the \"verb\" bus".into()),
+                (
+                    "send(bus(\"a\"), \"b\") + send(bus(\"b\"), \"a\")",
+                    "the \"a\" bus feeds back into itself".into()
+                ),
+                (
+                    "send(bus(\"a\"), \"b\") + send(bus(\"b\"), \"a\")",
+                    "the \"b\" bus feeds back into itself".into()
+                ),
+                ("bus(4)", "`bus` needs the name of a bus, like `bus(\"drums\")`".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_placement() {
+        assert_eq!(
+            values("play pan(pad, -.5); play pan(pad, [1, 0, .5, .5]); play channel(click, 3);"),
+            vec![
+                "program.play[0] = pan(pad, -0.5)",
+                "program.play[1] = pan(pad, [1, 0, 0.5, 0.5])",
+                "program.play[2] = channel(click, 3)"
+            ]
+        );
+
+        assert_eq!(
+            errors("play pan(pad, 2); play channel(click, 0); play pan(pad, 1) + kick;"),
+            vec![
+                (
+                    "pan(pad, 2)",
+                    "`pan` needs a position from -1 (the first channel) to 1 (the last), or a gain per channel, like `pan(pad, [1, 0, .5, .5])`".into()
+                ),
+                (
+                    "channel(click, 0)",
+                    "`channel` needs a channel, counting from 1, like `channel(click, 3)`".into()
+                ),
+                (
+                    "pan(pad, 1) + kick",
+                    "`pan` places what's played as a whole, like `play pan(pad, -.5)`".into()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plugin() {
+        assert_eq!(
+            values("play plugin{room_size = .8}(\"TAL Reverb\", pad);"),
+            vec!["program.play[0] = plugin(room_size = 0.8, \"TAL Reverb\", pad)"]
+        );
+    }
+
+    #[test]
+    fn test_slices() {
+        assert_eq!(
+            values("let drums = slices(sample#3); play drums[3];"),
+            vec![
+                "drums = slices(sample#3)",
+                "program.play[0] = slices(sample#3)[3]"
+            ]
+        );
+
+        assert_eq!(
+            errors("let drums = slices(sample#3); play drums[1.5];"),
+            vec![("drums[1.5]", "can't index slices with 1.5".into())]
+        );
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = eval("let xs = [1, 2, 3]; let ys = xs.filter(|x| true); play ys.sum();").values;
+        let new = eval("let xs = [1, 5, 3]; let ys = xs.filter(|x| false); play 6;").values;
+
+        assert_eq!(
+            diff(&old, &new),
+            vec![
+                Change::Changed(Key::new("xs[1]")),
+                Change::Removed(Key::new("ys:xs[0]")),
+                Change::Removed(Key::new("ys:xs[1]")),
+                Change::Removed(Key::new("ys:xs[2]")),
+            ]
+        );
+
+        // (filtering keeps the identities of what's left)
+        let old = eval("let xs = [1, 2, 3].map(_ * 2);").values;
+        let new = eval("let xs = [1, 2, 3].map(_ * 2).filter(|x| false);").values;
+        assert_eq!(diff(&old, &new).len(), 3);
+    }
+
+    #[test]
+    fn test_performables() {
+        let evaluation = eval(
+            "def fx = lowpass{f = perform(800hz), q = perform(ease(2, 1s))}(pad); let g = [perform(.5), 1]; let h = 2;",
+        );
+        assert_eq!(evaluation.errors, vec![]);
+        assert_eq!(
+            performables(&evaluation.values),
+            vec![
+                (Key::new("fx.f"), 800.0),
+                (Key::new("fx.q"), 2.0),
+                (Key::new("g[0]"), 0.5),
+            ]
+        );
+
+        // (they're still latched, like any number)
+        let new = eval("def fx = lowpass{f = perform(1200hz), q = perform(ease(2, 1s))}(pad);");
+        assert_eq!(
+            latches(&evaluation.values, &new.values),
+            vec![Latch {
+                key: Key::new("fx.f"),
+                from: 800.0,
+                to: 1200.0,
+                ease: None
+            }]
+        );
+
+        assert_eq!(
+            errors("let f = perform(pad);"),
+            vec![(
+                "perform(pad)",
+                "`perform` expects a number, like `perform(800hz)`".into()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_latches() {
+        let old = eval("def fx = lowpass{f = 800hz, q = ease(2, 1s)}(pad); let g = .5;").values;
+        let new = eval("def fx = lowpass{f = 1200hz, q = ease(4, 1s)}(pad); let g = .5;").values;
+
+        assert_eq!(
+            latches(&old, &new),
+            vec![
+                Latch {
+                    key: Key::new("fx.f"),
+                    from: 800.0,
+                    to: 1200.0,
+                    ease: None
+                },
+                Latch {
+                    key: Key::new("fx.q"),
+                    from: 2.0,
+                    to: 4.0,
+                    ease: Some(1.0)
+                },
+            ]
+        );
+
+        assert_eq!(
+            errors("let f = ease(800hz); let g = ease(pad, 1s);"),
+            vec![
+                (
+                    "ease(800hz)",
+                    "`ease` expects a number and how long it glides, like `ease(800hz, 200ms)`"
+                        .into()
+                ),
+                (
+                    "ease(pad, 1s)",
+                    "`ease` expects a number and how long it glides, like `ease(800hz, 200ms)`"
+                        .into()
+                ),
+            ]
+        );
+    }
+}
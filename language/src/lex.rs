@@ -0,0 +1,290 @@
+use std::ops::Range;
+
+use crate::music::note_number;
+use crate::parse_v2::{is_statement_line, KEYWORDS, LITERALS};
+
+/// (the units an amount can have, like the `hz` in `440hz`)
+const UNITS: &[&str] = &["min", "ms", "s", "khz", "hz", "db"];
+
+/// Longest first, so that `==` isn't lexed as two `=`s
+const OPERATORS: &[&str] = &[
+    "==", "!=", "<=", ">=", "&&", "||", "<", ">", "+", "-", "*", "/", "!",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Ws,
+    /// `// ..`, up to the end of the line (which the parser treats as whitespace)
+    Comment,
+    Num,
+    /// Right after a number (maybe with some whitespace in between), like in `440 hz`
+    Unit,
+    /// The whole string, including its quotes, escapes and `${..}`s
+    Str,
+    Color,
+    /// A note name, like `c3` or `a#4`
+    Note,
+    /// `true`, `false`, `pi` and `tau`
+    Literal,
+    Keyword,
+    Ident,
+    /// Like `sample#3`
+    WidgetRef,
+    Op,
+    ParenLeft,
+    ParenRight,
+    BracketLeft,
+    BracketRight,
+    CurlyLeft,
+    CurlyRight,
+    Dot,
+    Comma,
+    Semi,
+    Eq,
+    Pipe,
+    Percent,
+    /// A character that doesn't start any token
+    Unknown,
+}
+
+fn is_word_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
+/**
+    Splits code up into its tokens, without parsing it, so that things like highlighting (or an editor other than ours) don't need the whole syntax tree. Every byte of the code is in exactly one token, so whitespace and comments are tokens too, and whatever doesn't make sense is `Unknown`, one character at a time.
+*/
+pub fn lex(source: &str) -> Vec<(TokenKind, Range<usize>)> {
+    let mut tokens = vec![];
+    let mut i = 0;
+    // (whether a unit is expected, since the last token was a number)
+    let mut after_num = false;
+
+    while i < source.len() {
+        let rest = &source[i..];
+        let ch = rest.chars().next().unwrap();
+
+        let (kind, len) = if rest.starts_with("//") {
+            (TokenKind::Comment, rest.find('\n').unwrap_or(rest.len()))
+        } else if ch.is_whitespace() {
+            (TokenKind::Ws, rest.len() - rest.trim_start().len())
+        } else if ch.is_ascii_digit() || (ch == '.' && starts_with_digit(&rest[1..])) {
+            (TokenKind::Num, num_len(rest))
+        } else if ch == '"' {
+            (TokenKind::Str, str_len(rest))
+        } else if ch == '#' {
+            match color_len(rest) {
+                Some(len) => (TokenKind::Color, len),
+                None => (TokenKind::Unknown, 1),
+            }
+        } else if let Some(len) = note_len(rest) {
+            (TokenKind::Note, len)
+        } else if is_word_char(ch) {
+            word(rest, after_num)
+        } else if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(**op)) {
+            (TokenKind::Op, op.len())
+        } else {
+            let kind = match ch {
+                '(' => TokenKind::ParenLeft,
+                ')' => TokenKind::ParenRight,
+                '[' => TokenKind::BracketLeft,
+                ']' => TokenKind::BracketRight,
+                '{' => TokenKind::CurlyLeft,
+                '}' => TokenKind::CurlyRight,
+                '.' => TokenKind::Dot,
+                ',' => TokenKind::Comma,
+                ';' => TokenKind::Semi,
+                '=' => TokenKind::Eq,
+                '|' => TokenKind::Pipe,
+                '%' => TokenKind::Percent,
+                _ => TokenKind::Unknown,
+            };
+
+            (kind, ch.len_utf8())
+        };
+
+        if kind != TokenKind::Ws {
+            after_num = kind == TokenKind::Num;
+        }
+
+        tokens.push((kind, i..i + len));
+        i += len;
+    }
+
+    tokens
+}
+
+fn starts_with_digit(text: &str) -> bool {
+    text.starts_with(|ch: char| ch.is_ascii_digit())
+}
+
+/// Digits (and `_`s to group them), with at most one `.`
+fn num_len(text: &str) -> usize {
+    let digits = |from: usize| {
+        text[from..]
+            .find(|ch: char| !ch.is_ascii_digit() && ch != '_')
+            .map_or(text.len(), |len| from + len)
+    };
+
+    let end = digits(0);
+    if text[end..].starts_with('.') {
+        digits(end + 1)
+    } else {
+        end
+    }
+}
+
+/// Up to the closing quote, unless there isn't one, or it's past a line that looks like a new statement (then it's just the rest of the line, like the parser does it)
+fn str_len(text: &str) -> usize {
+    match closed_str_len(text) {
+        Some(len) if !text[..len].split('\n').skip(1).any(is_statement_line) => len,
+        _ => text.find('\n').unwrap_or(text.len()),
+    }
+}
+
+/// Up to the closing quote, skipping over escapes, and the `}`s and quotes in interpolations
+fn closed_str_len(text: &str) -> Option<usize> {
+    let mut chars = text.char_indices().skip(1).peekable();
+    // (how deep into `${..}`s, and braces in there)
+    let mut depth = 0;
+
+    while let Some((i, ch)) = chars.next() {
+        match ch {
+            '\\' if depth == 0 => {
+                chars.next();
+            }
+            '$' if depth == 0 && chars.peek().map(|&(_, ch)| ch) == Some('{') => {
+                chars.next();
+                depth += 1;
+            }
+            '{' if depth > 0 => depth += 1,
+            '}' if depth > 0 => depth -= 1,
+            '"' if depth > 0 => {
+                let len = str_len(&text[i..]);
+                while chars.next_if(|&(j, _)| j < i + len).is_some() {}
+            }
+            '"' => return Some(i + 1),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// 3, 6 or 8 hex digits after the `#`, and then not some other letter or digit
+fn color_len(text: &str) -> Option<usize> {
+    let digits = text[1..]
+        .find(|ch: char| !is_word_char(ch))
+        .unwrap_or(text.len() - 1);
+
+    let valid =
+        matches!(digits, 3 | 6 | 8) && text[1..1 + digits].chars().all(|ch| ch.is_ascii_hexdigit());
+
+    valid.then_some(1 + digits)
+}
+
+/// A letter from `a` to `g`, maybe `#` or `b`, and a digit, and then not some other letter or digit (so `a#4` isn't a widget, and `b3b` is just a name), and maybe how many cents it's off, like `c3+14ct`
+fn note_len(text: &str) -> Option<usize> {
+    let len = text
+        .char_indices()
+        .skip(1)
+        .find(|&(_, ch)| !is_word_char(ch) && ch != '#')
+        .map_or(text.len(), |(i, _)| i);
+    note_number(&text[..len])?;
+
+    // (without the `ct`, it's just adding)
+    let cents = text[len..]
+        .strip_prefix(['+', '-'])
+        .filter(|rest| starts_with_digit(rest))
+        .map(|rest| 1 + num_len(rest))
+        .filter(|&cents| {
+            let rest = &text[len + cents..];
+            rest.starts_with("ct") && !rest[2..].starts_with(is_word_char)
+        })
+        .map_or(0, |cents| cents + 2);
+
+    Some(len + cents)
+}
+
+fn word(text: &str, after_num: bool) -> (TokenKind, usize) {
+    let len = text
+        .find(|ch: char| !is_word_char(ch))
+        .unwrap_or(text.len());
+    let word = &text[..len];
+
+    let widget_digits = text[len..]
+        .strip_prefix('#')
+        .map(|rest| {
+            rest.find(|ch: char| !ch.is_ascii_digit())
+                .unwrap_or(rest.len())
+        })
+        .filter(|&digits| digits > 0);
+
+    if let Some(digits) = widget_digits {
+        (TokenKind::WidgetRef, len + 1 + digits)
+    } else if after_num && UNITS.contains(&word) {
+        (TokenKind::Unit, len)
+    } else if KEYWORDS.contains(&word) {
+        (TokenKind::Keyword, len)
+    } else if LITERALS.contains(&word) {
+        (TokenKind::Literal, len)
+    } else {
+        (TokenKind::Ident, len)
+    }
+}
+
+#[test]
+fn test_lex() {
+    let source =
+        "let f = 1_000.5hz; // cutoff\ndef x = kick#3 * .5 s |> \"a\\\"${ \"b\" }\" == #f80 + a#4 - c3+14ct;";
+
+    assert_eq!(
+        lex(source)
+            .into_iter()
+            .filter(|(kind, _)| *kind != TokenKind::Ws)
+            .map(|(kind, range)| (kind, &source[range]))
+            .collect::<Vec<_>>(),
+        vec![
+            (TokenKind::Keyword, "let"),
+            (TokenKind::Ident, "f"),
+            (TokenKind::Eq, "="),
+            (TokenKind::Num, "1_000.5"),
+            (TokenKind::Unit, "hz"),
+            (TokenKind::Semi, ";"),
+            (TokenKind::Comment, "// cutoff"),
+            (TokenKind::Keyword, "def"),
+            (TokenKind::Ident, "x"),
+            (TokenKind::Eq, "="),
+            (TokenKind::WidgetRef, "kick#3"),
+            (TokenKind::Op, "*"),
+            (TokenKind::Num, ".5"),
+            (TokenKind::Unit, "s"),
+            (TokenKind::Pipe, "|"),
+            (TokenKind::Op, ">"),
+            (TokenKind::Str, "\"a\\\"${ \"b\" }\""),
+            (TokenKind::Op, "=="),
+            (TokenKind::Color, "#f80"),
+            (TokenKind::Op, "+"),
+            (TokenKind::Note, "a#4"),
+            (TokenKind::Op, "-"),
+            (TokenKind::Note, "c3+14ct"),
+            (TokenKind::Semi, ";"),
+        ]
+    );
+
+    // every byte is in a token
+    let tokens = lex(source);
+    assert!(tokens.windows(2).all(|w| w[0].1.end == w[1].1.start));
+    assert_eq!(tokens.last().unwrap().1.end, source.len());
+
+    // an unclosed string ends with its line
+    let source = "play \"a;\nlet b = \"b\\n\nc\";";
+    assert_eq!(
+        lex(source)
+            .into_iter()
+            .filter(|(kind, _)| *kind == TokenKind::Str)
+            .map(|(_, range)| &source[range])
+            .collect::<Vec<_>>(),
+        vec!["\"a;", "\"b\\n\nc\""]
+    );
+}
@@ -1,10 +1,48 @@
-#![feature(assert_matches)]
-#![feature(let_chains)]
 #![feature(box_patterns)]
 
 pub mod ast;
+mod builtins;
 mod check;
+mod color;
+mod eval;
+mod lex;
+mod music;
 mod parse;
 mod parse_v2;
+mod paths;
+mod random;
+mod span;
+mod tuning;
+pub mod visit;
 
+pub use builtins::{builtin, function, Builtin, Function, Param, Setting, BUILTINS, FUNCTIONS};
+pub use check::{
+    check_settings, check_units, modulation, resolve_names, Dimension, Modulation, Occurrence,
+    Quantity,
+};
+pub use color::{format_color, parse_color};
+pub use eval::{
+    diff, evaluate, evaluate_in, evaluate_source, evaluate_source_in, evaluate_source_with_seed,
+    evaluate_with_seed, latches, performables, Change, Evaluation, Key, Latch, Timer, Timing,
+    Value,
+};
+pub use lex::{lex, TokenKind};
+pub use music::{freq_to_midi, midi_to_freq, note_number};
 pub use parse::parse_document;
+pub use parse_v2::format::format_document;
+pub use parse_v2::syntax_errors;
+pub use parse_v2::incremental::{IncrementalParse, ParsedDocument, TextEdit};
+pub use parse_v2::lint::{lint, lint_parsed, Lint, LintConfig, LintKind, Severity};
+pub use parse_v2::docs::{completions, documentation, Documentation};
+pub use parse_v2::outline::{
+    clips, color_literals, outline, overlay_statements, play_targets, signal_views, statement_at,
+    statements, Clip, ColorLiteral, PlayTarget, SignalView, SignalViewKind, Statement, Symbol,
+    SymbolKind,
+};
+pub use parse_v2::refactor::{
+    definition_at, extract_definition, rename_symbol, rename_symbols, Extraction,
+};
+pub use paths::{expand_glob, resolve_path};
+pub use random::seed;
+pub use span::{Loc, SourceSpan};
+pub use tuning::Tuning;
@@ -0,0 +1,159 @@
+use crate::tuning::Tuning;
+
+/**
+    The MIDI note number of a note name, like `c4` (60, middle C) or `a#4`, with `#` for sharp and `b` for flat, and octaves like in scientific pitch notation (so `a4` is 69, at 440Hz), and then a fraction of a semitone for how many cents it's off, like for `c3+14ct`
+*/
+pub fn note_number(name: &str) -> Option<f64> {
+    let (note, cents) = parse_note(name)?;
+    Some(note as f64 + cents / 100.0)
+}
+
+/// (the note, and the cents, apart, since a tuning only tunes the notes themselves)
+pub(crate) fn parse_note(name: &str) -> Option<(i64, f64)> {
+    let (name, cents) = match name.find(['+', '-']) {
+        Some(i) => {
+            let cents = name[i..].strip_suffix("ct")?.replace('_', "");
+            (
+                &name[..i],
+                cents.trim_start_matches('+').parse::<f64>().ok()?,
+            )
+        }
+        None => (name, 0.0),
+    };
+
+    let mut chars = name.chars();
+
+    let letter = match chars.next()? {
+        'c' => 0,
+        'd' => 2,
+        'e' => 4,
+        'f' => 5,
+        'g' => 7,
+        'a' => 9,
+        'b' => 11,
+        _ => return None,
+    };
+
+    let rest = chars.as_str();
+    let (accidental, octave) = match rest.strip_prefix('#') {
+        Some(octave) => (1, octave),
+        None => match rest.strip_prefix('b') {
+            Some(octave) => (-1, octave),
+            None => (0, rest),
+        },
+    };
+
+    if octave.len() != 1 {
+        return None;
+    }
+    let octave = octave.parse::<i64>().ok()?;
+
+    Some(((octave + 1) * 12 + letter + accidental, cents))
+}
+
+/// (equal temperament, tuned to `a4` at 440Hz, like the engine's `note_freq`)
+pub fn midi_to_freq(note: f64) -> f64 {
+    440.0 * 2f64.powf((note - 69.0) / 12.0)
+}
+
+pub fn freq_to_midi(freq: f64) -> f64 {
+    69.0 + 12.0 * (freq / 440.0).log2()
+}
+
+/// Scales, as semitones up from their root (within one octave)
+pub(crate) const SCALES: &[(&str, &[i64])] = &[
+    ("major", &[0, 2, 4, 5, 7, 9, 11]),
+    ("minor", &[0, 2, 3, 5, 7, 8, 10]),
+    ("harmonic minor", &[0, 2, 3, 5, 7, 8, 11]),
+    ("melodic minor", &[0, 2, 3, 5, 7, 9, 11]),
+    ("dorian", &[0, 2, 3, 5, 7, 9, 10]),
+    ("phrygian", &[0, 1, 3, 5, 7, 8, 10]),
+    ("lydian", &[0, 2, 4, 6, 7, 9, 11]),
+    ("mixolydian", &[0, 2, 4, 5, 7, 9, 10]),
+    ("locrian", &[0, 1, 3, 5, 6, 8, 10]),
+    ("pentatonic", &[0, 2, 4, 7, 9]),
+    ("minor pentatonic", &[0, 3, 5, 7, 10]),
+    ("blues", &[0, 3, 5, 6, 7, 10]),
+    ("whole tone", &[0, 2, 4, 6, 8, 10]),
+    ("chromatic", &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11]),
+];
+
+/// Chords, as semitones up from their root
+pub(crate) const CHORDS: &[(&str, &[i64])] = &[
+    ("major", &[0, 4, 7]),
+    ("minor", &[0, 3, 7]),
+    ("dim", &[0, 3, 6]),
+    ("aug", &[0, 4, 8]),
+    ("sus2", &[0, 2, 7]),
+    ("sus4", &[0, 5, 7]),
+    ("7", &[0, 4, 7, 10]),
+    ("maj7", &[0, 4, 7, 11]),
+    ("min7", &[0, 3, 7, 10]),
+    ("dim7", &[0, 3, 6, 9]),
+    ("9", &[0, 4, 7, 10, 14]),
+];
+
+/**
+    The frequencies of a scale's or chord's notes, from a root frequency (in the steps of the tuning), or what's there (for an error message) when there's none with that name
+*/
+pub(crate) fn intervals(
+    table: &[(&str, &[i64])],
+    name: &str,
+    root: f64,
+    tuning: &Tuning,
+) -> Result<Vec<f64>, String> {
+    match table.iter().find(|(known, _)| *known == name) {
+        Some((_, semitones)) => Ok(semitones
+            .iter()
+            .map(|&semitones| root * tuning.interval(semitones))
+            .collect()),
+        None => Err(table
+            .iter()
+            .map(|(known, _)| format!("\"{}\"", known))
+            .collect::<Vec<_>>()
+            .join(", ")),
+    }
+}
+
+/**
+    The nth note of a scale (counting from 1, for its root), continuing into the octaves above and below (or whatever the tuning repeats at), so that `7` in a pentatonic scale is the third one, an octave up, and `0` is the last one, an octave down
+*/
+pub(crate) fn degree(notes: &[f64], n: i64, tuning: &Tuning) -> f64 {
+    let len = notes.len() as i64;
+    let octave = (n - 1).div_euclid(len);
+    notes[(n - 1).rem_euclid(len) as usize] * tuning.period().powi(octave as i32)
+}
+
+#[test]
+fn test_music() {
+    assert_eq!(note_number("c4"), Some(60.0));
+    assert_eq!(note_number("a4"), Some(69.0));
+    assert_eq!(note_number("a#4"), Some(70.0));
+    assert_eq!(note_number("bb3"), Some(58.0));
+    assert_eq!(note_number("c0"), Some(12.0));
+    assert_eq!(note_number("h4"), None);
+    assert_eq!(note_number("c10"), None);
+    assert_eq!(note_number("cx4"), None);
+    assert!((note_number("c3+14ct").unwrap() - 48.14).abs() < 1e-9);
+    assert_eq!(note_number("a#4-50ct"), Some(69.5));
+    assert_eq!(note_number("c3+14"), None);
+
+    assert_eq!(midi_to_freq(69.0), 440.0);
+    assert!((midi_to_freq(60.0) - 261.63).abs() < 0.01);
+    assert!((freq_to_midi(midi_to_freq(61.5)) - 61.5).abs() < 1e-9);
+
+    let equal = Tuning::default();
+    let major = intervals(SCALES, "major", 100.0, &equal).unwrap();
+    assert_eq!(major.len(), 7);
+    assert!((major[4] - 100.0 * 2f64.powf(7.0 / 12.0)).abs() < 1e-9);
+    assert!(intervals(CHORDS, "mega", 100.0, &equal)
+        .unwrap_err()
+        .starts_with("\"major\", \"minor\""));
+
+    let pentatonic = intervals(SCALES, "pentatonic", 100.0, &equal).unwrap();
+    assert_eq!(degree(&pentatonic, 1, &equal), 100.0);
+    assert_eq!(degree(&pentatonic, 6, &equal), 200.0);
+    assert_eq!(degree(&pentatonic, 7, &equal), pentatonic[1] * 2.0);
+    assert_eq!(degree(&pentatonic, 0, &equal), pentatonic[4] / 2.0);
+    assert_eq!(degree(&pentatonic, -4, &equal), 50.0);
+}
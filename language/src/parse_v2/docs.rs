@@ -0,0 +1,273 @@
+use crate::{
+    builtins::{builtin, function, Function, BUILTINS, FUNCTIONS},
+    check::{resolve_names, Dimension},
+    span::SourceSpan,
+};
+
+use super::{lower::lower_document, parse_syntax_tree, Kind};
+
+/// What hovering a name shows: how it's used, what it does, and what goes into it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Documentation {
+    /// Like `fn kick(t)`, or `lowpass{f = 1000hz, q = 0.707}`
+    pub signature: String,
+    pub doc: String,
+    /// The parameters (or settings) that there's something to say about, with what that is
+    pub params: Vec<(String, String)>,
+}
+
+/**
+    The documentation of the name at the offset: the `///` comments of the definition it refers to, or, when it's not defined in the document, of the built-in with that name (which is also how methods, like the `swing` in `beat.swing(.6)`, are documented).
+*/
+pub fn documentation(source: &str, offset: usize) -> Option<Documentation> {
+    let (tree, _) = parse_syntax_tree(source);
+    let occurrences = resolve_names(&lower_document(&tree));
+
+    let at = |span: SourceSpan| span.start.offset <= offset && offset <= span.end.offset;
+
+    let Some(occurrence) = occurrences.iter().find(|o| at(o.span)) else {
+        let mut name = None;
+        tree.walk_postorder(&mut |node| {
+            if node.kind == Kind::Ident && at(node.range) {
+                name = Some(node.text());
+            }
+        });
+        return function(name?).map(function_documentation);
+    };
+
+    let Some(declaration) = occurrence.declaration else {
+        return builtin_documentation(&occurrence.name);
+    };
+    let declaration = occurrences[declaration].span;
+
+    // (parameters don't have doc comments)
+    let mut definition = None;
+    tree.walk_postorder(&mut |node| {
+        if matches!(node.kind, Kind::LetStmt | Kind::FnDecl)
+            && node
+                .name_after_keyword()
+                .is_some_and(|name| name.range == declaration)
+        {
+            definition = Some(node.clone());
+        }
+    });
+    let definition = definition?;
+
+    let doc = definition
+        .children_of_kind(Kind::DocComment)
+        .map(|line| {
+            let line = line.text().trim_start_matches("///");
+            line.strip_prefix(' ').unwrap_or(line).trim_end()
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    if doc.is_empty() {
+        return None;
+    }
+
+    let keyword = definition.child(Kind::Keyword)?.text();
+    let signature = match definition.kind {
+        Kind::FnDecl => {
+            // (each on one line, however they're spread out in the code)
+            let params = definition
+                .children_of_kind(Kind::Param)
+                .map(|param| {
+                    let param = source[param.range.range()].split_whitespace();
+                    param.collect::<Vec<_>>().join(" ")
+                })
+                .collect::<Vec<_>>();
+            format!("fn {}({})", occurrence.name, params.join(", "))
+        }
+        _ => format!("{} {}", keyword, occurrence.name),
+    };
+
+    Some(Documentation {
+        signature,
+        doc,
+        params: vec![],
+    })
+}
+
+/**
+    What a name that's being typed could become: what the document defines, and the built-ins, that start with it, in alphabetical order, each with its documentation (or just its name, when there's nothing to say). What the document defines comes first when it shadows a built-in.
+*/
+pub fn completions(source: &str, prefix: &str) -> Vec<(String, Documentation)> {
+    let (tree, _) = parse_syntax_tree(source);
+    let occurrences = resolve_names(&lower_document(&tree));
+
+    let mut completions: Vec<(String, Documentation)> = vec![];
+
+    for (i, occurrence) in occurrences.iter().enumerate() {
+        if occurrence.declaration != Some(i)
+            || !occurrence.name.starts_with(prefix)
+            || completions.iter().any(|(name, _)| *name == occurrence.name)
+        {
+            continue;
+        }
+
+        let docs =
+            documentation(source, occurrence.span.start.offset).unwrap_or_else(|| Documentation {
+                signature: occurrence.name.clone(),
+                doc: String::new(),
+                params: vec![],
+            });
+        completions.push((occurrence.name.clone(), docs));
+    }
+
+    let builtins = BUILTINS.iter().map(|builtin| builtin.name);
+    for name in builtins.chain(FUNCTIONS.iter().map(|function| function.name)) {
+        if !name.starts_with(prefix) || completions.iter().any(|(defined, _)| defined == name) {
+            continue;
+        }
+        if let Some(docs) = builtin_documentation(name) {
+            completions.push((name.to_string(), docs));
+        }
+    }
+
+    completions.sort_by(|(a, _), (b, _)| a.cmp(b));
+    completions
+}
+
+fn builtin_documentation(name: &str) -> Option<Documentation> {
+    let Some(builtin) = builtin(name) else {
+        return function(name).map(function_documentation);
+    };
+
+    let amount = |value: f64, dimension: Dimension| match dimension {
+        Dimension::Ratio => format!("{}", value),
+        Dimension::Time => format!("{}s", value),
+        Dimension::Frequency => format!("{}hz", value),
+        Dimension::Level => format!("{}db", value),
+    };
+
+    Some(Documentation {
+        signature: format!(
+            "{}{{{}}}",
+            builtin.name,
+            builtin
+                .settings
+                .iter()
+                .map(|setting| format!(
+                    "{} = {}",
+                    setting.name,
+                    amount(setting.default, setting.dimension)
+                ))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        doc: builtin.doc.to_string(),
+        params: builtin
+            .settings
+            .iter()
+            .map(|setting| {
+                (
+                    setting.name.to_string(),
+                    format!("{} ({})", setting.doc, setting.dimension),
+                )
+            })
+            .collect(),
+    })
+}
+
+fn function_documentation(function: &Function) -> Documentation {
+    Documentation {
+        signature: format!(
+            "{}({})",
+            function.name,
+            function
+                .params
+                .iter()
+                .map(|param| param.name)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        doc: function.doc.to_string(),
+        params: function
+            .params
+            .iter()
+            .filter_map(|param| {
+                let doc = match (param.doc, param.dimension) {
+                    ("", None) => return None,
+                    (doc, None) => doc.to_string(),
+                    ("", Some(dimension)) => dimension.to_string(),
+                    (doc, Some(dimension)) => format!("{} ({})", doc, dimension),
+                };
+                Some((param.name.to_string(), doc))
+            })
+            .collect(),
+    }
+}
+
+#[test]
+fn test_documentation() {
+    let source = "/// The kick,\n/// from the pack\ndef kick = path(\"kick.wav\");\n\n/// (the beat)\nfn beat(t,\n  v) { kick }\nlet x = 2;\nplay lowpass{f = 200hz}(beat).swing(.6);";
+    let at = |name: &str| documentation(source, source.rfind(name).unwrap() + 1);
+
+    assert_eq!(
+        at("kick }"),
+        Some(Documentation {
+            signature: "def kick".into(),
+            doc: "The kick,\nfrom the pack".into(),
+            params: vec![],
+        })
+    );
+    assert_eq!(
+        at("beat)"),
+        Some(Documentation {
+            signature: "fn beat(t, v)".into(),
+            doc: "(the beat)".into(),
+            params: vec![],
+        })
+    );
+    // (no doc comments, nothing to say)
+    assert_eq!(at("x ="), None);
+    assert_eq!(at("t,"), None);
+
+    let lowpass = at("lowpass").unwrap();
+    assert_eq!(lowpass.signature, "lowpass{f = 1000hz, q = 0.707}");
+    assert_eq!(
+        lowpass.params[0],
+        ("f".into(), "the cutoff frequency (a frequency)".into())
+    );
+
+    let path = at("path").unwrap();
+    assert_eq!(path.signature, "path(path)");
+    assert_eq!(
+        path.params,
+        vec![("path".into(), "relative to the project root".into())]
+    );
+
+    // (a method is documented like the function it is)
+    let swing = at("swing").unwrap();
+    assert_eq!(swing.signature, "swing(pattern, amount)");
+    assert_eq!(
+        swing.params,
+        vec![(
+            "amount".into(),
+            "how far into each pair of 16ths the second one lands (a number)".into()
+        )]
+    );
+}
+
+#[test]
+fn test_completions() {
+    let source = "/// The kick\ndef kick = path(\"kick.wav\");\nlet sine = 2;\nfn sidechain(x) { x }\nplay si";
+    let names = |prefix: &str| {
+        completions(source, prefix)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(names("si"), vec!["sidechain", "sin", "sine"]);
+    assert_eq!(names("ev"), vec!["every"]);
+    assert_eq!(names("zzz"), Vec::<String>::new());
+
+    let completions = completions(source, "");
+    let docs = |name: &str| &completions.iter().find(|(n, _)| n == name).unwrap().1;
+    assert_eq!(docs("kick").doc, "The kick");
+    // (without doc comments, there's just the name)
+    assert_eq!(docs("sine").signature, "sine");
+    assert_eq!(docs("sin").signature, "sin(frequency)");
+    assert_eq!(docs("lowpass").signature, "lowpass{f = 1000hz, q = 0.707}");
+}
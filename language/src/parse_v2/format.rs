@@ -0,0 +1,470 @@
+use super::{parse_syntax_tree, Kind, SyntaxNode};
+
+const INDENT: &str = "  ";
+
+/// Formats a document: normalizes the whitespace between tokens, the indentation, and the trailing commas in argument and parameter lists, while keeping comments and widget references where they are.
+///
+/// Top-level items with syntax errors in them are left exactly as they are (as are skipped characters, and the whitespace around them), because there's no telling what they're supposed to look like.
+pub fn format_document(source: &str) -> String {
+    let (tree, errors) = parse_syntax_tree(source);
+
+    let mut formatter = Formatter {
+        source,
+        errors: errors.into_iter().map(|e| e.0.start.offset).collect(),
+        out: String::new(),
+        item_indent: 0,
+    };
+
+    formatter.document(&tree);
+    formatter.out
+}
+
+/// What goes between two tokens
+#[derive(Debug, Clone, Copy)]
+struct Gap {
+    /// When they stay on the same line
+    inline: &'static str,
+    min_breaks: usize,
+    /// How many line breaks are kept (so 2 keeps at most one blank line), 0 means the tokens are always joined
+    max_breaks: usize,
+    /// Of whatever comes after a line break
+    indent: usize,
+    /// Of comments on their own line (which differs before a closing delimiter, where they belong to the inside)
+    comment_indent: usize,
+}
+
+impl Gap {
+    fn closing(max_breaks: usize, inline: &'static str, indent: usize) -> Self {
+        Self {
+            inline,
+            comment_indent: indent + 1,
+            ..Self::join(max_breaks, indent)
+        }
+    }
+
+    fn join(max_breaks: usize, indent: usize) -> Self {
+        Self {
+            inline: "",
+            min_breaks: 0,
+            max_breaks,
+            indent,
+            comment_indent: indent,
+        }
+    }
+
+    fn space(max_breaks: usize, indent: usize) -> Self {
+        Self {
+            inline: " ",
+            min_breaks: 0,
+            max_breaks,
+            indent,
+            comment_indent: indent,
+        }
+    }
+}
+
+enum Trivia<'a> {
+    Break,
+    Comment(&'a str),
+}
+
+/// The line breaks and comments in a piece of whitespace (the rest of it doesn't matter)
+fn trivia(ws: &str) -> Vec<Trivia<'_>> {
+    let mut pieces = vec![];
+    let mut rest = ws;
+
+    while let Some(ch) = rest.chars().next() {
+        if rest.starts_with("//") {
+            let end = rest.find('\n').unwrap_or(rest.len());
+            pieces.push(Trivia::Comment(rest[..end].trim_end()));
+            rest = &rest[end..];
+        } else {
+            if ch == '\n' {
+                pieces.push(Trivia::Break);
+            }
+            rest = &rest[ch.len_utf8()..];
+        }
+    }
+
+    pieces
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Item<'t, 'a> {
+    Node(&'t SyntaxNode<'a>),
+    /// A trailing comma that the formatter adds
+    Comma,
+}
+
+impl<'t, 'a> Item<'t, 'a> {
+    fn kind(&self) -> Kind {
+        match self {
+            Item::Node(node) => node.kind,
+            Item::Comma => Kind::Comma,
+        }
+    }
+}
+
+/// A node's children, each with the whitespace before it, and the whitespace after the last one
+fn items<'t, 'a>(node: &'t SyntaxNode<'a>) -> (Vec<(String, Item<'t, 'a>)>, String) {
+    let mut items = vec![];
+    let mut ws = String::new();
+
+    for child in &node.children {
+        if child.kind == Kind::Ws {
+            ws.push_str(child.fragment.unwrap_or_default());
+        } else {
+            items.push((std::mem::take(&mut ws), Item::Node(child)));
+        }
+    }
+
+    (items, ws)
+}
+
+/// A parenthesized (or bracketed) list gets a trailing comma when it's spread over multiple lines, and loses it when it's on one line
+fn normalize_trailing_comma(items: &mut Vec<(String, Item)>) {
+    let Some(close) = items.iter().position(|(_, item)| {
+        matches!(
+            item.kind(),
+            Kind::ParenRight | Kind::BracketRight | Kind::CurlyRight
+        )
+    }) else {
+        return;
+    };
+    let Some(open) = items[..close].iter().rposition(|(_, item)| {
+        matches!(
+            item.kind(),
+            Kind::ParenLeft | Kind::BracketLeft | Kind::CurlyLeft
+        )
+    }) else {
+        return;
+    };
+    if close == open + 1 {
+        return;
+    }
+
+    let multiline = items[close].0.contains('\n');
+    let has_trailing_comma = items[close - 1].1.kind() == Kind::Comma;
+
+    if has_trailing_comma && !multiline {
+        let (ws, _) = items.remove(close - 1);
+        items[close - 1].0.insert_str(0, &ws);
+    } else if !has_trailing_comma && multiline {
+        items.insert(close, (String::new(), Item::Comma));
+    }
+}
+
+fn is_opening(kind: Kind) -> bool {
+    matches!(kind, Kind::ParenLeft | Kind::BracketLeft | Kind::CurlyLeft)
+}
+
+struct Formatter<'s> {
+    source: &'s str,
+    /// Where the parse errors start
+    errors: Vec<usize>,
+    out: String,
+    /// The indentation of the line that the current statement (or argument) started on, which is what continuation lines are indented relative to
+    item_indent: usize,
+}
+
+impl<'s> Formatter<'s> {
+    fn document(&mut self, tree: &SyntaxNode) {
+        let (items, trailing) = items(tree);
+
+        let left_alone = |node: &SyntaxNode| {
+            let range = node.range.range();
+            node.kind == Kind::Skipped
+                || self
+                    .errors
+                    .iter()
+                    .any(|&e| range.start < e && e <= range.end)
+        };
+        let left_alone: Vec<bool> = items
+            .iter()
+            .map(|(_, item)| match item {
+                Item::Node(node) => left_alone(node),
+                Item::Comma => false,
+            })
+            .collect();
+
+        for (i, (ws, item)) in items.iter().enumerate() {
+            let Item::Node(node) = item else {
+                continue;
+            };
+
+            if left_alone[i] || (i > 0 && left_alone[i - 1]) {
+                self.out.push_str(ws);
+            } else if i == 0 {
+                self.gap(ws, Gap::join(2, 0));
+            } else if items[i - 1].1.kind() == Kind::Semi {
+                self.gap(
+                    ws,
+                    Gap {
+                        inline: " ",
+                        min_breaks: 1,
+                        max_breaks: 2,
+                        indent: 0,
+                        comment_indent: 0,
+                    },
+                );
+            } else if node.kind == Kind::Semi {
+                self.gap(ws, Gap::join(0, 1));
+            } else {
+                self.gap(ws, Gap::space(2, 0));
+            }
+
+            self.item_indent = 0;
+
+            if left_alone[i] {
+                self.out.push_str(&self.source[node.range.range()]);
+            } else {
+                self.node(node);
+            }
+        }
+
+        if left_alone.last() == Some(&true) {
+            self.out.push_str(&trailing);
+        } else {
+            self.gap(&trailing, Gap::join(2, 0));
+            self.out.truncate(self.out.trim_end().len());
+        }
+    }
+
+    fn node(&mut self, node: &SyntaxNode) {
+        if let Some(fragment) = node.fragment {
+            match node.kind {
+                Kind::DocComment => self.out.push_str(fragment.trim_end()),
+                _ => self.out.push_str(fragment),
+            }
+            return;
+        }
+
+        let (mut items, trailing) = items(node);
+        if matches!(
+            node.kind,
+            Kind::CallExpr
+                | Kind::ModifierExpr
+                | Kind::SettingsExpr
+                | Kind::ArrayExpr
+                | Kind::FnDecl
+        ) {
+            normalize_trailing_comma(&mut items);
+        }
+
+        let outer_item_indent = self.item_indent;
+        let mut open_indent = self.line_indent();
+
+        for (i, (ws, item)) in items.iter().enumerate() {
+            if i == 0 {
+                self.out.push_str(ws);
+            } else {
+                let (gap, starts_item) = self.gap_between(
+                    node.kind,
+                    i - 1,
+                    items[i - 1].1.kind(),
+                    item.kind(),
+                    open_indent,
+                );
+                self.gap(ws, gap);
+                if starts_item {
+                    self.item_indent = self.line_indent();
+                }
+            }
+
+            if is_opening(item.kind()) {
+                open_indent = self.line_indent();
+            }
+
+            match item {
+                Item::Node(child) => self.node(child),
+                Item::Comma => self.out.push(','),
+            }
+        }
+
+        self.out.push_str(&trailing);
+        self.item_indent = outer_item_indent;
+    }
+
+    /// The gap between two children of a `parent` node, and whether a new item (statement, argument, ..) starts after it
+    fn gap_between(
+        &self,
+        parent: Kind,
+        prev_index: usize,
+        prev: Kind,
+        next: Kind,
+        open_indent: usize,
+    ) -> (Gap, bool) {
+        use Kind::*;
+
+        let continuation = self.item_indent + 1;
+
+        match (parent, prev, next) {
+            // (the text of a string is left as it is)
+            (InterpolatedStr | Interpolation, _, _) => (Gap::join(0, continuation), false),
+            // (a doc comment is on its own line, right above what it documents)
+            (_, DocComment, _) => (
+                Gap {
+                    min_breaks: 1,
+                    ..Gap::join(1, self.item_indent)
+                },
+                false,
+            ),
+            (_, _, Semi | Comma) => (Gap::join(0, continuation), false),
+            (Block, CurlyLeft, CurlyRight) => (Gap::closing(1, "", open_indent), false),
+            (Block, _, CurlyRight) => (Gap::closing(1, " ", open_indent), false),
+            (_, _, ParenRight | BracketRight | CurlyRight) => {
+                (Gap::closing(1, "", open_indent), false)
+            }
+            (Block, CurlyLeft, _) => (Gap::space(1, open_indent + 1), true),
+            (Block, _, _) => (Gap::space(2, open_indent + 1), true),
+            (_, ParenLeft | BracketLeft, _) | (SettingsExpr, CurlyLeft, _) => {
+                (Gap::join(1, open_indent + 1), true)
+            }
+            (_, Comma, _) => (Gap::space(1, open_indent + 1), true),
+            (_, _, ParenLeft | BracketLeft) | (SettingsExpr, _, CurlyLeft) => {
+                (Gap::join(0, continuation), false)
+            }
+            (_, _, Percent) | (_, Percent, _) => (Gap::join(0, continuation), false),
+            (_, Dot, _) | (_, _, Dot) => (Gap::join(1, continuation), false),
+            (Amount, _, _) => (Gap::join(0, continuation), false),
+            (AnonymousFn, Pipe, _) if prev_index == 0 => (Gap::join(0, continuation), false),
+            (AnonymousFn, _, Pipe) => (Gap::join(0, continuation), false),
+            (UnaryExpr, Op, _) => (Gap::join(0, continuation), false),
+            (_, Keyword, _) => (Gap::space(0, continuation), false),
+            _ => (Gap::space(1, continuation), false),
+        }
+    }
+
+    /// Writes the whitespace `ws` (that was in the source) as `gap`, keeping its comments
+    fn gap(&mut self, ws: &str, gap: Gap) {
+        let mut breaks = 0;
+        let mut commented = false;
+
+        for piece in trivia(ws) {
+            match piece {
+                Trivia::Break => breaks += 1,
+                Trivia::Comment(comment) => {
+                    if breaks == 0 {
+                        if !self.out.is_empty() {
+                            self.out.push(' ');
+                        }
+                    } else {
+                        let breaks = breaks.clamp(1, gap.max_breaks.max(1));
+                        self.line_break(breaks, gap.comment_indent);
+                    }
+                    self.out.push_str(comment);
+                    breaks = 0;
+                    commented = true;
+                }
+            }
+        }
+
+        // (a comment always ends its line)
+        let min_breaks = if commented { 1 } else { gap.min_breaks };
+        let breaks = breaks.clamp(min_breaks, gap.max_breaks.max(min_breaks));
+
+        if breaks > 0 {
+            self.line_break(breaks, gap.indent);
+        } else {
+            self.out.push_str(gap.inline);
+        }
+    }
+
+    fn line_break(&mut self, breaks: usize, indent: usize) {
+        if self.out.is_empty() {
+            return;
+        }
+
+        self.out.truncate(self.out.trim_end_matches(' ').len());
+        self.out.push_str(&"\n".repeat(breaks));
+        self.out.push_str(&INDENT.repeat(indent));
+    }
+
+    /// The indentation of the line that's currently being written
+    fn line_indent(&self) -> usize {
+        let line = &self.out[self.out.rfind('\n').map_or(0, |i| i + 1)..];
+        (line.len() - line.trim_start_matches(' ').len()) / INDENT.len()
+    }
+}
+
+#[cfg(test)]
+fn assert_formats(source: &str, expected: &str) {
+    let formatted = format_document(source);
+    assert_eq!(formatted, expected);
+    assert_eq!(format_document(&formatted), formatted, "not idempotent");
+}
+
+#[test]
+fn test_format_whitespace() {
+    assert_formats("let  x=1+2 ;play   x;", "let x = 1 + 2;\nplay x;");
+    assert_formats(
+        "\n\n\ndef  a = f (1 , 2) [0] . b;\n\n\n\nlet b = 2 s;\n\n",
+        "def a = f(1, 2)[0].b;\n\nlet b = 2s;",
+    );
+    assert_formats("let f = |a ,b|a+b;", "let f = |a, b| a + b;");
+    assert_formats(
+        "let p = \"kicks/ ${ name }.wav\" ;",
+        "let p = \"kicks/ ${name}.wav\";",
+    );
+    assert_formats(
+        "let g = if ! a&&b>=2 {1}else  if c {2}else{3};",
+        "let g = if !a && b >= 2 { 1 } else if c { 2 } else { 3 };",
+    );
+}
+
+#[test]
+fn test_format_indentation() {
+    assert_formats(
+        "fn f(a,b){\nlet x = a;\n\n\n      x\n}",
+        "fn f(a, b) {\n  let x = a;\n\n  x\n}",
+    );
+    assert_formats("fn f() {  }", "fn f() {}");
+    assert_formats("let y = {let x = 2; x};", "let y = { let x = 2; x };");
+    assert_formats("let y = a\n+ b\n* c;", "let y = a\n  + b\n  * c;");
+}
+
+#[test]
+fn test_format_trailing_commas() {
+    assert_formats("f(a, b,);", "f(a, b);");
+    assert_formats("f(\na,\nb\n);", "f(\n  a,\n  b,\n);");
+    assert_formats("fn f(\na, b) { a }", "fn f(\n  a, b) { a }");
+    assert_formats("fn f(a,\nb\n) { a }", "fn f(a,\n  b,\n) { a }");
+    assert_formats("kick %[ rate=2, loop = 1, ];", "kick%[rate = 2, loop = 1];");
+    assert_formats(
+        "lowpass{ f=800hz,q = 2, }(x);",
+        "lowpass{f = 800hz, q = 2}(x);",
+    );
+    assert_formats(
+        "reverb{\nroom = 0.9,\nmix = 0.5\n}(x);",
+        "reverb{\n  room = 0.9,\n  mix = 0.5,\n}(x);",
+    );
+    assert_formats(
+        "let xs = [ (1,1) ,(3, .5), ];",
+        "let xs = [(1, 1), (3, .5)];",
+    );
+    assert_formats("let x = (1, );", "let x = (1,);");
+    assert_formats("let xs = [\n1,\n2\n];", "let xs = [\n  1,\n  2,\n];");
+}
+
+#[test]
+fn test_format_comments_and_widgets() {
+    assert_formats(
+        "// drums\n\n\n\ndef kick = sample#3 ;   // loud  \nplay kick; // go\n",
+        "// drums\n\ndef kick = sample#3; // loud\nplay kick; // go",
+    );
+    assert_formats(
+        "fn f() {\n  // nothing yet\n}",
+        "fn f() {\n  // nothing yet\n}",
+    );
+    assert_formats("f(a, // first\nb);", "f(a, // first\n  b);");
+    assert_formats(
+        "/// the kick  \n\n   /// (loud)\ndef kick = 1;\nfn f() {\n/// twice\nlet x = 2; x }",
+        "/// the kick\n/// (loud)\ndef kick = 1;\nfn f() {\n  /// twice\n  let x = 2; x }",
+    );
+}
+
+#[test]
+fn test_format_leaves_errors_alone() {
+    assert_formats("def x = ;\nlet  y = 2;", "def x = ;\nlet y = 2;");
+    assert_formats("let  a = 1;  @@ let  b = 2;", "let a = 1;  @@ let b = 2;");
+}
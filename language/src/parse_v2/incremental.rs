@@ -0,0 +1,521 @@
+use std::{cell::RefCell, mem, ops::Range, rc::Rc};
+
+use super::{
+    outline::{outline_of, Symbol},
+    p_document_item, CollectibleNodes, Kind, ParseError, ParseState, Span, SyntaxNode,
+};
+use crate::span::{Loc, SourceSpan};
+
+/// An edit to the source: the (old) range that was replaced, and how long the text is that replaced it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub new_len: usize,
+}
+
+impl TextEdit {
+    fn delta(&self) -> isize {
+        self.new_len as isize - self.range.len() as isize
+    }
+}
+
+/**
+    How the text after an edit moved: from where the edit ended in the old source, to where it ends in the new one (so lines move along by however many lines were added, and columns only on the line the edit ended on)
+*/
+#[derive(Debug, Clone, Copy)]
+struct Shift {
+    old_end: Loc,
+    new_end: Loc,
+}
+
+impl Shift {
+    const NONE: Shift = Shift {
+        old_end: Loc::START,
+        new_end: Loc::START,
+    };
+
+    /// (for places at or after the end of the edit)
+    fn loc(&self, loc: Loc) -> Loc {
+        Loc {
+            offset: loc.offset - self.old_end.offset + self.new_end.offset,
+            line: loc.line - self.old_end.line + self.new_end.line,
+            col: match loc.line == self.old_end.line {
+                true => loc.col - self.old_end.col + self.new_end.col,
+                false => loc.col,
+            },
+        }
+    }
+
+    fn span(&self, span: SourceSpan) -> SourceSpan {
+        SourceSpan {
+            start: self.loc(span.start),
+            end: self.loc(span.end),
+        }
+    }
+}
+
+impl<'a> SyntaxNode<'a> {
+    /// The same node without its text (which `rebased` puts back), so that it can outlive the source
+    fn detached(&self) -> SyntaxNode<'static> {
+        SyntaxNode {
+            kind: self.kind,
+            range: self.range,
+            fragment: self.fragment.map(|_| ""),
+            children: self.children.iter().map(SyntaxNode::detached).collect(),
+        }
+    }
+
+    /// The same node, moved along, in a new source (in which it covers the same text)
+    fn rebased<'b>(&self, shift: Shift, source: &'b str) -> SyntaxNode<'b> {
+        let range = shift.span(self.range);
+
+        SyntaxNode {
+            kind: self.kind,
+            range,
+            fragment: self.fragment.map(|_| &source[range.range()]),
+            children: self
+                .children
+                .iter()
+                .map(|child| child.rebased(shift, source))
+                .collect(),
+        }
+    }
+}
+
+/// One of the document's top-level items (as `p_document_item` parsed it), and the errors found while parsing it
+#[derive(Debug, Clone)]
+struct Item {
+    /// (which of the document's children it is)
+    children: Range<usize>,
+    errors: Vec<ParseError>,
+    /// How far the parser looked to parse it, if that's past its end (like for a string that isn't closed, which it first tries to find the end of)
+    looked_at: usize,
+}
+
+/**
+    A parsed document, that remembers which of its children make up which top-level item, and what errors were found while parsing each of them, so that it can be parsed again quickly after an edit (see `reparse`).
+*/
+pub struct IncrementalParse<'a> {
+    pub tree: SyntaxNode<'a>,
+    source: &'a str,
+    items: Vec<Item>,
+}
+
+impl<'a> IncrementalParse<'a> {
+    /// (the same as `parse_syntax_tree`, but keeping track of the items)
+    pub fn new(source: &'a str) -> Self {
+        let mut nodes = vec![];
+        let mut items = vec![];
+        parse_items(source, Loc::START, &mut nodes, &mut items, |_, _| false);
+
+        Self {
+            tree: document(nodes),
+            source,
+            items,
+        }
+    }
+
+    /// All of them, in the order they were found
+    pub fn errors(&self) -> Vec<ParseError> {
+        self.items
+            .iter()
+            .flat_map(|item| item.errors.iter().cloned())
+            .collect()
+    }
+
+    fn start_of(&self, item: &Item) -> usize {
+        self.tree.children[item.children.start].range.start.offset
+    }
+
+    fn end_of(&self, item: &Item) -> Loc {
+        self.tree.children[item.children.end - 1].range.end
+    }
+
+    /// Whether the item ends with a `;` (after which the parser doesn't look any further)
+    fn is_closed(&self, item: &Item) -> bool {
+        self.tree.children[item.children.clone()]
+            .last()
+            .is_some_and(|node| node.kind == Kind::Semi)
+    }
+
+    /**
+        Parses the document again after an edit (the new source is the old one, edited), reusing what the edit didn't touch, which is a lot quicker than parsing all of it for every keystroke.
+
+        The items in front of the edit are kept, up until the last one closed off by a `;` (anything after that might have continued differently), and that the parser didn't need to look past the edit for. From there, items are parsed again, until the parser gets back in step with the old tree: at the start of an item after the edit, right after a `;`. Since items are parsed one after the other without any state carried over, the rest is exactly what it was, only moved along.
+    */
+    pub fn reparse<'b>(&self, source: &'b str, edit: &TextEdit) -> IncrementalParse<'b> {
+        let old_len = self.source.len();
+        if edit.range.start > edit.range.end
+            || edit.range.end > old_len
+            || old_len - edit.range.len() + edit.new_len != source.len()
+        {
+            return IncrementalParse::new(source);
+        }
+
+        let delta = edit.delta();
+
+        let edit_start = Loc::at(source, edit.range.start);
+        let shift = Shift {
+            old_end: edit_start.after(&self.source[edit.range.clone()]),
+            new_end: edit_start.after(&source[edit.range.start..edit.range.start + edit.new_len]),
+        };
+
+        let looked_past_edit = self
+            .items
+            .iter()
+            .position(|item| item.looked_at >= edit.range.start)
+            .unwrap_or(self.items.len());
+        let kept = self.items[..looked_past_edit]
+            .iter()
+            .rposition(|item| self.is_closed(item) && self.end_of(item).offset <= edit.range.start)
+            .map_or(0, |i| i + 1);
+        let start = kept
+            .checked_sub(1)
+            .map_or(Loc::START, |i| self.end_of(&self.items[i]));
+
+        let kept_children = self
+            .items
+            .get(kept)
+            .map_or(self.tree.children.len(), |item| item.children.start);
+
+        let mut nodes = self.tree.children[..kept_children]
+            .iter()
+            .map(|node| node.rebased(Shift::NONE, source))
+            .collect::<Vec<_>>();
+
+        // (errors tend to run to the end of the document, so the ones in front of the edit can end after it)
+        let mut items = self.items[..kept]
+            .iter()
+            .map(|item| Item {
+                children: item.children.clone(),
+                looked_at: item.looked_at,
+                errors: item
+                    .errors
+                    .iter()
+                    .map(|ParseError(range, message)| {
+                        let end = match range.end.offset >= edit.range.end {
+                            true => shift.loc(range.end),
+                            false => range.end,
+                        };
+
+                        ParseError(SourceSpan { end, ..*range }, message.clone())
+                    })
+                    .collect(),
+            })
+            .collect::<Vec<_>>();
+
+        // (the old items after the edit, where the parser can get back in step)
+        let mut resync = kept;
+        let mut in_step_with = None;
+
+        parse_items(source, start, &mut nodes, &mut items, |offset, in_step| {
+            while self.items.get(resync).is_some_and(|item| {
+                let start = self.start_of(item);
+                start < edit.range.end || start as isize + delta < offset as isize
+            }) {
+                resync += 1;
+            }
+
+            in_step_with = self.items.get(resync).filter(|item| {
+                in_step
+                    && offset >= edit.range.start + edit.new_len
+                    && self.start_of(item) as isize + delta == offset as isize
+            });
+
+            in_step_with.is_some()
+        });
+
+        if let Some(from) = in_step_with {
+            let moved = nodes.len() as isize - from.children.start as isize;
+
+            nodes.extend(
+                self.tree.children[from.children.start..]
+                    .iter()
+                    .map(|node| node.rebased(shift, source)),
+            );
+
+            items.extend(self.items[resync..].iter().map(|item| {
+                Item {
+                    children: (item.children.start as isize + moved) as usize
+                        ..(item.children.end as isize + moved) as usize,
+                    errors: item
+                        .errors
+                        .iter()
+                        .map(|ParseError(range, message)| {
+                            ParseError(shift.span(*range), message.clone())
+                        })
+                        .collect(),
+                    looked_at: (item.looked_at as isize + delta) as usize,
+                }
+            }));
+        }
+
+        IncrementalParse {
+            tree: document(nodes),
+            source,
+            items,
+        }
+    }
+}
+
+/**
+    A document that's kept parsed while it's edited, like the editor's, which can't keep an `IncrementalParse` around, because the source it borrows from changes underneath it. So this one owns the source, and keeps the tree detached from it in the meantime.
+*/
+#[derive(Debug)]
+pub struct ParsedDocument {
+    source: String,
+    tree: SyntaxNode<'static>,
+    items: Vec<Item>,
+}
+
+impl ParsedDocument {
+    pub fn new(source: String) -> Self {
+        let IncrementalParse { tree, items, .. } = IncrementalParse::new(&source);
+        let tree = tree.detached();
+
+        Self {
+            source,
+            tree,
+            items,
+        }
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /**
+        Parses the new source incrementally, as an edit of the old one (from the first to the last character that's different), which is also right for a bunch of edits at once, like an undo, if a bit less quick
+    */
+    pub fn update(&mut self, source: String) {
+        if source == self.source {
+            return;
+        }
+
+        let edit = edit_between(&self.source, &source);
+        let parse = IncrementalParse {
+            tree: mem::replace(&mut self.tree, document(vec![])),
+            source: &self.source,
+            items: mem::take(&mut self.items),
+        };
+        let IncrementalParse { tree, items, .. } = parse.reparse(&source, &edit);
+
+        self.tree = tree.detached();
+        self.items = items;
+        self.source = source;
+    }
+
+    pub fn tree(&self) -> SyntaxNode<'_> {
+        self.tree.rebased(Shift::NONE, &self.source)
+    }
+
+    pub fn errors(&self) -> Vec<ParseError> {
+        self.items
+            .iter()
+            .flat_map(|item| item.errors.iter().cloned())
+            .collect()
+    }
+
+    /// (see `outline`)
+    pub fn outline(&self) -> Vec<Symbol> {
+        outline_of(&self.tree())
+    }
+}
+
+/// (the part of the old source that's different in the new one)
+fn edit_between(old: &str, new: &str) -> TextEdit {
+    let prefix = old
+        .char_indices()
+        .zip(new.chars())
+        .find(|((_, a), b)| a != b)
+        .map_or(old.len().min(new.len()), |((i, _), _)| i);
+    // (after the prefix, so that they don't overlap)
+    let suffix = old[prefix..]
+        .chars()
+        .rev()
+        .zip(new[prefix..].chars().rev())
+        .take_while(|(a, b)| a == b)
+        .map(|(a, _)| a.len_utf8())
+        .sum::<usize>();
+
+    TextEdit {
+        range: prefix..old.len() - suffix,
+        new_len: new.len() - prefix - suffix,
+    }
+}
+
+/**
+    Parses items from the offset on, until the end of the source, or until `stop` says so (given the offset, and whether the last item was closed off by a `;`)
+*/
+fn parse_items<'a>(
+    source: &'a str,
+    start: Loc,
+    nodes: &mut Vec<SyntaxNode<'a>>,
+    items: &mut Vec<Item>,
+    mut stop: impl FnMut(usize, bool) -> bool,
+) {
+    let errors = Rc::new(RefCell::new(vec![]));
+    // SAFETY: the fragment really is at that offset (and line) of the source
+    let mut input = unsafe {
+        Span::new_from_raw_offset(
+            start.offset,
+            start.line as u32,
+            &source[start.offset..],
+            ParseState::new(errors.clone()),
+        )
+    };
+    let state = input.extra.clone();
+    let mut closed = true;
+
+    while !input.is_empty() && !stop(input.location_offset(), closed) {
+        let (rem, item) = p_document_item(input).expect("could not parse document");
+        closed = item.last().is_some_and(|node| node.kind == Kind::Semi);
+
+        let first = nodes.len();
+        item.collect_into(nodes);
+        items.push(Item {
+            children: first..nodes.len(),
+            errors: errors.take(),
+            looked_at: state.take_looked_at(),
+        });
+
+        input = rem;
+    }
+}
+
+/// (the same node `p_document` wraps its items in)
+fn document(nodes: Vec<SyntaxNode>) -> SyntaxNode {
+    let range = SourceSpan {
+        start: Loc::START,
+        end: nodes.last().map_or(Loc::START, |node| node.range.end),
+    };
+
+    SyntaxNode::new(Kind::Document, range).with_collect_children(nodes)
+}
+
+#[cfg(test)]
+use super::parse_syntax_tree;
+
+#[cfg(test)]
+fn assert_reparses(old_source: &str, range: Range<usize>, replacement: &str) {
+    let mut source = old_source.to_string();
+    source.replace_range(range.clone(), replacement);
+
+    let edit = TextEdit {
+        range,
+        new_len: replacement.len(),
+    };
+    let reparsed = IncrementalParse::new(old_source).reparse(&source, &edit);
+
+    assert_eq!(
+        (reparsed.tree.clone(), reparsed.errors()),
+        parse_syntax_tree(&source),
+        "after replacing {:?} with {:?} in {:?}",
+        edit.range,
+        replacement,
+        old_source
+    );
+}
+
+#[cfg(test)]
+const DOCUMENT: &str = "let a = 1;\n\nfn kick(t) {\n  let inner = 2;\n  inner * t\n}\n\ndef beat = kick%[rate = 1.5] * \"x;${a}\";\nplay beat;\nlet c = 2 // comment;\n;\n  play c;\n";
+
+#[test]
+fn test_reparse_edits() {
+    let at = |text: &str| DOCUMENT.find(text).unwrap();
+
+    // typing within a statement
+    assert_reparses(DOCUMENT, at("1;")..at("1;") + 1, "100");
+    // removing a `;`, so that two statements run into each other
+    assert_reparses(DOCUMENT, at("1;") + 1..at("1;") + 2, "");
+    // opening a string, that swallows everything after it
+    assert_reparses(DOCUMENT, at("play beat")..at("play beat"), "\"");
+    // commenting out a line
+    assert_reparses(DOCUMENT, at("play beat")..at("play beat"), "// ");
+    // opening a block
+    assert_reparses(DOCUMENT, at("let inner")..at("let inner"), "{ ");
+    // at the very start and end
+    assert_reparses(DOCUMENT, 0..0, "let z = 3;");
+    assert_reparses(DOCUMENT, DOCUMENT.len()..DOCUMENT.len(), "play z");
+    // everything
+    assert_reparses(DOCUMENT, 0..DOCUMENT.len(), "play 1;");
+}
+
+#[test]
+fn test_reparse_random_edits() {
+    const SNIPPETS: &[&str] = &[
+        "",
+        ";",
+        "\"",
+        "{",
+        "}",
+        "(",
+        "//",
+        "\n",
+        "let x = 1",
+        "play y;",
+        "fn f() {",
+        " * 2",
+        "#f80",
+        "${",
+    ];
+
+    // (a small pseudo-random generator, so that failures can be reproduced)
+    let mut seed = 12345u64;
+    let mut random = |n: usize| {
+        seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (seed >> 33) as usize % n
+    };
+
+    // (every source stays around, since each tree borrows from it)
+    let mut source: &'static str = DOCUMENT;
+    let mut parsed = IncrementalParse::new(source);
+
+    for _ in 0..500 {
+        let start = random(source.len() + 1);
+        let end = (start + random(6)).min(source.len());
+        let replacement = SNIPPETS[random(SNIPPETS.len())];
+
+        let mut new_source = source.to_string();
+        new_source.replace_range(start..end, replacement);
+        let new_source: &'static str = Box::leak(new_source.into_boxed_str());
+
+        let edit = TextEdit {
+            range: start..end,
+            new_len: replacement.len(),
+        };
+        let reparsed = parsed.reparse(new_source, &edit);
+
+        assert_eq!(
+            (reparsed.tree.clone(), reparsed.errors()),
+            parse_syntax_tree(new_source),
+            "after replacing {:?} with {:?} in {:?}",
+            start..end,
+            replacement,
+            source
+        );
+
+        source = new_source;
+        parsed = reparsed;
+    }
+}
+
+#[test]
+fn test_parsed_document() {
+    let mut parsed = ParsedDocument::new(DOCUMENT.to_string());
+
+    for source in [
+        DOCUMENT.replace("play beat;", "play beat * 2;"),
+        DOCUMENT.replace("let a = 1;", "let a = \"é"),
+        DOCUMENT.replace("kick", "snare"),
+        "".to_string(),
+        DOCUMENT.to_string(),
+    ] {
+        parsed.update(source.clone());
+
+        assert_eq!(parsed.source(), source);
+        assert_eq!((parsed.tree(), parsed.errors()), parse_syntax_tree(&source));
+    }
+}
@@ -0,0 +1,353 @@
+use std::collections::HashMap;
+
+use super::{
+    incremental::ParsedDocument, lower::lower_document, outline::outline_of, Kind, SyntaxNode,
+};
+use crate::{
+    check::check_units,
+    span::{Loc, SourceSpan},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Off,
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "off" => Some(Self::Off),
+            "info" => Some(Self::Info),
+            "warning" => Some(Self::Warning),
+            "error" => Some(Self::Error),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LintKind {
+    UseBeforeDefinition,
+    DeepNesting,
+    LongLine,
+    /// Like `5hz + 3s`
+    UnitMismatch,
+    /// (this one's about the files on disk, so it's up to the editor to check it, but it's configured like the others)
+    UnreferencedSample,
+    /// (and this one's about what happens when the code runs, which the audio engine reports)
+    Runaway,
+}
+
+impl LintKind {
+    pub const ALL: &[LintKind] = &[
+        Self::UseBeforeDefinition,
+        Self::DeepNesting,
+        Self::LongLine,
+        Self::UnitMismatch,
+        Self::UnreferencedSample,
+        Self::Runaway,
+    ];
+
+    /// How it's referred to in the lint configuration
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::UseBeforeDefinition => "use_before_definition",
+            Self::DeepNesting => "deep_nesting",
+            Self::LongLine => "long_line",
+            Self::UnitMismatch => "unit_mismatch",
+            Self::UnreferencedSample => "unreferenced_sample",
+            Self::Runaway => "runaway",
+        }
+    }
+}
+
+/// A structural issue with a document. Unlike parse errors, these don't stop anything from working.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lint {
+    pub kind: LintKind,
+    pub severity: Severity,
+    pub message: String,
+    /// Where in the source the problem is, if it's in the source at all
+    pub range: Option<SourceSpan>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintConfig {
+    severities: HashMap<LintKind, Severity>,
+    pub max_nesting: usize,
+    pub max_line_length: usize,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        Self {
+            severities: HashMap::from([
+                // `def`s can be used before they're defined, it's just harder to read
+                (LintKind::UseBeforeDefinition, Severity::Info),
+                (LintKind::DeepNesting, Severity::Warning),
+                (LintKind::LongLine, Severity::Info),
+                // (it won't sound like anything that makes sense)
+                (LintKind::UnitMismatch, Severity::Error),
+                (LintKind::UnreferencedSample, Severity::Info),
+                // (the engine already throttled it, but you'll want to know why it sounds off)
+                (LintKind::Runaway, Severity::Warning),
+            ]),
+            max_nesting: 4,
+            max_line_length: 100,
+        }
+    }
+}
+
+impl LintConfig {
+    /// Parses a configuration like the following, where anything that's not mentioned keeps its default:
+    ///
+    /// ```text
+    /// use_before_definition   warning
+    /// long_line               off
+    /// max_line_length         120
+    /// ```
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let mut config = Self::default();
+
+        for (i, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let invalid = || format!("invalid lint config line {}: {:?}", i + 1, line);
+
+            match line.split_whitespace().collect::<Vec<_>>()[..] {
+                ["max_nesting", value] => {
+                    config.max_nesting = value.parse().map_err(|_| invalid())?;
+                }
+                ["max_line_length", value] => {
+                    config.max_line_length = value.parse().map_err(|_| invalid())?;
+                }
+                [name, severity] => {
+                    let kind = LintKind::ALL
+                        .iter()
+                        .find(|kind| kind.name() == name)
+                        .ok_or_else(invalid)?;
+
+                    let severity = Severity::parse(severity).ok_or_else(invalid)?;
+
+                    config.severities.insert(*kind, severity);
+                }
+                _ => return Err(invalid()),
+            }
+        }
+
+        Ok(config)
+    }
+
+    pub fn severity(&self, kind: LintKind) -> Severity {
+        self.severities.get(&kind).copied().unwrap_or(Severity::Off)
+    }
+}
+
+/// Checks the structure of a document, in source order per kind of lint.
+///
+/// Like the outline, this works on the lossless syntax tree, so that it also works for documents that don't parse cleanly.
+pub fn lint(source: &str, config: &LintConfig) -> Vec<Lint> {
+    lint_parsed(&ParsedDocument::new(source.to_string()), config)
+}
+
+/// (for a document that's already parsed, like the editor's)
+pub fn lint_parsed(parsed: &ParsedDocument, config: &LintConfig) -> Vec<Lint> {
+    let source = parsed.source();
+    let mut lints = vec![];
+
+    let mut report = |kind: LintKind, message: String, range: SourceSpan| {
+        let severity = config.severity(kind);
+        if severity != Severity::Off {
+            lints.push(Lint {
+                kind,
+                severity,
+                message,
+                range: Some(range),
+            });
+        }
+    };
+
+    let tree = parsed.tree();
+
+    // definitions defined after use
+    let symbols = outline_of(&tree);
+    let mut uses = vec![];
+    collect_uses(&tree, &mut uses);
+
+    for symbol in &symbols {
+        if let Some(first_use) = uses
+            .iter()
+            .find(|(name, range)| *name == symbol.name && range.start < symbol.name_range.start)
+        {
+            report(
+                LintKind::UseBeforeDefinition,
+                format!("`{}` is used before it's defined", symbol.name),
+                first_use.1,
+            );
+        }
+    }
+
+    // very deep nesting (reported once per time it gets too deep, and stray closing brackets are just ignored)
+    let mut depth = 0;
+    let mut reported = false;
+    tree.walk_postorder(&mut |node| match node.kind {
+        Kind::ParenLeft | Kind::BracketLeft | Kind::CurlyLeft => {
+            depth += 1;
+            if depth > config.max_nesting && !reported {
+                reported = true;
+                report(
+                    LintKind::DeepNesting,
+                    format!("nested more than {} levels deep", config.max_nesting),
+                    node.range,
+                );
+            }
+        }
+        Kind::ParenRight | Kind::BracketRight | Kind::CurlyRight => {
+            depth = depth.saturating_sub(1);
+            if depth <= config.max_nesting {
+                reported = false;
+            }
+        }
+        _ => {}
+    });
+
+    // units that don't add up
+    for (range, message) in check_units(&lower_document(&tree)) {
+        report(LintKind::UnitMismatch, message, range);
+    }
+
+    // extremely long lines
+    let mut offset = 0;
+    for (row, line) in source.split('\n').enumerate() {
+        let length = line.chars().count();
+        if length > config.max_line_length {
+            let overflow_start = line
+                .char_indices()
+                .nth(config.max_line_length)
+                .map_or(line.len(), |(i, _)| i);
+
+            report(
+                LintKind::LongLine,
+                format!(
+                    "line is {} characters long (max {})",
+                    length, config.max_line_length
+                ),
+                SourceSpan {
+                    start: Loc {
+                        offset: offset + overflow_start,
+                        line: row + 1,
+                        col: config.max_line_length + 1,
+                    },
+                    end: Loc {
+                        offset: offset + line.len(),
+                        line: row + 1,
+                        col: length + 1,
+                    },
+                },
+            );
+        }
+
+        offset += line.len() + 1;
+    }
+
+    lints
+}
+
+/// All identifiers that refer to something (so: not the names being declared, params, or member accesses)
+fn collect_uses<'a>(node: &SyntaxNode<'a>, uses: &mut Vec<(&'a str, SourceSpan)>) {
+    let declared = match node.kind {
+        Kind::LetStmt | Kind::FnDecl => node.name_after_keyword().map(|name| name.range),
+        Kind::Param => return,
+        _ => None,
+    };
+
+    let mut prev = None;
+
+    for child in node.children.iter() {
+        if child.kind == Kind::Ident {
+            if Some(child.range) != declared && prev != Some(Kind::Dot) {
+                uses.push((child.text(), child.range));
+            }
+        } else {
+            collect_uses(child, uses);
+        }
+
+        if child.kind != Kind::Ws {
+            prev = Some(child.kind);
+        }
+    }
+}
+
+#[test]
+fn test_lint_use_before_definition() {
+    let source = "def main = beat * kick;\ndef beat = kick;\ndef kick = 1;\nmain.beat;";
+    let lints = lint(source, &LintConfig::default());
+
+    assert_eq!(
+        lints
+            .iter()
+            .map(|lint| (lint.kind, &source[lint.range.unwrap().range()]))
+            .collect::<Vec<_>>(),
+        vec![
+            (LintKind::UseBeforeDefinition, "beat"),
+            (LintKind::UseBeforeDefinition, "kick"),
+        ]
+    );
+}
+
+#[test]
+fn test_lint_nesting_and_long_lines() {
+    let config =
+        LintConfig::parse("max_nesting 2\nmax_line_length 20\nuse_before_definition off").unwrap();
+
+    let source = "let a = ((1));\nlet b = (((2)));\nlet c = 1 + 2 + 3 + 4 + 5;";
+    let lints = lint(source, &config);
+
+    assert_eq!(
+        lints
+            .iter()
+            .map(|lint| (
+                lint.kind,
+                lint.severity,
+                &source[lint.range.unwrap().range()]
+            ))
+            .collect::<Vec<_>>(),
+        vec![
+            (LintKind::DeepNesting, Severity::Warning, "("),
+            (LintKind::LongLine, Severity::Info, "4 + 5;"),
+        ]
+    );
+}
+
+#[test]
+fn test_lint_unit_mismatch() {
+    let source = "def beat = 500ms;\nplay sin(1 / beat + beat);";
+    let lints = lint(source, &LintConfig::default());
+
+    assert_eq!(
+        lints
+            .iter()
+            .map(|lint| (
+                lint.kind,
+                lint.severity,
+                &source[lint.range.unwrap().range()]
+            ))
+            .collect::<Vec<_>>(),
+        vec![(LintKind::UnitMismatch, Severity::Error, "1 / beat + beat")]
+    );
+}
+
+#[test]
+fn test_lint_config() {
+    assert!(LintConfig::parse("long_line loud").is_err());
+    assert!(LintConfig::parse("shouting error").is_err());
+
+    let config = LintConfig::parse("# comment\n\nlong_line error\n").unwrap();
+    assert_eq!(config.severity(LintKind::LongLine), Severity::Error);
+    assert_eq!(config.severity(LintKind::DeepNesting), Severity::Warning);
+}
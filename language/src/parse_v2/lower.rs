@@ -0,0 +1,1031 @@
+use std::f64::consts::{PI, TAU};
+
+use crate::{
+    ast::{
+        self, AnonymousFn, Block, CallExpr, Decl, Document, Expr, FnDecl, Identifier, Modifier, Op,
+        Param, ParamList, Primitive, Stmt, StrPart, Unit,
+    },
+    color::parse_color,
+};
+
+use super::{Kind, SyntaxNode};
+
+/// (The AST's syntax node, as opposed to the lossless one we're lowering from)
+type Node<T> = ast::SyntaxNode<T>;
+
+fn lower_identifier(node: &SyntaxNode) -> Node<Identifier> {
+    Node::new(node.ast_range(), Some(Identifier(node.text().to_string())))
+}
+
+fn lower_number(text: &str) -> Primitive {
+    let text = text
+        .chars()
+        .filter(|&c| c != '_' && !c.is_whitespace())
+        .collect::<String>();
+
+    if !text.contains('.') && let Ok(int) = text.parse::<i64>() {
+        Primitive::Int(int)
+    } else {
+        Primitive::Float(text.parse::<f64>().unwrap_or(f64::NAN))
+    }
+}
+
+pub(super) fn lower_string(text: &str) -> String {
+    let text = text.strip_prefix('"').unwrap_or(text);
+    let text = text.strip_suffix('"').unwrap_or(text);
+
+    let mut unescaped = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' && let Some(escaped) = chars.next() {
+            unescaped.push(escaped);
+        } else {
+            unescaped.push(c);
+        }
+    }
+
+    unescaped
+}
+
+fn lower_primitive(node: &SyntaxNode) -> Primitive {
+    match node.kind {
+        Kind::Bool => Primitive::Bool(node.text() == "true"),
+        Kind::Num => lower_number(node.text()),
+        Kind::Amount => {
+            let value = match node.child(Kind::Num).map(|num| lower_number(num.text())) {
+                Some(Primitive::Int(int)) => int as f64,
+                Some(Primitive::Float(float)) => float,
+                _ => f64::NAN,
+            };
+
+            let unit = node
+                .child(Kind::Unit)
+                .map(|unit| Node::new(unit.ast_range(), Some(Unit::from(unit.text()))))
+                .unwrap_or(Node::MISSING);
+
+            Primitive::Quantity((value, unit))
+        }
+        Kind::MathConstant => match node.text() {
+            "pi" => Primitive::Float(PI),
+            _ => Primitive::Float(TAU),
+        },
+        Kind::Str => Primitive::Str(lower_string(node.text())),
+        // (the parser only lets valid ones through)
+        Kind::Color => Primitive::Color(parse_color(node.text()).unwrap_or([0, 0, 0, 255])),
+        Kind::Note => Primitive::Note(node.text().to_string()),
+        _ => unreachable!("not a primitive: {:?}", node.kind),
+    }
+}
+
+pub fn lower_expr(node: &SyntaxNode) -> Node<Expr> {
+    let expr = match node.kind {
+        // (widgets are bound by the editor, so to the language they're just variables)
+        Kind::Ident | Kind::WidgetRef => Expr::Var(lower_identifier(node)),
+        Kind::Bool
+        | Kind::Num
+        | Kind::Amount
+        | Kind::MathConstant
+        | Kind::Str
+        | Kind::Color
+        | Kind::Note => Expr::Prim(Node::new(node.ast_range(), Some(lower_primitive(node)))),
+        Kind::InterpolatedStr => Expr::Interpolated(
+            node.children
+                .iter()
+                .filter_map(|child| match child.kind {
+                    Kind::StrPart => Some(StrPart::Text(lower_string(child.text()))),
+                    Kind::Interpolation => Some(StrPart::Expr(lower_optional_expr(
+                        child
+                            .children
+                            .iter()
+                            .find(|child| child.kind.is_expression()),
+                    ))),
+                    _ => None,
+                })
+                .collect(),
+        ),
+        Kind::ParenExpr => Expr::Paren(lower_optional_expr(
+            node.children.iter().find(|child| child.kind.is_expression()),
+        )),
+        Kind::MemberExpr => Expr::Member(
+            lower_expr(&node.children[0]),
+            node.children_after(Kind::Dot)
+                .find(|child| child.kind == Kind::Ident)
+                .map(lower_identifier)
+                .unwrap_or(Node::MISSING),
+        ),
+        Kind::IndexExpr => Expr::Index(
+            lower_expr(&node.children[0]),
+            lower_optional_expr(
+                node.children_after(Kind::BracketLeft)
+                    .find(|child| child.kind.is_expression()),
+            ),
+        ),
+        Kind::CallExpr => Expr::Call(CallExpr {
+            fun: lower_expr(&node.children[0]),
+            args: node
+                .children_after(Kind::ParenLeft)
+                .filter(|child| child.kind.is_expression())
+                .map(lower_arg)
+                .collect(),
+        }),
+        Kind::ModifierExpr => Expr::Modify(
+            lower_expr(&node.children[0]),
+            lower_modifiers(node, Kind::BracketLeft),
+        ),
+        Kind::SettingsExpr => Expr::Settings(
+            lower_expr(&node.children[0]),
+            lower_modifiers(node, Kind::CurlyLeft),
+        ),
+        Kind::BinaryExpr => Expr::BinOp(
+            lower_expr(&node.children[0]),
+            // (`_ *= 2` is `_ * 2`, see `p_update`)
+            match node.child(Kind::Op).map(|op| op.text()) {
+                Some("+" | "+=") => Op::Add,
+                Some("-" | "-=") => Op::Sub,
+                Some("*" | "*=") => Op::Mul,
+                Some("==") => Op::Eq,
+                Some("!=") => Op::Ne,
+                Some("<") => Op::Lt,
+                Some("<=") => Op::Le,
+                Some(">") => Op::Gt,
+                Some(">=") => Op::Ge,
+                Some("&&") => Op::And,
+                Some("||") => Op::Or,
+                _ => Op::Div,
+            },
+            lower_optional_expr(
+                node.children
+                    .iter()
+                    .skip(1)
+                    .find(|child| child.kind.is_expression()),
+            ),
+        ),
+        Kind::UnaryExpr => Expr::Not(lower_optional_expr(
+            node.children.iter().find(|child| child.kind.is_expression()),
+        )),
+        Kind::IfExpr => {
+            let else_at = node
+                .children
+                .iter()
+                .position(|child| child.kind == Kind::Keyword && child.text() == "else");
+            let (branch, otherwise) = node
+                .children
+                .split_at(else_at.unwrap_or(node.children.len()));
+
+            Expr::If(
+                // (the parser doesn't take a block for the condition)
+                lower_optional_expr(
+                    branch
+                        .iter()
+                        .find(|child| child.kind.is_expression() && child.kind != Kind::Block),
+                ),
+                branch
+                    .iter()
+                    .find(|child| child.kind == Kind::Block)
+                    .map(lower_block)
+                    .unwrap_or(Node::MISSING),
+                else_at.map(|_| {
+                    lower_optional_expr(otherwise.iter().find(|child| child.kind.is_expression()))
+                }),
+            )
+        }
+        Kind::ArrayExpr => Expr::Array(
+            node.children_after(Kind::BracketLeft)
+                .filter(|child| child.kind.is_expression())
+                .map(lower_expr)
+                .collect(),
+        ),
+        Kind::TupleExpr => Expr::Tuple(
+            node.children
+                .iter()
+                .filter(|child| child.kind.is_expression())
+                .map(lower_expr)
+                .collect(),
+        ),
+        Kind::Block => Expr::Block(lower_block(node)),
+        Kind::AnonymousFn => Expr::AnonymousFn(Node::new(
+            node.ast_range(),
+            Some(AnonymousFn {
+                params: lower_params(node),
+                body: lower_optional_expr(
+                    node.children.iter().find(|child| child.kind.is_expression()),
+                ),
+            }),
+        )),
+        _ => unreachable!("not an expression: {:?}", node.kind),
+    };
+
+    Node::new(node.ast_range(), Some(expr))
+}
+
+/// Lowers a call argument, in which `_` is shorthand for the parameter of a function: `xs.map(_ * 2)` is `xs.map(|_| _ * 2)`. (A `_` on its own is passed on, so `xs.filter(f(_, 2))` is `xs.filter(|_| f(_, 2))`.)
+fn lower_arg(node: &SyntaxNode) -> Node<Expr> {
+    let arg = lower_expr(node);
+    if is_placeholder(&arg) || !uses_placeholder(&arg) {
+        return arg;
+    }
+
+    let param = Param {
+        ty: None,
+        name: Node::new(None, Some(Identifier("_".into()))),
+    };
+
+    Node::new(
+        arg.span(),
+        Some(Expr::AnonymousFn(Node::new(
+            arg.span(),
+            Some(AnonymousFn {
+                params: ParamList(vec![Node::new(None, Some(param))]),
+                body: arg,
+            }),
+        ))),
+    )
+}
+
+fn is_placeholder(expr: &Node<Expr>) -> bool {
+    match expr.node.as_deref() {
+        Some(Expr::Var(id)) => id.node.as_deref().is_some_and(|id| id.0 == "_"),
+        _ => false,
+    }
+}
+
+/// Whether `_` is used in an expression (but not in the blocks or functions in it, which have their own scope)
+fn uses_placeholder(expr: &Node<Expr>) -> bool {
+    let any = |exprs: &[Node<Expr>]| exprs.iter().any(uses_placeholder);
+    let modifiers = |modifiers: &[Modifier]| {
+        modifiers.iter().any(|modifier| match modifier {
+            Modifier::Arg(value) | Modifier::Setting(_, value) => uses_placeholder(value),
+        })
+    };
+
+    match expr.node.as_deref() {
+        Some(Expr::Var(_)) => is_placeholder(expr),
+        Some(Expr::Call(call)) => uses_placeholder(&call.fun) || any(&call.args),
+        Some(Expr::BinOp(a, _, b) | Expr::Index(a, b)) => {
+            uses_placeholder(a) || uses_placeholder(b)
+        }
+        Some(Expr::Paren(a) | Expr::Member(a, _) | Expr::Not(a)) => uses_placeholder(a),
+        // (and the condition of an `else if`)
+        Some(Expr::If(cond, _, otherwise)) => {
+            uses_placeholder(cond) || otherwise.as_ref().is_some_and(uses_placeholder)
+        }
+        Some(Expr::Modify(a, m) | Expr::Settings(a, m)) => uses_placeholder(a) || modifiers(m),
+        Some(Expr::Array(items) | Expr::Tuple(items)) => any(items),
+        Some(Expr::Interpolated(parts)) => parts.iter().any(|part| match part {
+            StrPart::Expr(expr) => uses_placeholder(expr),
+            StrPart::Text(_) => false,
+        }),
+        Some(Expr::Prim(_) | Expr::Block(_) | Expr::AnonymousFn(_)) | None => false,
+    }
+}
+
+/// The modifiers (or settings) after the `open`ing bracket
+fn lower_modifiers(node: &SyntaxNode, open: Kind) -> Vec<Modifier> {
+    node.children_after(open)
+        .filter_map(|child| match child.kind {
+            Kind::Setting => Some(Modifier::Setting(
+                child
+                    .child(Kind::Ident)
+                    .map(lower_identifier)
+                    .unwrap_or(Node::MISSING),
+                lower_optional_expr(
+                    child
+                        .children_after(Kind::Eq)
+                        .find(|child| child.kind.is_expression()),
+                ),
+            )),
+            kind if kind.is_expression() => Some(Modifier::Arg(lower_expr(child))),
+            _ => None,
+        })
+        .collect()
+}
+
+fn lower_optional_expr(node: Option<&SyntaxNode>) -> Node<Expr> {
+    node.map(lower_expr).unwrap_or(Node::MISSING)
+}
+
+pub fn lower_param(node: &SyntaxNode) -> Node<Param> {
+    let mut idents = node.children_of_kind(Kind::Ident).map(lower_identifier);
+
+    let param = match (idents.next(), idents.next()) {
+        (Some(ty), Some(name)) => Param { ty: Some(ty), name },
+        (Some(name), None) => Param { ty: None, name },
+        _ => Param {
+            ty: None,
+            name: Node::MISSING,
+        },
+    };
+
+    Node::new(node.ast_range(), Some(param))
+}
+
+fn lower_params(node: &SyntaxNode) -> ParamList {
+    ParamList(node.children_of_kind(Kind::Param).map(lower_param).collect())
+}
+
+pub fn lower_block(node: &SyntaxNode) -> Node<Block> {
+    let mut block = Block {
+        stmts: vec![],
+        expr: None,
+    };
+
+    // (the same logic as what reports the "missing `;`" errors while parsing)
+    for child in node.children.iter() {
+        let is_item =
+            child.kind.is_statement() || child.kind.is_expression() || child.kind == Kind::FnDecl;
+
+        if is_item && let Some(expr) = block.expr.take() {
+            block.stmts.push(Stmt::Expr(expr));
+        }
+
+        if child.kind.is_statement() {
+            block.stmts.push(lower_stmt(child));
+        } else if child.kind == Kind::FnDecl {
+            block.stmts.push(Stmt::Decl(lower_decl(child)));
+        } else if child.kind.is_expression() {
+            block.expr = Some(lower_expr(child));
+        } else if child.kind == Kind::Semi && let Some(expr) = block.expr.take() {
+            block.stmts.push(Stmt::Expr(expr));
+        }
+    }
+
+    Node::new(node.ast_range(), Some(block))
+}
+
+pub fn lower_decl(node: &SyntaxNode) -> Node<Decl> {
+    let fn_decl = FnDecl {
+        name: node
+            .name_after_keyword()
+            .map(lower_identifier)
+            .unwrap_or(Node::MISSING),
+        params: lower_params(node),
+        body: node
+            .child(Kind::Block)
+            .map(lower_block)
+            .unwrap_or(Node::MISSING),
+    };
+
+    Node::new(
+        node.ast_range(),
+        Some(Decl::FnDecl(Node::new(node.ast_range(), Some(fn_decl)))),
+    )
+}
+
+/// Lowers a bare statement (let, return, play)
+pub fn lower_stmt(node: &SyntaxNode) -> Stmt {
+    match node.kind {
+        Kind::LetStmt => {
+            let name = node.name_after_keyword();
+
+            // the expression is whatever comes after the name (which can be an identifier too)
+            let expr = node
+                .children_after(Kind::Keyword)
+                .filter(|child| child.kind.is_expression())
+                .find(|child| !name.is_some_and(|name| std::ptr::eq(*child, name)));
+
+            Stmt::Let((
+                name.map(lower_identifier).unwrap_or(Node::MISSING),
+                lower_optional_expr(expr),
+            ))
+        }
+        Kind::ReturnStmt => Stmt::Return(
+            node.children
+                .iter()
+                .find(|child| child.kind.is_expression())
+                .map(lower_expr),
+        ),
+        Kind::PlayStmt => Stmt::Play(lower_optional_expr(
+            node.children.iter().find(|child| child.kind.is_expression()),
+        )),
+        _ => unreachable!("not a statement: {:?}", node.kind),
+    }
+}
+
+pub fn lower_document(node: &SyntaxNode) -> Document {
+    let stmts = node
+        .children
+        .iter()
+        .filter_map(|child| {
+            if child.kind.is_statement() {
+                Some(lower_stmt(child))
+            } else if child.kind == Kind::FnDecl {
+                Some(Stmt::Decl(lower_decl(child)))
+            } else if child.kind.is_expression() {
+                Some(Stmt::Expr(lower_expr(child)))
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    Document { stmts }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{assert_matches, fmt::Debug};
+
+    use nom::{combinator::map, sequence::tuple, Parser};
+
+    use super::super::*;
+    use super::*;
+
+    fn debug<T: Debug>(x: T) -> String {
+        format!("{:?}", x)
+    }
+
+    fn lower_debug(node: &SyntaxNode) -> String {
+        match node.kind {
+            Kind::Document => debug(lower_document(node)),
+            Kind::FnDecl => debug(lower_decl(node)),
+            Kind::Param => debug(lower_param(node)),
+            kind if kind.is_statement() => debug(lower_stmt(node)),
+            kind if kind.is_expression() => debug(lower_expr(node)),
+            kind => panic!("can't lower {:?}", kind),
+        }
+    }
+
+    type NomError<'a> = nom::error::Error<Span<'a>>;
+
+    /// Parses (ignoring surrounding whitespace), lowers, and debug-prints the result
+    fn parse_debug<'a>(
+        parser: impl Parser<Span<'a>, SyntaxNode<'a>, NomError<'a>>,
+        str: &'a str,
+    ) -> Result<(&'a str, String, Vec<String>), nom::Err<NomError<'a>>> {
+        test_parse(
+            map(tuple((p_ws0, parser, p_ws0)), |(_, node, _)| lower_debug(&node)),
+            str,
+        )
+        .map(|(rem, res, errs)| {
+            (
+                rem,
+                res,
+                errs.into_iter().map(|err| err.1).collect::<Vec<_>>(),
+            )
+        })
+    }
+
+    fn parse_expr(str: &str) -> ast::SyntaxNode<Expr> {
+        lower_expr(&test_parse(p_expression, str).unwrap().1)
+    }
+
+    #[test]
+    fn test_expr_errors() {
+        assert_eq!(
+            parse_debug(p_expression, "(123)!",),
+            Ok(("!", "(123)".into(), vec![],))
+        );
+
+        assert_eq!(
+            parse_debug(p_expression, "(123!",),
+            Ok(("!", "(123)".into(), vec!["missing `)`".into()]))
+        );
+
+        assert_eq!(
+            parse_debug(p_expression, "(123 + 456!",),
+            Ok(("!", "((123 + 456))".into(), vec!["missing `)`".into()]))
+        );
+
+        assert_eq!(
+            parse_debug(p_expression, "123 + ()!",),
+            Ok((
+                "!",
+                "(123 + (<MISSING>))".into(),
+                vec!["expected expression after `(`".into()]
+            ))
+        );
+
+        assert_eq!(
+            format!("{:?}", Expr::Prim(Primitive::Str("kelley".into()).into())),
+            r#""kelley""#
+        );
+
+        assert_eq!(
+            parse_debug(p_expression, r#""hello" "#,),
+            Ok(("", r#""hello""#.into(), vec![]))
+        );
+
+        assert_eq!(
+            parse_debug(p_expression, r#""hello "#,),
+            Ok((
+                "",
+                r#""hello ""#.into(),
+                vec!["expected closing quote for string".into()]
+            ))
+        );
+
+        assert_eq!(
+            parse_debug(p_expression, r#""bla/bla" "#,),
+            Ok(("", r#""bla/bla""#.into(), vec![]))
+        );
+        assert_eq!(
+            parse_debug(
+                p_expression,
+                r#""bla/bla
+bla" "#,
+            ),
+            Ok(("", "\"bla/bla\nbla\"".into(), vec![]))
+        );
+        assert_eq!(
+            parse_debug(
+                p_expression,
+                r#""/Users/kelley/emp/2022-11 Blabl Project/Samples/Processed/Freeze/Freeze RES [2022-11-23 221454].wav""#,
+            ),
+            Ok(("", "\"/Users/kelley/emp/2022-11 Blabl Project/Samples/Processed/Freeze/Freeze RES [2022-11-23 221454].wav\"".into(), vec![]))
+        );
+    }
+
+    #[test]
+    fn test_anonymous_fn() {
+        assert_eq!(
+            parse_debug(p_expression, "|| 5"),
+            Ok(("", "|| 5".into(), vec![]))
+        );
+
+        assert_eq!(
+            parse_debug(p_expression, "||",),
+            Ok((
+                "",
+                "|| <MISSING>".into(),
+                vec!["expected anonymous function body".into()]
+            ))
+        );
+
+        // (`_` is shorthand for a function's parameter)
+        assert_eq!(
+            parse_debug(p_expression, "xs.map(_ * .2s).filter(f(_, 2)) "),
+            Ok((
+                "",
+                "xs.map(|_| _ * 0.2s).filter(|_| f(_, 2))".into(),
+                vec![]
+            ))
+        );
+        assert_eq!(
+            parse_debug(p_expression, "xs.map(_ *= .2s) "),
+            Ok(("", "xs.map(|_| _ * 0.2s)".into(), vec![]))
+        );
+        assert_eq!(
+            parse_debug(p_expression, "xs.map(|x| _) "),
+            Ok(("", "xs.map(|x| _)".into(), vec![]))
+        );
+    }
+
+    #[test]
+    fn test_expr_factor() {
+        assert_eq!(parse_debug(p_factor, "  3  "), Ok(("", "3".into(), vec![])));
+
+        assert_eq!(
+            parse_debug(p_usage, "kelley.bla "),
+            Ok(("", "kelley.bla".into(), vec![]))
+        );
+
+        assert_eq!(
+            parse_debug(p_usage, "kelley[bla] "),
+            Ok(("", "kelley[bla]".into(), vec![]))
+        );
+
+        assert_eq!(
+            parse_debug(p_usage, "kelley(bla, 123) "),
+            Ok(("", "kelley(bla, 123)".into(), vec![]))
+        );
+
+        assert_eq!(
+            parse_debug(p_usage, "kelley(bla, 123)[bla] "),
+            Ok(("", "kelley(bla, 123)[bla]".into(), vec![]))
+        );
+
+        assert_eq!(
+            parse_debug(p_expression, "midi_in * bla  * bla(  4, 6) "),
+            Ok(("", "((midi_in * bla) * bla(4, 6))".into(), vec![]))
+        );
+
+        assert_eq!(
+            parse_debug(p_usage, "kick %[ rate=1.5,pitch = -2, 3 ] * 2 "),
+            Ok(("* 2 ", "kick%[rate = 1.5, pitch = -2, 3]".into(), vec![]))
+        );
+
+        assert_eq!(
+            parse_debug(p_expression, r#""kicks/\$${ name }_${i + 1}.wav""#),
+            Ok(("", r#""kicks/$${name}_${(i + 1)}.wav""#.into(), vec![]))
+        );
+
+        assert_eq!(
+            parse_debug(p_usage, "[ (1, 1) ,(3,.5), (5,) ] "),
+            Ok(("", "[(1, 1), (3, 0.5), (5,)]".into(), vec![]))
+        );
+
+        assert_eq!(
+            parse_debug(p_usage, "lowpass{ f=sin(4hz) ,q = 2 }(x) "),
+            Ok(("", "lowpass{f = sin(4hz), q = 2}(x)".into(), vec![]))
+        );
+    }
+
+    #[test]
+    fn test_term() {
+        assert_eq!(
+            parse_debug(p_term, " 3 *  5   ",),
+            Ok(("", "(3 * 5)".into(), vec![]))
+        );
+        assert_eq!(
+            parse_debug(p_term, " 3 *  5hz   ",),
+            Ok(("", "(3 * 5hz)".into(), vec![]))
+        );
+    }
+
+    #[test]
+    fn test_expr() {
+        assert_eq!(
+            parse_debug(p_expression, " 1 + 2 *  3 ",),
+            Ok(("", "(1 + (2 * 3))".into(), vec![]))
+        );
+        assert_eq!(
+            parse_debug(p_expression, " 1 + 2 hz *  3 / 4 - 5 ",),
+            Ok(("", "((1 + ((2hz * 3) / 4)) - 5)".into(), vec![]))
+        );
+        assert_eq!(
+            parse_debug(p_expression, " 72 / 2 / 3 ",),
+            Ok(("", "((72 / 2) / 3)".into(), vec![]))
+        );
+        assert_eq!(
+            parse_debug(p_expression, " !a || b < 2 && c != 1 ",),
+            Ok(("", "(!a || ((b < 2) && (c != 1)))".into(), vec![]))
+        );
+        assert_eq!(
+            parse_debug(p_expression, " if x >= 1 { a } else if y { b } else { c } ",),
+            Ok((
+                "",
+                "if (x >= 1) { a } else if y { b } else { c }".into(),
+                vec![]
+            ))
+        );
+        assert_eq!(
+            parse_debug(p_expression, " if { a } ",),
+            Ok((
+                "",
+                "if <MISSING> { a }".into(),
+                vec!["expected condition after `if`".into()]
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parens() {
+        assert_eq!(
+            parse_debug(p_expression, " ( 1.2s + (2) ) *  3 ",),
+            Ok(("", "(((1.2s + (2))) * 3)".into(), vec![]))
+        );
+    }
+
+    #[test]
+    fn test_block_expr() {
+        assert_eq!(
+            parse_debug(p_expression, " ( 1.2s + { let x = 2; 5; x + 1 } ) *  3 ",),
+            Ok((
+                "",
+                "(((1.2s + { let x = 2; 5; (x + 1) })) * 3)".into(),
+                vec![]
+            ))
+        );
+
+        assert_eq!(
+            parse_debug(p_expression, " ( 1.2s + { let x = 2; 5; x + 1; } ) *  3 ",),
+            Ok((
+                "",
+                "(((1.2s + { let x = 2; 5; (x + 1); })) * 3)".into(),
+                vec![]
+            ))
+        );
+
+        assert_eq!(
+            parse_debug(p_expression, " ( 1.2s + { let x = 2; 5; x + 1;  ) *  3 ",),
+            Ok((
+                "",
+                "(((1.2s + { let x = 2; 5; (x + 1); })) * 3)".into(),
+                vec!["missing `}`".into()]
+            ))
+        );
+
+        assert_eq!(
+            parse_debug(p_statement_bare, "let x = a(2, 3;more",),
+            Ok((
+                ";more",
+                "let x = a(2, 3);".into(),
+                vec!["expected closing `)`".into()]
+            ))
+        );
+
+        assert_matches!(parse_debug(p_statement_bare, "lets"), Err(_));
+    }
+
+    #[test]
+    fn test_fn_expr() {
+        assert_eq!(
+            parse_debug(p_param, "osc s, "),
+            Ok((", ", "osc s".into(), vec![]))
+        );
+
+        assert_eq!(
+            parse_debug(p_expression, "|osc s| s + 5hz?!",),
+            Ok(("?!", "|osc s| (s + 5hz)".into(), vec![]))
+        );
+
+        assert_eq!(
+            parse_debug(p_expression, "|osc s| { s + 5hz }?!",),
+            Ok(("?!", "|osc s| { (s + 5hz) }".into(), vec![]))
+        );
+
+        assert_eq!(
+            parse_debug(p_statement_bare, "let x = |osc s| { s + 5hz };?!",),
+            Ok((";?!", "let x = |osc s| { (s + 5hz) };".into(), vec![]))
+        );
+
+        assert_eq!(
+            parse_debug(p_statement_bare, "let = |osc s| { s + 5hz };?!",),
+            Ok((
+                ";?!",
+                "let <MISSING> = |osc s| { (s + 5hz) };".into(),
+                vec!["missing let identifier".into()]
+            ))
+        );
+
+        assert_eq!(
+            parse_debug(p_statement_bare, "let xyz =  ?!",),
+            Ok((
+                "?!",
+                "let xyz = <MISSING>;".into(),
+                vec!["missing let expression".into()]
+            ))
+        );
+
+        assert_eq!(
+            parse_debug(p_statement_bare, "let xyz; let a = 4",),
+            Ok((
+                "; let a = 4",
+                "let xyz = <MISSING>;".into(),
+                vec!["missing `=`".into(), "missing let expression".into()]
+            ))
+        );
+
+        assert_eq!(
+            parse_debug(p_statement_bare, "let x = { a b }",),
+            Ok(("", "let x = { a; b };".into(), vec!["missing `;`".into()]))
+        );
+
+        assert_eq!(
+            parse_debug(p_statement_bare, "let xyz 234; let a = 4",),
+            Ok((
+                "; let a = 4",
+                "let xyz = 234;".into(),
+                vec!["missing `=`".into()]
+            ))
+        );
+
+        assert_eq!(
+            parse_debug(p_statement_bare, "let = let a=b; let xyz=23;",),
+            Ok((
+                "let a=b; let xyz=23;",
+                "let <MISSING> = <MISSING>;".into(),
+                vec![
+                    "missing let identifier".into(),
+                    "missing let expression".into()
+                ]
+            ))
+        );
+    }
+
+    #[test]
+    fn test_expr_syntax_1() {
+        let p = parse_expr("4 + 12");
+        assert_eq!(p.range(), Some(0..6));
+        assert!(match p {
+            ast::SyntaxNode {
+                node: Some(box Expr::BinOp(a, Op::Add, b)),
+                ..
+            } => {
+                assert_eq!(a.range(), Some(0..1));
+                assert_eq!(b.range(), Some(4..6));
+                true
+            }
+            _ => false,
+        });
+    }
+
+    #[test]
+    fn test_expr_syntax_2() {
+        let p = parse_expr("4 + 12 * 13 + 1");
+        assert_eq!(p.range(), Some(0..15));
+        assert_eq!(format!("{:?}", p), "((4 + (12 * 13)) + 1)");
+        assert!(match p {
+            ast::SyntaxNode {
+                node: Some(box Expr::BinOp(a, Op::Add, b)),
+                ..
+            } => {
+                assert_eq!(a.range(), Some(0..11));
+                assert!(match a {
+                    ast::SyntaxNode {
+                        node: Some(box Expr::BinOp(a, Op::Add, b)),
+                        ..
+                    } => {
+                        assert_eq!(a.range(), Some(0..1));
+                        assert_eq!(b.range(), Some(4..11));
+                        assert!(match b {
+                            ast::SyntaxNode {
+                                node: Some(box Expr::BinOp(a, Op::Mul, b)),
+                                ..
+                            } => {
+                                assert_eq!(a.range(), Some(4..6));
+                                assert_eq!(b.range(), Some(9..11));
+                                true
+                            }
+                            _ => false,
+                        });
+
+                        true
+                    }
+                    _ => false,
+                });
+
+                assert_eq!(b.range(), Some(14..15));
+                true
+            }
+            _ => false,
+        });
+    }
+
+    #[test]
+    fn test_stmts() {
+        assert_eq!(
+            parse_debug(p_statement_bare, "return 26; }",),
+            Ok(("; }", "return 26;".into(), vec![]))
+        );
+
+        assert_matches!(parse_debug(p_statement_bare, "return26;"), Err(_));
+
+        assert_eq!(
+            parse_debug(p_statement_bare, "return; }",),
+            Ok(("; }", "return;".into(), vec![]))
+        );
+
+        assert_eq!(
+            parse_debug(p_statement_bare, "return ; }",),
+            Ok(("; }", "return;".into(), vec![]))
+        );
+
+        assert_eq!(
+            parse_debug(p_statement_bare, "let x= (26 * 1hz); }",),
+            Ok(("; }", "let x = ((26 * 1hz));".into(), vec![]))
+        );
+        assert_eq!(
+            parse_debug(p_declaration, "fn add( int x, wave bla) { 5 }?",),
+            Ok(("?", "fn add(int x, wave bla) { 5 }".into(), vec![]))
+        );
+        assert_eq!(
+            parse_debug(p_declaration, "fn add( int x, wave bla, ) { 5 }?",),
+            Ok(("?", "fn add(int x, wave bla) { 5 }".into(), vec![]))
+        );
+
+        assert_eq!(
+            parse_debug(p_statement_bare, "play ?",),
+            Ok((
+                "?",
+                "play <MISSING>;".into(),
+                vec!["missing play expression".into()]
+            ))
+        );
+
+        assert_eq!(
+            parse_debug(p_declaration, "fn () { 5",),
+            Ok((
+                "",
+                "fn <MISSING>() { 5 }".into(),
+                vec!["expected function name".into(), "missing `}`".into()]
+            ))
+        );
+
+        assert_eq!(
+            parse_debug(p_declaration, "fn ( { 5 let h = 6"),
+            Ok((
+                "",
+                "fn <MISSING>() { 5; let h = 6; }".into(),
+                vec![
+                    "expected function name".into(),
+                    "expected function parameters closing `)`".into(),
+                    "missing `;`".into(),
+                    "missing `;`".into(),
+                    "missing `}`".into(),
+                ]
+            ))
+        );
+
+        assert_eq!(
+            parse_debug(p_declaration, "fn { 5; let h = 6"),
+            Ok((
+                "",
+                "fn <MISSING>() { 5; let h = 6; }".into(),
+                vec![
+                    "expected function name".into(),
+                    "expected function parameters opening `(`".into(),
+                    "expected function parameters closing `)`".into(),
+                    "missing `;`".into(),
+                    "missing `}`".into(),
+                ]
+            ))
+        );
+
+        assert_eq!(
+            parse_debug(p_declaration, "fn "),
+            Ok((
+                "",
+                "fn <MISSING>() <MISSING>".into(),
+                vec![
+                    "expected function name".into(),
+                    "expected function parameters opening `(`".into(),
+                    "expected function parameters closing `)`".into(),
+                    "expected function body".into(),
+                ]
+            ))
+        );
+
+        assert_eq!(
+            parse_debug(p_declaration, "fn ;"),
+            Ok((
+                ";",
+                "fn <MISSING>() <MISSING>".into(),
+                vec![
+                    "expected function name".into(),
+                    "expected function parameters opening `(`".into(),
+                    "expected function parameters closing `)`".into(),
+                    "expected function body".into(),
+                ]
+            ))
+        );
+
+        assert_eq!(
+            parse_debug(p_declaration, "fn );"),
+            Ok((
+                ";",
+                "fn <MISSING>() <MISSING>".into(),
+                vec![
+                    "expected function name".into(),
+                    "expected function parameters opening `(`".into(),
+                    "expected function body".into(),
+                ]
+            ))
+        );
+
+        assert_eq!(
+            parse_debug(p_declaration, "fn )};"),
+            Ok((
+                "};",
+                "fn <MISSING>() <MISSING>".into(),
+                vec![
+                    "expected function name".into(),
+                    "expected function parameters opening `(`".into(),
+                    "expected function body".into(),
+                ]
+            ))
+        );
+
+        assert_eq!(
+            parse_debug(p_declaration, "fn ){;"),
+            Ok((
+                "",
+                "fn <MISSING>() { }".into(),
+                vec![
+                    "expected function name".into(),
+                    "expected function parameters opening `(`".into(),
+                    "missing `}`".into(),
+                ]
+            ))
+        );
+
+        assert_matches!(parse_debug(p_declaration, "fn){;"), Err(_));
+    }
+
+    #[test]
+    fn test_document() {
+        assert_eq!(
+            parse_debug(
+                p_document,
+                "fn { 5; let h = 6 }}; let h = 6;; 123 *68 play 6;",
+            ),
+            Ok((
+                "",
+                [
+                    "fn <MISSING>() { 5; let h = 6; }",
+                    "let h = 6;",
+                    "(123 * 68);",
+                    "play 6;",
+                ]
+                .join("\n\n"),
+                vec![
+                    "expected function name".into(),
+                    "expected function parameters opening `(`".into(),
+                    "expected function parameters closing `)`".into(),
+                    "missing `;`".into(),
+                    "missing `;`".into(),
+                ],
+            )),
+        );
+    }
+}
@@ -1,75 +1,97 @@
-use core::f64;
 use std::{
-    assert_matches::assert_matches,
-    cell::RefCell,
-    f64::consts::{PI, TAU},
+    cell::{Cell, RefCell},
     fmt::Write,
-    ops::Range,
-    path::Iter,
-    sync::Arc,
-    vec,
+    rc::Rc,
 };
 
+#[cfg(test)]
+use std::assert_matches;
+
 use nom::{
     branch::*,
     bytes::complete::*,
     character::complete::{char, *},
-    combinator::{cut, map, opt, peek, recognize, value, verify},
-    error,
-    multi::{many0, many1, separated_list0},
-    sequence::{delimited, pair, preceded, terminated, tuple},
+    combinator::{cut, map, not, opt, peek, recognize, verify},
+    multi::{many0, many1},
+    sequence::{preceded, terminated, tuple},
     IResult, Offset, Parser, Slice,
 };
-use nom_locate::position;
+
+use crate::span::{Loc, SourceSpan};
+
+pub mod docs;
+pub mod format;
+pub mod incremental;
+pub mod lint;
+pub mod lower;
+pub mod outline;
+pub mod refactor;
 
 /// Error containing a text span and an error message to display.
 #[derive(Debug, Clone, PartialEq)]
-pub struct ParseError(pub SpanRange, pub String);
+pub struct ParseError(pub SourceSpan, pub String);
 
 /// Carried around in the `LocatedSpan::extra` field in
 /// between `nom` parsers.
 #[derive(Clone, Debug)]
-pub struct ParseState(pub Arc<RefCell<Vec<ParseError>>>);
+pub struct ParseState(pub Rc<RefCell<Vec<ParseError>>>, Rc<Cell<usize>>);
 
 impl ParseState {
+    pub fn new(errors: Rc<RefCell<Vec<ParseError>>>) -> Self {
+        Self(errors, Rc::new(Cell::new(0)))
+    }
+
     /// Pushes an error onto the errors stack from within a `nom`
     /// parser combinator while still allowing parsing to continue.
     #[allow(unused)]
     pub fn report_error(&self, error: ParseError) {
         self.0.borrow_mut().push(error);
     }
-}
 
-pub type Span<'a> = nom_locate::LocatedSpan<&'a str, ParseState>;
+    /// How many errors have been reported so far, to `rewind` to when something is parsed again in a different way
+    fn checkpoint(&self) -> usize {
+        self.0.borrow().len()
+    }
 
-pub type ParseResult<'a, T> = nom::IResult<Span<'a>, T>;
+    fn rewind(&self, checkpoint: usize) {
+        self.0.borrow_mut().truncate(checkpoint);
+    }
+
+    /// Notes that the parser looked at the source up until here, to decide how to parse something that ends before it (which the incremental parser needs to know)
+    fn looked_at(&self, offset: usize) {
+        self.1.set(self.1.get().max(offset));
+    }
 
-pub fn span_range(span: &Span) -> SpanRange {
-    SpanRange {
-        start: Loc(span.location_offset()),
-        end: Loc(span.location_offset() + span.len()),
+    /// How far the parser looked since the last time this was asked
+    pub(crate) fn take_looked_at(&self) -> usize {
+        self.1.take()
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-struct Loc(usize); // TODO: text offset + (col, row). For now: just text offset
+pub type Span<'a> = nom_locate::LocatedSpan<&'a str, ParseState>;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-pub struct SpanRange {
-    start: Loc,
-    end: Loc,
-}
+pub type ParseResult<'a, T> = nom::IResult<Span<'a>, T>;
+
+pub fn source_span(span: &Span) -> SourceSpan {
+    let start = Loc {
+        offset: span.location_offset(),
+        line: span.location_line() as usize,
+        col: span.get_utf8_column(),
+    };
 
-fn cover_ranges(a: SpanRange, b: SpanRange) -> SpanRange {
-    SpanRange {
-        start: Loc(a.start.0.min(b.start.0)),
-        end: Loc(a.end.0.min(b.end.0)),
+    SourceSpan {
+        start,
+        end: start.after(span.fragment()),
     }
 }
 
 /// Evaluate `parser` and wrap the result in a `Some(_)`. Otherwise,
 /// emit the  provided `error_msg` and return a `None` while allowing
 /// parsing to continue.
+///
+/// (Unlike the original version, this continues from where the parser
+/// started, not from where it failed, so that no input gets lost from
+/// the tree.)
 fn expecting<'a, F, E, T>(
     mut parser: F,
     error_msg: E,
@@ -78,12 +100,11 @@ where
     F: FnMut(Span<'a>) -> ParseResult<T>,
     E: ToString,
 {
-    move |input: Span| {
-        match parser.parse(input) {
+    move |input: Span<'a>| {
+        match parser.parse(input.clone()) {
             Ok((remaining, out)) => Ok((remaining, Some(out))),
-            Err(nom::Err::Error(nom::error::Error { input, .. }))
-            | Err(nom::Err::Failure(nom::error::Error { input, .. })) => {
-                let err = ParseError(span_range(&input), error_msg.to_string());
+            Err(nom::Err::Error(_)) | Err(nom::Err::Failure(_)) => {
+                let err = ParseError(source_span(&input), error_msg.to_string());
                 input.extra.report_error(err); // Push error onto stack.
                 Ok((input, None)) // Parsing failed, but keep going.
             }
@@ -117,8 +138,17 @@ pub enum Kind {
     Amount,
     Unit,
     Str,
+    // `#ff8800`
+    Color,
+    // `c3`, `a#4`
+    Note,
+    // the text in between the `${..}`s of an interpolated string, and its quotes
+    StrPart,
+    Quote,
+    DollarCurly,
 
     Ident,
+    WidgetRef,
 
     ParenLeft,
     ParenRight,
@@ -131,17 +161,90 @@ pub enum Kind {
 
     Dot,
     Comma,
+    Semi,
+    Eq,
+    Pipe,
+    Percent,
+
+    Keyword,
+
+    // characters that we couldn't make any sense of, at the top level
+    Skipped,
+
+    // a `/// ..` line right before a definition (which it's part of), documenting it
+    DocComment,
 
     ParenExpr,
     MemberExpr,
     IndexExpr,
     CallExpr,
+    BinaryExpr,
+    // `!done`
+    UnaryExpr,
+    // `if cond { .. } else { .. }`
+    IfExpr,
+    ModifierExpr,
+    SettingsExpr,
+    // `[1, 2, 3]` and `(1, .5)`
+    ArrayExpr,
+    TupleExpr,
+    // `"kicks/${name}.wav"`, and the `${name}` in it
+    InterpolatedStr,
+    Interpolation,
+    Block,
+    AnonymousFn,
+    Param,
+    // `name = value`, in modifiers or settings
+    Setting,
+
+    LetStmt,
+    ReturnStmt,
+    PlayStmt,
+    FnDecl,
+
+    Document,
+}
+
+impl Kind {
+    pub fn is_expression(&self) -> bool {
+        matches!(
+            self,
+            Kind::Bool
+                | Kind::MathConstant
+                | Kind::Num
+                | Kind::Amount
+                | Kind::Str
+                | Kind::Color
+                | Kind::Note
+                | Kind::InterpolatedStr
+                | Kind::Ident
+                | Kind::WidgetRef
+                | Kind::ParenExpr
+                | Kind::MemberExpr
+                | Kind::IndexExpr
+                | Kind::CallExpr
+                | Kind::BinaryExpr
+                | Kind::UnaryExpr
+                | Kind::IfExpr
+                | Kind::ModifierExpr
+                | Kind::SettingsExpr
+                | Kind::ArrayExpr
+                | Kind::TupleExpr
+                | Kind::Block
+                | Kind::AnonymousFn
+        )
+    }
+
+    /// (Bare) statements, i.e. the ones that need a `;` after them
+    pub fn is_statement(&self) -> bool {
+        matches!(self, Kind::LetStmt | Kind::ReturnStmt | Kind::PlayStmt)
+    }
 }
 
 #[derive(Clone, PartialEq, Eq)]
 pub struct SyntaxNode<'a> {
     kind: Kind,
-    range: SpanRange,
+    range: SourceSpan,
 
     // either this (leaf)
     fragment: Option<&'a str>,
@@ -187,40 +290,43 @@ where
     }
 }
 
-impl<'a, C, D> CollectibleNodes<'a> for (C, D)
-where
-    C: CollectibleNodes<'a> + Sized,
-    D: CollectibleNodes<'a> + Sized,
-{
-    fn collect_into(self, nodes: &mut Vec<SyntaxNode<'a>>) {
-        self.0.collect_into(nodes);
-        self.1.collect_into(nodes);
-    }
+macro_rules! impl_collectible_tuple {
+    ($($item:ident),+) => {
+        impl<'a, $($item),+> CollectibleNodes<'a> for ($($item,)+)
+        where
+            $($item: CollectibleNodes<'a> + Sized,)+
+        {
+            #[allow(non_snake_case)]
+            fn collect_into(self, nodes: &mut Vec<SyntaxNode<'a>>) {
+                let ($($item,)+) = self;
+                $($item.collect_into(nodes);)+
+            }
+        }
+    };
 }
 
-impl<'a, C, D, E> CollectibleNodes<'a> for (C, D, E)
-where
-    C: CollectibleNodes<'a> + Sized,
-    D: CollectibleNodes<'a> + Sized,
-    E: CollectibleNodes<'a> + Sized,
-{
-    fn collect_into(self, nodes: &mut Vec<SyntaxNode<'a>>) {
-        self.0.collect_into(nodes);
-        self.1.collect_into(nodes);
-    }
-}
+impl_collectible_tuple!(C, D);
+impl_collectible_tuple!(C, D, E);
+impl_collectible_tuple!(C, D, E, F);
+impl_collectible_tuple!(C, D, E, F, G);
+impl_collectible_tuple!(C, D, E, F, G, H);
+impl_collectible_tuple!(C, D, E, F, G, H, I);
+impl_collectible_tuple!(C, D, E, F, G, H, I, J);
+impl_collectible_tuple!(C, D, E, F, G, H, I, J, K);
+impl_collectible_tuple!(C, D, E, F, G, H, I, J, K, L);
+impl_collectible_tuple!(C, D, E, F, G, H, I, J, K, L, M);
 
 impl<'a> SyntaxNode<'a> {
     pub fn leaf(kind: Kind, span: Span<'a>) -> Self {
         Self {
             kind,
-            range: span_range(&span),
+            range: source_span(&span),
             fragment: Some(span.fragment()),
             children: vec![],
         }
     }
 
-    pub fn new(kind: Kind, range: SpanRange) -> Self {
+    pub fn new(kind: Kind, range: SourceSpan) -> Self {
         Self {
             kind,
             range,
@@ -229,8 +335,13 @@ impl<'a> SyntaxNode<'a> {
         }
     }
 
+    /// A parent node, covering the given span
+    pub fn parent(kind: Kind, span: Span<'a>) -> Self {
+        Self::new(kind, source_span(&span))
+    }
+
     pub fn empty(&self) -> bool {
-        self.range.end.0 == self.range.start.0
+        self.range.is_empty()
     }
 
     fn with_collect_children<I>(mut self, collect: I) -> Self
@@ -279,6 +390,37 @@ impl<'a> SyntaxNode<'a> {
 
         str
     }
+
+    fn text(&self) -> &'a str {
+        self.fragment.unwrap_or("")
+    }
+
+    fn ast_range(&self) -> Option<SourceSpan> {
+        Some(self.range)
+    }
+
+    fn child(&self, kind: Kind) -> Option<&SyntaxNode<'a>> {
+        self.children.iter().find(|child| child.kind == kind)
+    }
+
+    fn children_of_kind(&self, kind: Kind) -> impl Iterator<Item = &SyntaxNode<'a>> {
+        self.children.iter().filter(move |child| child.kind == kind)
+    }
+
+    /// The children following the first child of the given kind
+    fn children_after(&self, kind: Kind) -> impl Iterator<Item = &SyntaxNode<'a>> {
+        self.children
+            .iter()
+            .skip_while(move |child| child.kind != kind)
+            .skip(1)
+    }
+
+    /// The first "meaningful" child after the keyword, that is, if it's an identifier
+    fn name_after_keyword(&self) -> Option<&SyntaxNode<'a>> {
+        self.children_after(Kind::Keyword)
+            .find(|child| child.kind != Kind::Ws)
+            .filter(|child| child.kind == Kind::Ident)
+    }
 }
 
 impl<'a> std::fmt::Debug for SyntaxNode<'a> {
@@ -321,27 +463,50 @@ where
     }
 }
 
-fn p_ws0(input: Span) -> ParseResult<SyntaxNode> {
-    map(multispace0, |span| SyntaxNode::leaf(Kind::Ws, span)).parse(input)
+/// A `// ..` comment, up to (but not including) the end of the line (except for doc comments, which belong to the definition after them)
+fn line_comment(input: Span) -> IResult<Span, Span> {
+    preceded(
+        not(p_doc_comments),
+        recognize(tuple((tag("//"), not_line_ending))),
+    )
+    .parse(input)
 }
 
-fn p_ws1(input: Span) -> ParseResult<SyntaxNode> {
-    map(multispace1, |span| SyntaxNode::leaf(Kind::Ws, span)).parse(input)
+/// The `/// ..` lines right before a `let`, `def` or `fn`, each with the whitespace after it
+fn p_doc_comments(input: Span) -> ParseResult<Vec<SyntaxNode>> {
+    map(
+        terminated(
+            many1(tuple((
+                leaf(
+                    Kind::DocComment,
+                    tuple((tag("///"), not(char('/')), not_line_ending)),
+                ),
+                leaf(Kind::Ws, multispace1),
+            ))),
+            peek(alt((p_keyword("let"), p_keyword("def"), p_keyword("fn")))),
+        ),
+        |items| {
+            let mut nodes = vec![];
+            items.collect_into(&mut nodes);
+            nodes
+        },
+    )
+    .parse(input)
 }
 
-fn p_op(input: Span) -> ParseResult<SyntaxNode> {
-    map(recognize(one_of("+-*/")), |span| {
-        SyntaxNode::leaf(Kind::Op, span)
+/// Whitespace, including comments (they're just as meaningless to the parser)
+fn p_ws0(input: Span) -> ParseResult<SyntaxNode> {
+    map(recognize(many0(alt((multispace1, line_comment)))), |span| {
+        SyntaxNode::leaf(Kind::Ws, span)
     })
     .parse(input)
 }
 
-#[test]
-fn test_op() {
-    assert_eq!(
-        test_parse_debug(p_op, "+ 3"),
-        Ok((" 3", "Op[+]".into(), vec![]))
-    );
+fn p_ws1(input: Span) -> ParseResult<SyntaxNode> {
+    map(recognize(many1(alt((multispace1, line_comment)))), |span| {
+        SyntaxNode::leaf(Kind::Ws, span)
+    })
+    .parse(input)
 }
 
 fn p_bool(input: Span) -> ParseResult<SyntaxNode> {
@@ -389,7 +554,14 @@ fn p_num(input: Span) -> ParseResult<SyntaxNode> {
 
 fn p_unit(input: Span) -> ParseResult<SyntaxNode> {
     map(
-        alt((tag("min"), tag("ms"), tag("s"), tag("khz"), tag("hz"))),
+        alt((
+            tag("min"),
+            tag("ms"),
+            tag("s"),
+            tag("khz"),
+            tag("hz"),
+            tag("db"),
+        )),
         |span| SyntaxNode::leaf(Kind::Unit, span),
     )
     .parse(input)
@@ -400,7 +572,7 @@ fn p_num_or_amount(input: Span) -> ParseResult<SyntaxNode> {
         with_span(tuple((p_num, opt(tuple((p_ws0, p_unit)))))),
         |(span, (num, and_unit))| match and_unit {
             None => num,
-            Some(items) => SyntaxNode::leaf(Kind::Amount, span).with_collect_children((num, items)),
+            Some(items) => SyntaxNode::parent(Kind::Amount, span).with_collect_children((num, items)),
         },
     )
     .parse(input)
@@ -424,24 +596,221 @@ fn test_num_or_amount() {
     );
 }
 
-fn str(input: Span) -> IResult<Span, Span> {
-    escaped(none_of("\\\""), '\\', one_of("\"\n")).parse(input)
+/// Text in a string, up to the next `${` (or the end of the string, or of the line, unless it's `multiline`), including escapes like `\"` and `\$`
+fn p_str_part(input: Span, multiline: bool) -> ParseResult<SyntaxNode> {
+    let allowed = move |c: &char| multiline || *c != '\n';
+
+    leaf(
+        Kind::StrPart,
+        recognize(many1(alt((
+            recognize(preceded(char('\\'), verify(anychar, allowed))),
+            recognize(verify(none_of("\\\"$"), allowed)),
+            recognize(terminated(char('$'), not(char('{')))),
+        )))),
+    )
+    .parse(input)
 }
 
-fn p_str(input: Span) -> ParseResult<SyntaxNode> {
+fn p_interpolation(input: Span) -> ParseResult<SyntaxNode> {
     map(
-        recognize(preceded(
-            char('\"'),
-            cut(terminated(
-                str,
-                expecting(char('\"'), "expected closing quote for string"),
-            )),
-        )),
-        |span| SyntaxNode::leaf(Kind::Str, span),
+        with_span(tuple((
+            leaf(Kind::DollarCurly, tag("${")),
+            cut(tuple((
+                p_ws0,
+                expecting(p_expression, "expected expression in string"),
+                p_ws0,
+                expecting(
+                    leaf(Kind::CurlyRight, tag("}")),
+                    "expected closing `}` in string",
+                ),
+            ))),
+        ))),
+        |(span, items)| SyntaxNode::parent(Kind::Interpolation, span).with_collect_children(items),
+    )
+    .parse(input)
+}
+
+/**
+    A string, which is just a `Str` leaf, unless it has `${..}`s in it.
+
+    Strings can go over multiple lines, but one that isn't closed (or that would run into a line that looks like a new top-level statement, so probably isn't closed where it's meant to be) is parsed again, ending at the end of its first line, so that it doesn't swallow the rest of the document.
+*/
+fn p_str(input: Span) -> ParseResult<SyntaxNode> {
+    let string = |multiline: bool| {
+        with_span(tuple((
+            leaf(Kind::Quote, tag("\"")),
+            cut(tuple((
+                many0(alt((
+                    move |input| p_str_part(input, multiline),
+                    p_interpolation,
+                ))),
+                expecting(
+                    leaf(Kind::Quote, tag("\"")),
+                    "expected closing quote for string",
+                ),
+            ))),
+        )))
+    };
+
+    let checkpoint = input.extra.checkpoint();
+    let (mut rem, mut parsed) = string(true).parse(input.clone())?;
+
+    let (span, (_, (_, close))) = &parsed;
+    input.extra.looked_at(rem.location_offset());
+    if close.is_none() || span.fragment().split('\n').skip(1).any(is_statement_line) {
+        input.extra.rewind(checkpoint);
+        (rem, parsed) = string(false).parse(input)?;
+    }
+
+    let (span, (open, (parts, close))) = parsed;
+    let node = if parts.iter().all(|part| part.kind == Kind::StrPart) {
+        SyntaxNode::leaf(Kind::Str, span)
+    } else {
+        SyntaxNode::parent(Kind::InterpolatedStr, span).with_collect_children((open, parts, close))
+    };
+
+    Ok((rem, node))
+}
+
+#[test]
+fn test_str() {
+    assert_eq!(
+        test_parse_debug(p_str, r#""kicks/\"$1\".wav" "#),
+        Ok((" ", r#"Str["kicks/\"$1\".wav"]"#.into(), vec![]))
+    );
+
+    assert_eq!(
+        test_parse_debug(p_str, r#""kicks/${ name }.wav""#),
+        Ok((
+            "",
+            "InterpolatedStr[Quote[\"], StrPart[kicks/], Interpolation[DollarCurly[${], Ws, Ident[name], Ws, CurlyRight], StrPart[.wav], Quote[\"]]".into(),
+            vec![]
+        ))
+    );
+
+    assert_eq!(
+        test_parse_debug(p_str, r#""${a}${b + 1}"#),
+        Ok((
+            "",
+            "InterpolatedStr[Quote[\"], Interpolation[DollarCurly[${], Ident[a], CurlyRight], Interpolation[DollarCurly[${], BinaryExpr[Ident[b], Ws, Op[+], Ws, Num[1]], CurlyRight]]".into(),
+            vec!["expected closing quote for string".into()]
+        ))
+    );
+
+    assert_eq!(
+        test_parse_debug(p_str, "\"a\nb\" "),
+        Ok((" ", "Str[\"a\nb\"]".into(), vec![]))
+    );
+
+    assert_eq!(
+        test_parse_debug(p_str, "\"a;\nplay \"b\";"),
+        Ok((
+            "\nplay \"b\";",
+            "Str[\"a;]".into(),
+            vec!["expected closing quote for string".into()]
+        ))
+    );
+}
+
+/// A color, as 3, 6 or 8 (with alpha) hex digits after a `#`, like `#f80` or `#ff8800`
+fn p_color(input: Span) -> ParseResult<SyntaxNode> {
+    leaf(
+        Kind::Color,
+        terminated(
+            verify(recognize(tuple((char('#'), hex_digit1))), |span: &Span| {
+                matches!(span.len(), 4 | 7 | 9)
+            }),
+            not(peek(alt((alphanumeric1, tag("_"))))),
+        ),
+    )
+    .parse(input)
+}
+
+#[test]
+fn test_color() {
+    assert_eq!(
+        test_parse_debug(p_expression, "#ff8800 "),
+        Ok((" ", "Color[#ff8800]".into(), vec![]))
+    );
+
+    assert_eq!(
+        test_parse_debug(p_expression, "tint(#F80, 0.5) "),
+        Ok((
+            " ",
+            "CallExpr[Ident[tint], ParenLeft, Color[#F80], Comma, Ws, Num[0.5], ParenRight]".into(),
+            vec![]
+        ))
+    );
+
+    assert_matches!(test_parse_debug(p_color, "#ff88 "), Err(_));
+    assert_matches!(test_parse_debug(p_color, "#ff8800g "), Err(_));
+}
+
+/// A note name, like `c3`, `a#4` or `eb2` (which is a frequency), and not a name that happens to start like one, like `c3po`, maybe with how many cents it's off, like `c3+14ct`
+fn p_note(input: Span) -> ParseResult<SyntaxNode> {
+    let cents = tuple((
+        one_of("+-"),
+        digit1,
+        opt(tuple((char('.'), digit1))),
+        tag("ct"),
+    ));
+
+    leaf(
+        Kind::Note,
+        terminated(
+            recognize(tuple((
+                one_of("abcdefg"),
+                opt(one_of("#b")),
+                satisfy(|ch| ch.is_ascii_digit()),
+                not(peek(alt((alphanumeric1, tag("_"), tag("#"))))),
+                opt(cents),
+            ))),
+            not(peek(alt((alphanumeric1, tag("_"))))),
+        ),
     )
     .parse(input)
 }
 
+#[test]
+fn test_note() {
+    assert_eq!(
+        test_parse_debug(p_expression, "a#4 * 2 "),
+        Ok((
+            " ",
+            "BinaryExpr[Note[a#4], Ws, Op[*], Ws, Num[2]]".into(),
+            vec![]
+        ))
+    );
+
+    assert_eq!(
+        test_parse_debug(p_expression, "scale(\"minor\", eb3) "),
+        Ok((
+            " ",
+            "CallExpr[Ident[scale], ParenLeft, Str[\"minor\"], Comma, Ws, Note[eb3], ParenRight]"
+                .into(),
+            vec![]
+        ))
+    );
+
+    assert_eq!(
+        test_parse_debug(p_expression, "c3+14ct - c3+14 "),
+        Ok((
+            " ",
+            "BinaryExpr[BinaryExpr[Note[c3+14ct], Ws, Op[-], Ws, Note[c3]], Op[+], Num[14]]".into(),
+            vec![]
+        ))
+    );
+
+    assert_eq!(
+        test_parse_debug(p_expression, "c3po "),
+        Ok((" ", "Ident[c3po]".into(), vec![]))
+    );
+    assert_eq!(
+        test_parse_debug(p_expression, "e#12 "),
+        Ok((" ", "WidgetRef[e#12]".into(), vec![]))
+    );
+}
+
 fn p_primitive(input: Span) -> ParseResult<SyntaxNode> {
     alt((
         //
@@ -449,6 +818,7 @@ fn p_primitive(input: Span) -> ParseResult<SyntaxNode> {
         p_num_or_amount,
         p_math_constant,
         p_str,
+        p_color,
     ))
     .parse(input)
 }
@@ -460,18 +830,39 @@ fn test_primitive() {
         Ok((" ", "Bool[true]".into(), vec![]))
     );
 
+    assert_eq!(
+        test_parse_debug(p_expression, "false "),
+        Ok((" ", "Bool[false]".into(), vec![]))
+    );
+
+    assert_eq!(
+        test_parse_debug(p_expression, "pie "),
+        Ok((" ", "Ident[pie]".into(), vec![]))
+    );
+
     assert_eq!(
         test_parse_debug(p_num_or_amount, "12 "),
         Ok((" ", "Num[12]".into(), vec![]))
     );
 }
 
-const KEYWORDS: &'static [&'static str] = &["let", "fn", "return", "play", "pause"];
+pub(crate) const KEYWORDS: &[&str] = &["let", "def", "fn", "return", "play", "pause", "if", "else"];
 
 fn is_keyword(str: &str) -> bool {
     KEYWORDS.contains(&str)
 }
 
+/// (these are primitives, and so not names)
+pub(crate) const LITERALS: &[&str] = &["true", "false", "pi", "tau"];
+
+/// Whether the text is a name (and not a keyword, or a literal like `true`)
+pub(crate) fn is_identifier(text: &str) -> bool {
+    let errors = Rc::new(RefCell::new(vec![]));
+    let span = Span::new_extra(text, ParseState::new(errors));
+
+    p_identifier(span).is_ok_and(|(rem, _)| rem.is_empty())
+}
+
 fn p_identifier(input: Span) -> ParseResult<SyntaxNode> {
     map(
         verify(
@@ -479,17 +870,48 @@ fn p_identifier(input: Span) -> ParseResult<SyntaxNode> {
                 alt((alpha1, tag("_"))),
                 many0(alt((alphanumeric1, tag("_")))),
             ))),
-            |span: &Span| !is_keyword(&span.to_string()),
+            |span: &Span| {
+                let name = span.to_string();
+                !is_keyword(&name) && !LITERALS.contains(&name.as_str())
+            },
         ),
         |span: Span| SyntaxNode::leaf(Kind::Ident, span),
     )
     .parse(input)
 }
 
+/// A reference to one of the editor's widgets, like `sample#3`, which is how widgets end up in the source that the editor hands to the language
+fn p_widget_ref(input: Span) -> ParseResult<SyntaxNode> {
+    leaf(
+        Kind::WidgetRef,
+        tuple((
+            alt((alpha1, tag("_"))),
+            many0(alt((alphanumeric1, tag("_")))),
+            char('#'),
+            digit1,
+        )),
+    )
+    .parse(input)
+}
+
+#[test]
+fn test_widget_ref() {
+    assert_eq!(
+        test_parse_debug(p_expression, "sample#3 * 2 "),
+        Ok((
+            " ",
+            "BinaryExpr[WidgetRef[sample#3], Ws, Op[*], Ws, Num[2]]".into(),
+            vec![]
+        ))
+    );
+}
+
 enum SubsequenctUse {
     Index,
     AccessMember,
     Call,
+    Modify,
+    Configure,
 }
 
 fn p_use_index(input: Span) -> ParseResult<(SubsequenctUse, Vec<SyntaxNode>)> {
@@ -498,35 +920,18 @@ fn p_use_index(input: Span) -> ParseResult<(SubsequenctUse, Vec<SyntaxNode>)> {
             p_ws0,
             leaf(Kind::BracketLeft, tag("[")),
             cut(tuple((
-                expecting(tuple((p_ws0, p_expression)), "expected index expression"),
+                p_ws0,
+                expecting(p_expression, "expected index expression"),
+                p_ws0,
                 expecting(
-                    tuple((p_ws0, leaf(Kind::BracketRight, tag("]")))),
+                    leaf(Kind::BracketRight, tag("]")),
                     "expected closing `]` for index",
                 ),
             ))),
         )),
-        |(ws, open, (expr, close))| {
+        |items| {
             let mut children = vec![];
-
-            if !ws.empty() {
-                children.push(ws);
-            }
-            children.push(open);
-
-            if let Some((ws, expr)) = expr {
-                if !ws.empty() {
-                    children.push(ws);
-                }
-                children.push(expr);
-            }
-
-            if let Some((ws, close)) = close {
-                if !ws.empty() {
-                    children.push(ws);
-                }
-                children.push(close);
-            }
-
+            items.collect_into(&mut children);
             (SubsequenctUse::Index, children)
         },
     )
@@ -538,26 +943,14 @@ fn p_use_access_member(input: Span) -> ParseResult<(SubsequenctUse, Vec<SyntaxNo
         tuple((
             p_ws0,
             leaf(Kind::Dot, tag(".")),
-            cut(tuple((expecting(
-                tuple((p_ws0, p_identifier)),
-                "expected member identifier",
-            ),))),
+            cut(tuple((
+                p_ws0,
+                expecting(p_identifier, "expected member identifier"),
+            ))),
         )),
-        |(ws, dot, (id,))| {
+        |items| {
             let mut children = vec![];
-
-            if !ws.empty() {
-                children.push(ws);
-            }
-            children.push(dot);
-
-            if let Some((ws, id)) = id {
-                if !ws.empty() {
-                    children.push(ws);
-                }
-                children.push(id);
-            }
-
+            items.collect_into(&mut children);
             (SubsequenctUse::AccessMember, children)
         },
     )
@@ -568,9 +961,46 @@ fn p_comma(input: Span) -> ParseResult<SyntaxNode> {
     leaf(Kind::Comma, tag(",")).parse(input)
 }
 
+fn p_semi(input: Span) -> ParseResult<SyntaxNode> {
+    leaf(Kind::Semi, tag(";")).parse(input)
+}
+
+fn p_keyword<'a>(keyword: &'static str) -> impl FnMut(Span<'a>) -> ParseResult<SyntaxNode<'a>> {
+    // (so that `iffy` is just a name)
+    leaf(
+        Kind::Keyword,
+        terminated(
+            tag(keyword),
+            not(satisfy(|c: char| c.is_alphanumeric() || c == '_')),
+        ),
+    )
+}
+
 fn p_args(input: Span) -> ParseResult<Vec<SyntaxNode>> {
-    let (input, nodes) = many0(alt((p_ws1, p_comma, p_expression))).parse(input)?;
+    let (input, nodes) = many0(alt((p_ws1, p_comma, p_update, p_expression))).parse(input)?;
+
+    check_commas(&input, &nodes);
+
+    Ok((input, nodes))
+}
+
+/// An argument like `_ *= .2s`, which is `_ * .2s`, but reads as what happens to every element in `xs.map(_ *= .2s)` (only with the `_` shorthand, since there's nothing to assign to otherwise)
+fn p_update(input: Span) -> ParseResult<SyntaxNode> {
+    map(
+        with_span(tuple((
+            verify(p_identifier, |node: &SyntaxNode| node.text() == "_"),
+            p_ws0,
+            leaf(Kind::Op, alt((tag("+="), tag("-="), tag("*="), tag("/=")))),
+            p_ws0,
+            cut(expecting(p_expression, "expected expression")),
+        ))),
+        |(span, items)| SyntaxNode::parent(Kind::BinaryExpr, span).with_collect_children(items),
+    )
+    .parse(input)
+}
 
+/// Reports missing and superfluous commas in between the items of a list
+fn check_commas(input: &Span, nodes: &[SyntaxNode]) {
     enum State {
         AwaitingExpr,
         AwaitingComma,
@@ -601,8 +1031,6 @@ fn p_args(input: Span) -> ParseResult<Vec<SyntaxNode>> {
             },
         }
     }
-
-    Ok((input, nodes))
 }
 
 #[test]
@@ -663,15 +1091,141 @@ fn p_use_call(input: Span) -> ParseResult<(SubsequenctUse, Vec<SyntaxNode>)> {
     .parse(input)
 }
 
-fn p_factor(input: Span) -> ParseResult<SyntaxNode> {
-    alt((
-        p_identifier,
-        p_primitive,
-        p_parenthesized_expr,
-        // p_block,
-        // p_anonymous_function,
-    ))
-    .parse(input)
+fn p_setting(input: Span) -> ParseResult<SyntaxNode> {
+    map(
+        with_span(tuple((
+            p_identifier,
+            p_ws0,
+            leaf(Kind::Eq, terminated(tag("="), not(char('=')))),
+            p_ws0,
+            cut(expecting(p_expression, "missing setting value")),
+        ))),
+        |(span, items)| SyntaxNode::parent(Kind::Setting, span).with_collect_children(items),
+    )
+    .parse(input)
+}
+
+/// Modifiers tweak what an expression produces, e.g. how a sample is played: `kick%[rate = 1.5, loop = true]`
+fn p_use_modify(input: Span) -> ParseResult<(SubsequenctUse, Vec<SyntaxNode>)> {
+    map(
+        tuple((
+            p_ws0,
+            leaf(Kind::Percent, tag("%")),
+            cut(tuple((
+                p_ws0,
+                expecting(leaf(Kind::BracketLeft, tag("[")), "expected `[` after `%`"),
+                p_modifier_args,
+                expecting(
+                    leaf(Kind::BracketRight, tag("]")),
+                    "expected closing `]` for modifiers",
+                ),
+            ))),
+        )),
+        |items| {
+            let mut children = vec![];
+            items.collect_into(&mut children);
+            (SubsequenctUse::Modify, children)
+        },
+    )
+    .parse(input)
+}
+
+/// Settings configure a built-in by name, like `lowpass{f = 800hz, q = 2}` (the `{` comes right after it, or it'd be a block)
+fn p_use_settings(input: Span) -> ParseResult<(SubsequenctUse, Vec<SyntaxNode>)> {
+    map(
+        tuple((
+            leaf(Kind::CurlyLeft, tag("{")),
+            cut(tuple((
+                p_modifier_args,
+                expecting(
+                    leaf(Kind::CurlyRight, tag("}")),
+                    "expected closing `}` for settings",
+                ),
+            ))),
+        )),
+        |items| {
+            let mut children = vec![];
+            items.collect_into(&mut children);
+            (SubsequenctUse::Configure, children)
+        },
+    )
+    .parse(input)
+}
+
+fn p_modifier_args(input: Span) -> ParseResult<Vec<SyntaxNode>> {
+    let (input, nodes) = many0(alt((p_ws1, p_comma, p_setting, p_expression))).parse(input)?;
+
+    check_commas(&input, &nodes);
+
+    Ok((input, nodes))
+}
+
+#[test]
+fn test_modifiers() {
+    assert_eq!(
+        test_parse_debug(p_expression, "kick%[rate = 1.5, 2] "),
+        Ok((
+            " ",
+            "ModifierExpr[Ident[kick], Percent[%], BracketLeft, Setting[Ident[rate], Ws, Eq[=], Ws, Num[1.5]], Comma, Ws, Num[2], BracketRight]".into(),
+            vec![]
+        ))
+    );
+
+    assert_eq!(
+        test_parse_debug(p_expression, "kick%[rate = 1.5 loop = 1"),
+        Ok((
+            "",
+            "ModifierExpr[Ident[kick], Percent[%], BracketLeft, Setting[Ident[rate], Ws, Eq[=], Ws, Num[1.5]], Ws, Setting[Ident[loop], Ws, Eq[=], Ws, Num[1]]]".into(),
+            vec![
+                "expected comma".into(),
+                "expected closing `]` for modifiers".into()
+            ]
+        ))
+    );
+}
+
+#[test]
+fn test_settings() {
+    assert_eq!(
+        test_parse_debug(p_expression, "lowpass{f = 800hz, q = 2} "),
+        Ok((
+            " ",
+            "SettingsExpr[Ident[lowpass], CurlyLeft, Setting[Ident[f], Ws, Eq[=], Ws, Amount[Num[800], Unit[hz]]], Comma, Ws, Setting[Ident[q], Ws, Eq[=], Ws, Num[2]], CurlyRight]".into(),
+            vec![]
+        ))
+    );
+
+    // (with a space, it's something followed by a block)
+    assert_eq!(
+        test_parse_debug(p_expression, "lowpass {f}"),
+        Ok((" {f}", "Ident[lowpass]".into(), vec![]))
+    );
+
+    assert_eq!(
+        test_parse_debug(p_expression, "delay{time = 1"),
+        Ok((
+            "",
+            "SettingsExpr[Ident[delay], CurlyLeft, Setting[Ident[time], Ws, Eq[=], Ws, Num[1]]]"
+                .into(),
+            vec!["expected closing `}` for settings".into()]
+        ))
+    );
+}
+
+fn p_factor(input: Span) -> ParseResult<SyntaxNode> {
+    alt((
+        // (before widgets, since `a#4` could be one)
+        p_note,
+        p_widget_ref,
+        p_identifier,
+        p_primitive,
+        p_parenthesized_expr,
+        p_array,
+        p_if,
+        p_block,
+        p_anonymous_function,
+    ))
+    .parse(input)
 }
 
 #[test]
@@ -687,12 +1241,14 @@ fn fold_usages<'a>(
     usages: Vec<(SubsequenctUse, Vec<SyntaxNode<'a>>)>,
 ) -> SyntaxNode<'a> {
     usages.into_iter().fold(initial, |expr, (usage, nodes)| {
-        let range = cover_ranges(expr.range, nodes.last().unwrap().range);
+        let range = expr.range.cover(nodes.last().unwrap().range);
         let mut parent = SyntaxNode::new(
             match usage {
                 SubsequenctUse::Index => Kind::IndexExpr,
                 SubsequenctUse::AccessMember => Kind::MemberExpr,
                 SubsequenctUse::Call => Kind::CallExpr,
+                SubsequenctUse::Modify => Kind::ModifierExpr,
+                SubsequenctUse::Configure => Kind::SettingsExpr,
             },
             range,
         );
@@ -707,15 +1263,101 @@ fn fold_usages<'a>(
 fn p_usage(i: Span) -> ParseResult<SyntaxNode> {
     let (i, initial) = p_factor(i)?;
 
-    let (i, usages) = many0(alt((p_use_index, p_use_access_member, p_use_call))).parse(i)?;
+    let (i, usages) = many0(alt((
+        p_use_index,
+        p_use_access_member,
+        p_use_call,
+        p_use_modify,
+        p_use_settings,
+    )))
+    .parse(i)?;
 
     Ok((i, fold_usages(initial, usages)))
 }
 
-// TODO
-#[allow(unused)]
-pub fn p_expression(input: Span) -> ParseResult<SyntaxNode> {
-    p_usage.parse(input)
+fn fold_binary<'a>(
+    initial: SyntaxNode<'a>,
+    remainder: Vec<(SyntaxNode<'a>, SyntaxNode<'a>, SyntaxNode<'a>, SyntaxNode<'a>)>,
+) -> SyntaxNode<'a> {
+    remainder
+        .into_iter()
+        .fold(initial, |left, (ws_before, op, ws_after, right)| {
+            let range = left.range.cover(right.range);
+            SyntaxNode::new(Kind::BinaryExpr, range)
+                .with_collect_children((left, ws_before, op, ws_after, right))
+        })
+}
+
+fn p_unary(input: Span) -> ParseResult<SyntaxNode> {
+    alt((
+        map(
+            with_span(tuple((
+                leaf(Kind::Op, tag("!")),
+                p_ws0,
+                cut(expecting(p_unary, "expected expression after `!`")),
+            ))),
+            |(span, items)| SyntaxNode::parent(Kind::UnaryExpr, span).with_collect_children(items),
+        ),
+        p_usage,
+    ))
+    .parse(input)
+}
+
+fn p_term(i: Span) -> ParseResult<SyntaxNode> {
+    let (i, initial) = p_unary(i)?;
+    let (i, remainder) =
+        many0(tuple((p_ws0, leaf(Kind::Op, one_of("*/")), p_ws0, p_unary))).parse(i)?;
+
+    Ok((i, fold_binary(initial, remainder)))
+}
+
+fn p_additive(i: Span) -> ParseResult<SyntaxNode> {
+    let (i, initial) = p_term(i)?;
+    let (i, remainder) =
+        many0(tuple((p_ws0, leaf(Kind::Op, one_of("+-")), p_ws0, p_term))).parse(i)?;
+
+    Ok((i, fold_binary(initial, remainder)))
+}
+
+fn p_comparison(i: Span) -> ParseResult<SyntaxNode> {
+    let op = alt((
+        tag("=="),
+        tag("!="),
+        tag("<="),
+        tag(">="),
+        tag("<"),
+        tag(">"),
+    ));
+    let (i, initial) = p_additive(i)?;
+    let (i, remainder) = many0(tuple((p_ws0, leaf(Kind::Op, op), p_ws0, p_additive))).parse(i)?;
+
+    Ok((i, fold_binary(initial, remainder)))
+}
+
+fn p_conjunction(i: Span) -> ParseResult<SyntaxNode> {
+    let (i, initial) = p_comparison(i)?;
+    let (i, remainder) = many0(tuple((
+        p_ws0,
+        leaf(Kind::Op, tag("&&")),
+        p_ws0,
+        p_comparison,
+    )))
+    .parse(i)?;
+
+    Ok((i, fold_binary(initial, remainder)))
+}
+
+pub fn p_expression(i: Span) -> ParseResult<SyntaxNode> {
+    let (i, initial) = p_conjunction(i)?;
+    let (i, remainder) = many0(tuple((
+        p_ws0,
+        leaf(Kind::Op, tag("||")),
+        p_ws0,
+        p_conjunction,
+    )))
+    .parse(i)?;
+
+    Ok((i, fold_binary(initial, remainder)))
 }
 
 #[test]
@@ -785,24 +1427,639 @@ fn test_expr() {
     assert_eq!(node.stringify(), "hi .  there [4] (a, b ,, c ");
 }
 
+/// A parenthesized expression, or a tuple if there's a comma in it, like `(1, .5)`
 fn p_parenthesized_expr(i: Span) -> ParseResult<SyntaxNode> {
     map(
         with_span(tuple((
-            leaf(Kind::CurlyLeft, tag("(")),
-            expecting(
-                tuple((p_ws0, p_expression)),
-                "expected expression after `(`",
-            ),
-            expecting(
-                tuple((p_ws0, leaf(Kind::CurlyRight, tag(")")))),
-                "missing `)`",
-            ),
+            leaf(Kind::ParenLeft, tag("(")),
+            p_ws0,
+            expecting(p_expression, "expected expression after `(`"),
+            p_ws0,
+            opt(tuple((p_comma, p_args))),
+            expecting(leaf(Kind::ParenRight, tag(")")), "missing `)`"),
         ))),
-        |(span, items)| SyntaxNode::leaf(Kind::ParenExpr, span).with_collect_children(items),
+        |(span, items)| {
+            let kind = match items.4 {
+                Some(_) => Kind::TupleExpr,
+                None => Kind::ParenExpr,
+            };
+            SyntaxNode::parent(kind, span).with_collect_children(items)
+        },
     )
     .parse(i)
 }
 
+fn p_array(input: Span) -> ParseResult<SyntaxNode> {
+    map(
+        with_span(tuple((
+            leaf(Kind::BracketLeft, tag("[")),
+            cut(tuple((
+                p_args,
+                expecting(
+                    leaf(Kind::BracketRight, tag("]")),
+                    "expected closing `]` for array",
+                ),
+            ))),
+        ))),
+        |(span, items)| SyntaxNode::parent(Kind::ArrayExpr, span).with_collect_children(items),
+    )
+    .parse(input)
+}
+
+#[test]
+fn test_array_and_tuple() {
+    assert_eq!(
+        test_parse_debug(p_expression, "[(1, 1), (3,.5)] "),
+        Ok((
+            " ",
+            "ArrayExpr[BracketLeft, TupleExpr[ParenLeft, Num[1], Comma, Ws, Num[1], ParenRight], Comma, Ws, TupleExpr[ParenLeft, Num[3], Comma, Num[.5], ParenRight], BracketRight]".into(),
+            vec![]
+        ))
+    );
+
+    assert_eq!(
+        test_parse_debug(p_expression, "(1,) "),
+        Ok((
+            " ",
+            "TupleExpr[ParenLeft, Num[1], Comma, ParenRight]".into(),
+            vec![]
+        ))
+    );
+
+    assert_eq!(
+        test_parse_debug(p_expression, "[1 2"),
+        Ok((
+            "",
+            "ArrayExpr[BracketLeft, Num[1], Ws, Num[2]]".into(),
+            vec![
+                "expected comma".into(),
+                "expected closing `]` for array".into()
+            ]
+        ))
+    );
+
+    assert_eq!(
+        test_parse_debug(p_expression, "xs.map(_ *= .2s) "),
+        Ok((
+            " ",
+            "CallExpr[MemberExpr[Ident[xs], Dot, Ident[map]], ParenLeft, BinaryExpr[Ident[_], Ws, Op[*=], Ws, Amount[Num[.2], Unit[s]]], ParenRight]".into(),
+            vec![]
+        ))
+    );
+    assert_eq!(
+        test_parse_debug(p_expression, "xs.map(_ += ) "),
+        Ok((
+            " ",
+            "CallExpr[MemberExpr[Ident[xs], Dot, Ident[map]], ParenLeft, BinaryExpr[Ident[_], Ws, Op[+=], Ws], ParenRight]".into(),
+            vec!["expected expression".into()]
+        ))
+    );
+
+    assert_eq!(
+        test_parse_debug(p_expression, "[1, 2].map(_ * .2s) "),
+        Ok((
+            " ",
+            "CallExpr[MemberExpr[ArrayExpr[BracketLeft, Num[1], Comma, Ws, Num[2], BracketRight], Dot, Ident[map]], ParenLeft, BinaryExpr[Ident[_], Ws, Op[*], Ws, Amount[Num[.2], Unit[s]]], ParenRight]".into(),
+            vec![]
+        ))
+    );
+}
+
+#[test]
+fn test_binary_expr() {
+    assert_eq!(
+        test_parse_debug(p_expression, "1 + 2 *  3 "),
+        Ok((
+            " ",
+            "BinaryExpr[Num[1], Ws, Op[+], Ws, BinaryExpr[Num[2], Ws, Op[*], Ws, Num[3]]]".into(),
+            vec![]
+        ))
+    );
+
+    assert_eq!(
+        test_parse_debug(p_expression, "a || b && c <= 1 + 2"),
+        Ok((
+            "",
+            "BinaryExpr[Ident[a], Ws, Op[||], Ws, BinaryExpr[Ident[b], Ws, Op[&&], Ws, BinaryExpr[Ident[c], Ws, Op[<=], Ws, BinaryExpr[Num[1], Ws, Op[+], Ws, Num[2]]]]]".into(),
+            vec![]
+        ))
+    );
+
+    assert_eq!(
+        test_parse_debug(p_expression, "!a == b"),
+        Ok((
+            "",
+            "BinaryExpr[UnaryExpr[Op[!], Ident[a]], Ws, Op[==], Ws, Ident[b]]".into(),
+            vec![]
+        ))
+    );
+
+    let node = test_parse(p_expression, "(1.2s + (2) ) *  3").unwrap().1;
+
+    assert_eq!(node.stringify(), "(1.2s + (2) ) *  3");
+}
+
+/// Whether a line starts with `let`, `def`, `fn` or `play` (or a doc comment), which is what a new top-level statement looks like
+pub(crate) fn is_statement_line(line: &str) -> bool {
+    line.starts_with("///") || ["let", "def", "fn", "play"].iter().any(|keyword| {
+        line.strip_prefix(keyword)
+            .is_some_and(|rest| !rest.starts_with(|c: char| c.is_alphanumeric() || c == '_'))
+    })
+}
+
+fn starts_statement_line(input: &Span) -> bool {
+    input.get_column() == 1 && is_statement_line(input.fragment())
+}
+
+/// The items in a block, until there are no more (or, when `cut_off`, until a line that looks like a new top-level statement)
+fn p_block_inner(mut input: Span, cut_off: bool) -> ParseResult<Vec<SyntaxNode>> {
+    let mut nodes = vec![];
+
+    // an expression that would be the block's value, unless more items follow
+    let mut trailing_expr = false;
+    let mut missing_stmt_semi = false;
+
+    loop {
+        let mut item = alt((p_statement_bare, p_declaration, p_expression, p_semi, p_ws1));
+
+        let parsed = match cut_off && starts_statement_line(&input) {
+            true => Err(nom::Err::Error(nom::error::Error::new(
+                input.clone(),
+                nom::error::ErrorKind::Not,
+            ))),
+            false => item.parse(input.clone()),
+        };
+
+        match parsed {
+            Ok((rem, node)) => {
+                let is_item = node.kind.is_statement()
+                    || node.kind.is_expression()
+                    || node.kind == Kind::FnDecl;
+
+                if is_item && trailing_expr {
+                    let err = ParseError(source_span(&rem), "missing `;`".into());
+                    rem.extra.report_error(err);
+                    missing_stmt_semi = false;
+                    trailing_expr = false;
+                }
+
+                if node.kind.is_statement() {
+                    missing_stmt_semi = true;
+                } else if node.kind.is_expression() {
+                    trailing_expr = true;
+                } else if node.kind == Kind::Semi {
+                    trailing_expr = false;
+                    missing_stmt_semi = false;
+                }
+
+                nodes.push(node);
+                input = rem;
+            }
+            Err(nom::Err::Error(_)) => {
+                if missing_stmt_semi {
+                    let err = ParseError(source_span(&input), "missing `;`".into());
+                    input.extra.report_error(err);
+                }
+                return Ok((input, nodes));
+            }
+            Err(e) => {
+                return Err(e);
+            }
+        }
+    }
+}
+
+/**
+    A `{ .. }` block. One that isn't closed is parsed again, cut off before the first line that looks like a new top-level statement, so that a stray `{` doesn't swallow all the definitions after it.
+*/
+fn p_block(input: Span) -> ParseResult<SyntaxNode> {
+    let block = |cut_off: bool| {
+        map(
+            with_span(tuple((
+                leaf(Kind::CurlyLeft, tag("{")),
+                move |input| p_block_inner(input, cut_off),
+                expecting(leaf(Kind::CurlyRight, tag("}")), "missing `}`"),
+            ))),
+            |(span, items)| SyntaxNode::parent(Kind::Block, span).with_collect_children(items),
+        )
+    };
+
+    let checkpoint = input.extra.checkpoint();
+    let (rem, node) = block(false).parse(input.clone())?;
+    input.extra.looked_at(rem.location_offset());
+
+    if node.children.last().map(|child| child.kind) == Some(Kind::CurlyRight) {
+        return Ok((rem, node));
+    }
+
+    input.extra.rewind(checkpoint);
+    block(true).parse(input)
+}
+
+#[test]
+fn test_block() {
+    assert_eq!(
+        test_parse_debug(p_block, "{ let x = 2; x }"),
+        Ok((
+            "",
+            "Block[CurlyLeft, Ws, LetStmt[Keyword[let], Ws, Ident[x], Ws, Eq[=], Ws, Num[2]], Semi[;], Ws, Ident[x], Ws, CurlyRight]".into(),
+            vec![]
+        ))
+    );
+
+    assert_eq!(
+        test_parse_debug(p_block, "{ a b"),
+        Ok((
+            "",
+            "Block[CurlyLeft, Ws, Ident[a], Ws, Ident[b]]".into(),
+            vec!["missing `;`".into(), "missing `}`".into()]
+        ))
+    );
+
+    assert_eq!(
+        test_parse_debug(p_block, "{ a\nlet b = 2;"),
+        Ok((
+            "let b = 2;",
+            "Block[CurlyLeft, Ws, Ident[a], Ws]".into(),
+            vec!["missing `}`".into()]
+        ))
+    );
+}
+
+/// `if cond { .. } else { .. }`, where the `else` can be followed by another `if`. (Like settings, the condition's last name shouldn't be right up against the `{`.)
+fn p_if(input: Span) -> ParseResult<SyntaxNode> {
+    map(
+        with_span(tuple((
+            p_keyword("if"),
+            cut(tuple((
+                p_ws0,
+                // (a block would be the one to run)
+                expecting(
+                    verify(p_expression, |node| node.kind != Kind::Block),
+                    "expected condition after `if`",
+                ),
+                p_ws0,
+                expecting(p_block, "expected `{` after the condition"),
+                opt(tuple((
+                    p_ws0,
+                    p_keyword("else"),
+                    p_ws0,
+                    expecting(alt((p_if, p_block)), "expected `{` or `if` after `else`"),
+                ))),
+            ))),
+        ))),
+        |(span, items)| SyntaxNode::parent(Kind::IfExpr, span).with_collect_children(items),
+    )
+    .parse(input)
+}
+
+#[test]
+fn test_if() {
+    assert_eq!(
+        test_parse_debug(p_expression, "if x > 1 { a } else if !y { b } else { c }"),
+        Ok((
+            "",
+            "IfExpr[Keyword[if], Ws, BinaryExpr[Ident[x], Ws, Op[>], Ws, Num[1]], Ws, Block[CurlyLeft, Ws, Ident[a], Ws, CurlyRight], Ws, Keyword[else], Ws, IfExpr[Keyword[if], Ws, UnaryExpr[Op[!], Ident[y]], Ws, Block[CurlyLeft, Ws, Ident[b], Ws, CurlyRight], Ws, Keyword[else], Ws, Block[CurlyLeft, Ws, Ident[c], Ws, CurlyRight]]]".into(),
+            vec![]
+        ))
+    );
+
+    assert_eq!(
+        test_parse_debug(p_expression, "if x { a }"),
+        Ok((
+            "",
+            "IfExpr[Keyword[if], Ws, Ident[x], Ws, Block[CurlyLeft, Ws, Ident[a], Ws, CurlyRight]]"
+                .into(),
+            vec![]
+        ))
+    );
+
+    assert_eq!(
+        test_parse_debug(p_expression, "if { a } else"),
+        Ok((
+            "",
+            "IfExpr[Keyword[if], Ws, Block[CurlyLeft, Ws, Ident[a], Ws, CurlyRight], Ws, Keyword[else]]".into(),
+            vec![
+                "expected condition after `if`".into(),
+                "expected `{` or `if` after `else`".into()
+            ]
+        ))
+    );
+
+    assert_eq!(
+        test_parse_debug(p_expression, "iffy"),
+        Ok(("", "Ident[iffy]".into(), vec![]))
+    );
+}
+
+fn p_param(input: Span) -> ParseResult<SyntaxNode> {
+    map(
+        with_span(alt((
+            map(tuple((p_identifier, p_ws1, p_identifier)), |(ty, ws, name)| {
+                vec![ty, ws, name]
+            }),
+            map(p_identifier, |name| vec![name]),
+        ))),
+        |(span, items)| SyntaxNode::parent(Kind::Param, span).with_collect_children(items),
+    )
+    .parse(input)
+}
+
+fn p_params(input: Span) -> ParseResult<Vec<SyntaxNode>> {
+    map(
+        opt(tuple((
+            p_param,
+            many0(tuple((p_ws0, p_comma, p_ws0, p_param))),
+        ))),
+        |items| {
+            let mut nodes = vec![];
+            items.collect_into(&mut nodes);
+            nodes
+        },
+    )
+    .parse(input)
+}
+
+fn p_anonymous_function(input: Span) -> ParseResult<SyntaxNode> {
+    map(
+        with_span(tuple((
+            leaf(Kind::Pipe, tag("|")),
+            p_ws0,
+            cut(tuple((
+                p_params,
+                p_ws0,
+                opt(leaf(Kind::Pipe, tag("|"))),
+                p_ws0,
+                expecting(p_expression, "expected anonymous function body"),
+            ))),
+        ))),
+        |(span, items)| SyntaxNode::parent(Kind::AnonymousFn, span).with_collect_children(items),
+    )
+    .parse(input)
+}
+
+#[test]
+fn test_anonymous_function() {
+    assert_eq!(
+        test_parse_debug(p_expression, "|osc s, t| s"),
+        Ok((
+            "",
+            "AnonymousFn[Pipe[|], Param[Ident[osc], Ws, Ident[s]], Comma, Ws, Param[Ident[t]], Pipe[|], Ws, Ident[s]]".into(),
+            vec![]
+        ))
+    );
+}
+
+fn p_function_declaration(input: Span) -> ParseResult<SyntaxNode> {
+    map(
+        with_span(tuple((
+            opt(p_doc_comments),
+            p_keyword("fn"),
+            p_ws1,
+            cut(tuple((
+                expecting(p_identifier, "expected function name"),
+                p_ws0,
+                expecting(
+                    leaf(Kind::ParenLeft, tag("(")),
+                    "expected function parameters opening `(`",
+                ),
+                p_ws0,
+                p_params,
+                p_ws0,
+                opt(p_comma),
+                p_ws0,
+                expecting(
+                    leaf(Kind::ParenRight, tag(")")),
+                    "expected function parameters closing `)`",
+                ),
+                p_ws0,
+                expecting(p_block, "expected function body"),
+            ))),
+        ))),
+        |(span, items)| SyntaxNode::parent(Kind::FnDecl, span).with_collect_children(items),
+    )
+    .parse(input)
+}
+
+fn p_declaration(input: Span) -> ParseResult<SyntaxNode> {
+    alt((
+        p_function_declaration,
+        // others to come..
+    ))
+    .parse(input)
+}
+
+fn p_return_statement(input: Span) -> ParseResult<SyntaxNode> {
+    map(
+        with_span(tuple((
+            p_keyword("return"),
+            alt((map(peek(tag(";")), |_| None), map(p_ws1, Some))),
+            cut(opt(p_expression)),
+        ))),
+        |(span, items)| SyntaxNode::parent(Kind::ReturnStmt, span).with_collect_children(items),
+    )
+    .parse(input)
+}
+
+fn p_play_statement(input: Span) -> ParseResult<SyntaxNode> {
+    map(
+        with_span(tuple((
+            p_keyword("play"),
+            p_ws1,
+            cut(expecting(p_expression, "missing play expression")),
+        ))),
+        |(span, items)| SyntaxNode::parent(Kind::PlayStmt, span).with_collect_children(items),
+    )
+    .parse(input)
+}
+
+fn p_let_statement(input: Span) -> ParseResult<SyntaxNode> {
+    map(
+        with_span(tuple((
+            opt(p_doc_comments),
+            alt((p_keyword("let"), p_keyword("def"))),
+            p_ws1,
+            cut(tuple((
+                expecting(p_identifier, "missing let identifier"),
+                p_ws0,
+                expecting(leaf(Kind::Eq, tag("=")), "missing `=`"),
+                p_ws0,
+                expecting(p_expression, "missing let expression"),
+            ))),
+        ))),
+        |(span, items)| SyntaxNode::parent(Kind::LetStmt, span).with_collect_children(items),
+    )
+    .parse(input)
+}
+
+#[test]
+fn test_let_statement() {
+    assert_eq!(
+        test_parse_debug(p_let_statement, "def beat = 4;"),
+        Ok((
+            ";",
+            "LetStmt[Keyword[def], Ws, Ident[beat], Ws, Eq[=], Ws, Num[4]]".into(),
+            vec![]
+        ))
+    );
+
+    assert_eq!(
+        test_parse_debug(p_let_statement, "/// four\n/// to the floor\ndef beat = 4;"),
+        Ok((
+            ";",
+            "LetStmt[DocComment[/// four], Ws, DocComment[/// to the floor], Ws, Keyword[def], Ws, Ident[beat], Ws, Eq[=], Ws, Num[4]]".into(),
+            vec![]
+        ))
+    );
+}
+
+/// Parses a statement, but WITHOUT the delimiting semicolon, and NOT INCLUDING an expression statement or declaration statement
+fn p_statement_bare(input: Span) -> ParseResult<SyntaxNode> {
+    alt((p_return_statement, p_play_statement, p_let_statement)).parse(input)
+}
+
+fn terminated_by_semi<'a>(
+    parser: impl FnMut(Span<'a>) -> ParseResult<SyntaxNode<'a>>,
+) -> impl FnMut(Span<'a>) -> ParseResult<Vec<SyntaxNode<'a>>> {
+    map(
+        tuple((parser, p_ws0, expecting(p_semi, "missing `;`"))),
+        |items| {
+            let mut nodes = vec![];
+            items.collect_into(&mut nodes);
+            nodes
+        },
+    )
+}
+
+fn p_statement_complete(input: Span) -> ParseResult<Vec<SyntaxNode>> {
+    alt((
+        terminated_by_semi(p_statement_bare),
+        map(p_declaration, |decl| vec![decl]),
+        terminated_by_semi(p_expression),
+    ))
+    .parse(input)
+}
+
+/// One top-level item of the document: a complete statement (with its `;`), or else whitespace, or else a character that's skipped
+fn p_document_item(input: Span) -> ParseResult<Vec<SyntaxNode>> {
+    match p_statement_complete.parse(input.clone()) {
+        Err(nom::Err::Error(_)) => {
+            let (rem, node) = alt((p_ws1, leaf(Kind::Skipped, take(1usize)))).parse(input)?;
+            Ok((rem, vec![node]))
+        }
+        result => result,
+    }
+}
+
+fn p_document(mut input: Span) -> ParseResult<SyntaxNode> {
+    let range = source_span(&input);
+    let mut nodes = vec![];
+
+    while !input.is_empty() {
+        let (rem, item) = p_document_item(input)?;
+        item.collect_into(&mut nodes);
+        input = rem;
+    }
+
+    Ok((
+        input,
+        SyntaxNode::new(Kind::Document, range).with_collect_children(nodes),
+    ))
+}
+
+/// Parses a whole document into a lossless syntax tree, collecting all syntax errors along the way
+pub fn parse_syntax_tree(source: &str) -> (SyntaxNode<'_>, Vec<ParseError>) {
+    let errors = Rc::new(RefCell::new(vec![]));
+    let span = Span::new_extra(source, ParseState::new(errors.clone()));
+
+    let (_, tree) = p_document(span).expect("could not parse document");
+
+    let errors = errors.take();
+
+    (tree, errors)
+}
+
+/// The syntax errors in a document, with where each of them starts (they don't really end anywhere meaningful, the parser just picks up again at some point)
+pub fn syntax_errors(source: &str) -> Vec<(Loc, String)> {
+    let (_, errors) = parse_syntax_tree(source);
+
+    errors
+        .into_iter()
+        .map(|ParseError(range, message)| (range.start, message))
+        .collect()
+}
+
+#[test]
+fn test_syntax_errors() {
+    assert_eq!(syntax_errors("let x = 1;\nplay x;"), vec![]);
+    assert_eq!(
+        syntax_errors("let x = 1\nplay x;"),
+        vec![(
+            Loc {
+                offset: 10,
+                line: 2,
+                col: 1
+            },
+            "missing `;`".to_string()
+        )]
+    );
+}
+
+#[test]
+fn test_document() {
+    let source = "fn { 5; let h = 6 }}; let h = 6;; 123 *68 play 6;";
+    let (tree, errors) = parse_syntax_tree(source);
+
+    assert_eq!(tree.stringify(), source);
+    assert_eq!(errors.len(), 5);
+
+    // (a doc comment is part of the definition after it, but any other comment is just whitespace)
+    let source =
+        "/// the kick\ndef kick = 1;\n/// not a definition\nplay kick;\n//// nor this\nfn f() {}";
+    let (tree, errors) = parse_syntax_tree(source);
+
+    assert_eq!(tree.stringify(), source);
+    assert_eq!(errors, vec![]);
+    assert_eq!(
+        tree.children
+            .iter()
+            .map(|child| child.kind)
+            .filter(|kind| *kind != Kind::Ws)
+            .collect::<Vec<_>>(),
+        vec![
+            Kind::LetStmt,
+            Kind::Semi,
+            Kind::PlayStmt,
+            Kind::Semi,
+            Kind::FnDecl
+        ]
+    );
+    assert_eq!(tree.children[0].children[0].kind, Kind::DocComment);
+    assert_eq!(tree.children[6].children[0].kind, Kind::Keyword);
+
+    // (a stray `{` or quote doesn't take the definitions after it along)
+    let source = "fn f() {\nplay \"a;\ndef b = 2;\nplay b;";
+    let (tree, _) = parse_syntax_tree(source);
+
+    assert_eq!(tree.stringify(), source);
+    assert_eq!(
+        tree.children
+            .iter()
+            .map(|child| child.kind)
+            .filter(|kind| *kind != Kind::Ws)
+            .collect::<Vec<_>>(),
+        vec![
+            Kind::FnDecl,
+            Kind::PlayStmt,
+            Kind::LetStmt,
+            Kind::Semi,
+            Kind::PlayStmt,
+            Kind::Semi
+        ]
+    );
+}
+
+#[cfg(test)]
 fn test_parse<'a, R, E>(
     mut parser: impl Parser<Span<'a>, R, E>,
     str: &'a str,
@@ -813,14 +2070,15 @@ where
     // Store our error stack external to our `nom` parser here. It
     // is wrapped in a `RefCell` so parser functions down the line
     // can remotely push errors onto it as they run.
-    let errors = Arc::new(RefCell::new(vec![]));
-    let span = Span::new_extra(str, ParseState(errors.clone()));
+    let errors = Rc::new(RefCell::new(vec![]));
+    let span = Span::new_extra(str, ParseState::new(errors.clone()));
 
     parser
         .parse(span)
         .map(|(span, result)| (*span.fragment(), result, errors.take()))
 }
 
+#[cfg(test)]
 fn test_parse_debug<'a, R, E>(
     parser: impl Parser<Span<'a>, R, E>,
     str: &'a str,
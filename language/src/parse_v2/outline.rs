@@ -0,0 +1,470 @@
+use std::{collections::HashMap, ops::Range};
+
+use crate::{color::parse_color, span::SourceSpan};
+
+use super::{lower::lower_string, parse_syntax_tree, Kind, SyntaxNode};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Let,
+    Def,
+    Fn,
+}
+
+/// A top-level declaration, as listed in the editor's outline panel and symbol picker
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub kind: SymbolKind,
+    pub name: String,
+    /// The declared name, which is where "go to definition" jumps to
+    pub name_range: SourceSpan,
+    /// The whole declaration
+    pub range: SourceSpan,
+}
+
+/// The document's top-level declarations, in source order.
+///
+/// This is based on the lossless syntax tree rather than the AST, so that it also works (as much as possible) for documents that don't parse cleanly.
+pub fn outline(source: &str) -> Vec<Symbol> {
+    let (tree, _) = parse_syntax_tree(source);
+
+    outline_of(&tree)
+}
+
+/// (of a document that's already parsed)
+pub(super) fn outline_of(tree: &SyntaxNode) -> Vec<Symbol> {
+    tree.children.iter().filter_map(symbol).collect()
+}
+
+fn symbol(node: &SyntaxNode) -> Option<Symbol> {
+    let kind = match node.kind {
+        Kind::LetStmt => match node.child(Kind::Keyword)?.text() {
+            "def" => SymbolKind::Def,
+            _ => SymbolKind::Let,
+        },
+        Kind::FnDecl => SymbolKind::Fn,
+        _ => return None,
+    };
+
+    let name = node.name_after_keyword()?;
+
+    Some(Symbol {
+        kind,
+        name: name.text().to_string(),
+        name_range: name.range,
+        range: node.range,
+    })
+}
+
+/// A top-level `play` of a named declaration, like `play beat;`, which is how the audio engine refers to what it's playing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlayTarget {
+    pub name: String,
+    /// Which of the document's play statements it is, counting from 0 (so its value is `program.play[index]`)
+    pub index: usize,
+    /// The range of the whole play statement
+    pub range: SourceSpan,
+}
+
+/// The document's top-level plays of a named declaration, in source order (playing any other expression doesn't get a name)
+pub fn play_targets(source: &str) -> Vec<PlayTarget> {
+    let (tree, _) = parse_syntax_tree(source);
+
+    tree.children
+        .iter()
+        .filter(|node| node.kind == Kind::PlayStmt)
+        .enumerate()
+        .filter_map(|(index, node)| {
+            Some(PlayTarget {
+                name: node.name_after_keyword()?.text().to_string(),
+                index,
+                range: node.range,
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignalViewKind {
+    Scope,
+    Spectrum,
+}
+
+impl SignalViewKind {
+    /// The built-in function that shows it
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Scope => "scope",
+            Self::Spectrum => "spectrum",
+        }
+    }
+}
+
+/// A `scope(name)` or `spectrum(name)` call anywhere in the document, which the editor shows a live view of the named play target for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignalView {
+    pub kind: SignalViewKind,
+    pub target: String,
+    /// The range of the whole call
+    pub range: SourceSpan,
+}
+
+/// All the signal views in the document, in source order (calls with anything other than a single name as the argument are left out)
+pub fn signal_views(source: &str) -> Vec<SignalView> {
+    let (tree, _) = parse_syntax_tree(source);
+
+    let mut views = vec![];
+
+    tree.walk_postorder(&mut |node| {
+        if node.kind != Kind::CallExpr {
+            return;
+        }
+
+        let mut children = node.children.iter().filter(|child| child.kind != Kind::Ws);
+
+        let kind = match children.next().map(|callee| (callee.kind, callee.text())) {
+            Some((Kind::Ident, "scope")) => SignalViewKind::Scope,
+            Some((Kind::Ident, "spectrum")) => SignalViewKind::Spectrum,
+            _ => return,
+        };
+
+        if let [paren_left, arg, paren_right] = children.collect::<Vec<_>>()[..]
+            && paren_left.kind == Kind::ParenLeft
+            && arg.kind == Kind::Ident
+            && paren_right.kind == Kind::ParenRight
+        {
+            views.push(SignalView {
+                kind,
+                target: arg.text().to_string(),
+                range: node.range,
+            });
+        }
+    });
+
+    views.sort_by_key(|view| view.range.start);
+    views
+}
+
+/// A `clip(beat, "drums", 1)` call anywhere in the document, which puts the named play target into the editor's clip launcher, on the track's row, in the scene's column (counting from 1). A clip is only heard while it's launched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Clip {
+    pub target: String,
+    pub track: String,
+    pub scene: usize,
+    /// The range of the whole call
+    pub range: SourceSpan,
+}
+
+/// All the clips in the document, in source order (calls that aren't exactly a name, a string and a scene number are left out)
+pub fn clips(source: &str) -> Vec<Clip> {
+    let (tree, _) = parse_syntax_tree(source);
+
+    let mut clips = vec![];
+
+    tree.walk_postorder(&mut |node| {
+        if node.kind != Kind::CallExpr {
+            return;
+        }
+
+        let children = node
+            .children
+            .iter()
+            .filter(|child| child.kind != Kind::Ws)
+            .collect::<Vec<_>>();
+
+        if let [callee, paren_left, target, comma1, track, comma2, scene, paren_right] =
+            children[..]
+            && (callee.kind, callee.text()) == (Kind::Ident, "clip")
+            && paren_left.kind == Kind::ParenLeft
+            && target.kind == Kind::Ident
+            && comma1.kind == Kind::Comma
+            && track.kind == Kind::Str
+            && comma2.kind == Kind::Comma
+            && scene.kind == Kind::Num
+            && paren_right.kind == Kind::ParenRight
+            && let Ok(number) = scene.text().parse::<usize>()
+            && number >= 1
+        {
+            clips.push(Clip {
+                target: target.text().to_string(),
+                track: lower_string(track.text()),
+                scene: number,
+                range: node.range,
+            });
+        }
+    });
+
+    clips.sort_by_key(|clip| clip.range.start);
+    clips
+}
+
+/// A color literal, like `#ff8800`, which the editor shows a swatch (and a picker) for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColorLiteral {
+    pub color: [u8; 4],
+    pub range: SourceSpan,
+}
+
+/// All the color literals in the document, in source order
+pub fn color_literals(source: &str) -> Vec<ColorLiteral> {
+    let (tree, _) = parse_syntax_tree(source);
+
+    let mut literals = vec![];
+
+    tree.walk_postorder(&mut |node| {
+        if node.kind == Kind::Color
+            && let Some(color) = parse_color(node.text())
+        {
+            literals.push(ColorLiteral {
+                color,
+                range: node.range,
+            });
+        }
+    });
+
+    literals.sort_by_key(|literal| literal.range.start);
+    literals
+}
+
+/// The top-level statement (or declaration) at the offset, including its `;`, which is what the editor evaluates when there's no selection.
+///
+/// An offset right after a statement counts as being in it, so that evaluating with the caret at the end of a line works.
+pub fn statement_at(source: &str, offset: usize) -> Option<SourceSpan> {
+    let (tree, _) = parse_syntax_tree(source);
+
+    let items = tree
+        .children
+        .iter()
+        .filter(|node| node.kind != Kind::Ws)
+        .collect::<Vec<_>>();
+
+    (0..items.len())
+        .filter(|&i| items[i].kind != Kind::Semi)
+        .map(|i| match items.get(i + 1) {
+            Some(next) if next.kind == Kind::Semi => items[i].range.cover(next.range),
+            _ => items[i].range,
+        })
+        .find(|span| span.start.offset <= offset && offset <= span.end.offset)
+}
+
+/// A top-level statement, and what it stays across edits (see `statements`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Statement {
+    pub id: String,
+    pub is_play: bool,
+    /// (with its semicolon, if it has one)
+    pub range: SourceSpan,
+}
+
+/**
+    The document's top-level statements, in source order, with what they are across edits: a declaration is its name, a play of a named declaration is that, the other plays are how many came before, and anything else is its text. (When it comes up again, it's counted, so the second `let x` is another one.)
+*/
+pub fn statements(source: &str) -> Vec<Statement> {
+    let (tree, _) = parse_syntax_tree(source);
+
+    let items = tree
+        .children
+        .iter()
+        .filter(|node| node.kind != Kind::Ws)
+        .collect::<Vec<_>>();
+
+    let mut seen = HashMap::<String, usize>::new();
+
+    (0..items.len())
+        .filter(|&i| items[i].kind != Kind::Semi)
+        .map(|i| {
+            let node = items[i];
+            let range = match items.get(i + 1) {
+                Some(next) if next.kind == Kind::Semi => node.range.cover(next.range),
+                _ => node.range,
+            };
+
+            let is_play = node.kind == Kind::PlayStmt;
+            let id = match symbol(node) {
+                Some(symbol) => symbol.name,
+                None if is_play => match node.name_after_keyword() {
+                    Some(name) => format!("play {}", name.text()),
+                    None => "play".into(),
+                },
+                None => source[node.range.range()].to_string(),
+            };
+
+            let n = seen.entry(id.clone()).or_default();
+            *n += 1;
+
+            Statement {
+                id: if *n == 1 { id } else { format!("{} #{}", id, n) },
+                is_play,
+                range,
+            }
+        })
+        .collect()
+}
+
+/**
+    What a document is when only some of its statements are evaluated (the ones that overlap the byte ranges), on top of what was `live` before: those are as they are now, and the rest as they were when they were last evaluated. What was never evaluated is taken as it is (so that it can be referred to), except for plays, which only start playing when they're evaluated, and what's not in the document anymore is left out.
+*/
+pub fn overlay_statements(live: &str, current: &str, evaluated: &[Range<usize>]) -> String {
+    let live = statements(live)
+        .into_iter()
+        .map(|statement| (statement.id, &live[statement.range.range()]))
+        .collect::<HashMap<_, _>>();
+
+    statements(current)
+        .iter()
+        .filter_map(|statement| {
+            let range = statement.range.range();
+            let is_evaluated = evaluated
+                .iter()
+                .any(|r| r.start < range.end && range.start < r.end);
+
+            match live.get(&statement.id) {
+                _ if is_evaluated => Some(&current[range]),
+                Some(text) => Some(*text),
+                None if statement.is_play => None,
+                None => Some(&current[range]),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[test]
+fn test_outline() {
+    let source = "let a = 1;\n\nfn kick(t) {\n  let inner = 2;\n}\n\ndef beat = kick;\nplay beat;";
+
+    assert_eq!(
+        outline(source)
+            .iter()
+            .map(|symbol| (symbol.kind, symbol.name.as_str(), &source[symbol.name_range.range()]))
+            .collect::<Vec<_>>(),
+        vec![
+            (SymbolKind::Let, "a", "a"),
+            (SymbolKind::Fn, "kick", "kick"),
+            (SymbolKind::Def, "beat", "beat"),
+        ]
+    );
+}
+
+#[test]
+fn test_play_targets() {
+    let source = "def beat = kick;\nplay beat;\nplay beat * 2;\n\nplay  bass;";
+
+    assert_eq!(
+        play_targets(source)
+            .iter()
+            .map(|target| (
+                target.name.as_str(),
+                target.index,
+                &source[target.range.range()]
+            ))
+            .collect::<Vec<_>>(),
+        vec![("beat", 0, "play beat"), ("bass", 2, "play  bass")]
+    );
+}
+
+#[test]
+fn test_signal_views() {
+    let source = "play beat;\nscope(beat);\nlet x = 2 * spectrum( bass );\nscope(beat, bass);\nscope(1);";
+
+    assert_eq!(
+        signal_views(source)
+            .iter()
+            .map(|view| (view.kind, view.target.as_str(), &source[view.range.range()]))
+            .collect::<Vec<_>>(),
+        vec![
+            (SignalViewKind::Scope, "beat", "scope(beat)"),
+            (SignalViewKind::Spectrum, "bass", "spectrum( bass )"),
+        ]
+    );
+}
+
+#[test]
+fn test_clips() {
+    let source = "play beat;\nclip(beat, \"drums\", 1);\nclip( fill ,\"drums\", 2);\nclip(bass, \"bass\", 0);\nclip(pad, keys, 1);";
+
+    assert_eq!(
+        clips(source)
+            .iter()
+            .map(|clip| (
+                clip.target.as_str(),
+                clip.track.as_str(),
+                clip.scene,
+                &source[clip.range.range()]
+            ))
+            .collect::<Vec<_>>(),
+        vec![
+            ("beat", "drums", 1, "clip(beat, \"drums\", 1)"),
+            ("fill", "drums", 2, "clip( fill ,\"drums\", 2)"),
+        ]
+    );
+}
+
+#[test]
+fn test_color_literals() {
+    let source = "def bus = send(x, \"drums\", #f80);\nlet c = [#00ff0080, #123];";
+
+    assert_eq!(
+        color_literals(source)
+            .iter()
+            .map(|literal| (literal.color, &source[literal.range.range()]))
+            .collect::<Vec<_>>(),
+        vec![
+            ([255, 136, 0, 255], "#f80"),
+            ([0, 255, 0, 128], "#00ff0080"),
+            ([17, 34, 51, 255], "#123"),
+        ]
+    );
+}
+
+#[test]
+fn test_statement_at() {
+    let source = "let a = 1;\n\nfn kick(t) {\n  let inner = 2;\n}\n\nplay kick;";
+
+    let at = |offset| statement_at(source, offset).map(|span| &source[span.range()]);
+
+    assert_eq!(at(0), Some("let a = 1;"));
+    assert_eq!(at(5), Some("let a = 1;"));
+    assert_eq!(at(9), Some("let a = 1;"));
+    assert_eq!(at(11), None);
+    assert_eq!(at(30), Some("fn kick(t) {\n  let inner = 2;\n}"));
+    assert_eq!(at(source.len()), Some("play kick;"));
+}
+
+#[test]
+fn test_overlay_statements() {
+    let live = "def pad = sin(220hz);\nplay pad;\nplay sin(1hz);";
+    let current = "def pad = sin(330hz);\nlet f = 2hz;\nplay pad;\nplay sin(f);\nplay kick;";
+    let at = |code: &str| {
+        let start = current.find(code).unwrap();
+        vec![statement_at(current, start).unwrap().range()]
+    };
+
+    assert_eq!(
+        statements(current)
+            .iter()
+            .map(|statement| (statement.id.as_str(), statement.is_play))
+            .collect::<Vec<_>>(),
+        vec![
+            ("pad", false),
+            ("f", false),
+            ("play pad", true),
+            ("play", true),
+            ("play kick", true),
+        ]
+    );
+
+    // (the new `f` is taken as it is, but the old `pad` and anonymous play keep playing, and `kick` doesn't start)
+    assert_eq!(
+        overlay_statements(live, current, &at("play kick")),
+        "def pad = sin(220hz);\nlet f = 2hz;\nplay pad;\nplay sin(1hz);\nplay kick;"
+    );
+    assert_eq!(
+        overlay_statements(live, current, &at("def pad")),
+        "def pad = sin(330hz);\nlet f = 2hz;\nplay pad;\nplay sin(1hz);"
+    );
+    assert_eq!(
+        overlay_statements("", current, &at("play sin")),
+        "def pad = sin(330hz);\nlet f = 2hz;\nplay sin(f);"
+    );
+}
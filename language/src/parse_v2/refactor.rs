@@ -0,0 +1,379 @@
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Range,
+};
+
+use crate::{
+    ast::{Expr, Identifier, SyntaxNode as AstNode},
+    check::resolve_names,
+    span::{Loc, SourceSpan},
+    visit::{walk_expr, Rewrite, Visitor},
+};
+
+use super::{
+    is_identifier, lower::lower_document, parse_syntax_tree, Kind, SyntaxNode, KEYWORDS, LITERALS,
+};
+
+/// Renames identifiers throughout the code, both where they're declared and where they're used (but not member accesses like the `f` in `x.f`, or the names of settings, like in `lowpass{f = 200hz}`), which is how code from elsewhere is pasted in without its names colliding with the ones that are already there
+pub fn rename_symbols(source: &str, renames: &HashMap<String, String>) -> String {
+    let (tree, _) = parse_syntax_tree(source);
+
+    let mut renaming = Renaming {
+        renames,
+        rewrite: Rewrite::new(source),
+    };
+    renaming.visit_document(&lower_document(&tree));
+
+    renaming
+        .rewrite
+        .finish()
+        .expect("identifiers don't overlap")
+}
+
+struct Renaming<'a, 'r> {
+    renames: &'r HashMap<String, String>,
+    rewrite: Rewrite<'a>,
+}
+
+impl Renaming<'_, '_> {
+    fn rename(&mut self, name: &AstNode<Identifier>) {
+        if let Some(new_name) = name
+            .node
+            .as_deref()
+            .and_then(|name| self.renames.get(&name.0))
+        {
+            self.rewrite.replace_node(name, new_name.as_str());
+        }
+    }
+}
+
+impl Visitor for Renaming<'_, '_> {
+    fn visit_binding(&mut self, name: &AstNode<Identifier>) {
+        self.rename(name);
+    }
+
+    fn visit_var(&mut self, name: &AstNode<Identifier>) {
+        self.rename(name);
+    }
+}
+
+/**
+    Renames the name at the offset (where it's declared, or where it's used) everywhere it refers to the same declaration, returning the spans to put the new name in.
+
+    It refuses (with why) when the new name isn't a name, or when renaming would change what some name refers to, like when the new name is already used in there, and one would shadow the other.
+*/
+pub fn rename_symbol(
+    source: &str,
+    offset: usize,
+    new_name: &str,
+) -> Result<Vec<SourceSpan>, String> {
+    let occurrences = resolve_names(&lower_document(&parse_syntax_tree(source).0));
+
+    let Some(at) = occurrences
+        .iter()
+        .find(|o| o.span.start.offset <= offset && offset <= o.span.end.offset)
+    else {
+        return Err("there's no name here to rename".into());
+    };
+    let Some(declaration) = at.declaration else {
+        return Err(format!("`{}` isn't declared in this document", at.name));
+    };
+
+    if KEYWORDS.contains(&new_name) {
+        return Err(format!("`{}` is a keyword", new_name));
+    } else if LITERALS.contains(&new_name) {
+        return Err(format!("`{}` is a built-in value", new_name));
+    } else if !is_identifier(new_name) {
+        return Err(format!("`{}` isn't a valid name", new_name));
+    }
+
+    let spans = occurrences
+        .iter()
+        .filter(|o| o.declaration == Some(declaration))
+        .map(|o| o.span)
+        .collect::<Vec<_>>();
+
+    // (renaming doesn't add or remove names, so they all still have to refer to the same declarations afterwards)
+    let mut rewrite = Rewrite::new(source);
+    for &span in &spans {
+        rewrite.replace(span, new_name);
+    }
+    let renamed = rewrite.finish()?;
+    let resolved = resolve_names(&lower_document(&parse_syntax_tree(&renamed).0));
+
+    if resolved.len() != occurrences.len()
+        || resolved
+            .iter()
+            .zip(&occurrences)
+            .any(|(after, before)| after.declaration != before.declaration)
+    {
+        return Err(format!(
+            "renaming `{}` to `{}` would change what other names refer to",
+            at.name, new_name
+        ));
+    }
+
+    Ok(spans)
+}
+
+/**
+    Where the name at the offset (where it's used, or declared) is declared, for "go to definition". Builtins and names that aren't declared in the document (or not yet, where they're used) have no definition.
+*/
+pub fn definition_at(source: &str, offset: usize) -> Option<SourceSpan> {
+    let occurrences = resolve_names(&lower_document(&parse_syntax_tree(source).0));
+
+    let at = occurrences
+        .iter()
+        .find(|o| o.span.start.offset <= offset && offset <= o.span.end.offset)?;
+
+    Some(occurrences[at.declaration?].span)
+}
+
+/// What extracting an expression into a definition of its own takes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Extraction {
+    pub name: String,
+    /// What to replace with what (in the source as it was)
+    pub edits: Vec<(SourceSpan, String)>,
+    /// Where the new name is declared, once the edits are made (to rename it right away)
+    pub name_offset: usize,
+}
+
+/**
+    Extracts the selected expression into a `let` right before the statement it's in (in the innermost block, so that it can still use what's declared in there), and puts a fresh name in its place.
+
+    It refuses when the selection isn't exactly an expression, or when the expression uses a name that isn't known yet before that statement, like a parameter of an anonymous function it's in.
+*/
+pub fn extract_definition(source: &str, selection: Range<usize>) -> Result<Extraction, String> {
+    let text = &source[selection.clone()];
+    let start = selection.start + (text.len() - text.trim_start().len());
+    let end = selection.end - (text.len() - text.trim_end().len());
+
+    let (tree, _) = parse_syntax_tree(source);
+    let doc = lower_document(&tree);
+
+    let mut finder = FindExpr {
+        range: start..end,
+        found: false,
+    };
+    finder.visit_document(&doc);
+    if start >= end || !finder.found {
+        return Err("select an expression to extract".into());
+    }
+
+    let Some(statement) = enclosing_statement(&tree, start..end) else {
+        return Err("select an expression to extract".into());
+    };
+    let insert_at = statement.range.start;
+
+    let occurrences = resolve_names(&doc);
+    let within = |span: SourceSpan| start <= span.start.offset && span.end.offset <= end;
+    for occurrence in occurrences.iter().filter(|o| within(o.span)) {
+        if let Some(declaration) = occurrence.declaration.map(|i| occurrences[i].span)
+            && !within(declaration)
+            && declaration.start.offset >= insert_at.offset
+        {
+            return Err(format!(
+                "`{}` isn't known yet before the statement the expression is in",
+                occurrence.name
+            ));
+        }
+    }
+
+    let taken = occurrences
+        .iter()
+        .map(|o| o.name.as_str())
+        .collect::<HashSet<_>>();
+    let name = std::iter::once("extracted".to_string())
+        .chain((2..).map(|n| format!("extracted_{}", n)))
+        .find(|name| !taken.contains(name.as_str()))
+        .unwrap();
+
+    // (on a line of its own, indented like the statement, unless the statement doesn't start its line)
+    let line_start = source[..insert_at.offset].rfind('\n').map_or(0, |i| i + 1);
+    let indent = &source[line_start..insert_at.offset];
+    let separator = match indent.trim().is_empty() {
+        true => format!("\n{}", indent),
+        false => " ".to_string(),
+    };
+
+    Ok(Extraction {
+        edits: vec![
+            (
+                SourceSpan {
+                    start: insert_at,
+                    end: insert_at,
+                },
+                format!("let {} = {};{}", name, &source[start..end], separator),
+            ),
+            (
+                SourceSpan {
+                    start: Loc::at(source, start),
+                    end: Loc::at(source, end),
+                },
+                name.clone(),
+            ),
+        ],
+        name_offset: insert_at.offset + "let ".len(),
+        name,
+    })
+}
+
+/// (whether there's an expression that's exactly the range)
+struct FindExpr {
+    range: Range<usize>,
+    found: bool,
+}
+
+impl Visitor for FindExpr {
+    fn visit_expr(&mut self, expr: &AstNode<Expr>) {
+        if expr.range() == Some(self.range.clone()) {
+            self.found = true;
+        } else if expr
+            .range()
+            .is_some_and(|range| range.start <= self.range.start && self.range.end <= range.end)
+        {
+            walk_expr(self, expr);
+        }
+    }
+}
+
+/// The statement (or declaration, or a block's last expression) in the innermost block (or the document) that the range is in
+fn enclosing_statement<'t, 'a>(
+    node: &'t SyntaxNode<'a>,
+    range: Range<usize>,
+) -> Option<&'t SyntaxNode<'a>> {
+    let child = node.children.iter().find(|child| {
+        child.range.start.offset <= range.start && range.end <= child.range.end.offset
+    })?;
+
+    let is_statement = matches!(node.kind, Kind::Document | Kind::Block)
+        && !matches!(
+            child.kind,
+            Kind::Ws | Kind::Semi | Kind::CurlyLeft | Kind::CurlyRight
+        );
+
+    enclosing_statement(child, range).or(is_statement.then_some(child))
+}
+
+#[test]
+fn test_rename_symbols() {
+    let source = "let f = 2;\ndef fx = lowpass{f = f * 100hz}(x.f);\nplay fx;";
+    let renames = HashMap::from([
+        ("f".to_string(), "f_2".to_string()),
+        ("fx".to_string(), "fx_2".to_string()),
+    ]);
+
+    assert_eq!(
+        rename_symbols(source, &renames),
+        "let f_2 = 2;\ndef fx_2 = lowpass{f = f_2 * 100hz}(x.f);\nplay fx_2;"
+    );
+}
+
+#[test]
+fn test_rename_symbol() {
+    let source = "let a = 1;\nfn f(x) { let y = 2; x * a + y }\nplay f(a) + sin(a);";
+    let rename = |at: &str, new_name: &str| {
+        rename_symbol(source, source.find(at).unwrap(), new_name).map(|spans| {
+            let mut rewrite = Rewrite::new(source);
+            for span in spans {
+                rewrite.replace(span, new_name);
+            }
+            rewrite.finish().unwrap()
+        })
+    };
+
+    assert_eq!(
+        rename("a + y", "gain"),
+        Ok("let gain = 1;\nfn f(x) { let y = 2; x * gain + y }\nplay f(gain) + sin(gain);".into())
+    );
+    assert_eq!(
+        rename("x)", "input"),
+        Ok("let a = 1;\nfn f(input) { let y = 2; input * a + y }\nplay f(a) + sin(a);".into())
+    );
+
+    assert_eq!(
+        rename("y =", "a"),
+        Err("renaming `y` to `a` would change what other names refer to".into())
+    );
+    assert_eq!(
+        rename("a =", "sin"),
+        Err("renaming `a` to `sin` would change what other names refer to".into())
+    );
+    assert_eq!(rename("a =", "play"), Err("`play` is a keyword".into()));
+    assert_eq!(rename("a =", "2a"), Err("`2a` isn't a valid name".into()));
+    assert_eq!(
+        rename("sin", "sine"),
+        Err("`sin` isn't declared in this document".into())
+    );
+    assert_eq!(
+        rename("1;", "b"),
+        Err("there's no name here to rename".into())
+    );
+}
+
+#[test]
+fn test_extract_definition() {
+    let extract = |source: &str, selected: &str| {
+        let start = source.find(selected).unwrap();
+        extract_definition(source, start..start + selected.len()).map(|extraction| {
+            let mut rewrite = Rewrite::new(source);
+            for (span, text) in extraction.edits {
+                rewrite.replace(span, text);
+            }
+            let extracted = rewrite.finish().unwrap();
+            assert!(extracted[extraction.name_offset..].starts_with(&extraction.name));
+            extracted
+        })
+    };
+
+    assert_eq!(
+        extract(
+            "let f = 200hz;\nplay lowpass{f = f * 2}(saw(f)) * 0.5;",
+            " lowpass{f = f * 2}(saw(f))"
+        ),
+        Ok(
+            "let f = 200hz;\nlet extracted = lowpass{f = f * 2}(saw(f));\nplay extracted * 0.5;"
+                .into()
+        )
+    );
+    assert_eq!(
+        extract("fn f(x) {\n  let extracted = 1;\n  x * 2 + extracted\n}", "x * 2"),
+        Ok("fn f(x) {\n  let extracted = 1;\n  let extracted_2 = x * 2;\n  extracted_2 + extracted\n}".into())
+    );
+    assert_eq!(
+        extract("play { sin(2hz) * 3 };", "sin(2hz)"),
+        Ok("play { let extracted = sin(2hz); extracted * 3 };".into())
+    );
+
+    assert_eq!(
+        extract("play map([1, 2], |x| x * 2);", "x * 2"),
+        Err("`x` isn't known yet before the statement the expression is in".into())
+    );
+    assert_eq!(
+        extract("play sin(2hz) * 3;", "sin(2hz) *"),
+        Err("select an expression to extract".into())
+    );
+    assert_eq!(
+        extract("let a = 2;", "a"),
+        Err("select an expression to extract".into())
+    );
+}
+
+#[test]
+fn test_definition_at() {
+    let source = "let a = 1;\nfn kick(t) {\n  let b = t * a;\n  b\n}\nplay kick(a) + sin(2hz);";
+
+    let offset = |needle: &str| source.find(needle).unwrap();
+    let at = |needle: &str| definition_at(source, offset(needle)).map(|span| span.start.offset);
+
+    assert_eq!(at("kick(a)"), Some(offset("kick")));
+    assert_eq!(at("t * a"), Some(offset("t)")));
+    assert_eq!(at("a;"), Some(offset("a = 1")));
+    assert_eq!(at("b\n}"), Some(offset("b =")));
+    // (a declaration is its own definition)
+    assert_eq!(at("a = 1"), Some(offset("a = 1")));
+    // (builtins aren't declared in the document)
+    assert_eq!(at("sin"), None);
+    assert_eq!(at("2hz"), None);
+}
@@ -0,0 +1,163 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// What `path("kicks/1.wav")` resolves to: relative to the project root, unless it's absolute already
+pub fn resolve_path(root: &Path, path: &str) -> PathBuf {
+    root.join(path)
+}
+
+/// What `samples("kicks/*.wav")` expands to: the files matching the pattern (relative to the project root, like `path()`), in alphabetical order. Every part of the pattern can have `*` (any characters) and `?` (any one character) in it, and `**` matches any number of directories. Hidden files are only matched by patterns that start with a `.` themselves.
+pub fn expand_glob(root: &Path, pattern: &str) -> Vec<PathBuf> {
+    let base = if pattern.starts_with('/') {
+        PathBuf::from("/")
+    } else {
+        root.to_path_buf()
+    };
+
+    let parts = pattern
+        .split('/')
+        .filter(|part| !part.is_empty() && *part != ".")
+        .collect::<Vec<_>>();
+
+    let mut files = vec![];
+    walk(&base, &parts, &mut files);
+
+    files.sort();
+    files.dedup();
+    files
+}
+
+fn walk(dir: &Path, parts: &[&str], files: &mut Vec<PathBuf>) {
+    let Some((&part, rest)) = parts.split_first() else {
+        return;
+    };
+
+    if part == "**" {
+        // (no directories at all, or one more)
+        walk(dir, rest, files);
+        for entry in entries(dir, "*") {
+            if entry.is_dir() {
+                walk(&entry, parts, files);
+            }
+        }
+        return;
+    }
+
+    let candidates = if part.contains(['*', '?']) {
+        entries(dir, part)
+    } else {
+        vec![dir.join(part)]
+    };
+
+    for candidate in candidates {
+        if rest.is_empty() {
+            if candidate.is_file() {
+                files.push(candidate);
+            }
+        } else if candidate.is_dir() {
+            walk(&candidate, rest, files);
+        }
+    }
+}
+
+/// The entries of a directory whose names match the pattern
+fn entries(dir: &Path, pattern: &str) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return vec![];
+    };
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            (pattern.starts_with('.') || !name.starts_with('.')) && matches(pattern, &name)
+        })
+        .map(|entry| entry.path())
+        .collect()
+}
+
+fn matches(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+
+    // (where the last `*` was, and where in the name it's matched up to, to backtrack to)
+    let (mut p, mut n) = (0, 0);
+    let mut star = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[test]
+fn test_matches() {
+    assert!(matches("*.wav", "kick.wav"));
+    assert!(matches("k?ck*", "kick_2.wav"));
+    assert!(matches("*", ""));
+    assert!(!matches("*.wav", "kick.wav.bak"));
+    assert!(!matches("k?ck", "kck"));
+}
+
+#[test]
+fn test_expand_glob() {
+    let root = std::env::temp_dir().join(format!("live_language_glob_{}", std::process::id()));
+    for file in [
+        "kicks/2.wav",
+        "kicks/1.wav",
+        "kicks/notes.txt",
+        "kicks/.hidden.wav",
+        "kicks/old/3.wav",
+        "snares/1.wav",
+    ] {
+        let path = root.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "").unwrap();
+    }
+
+    let expand = |pattern: &str| {
+        expand_glob(&root, pattern)
+            .into_iter()
+            .map(|path| {
+                path.strip_prefix(&root)
+                    .unwrap()
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(expand("kicks/*.wav"), vec!["kicks/1.wav", "kicks/2.wav"]);
+    assert_eq!(expand("**/1.wav"), vec!["kicks/1.wav", "snares/1.wav"]);
+    assert_eq!(
+        expand("kicks/**/*.wav"),
+        vec!["kicks/1.wav", "kicks/2.wav", "kicks/old/3.wav"]
+    );
+    assert_eq!(expand("./snares/1.wav"), vec!["snares/1.wav"]);
+    assert_eq!(expand("hats/*.wav"), Vec::<String>::new());
+
+    assert_eq!(resolve_path(&root, "kicks/1.wav"), root.join("kicks/1.wav"));
+
+    fs::remove_dir_all(&root).unwrap();
+}
@@ -0,0 +1,38 @@
+/**
+    Where the random stream of one place in the code starts: the document's seed, mixed with what identifies that place (like its key and its code). It's FNV-1a, rather than std's hasher, which isn't promised to hash the same from one Rust version to the next, and the same code should be just as random every time.
+*/
+pub fn seed(seed: u64, parts: &[&str]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325 ^ seed;
+    for part in parts {
+        // (so that `["ab", "c"]` and `["a", "bc"]` aren't the same)
+        for byte in part.bytes().chain([0xff]) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+/**
+    A random number in [0, 1), the same for the same seed and index (it's SplitMix64, like the engine's)
+*/
+pub(crate) fn random(seed: u64, i: u64) -> f64 {
+    let mut z = seed.wrapping_add(i.wrapping_add(1).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^= z >> 31;
+
+    (z >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[test]
+fn test_random() {
+    assert_eq!(seed(1, &["x", "rand()"]), seed(1, &["x", "rand()"]));
+    assert_ne!(seed(1, &["x", "rand()"]), seed(2, &["x", "rand()"]));
+    assert_ne!(seed(1, &["ab", "c"]), seed(1, &["a", "bc"]));
+
+    let xs = (0..1000).map(|i| random(7, i)).collect::<Vec<_>>();
+    assert!(xs.iter().all(|x| (0.0..1.0).contains(x)));
+    assert!((xs.iter().sum::<f64>() / 1000.0 - 0.5).abs() < 0.05);
+    assert_eq!(random(7, 3), xs[3]);
+}
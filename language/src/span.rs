@@ -0,0 +1,99 @@
+use std::ops::Range;
+
+/**
+    A place in the source: its byte offset, and the line and column it's on (both counting from 1, like editors and compilers show them, and the column in characters, not bytes)
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Loc {
+    pub offset: usize,
+    pub line: usize,
+    pub col: usize,
+}
+
+impl Loc {
+    pub const START: Loc = Loc {
+        offset: 0,
+        line: 1,
+        col: 1,
+    };
+
+    /// Where some text ends, if it starts here
+    pub fn after(self, text: &str) -> Loc {
+        let offset = self.offset + text.len();
+
+        match text.rfind('\n') {
+            Some(i) => Loc {
+                offset,
+                line: self.line + text.matches('\n').count(),
+                col: text[i + 1..].chars().count() + 1,
+            },
+            None => Loc {
+                offset,
+                line: self.line,
+                col: self.col + text.chars().count(),
+            },
+        }
+    }
+
+    /// (this goes through the source up until the offset, so spans from the parser are the better way to get one)
+    pub fn at(source: &str, offset: usize) -> Loc {
+        Loc::START.after(&source[..offset])
+    }
+}
+
+/**
+    A piece of the source, by where it starts and ends. This is what the parser, the checks, the linter and the evaluator all report things with, so that the editor (or the command line) can point at them without having to work out lines and columns again.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SourceSpan {
+    pub start: Loc,
+    pub end: Loc,
+}
+
+impl SourceSpan {
+    /// The bytes it covers, to slice the source with
+    pub fn range(&self) -> Range<usize> {
+        self.start.offset..self.end.offset
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start.offset == self.end.offset
+    }
+
+    /// The smallest span covering both
+    pub fn cover(self, other: SourceSpan) -> SourceSpan {
+        SourceSpan {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+}
+
+impl From<SourceSpan> for Range<usize> {
+    fn from(span: SourceSpan) -> Self {
+        span.range()
+    }
+}
+
+#[test]
+fn test_loc_after() {
+    let source = "let x = 1;\nplay \"é\" * x;";
+
+    assert_eq!(
+        Loc::at(source, source.find("x;").unwrap()),
+        Loc {
+            offset: 23,
+            line: 2,
+            col: 12
+        }
+    );
+    assert_eq!(
+        Loc::at(source, 11),
+        Loc {
+            offset: 11,
+            line: 2,
+            col: 1
+        }
+    );
+    assert_eq!(Loc::at(source, 0), Loc::START);
+}
@@ -0,0 +1,277 @@
+use std::str::FromStr;
+
+/**
+    How note names (and the steps of scales and chords) turn into frequencies: by default equal temperament, tuned to `a4` at 440Hz, or otherwise as a Scala file (`.scl`) says, mapped onto the MIDI notes as its keyboard mapping (`.kbm`) says
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tuning {
+    /// The scale's steps, in cents above its first one (which is 0, and not in here), where the last one is what it repeats at (usually an octave, 1200 cents)
+    steps: Vec<f64>,
+    keyboard: Keyboard,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Keyboard {
+    /// The degree of the scale that every key in the pattern plays (or none), which repeats across the keyboard, or empty, for every key playing the next degree
+    mapping: Vec<Option<i64>>,
+    /// The keys that play at all
+    first: i64,
+    last: i64,
+    /// The key that the mapping starts at, which plays the scale's first step
+    middle: i64,
+    /// And the key that's tuned to a frequency, which the rest are relative to
+    reference: i64,
+    frequency: f64,
+    /// The degree that the mapping repeats at
+    octave: i64,
+}
+
+impl Default for Tuning {
+    fn default() -> Self {
+        Self {
+            steps: (1..=12).map(|i| i as f64 * 100.0).collect(),
+            keyboard: Keyboard::linear(),
+        }
+    }
+}
+
+impl Keyboard {
+    /// (what Scala does without a keyboard mapping, tuned to `a4` at 440Hz)
+    fn linear() -> Self {
+        Self {
+            mapping: vec![],
+            first: 0,
+            last: 127,
+            middle: 60,
+            reference: 69,
+            frequency: 440.0,
+            octave: 0,
+        }
+    }
+}
+
+impl Tuning {
+    /**
+        Reads a Scala file (and maybe a keyboard mapping), where errors say on which line it went wrong
+    */
+    pub fn from_scala(scl: &str, kbm: Option<&str>) -> Result<Self, String> {
+        let mut lines = lines(scl);
+
+        // (the first line is a description, which may be empty)
+        lines.next().ok_or("the scale file is empty")?;
+
+        let count = match lines.next() {
+            Some((i, line)) => line.parse::<usize>().map_err(|_| {
+                format!(
+                    "line {} of the scale file: expected how many notes there are",
+                    i + 1
+                )
+            })?,
+            None => return Err("the scale file says nothing after its description".into()),
+        };
+        if count == 0 {
+            return Err("the scale file has no notes".into());
+        }
+
+        let steps = lines
+            .take(count)
+            .map(|(i, line)| {
+                pitch(line).ok_or_else(|| {
+                    format!(
+                        "line {} of the scale file: expected cents (like `100.0`) or a ratio (like `5/4`)",
+                        i + 1
+                    )
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if steps.len() < count {
+            return Err(format!(
+                "the scale file says there are {} notes, but there are {}",
+                count,
+                steps.len()
+            ));
+        }
+
+        let keyboard = match kbm {
+            Some(kbm) => keyboard(kbm)?,
+            None => Keyboard::linear(),
+        };
+
+        let tuning = Self { steps, keyboard };
+        if tuning.cents(tuning.keyboard.reference).is_none() {
+            return Err("the reference note isn't mapped to any note of the scale".into());
+        }
+        Ok(tuning)
+    }
+
+    /**
+        The frequency of a MIDI note, if it's mapped to one
+    */
+    pub fn freq(&self, note: i64) -> Option<f64> {
+        let cents = self.cents(note)? - self.cents(self.keyboard.reference)?;
+        Some(self.keyboard.frequency * 2f64.powf(cents / 1200.0))
+    }
+
+    /**
+        How many times higher a note is, so many steps of the scale up (or down) from another, for scales and chords, which count in semitones (so in anything but a 12 note tuning, they're in its steps instead)
+    */
+    pub fn interval(&self, steps: i64) -> f64 {
+        2f64.powf(self.degree(steps) / 1200.0)
+    }
+
+    /// (how many times higher the scale repeats, like 2 for an octave)
+    pub fn period(&self) -> f64 {
+        self.interval(self.steps.len() as i64)
+    }
+
+    /// (in cents above the first step, continuing into the repetitions above and below)
+    fn degree(&self, degree: i64) -> f64 {
+        let len = self.steps.len() as i64;
+        let period = self.steps[self.steps.len() - 1];
+        let step = match degree.rem_euclid(len) {
+            0 => 0.0,
+            i => self.steps[i as usize - 1],
+        };
+
+        degree.div_euclid(len) as f64 * period + step
+    }
+
+    /// (of a note, relative to the middle one)
+    fn cents(&self, note: i64) -> Option<f64> {
+        let keyboard = &self.keyboard;
+        if note < keyboard.first || note > keyboard.last {
+            return None;
+        }
+
+        let key = note - keyboard.middle;
+        if keyboard.mapping.is_empty() {
+            return Some(self.degree(key));
+        }
+
+        let size = keyboard.mapping.len() as i64;
+        let degree = keyboard.mapping[key.rem_euclid(size) as usize]?;
+        let octave = match keyboard.octave {
+            0 => self.degree(self.steps.len() as i64),
+            octave => self.degree(octave),
+        };
+
+        Some(key.div_euclid(size) as f64 * octave + self.degree(degree))
+    }
+}
+
+/// The lines that aren't comments, trimmed, with their line numbers (counting from 0)
+fn lines(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.starts_with('!'))
+        .map(|(i, line)| (i, line.trim()))
+}
+
+/// A pitch in a Scala file, in cents: cents have a `.` in them, and anything else is a ratio (or a whole number, like `2` for `2/1`), where whatever's after it on the line is a comment
+fn pitch(line: &str) -> Option<f64> {
+    let pitch = line.split_whitespace().next()?;
+
+    if pitch.contains('.') {
+        return pitch.parse().ok();
+    }
+
+    let (num, den) = pitch.split_once('/').unwrap_or((pitch, "1"));
+    let (num, den) = (num.parse::<f64>().ok()?, den.parse::<f64>().ok()?);
+    (num > 0.0 && den > 0.0).then(|| 1200.0 * (num / den).log2())
+}
+
+fn keyboard(kbm: &str) -> Result<Keyboard, String> {
+    let mut lines = lines(kbm)
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(i, line)| (i, line.split_whitespace().next().unwrap_or_default()));
+
+    let size: i64 = field(&mut lines, "the size of the map")?;
+    let first = field(&mut lines, "the first note")?;
+    let last = field(&mut lines, "the last note")?;
+    let middle = field(&mut lines, "the middle note")?;
+    let reference = field(&mut lines, "the reference note")?;
+    let frequency: f64 = field(&mut lines, "the reference frequency")?;
+    if frequency <= 0.0 {
+        return Err("the reference frequency has to be above 0".into());
+    }
+    let octave = field(&mut lines, "the degree of the octave")?;
+
+    // (and then a degree for every key, or `x` for none, where the ones that are left out are none, too)
+    let mut mapping = vec![None; size.max(0) as usize];
+    for slot in &mut mapping {
+        match lines.next() {
+            Some((_, "x")) | None => {}
+            Some((i, degree)) => {
+                *slot = Some(degree.parse::<i64>().map_err(|_| {
+                    format!(
+                        "line {} of the keyboard mapping: expected a degree, or `x`",
+                        i + 1
+                    )
+                })?);
+            }
+        }
+    }
+
+    Ok(Keyboard {
+        mapping,
+        first,
+        last,
+        middle,
+        reference,
+        frequency,
+        octave,
+    })
+}
+
+fn field<'a, T: FromStr>(
+    lines: &mut impl Iterator<Item = (usize, &'a str)>,
+    what: &str,
+) -> Result<T, String> {
+    let (i, value) = lines
+        .next()
+        .ok_or_else(|| format!("the keyboard mapping ends before {}", what))?;
+
+    value
+        .parse()
+        .map_err(|_| format!("line {} of the keyboard mapping: expected {}", i + 1, what))
+}
+
+#[test]
+fn test_tuning() {
+    let equal = Tuning::default();
+    assert_eq!(equal.freq(69), Some(440.0));
+    assert!((equal.freq(60).unwrap() - 261.63).abs() < 0.01);
+    assert!((equal.interval(7) - 2f64.powf(7.0 / 12.0)).abs() < 1e-9);
+    assert_eq!(equal.period(), 2.0);
+
+    let just = "! just.scl\n!\nJust intonation\n 12\n!\n16/15\n9/8\n6/5\n5/4\n4/3\n45/32\n3/2\n8/5\n5/3\n9/5\n15/8\n2/1\n";
+    let kbm = "! c.kbm\n12\n0\n127\n60\n60\n261.6256\n12\n0\n1\n2\n3\n4\n5\n6\n7\n8\n9\nx\n11\n";
+    let tuning = Tuning::from_scala(just, Some(kbm)).unwrap();
+    assert!((tuning.freq(60).unwrap() - 261.6256).abs() < 1e-9);
+    assert!((tuning.freq(67).unwrap() / tuning.freq(60).unwrap() - 1.5).abs() < 1e-9);
+    assert!((tuning.freq(52).unwrap() / tuning.freq(48).unwrap() - 1.25).abs() < 1e-9);
+    assert_eq!(tuning.freq(70), None);
+    assert!((tuning.interval(4) - 1.25).abs() < 1e-9);
+
+    // (cents, and a scale that repeats at a tritave)
+    let bohlen_pierce = "Bohlen-Pierce\n3\n 600.0 a comment\n1200.\n3\n";
+    let tuning = Tuning::from_scala(bohlen_pierce, None).unwrap();
+    assert_eq!(tuning.period(), 3.0);
+    assert!((tuning.freq(72).unwrap() / tuning.freq(69).unwrap() - 3.0).abs() < 1e-9);
+
+    assert_eq!(
+        Tuning::from_scala("x\n3\n100.0\nfoo\n", None),
+        Err(
+            "line 4 of the scale file: expected cents (like `100.0`) or a ratio (like `5/4`)"
+                .into()
+        )
+    );
+    assert_eq!(
+        Tuning::from_scala("x\n3\n100.0\n200.0\n", None),
+        Err("the scale file says there are 3 notes, but there are 2".into())
+    );
+    assert_eq!(
+        Tuning::from_scala(just, Some("12\n0\n127\n60\n60\n")),
+        Err("the keyboard mapping ends before the reference frequency".into())
+    );
+}
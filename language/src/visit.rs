@@ -0,0 +1,327 @@
+use std::ops::Range;
+
+use crate::{
+    ast::{
+        AnonymousFn, Block, CallExpr, Decl, Document, Expr, FnDecl, Identifier, Modifier,
+        ParamList, Stmt, StrPart, SyntaxNode,
+    },
+    span::SourceSpan,
+};
+
+/**
+    Goes through the AST, so that a tool only has to say what it does with the parts it's interested in, instead of recursing through everything itself.
+
+    Every method by default just goes on into the children (with the `walk_*` function of the same name), so an implementation that overrides one, and still wants to see what's inside, calls that itself. Missing nodes (where the code didn't parse) are skipped.
+*/
+pub trait Visitor {
+    fn visit_document(&mut self, doc: &Document) {
+        walk_document(self, doc);
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        walk_stmt(self, stmt);
+    }
+
+    fn visit_fn_decl(&mut self, decl: &SyntaxNode<FnDecl>) {
+        walk_fn_decl(self, decl);
+    }
+
+    fn visit_block(&mut self, block: &SyntaxNode<Block>) {
+        walk_block(self, block);
+    }
+
+    fn visit_expr(&mut self, expr: &SyntaxNode<Expr>) {
+        walk_expr(self, expr);
+    }
+
+    /// A name being declared: by a `let` or `def`, a function, or a parameter
+    fn visit_binding(&mut self, _name: &SyntaxNode<Identifier>) {}
+
+    /// A name being used (but not a member name, like the `f` in `x.f`, or the name of a setting, like in `lowpass{f = 200hz}`)
+    fn visit_var(&mut self, _name: &SyntaxNode<Identifier>) {}
+}
+
+pub fn walk_document<V: Visitor + ?Sized>(visitor: &mut V, doc: &Document) {
+    for stmt in &doc.stmts {
+        visitor.visit_stmt(stmt);
+    }
+}
+
+pub fn walk_stmt<V: Visitor + ?Sized>(visitor: &mut V, stmt: &Stmt) {
+    match stmt {
+        Stmt::Skip | Stmt::Return(None) => {}
+        Stmt::Expr(expr) | Stmt::Play(expr) | Stmt::Return(Some(expr)) => {
+            visitor.visit_expr(expr);
+        }
+        Stmt::Let((name, expr)) => {
+            visitor.visit_binding(name);
+            visitor.visit_expr(expr);
+        }
+        Stmt::Decl(decl) => match decl.node.as_deref() {
+            Some(Decl::FnDecl(decl)) => visitor.visit_fn_decl(decl),
+            None => {}
+        },
+    }
+}
+
+pub fn walk_fn_decl<V: Visitor + ?Sized>(visitor: &mut V, decl: &SyntaxNode<FnDecl>) {
+    let Some(FnDecl { name, params, body }) = decl.node.as_deref() else {
+        return;
+    };
+
+    visitor.visit_binding(name);
+    walk_params(visitor, params);
+    visitor.visit_block(body);
+}
+
+pub fn walk_block<V: Visitor + ?Sized>(visitor: &mut V, block: &SyntaxNode<Block>) {
+    let Some(Block { stmts, expr }) = block.node.as_deref() else {
+        return;
+    };
+
+    for stmt in stmts {
+        visitor.visit_stmt(stmt);
+    }
+    if let Some(expr) = expr {
+        visitor.visit_expr(expr);
+    }
+}
+
+pub fn walk_expr<V: Visitor + ?Sized>(visitor: &mut V, expr: &SyntaxNode<Expr>) {
+    let Some(expr) = expr.node.as_deref() else {
+        return;
+    };
+
+    match expr {
+        Expr::Prim(_) => {}
+        Expr::Var(name) => visitor.visit_var(name),
+        Expr::Call(CallExpr { fun, args }) => {
+            visitor.visit_expr(fun);
+            for arg in args {
+                visitor.visit_expr(arg);
+            }
+        }
+        Expr::BinOp(left, _, right) | Expr::Index(left, right) => {
+            visitor.visit_expr(left);
+            visitor.visit_expr(right);
+        }
+        Expr::Not(inner) | Expr::Paren(inner) | Expr::Member(inner, _) => {
+            visitor.visit_expr(inner);
+        }
+        Expr::If(cond, then, otherwise) => {
+            visitor.visit_expr(cond);
+            visitor.visit_block(then);
+            if let Some(otherwise) = otherwise {
+                visitor.visit_expr(otherwise);
+            }
+        }
+        Expr::Block(block) => visitor.visit_block(block),
+        Expr::AnonymousFn(fun) => {
+            if let Some(AnonymousFn { params, body }) = fun.node.as_deref() {
+                walk_params(visitor, params);
+                visitor.visit_expr(body);
+            }
+        }
+        Expr::Modify(inner, modifiers) | Expr::Settings(inner, modifiers) => {
+            visitor.visit_expr(inner);
+            for modifier in modifiers {
+                match modifier {
+                    Modifier::Arg(expr) | Modifier::Setting(_, expr) => visitor.visit_expr(expr),
+                }
+            }
+        }
+        Expr::Interpolated(parts) => {
+            for part in parts {
+                if let StrPart::Expr(expr) = part {
+                    visitor.visit_expr(expr);
+                }
+            }
+        }
+        Expr::Array(items) | Expr::Tuple(items) => {
+            for item in items {
+                visitor.visit_expr(item);
+            }
+        }
+    }
+}
+
+/// (the parameters aren't visited by themselves, so that whatever has them can decide what they're in scope for)
+pub fn walk_params<V: Visitor + ?Sized>(visitor: &mut V, params: &ParamList) {
+    for param in &params.0 {
+        if let Some(param) = param.node.as_deref() {
+            visitor.visit_binding(&param.name);
+        }
+    }
+}
+
+/**
+    Changes to the source, by the spans of what's replaced. Everything in between is kept exactly as it was (comments, whitespace, and code that doesn't parse), so a refactoring only has to produce the bits it changes, like the new name for every use of a variable.
+
+    The edits can be made in any order, but they can't overlap.
+*/
+#[derive(Debug, Clone)]
+pub struct Rewrite<'a> {
+    source: &'a str,
+    edits: Vec<(Range<usize>, String)>,
+}
+
+impl<'a> Rewrite<'a> {
+    pub fn new(source: &'a str) -> Self {
+        Self {
+            source,
+            edits: vec![],
+        }
+    }
+
+    /// (the original text, to move a piece of code somewhere else)
+    pub fn text(&self, span: SourceSpan) -> &'a str {
+        &self.source[span.range()]
+    }
+
+    pub fn replace(&mut self, span: SourceSpan, text: impl Into<String>) -> &mut Self {
+        self.edits.push((span.range(), text.into()));
+        self
+    }
+
+    /// (when the node is missing, there's nothing to replace)
+    pub fn replace_node<T>(&mut self, node: &SyntaxNode<T>, text: impl Into<String>) -> &mut Self {
+        if let Some(span) = node.span() {
+            self.replace(span, text);
+        }
+        self
+    }
+
+    pub fn insert(&mut self, offset: usize, text: impl Into<String>) -> &mut Self {
+        self.edits.push((offset..offset, text.into()));
+        self
+    }
+
+    pub fn remove(&mut self, span: SourceSpan) -> &mut Self {
+        self.replace(span, "")
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty()
+    }
+
+    /// The source, with all the edits made (insertions at the same place stay in the order they were added)
+    pub fn finish(mut self) -> Result<String, String> {
+        self.edits
+            .sort_by_key(|(range, _)| (range.start, range.end));
+
+        if let Some(pair) = self
+            .edits
+            .windows(2)
+            .find(|pair| pair[0].0.end > pair[1].0.start)
+        {
+            return Err(format!(
+                "edits at {:?} and {:?} overlap",
+                pair[0].0, pair[1].0
+            ));
+        }
+
+        let mut out = String::with_capacity(self.source.len());
+        let mut at = 0;
+        for (range, text) in &self.edits {
+            out.push_str(&self.source[at..range.start]);
+            out.push_str(text);
+            at = range.end;
+        }
+        out.push_str(&self.source[at..]);
+
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+use crate::{parse::parse_document, span::Loc};
+
+#[test]
+fn test_visitor() {
+    #[derive(Default)]
+    struct Names {
+        bindings: Vec<String>,
+        vars: Vec<String>,
+    }
+
+    impl Visitor for Names {
+        fn visit_binding(&mut self, name: &SyntaxNode<Identifier>) {
+            self.bindings.push(name.to_string());
+        }
+
+        fn visit_var(&mut self, name: &SyntaxNode<Identifier>) {
+            self.vars.push(name.to_string());
+        }
+    }
+
+    let (doc, _) = parse_document(
+        "let f = 2;\nfn kick(t) { let x = \"${t}\"; lowpass{f = f}(x.f) }\ndef beat = |a| [kick(a), f];\nplay beat;",
+    );
+    let mut names = Names::default();
+    names.visit_document(&doc);
+
+    assert_eq!(names.bindings, vec!["f", "kick", "t", "x", "beat", "a"]);
+    assert_eq!(
+        names.vars,
+        vec!["t", "lowpass", "f", "x", "kick", "a", "f", "beat"]
+    );
+}
+
+#[test]
+fn test_rewrite() {
+    // (inlining a variable, like a refactoring would)
+    struct Inline<'a, 'r> {
+        name: &'a str,
+        value: Option<&'a str>,
+        rewrite: &'r mut Rewrite<'a>,
+    }
+
+    impl Visitor for Inline<'_, '_> {
+        fn visit_stmt(&mut self, stmt: &Stmt) {
+            if let Stmt::Let((name, expr)) = stmt
+                && name.to_string() == self.name
+            {
+                self.value = expr.span().map(|span| self.rewrite.text(span));
+            }
+            walk_stmt(self, stmt);
+        }
+
+        fn visit_var(&mut self, name: &SyntaxNode<Identifier>) {
+            if let Some(value) = self.value
+                && name.to_string() == self.name
+            {
+                self.rewrite.replace_node(name, format!("({value})"));
+            }
+        }
+    }
+
+    let source = "let a = 2 * x; // double\nplay a + lowpass{f = a}(a.f);";
+    let (doc, _) = parse_document(source);
+    let mut rewrite = Rewrite::new(source);
+    Inline {
+        name: "a",
+        value: None,
+        rewrite: &mut rewrite,
+    }
+    .visit_document(&doc);
+
+    assert_eq!(
+        rewrite.finish(),
+        Ok("let a = 2 * x; // double\nplay (2 * x) + lowpass{f = (2 * x)}((2 * x).f);".into())
+    );
+
+    let span = |range: Range<usize>| SourceSpan {
+        start: Loc::at(source, range.start),
+        end: Loc::at(source, range.end),
+    };
+
+    let mut rewrite = Rewrite::new(source);
+    rewrite.insert(0, "// inlined\n").replace(span(0..3), "def");
+    assert_eq!(
+        rewrite.clone().finish(),
+        Ok("// inlined\ndef a = 2 * x; // double\nplay a + lowpass{f = a}(a.f);".into())
+    );
+
+    rewrite.remove(span(2..5));
+    assert!(rewrite.finish().is_err());
+}